@@ -0,0 +1,71 @@
+//! Benchmarks of the terrain interpolation, run with `cargo bench`.
+//!
+//! That the vectorized implementation gives the exact same output as the legacy one is checked
+//! by the tests in `src/world/terrain_generation/interpolation/mod.rs`, run with `cargo test`.
+//!
+//! Generating whole chunks needs the block configurations, which are only loaded once the server
+//! has started. Start the server with `--terrain-snapshots check` to time the generation of the
+//! snapshot chunks instead.
+
+use std::hint::black_box;
+
+use criterion::{Criterion, criterion_group, criterion_main};
+use fmc_173::world::interpolation::{
+    self, CAVES_NOISE_LEN, CONTINENT_HEIGHT_NOISE_LEN, TERRAIN_NOISE_LEN, legacy, vectorized,
+};
+
+// Noise in the range -100..100, the generator's noise stays well within it.
+fn noise(len: usize, seed: u32) -> Vec<f32> {
+    let mut state = seed;
+    (0..len)
+        .map(|_| {
+            state = state.wrapping_mul(1664525).wrapping_add(1013904223);
+            (state >> 8) as f32 / (1 << 24) as f32 * 200.0 - 100.0
+        })
+        .collect()
+}
+
+fn interpolation(c: &mut Criterion) {
+    let terrain = noise(TERRAIN_NOISE_LEN, 0);
+    let caves = noise(CAVES_NOISE_LEN, 0);
+    let continent_height = noise(CONTINENT_HEIGHT_NOISE_LEN, 0);
+
+    let mut group = c.benchmark_group("terrain");
+    group.bench_function("legacy", |b| {
+        b.iter(|| legacy::terrain(black_box(&terrain)))
+    });
+    group.bench_function("vectorized", |b| {
+        b.iter(|| vectorized::terrain(black_box(&terrain)))
+    });
+    group.finish();
+
+    let mut group = c.benchmark_group("caves");
+    group.bench_function("legacy", |b| b.iter(|| legacy::caves(black_box(&caves))));
+    group.bench_function("vectorized", |b| {
+        b.iter(|| vectorized::caves(black_box(&caves)))
+    });
+    group.finish();
+
+    let mut group = c.benchmark_group("continent_height");
+    group.bench_function("legacy", |b| {
+        b.iter(|| legacy::continent_height(black_box(&continent_height)))
+    });
+    group.bench_function("vectorized", |b| {
+        b.iter(|| vectorized::continent_height(black_box(&continent_height)))
+    });
+    group.finish();
+
+    // What the generator uses, changes with the 'legacy-interpolation' feature.
+    c.bench_function("chunk_interpolation", |b| {
+        b.iter(|| {
+            (
+                interpolation::terrain(black_box(&terrain)),
+                interpolation::caves(black_box(&caves)),
+                interpolation::continent_height(black_box(&continent_height)),
+            )
+        })
+    });
+}
+
+criterion_group!(benches, interpolation);
+criterion_main!(benches);
@@ -1,4 +1,4 @@
-use std::collections::{HashSet, HashMap};
+use std::collections::{HashMap, HashSet};
 
 use fmc_client_api::{
     self as fmc,
@@ -19,6 +19,15 @@ const GRAVITY: Vec3 = Vec3::new(0.0, -32.0, 0.0);
 // This is needed so that whenever you land early you can't just instantly jump again.
 // v_t = v_0 * at => (v_t - v_0) / a = t
 const JUMP_TIME: f32 = JUMP_VELOCITY * 1.7 / -GRAVITY.y;
+// The longest distance the player is moved before checking for collisions. Overlaps are resolved
+// by moving the player back the way they came, so this has to be shorter than the thinnest block
+// or the player would end up on the other side of it.
+const MAX_STEP: f32 = 0.25;
+// Limits the work done in a single frame, at 60 updates a second this is still 960 blocks a
+// second.
+const MAX_STEPS: u32 = 64;
+// Drag when the server makes the player swim outside of water
+const SWIM_DRAG: Vec3 = Vec3::splat(4.0);
 
 type ModelId = u32;
 
@@ -31,15 +40,163 @@ struct MovementPlugin {
     // Because of custom models we have to have unique collision configs for each model.
     models: HashMap<ModelId, CollisionConfig>,
     block_configs: Vec<CollisionConfig>,
+    controls: Controls,
+    /// Set while the player rides a vehicle
+    vehicle: Option<VehicleConfig>,
+    mode: MovementMode,
+    /// How high a ledge the player walks up without jumping
+    step_height: f32,
+    /// Jump when walking into a ledge one block high
+    auto_jump: bool,
     initialized: bool,
 }
 
+/// How the vehicle the player rides moves, the server decides what it is.
+#[derive(Deserialize)]
+struct VehicleConfig {
+    /// How far below the player's feet the bottom of the vehicle is
+    seat_height: f32,
+    acceleration: f32,
+    drag: f32,
+    /// The vehicle can only be steered while it floats on water
+    water_only: bool,
+}
+
+/// The keys the player moves with, the server decides what they are.
+struct Controls {
+    forward: fmc::Key,
+    back: fmc::Key,
+    left: fmc::Key,
+    right: fmc::Key,
+    jump: fmc::Key,
+    sneak: fmc::Key,
+    sprint: fmc::Key,
+    /// Toggles flight in creative. When it isn't set, flight is toggled by double tapping jump.
+    fly_toggle: Option<fmc::Key>,
+}
+
+impl Default for Controls {
+    fn default() -> Self {
+        Self {
+            forward: fmc::Key::KeyW,
+            back: fmc::Key::KeyS,
+            left: fmc::Key::KeyA,
+            right: fmc::Key::KeyD,
+            jump: fmc::Key::Space,
+            sneak: fmc::Key::Shift,
+            sprint: fmc::Key::Control,
+            fly_toggle: None,
+        }
+    }
+}
+
+/// The key bindings as they are sent by the server, keys are identified by name.
+#[derive(Deserialize)]
+struct Keybinds {
+    forward: String,
+    back: String,
+    left: String,
+    right: String,
+    jump: String,
+    sneak: String,
+    sprint: String,
+    fly_toggle: Option<String>,
+}
+
+impl Controls {
+    // Keys the plugin doesn't know are left as they were.
+    fn apply(&mut self, keybinds: &Keybinds) {
+        let bind = |key: &mut fmc::Key, name: &str| {
+            if let Some(parsed) = parse_key(name) {
+                *key = parsed;
+            } else {
+                fmc::log(&format!(
+                    "'Movement' plugin doesn't know the key '{}'",
+                    name
+                ));
+            }
+        };
+
+        bind(&mut self.forward, &keybinds.forward);
+        bind(&mut self.back, &keybinds.back);
+        bind(&mut self.left, &keybinds.left);
+        bind(&mut self.right, &keybinds.right);
+        bind(&mut self.jump, &keybinds.jump);
+        bind(&mut self.sneak, &keybinds.sneak);
+        bind(&mut self.sprint, &keybinds.sprint);
+        self.fly_toggle = keybinds.fly_toggle.as_deref().and_then(parse_key);
+    }
+}
+
+fn parse_key(name: &str) -> Option<fmc::Key> {
+    let key = match name {
+        "KeyA" => fmc::Key::KeyA,
+        "KeyB" => fmc::Key::KeyB,
+        "KeyC" => fmc::Key::KeyC,
+        "KeyD" => fmc::Key::KeyD,
+        "KeyE" => fmc::Key::KeyE,
+        "KeyF" => fmc::Key::KeyF,
+        "KeyG" => fmc::Key::KeyG,
+        "KeyH" => fmc::Key::KeyH,
+        "KeyI" => fmc::Key::KeyI,
+        "KeyJ" => fmc::Key::KeyJ,
+        "KeyK" => fmc::Key::KeyK,
+        "KeyL" => fmc::Key::KeyL,
+        "KeyM" => fmc::Key::KeyM,
+        "KeyN" => fmc::Key::KeyN,
+        "KeyO" => fmc::Key::KeyO,
+        "KeyP" => fmc::Key::KeyP,
+        "KeyQ" => fmc::Key::KeyQ,
+        "KeyR" => fmc::Key::KeyR,
+        "KeyS" => fmc::Key::KeyS,
+        "KeyT" => fmc::Key::KeyT,
+        "KeyU" => fmc::Key::KeyU,
+        "KeyV" => fmc::Key::KeyV,
+        "KeyW" => fmc::Key::KeyW,
+        "KeyX" => fmc::Key::KeyX,
+        "KeyY" => fmc::Key::KeyY,
+        "KeyZ" => fmc::Key::KeyZ,
+        "Space" => fmc::Key::Space,
+        "Shift" => fmc::Key::Shift,
+        "Control" => fmc::Key::Control,
+        _ => return None,
+    };
+    Some(key)
+}
+
+/// How a block is climbed
+#[derive(Deserialize, Clone, Copy)]
+struct ClimbConfig {
+    up_speed: f32,
+    /// The fastest the player slides down
+    down_speed: f32,
+    /// Sneaking holds the player in place instead of climbing down
+    sneak_holds: bool,
+}
+
+impl Default for ClimbConfig {
+    fn default() -> Self {
+        Self {
+            up_speed: 5.0,
+            down_speed: 5.0,
+            sneak_holds: true,
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+struct Climbing {
+    /// The direction the climbed block faces, the player climbs by walking against it
+    direction: Vec3,
+    config: ClimbConfig,
+}
+
 #[derive(Default)]
 struct PlayerProperties {
     game_mode: GameMode,
     acceleration: Vec3,
     velocity: Vec3,
-    climbing: Option<Vec3>,
+    climbing: Option<Climbing>,
     is_swimming: bool,
     is_grounded: BVec3,
     is_flying: bool,
@@ -47,6 +204,19 @@ struct PlayerProperties {
     last_jump: f32,
 }
 
+/// How the server wants the player to move, on top of the game mode.
+#[derive(Deserialize, Default, PartialEq)]
+enum MovementMode {
+    #[default]
+    Walking,
+    /// The server moves the player, the plugin leaves them be.
+    Riding,
+    /// The player can climb anywhere.
+    Climbing,
+    /// The player swims even when they are not in water.
+    Swimming,
+}
+
 #[derive(Default, PartialEq)]
 enum GameMode {
     #[default]
@@ -66,6 +236,14 @@ impl fmc::Plugin for MovementPlugin {
         self.properties.last_jump += self.delta_time;
         self.properties.last_spacebar += self.delta_time;
         self.update_keyboard_input();
+        if self.mode == MovementMode::Riding {
+            return;
+        }
+        if self.vehicle.is_some() {
+            self.vehicle_controller();
+            self.collision();
+            return;
+        }
         match self.properties.game_mode {
             GameMode::Spectator => {
                 self.flight_controller();
@@ -87,27 +265,62 @@ impl fmc::Plugin for MovementPlugin {
     }
 
     fn handle_server_data(&mut self, data: Vec<u8>) {
+        // The packets are told apart by the index of their variant, new packets are only added at
+        // the end. A server that is newer than the plugin might send packets it doesn't know,
+        // those are skipped. Older servers never send the new ones.
         #[derive(Deserialize)]
         enum Packet {
             Setup {
                 blocks: Vec<CollisionConfig>,
+                keybinds: Keybinds,
+                step_height: f32,
             },
             /// Changes the player's velocity
             Velocity(Vec3),
-            /// Notifies the plugin of which models it should collide with.
+            /// Replaces all the models the plugin should collide with.
             Models(HashMap<ModelId, CollisionConfig>),
             /// Changes the game mode.
             Mode(u32),
+            /// Adds models the plugin should collide with, replacing the ones it already has.
+            AddModels(HashMap<ModelId, CollisionConfig>),
+            /// Removes models the plugin should no longer collide with.
+            RemoveModels(Vec<ModelId>),
+            /// Enters or leaves vehicle mode.
+            Vehicle(Option<VehicleConfig>),
+            /// Changes how the player moves.
+            MovementMode(MovementMode),
+            /// Turns auto jump on or off.
+            AutoJump(bool),
         }
-
-        let Ok(packet) = bincode::deserialize::<Packet>(&data) else {
-            fmc::log("'Movement' plugin received malformed data from the server");
-            return;
+        const PACKET_COUNT: u32 = 9;
+
+        let packet = match bincode::deserialize::<Packet>(&data) {
+            Ok(packet) => packet,
+            Err(_) => {
+                // Bincode starts enums with the index of the variant
+                let index = data
+                    .get(..4)
+                    .map(|bytes| u32::from_le_bytes(bytes.try_into().unwrap()));
+                if index.is_some_and(|index| index >= PACKET_COUNT) {
+                    fmc::log(
+                        "'Movement' plugin received a packet it doesn't know, the server is newer than the plugin",
+                    );
+                } else {
+                    fmc::log("'Movement' plugin received malformed data from the server");
+                }
+                return;
+            }
         };
 
         match packet {
-            Packet::Setup { blocks } => {
+            Packet::Setup {
+                blocks,
+                keybinds,
+                step_height,
+            } => {
                 self.block_configs = blocks;
+                self.controls.apply(&keybinds);
+                self.step_height = step_height;
                 self.initialized = true;
             }
             Packet::Velocity(velocity) => self.properties.velocity += velocity,
@@ -127,6 +340,22 @@ impl fmc::Plugin for MovementPlugin {
                 }
                 _ => (),
             },
+            Packet::AddModels(models) => self.models.extend(models),
+            Packet::RemoveModels(model_ids) => {
+                for model_id in model_ids {
+                    self.models.remove(&model_id);
+                }
+            }
+            Packet::AutoJump(auto_jump) => self.auto_jump = auto_jump,
+            Packet::MovementMode(mode) => {
+                self.mode = mode;
+                self.properties.velocity = Vec3::ZERO;
+            }
+            Packet::Vehicle(vehicle) => {
+                self.vehicle = vehicle;
+                self.properties.velocity = Vec3::ZERO;
+                self.properties.is_flying = false;
+            }
         }
     }
 
@@ -146,14 +375,19 @@ impl MovementPlugin {
     fn update_keyboard_input(&mut self) {
         for key_update in fmc::keyboard_input() {
             if key_update.released {
-                if self.properties.game_mode == GameMode::Creative
-                    && key_update.key == fmc::Key::Space
-                {
-                    if self.properties.last_spacebar < 0.25 {
-                        self.properties.is_flying = !self.properties.is_flying;
-                        self.properties.velocity = Vec3::ZERO;
+                if self.properties.game_mode == GameMode::Creative {
+                    if let Some(fly_toggle) = self.controls.fly_toggle {
+                        if key_update.key == fly_toggle {
+                            self.properties.is_flying = !self.properties.is_flying;
+                            self.properties.velocity = Vec3::ZERO;
+                        }
+                    } else if key_update.key == self.controls.jump {
+                        if self.properties.last_spacebar < 0.25 {
+                            self.properties.is_flying = !self.properties.is_flying;
+                            self.properties.velocity = Vec3::ZERO;
+                        }
+                        self.properties.last_spacebar = 0.0;
                     }
-                    self.properties.last_spacebar = 0.0;
                 }
                 self.pressed_keys.remove(&key_update.key);
             } else {
@@ -171,30 +405,43 @@ impl MovementPlugin {
         let mut horizontal_acceleration = Vec3::ZERO;
         let mut vertical_acceleration = GRAVITY;
 
-        for key in self.pressed_keys.iter() {
-            match *key {
-                fmc::Key::KeyW => horizontal_acceleration += forward,
-                fmc::Key::KeyS => horizontal_acceleration -= forward,
-                fmc::Key::KeyA => horizontal_acceleration -= sideways,
-                fmc::Key::KeyD => horizontal_acceleration += sideways,
-                fmc::Key::Space => {
-                    if self.properties.is_swimming {
-                        vertical_acceleration.y = 30.0
-                    } else if self.properties.is_grounded.y && self.properties.last_jump > JUMP_TIME
-                    {
-                        self.properties.last_jump = 0.0;
-                        self.properties.velocity.y = JUMP_VELOCITY;
-                    }
+        // When the server makes the player climb there's nothing to push against, they climb
+        // with the jump key.
+        let climbing = self.properties.climbing.or_else(|| {
+            (self.mode == MovementMode::Climbing).then_some(Climbing {
+                direction: Vec3::ZERO,
+                config: ClimbConfig::default(),
+            })
+        });
+
+        let mut climb_up = false;
+        let mut sneaking = false;
+
+        let controls = &self.controls;
+        for key in self.pressed_keys.iter().copied() {
+            if key == controls.forward {
+                horizontal_acceleration += forward;
+            } else if key == controls.back {
+                horizontal_acceleration -= forward;
+            } else if key == controls.left {
+                horizontal_acceleration -= sideways;
+            } else if key == controls.right {
+                horizontal_acceleration += sideways;
+            } else if key == controls.jump {
+                if climbing.is_some() {
+                    climb_up = true;
+                } else if self.properties.is_swimming {
+                    vertical_acceleration.y = 30.0
+                } else if self.properties.is_grounded.y && self.properties.last_jump > JUMP_TIME {
+                    self.properties.last_jump = 0.0;
+                    self.properties.velocity.y = JUMP_VELOCITY;
                 }
-                fmc::Key::Shift => {
-                    if self.properties.is_swimming {
-                        vertical_acceleration.y = -30.0
-                    } else if self.properties.climbing.is_some() {
-                        self.properties.velocity.y = 0.0;
-                        vertical_acceleration.y = 0.0;
-                    }
+            } else if key == controls.sneak {
+                if climbing.is_some() {
+                    sneaking = true;
+                } else if self.properties.is_swimming {
+                    vertical_acceleration.y = -30.0
                 }
-                _ => (),
             }
         }
 
@@ -209,17 +456,105 @@ impl MovementPlugin {
                 horizontal_acceleration *= 20.0;
             }
 
-            if let Some(climbing_direction) = self.properties.climbing
-                && climbing_direction.dot(horizontal_acceleration) < 0.0
+            // Walking into what is climbed climbs it
+            if let Some(climbing) = climbing
+                && climbing.direction.dot(horizontal_acceleration) < 0.0
             {
-                self.properties.velocity.y = 5.0;
+                climb_up = true;
+            }
+        }
+
+        if let Some(Climbing { config, .. }) = climbing {
+            if climb_up {
+                self.properties.velocity.y = config.up_speed;
                 vertical_acceleration.y = 0.0;
+            } else if sneaking && config.sneak_holds {
+                self.properties.velocity.y = 0.0;
+                vertical_acceleration.y = 0.0;
+            } else if sneaking {
+                self.properties.velocity.y = -config.down_speed;
+                vertical_acceleration.y = 0.0;
+            } else {
+                // Grabbing on while falling slows the player down, and they slide down no faster
+                // than the climb lets them. Moving up they slow down like after a jump.
+                self.properties.velocity.y = self.properties.velocity.y.max(-config.down_speed);
             }
         }
 
         self.properties.acceleration = horizontal_acceleration + vertical_acceleration;
     }
 
+    // The movement keys steer the vehicle instead of the player. Vehicles float on water, the
+    // water's drag is replaced by the vehicle's own.
+    fn vehicle_controller(&mut self) {
+        let Some(vehicle) = &self.vehicle else {
+            return;
+        };
+
+        let camera_transform = fmc::get_camera_transform();
+        let camera_forward = camera_transform.forward();
+        let forward = Vec3::new(camera_forward.x, 0., camera_forward.z);
+        let sideways = Vec3::new(-camera_forward.z, 0., camera_forward.x);
+
+        let position = fmc::get_player_transform().translation;
+        let is_sunk = self.is_water(position);
+        let is_floating = is_sunk || self.is_water(position - Vec3::Y * vehicle.seat_height);
+
+        let mut horizontal_acceleration = Vec3::ZERO;
+        if is_floating || !vehicle.water_only {
+            let controls = &self.controls;
+            for key in self.pressed_keys.iter().copied() {
+                if key == controls.forward {
+                    horizontal_acceleration += forward;
+                } else if key == controls.back {
+                    horizontal_acceleration -= forward;
+                } else if key == controls.left {
+                    horizontal_acceleration -= sideways;
+                } else if key == controls.right {
+                    horizontal_acceleration += sideways;
+                }
+            }
+        }
+
+        if horizontal_acceleration != Vec3::ZERO {
+            horizontal_acceleration = horizontal_acceleration.normalize() * vehicle.acceleration;
+        }
+
+        let vertical_acceleration = if is_sunk {
+            self.properties.velocity.y = 2.0;
+            Vec3::ZERO
+        } else if is_floating {
+            self.properties.velocity.y = 0.0;
+            Vec3::ZERO
+        } else {
+            GRAVITY
+        };
+
+        let drag = (-vehicle.drag * self.delta_time).exp();
+        self.properties.velocity.x *= drag;
+        self.properties.velocity.z *= drag;
+
+        self.properties.acceleration = horizontal_acceleration + vertical_acceleration;
+    }
+
+    // If the player can move through the block
+    fn is_passable(&self, block_position: IVec3) -> bool {
+        let Some(block_id) = fmc::get_block(block_position) else {
+            return false;
+        };
+        let block_config = &self.block_configs[block_id as usize];
+        block_config.is_model || block_config.drag().is_some()
+    }
+
+    fn is_water(&self, position: Vec3) -> bool {
+        let Some(block_id) = fmc::get_block(position.floor().as_ivec3()) else {
+            return false;
+        };
+        self.block_configs[block_id as usize]
+            .drag()
+            .is_some_and(|drag| drag.y >= 0.5)
+    }
+
     fn flight_controller(&mut self) {
         let camera_transform = fmc::get_camera_transform();
         let camera_forward = camera_transform.forward();
@@ -230,19 +565,20 @@ impl MovementPlugin {
 
         let mut acceleration = Vec3::ZERO;
 
-        for key in self.pressed_keys.iter() {
-            match *key {
-                fmc::Key::KeyW => acceleration += forward,
-                fmc::Key::KeyS => acceleration -= forward,
-                fmc::Key::KeyA => acceleration -= sideways,
-                fmc::Key::KeyD => acceleration += sideways,
-                fmc::Key::Space => {
-                    self.properties.velocity.y = JUMP_VELOCITY * 2.0;
-                }
-                fmc::Key::Shift => {
-                    self.properties.velocity.y = -JUMP_VELOCITY * 2.0;
-                }
-                _ => (),
+        let controls = &self.controls;
+        for key in self.pressed_keys.iter().copied() {
+            if key == controls.forward {
+                acceleration += forward;
+            } else if key == controls.back {
+                acceleration -= forward;
+            } else if key == controls.left {
+                acceleration -= sideways;
+            } else if key == controls.right {
+                acceleration += sideways;
+            } else if key == controls.jump {
+                self.properties.velocity.y = JUMP_VELOCITY * 2.0;
+            } else if key == controls.sneak {
+                self.properties.velocity.y = -JUMP_VELOCITY * 2.0;
             }
         }
 
@@ -250,7 +586,7 @@ impl MovementPlugin {
             acceleration = acceleration.normalize() * 30.0;
         }
 
-        if self.pressed_keys.contains(&fmc::Key::Control) {
+        if self.pressed_keys.contains(&self.controls.sprint) {
             acceleration *= 10.0;
         }
 
@@ -277,7 +613,6 @@ impl MovementPlugin {
         self.properties.velocity *= (-friction / mass * self.delta_time).exp();
     }
 
-    // TODO: This tunnels if you move faster than maybe a few blocks a second
     fn collision(&mut self) {
         let player_transform = fmc::get_player_transform();
         let delta_time = Vec3::splat(self.delta_time);
@@ -299,15 +634,74 @@ impl MovementPlugin {
         let was_swimming = self.properties.is_swimming;
         self.properties.is_swimming = false;
 
-        let mut new_position = player_transform.translation + self.properties.velocity * delta_time;
-        let mut move_back = Vec3::ZERO;
+        // The move is split into steps short enough that the player can't pass through a block
+        // in one of them. Collisions change the velocity, so each step moves by what is left.
+        let distance = (self.properties.velocity * self.delta_time)
+            .abs()
+            .max_element();
+        let steps = ((distance / MAX_STEP).ceil() as u32).clamp(1, MAX_STEPS);
+        let step_time = Vec3::splat(self.delta_time / steps as f32);
+
+        let mut new_position = player_transform.translation;
         let mut friction = Vec3::ZERO;
+        let mut auto_jump = false;
+        for _ in 0..steps {
+            let Some(position) =
+                self.collision_step(new_position, step_time, &mut friction, &mut auto_jump)
+            else {
+                return;
+            };
+            new_position = position;
+        }
+
+        // A normal jump, so the player moves up smoothly instead of being lifted onto the ledge
+        if auto_jump && self.properties.last_jump > JUMP_TIME {
+            self.properties.last_jump = 0.0;
+            self.properties.velocity.y = JUMP_VELOCITY;
+        }
+
+        if self.mode == MovementMode::Swimming {
+            self.properties.is_swimming = true;
+            friction = friction.max(SWIM_DRAG);
+        }
+
+        if player_transform.translation != new_position {
+            fmc::set_player_transform(Transform {
+                translation: new_position,
+                rotation: DQuat::IDENTITY,
+                scale: Vec3::ONE,
+            });
+        }
+
+        let mass = 1.0;
+        self.properties.velocity *= (-friction / mass * self.delta_time).exp();
+
+        // Give a little boost when exiting water so that the bob stays constant.
+        if was_swimming && !self.properties.is_swimming {
+            self.properties.velocity.y += 1.5;
+        }
+    }
+
+    // Moves the player from the translation by their velocity, and moves them back out of
+    // anything they collide with. Returns where the player ends up, None if the blocks around
+    // them aren't loaded.
+    fn collision_step(
+        &mut self,
+        translation: Vec3,
+        delta_time: Vec3,
+        friction: &mut Vec3,
+        auto_jump: &mut bool,
+    ) -> Option<Vec3> {
+        let new_position = translation + self.properties.velocity * delta_time;
+        let mut move_back = Vec3::ZERO;
         for velocity in [
             Vec3::new(0.0, self.properties.velocity.y, 0.0),
             Vec3::new(self.properties.velocity.x, 0.0, self.properties.velocity.z),
         ] {
-            let pos_after_move = player_transform
-                .with_translation(player_transform.translation + velocity * delta_time);
+            let pos_after_move = Transform {
+                translation: translation + velocity * delta_time,
+                ..Transform::IDENTITY
+            };
 
             let player_aabb =
                 Aabb::from_min_max(Vec3::new(-0.3, 0.0, -0.3), Vec3::new(0.3, 1.8, 0.3));
@@ -317,7 +711,7 @@ impl MovementPlugin {
                 let block_id = match fmc::get_block(block_pos) {
                     Some(id) => id,
                     // Disconnect? Should always have your surroundings loaded.
-                    None => return,
+                    None => return None,
                 };
 
                 let block_config = &self.block_configs[block_id as usize];
@@ -346,29 +740,54 @@ impl MovementPlugin {
                     continue;
                 };
 
-                if block_config.climbable {
-                    self.properties.climbing = Some((rotation * DVec3::Z).as_vec3());
+                if let Some(config) = block_config.climbing {
+                    self.properties.climbing = Some(Climbing {
+                        direction: (rotation * DVec3::Z).as_vec3(),
+                        config,
+                    });
                 }
 
                 if let Some(drag) = block_config.drag() {
-                    friction = friction.max(drag);
+                    // Vehicles float on top of the water
+                    if self.vehicle.is_some() {
+                        continue;
+                    }
+                    *friction = friction.max(drag);
                     if drag.y >= 0.5 {
                         self.properties.is_swimming = true;
                     }
                     continue;
                 }
 
+                // Walking into a ledge one block high, with room to stand on top of it
+                if self.auto_jump
+                    && velocity.y == 0.0
+                    && self.properties.is_grounded.y
+                    && overlap.y > self.step_height
+                    && overlap.y < 1.01
+                    && self.is_passable(block_pos + IVec3::Y)
+                    && self.is_passable(block_pos + IVec3::Y * 2)
+                {
+                    *auto_jump = true;
+                }
+
                 Self::resolve_conflict(
                     &mut self.properties,
                     &mut move_back,
-                    &mut friction,
+                    friction,
                     &block_config,
                     velocity,
                     overlap,
                     delta_time,
+                    self.step_height,
                 );
             }
 
+            // The player would collide with the vehicle they're sitting in
+            if self.vehicle.is_some() {
+                continue;
+            }
+
             let world_aabb = player_aabb.transform(&pos_after_move);
             for model_id in fmc::get_models(world_aabb.min(), world_aabb.max()) {
                 let Some(config) = self.models.get(&model_id) else {
@@ -377,43 +796,26 @@ impl MovementPlugin {
 
                 let transform = fmc::get_model_transform(model_id);
 
-                let Some(intersection) = player_collider.intersection(
-                    &pos_after_move,
-                    &transform,
-                    &config.collider,
-                ) else {
+                let Some(intersection) =
+                    player_collider.intersection(&pos_after_move, &transform, &config.collider)
+                else {
                     continue;
                 };
 
                 Self::resolve_conflict(
                     &mut self.properties,
                     &mut move_back,
-                    &mut friction,
+                    friction,
                     &config,
                     velocity,
                     intersection,
                     delta_time,
+                    self.step_height,
                 );
             }
         }
 
-        new_position += move_back;
-
-        if player_transform.translation != new_position {
-            fmc::set_player_transform(Transform {
-                translation: new_position,
-                rotation: DQuat::IDENTITY,
-                scale: Vec3::ONE,
-            });
-        }
-
-        let mass = 1.0;
-        self.properties.velocity *= (-friction / mass * self.delta_time).exp();
-
-        // Give a little boost when exiting water so that the bob stays constant.
-        if was_swimming && !self.properties.is_swimming {
-            self.properties.velocity.y += 1.5;
-        }
+        Some(new_position + move_back)
     }
 
     #[inline]
@@ -425,13 +827,14 @@ impl MovementPlugin {
         velocity: Vec3,
         overlap: Vec3,
         delta_time: Vec3,
+        step_height: f32,
     ) {
         let backwards_time = overlap / -velocity;
         let valid_axes = backwards_time.cmplt(delta_time + delta_time / 100.0)
             & backwards_time.cmpgt(Vec3::splat(0.0));
         let resolution_axis = Vec3::select(valid_axes, backwards_time, Vec3::NAN).max_element();
 
-        if properties.is_grounded.y && overlap.y > 0.0 && overlap.y < 0.51 {
+        if properties.is_grounded.y && overlap.y > 0.0 && overlap.y < step_height {
             // This let's the player step up short distances when moving horizontally
             move_back.y = move_back.y.max(0.05_f32.min(overlap.y + overlap.y / 100.0));
             properties.is_grounded.y = true;
@@ -570,7 +973,7 @@ impl Aabb {
 pub struct CollisionConfig {
     collider: Collider,
     friction: Friction,
-    climbable: bool,
+    climbing: Option<ClimbConfig>,
     is_model: bool,
 }
 
@@ -666,9 +1069,7 @@ impl Collider {
         let min = aabb.min().floor().as_ivec3();
         let max = aabb.max().floor().as_ivec3();
         (min.x..=max.x).flat_map(move |x| {
-            (min.z..=max.z).flat_map(move |z| {
-                (min.y..=max.y).map(move |y| IVec3::new(x, y, z))
-            })
+            (min.z..=max.z).flat_map(move |z| (min.y..=max.y).map(move |y| IVec3::new(x, y, z)))
         })
     }
 
@@ -1,3 +1,5 @@
+use std::path::{Path, PathBuf};
+
 use fmc::{prelude::*, terminal::Cli};
 
 /// Extracts the bundled assets to the "assets" directory
@@ -39,3 +41,225 @@ impl Plugin for ExtractBundledAssetsPlugin {
         }
     }
 }
+
+/// Collects the problems found while loading the asset files, so that all of them can be fixed
+/// in one go. If any are found during startup, the server stops with a report of all of them
+/// once everything has been loaded.
+pub struct AssetErrorsPlugin;
+impl Plugin for AssetErrorsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<AssetErrors>()
+            .add_systems(PostStartup, report_asset_errors.in_set(AssetErrorSystems));
+    }
+}
+
+/// Systems that find asset errors during [PostStartup] must run before this set.
+#[derive(SystemSet, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct AssetErrorSystems;
+
+/// A problem with one of the asset files
+pub struct AssetError {
+    path: PathBuf,
+    /// Where in the file the problem is, the line and column for files that couldn't be read, or
+    /// the name of the field.
+    location: Option<String>,
+    message: String,
+    suggestion: Option<String>,
+}
+
+impl AssetError {
+    pub fn new(path: impl AsRef<Path>, message: impl Into<String>) -> Self {
+        Self {
+            path: path.as_ref().to_owned(),
+            location: None,
+            message: message.into(),
+            suggestion: None,
+        }
+    }
+
+    /// The file isn't valid json, or doesn't have the expected fields.
+    pub fn json(path: impl AsRef<Path>, error: &serde_json::Error) -> Self {
+        let mut asset_error = Self::new(path, error.to_string());
+        if error.line() != 0 {
+            asset_error.location = Some(format!("line {} column {}", error.line(), error.column()));
+        }
+        asset_error
+    }
+
+    pub fn field(mut self, field: impl Into<String>) -> Self {
+        self.location = Some(format!("field '{}'", field.into()));
+        self
+    }
+
+    pub fn suggestion(mut self, suggestion: impl Into<String>) -> Self {
+        self.suggestion = Some(suggestion.into());
+        self
+    }
+
+    /// Suggest the name of the asset in `directory` that is most like `name`, for when `name`
+    /// doesn't exist.
+    pub fn misspelled(self, name: &str, directory: &str) -> Self {
+        match closest_name(name, directory) {
+            Some(closest) => self.suggestion(format!("Did you mean '{}'?", closest)),
+            None => self.suggestion(format!("Make sure it is present at '{}'", directory)),
+        }
+    }
+}
+
+impl std::fmt::Display for AssetError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.path.display())?;
+        if let Some(location) = &self.location {
+            write!(f, ", {}", location)?;
+        }
+        write!(f, "\n    {}", self.message)?;
+        if let Some(suggestion) = &self.suggestion {
+            write!(f, "\n    {}", suggestion)?;
+        }
+        Ok(())
+    }
+}
+
+/// All the problems found while loading some assets.
+#[derive(Resource, Default)]
+pub struct AssetErrors(Vec<AssetError>);
+
+impl AssetErrors {
+    pub fn push(&mut self, error: AssetError) {
+        self.0.push(error);
+    }
+
+    pub fn extend(&mut self, other: AssetErrors) {
+        self.0.extend(other.0);
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Ok if no problems were found
+    pub fn into_result<T>(self, value: T) -> Result<T, AssetErrors> {
+        if self.is_empty() {
+            Ok(value)
+        } else {
+            Err(self)
+        }
+    }
+}
+
+impl From<AssetError> for AssetErrors {
+    fn from(error: AssetError) -> Self {
+        Self(vec![error])
+    }
+}
+
+impl std::fmt::Display for AssetErrors {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.0.len() == 1 {
+            write!(f, "Found a problem in the assets:")?;
+        } else {
+            write!(f, "Found {} problems in the assets:", self.0.len())?;
+        }
+        for error in self.0.iter() {
+            write!(f, "\n\n{}", error)?;
+        }
+        Ok(())
+    }
+}
+
+fn report_asset_errors(asset_errors: Res<AssetErrors>) {
+    if asset_errors.is_empty() {
+        return;
+    }
+
+    error!("{}", *asset_errors);
+    std::process::exit(1);
+}
+
+// The name of the file in the directory that takes the fewest edits to turn into the name. Names
+// that need more than a third of their letters changed are too different to be a typo.
+fn closest_name(name: &str, directory: &str) -> Option<String> {
+    let max_distance = (name.chars().count() / 3).max(1);
+
+    std::fs::read_dir(directory)
+        .ok()?
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| Some(entry.path().file_stem()?.to_string_lossy().into_owned()))
+        .map(|candidate| (edit_distance(name, &candidate), candidate))
+        .filter(|(distance, _)| *distance <= max_distance)
+        .min_by_key(|(distance, _)| *distance)
+        .map(|(_, candidate)| candidate)
+}
+
+// How many letters have to be inserted, removed or replaced to turn one word into the other.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    let mut current = vec![0; b.len() + 1];
+
+    for (i, a_char) in a.chars().enumerate() {
+        current[0] = i + 1;
+        for (j, b_char) in b.iter().enumerate() {
+            let replace = previous[j] + (a_char != *b_char) as usize;
+            current[j + 1] = replace.min(previous[j + 1] + 1).min(current[j] + 1);
+        }
+        std::mem::swap(&mut previous, &mut current);
+    }
+
+    previous[b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::fixture;
+
+    #[test]
+    fn edit_distances() {
+        assert_eq!(edit_distance("stone", "stone"), 0);
+        assert_eq!(edit_distance("stne", "stone"), 1);
+        assert_eq!(edit_distance("stone", "stones"), 1);
+        assert_eq!(edit_distance("gras", "glass"), 2);
+        assert_eq!(edit_distance("", "air"), 3);
+    }
+
+    #[test]
+    fn misspelled_names() {
+        let directory = fixture("biomes");
+
+        let error = AssetError::new("biome.json", "No biome by that name")
+            .misspelled("unknown_name", &directory);
+        assert_eq!(
+            error.suggestion.as_deref(),
+            Some("Did you mean 'unknown_names'?")
+        );
+
+        let error =
+            AssetError::new("biome.json", "No biome by that name").misspelled("desert", &directory);
+        assert_eq!(
+            error.suggestion,
+            Some(format!("Make sure it is present at '{}'", directory))
+        );
+    }
+
+    #[test]
+    fn report_lists_every_problem() {
+        let mut errors = AssetErrors::default();
+        errors.push(
+            AssetError::new("recipes/ladder.json", "Item name 'stik' is not recognized")
+                .field("pattern")
+                .suggestion("Did you mean 'stick'?"),
+        );
+        errors.push(AssetError::new("biomes", "There are no biomes"));
+
+        assert_eq!(
+            errors.to_string(),
+            "Found 2 problems in the assets:\n\n\
+            recipes/ladder.json, field 'pattern'\n    \
+            Item name 'stik' is not recognized\n    \
+            Did you mean 'stick'?\n\n\
+            biomes\n    \
+            There are no biomes"
+        );
+    }
+}
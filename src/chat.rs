@@ -1,107 +1,980 @@
+use std::collections::HashMap;
+
 use crate::{
+    console::ConsoleCommand,
+    database_io::DatabaseIo,
     fmc::{
-        networking::{NetworkEvent, NetworkMessage, Server},
+        bevy::math::DVec3,
+        blocks::{BlockPosition, Blocks},
+        networking::{NetworkMessage, Server},
         players::Player,
         prelude::*,
         protocol::messages,
     },
-    players::GameMode,
-    skybox::Clock,
+    metrics::Metrics,
+    mobs::name_tags::NameItem,
+    players::{
+        GameMode, OpenAdvancements,
+        access::{self, AccessControl},
+        mail::{OpenMailbox, SendMail},
+        roster::Roster,
+        teleport::{Destination, RemoveWarp, SetWaypoint, Teleport, Waypoint},
+    },
+    reload::ReloadAssets,
+    settings::Settings,
+    skybox::{Clock, FrozenTime},
+    world::{
+        blocks::water::DrainWater,
+        edit::{Edit, EditBlocks},
+        game_rules::{Difficulty, GameRules},
+        saving::SaveWorld,
+        schematics::Schematic,
+        worlds::{CreateWorld, ListWorlds, SwitchWorld},
+    },
 };
 
 pub const CHAT_FONT_SIZE: f32 = 8.0;
 pub const CHAT_TEXT_COLOR: &str = "#ffffff";
 
+/// Radius /drain uses when none is given
+const DEFAULT_DRAIN_RADIUS: u32 = 8;
+/// The largest radius /drain accepts
+const MAX_DRAIN_RADIUS: u32 = 32;
+
+const NO_PERMISSION: &str = "You do not have permission to use this command";
+const PLAYERS_ONLY: &str = "Only players can use this command";
+const TELEPORT_USAGE: &str = "Usage: /tp [player] <target player | x y z>";
+/// The name messages sent from the server console are shown with
+const CONSOLE_NAME: &str = "Server";
+
 pub struct ChatPlugin;
 impl Plugin for ChatPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(Update, (handle_chat_messages, send_connection_messages));
+        app.add_systems(Update, (handle_chat_messages, send_chat_messages));
     }
 }
 
 fn handle_chat_messages(
+    mut commands: Commands,
     net: Res<Server>,
-    mut player_query: Query<(&Player, &mut GameMode)>,
+    database_io: Res<DatabaseIo>,
+    roster: Res<Roster>,
+    mut access_control: ResMut<AccessControl>,
+    mut player_query: Query<(&Player, &mut GameMode, &mut Transform)>,
     mut clock: ResMut<Clock>,
-    mut chat_message_query: MessageReader<NetworkMessage<messages::InterfaceTextInput>>,
+    metrics: Res<Metrics>,
+    mut game_rules: ResMut<GameRules>,
+    mut chat_inputs: (
+        MessageReader<NetworkMessage<messages::InterfaceTextInput>>,
+        MessageReader<ConsoleCommand>,
+    ),
+    mut mail_events: (MessageWriter<SendMail>, MessageWriter<OpenMailbox>),
+    mut player_events: (MessageWriter<NameItem>, MessageWriter<OpenAdvancements>),
+    mut teleport_events: (
+        MessageWriter<Teleport>,
+        MessageWriter<SetWaypoint>,
+        MessageWriter<RemoveWarp>,
+    ),
+    mut save_events: MessageWriter<SaveWorld>,
+    mut operator_events: (
+        MessageWriter<DrainWater>,
+        MessageWriter<ReloadAssets>,
+        MessageWriter<EditBlocks>,
+    ),
+    mut world_events: (
+        MessageWriter<ListWorlds>,
+        MessageWriter<CreateWorld>,
+        MessageWriter<SwitchWorld>,
+    ),
 ) {
-    for chat_message in chat_message_query.read() {
-        if &chat_message.interface_path != "chat/input" {
-            continue;
-        }
-
-        let Ok((player, mut game_mode)) = player_query.get_mut(chat_message.player_entity) else {
-            // TODO: Should probably disconnect
-            continue;
+    for (sender, text) in read_chat_inputs(&mut chat_inputs.0, &mut chat_inputs.1) {
+        let operator_level = match sender {
+            CommandSender::Player(player_entity) => {
+                let Ok((player, _, _)) = player_query.get(player_entity) else {
+                    // TODO: Should probably disconnect
+                    continue;
+                };
+                access_control.operator_level(&player.username)
+            }
+            CommandSender::Console => access::OP_LEVEL,
         };
 
         // TODO: All commands are handled here until a proper system is figured out
-        if chat_message.text.starts_with("/") {
-            if let Some(time) = chat_message.text.strip_prefix("/time ") {
+        if text.starts_with("/") {
+            if let Some(time) = text.strip_prefix("/time ") {
+                if operator_level < access::GAME_RULE_LEVEL {
+                    sender.reply(&net, NO_PERMISSION.to_owned());
+                    continue;
+                }
+
                 match time {
                     "noon" => clock.set_noon(),
                     "midnight" => clock.set_midnight(),
                     "sunrise" => clock.set_sunrise(),
                     "sunset" => clock.set_sunset(),
+                    // Only the player's own sky is frozen, the clock keeps running
+                    "freeze" | "unfreeze" => {
+                        let Some(player_entity) = sender.player_entity() else {
+                            sender.reply(&net, PLAYERS_ONLY.to_owned());
+                            continue;
+                        };
+
+                        if time == "freeze" {
+                            commands
+                                .entity(player_entity)
+                                .insert(FrozenTime(clock.get_time()));
+                        } else {
+                            commands.entity(player_entity).remove::<FrozenTime>();
+                        }
+                    }
                     custom_time => {
                         if let Some(time) = custom_time
                             .strip_prefix("set ")
                             .and_then(|time| str::parse::<f32>(time).ok())
                         {
                             clock.set_time(time);
+                        } else if let Some(seconds) = custom_time
+                            .strip_prefix("add ")
+                            .and_then(|seconds| str::parse::<f32>(seconds).ok())
+                        {
+                            clock.advance(seconds);
                         }
                     }
                 }
-            } else if let Some(mode) = chat_message.text.strip_prefix("/gamemode ") {
+            } else if let Some(mode) = text.strip_prefix("/gamemode ") {
+                if operator_level < access::GAME_RULE_LEVEL {
+                    sender.reply(&net, NO_PERMISSION.to_owned());
+                    continue;
+                }
+
+                let Some(player_entity) = sender.player_entity() else {
+                    sender.reply(&net, PLAYERS_ONLY.to_owned());
+                    continue;
+                };
+                let Ok((_, mut game_mode, _)) = player_query.get_mut(player_entity) else {
+                    continue;
+                };
+
                 match mode {
                     "0" => *game_mode = GameMode::Survival,
                     "1" => *game_mode = GameMode::Creative,
+                    "2" => *game_mode = GameMode::Spectator,
                     _ => (),
                 }
+            } else if let Some(arguments) = text
+                .strip_prefix("/gamerule")
+                .filter(|arguments| arguments.is_empty() || arguments.starts_with(" "))
+            {
+                let arguments: Vec<&str> = arguments.split_whitespace().collect();
+                let text = match arguments.as_slice() {
+                    [] => GameRules::NAMES
+                        .iter()
+                        .map(|name| format!("{} = {}", name, game_rules.get(name).unwrap()))
+                        .collect::<Vec<_>>()
+                        .join(", "),
+                    [name] => match game_rules.get(name) {
+                        Some(value) => format!("{} = {}", name, value),
+                        None => format!("There is no game rule named {}", name),
+                    },
+                    [name, value] => {
+                        if operator_level < access::GAME_RULE_LEVEL {
+                            NO_PERMISSION.to_owned()
+                        } else {
+                            match game_rules.set(name, value) {
+                                Ok(()) => format!("Set {} to {}", name, value),
+                                Err(e) => e,
+                            }
+                        }
+                    }
+                    _ => "Usage: /gamerule [rule] [true | false]".to_owned(),
+                };
+                sender.reply(&net, text);
+            } else if let Some(arguments) = text
+                .strip_prefix("/difficulty")
+                .filter(|arguments| arguments.is_empty() || arguments.starts_with(" "))
+            {
+                let text = match arguments.split_whitespace().collect::<Vec<_>>().as_slice() {
+                    [] => format!("The difficulty is {}", game_rules.difficulty().name()),
+                    [name] => {
+                        if operator_level < access::GAME_RULE_LEVEL {
+                            NO_PERMISSION.to_owned()
+                        } else if let Some(difficulty) = Difficulty::from_name(name) {
+                            game_rules.set_difficulty(difficulty);
+                            format!("Set the difficulty to {}", difficulty.name())
+                        } else {
+                            format!(
+                                "The difficulty must be one of: {}",
+                                Difficulty::NAMES.join(", ")
+                            )
+                        }
+                    }
+                    _ => format!("Usage: /difficulty [{}]", Difficulty::NAMES.join(" | ")),
+                };
+                sender.reply(&net, text);
+            } else if let Some(arguments) = text
+                .strip_prefix("/tp")
+                .filter(|arguments| arguments.is_empty() || arguments.starts_with(" "))
+            {
+                let arguments: Vec<&str> = arguments.split_whitespace().collect();
+                // Spectators can follow other players, everything else is for operators
+                let is_spectating = sender
+                    .player_entity()
+                    .and_then(|player_entity| player_query.get(player_entity).ok())
+                    .is_some_and(|(_, game_mode, _)| *game_mode == GameMode::Spectator);
+                if operator_level < access::TELEPORT_LEVEL
+                    && !(is_spectating && arguments.len() == 1)
+                {
+                    sender.reply(&net, NO_PERMISSION.to_owned());
+                    continue;
+                }
+
+                // The sender is teleported unless the first argument is who to teleport
+                let (player, target) = match arguments.as_slice() {
+                    [player, target @ ..] if target.len() == 1 || target.len() == 3 => {
+                        (Some(*player), target)
+                    }
+                    target => (None, target),
+                };
+
+                let player_entity = match player {
+                    Some(username) => roster.get_entity(username),
+                    None => sender.player_entity(),
+                };
+                let Some(player_entity) = player_entity else {
+                    match player {
+                        Some(username) => sender.reply(
+                            &net,
+                            format!("There is no player named {} online", username),
+                        ),
+                        None => sender.reply(&net, PLAYERS_ONLY.to_owned()),
+                    }
+                    continue;
+                };
+
+                let origin = sender
+                    .player_entity()
+                    .and_then(|player_entity| player_query.get(player_entity).ok())
+                    .map(|(_, _, transform)| BlockPosition::from(transform.translation));
+                let destination = match target {
+                    [username] => {
+                        let Some(position) = roster
+                            .get_entity(username)
+                            .and_then(|entity| player_query.get(entity).ok())
+                            .map(|(_, _, transform)| transform.translation)
+                        else {
+                            sender.reply(
+                                &net,
+                                format!("There is no player named {} online", username),
+                            );
+                            continue;
+                        };
+                        position
+                    }
+                    [_, _, _] => match parse_position(target, origin) {
+                        Some(position) => position.as_dvec3() + DVec3::new(0.5, 0.0, 0.5),
+                        None => {
+                            sender.reply(&net, TELEPORT_USAGE.to_owned());
+                            continue;
+                        }
+                    },
+                    _ => {
+                        sender.reply(&net, TELEPORT_USAGE.to_owned());
+                        continue;
+                    }
+                };
+
+                teleport_events.0.write(Teleport {
+                    player_entity,
+                    destination: Destination::Position(destination),
+                });
+            } else if let Some(destination) = match text.as_str() {
+                "/spawn" => Some(Destination::WorldSpawn),
+                "/home" => Some(Destination::Waypoint(Waypoint::Home)),
+                _ => text
+                    .strip_prefix("/warp ")
+                    .map(|name| Destination::Waypoint(Waypoint::Warp(name.trim().to_owned()))),
+            } {
+                let Some(player_entity) = sender.player_entity() else {
+                    sender.reply(&net, PLAYERS_ONLY.to_owned());
+                    continue;
+                };
+                teleport_events.0.write(Teleport {
+                    player_entity,
+                    destination,
+                });
+            } else if let Some(waypoint) = match text.as_str() {
+                "/sethome" => Some(Waypoint::Home),
+                _ => text
+                    .strip_prefix("/setwarp ")
+                    .map(|name| Waypoint::Warp(name.trim().to_owned())),
+            } {
+                let Some(player_entity) = sender.player_entity() else {
+                    sender.reply(&net, PLAYERS_ONLY.to_owned());
+                    continue;
+                };
+
+                if let Waypoint::Warp(name) = &waypoint {
+                    if operator_level < access::WARP_LEVEL {
+                        sender.reply(&net, NO_PERMISSION.to_owned());
+                        continue;
+                    } else if name.is_empty() || name.contains(char::is_whitespace) {
+                        sender.reply(&net, "Usage: /setwarp <name>".to_owned());
+                        continue;
+                    }
+                }
+
+                teleport_events.1.write(SetWaypoint {
+                    player_entity,
+                    waypoint,
+                });
+            } else if let Some(name) = text.strip_prefix("/delwarp ") {
+                if operator_level < access::WARP_LEVEL {
+                    sender.reply(&net, NO_PERMISSION.to_owned());
+                    continue;
+                }
+
+                teleport_events.2.write(RemoveWarp {
+                    requested_by: sender.player_entity(),
+                    name: name.trim().to_owned(),
+                });
+            } else if let Some(arguments) = text.strip_prefix("/ban ") {
+                if operator_level < access::BAN_LEVEL {
+                    sender.reply(&net, NO_PERMISSION.to_owned());
+                    continue;
+                }
+
+                let (username, reason) = arguments.split_once(" ").unwrap_or((arguments, ""));
+                access_control.ban(username, reason.trim(), &database_io);
+
+                if let Some(entity) = roster.get_entity(username) {
+                    let reason = if reason.trim().is_empty() {
+                        "You have been banned from this server".to_owned()
+                    } else {
+                        format!("You have been banned from this server: {}", reason.trim())
+                    };
+                    access::kick(&net, entity, reason);
+                }
+
+                sender.reply(&net, format!("Banned {}", username));
+            } else if let Some(username) = text.strip_prefix("/pardon ") {
+                if operator_level < access::BAN_LEVEL {
+                    sender.reply(&net, NO_PERMISSION.to_owned());
+                    continue;
+                }
+
+                let text = if access_control.pardon(username, &database_io) {
+                    format!("Pardoned {}", username)
+                } else {
+                    format!("{} is not banned", username)
+                };
+                sender.reply(&net, text);
+            } else if let Some(username) = text.strip_prefix("/whitelist add ") {
+                if operator_level < access::WHITELIST_LEVEL {
+                    sender.reply(&net, NO_PERMISSION.to_owned());
+                    continue;
+                }
+
+                access_control.whitelist_add(username, &database_io);
+                sender.reply(&net, format!("Added {} to the whitelist", username));
+            } else if text == "/advancements" {
+                let Some(player_entity) = sender.player_entity() else {
+                    sender.reply(&net, PLAYERS_ONLY.to_owned());
+                    continue;
+                };
+                player_events.1.write(OpenAdvancements { player_entity });
+            } else if text == "/mail" {
+                let Some(player_entity) = sender.player_entity() else {
+                    sender.reply(&net, PLAYERS_ONLY.to_owned());
+                    continue;
+                };
+                mail_events.1.write(OpenMailbox { player_entity });
+            } else if let Some(arguments) = text.strip_prefix("/mail send ") {
+                let Some(player_entity) = sender.player_entity() else {
+                    sender.reply(&net, PLAYERS_ONLY.to_owned());
+                    continue;
+                };
+
+                let (recipient, message) = arguments.split_once(" ").unwrap_or((arguments, ""));
+                if recipient.is_empty() {
+                    sender.reply(&net, "Usage: /mail send <username> [message]".to_owned());
+                    continue;
+                }
+
+                mail_events.0.write(SendMail {
+                    sender_entity: player_entity,
+                    recipient: recipient.to_owned(),
+                    message: message.trim().to_owned(),
+                });
+            } else if let Some(name) = text.strip_prefix("/name ") {
+                let Some(player_entity) = sender.player_entity() else {
+                    sender.reply(&net, PLAYERS_ONLY.to_owned());
+                    continue;
+                };
+                player_events.0.write(NameItem {
+                    player_entity,
+                    name: name.to_owned(),
+                });
+            } else if let Some(arguments) = text
+                .strip_prefix("/drain")
+                .filter(|arguments| arguments.is_empty() || arguments.starts_with(" "))
+            {
+                if operator_level < access::DRAIN_LEVEL {
+                    sender.reply(&net, NO_PERMISSION.to_owned());
+                    continue;
+                }
+
+                let radius = match arguments.trim() {
+                    "" => Some(DEFAULT_DRAIN_RADIUS),
+                    radius => radius
+                        .parse::<u32>()
+                        .ok()
+                        .filter(|radius| *radius <= MAX_DRAIN_RADIUS),
+                };
+                let Some(radius) = radius else {
+                    sender.reply(
+                        &net,
+                        format!("Usage: /drain [radius 0-{}]", MAX_DRAIN_RADIUS),
+                    );
+                    continue;
+                };
+
+                let Some(player_entity) = sender.player_entity() else {
+                    sender.reply(&net, PLAYERS_ONLY.to_owned());
+                    continue;
+                };
+
+                let Ok((_, _, transform)) = player_query.get(player_entity) else {
+                    continue;
+                };
+                operator_events.0.write(DrainWater {
+                    position: BlockPosition::from(transform.translation),
+                    radius,
+                });
+                sender.reply(&net, format!("Drained the water within {} blocks", radius));
+            } else if let Some((command, arguments)) = ["/fill", "/setblock", "/clone", "/undo"]
+                .into_iter()
+                .find_map(|command| Some((command, text.strip_prefix(command)?)))
+                .filter(|(_, arguments)| arguments.is_empty() || arguments.starts_with(" "))
+            {
+                if operator_level < access::EDIT_LEVEL {
+                    sender.reply(&net, NO_PERMISSION.to_owned());
+                    continue;
+                }
+
+                let origin = sender
+                    .player_entity()
+                    .and_then(|player_entity| player_query.get(player_entity).ok())
+                    .map(|(_, _, transform)| BlockPosition::from(transform.translation));
+                let arguments: Vec<&str> = arguments.split_whitespace().collect();
+                let Some(edit) = parse_edit(command, &arguments, origin) else {
+                    let usage = match command {
+                        "/fill" => "Usage: /fill <x1> <y1> <z1> <x2> <y2> <z2> <block>",
+                        "/setblock" => "Usage: /setblock <x> <y> <z> <block>",
+                        "/clone" => "Usage: /clone <x1> <y1> <z1> <x2> <y2> <z2> <x> <y> <z>",
+                        _ => "Usage: /undo",
+                    };
+                    sender.reply(&net, usage.to_owned());
+                    continue;
+                };
+
+                operator_events.2.write(EditBlocks {
+                    requested_by: sender.player_entity(),
+                    edit,
+                });
+            } else if let Some(arguments) = text
+                .strip_prefix("/schem")
+                .filter(|arguments| arguments.is_empty() || arguments.starts_with(" "))
+            {
+                if operator_level < access::SCHEMATIC_LEVEL {
+                    sender.reply(&net, NO_PERMISSION.to_owned());
+                    continue;
+                }
+
+                let origin = sender
+                    .player_entity()
+                    .and_then(|player_entity| player_query.get(player_entity).ok())
+                    .map(|(_, _, transform)| BlockPosition::from(transform.translation));
+                let arguments: Vec<&str> = arguments.split_whitespace().collect();
+                let Some(edit) = parse_schematic(&arguments, origin) else {
+                    sender.reply(
+                        &net,
+                        "Usage: /schem save <name> <x1> <y1> <z1> <x2> <y2> <z2>".to_owned(),
+                    );
+                    sender.reply(
+                        &net,
+                        "Usage: /schem paste <name> [<x> <y> <z>] [0|90|180|270]".to_owned(),
+                    );
+                    continue;
+                };
+
+                operator_events.2.write(EditBlocks {
+                    requested_by: sender.player_entity(),
+                    edit,
+                });
+            } else if text == "/metrics" {
+                if operator_level < access::METRICS_LEVEL {
+                    sender.reply(&net, NO_PERMISSION.to_owned());
+                    continue;
+                }
+
+                for line in metrics.summary() {
+                    sender.reply(&net, line);
+                }
+            } else if text == "/reload" {
+                if operator_level < access::RELOAD_LEVEL {
+                    sender.reply(&net, NO_PERMISSION.to_owned());
+                    continue;
+                }
+
+                operator_events.1.write(ReloadAssets {
+                    requested_by: sender.player_entity(),
+                });
+            } else if text == "/save-all" || text == "/backup" {
+                if operator_level < access::SAVE_LEVEL {
+                    sender.reply(&net, NO_PERMISSION.to_owned());
+                    continue;
+                }
+
+                save_events.write(SaveWorld {
+                    requested_by: sender.player_entity(),
+                    backup: text == "/backup",
+                });
+            } else if let Some(arguments) = text
+                .strip_prefix("/world")
+                .filter(|arguments| arguments.is_empty() || arguments.starts_with(" "))
+            {
+                if operator_level < access::WORLD_LEVEL {
+                    sender.reply(&net, NO_PERMISSION.to_owned());
+                    continue;
+                }
+
+                let Some(player_entity) = sender.player_entity() else {
+                    sender.reply(&net, PLAYERS_ONLY.to_owned());
+                    continue;
+                };
+                let arguments: Vec<&str> = arguments.split_whitespace().collect();
+                match arguments.as_slice() {
+                    [] | ["list"] => {
+                        world_events.0.write(ListWorlds { player_entity });
+                    }
+                    ["create", name] | ["create", name, _] => {
+                        world_events.1.write(CreateWorld {
+                            player_entity,
+                            name: name.to_string(),
+                            seed: arguments.get(2).map(|seed| seed.to_string()),
+                        });
+                    }
+                    ["switch", name] => {
+                        world_events.2.write(SwitchWorld {
+                            player_entity,
+                            name: name.to_string(),
+                        });
+                    }
+                    _ => {
+                        send_message(
+                            &net,
+                            player_entity,
+                            "Usage: /world [list | create <name> [seed] | switch <name>]"
+                                .to_owned(),
+                        );
+                    }
+                }
+            } else if let Some(arguments) = text.strip_prefix("/op ") {
+                if operator_level < access::OP_LEVEL {
+                    sender.reply(&net, NO_PERMISSION.to_owned());
+                    continue;
+                }
+
+                let Some((username, level)) = arguments
+                    .split_once(" ")
+                    .and_then(|(username, level)| Some((username, level.parse::<u8>().ok()?)))
+                    .filter(|(_, level)| *level <= access::OP_LEVEL)
+                else {
+                    sender.reply(
+                        &net,
+                        format!("Usage: /op <username> <level 0-{}>", access::OP_LEVEL),
+                    );
+                    continue;
+                };
+
+                access_control.set_operator_level(username, level, &database_io);
+                sender.reply(
+                    &net,
+                    format!("Set the operator level of {} to {}", username, level),
+                );
             }
-        } else {
-            net.broadcast(messages::InterfaceTextUpdate {
-                interface_path: "chat/history".to_owned(),
-                index: i32::MAX,
-                text: format!("[{}] {}", &player.username, &chat_message.text),
-                font_size: CHAT_FONT_SIZE,
-                color: CHAT_TEXT_COLOR.to_owned(),
-            });
         }
     }
 }
 
-// TODO: Maybe players should be passed the chat history too.
-// TODO: The "joined game" message sometimes shows for the player that joined. Intermitent problem,
-// the message should arrive before the client finishes setup. In which case it should be
-// discarded after two event buffer switches.
-fn send_connection_messages(
+/// The longest chat message that is sent on, anything past it is cut off
+const MAX_MESSAGE_LENGTH: usize = 256;
+/// Color of the messages that mention the player they are shown to
+const MENTION_COLOR: &str = "#ffff55";
+/// Color of private messages
+const PRIVATE_MESSAGE_COLOR: &str = "#d98cff";
+
+/// Colors that can be picked with a '&' followed by the code at the start of a message
+const COLOR_CODES: [(char, &str); 16] = [
+    ('0', "#000000"),
+    ('1', "#0000aa"),
+    ('2', "#00aa00"),
+    ('3', "#00aaaa"),
+    ('4', "#aa0000"),
+    ('5', "#aa00aa"),
+    ('6', "#ffaa00"),
+    ('7', "#aaaaaa"),
+    ('8', "#555555"),
+    ('9', "#5555ff"),
+    ('a', "#55ff55"),
+    ('b', "#55ffff"),
+    ('c', "#ff5555"),
+    ('d', "#ff55ff"),
+    ('e', "#ffff55"),
+    ('f', "#ffffff"),
+];
+
+/// Clean up text from a player before it is shown to others. Control characters are removed and
+/// the text is cut to [MAX_MESSAGE_LENGTH]. A line is shown in one color, so if the player is
+/// allowed colors, the first color code picks the color of the whole message. Other codes are
+/// removed.
+fn sanitize(text: &str, allow_color: bool) -> (String, &'static str) {
+    let mut color = CHAT_TEXT_COLOR;
+    let mut picked_color = !allow_color;
+    let mut sanitized = String::with_capacity(text.len());
+
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c.is_control() {
+            continue;
+        }
+
+        if c == '&'
+            && let Some(code) = chars.peek()
+            && let Some((_, code_color)) = COLOR_CODES
+                .iter()
+                .find(|(color_code, _)| *color_code == code.to_ascii_lowercase())
+        {
+            chars.next();
+            if !picked_color {
+                color = *code_color;
+                picked_color = true;
+            }
+            continue;
+        }
+
+        sanitized.push(c);
+    }
+
+    let sanitized: String = sanitized.trim().chars().take(MAX_MESSAGE_LENGTH).collect();
+    (sanitized, color)
+}
+
+// Players that are mentioned with an '@' in front of their name
+fn mentions<'a>(text: &'a str, roster: &Roster) -> Vec<&'a str> {
+    text.split_whitespace()
+        .filter_map(|word| word.strip_prefix('@'))
+        .map(|name| name.trim_end_matches(|c: char| c.is_ascii_punctuation()))
+        .filter(|name| roster.get_entity(name).is_some())
+        .collect()
+}
+
+/// Show the text in the player's chat.
+pub fn send_message(net: &Server, player_entity: Entity, text: String) {
+    send_colored_message(net, player_entity, text, CHAT_TEXT_COLOR);
+}
+
+/// Show the text in the player's chat in another color than the default.
+pub fn send_colored_message(net: &Server, player_entity: Entity, text: String, color: &str) {
+    net.send_one(player_entity, chat_message(text, color));
+}
+
+/// Show the text in the chat of every player.
+pub fn broadcast_message(net: &Server, text: String) {
+    net.broadcast(chat_message(text, CHAT_TEXT_COLOR));
+}
+
+fn chat_message(text: String, color: &str) -> messages::InterfaceTextUpdate {
+    messages::InterfaceTextUpdate {
+        interface_path: "chat/history".to_owned(),
+        index: i32::MAX,
+        text,
+        font_size: CHAT_FONT_SIZE,
+        color: color.to_owned(),
+    }
+}
+
+// Messages between players: what is said in the chat, /msg and /reply, and muting players.
+fn send_chat_messages(
     net: Res<Server>,
-    player_query: Query<&Player>,
-    mut network_events: MessageReader<NetworkEvent>,
+    time: Res<Time>,
+    settings: Res<Settings>,
+    database_io: Res<DatabaseIo>,
+    roster: Res<Roster>,
+    mut access_control: ResMut<AccessControl>,
+    players: Query<(Entity, &Player)>,
+    mut chat_inputs: (
+        MessageReader<NetworkMessage<messages::InterfaceTextInput>>,
+        MessageReader<ConsoleCommand>,
+    ),
+    // When each player last said something, in seconds since startup
+    mut last_message: Local<HashMap<String, f64>>,
+    // Who each player last had a private conversation with
+    mut reply_to: Local<HashMap<String, String>>,
 ) {
-    for event in network_events.read() {
-        match event {
-            NetworkEvent::Connected { entity } => {
-                let player = &player_query.get(*entity).unwrap();
-                net.broadcast(messages::InterfaceTextUpdate {
-                    interface_path: "chat/history".to_owned(),
-                    index: i32::MAX,
-                    text: format!("{} joined the game", player.username),
-                    font_size: CHAT_FONT_SIZE,
-                    color: CHAT_TEXT_COLOR.to_owned(),
-                });
+    for (sender, chat_text) in read_chat_inputs(&mut chat_inputs.0, &mut chat_inputs.1) {
+        let (name, operator_level) = match sender {
+            CommandSender::Player(player_entity) => {
+                let Ok((_, player)) = players.get(player_entity) else {
+                    continue;
+                };
+                (
+                    player.username.clone(),
+                    access_control.operator_level(&player.username),
+                )
             }
-            NetworkEvent::Disconnected { entity } => {
-                let player = player_query.get(*entity).unwrap();
-                net.broadcast(messages::InterfaceTextUpdate {
-                    interface_path: "chat/history".to_owned(),
-                    index: i32::MAX,
-                    text: format!("{} left the game", player.username),
-                    font_size: CHAT_FONT_SIZE,
-                    color: CHAT_TEXT_COLOR.to_owned(),
-                });
+            CommandSender::Console => (CONSOLE_NAME.to_owned(), access::OP_LEVEL),
+        };
+        let username = &name;
+
+        if let Some(arguments) = chat_text.strip_prefix("/mute ") {
+            if operator_level < access::MUTE_LEVEL {
+                sender.reply(&net, NO_PERMISSION.to_owned());
+                continue;
             }
+
+            let target = arguments.trim();
+            access_control.mute(target, &database_io);
+            sender.reply(&net, format!("Muted {}", target));
+            if let Some(target_entity) = roster.get_entity(target) {
+                send_message(&net, target_entity, "You have been muted".to_owned());
+            }
+            continue;
+        } else if let Some(arguments) = chat_text.strip_prefix("/unmute ") {
+            if operator_level < access::MUTE_LEVEL {
+                sender.reply(&net, NO_PERMISSION.to_owned());
+                continue;
+            }
+
+            let target = arguments.trim();
+            let text = if access_control.unmute(target, &database_io) {
+                format!("Unmuted {}", target)
+            } else {
+                format!("{} is not muted", target)
+            };
+            sender.reply(&net, text);
+            continue;
+        }
+
+        let (recipient, text) = if let Some(arguments) = chat_text.strip_prefix("/msg ") {
+            let Some((recipient, text)) = arguments.trim_start().split_once(' ') else {
+                sender.reply(&net, "Usage: /msg <player> <message>".to_owned());
+                continue;
+            };
+            (Some(recipient.to_owned()), text)
+        } else if let Some(text) = chat_text.strip_prefix("/reply ") {
+            let Some(recipient) = reply_to.get(username) else {
+                sender.reply(&net, "There is no one to reply to".to_owned());
+                continue;
+            };
+            (Some(recipient.clone()), text)
+        } else if chat_text.starts_with("/") {
+            // Other commands are handled by 'handle_chat_messages'
+            continue;
+        } else {
+            (None, chat_text.as_str())
+        };
+
+        if access_control.is_muted(username) {
+            sender.reply(&net, "You are muted".to_owned());
+            continue;
+        }
+
+        let now = time.elapsed_secs_f64();
+        if operator_level == 0
+            && last_message
+                .get(username)
+                .is_some_and(|last| now - last < settings.chat_cooldown as f64)
+        {
+            sender.reply(&net, "You are sending messages too quickly".to_owned());
+            continue;
+        }
+
+        let (text, color) = sanitize(text, operator_level >= access::CHAT_COLOR_LEVEL);
+        if text.is_empty() {
+            continue;
+        }
+        last_message.insert(username.clone(), now);
+
+        if let Some(recipient) = recipient {
+            let Some(recipient_entity) = roster.get_entity(&recipient) else {
+                sender.reply(
+                    &net,
+                    format!("There is no player named {} online", recipient),
+                );
+                continue;
+            };
+
+            send_colored_message(
+                &net,
+                recipient_entity,
+                format!("[{} -> you] {}", username, text),
+                PRIVATE_MESSAGE_COLOR,
+            );
+            sender.reply_colored(
+                &net,
+                format!("[you -> {}] {}", recipient, text),
+                PRIVATE_MESSAGE_COLOR,
+            );
+            reply_to.insert(recipient.clone(), username.clone());
+            reply_to.insert(username.clone(), recipient);
+            continue;
+        }
+
+        // Players that are mentioned get the message highlighted
+        let mentioned = mentions(&text, &roster);
+        let text = format!("[{}] {}", username, text);
+        for (player_entity, player) in players.iter() {
+            let color = if mentioned.contains(&player.username.as_str()) {
+                MENTION_COLOR
+            } else {
+                color
+            };
+            send_colored_message(&net, player_entity, text.clone(), color);
         }
     }
 }
+
+/// Who sent a command, a player through the chat or an admin through the server console.
+#[derive(Clone, Copy)]
+enum CommandSender {
+    Player(Entity),
+    Console,
+}
+
+impl CommandSender {
+    fn player_entity(self) -> Option<Entity> {
+        match self {
+            Self::Player(player_entity) => Some(player_entity),
+            Self::Console => None,
+        }
+    }
+
+    /// Answer the sender, answers to the console are logged.
+    fn reply(self, net: &Server, text: String) {
+        self.reply_colored(net, text, CHAT_TEXT_COLOR);
+    }
+
+    fn reply_colored(self, net: &Server, text: String, color: &str) {
+        match self {
+            Self::Player(player_entity) => send_colored_message(net, player_entity, text, color),
+            Self::Console => info!("{}", text),
+        }
+    }
+}
+
+// Block coordinates of a command. Coordinates starting with '~' are relative to the origin, the
+// position of the player that sent the command.
+fn parse_position(coordinates: &[&str], origin: Option<BlockPosition>) -> Option<BlockPosition> {
+    let mut position = [0; 3];
+    for (i, coordinate) in coordinates.iter().enumerate() {
+        position[i] = match coordinate.strip_prefix("~") {
+            Some("") => origin?[i],
+            Some(offset) => origin?[i] + offset.parse::<i32>().ok()?,
+            None => coordinate.parse::<i32>().ok()?,
+        };
+    }
+    Some(BlockPosition::new(position[0], position[1], position[2]))
+}
+
+fn parse_edit(command: &str, arguments: &[&str], origin: Option<BlockPosition>) -> Option<Edit> {
+    let block_id = |name: &str| {
+        let blocks = Blocks::get();
+        blocks.contains_block(name).then(|| blocks.get_id(name))
+    };
+
+    let edit = match (command, arguments) {
+        ("/fill", [_, _, _, _, _, _, block]) => Edit::Fill {
+            from: parse_position(&arguments[0..3], origin)?,
+            to: parse_position(&arguments[3..6], origin)?,
+            block_id: block_id(block)?,
+        },
+        ("/setblock", [_, _, _, block]) => Edit::SetBlock {
+            position: parse_position(&arguments[0..3], origin)?,
+            block_id: block_id(block)?,
+        },
+        ("/clone", _) if arguments.len() == 9 => Edit::Clone {
+            from: parse_position(&arguments[0..3], origin)?,
+            to: parse_position(&arguments[3..6], origin)?,
+            destination: parse_position(&arguments[6..9], origin)?,
+        },
+        ("/undo", []) => Edit::Undo,
+        _ => return None,
+    };
+
+    Some(edit)
+}
+
+// Schematics are pasted at the player that sent the command if no position is given. The
+// rotation is in degrees, turning the front of the schematic towards the right.
+fn parse_schematic(arguments: &[&str], origin: Option<BlockPosition>) -> Option<Edit> {
+    if !arguments
+        .get(1)
+        .is_some_and(|name| Schematic::is_valid_name(name))
+    {
+        return None;
+    }
+
+    let edit = match arguments {
+        ["save", name, coordinates @ ..] if coordinates.len() == 6 => Edit::SaveSchematic {
+            from: parse_position(&coordinates[0..3], origin)?,
+            to: parse_position(&coordinates[3..6], origin)?,
+            name: name.to_string(),
+        },
+        ["paste", name, rest @ ..] => {
+            let (destination, rotation) = match rest {
+                [] => (origin?, "0"),
+                [rotation] => (origin?, *rotation),
+                [_, _, _] => (parse_position(rest, origin)?, "0"),
+                [_, _, _, rotation] => (parse_position(&rest[0..3], origin)?, *rotation),
+                _ => return None,
+            };
+            Edit::PasteSchematic {
+                name: name.to_string(),
+                destination,
+                quarter_turns: match rotation {
+                    "0" => 0,
+                    "90" => 1,
+                    "180" => 2,
+                    "270" => 3,
+                    _ => return None,
+                },
+            }
+        }
+        _ => return None,
+    };
+
+    Some(edit)
+}
+
+// Everything that was typed into the chat or the server console, in the order it was read.
+fn read_chat_inputs(
+    chat_messages: &mut MessageReader<NetworkMessage<messages::InterfaceTextInput>>,
+    console_commands: &mut MessageReader<ConsoleCommand>,
+) -> Vec<(CommandSender, String)> {
+    chat_messages
+        .read()
+        .filter(|chat_message| chat_message.interface_path == "chat/input")
+        .map(|chat_message| {
+            (
+                CommandSender::Player(chat_message.player_entity),
+                chat_message.text.clone(),
+            )
+        })
+        .chain(
+            console_commands
+                .read()
+                .map(|command| (CommandSender::Console, command.text.clone())),
+        )
+        .collect()
+}
@@ -0,0 +1,165 @@
+use std::sync::{
+    Mutex,
+    mpsc::{self, Receiver, Sender},
+};
+
+use fmc::{
+    bevy::log::{
+        BoxedLayer,
+        tracing::{
+            Event, Level, Subscriber,
+            field::{Field, Visit},
+        },
+        tracing_subscriber::{Layer, layer::Context},
+    },
+    networking::Server,
+    players::Player,
+    prelude::*,
+    protocol::messages,
+};
+
+use crate::{
+    chat::send_colored_message,
+    players::access::{self, AccessControl},
+    settings::Settings,
+};
+
+/// Lets the server be run from the terminal it was started in. Lines typed into it are handled
+/// like chat messages from an operator with the highest level: lines starting with a '/' run the
+/// same commands as in the chat and anything else is said in the chat as the server. Answers to
+/// the commands are logged.
+///
+/// If `log-to-operators` is set in the settings, warnings and errors from the log are also shown
+/// in the chat of the operators that are online. The log is captured by [log_layer], which must be
+/// given to bevy's `LogPlugin`.
+pub struct ConsolePlugin;
+impl Plugin for ConsolePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_message::<ConsoleCommand>()
+            .add_systems(Startup, start_console)
+            .add_systems(PreUpdate, read_console)
+            .add_systems(Update, send_log_to_operators);
+    }
+}
+
+const WARNING_COLOR: &str = "#ffaa00";
+const ERROR_COLOR: &str = "#ff5555";
+
+/// A line that was typed into the server console
+#[derive(Message)]
+pub struct ConsoleCommand {
+    pub text: String,
+}
+
+/// Lines read from stdin by the console thread
+#[derive(Resource)]
+struct ConsoleInput(Mutex<Receiver<String>>);
+
+/// Warnings and errors that have been logged since they were last sent to the operators
+#[derive(Resource)]
+struct ServerLog(Mutex<Receiver<(Level, String)>>);
+
+/// Tracing layer that sends on the warnings and errors that are logged
+struct ServerLogLayer(Sender<(Level, String)>);
+
+impl<S: Subscriber> Layer<S> for ServerLogLayer {
+    fn on_event(&self, event: &Event<'_>, _ctx: Context<'_, S>) {
+        let level = *event.metadata().level();
+        // Less severe levels are greater
+        if level > Level::WARN {
+            return;
+        }
+
+        let mut message = MessageVisitor(String::new());
+        event.record(&mut message);
+        let _ = self.0.send((level, message.0));
+    }
+}
+
+// Takes out the message of a log event, the other fields are left out.
+struct MessageVisitor(String);
+
+impl Visit for MessageVisitor {
+    fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+        if field.name() == "message" {
+            self.0 = format!("{:?}", value);
+        }
+    }
+}
+
+/// Captures the warnings and errors that are logged so they can be shown to the operators. Set
+/// it as the `custom_layer` of bevy's `LogPlugin`.
+pub fn log_layer(app: &mut App) -> Option<BoxedLayer> {
+    let (sender, receiver) = mpsc::channel();
+    app.insert_resource(ServerLog(Mutex::new(receiver)));
+    Some(Box::new(ServerLogLayer(sender)))
+}
+
+fn start_console(mut commands: Commands) {
+    let (sender, receiver) = mpsc::channel();
+    std::thread::Builder::new()
+        .name("console".to_owned())
+        .spawn(move || {
+            // Ends when stdin is closed, e.g. when the server is run in the background.
+            for line in std::io::stdin().lines() {
+                let Ok(line) = line else {
+                    break;
+                };
+                if sender.send(line).is_err() {
+                    break;
+                }
+            }
+        })
+        .expect("Failed to start the console thread");
+
+    commands.insert_resource(ConsoleInput(Mutex::new(receiver)));
+}
+
+fn read_console(
+    console_input: Res<ConsoleInput>,
+    mut console_commands: MessageWriter<ConsoleCommand>,
+) {
+    for line in console_input.0.lock().unwrap().try_iter() {
+        let text = line.trim();
+        if text.is_empty() {
+            continue;
+        }
+
+        console_commands.write(ConsoleCommand {
+            text: text.to_owned(),
+        });
+    }
+}
+
+fn send_log_to_operators(
+    net: Res<Server>,
+    settings: Res<Settings>,
+    access_control: Res<AccessControl>,
+    server_log: Option<Res<ServerLog>>,
+    players: Query<(Entity, &Player)>,
+) {
+    let Some(server_log) = server_log else {
+        return;
+    };
+
+    // The log is always emptied so it doesn't grow while it's turned off.
+    let lines: Vec<(Level, String)> = server_log.0.lock().unwrap().try_iter().collect();
+    if !settings.log_to_operators || lines.is_empty() {
+        return;
+    }
+
+    for (player_entity, player) in players.iter() {
+        if access_control.operator_level(&player.username) < access::LOG_LEVEL {
+            continue;
+        }
+
+        for (level, text) in &lines {
+            let color = if *level == Level::ERROR {
+                ERROR_COLOR
+            } else {
+                WARNING_COLOR
+            };
+            send_colored_message(&net, player_entity, format!("[{}] {}", level, text), color);
+        }
+    }
+}
@@ -0,0 +1,123 @@
+use std::{
+    future::Future,
+    pin::Pin,
+    sync::{Arc, Mutex, mpsc},
+    task::{Context, Poll, Waker},
+};
+
+use fmc::{database::Database, prelude::*};
+
+/// Moves database reads and writes off the main thread.
+///
+/// All queries are sent to a dedicated IO thread that runs them one at a time in the order they
+/// were sent, so a save that is followed by a load of the same thing always sees the save. Systems
+/// get a [DatabaseTask] back that can be polled each frame, or awaited from async code.
+pub struct DatabaseIoPlugin;
+impl Plugin for DatabaseIoPlugin {
+    fn build(&self, app: &mut App) {
+        // The database is only known after the settings have been loaded.
+        app.add_systems(PreStartup, setup);
+    }
+}
+
+type Job = Box<dyn FnOnce(&Database) + Send>;
+
+/// Runs database queries on the IO thread.
+#[derive(Resource, Clone)]
+pub struct DatabaseIo {
+    sender: mpsc::Sender<Job>,
+}
+
+impl DatabaseIo {
+    fn new(database: Database) -> Self {
+        let (sender, receiver) = mpsc::channel::<Job>();
+
+        std::thread::Builder::new()
+            .name("database io".to_owned())
+            .spawn(move || {
+                // Stops when the last sender is dropped
+                for job in receiver {
+                    job(&database);
+                }
+            })
+            .expect("Failed to start the database io thread");
+
+        Self { sender }
+    }
+
+    /// Run the function on the IO thread, the task resolves to what it returns.
+    pub fn run<T: Send + 'static>(
+        &self,
+        function: impl FnOnce(&Database) -> T + Send + 'static,
+    ) -> DatabaseTask<T> {
+        let task = DatabaseTask {
+            shared: Arc::new(Mutex::new(Shared {
+                result: None,
+                waker: None,
+            })),
+        };
+
+        let shared = task.shared.clone();
+        let job = Box::new(move |database: &Database| {
+            let result = function(database);
+            let mut shared = shared.lock().unwrap();
+            shared.result = Some(result);
+            if let Some(waker) = shared.waker.take() {
+                waker.wake();
+            }
+        });
+
+        self.sender
+            .send(job)
+            .expect("The database io thread has stopped");
+
+        task
+    }
+
+    /// Block until everything that has been sent to the IO thread is done. Only for when the
+    /// server shuts down, otherwise use the tasks.
+    pub fn flush(&self) {
+        self.run(|_| ()).wait();
+    }
+}
+
+struct Shared<T> {
+    result: Option<T>,
+    waker: Option<Waker>,
+}
+
+/// The result of a database query that is running on the IO thread.
+pub struct DatabaseTask<T> {
+    shared: Arc<Mutex<Shared<T>>>,
+}
+
+impl<T> DatabaseTask<T> {
+    /// Take the result if the query is done.
+    pub fn try_take(&mut self) -> Option<T> {
+        self.shared.lock().unwrap().result.take()
+    }
+
+    /// Block until the query is done.
+    pub fn wait(self) -> T {
+        futures_lite::future::block_on(self)
+    }
+}
+
+impl<T> Future for DatabaseTask<T> {
+    type Output = T;
+
+    fn poll(self: Pin<&mut Self>, context: &mut Context<'_>) -> Poll<T> {
+        let mut shared = self.shared.lock().unwrap();
+        match shared.result.take() {
+            Some(result) => Poll::Ready(result),
+            None => {
+                shared.waker = Some(context.waker().clone());
+                Poll::Pending
+            }
+        }
+    }
+}
+
+fn setup(mut commands: Commands, database: Res<Database>) {
+    commands.insert_resource(DatabaseIo::new(database.clone()));
+}
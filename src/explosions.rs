@@ -3,9 +3,18 @@ use fmc::{
     blocks::{BlockPosition, Blocks},
     networking::Server,
     particle_effects::ParticleEffects,
+    physics::Physics,
+    players::Player,
     prelude::*,
     protocol::messages,
-    world::{BlockUpdate, ChunkSubscriptions, chunk::ChunkPosition},
+    world::{BlockUpdate, ChunkSubscriptions, WorldMap, chunk::ChunkPosition},
+};
+
+use crate::{
+    items::DroppedItem,
+    mobs::{Mob, MobDamageEvent},
+    players::PlayerDamageEvent,
+    world::game_rules::GameRules,
 };
 
 pub struct ExplosionsPlugin;
@@ -22,31 +31,154 @@ pub struct ExplosionEvent {
     pub radius: u32,
 }
 
+/// Entities are affected out to this many times the radius of the explosion.
+const REACH_FACTOR: f64 = 2.0;
+/// Damage dealt to an entity at the center of an explosion, per block of radius
+const DAMAGE_PER_RADIUS: f64 = 7.0;
+/// Speed an entity at the center of an explosion is thrown with
+const KNOCK_BACK: f64 = 25.0;
+/// Heights above an entity's feet that are checked for line of sight to the explosion
+const EXPOSURE_SAMPLES: [f64; 3] = [0.2, 0.8, 1.4];
+
+// How much of the entity the explosion can see, from 0 to 1. Entities behind walls are shielded
+// from the blast.
+fn exposure(world_map: &WorldMap, center: DVec3, entity_position: DVec3) -> f64 {
+    let blocks = Blocks::get();
+    let mut visible = 0;
+
+    for height in EXPOSURE_SAMPLES {
+        let target = entity_position + DVec3::new(0.0, height, 0.0);
+        let distance = center.distance(target);
+
+        let mut transform = Transform::from_translation(center);
+        transform.look_to(target - center, DVec3::Y);
+
+        let mut raycast = world_map.raycast(&transform, distance);
+        let mut obstructed = false;
+        while let Some(block_id) = raycast.next_block() {
+            if blocks.get_config(&block_id).is_solid() {
+                obstructed = true;
+                break;
+            }
+        }
+
+        if !obstructed {
+            visible += 1;
+        }
+    }
+
+    visible as f64 / EXPOSURE_SAMPLES.len() as f64
+}
+
+/// How hard an explosion hits an entity
+struct Impact {
+    damage: u32,
+    knock_back: DVec3,
+}
+
+impl Impact {
+    fn new(
+        world_map: &WorldMap,
+        explosion: &ExplosionEvent,
+        entity_position: DVec3,
+    ) -> Option<Self> {
+        let reach = explosion.radius as f64 * REACH_FACTOR;
+        let offset = entity_position - explosion.position;
+        let distance = offset.length();
+        if distance >= reach {
+            return None;
+        }
+
+        let strength =
+            (1.0 - distance / reach) * exposure(world_map, explosion.position, entity_position);
+        if strength <= 0.0 {
+            return None;
+        }
+
+        // Entities are always thrown a little upwards, even when the explosion is above them.
+        let direction = (offset.normalize_or_zero() + DVec3::Y * 0.5).normalize();
+
+        Some(Self {
+            damage: (strength * DAMAGE_PER_RADIUS * explosion.radius as f64).round() as u32,
+            knock_back: direction * KNOCK_BACK * strength,
+        })
+    }
+}
+
 // TODO: See https://minecraft.wiki/w/Explosion for how to actually do explosions
 fn explode(
     net: Res<Server>,
+    game_rules: Res<GameRules>,
+    world_map: Res<WorldMap>,
     particle_effects: Res<ParticleEffects>,
     chunk_subscriptions: Res<ChunkSubscriptions>,
+    players: Query<(Entity, &Transform), With<Player>>,
+    mobs: Query<(Entity, &Transform), With<Mob>>,
+    mut dropped_items: Query<(&Transform, &mut Physics), With<DroppedItem>>,
     mut explosion_events: MessageReader<ExplosionEvent>,
     mut block_update_writer: MessageWriter<BlockUpdate>,
+    mut player_damage_events: MessageWriter<PlayerDamageEvent>,
+    mut mob_damage_events: MessageWriter<MobDamageEvent>,
 ) {
     for explosion in explosion_events.read() {
-        let air = Blocks::get().get_id("air");
-        let radius = 3;
-        for x in -radius..radius {
-            for z in -radius..radius {
-                for y in -radius..radius {
-                    let position = BlockPosition::new(x, y, z);
-                    if position.length_squared() > radius * radius {
-                        continue;
-                    }
+        // The blast is computed before any blocks are removed, so that walls shield what is
+        // behind them even if the explosion destroys them.
+        for (player_entity, transform) in players.iter() {
+            if let Some(impact) = Impact::new(&world_map, explosion, transform.translation) {
+                player_damage_events.write(PlayerDamageEvent {
+                    player_entity,
+                    damage: impact.damage,
+                    knock_back: Some(impact.knock_back),
+                });
+            }
+        }
 
-                    block_update_writer.write(BlockUpdate::Replace {
-                        position: BlockPosition::from(explosion.position) + position,
-                        block_id: air,
-                        block_state: None,
-                        block_data: None,
-                    });
+        for (mob_entity, transform) in mobs.iter() {
+            if let Some(impact) = Impact::new(&world_map, explosion, transform.translation) {
+                mob_damage_events.write(MobDamageEvent {
+                    mob_entity,
+                    damage: impact.damage,
+                    attacker: None,
+                    knock_back: Some(impact.knock_back),
+                });
+            }
+        }
+
+        for (transform, mut physics) in dropped_items.iter_mut() {
+            if let Some(impact) = Impact::new(&world_map, explosion, transform.translation) {
+                physics.velocity += impact.knock_back;
+            }
+        }
+
+        // Without griefing the explosion still hurts, it just leaves the blocks alone.
+        if game_rules.mob_griefing() {
+            let blocks = Blocks::get();
+            let air = blocks.get_id("air");
+            let radius = explosion.radius as i32;
+            for x in -radius..radius {
+                for z in -radius..radius {
+                    for y in -radius..radius {
+                        let position = BlockPosition::new(x, y, z);
+                        if position.length_squared() > radius * radius {
+                            continue;
+                        }
+
+                        let position = BlockPosition::from(explosion.position) + position;
+                        // Unbreakable blocks can't be blown up either
+                        if world_map
+                            .get_block(position)
+                            .is_some_and(|block_id| blocks.get_config(&block_id).hardness.is_none())
+                        {
+                            continue;
+                        }
+
+                        block_update_writer.write(BlockUpdate::Replace {
+                            position,
+                            block_id: air,
+                            block_state: None,
+                            block_data: None,
+                        });
+                    }
                 }
             }
         }
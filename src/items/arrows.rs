@@ -10,10 +10,9 @@ use fmc::{
 };
 use std::collections::{HashMap, HashSet};
 
-use super::{ItemRegistry, ItemUses};
 use crate::{
-    mobs::Mob,
-    players::{HealEvent, Inventory, PlayerDamageEvent},
+    mobs::{Mob, MobDamageEvent},
+    players::PlayerDamageEvent,
 };
 
 pub struct ArrowPlugin;
@@ -23,11 +22,17 @@ impl Plugin for ArrowPlugin {
     }
 }
 
+/// Damage dealt by arrows that aren't given their own
+const DEFAULT_DAMAGE: u32 = 4;
+
 #[derive(Component)]
 pub struct Arrow {
     despawn_timer: Option<Timer>,
     stuck_position: Option<BlockPosition>,
     velocity: DVec3,
+    damage: u32,
+    /// The entity that shot the arrow, it can't be hit by it.
+    owner: Option<Entity>,
 }
 
 impl Arrow {
@@ -36,9 +41,21 @@ impl Arrow {
             despawn_timer: None,
             stuck_position: None,
             velocity,
+            damage: DEFAULT_DAMAGE,
+            owner: None,
         }
     }
 
+    pub fn with_damage(mut self, damage: u32) -> Self {
+        self.damage = damage;
+        self
+    }
+
+    pub fn with_owner(mut self, owner: Entity) -> Self {
+        self.owner = Some(owner);
+        self
+    }
+
     fn start_despawn_timer(&mut self) {
         self.despawn_timer = Some(Timer::from_seconds(120.0, TimerMode::Once));
     }
@@ -79,6 +96,7 @@ fn arrows(
     mut block_updates: MessageReader<ChangedBlockEvent>,
     mut stuck_arrows: Local<StuckArrows>,
     mut player_damage_events: MessageWriter<PlayerDamageEvent>,
+    mut mob_damage_events: MessageWriter<MobDamageEvent>,
 ) {
     for (arrow_entity, mut arrow, mut transform) in arrow_query.iter_mut() {
         if let Some(timer) = &mut arrow.despawn_timer {
@@ -98,27 +116,54 @@ fn arrows(
 
         let max_distance = (arrow.velocity * time.delta_secs_f64()).length();
 
+        // The closest model in the arrow's path
+        let mut closest_hit: Option<(Entity, &Transform, f64, bool, bool)> = None;
         for chunk_position in ChunkPosition::from(transform.translation).neighbourhood() {
             for (model_entity, model_transform, collider, is_player, is_mob) in
                 model_query.iter_many(model_map.iter_entities(&chunk_position))
             {
+                if Some(model_entity) == arrow.owner {
+                    continue;
+                }
+
                 let Some((distance, _)) = collider.ray_intersection(model_transform, &transform)
                 else {
                     continue;
                 };
-                if distance > max_distance {
-                    continue;
+
+                if distance <= max_distance
+                    && closest_hit.is_none_or(|(_, _, closest, _, _)| distance < closest)
+                {
+                    closest_hit =
+                        Some((model_entity, model_transform, distance, is_player, is_mob));
                 }
+            }
+        }
 
-                transform.translation += arrow.velocity.normalize() * (distance - 0.3);
-                transform.translation = transform.translation - model_transform.translation;
-                commands.entity(model_entity).add_child(arrow_entity);
+        if let Some((model_entity, model_transform, distance, is_player, is_mob)) = closest_hit {
+            let knock_back = arrow.velocity.normalize() * 10.0;
+            if is_player {
+                player_damage_events.write(PlayerDamageEvent {
+                    player_entity: model_entity,
+                    damage: arrow.damage,
+                    knock_back: Some(knock_back),
+                });
+            } else if is_mob {
+                mob_damage_events.write(MobDamageEvent {
+                    mob_entity: model_entity,
+                    damage: arrow.damage,
+                    attacker: arrow.owner,
+                    knock_back: Some(DVec3::new(knock_back.x, 5.0, knock_back.z)),
+                });
+            }
 
-                arrow.start_despawn_timer();
-                arrow.velocity = DVec3::ZERO;
+            transform.translation += arrow.velocity.normalize() * (distance - 0.3);
+            transform.translation = transform.translation - model_transform.translation;
+            commands.entity(model_entity).add_child(arrow_entity);
 
-                if is_player {}
-            }
+            arrow.start_despawn_timer();
+            arrow.velocity = DVec3::ZERO;
+            continue;
         }
 
         let blocks = Blocks::get();
@@ -0,0 +1,124 @@
+use fmc::{
+    bevy::math::DVec3,
+    blocks::{BlockId, Blocks},
+    items::{ItemStack, Items},
+    models::{Model, Models},
+    physics::{Collider, Friction},
+    players::{Player, Target, Targets},
+    prelude::*,
+};
+
+use super::{DroppedItem, ItemRegistry, ItemUseSystems, ItemUses};
+use crate::players::{HandHits, HandInteractions, Inventory, ModelCollider, Seat, VehicleConfig};
+
+/// Boats are placed on water by right clicking it. Players ride them by right clicking them, and
+/// break them back into an item by hitting them while nobody is in them.
+pub struct BoatPlugin;
+impl Plugin for BoatPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Startup, register_boats)
+            .add_systems(Update, (place_boats.after(ItemUseSystems), break_boats));
+    }
+}
+
+/// How far the bottom of the boat sinks into the water
+const DRAUGHT: f64 = 0.2;
+
+#[derive(Component)]
+struct BoatUses;
+
+#[derive(Component)]
+struct Boat;
+
+fn register_boats(
+    mut commands: Commands,
+    items: Res<Items>,
+    mut item_registry: ResMut<ItemRegistry>,
+) {
+    let boat = items.get_id("boat").unwrap();
+    let entity = commands.spawn((ItemUses::default(), BoatUses)).id();
+    item_registry.insert(boat, entity);
+}
+
+fn is_water(block_id: &BlockId) -> bool {
+    match &Blocks::get().get_config(block_id).friction {
+        Friction::Drag(drag) => drag.y >= 0.5,
+        _ => false,
+    }
+}
+
+fn place_boats(
+    mut commands: Commands,
+    models: Res<Models>,
+    mut player_query: Query<(&Targets, &mut Inventory), With<Player>>,
+    mut boat_uses: Query<&mut ItemUses, (With<BoatUses>, Changed<ItemUses>)>,
+) {
+    let Ok(mut uses) = boat_uses.single_mut() else {
+        return;
+    };
+
+    for player_entity in uses.read() {
+        let (targets, mut inventory) = player_query.get_mut(player_entity).unwrap();
+
+        let Some(Target::Block { block_position, .. }) = targets.get_first_block(is_water) else {
+            continue;
+        };
+
+        inventory.held_item_stack_mut().take(1);
+
+        let position = block_position.as_dvec3() + DVec3::new(0.5, 1.0 - DRAUGHT, 0.5);
+        let model = models.get_config_by_name("boat").unwrap();
+
+        let collider =
+            Collider::from_min_max(DVec3::new(-0.6, 0.0, -0.6), DVec3::new(0.6, 0.5, 0.6));
+
+        commands.spawn((
+            Boat,
+            Model::Asset(model.id),
+            Transform::from_translation(position),
+            collider.clone(),
+            ModelCollider {
+                collider,
+                friction: Friction::Surface {
+                    front: 0.0,
+                    back: 0.0,
+                    right: 0.0,
+                    left: 0.0,
+                    top: 10.0,
+                    bottom: 0.0,
+                },
+            },
+            HandInteractions::default(),
+            HandHits::default(),
+            Seat::new(
+                DVec3::new(0.0, DRAUGHT, 0.0),
+                VehicleConfig {
+                    seat_height: DRAUGHT as f32,
+                    acceleration: 25.0,
+                    drag: 2.0,
+                    water_only: true,
+                },
+            ),
+        ));
+    }
+}
+
+fn break_boats(
+    mut commands: Commands,
+    items: Res<Items>,
+    boats: Query<(Entity, &Transform, &Seat, &HandHits), (With<Boat>, Changed<HandHits>)>,
+) {
+    for (entity, transform, seat, hits) in boats.iter() {
+        if hits.iter().next().is_none() || seat.rider().is_some() {
+            continue;
+        }
+
+        commands.entity(entity).despawn();
+
+        let boat = items.get_id("boat").unwrap();
+        commands.spawn((
+            DroppedItem::new(ItemStack::new(items.get_config(&boat), 1)),
+            Transform::from_translation(transform.translation + DVec3::Y * 0.5),
+        ));
+    }
+}
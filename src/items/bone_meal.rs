@@ -0,0 +1,174 @@
+use fmc::{
+    bevy::math::DVec3,
+    blocks::{BlockId, BlockPosition, Blocks},
+    items::Items,
+    networking::Server,
+    particle_effects::ParticleEffects,
+    players::{Player, Target, Targets},
+    prelude::*,
+    protocol::messages,
+    random::{Rng, UniformDistribution},
+    world::{BlockUpdate, ChunkSubscriptions, WorldMap, chunk::ChunkPosition},
+};
+
+use crate::{players::Inventory, world::block_variants::BlockVariants};
+
+use super::{ItemRegistry, ItemUses};
+
+/// Bone meal makes crops grow instantly, and tall grass sprout around grass blocks.
+pub struct BoneMealPlugin;
+impl Plugin for BoneMealPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Startup, register_bone_meal)
+            .add_systems(Update, use_bone_meal.after(super::ItemUseSystems));
+    }
+}
+
+/// How many stages a crop grows each time bone meal is used on it.
+const CROP_GROWTH: (u8, u8) = (2, 5);
+/// Tall grass sprouts this many blocks from the grass block at most.
+const GRASS_SPREAD: i32 = 3;
+/// How many places around the grass block tall grass tries to sprout at.
+const GRASS_ATTEMPTS: usize = 24;
+
+#[derive(Component)]
+struct BoneMealConfig {
+    air: BlockId,
+    grass: BlockId,
+    tall_grass: BlockId,
+}
+
+fn register_bone_meal(
+    mut commands: Commands,
+    blocks: Res<Blocks>,
+    items: Res<Items>,
+    mut usable_items: ResMut<ItemRegistry>,
+) {
+    usable_items.insert(
+        items.get_id("bone_meal").unwrap(),
+        commands
+            .spawn((
+                ItemUses::default(),
+                BoneMealConfig {
+                    air: blocks.get_id("air"),
+                    grass: blocks.get_id("grass"),
+                    tall_grass: blocks.get_id("tall_grass"),
+                },
+            ))
+            .id(),
+    );
+}
+
+fn use_bone_meal(
+    net: Res<Server>,
+    world_map: Res<WorldMap>,
+    block_variants: Res<BlockVariants>,
+    particle_effects: Res<ParticleEffects>,
+    chunk_subscriptions: Res<ChunkSubscriptions>,
+    mut player_query: Query<(&Targets, &mut Inventory), With<Player>>,
+    mut bone_meal_uses: Query<(&mut ItemUses, &BoneMealConfig), Changed<ItemUses>>,
+    mut block_update_writer: MessageWriter<BlockUpdate>,
+    mut rng: Local<Rng>,
+) {
+    let Ok((mut uses, config)) = bone_meal_uses.single_mut() else {
+        return;
+    };
+
+    for player_entity in uses.read() {
+        let (targets, mut inventory) = player_query.get_mut(player_entity).unwrap();
+
+        let Some(Target::Block {
+            block_position,
+            block_id,
+            ..
+        }) = targets.get_first_block(|block_id| {
+            *block_id == config.grass || block_variants.state("wheat", *block_id).is_some()
+        })
+        else {
+            continue;
+        };
+
+        let block_position = *block_position;
+
+        let fertilized = if *block_id == config.grass {
+            let mut sprouted = false;
+            let horizontal = UniformDistribution::new(-GRASS_SPREAD, GRASS_SPREAD);
+            let vertical = UniformDistribution::new(-1, 1);
+            for _ in 0..GRASS_ATTEMPTS {
+                let position = block_position
+                    + IVec3::new(
+                        horizontal.sample(&mut rng),
+                        vertical.sample(&mut rng),
+                        horizontal.sample(&mut rng),
+                    );
+                if world_map.get_block(position) != Some(config.grass)
+                    || world_map.get_block(position + IVec3::Y) != Some(config.air)
+                {
+                    continue;
+                }
+
+                block_update_writer.write(BlockUpdate::Replace {
+                    position: position + IVec3::Y,
+                    block_id: config.tall_grass,
+                    block_state: None,
+                    block_data: None,
+                });
+                sprouted = true;
+            }
+            sprouted
+        } else {
+            let wheat = block_variants.state("wheat", *block_id).unwrap();
+            let age = wheat.get("age");
+            let max_age = wheat.value_count("age") - 1;
+            if age == max_age {
+                // Fully grown, the bone meal isn't used up
+                false
+            } else {
+                let growth =
+                    UniformDistribution::new(CROP_GROWTH.0, CROP_GROWTH.1).sample(&mut rng);
+                block_update_writer.write(BlockUpdate::Swap {
+                    position: block_position,
+                    block_id: wheat.with("age", (age + growth).min(max_age)).block_id(),
+                    block_state: None,
+                });
+                true
+            }
+        };
+
+        if !fertilized {
+            continue;
+        }
+
+        inventory.held_item_stack_mut().take(1);
+
+        send_particles(
+            &net,
+            &chunk_subscriptions,
+            &particle_effects,
+            block_position,
+        );
+    }
+}
+
+fn send_particles(
+    net: &Server,
+    chunk_subscriptions: &ChunkSubscriptions,
+    particle_effects: &ParticleEffects,
+    block_position: BlockPosition,
+) {
+    let chunk_position = ChunkPosition::from(block_position);
+    let Some(subscribers) = chunk_subscriptions.get_subscribers(&chunk_position) else {
+        return;
+    };
+
+    net.send_many(
+        subscribers,
+        messages::ParticleEffect {
+            id: particle_effects.get_id("bone_meal").unwrap(),
+            position: block_position.as_dvec3() + DVec3::new(0.5, 1.0, 0.5),
+            rotation: Quat::IDENTITY,
+            texture: "particles/bone_meal.png".to_owned(),
+            color: Vec4::new(0.45, 0.9, 0.35, 1.0),
+        },
+    );
+}
@@ -0,0 +1,164 @@
+use fmc::{
+    bevy::math::{DQuat, DVec3},
+    items::{ItemId, Items},
+    models::{Model, Models},
+    networking::NetworkMessage,
+    players::{Camera, Player},
+    prelude::*,
+    protocol::messages,
+};
+
+use super::{ItemRegistry, ItemUseSystems, ItemUses, arrows::Arrow};
+use crate::players::Inventory;
+
+/// Bows are drawn by holding right click, and shoot an arrow from the inventory when it is
+/// released. The longer the bow is drawn, the faster and harder the arrow hits.
+pub struct BowPlugin;
+impl Plugin for BowPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Startup, register_bows).add_systems(
+            Update,
+            (draw_bows.after(ItemUseSystems), release_bows).chain(),
+        );
+    }
+}
+
+/// Seconds it takes to draw a bow fully
+const FULL_DRAW_TIME: f32 = 1.0;
+/// Bows released before they are drawn this much don't shoot
+const MIN_DRAW: f32 = 0.1;
+const ARROW_SPEED: f64 = 50.0;
+/// Damage dealt by a fully drawn bow if the item doesn't define its own "arrow_damage" property
+const DEFAULT_DAMAGE: u32 = 9;
+
+#[derive(Component)]
+struct BowUses {
+    bow: ItemId,
+    arrow: ItemId,
+}
+
+/// Added to players while they draw a bow
+#[derive(Component)]
+struct DrawnBow {
+    // Elapsed time when the bow started being drawn
+    started: f32,
+}
+
+fn register_bows(
+    mut commands: Commands,
+    items: Res<Items>,
+    mut usable_items: ResMut<ItemRegistry>,
+) {
+    let bow = items.get_id("bow").unwrap();
+    let arrow = items.get_id("arrow").unwrap();
+    let entity = commands
+        .spawn((ItemUses::default(), BowUses { bow, arrow }))
+        .id();
+    usable_items.insert(bow, entity);
+}
+
+fn draw_bows(
+    mut commands: Commands,
+    time: Res<Time>,
+    player_query: Query<&Inventory, (With<Player>, Without<DrawnBow>)>,
+    mut bow_uses: Query<(&mut ItemUses, &BowUses), Changed<ItemUses>>,
+) {
+    let Ok((mut uses, bow_config)) = bow_uses.single_mut() else {
+        return;
+    };
+
+    for player_entity in uses.read() {
+        let Ok(inventory) = player_query.get(player_entity) else {
+            continue;
+        };
+
+        // Without arrows the bow can't be drawn.
+        if !inventory.iter().any(|item_stack| {
+            item_stack
+                .item()
+                .is_some_and(|item| item.id == bow_config.arrow)
+        }) {
+            continue;
+        }
+
+        commands.entity(player_entity).insert(DrawnBow {
+            started: time.elapsed_secs(),
+        });
+    }
+}
+
+fn release_bows(
+    mut commands: Commands,
+    time: Res<Time>,
+    items: Res<Items>,
+    models: Res<Models>,
+    bow_uses: Query<&BowUses>,
+    mut player_query: Query<(&mut Inventory, &GlobalTransform, &Camera, &DrawnBow), With<Player>>,
+    mut clicks: MessageReader<NetworkMessage<messages::RightClick>>,
+) {
+    let Ok(bow_config) = bow_uses.single() else {
+        return;
+    };
+
+    for click in clicks.read() {
+        if click.message != messages::RightClick::Release {
+            continue;
+        }
+
+        let Ok((mut inventory, transform, camera, drawn_bow)) =
+            player_query.get_mut(click.player_entity)
+        else {
+            continue;
+        };
+
+        let draw_time = time.elapsed_secs() - drawn_bow.started;
+        commands.entity(click.player_entity).remove::<DrawnBow>();
+
+        // The player switched away from the bow while drawing it.
+        if inventory
+            .held_item_stack()
+            .item()
+            .is_none_or(|item| item.id != bow_config.bow)
+        {
+            continue;
+        }
+
+        let draw = (draw_time / FULL_DRAW_TIME).min(1.0);
+        if draw < MIN_DRAW {
+            continue;
+        }
+
+        let full_damage = items
+            .get_config(&bow_config.bow)
+            .properties
+            .get("arrow_damage")
+            .and_then(|damage| damage.as_u64())
+            .map(|damage| damage as u32)
+            .unwrap_or(DEFAULT_DAMAGE);
+        let damage = ((full_damage as f32 * draw).round() as u32).max(1);
+
+        let Some(arrow_stack) = inventory.iter_mut().find(|item_stack| {
+            item_stack
+                .item()
+                .is_some_and(|item| item.id == bow_config.arrow)
+        }) else {
+            continue;
+        };
+        arrow_stack.take(1);
+
+        let model_config = models.get_config_by_name("arrow").unwrap();
+        let velocity = camera.forward() * ARROW_SPEED * draw as f64;
+
+        commands.spawn((
+            Model::Asset(model_config.id),
+            Arrow::new(velocity)
+                .with_owner(click.player_entity)
+                .with_damage(damage),
+            Transform {
+                translation: transform.translation() + camera.translation,
+                rotation: DQuat::from_rotation_arc(DVec3::NEG_Z, velocity.normalize()),
+                scale: DVec3::splat(0.0625),
+            },
+        ));
+    }
+}
@@ -12,6 +12,9 @@ impl Plugin for BreadPlugin {
     }
 }
 
+/// Health restored by bread if its config doesn't have a "healing" property
+const DEFAULT_HEALING: u32 = 8;
+
 #[derive(Component)]
 struct Bread;
 
@@ -27,6 +30,7 @@ fn register_bread(
 }
 
 fn eat_bread(
+    items: Res<Items>,
     mut bread_uses: Query<&mut ItemUses, (With<Bread>, Changed<ItemUses>)>,
     mut player_query: Query<&mut Inventory, With<Player>>,
     mut heal_events: MessageWriter<HealEvent>,
@@ -38,10 +42,20 @@ fn eat_bread(
     for player_entity in uses.read() {
         let mut inventory = player_query.get_mut(player_entity).unwrap();
         let held_item = inventory.held_item_stack_mut();
-
+        let Some(item) = held_item.item() else {
+            continue;
+        };
+
+        let healing = items
+            .get_config(&item.id)
+            .properties
+            .get("healing")
+            .and_then(|healing| healing.as_u64())
+            .map(|healing| healing as u32)
+            .unwrap_or(DEFAULT_HEALING);
         heal_events.write(HealEvent {
             player_entity,
-            healing: 8,
+            healing,
         });
 
         held_item.take(1);
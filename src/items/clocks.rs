@@ -0,0 +1,84 @@
+use std::collections::HashMap;
+
+use fmc::{
+    items::{ItemId, Items},
+    networking::Server,
+    players::Player,
+    prelude::*,
+    protocol::messages,
+};
+
+use crate::{
+    chat::{CHAT_FONT_SIZE, CHAT_TEXT_COLOR},
+    players::Inventory,
+    skybox::Clock,
+};
+
+use super::held_interfaces::HeldInterfaces;
+
+/// Clocks show the time of day while they are held.
+pub struct ClockPlugin;
+impl Plugin for ClockPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Startup, register_clocks)
+            .add_systems(Update, show_time);
+    }
+}
+
+#[derive(Resource)]
+struct ClockItem(ItemId);
+
+fn register_clocks(
+    mut commands: Commands,
+    items: Res<Items>,
+    mut held_interfaces: ResMut<HeldInterfaces>,
+) {
+    let clock = items.get_id("clock").unwrap();
+    held_interfaces.insert(clock, "clock");
+    commands.insert_resource(ClockItem(clock));
+}
+
+// The clock starts at dawn, which is shown as six in the morning.
+fn time_of_day(clock: &Clock) -> String {
+    let minutes = (clock.get_time() / clock.day_length() * 24.0 * 60.0) as u32 + 6 * 60;
+    format!("{:02}:{:02}", minutes / 60 % 24, minutes % 60)
+}
+
+fn show_time(
+    net: Res<Server>,
+    clock: Res<Clock>,
+    clock_item: Res<ClockItem>,
+    player_query: Query<(Entity, &Inventory), With<Player>>,
+    mut shown: Local<HashMap<Entity, String>>,
+) {
+    let time = time_of_day(&clock);
+
+    for (player_entity, inventory) in player_query.iter() {
+        if !inventory
+            .held_item_stack()
+            .item()
+            .is_some_and(|item| item.id == clock_item.0)
+        {
+            shown.remove(&player_entity);
+            continue;
+        }
+
+        if shown.get(&player_entity) == Some(&time) {
+            continue;
+        }
+        shown.insert(player_entity, time.clone());
+
+        net.send_one(
+            player_entity,
+            messages::InterfaceTextUpdate {
+                interface_path: "clock/time".to_owned(),
+                index: 0,
+                text: time.clone(),
+                font_size: CHAT_FONT_SIZE,
+                color: CHAT_TEXT_COLOR.to_owned(),
+            },
+        );
+    }
+
+    shown.retain(|player_entity, _| player_query.contains(*player_entity));
+}
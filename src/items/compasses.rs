@@ -0,0 +1,87 @@
+use std::collections::HashMap;
+
+use fmc::{
+    bevy::math::DVec3,
+    items::{ItemId, Items},
+    networking::Server,
+    players::{Camera, Player},
+    prelude::*,
+    protocol::messages,
+};
+
+use crate::{players::Inventory, world::WorldProperties};
+
+use super::held_interfaces::HeldInterfaces;
+
+/// Compasses show a needle that points to the world spawn while they are held.
+///
+/// The needle is one of a set of images, the one that points the right way relative to where the
+/// player is looking is shown. It is updated as the player moves and turns.
+pub struct CompassPlugin;
+impl Plugin for CompassPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Startup, register_compasses)
+            .add_systems(Update, point_needles);
+    }
+}
+
+/// How many directions the needle can point in, clockwise from straight ahead
+const DIRECTIONS: usize = 8;
+
+#[derive(Resource)]
+struct Compass(ItemId);
+
+fn register_compasses(
+    mut commands: Commands,
+    items: Res<Items>,
+    mut held_interfaces: ResMut<HeldInterfaces>,
+) {
+    let compass = items.get_id("compass").unwrap();
+    held_interfaces.insert(compass, "compass");
+    commands.insert_resource(Compass(compass));
+}
+
+fn point_needles(
+    net: Res<Server>,
+    compass: Res<Compass>,
+    world_properties: Res<WorldProperties>,
+    player_query: Query<(Entity, &Inventory, &Camera, &Transform), With<Player>>,
+    mut needles: Local<HashMap<Entity, usize>>,
+) {
+    let spawn = world_properties.spawn_point.center.as_dvec3();
+
+    for (player_entity, inventory, camera, transform) in player_query.iter() {
+        if !inventory
+            .held_item_stack()
+            .item()
+            .is_some_and(|item| item.id == compass.0)
+        {
+            // The needle is sent again the next time the compass is held
+            needles.remove(&player_entity);
+            continue;
+        }
+
+        let forward = camera.forward().with_y(0.0).normalize_or_zero();
+        let right = DVec3::new(-forward.z, 0.0, forward.x);
+        let to_spawn = (spawn - transform.translation).with_y(0.0);
+        let angle = to_spawn.dot(right).atan2(to_spawn.dot(forward));
+
+        let step = std::f64::consts::TAU / DIRECTIONS as f64;
+        let direction = ((angle / step).round() as i32).rem_euclid(DIRECTIONS as i32) as usize;
+        if needles.insert(player_entity, direction) == Some(direction) {
+            continue;
+        }
+
+        let mut node_update = messages::InterfaceNodeVisibilityUpdate::default();
+        for needle in 0..DIRECTIONS {
+            if needle == direction {
+                node_update.set_visible(format!("compass/{}", needle));
+            } else {
+                node_update.set_hidden(format!("compass/{}", needle));
+            }
+        }
+        net.send_one(player_entity, node_update);
+    }
+
+    needles.retain(|player_entity, _| player_query.contains(*player_entity));
+}
@@ -4,6 +4,8 @@ use fmc::{
 };
 use serde::{Deserialize, Serialize};
 
+use crate::assets::{AssetError, AssetErrors};
+
 use std::collections::HashMap;
 
 mod shaped;
@@ -11,110 +13,30 @@ mod shaped;
 pub struct CraftingPlugin;
 impl Plugin for CraftingPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(Startup, load_recipes);
+        app.add_message::<CraftEvent>()
+            .add_systems(Startup, load_recipes);
     }
 }
 
-fn load_recipes(mut commands: Commands, items: Res<Items>) {
-    let mut recipes = HashMap::new();
-
-    let directory = std::fs::read_dir("assets/client/items/recipes").expect(
-        "Couldn't read recipe directory make sure it is present at: \
-                assets/client/items/recipes",
-    );
-
-    for dir_entry in directory {
-        let file_path = match dir_entry {
-            Ok(d) => d.path(),
-            Err(e) => panic!("Failed to read the filename of a recipe\nError: {}", e),
-        };
-
-        let file = match std::fs::File::open(&file_path) {
-            Ok(f) => f,
-            Err(e) => panic!(
-                "Failed to open recipe at path: {}\nError: {}",
-                &file_path.display(),
-                e
-            ),
-        };
-
-        let item_recipes: Vec<RecipeJson> = match serde_json::from_reader(file) {
-            Ok(i) => i,
-            Err(e) => panic!(
-                "Failed to read item recipe in file: {}\nError:{}",
-                file_path.display(),
-                e
-            ),
-        };
+/// Sent when a player takes the output of a recipe, from crafting or smelting.
+#[derive(Message)]
+pub struct CraftEvent {
+    pub player_entity: Entity,
+    /// The name of the recipe collection, "crafting" or "smelting"
+    pub collection: &'static str,
+    pub item_id: ItemId,
+    pub amount: u32,
+}
 
-        for recipe_json in item_recipes.into_iter() {
-            match recipe_json.pattern_type.as_str() {
-                "shaped" => {
-                    let (pattern, required_amount): (Vec<Vec<Option<ItemId>>>, Vec<Vec<u32>>) =
-                        match &recipe_json.pattern {
-                            PatternJson::Grid(pattern) => pattern
-                                .iter()
-                                .map(|row| {
-                                    row.iter()
-                                        .map(|(name, amount)| match name.as_str() {
-                                            // Empty part of pattern
-                                            "" => (None, 0),
-                                            // Item part of pattern
-                                            _ => match items.get_id(name) {
-                                                Some(id) => (Some(id), *amount),
-                                                None => panic!(
-                                                    "Error parsing item recipe pattern at: {}\n\
-                                                        Item name '{}' is not recognized",
-                                                    file_path.display(),
-                                                    name
-                                                ),
-                                            },
-                                        })
-                                        .unzip()
-                                })
-                                .unzip(),
-                            _ => panic!(
-                                r#"Error parsing item recipe pattern at: {}
-'pattern_type' is 'shaped', but the pattern is not in the form of a grid. Should be like:
-[
-       [["", 0], ["item", 1]],
-       [["item", 1], ["", 0]]
-]"#,
-                                file_path.display()
-                            ),
-                        };
+const RECIPE_PATH: &str = "assets/client/items/recipes";
+/// Where the item configs are, each item is named after its file.
+pub const ITEM_CONFIG_PATH: &str = "assets/client/items/configurations";
 
-                    let output_config = match items.get_config_by_name(&recipe_json.output_item) {
-                        Some(id) => id,
-                        None => panic!(
-                            "Error parsing item recipe pattern at: {}\n Item name '{}'\
-                            is not recognized",
-                            file_path.display(),
-                            &recipe_json.output_item
-                        ),
-                    };
-
-                    let recipe = shaped::Recipe {
-                        required_amount,
-                        output: ItemStack::new(output_config, recipe_json.output_amount),
-                    };
-
-                    recipes
-                        .entry(recipe_json.collection_name)
-                        .or_insert(RecipeCollection::default())
-                        .insert(
-                            Pattern::Shaped(shaped::Pattern { inner: pattern }),
-                            Recipe::Shaped(recipe),
-                        );
-                }
-                _ => (),
-            }
-        }
+fn load_recipes(mut commands: Commands, items: Res<Items>, mut asset_errors: ResMut<AssetErrors>) {
+    match Recipes::load(&items) {
+        Ok(recipes) => commands.insert_resource(recipes),
+        Err(e) => asset_errors.extend(e),
     }
-
-    commands.insert_resource(Recipes {
-        collections: recipes,
-    })
 }
 
 /// A square crafting grid.
@@ -257,6 +179,51 @@ impl RecipeCollection {
         }
     }
 
+    /// Craft as many as will fit in the item stacks, e.g. the player's inventory. The crafted
+    /// items are returned, it is up to the caller to put them there.
+    pub fn craft_to_fit(
+        &self,
+        input: &mut CraftingGrid,
+        item_stacks: &[ItemStack],
+    ) -> Option<ItemStack> {
+        if self.shaped {
+            let pattern = Pattern::Shaped(shaped::Pattern::from(input.as_slice()));
+            let Some(recipe) = self.recipes.get(&pattern) else {
+                return None;
+            };
+
+            let output = recipe.output();
+            let space: u32 = item_stacks
+                .iter()
+                .map(|item_stack| {
+                    if item_stack.is_empty() {
+                        output.capacity()
+                    } else if item_stack.item() == output.item() {
+                        item_stack.remaining_capacity()
+                    } else {
+                        0
+                    }
+                })
+                .sum();
+
+            return recipe.craft(input, space);
+        } else {
+            // Only shaped recipes are implemented, a collection without any has nothing to craft.
+            None
+        }
+    }
+
+    /// If any of the recipes take the item as input
+    pub fn uses_item(&self, item_id: ItemId) -> bool {
+        self.recipes.keys().any(|pattern| match pattern {
+            Pattern::Shaped(pattern) => pattern
+                .inner
+                .iter()
+                .flatten()
+                .any(|id| *id == Some(item_id)),
+        })
+    }
+
     pub fn get_recipe(&self, input: &CraftingGrid) -> Option<&Recipe> {
         if self.shaped {
             let pattern = Pattern::Shaped(shaped::Pattern::from(input.as_slice()));
@@ -276,6 +243,166 @@ pub struct Recipes {
 }
 
 impl Recipes {
+    /// Read all the recipes from the recipe directory. Every recipe is checked, and all the
+    /// problems found are returned together.
+    pub fn load(items: &Items) -> Result<Self, AssetErrors> {
+        Self::load_from(RECIPE_PATH, items)
+    }
+
+    fn load_from(recipe_path: &str, items: &Items) -> Result<Self, AssetErrors> {
+        let mut recipes = HashMap::new();
+        let mut errors = AssetErrors::default();
+
+        let directory = match std::fs::read_dir(recipe_path) {
+            Ok(d) => d,
+            Err(e) => {
+                return Err(AssetError::new(
+                    recipe_path,
+                    format!("Couldn't read recipe directory\nError: {}", e),
+                )
+                .suggestion(format!("Make sure it is present at: {}", recipe_path))
+                .into());
+            }
+        };
+
+        for dir_entry in directory {
+            let file_path = match dir_entry {
+                Ok(d) => d.path(),
+                Err(e) => {
+                    errors.push(AssetError::new(
+                        recipe_path,
+                        format!("Failed to read the filename of a recipe\nError: {}", e),
+                    ));
+                    continue;
+                }
+            };
+
+            let file = match std::fs::File::open(&file_path) {
+                Ok(f) => f,
+                Err(e) => {
+                    errors.push(AssetError::new(
+                        &file_path,
+                        format!("Failed to open recipe\nError: {}", e),
+                    ));
+                    continue;
+                }
+            };
+
+            let item_recipes: Vec<RecipeJson> = match serde_json::from_reader(file) {
+                Ok(i) => i,
+                Err(e) => {
+                    errors.push(AssetError::json(&file_path, &e));
+                    continue;
+                }
+            };
+
+            for recipe_json in item_recipes.into_iter() {
+                match recipe_json.pattern_type.as_str() {
+                    "shaped" => {
+                        let PatternJson::Grid(grid) = &recipe_json.pattern else {
+                            errors.push(
+                                AssetError::new(
+                                    &file_path,
+                                    "'pattern_type' is 'shaped', but the pattern is not in the form of a grid.",
+                                )
+                                .field("pattern")
+                                .suggestion(
+                                    r#"Should be like:
+    [
+           [["", 0], ["item", 1]],
+           [["item", 1], ["", 0]]
+    ]"#,
+                                ),
+                            );
+                            continue;
+                        };
+
+                        let mut pattern: Vec<Vec<Option<ItemId>>> = Vec::new();
+                        let mut required_amount: Vec<Vec<u32>> = Vec::new();
+                        let mut valid = true;
+                        for row in grid {
+                            let mut pattern_row = Vec::new();
+                            let mut amount_row = Vec::new();
+                            for (name, amount) in row {
+                                match name.as_str() {
+                                    // Empty part of pattern
+                                    "" => {
+                                        pattern_row.push(None);
+                                        amount_row.push(0);
+                                    }
+                                    // Item part of pattern
+                                    _ => match items.get_id(name) {
+                                        Some(id) => {
+                                            pattern_row.push(Some(id));
+                                            amount_row.push(*amount);
+                                        }
+                                        None => {
+                                            errors.push(
+                                                AssetError::new(
+                                                    &file_path,
+                                                    format!(
+                                                        "Item name '{}' is not recognized",
+                                                        name
+                                                    ),
+                                                )
+                                                .field("pattern")
+                                                .misspelled(name, ITEM_CONFIG_PATH),
+                                            );
+                                            valid = false;
+                                        }
+                                    },
+                                }
+                            }
+                            pattern.push(pattern_row);
+                            required_amount.push(amount_row);
+                        }
+
+                        let output_config = match items.get_config_by_name(&recipe_json.output_item)
+                        {
+                            Some(id) => id,
+                            None => {
+                                errors.push(
+                                    AssetError::new(
+                                        &file_path,
+                                        format!(
+                                            "Item name '{}' is not recognized",
+                                            &recipe_json.output_item
+                                        ),
+                                    )
+                                    .field("output_item")
+                                    .misspelled(&recipe_json.output_item, ITEM_CONFIG_PATH),
+                                );
+                                continue;
+                            }
+                        };
+
+                        if !valid {
+                            continue;
+                        }
+
+                        let recipe = shaped::Recipe {
+                            required_amount,
+                            output: ItemStack::new(output_config, recipe_json.output_amount),
+                        };
+
+                        recipes
+                            .entry(recipe_json.collection_name)
+                            .or_insert(RecipeCollection::default())
+                            .insert(
+                                Pattern::Shaped(shaped::Pattern { inner: pattern }),
+                                Recipe::Shaped(recipe),
+                            );
+                    }
+                    _ => (),
+                }
+            }
+        }
+
+        errors.into_result(Recipes {
+            collections: recipes,
+        })
+    }
+
     pub fn get(&self, collection_name: &str) -> &RecipeCollection {
         return match self.collections.get(collection_name) {
             Some(c) => c,
@@ -286,3 +413,48 @@ impl Recipes {
         };
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use super::*;
+    use crate::testing::{fixture, run_with_assets};
+
+    #[test]
+    fn broken_recipes() {
+        run_with_assets(|items: Res<Items>| {
+            let directory = fixture("recipes");
+            let Err(errors) = Recipes::load_from(&directory, &items) else {
+                panic!("The broken recipes were loaded without any errors");
+            };
+
+            let report = errors.to_string();
+            let file = |name: &str| Path::new(&directory).join(name).display().to_string();
+            let expected = [
+                "Found 4 problems in the assets:".to_owned(),
+                format!("{}, line 4", file("syntax_error.json")),
+                format!(
+                    "{}, field 'pattern'\n    Item name 'stik' is not recognized\n    Did you mean 'stick'?",
+                    file("unknown_items.json")
+                ),
+                format!(
+                    "{}, field 'output_item'\n    Item name 'woden_sword' is not recognized\n    Did you mean 'wooden_sword'?",
+                    file("unknown_items.json")
+                ),
+                format!(
+                    "{}, field 'pattern'\n    'pattern_type' is 'shaped', but the pattern is not in the form of a grid.\n    Should be like:",
+                    file("pattern_not_grid.json")
+                ),
+            ];
+            for expected in expected {
+                assert!(
+                    report.contains(&expected),
+                    "Expected the report to contain:\n{}\n\nReport:\n{}",
+                    expected,
+                    report
+                );
+            }
+        });
+    }
+}
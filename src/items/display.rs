@@ -0,0 +1,56 @@
+use fmc::{
+    items::{ItemStack, Items},
+    models::{AnimationPlayer, Model, Models},
+    physics::shapes::Aabb,
+};
+
+/// The model of an item stack, scaled to fit inside a box. Use this whenever an item should be
+/// shown in the world, like dropped items and items put in item frames.
+pub struct ItemDisplay {
+    pub model: Model,
+    /// Plays the item model's "dropped" animation, if it has one.
+    pub dropped_animation: AnimationPlayer,
+    /// The scale that should be applied to the transform of the entity showing the model.
+    pub scale: f64,
+    /// Collider of the model, in model space. It becomes the right size once the scale is applied.
+    pub aabb: Aabb,
+}
+
+impl ItemDisplay {
+    /// The model is scaled so that it has the same volume as a box with `half_extent` half
+    /// extents, but never gets taller than `max_half_height`.
+    ///
+    /// Panics if the item stack is empty.
+    pub fn new(
+        items: &Items,
+        models: &Models,
+        item_stack: &ItemStack,
+        half_extent: f64,
+        max_half_height: f64,
+    ) -> Self {
+        let item_id = item_stack.item().unwrap().id;
+        let item_config = items.get_config(&item_id);
+        let model_config = models.get_config(&item_config.model_id);
+
+        let aabb = model_config.collider.as_aabb();
+
+        // There are two scales, one scales the model to have a volume and the other scales
+        // it to be some height. We choose whatever makes it smaller.
+        let half_volume = aabb.half_extents.x * aabb.half_extents.y * aabb.half_extents.z;
+        let volume_scale = (half_extent.powi(3) / half_volume).cbrt();
+        let y_scale = max_half_height / aabb.half_extents.y;
+        let scale = volume_scale.min(y_scale);
+
+        let mut dropped_animation = AnimationPlayer::default();
+        let animation_index = model_config.animations.get("dropped").cloned();
+        dropped_animation.set_idle_animation(animation_index);
+        dropped_animation.set_move_animation(animation_index);
+
+        Self {
+            model: Model::Asset(item_config.model_id),
+            dropped_animation,
+            scale,
+            aabb,
+        }
+    }
+}
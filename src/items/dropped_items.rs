@@ -1,7 +1,9 @@
+use std::{collections::HashSet, time::Duration};
+
 use fmc::{
     bevy::math::DVec3,
     items::{ItemStack, Items},
-    models::{AnimationPlayer, Model, ModelMap, Models},
+    models::{ModelMap, Models},
     networking::Server,
     physics::{Collider, Physics},
     players::Camera,
@@ -11,12 +13,18 @@ use fmc::{
     world::{ChunkSubscriptions, chunk::ChunkPosition},
 };
 
-use crate::players::{Health, Inventory};
+use crate::{
+    items::display::ItemDisplay,
+    players::{GameMode, Health, Inventory},
+    world::budgets::{ChunkBudgetSystems, ChunkBudgets, MAX_DROPPED_ITEMS},
+};
 
 pub struct DroppedItemsPlugin;
 impl Plugin for DroppedItemsPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(Update, item_pickup)
+        app.add_message::<PickupEvent>()
+            .add_systems(Update, (item_pickup, merge_items, despawn_items))
+            .add_systems(Update, enforce_chunk_budget.after(ChunkBudgetSystems))
             .add_systems(Update, spawn_model.in_set(DropItems));
     }
 }
@@ -25,13 +33,38 @@ impl Plugin for DroppedItemsPlugin {
 #[derive(SystemSet, Debug, Hash, PartialEq, Eq, Clone)]
 pub struct DropItems;
 
+/// Dropped items are removed after lying on the ground for this long.
+const DESPAWN_TIME: Duration = Duration::from_secs(5 * 60);
+/// Items of the same kind that are closer than this are merged into one stack.
+const MERGE_DISTANCE: f64 = 0.5;
+/// How often dropped items are checked for merging, in seconds.
+const MERGE_INTERVAL: f32 = 0.5;
+
+/// Items start moving towards players within this distance.
+const ATTRACTION_RANGE: f64 = 3.0;
+/// The speed items move towards the player at.
+const ATTRACTION_SPEED: f64 = 12.0;
+/// How quickly items get up to speed, they pick up speed faster the closer they are.
+const ATTRACTION_ACCELERATION: f64 = 20.0;
+/// Items are picked up when they are this close to the player.
+const PICKUP_DISTANCE: f64 = 0.3;
+
+/// Sent for each inventory slot that received items when a player picks up a dropped item.
+#[derive(Message)]
+pub struct PickupEvent {
+    pub player_entity: Entity,
+    /// Index of the slot in the player's [Inventory]
+    pub slot: usize,
+    pub amount: u32,
+}
+
 /// An item stack that is dropped on the ground.
 #[derive(Component)]
 #[require(Transform)]
 pub struct DroppedItem {
     stack: ItemStack,
     drop_time: std::time::Instant,
-    pickup_delay: std::time::Duration,
+    pickup_delay: Duration,
 }
 
 impl DroppedItem {
@@ -39,12 +72,12 @@ impl DroppedItem {
         Self {
             stack: item_stack,
             drop_time: std::time::Instant::now(),
-            pickup_delay: std::time::Duration::from_secs_f32(0.5),
+            pickup_delay: Duration::from_secs_f32(0.5),
         }
     }
 
     pub fn with_delay(mut self, delay: f32) -> Self {
-        self.pickup_delay = std::time::Duration::from_secs_f32(delay);
+        self.pickup_delay = Duration::from_secs_f32(delay);
         self
     }
 }
@@ -60,35 +93,18 @@ fn spawn_model(
     mut rng: Local<Rng>,
 ) {
     for (entity, dropped_item, maybe_physics, mut transform) in dropped_items.iter_mut() {
-        let item_id = dropped_item.stack.item().unwrap().id;
-        let item_config = items.get_config(&item_id);
-        let model_config = models.get_config(&item_config.model_id);
-
-        let mut aabb = model_config.collider.as_aabb();
-
-        // There are two scales, one scales the model to have a volume and the other scales
-        // it to be some height. We choose whatever makes it smaller.
-        const HALF_VOLUME: f64 = 0.15 * 0.15 * 0.15;
-        let half_volume = aabb.half_extents.x * aabb.half_extents.y * aabb.half_extents.z;
-        let volume_scale = (HALF_VOLUME / half_volume).cbrt();
-        const HALF_HEIGHT: f64 = 0.25;
-        let y_scale = HALF_HEIGHT / aabb.half_extents.y;
-        let scale = volume_scale.min(y_scale);
-
-        transform.scale = DVec3::splat(scale);
-        // Moving it down to create some constant spacing between the item and the ground.
-        aabb.center.y -= 0.01 / scale;
+        let display = ItemDisplay::new(&items, &models, &dropped_item.stack, 0.15, 0.25);
+        let mut aabb = display.aabb;
 
-        let mut animation_player = AnimationPlayer::default();
-        let animation_index = model_config.animations.get("dropped").cloned();
-        animation_player.set_idle_animation(animation_index);
-        animation_player.set_move_animation(animation_index);
+        transform.scale = DVec3::splat(display.scale);
+        // Moving it down to create some constant spacing between the item and the ground.
+        aabb.center.y -= 0.01 / display.scale;
 
         let mut entity_commands = commands.entity(entity);
 
         entity_commands.insert((
-            Model::Asset(item_config.model_id),
-            animation_player,
+            display.model,
+            display.dropped_animation,
             Collider::Single(aabb),
         ));
 
@@ -113,15 +129,27 @@ fn spawn_model(
 fn item_pickup(
     mut commands: Commands,
     net: Res<Server>,
+    time: Res<Time>,
     model_map: Res<ModelMap>,
     chunk_subscriptions: Res<ChunkSubscriptions>,
-    mut players: Query<(&GlobalTransform, &mut Inventory, &Health, &Camera)>,
+    mut players: Query<(
+        Entity,
+        &GlobalTransform,
+        &mut Inventory,
+        &Health,
+        &GameMode,
+        &Camera,
+    )>,
     mut dropped_items: Query<(Entity, &mut DroppedItem, &mut Physics, &Transform)>,
+    mut pickup_events: MessageWriter<PickupEvent>,
 ) {
     let now = std::time::Instant::now();
 
-    for (player_transform, mut player_inventory, health, camera) in players.iter_mut() {
-        if health.is_dead() {
+    for (player_entity, player_transform, mut player_inventory, health, game_mode, camera) in
+        players.iter_mut()
+    {
+        // Spectators shouldn't affect the world, they pass through items without picking them up.
+        if health.is_dead() || *game_mode == GameMode::Spectator {
             continue;
         }
 
@@ -145,82 +173,242 @@ fn item_pickup(
                 continue;
             }
 
-            let distance_squared = item_transform.translation.distance_squared(player_position);
+            let distance = item_transform.translation.distance(player_position);
 
-            if distance_squared >= 4.0 {
+            if distance >= ATTRACTION_RANGE {
                 continue;
             }
 
             // First test that the item can be picked up. This is primarily to prevent triggering
             // change detection for the inventory, which would cause and interface update, but also
             // so the item doesn't move unless there's room in the inventory.
-            let mut has_capacity = false;
-            for item_stack in player_inventory.iter() {
-                if (item_stack.item() == dropped_item.stack.item()
+            let has_capacity = player_inventory.iter().any(|item_stack| {
+                (item_stack.item() == dropped_item.stack.item()
                     && item_stack.remaining_capacity() != 0)
                     || item_stack.is_empty()
-                {
-                    has_capacity = true;
-                    break;
-                }
-            }
+            });
             if !has_capacity {
-                break;
+                continue;
             }
 
-            // Move the item towards the player
-            physics.velocity = (player_position - item_transform.translation).normalize() * 10.0;
+            // Pull the item towards the player, it speeds up the closer it gets.
+            let direction = (player_position - item_transform.translation).normalize_or_zero();
+            let pull = (1.0 - distance / ATTRACTION_RANGE).max(0.1);
+            let target_velocity = direction * ATTRACTION_SPEED;
+            let blend = (ATTRACTION_ACCELERATION * pull * time.delta_secs_f64()).min(1.0);
+            physics.velocity = physics.velocity.lerp(target_velocity, blend);
 
             // Pick up when it's just close enough not to disturb the camera view
-            if distance_squared < 0.1 {
-                if let Some(subscribers) = chunk_subscriptions
-                    .get_subscribers(&ChunkPosition::from(item_transform.translation))
-                {
-                    net.send_many(
-                        subscribers,
-                        messages::Sound {
-                            position: Some(player_position),
-                            volume: 0.05,
-                            speed: 1.5,
-                            sound: "pickup.ogg".to_owned(),
-                        },
-                    );
-                }
+            if distance >= PICKUP_DISTANCE {
+                continue;
+            }
 
-                // TODO: Auto-filling a slot in the inventory should be a method on Inventory.
-                // It will be done other places.
-                //
-                // First try to fill item stacks that already have the item
-                for item_stack in player_inventory.iter_mut() {
-                    if item_stack.item() == dropped_item.stack.item() {
-                        dropped_item.stack.transfer_to(item_stack, u32::MAX);
-                    }
+            if let Some(subscribers) = chunk_subscriptions
+                .get_subscribers(&ChunkPosition::from(item_transform.translation))
+            {
+                net.send_many(
+                    subscribers,
+                    messages::Sound {
+                        position: Some(player_position),
+                        volume: 0.05,
+                        speed: 1.5,
+                        sound: "pickup.ogg".to_owned(),
+                    },
+                );
+            }
 
+            // TODO: Auto-filling a slot in the inventory should be a method on Inventory.
+            // It will be done other places.
+            //
+            // First try to fill item stacks that already have the item, then go again and fill
+            // empty spots. Whatever doesn't fit stays on the ground.
+            for fill_empty in [false, true] {
+                for (slot, item_stack) in player_inventory.iter_mut().enumerate() {
                     if dropped_item.stack.is_empty() {
                         break;
                     }
+
+                    let matches = if fill_empty {
+                        item_stack.is_empty()
+                    } else {
+                        item_stack.item() == dropped_item.stack.item()
+                    };
+                    if !matches {
+                        continue;
+                    }
+
+                    let before = dropped_item.stack.size();
+                    dropped_item.stack.transfer_to(item_stack, u32::MAX);
+                    let amount = before - dropped_item.stack.size();
+                    if amount != 0 {
+                        pickup_events.write(PickupEvent {
+                            player_entity,
+                            slot,
+                            amount,
+                        });
+                    }
                 }
+            }
+
+            if dropped_item.stack.is_empty() {
+                commands.entity(entity).despawn();
+            }
+        }
+    }
+}
+
+// Items of the same kind that land next to each other are merged so they don't pile up. The newer
+// item is moved into the older one, so the merged stack despawns when the oldest part of it would
+// have. Items are only merged with items in the same chunk.
+fn merge_items(
+    mut commands: Commands,
+    time: Res<Time>,
+    model_map: Res<ModelMap>,
+    mut dropped_items: Query<(&mut DroppedItem, &Transform)>,
+    mut timer: Local<Timer>,
+) {
+    timer.tick(time.delta());
+    if !timer.is_finished() {
+        return;
+    }
+    *timer = Timer::from_seconds(MERGE_INTERVAL, TimerMode::Once);
+
+    let now = std::time::Instant::now();
 
-                if dropped_item.stack.is_empty() {
-                    commands.entity(entity).despawn();
+    let chunk_positions: HashSet<ChunkPosition> = dropped_items
+        .iter()
+        .map(|(_, transform)| ChunkPosition::from(transform.translation))
+        .collect();
+
+    for chunk_position in chunk_positions {
+        let mut item_entities: Vec<Entity> = model_map
+            .iter_entities(&chunk_position)
+            .filter(|entity| {
+                // Items that were just thrown are left alone until they can be picked up,
+                // otherwise they would be merged into items that can.
+                dropped_items.get(*entity).is_ok_and(|(dropped_item, _)| {
+                    now.duration_since(dropped_item.drop_time) >= dropped_item.pickup_delay
+                })
+            })
+            .collect();
+        item_entities.sort_by_key(|entity| dropped_items.get(*entity).unwrap().0.drop_time);
+
+        let mut merged = HashSet::new();
+        for (index, older) in item_entities.iter().copied().enumerate() {
+            if merged.contains(&older) {
+                continue;
+            }
+
+            for newer in item_entities[index + 1..].iter().copied() {
+                if merged.contains(&newer) {
                     continue;
                 }
 
-                // Then go again and fill empty spots
-                for item_stack in player_inventory.iter_mut() {
-                    if item_stack.is_empty() {
-                        dropped_item.stack.transfer_to(item_stack, u32::MAX);
-                    }
+                let Ok(
+                    [
+                        (mut older_item, older_transform),
+                        (mut newer_item, newer_transform),
+                    ],
+                ) = dropped_items.get_many_mut([older, newer])
+                else {
+                    continue;
+                };
 
-                    if dropped_item.stack.is_empty() {
-                        break;
-                    }
+                if !can_merge(&older_item.stack, &newer_item.stack)
+                    || older_transform
+                        .translation
+                        .distance(newer_transform.translation)
+                        > MERGE_DISTANCE
+                {
+                    continue;
+                }
+
+                if older_item.stack.remaining_capacity() == 0 {
+                    break;
+                }
+
+                newer_item
+                    .stack
+                    .transfer_to(&mut older_item.stack, u32::MAX);
+
+                if newer_item.stack.is_empty() {
+                    commands.entity(newer).despawn();
+                    merged.insert(newer);
                 }
+            }
+        }
+    }
+}
+
+fn despawn_items(mut commands: Commands, dropped_items: Query<(Entity, &DroppedItem)>) {
+    let now = std::time::Instant::now();
+    for (entity, dropped_item) in dropped_items.iter() {
+        if now.duration_since(dropped_item.drop_time) > DESPAWN_TIME {
+            commands.entity(entity).despawn();
+        }
+    }
+}
 
-                if dropped_item.stack.is_empty() {
-                    commands.entity(entity).despawn();
+// Stacks are only merged when they are the same item with the same properties, so that e.g.
+// durability and enchantments aren't lost, and can hold the same amount.
+fn can_merge(older: &ItemStack, newer: &ItemStack) -> bool {
+    let kind = |stack: &ItemStack| stack.item().map(|item| (item.id, &item.properties));
+    let capacity = |stack: &ItemStack| stack.size() + stack.remaining_capacity();
+    kind(older) == kind(newer) && capacity(older) == capacity(newer)
+}
+
+// When a chunk has too many dropped items, items of the same kind are first merged together. If
+// that is not enough the oldest items are removed.
+fn enforce_chunk_budget(
+    mut commands: Commands,
+    budgets: Res<ChunkBudgets>,
+    model_map: Res<ModelMap>,
+    mut dropped_items: Query<&mut DroppedItem>,
+) {
+    for (chunk_position, counts) in budgets.iter() {
+        if counts.dropped_items <= MAX_DROPPED_ITEMS {
+            continue;
+        }
+
+        let mut item_entities: Vec<Entity> = model_map
+            .iter_entities(chunk_position)
+            .filter(|entity| dropped_items.contains(*entity))
+            .collect();
+        item_entities.sort_by_key(|entity| dropped_items.get(*entity).unwrap().drop_time);
+
+        let mut remaining = Vec::with_capacity(item_entities.len());
+        for newer in item_entities {
+            for older in remaining.iter().copied() {
+                let Ok([mut older_item, mut newer_item]) =
+                    dropped_items.get_many_mut([older, newer])
+                else {
+                    continue;
+                };
+
+                if !can_merge(&older_item.stack, &newer_item.stack) {
                     continue;
                 }
+
+                newer_item
+                    .stack
+                    .transfer_to(&mut older_item.stack, u32::MAX);
+
+                if newer_item.stack.is_empty() {
+                    break;
+                }
+            }
+
+            if dropped_items.get(newer).unwrap().stack.is_empty() {
+                commands.entity(newer).despawn();
+            } else {
+                remaining.push(newer);
+            }
+        }
+
+        if remaining.len() > MAX_DROPPED_ITEMS {
+            let excess = remaining.len() - MAX_DROPPED_ITEMS;
+            for entity in remaining.drain(..excess) {
+                commands.entity(entity).despawn();
             }
         }
     }
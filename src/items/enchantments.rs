@@ -0,0 +1,28 @@
+use fmc::items::ItemStack;
+
+// TODO: There is no way to enchant items yet, so only items that have their enchantments set in
+// their item config have any.
+//
+/// Enchantments are stored in an item's properties, as a map from the name of the enchantment to
+/// its level:
+/// ```json
+/// "properties": {
+///     "enchantments": {
+///         "returning": 1
+///     }
+/// }
+/// ```
+/// Returns the level of the enchantment, or None if the item doesn't have it.
+pub fn enchantment_level(item_stack: &ItemStack, enchantment: &str) -> Option<u32> {
+    item_stack
+        .item()?
+        .properties
+        .get("enchantments")?
+        .get(enchantment)?
+        .as_u64()
+        .map(|level| level as u32)
+}
+
+pub fn has_enchantment(item_stack: &ItemStack, enchantment: &str) -> bool {
+    enchantment_level(item_stack, enchantment).is_some_and(|level| level > 0)
+}
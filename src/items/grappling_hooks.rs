@@ -0,0 +1,238 @@
+use fmc::{
+    bevy::math::{DQuat, DVec3},
+    blocks::{BlockPosition, Blocks},
+    items::Items,
+    models::{Model, Models},
+    networking::Server,
+    players::{Camera, Player},
+    prelude::*,
+    protocol::messages,
+    world::{ChangedBlockEvent, WorldMap},
+};
+
+use super::{ItemRegistry, ItemUseSystems, ItemUses};
+use crate::players::{Inventory, MovementPluginPacket};
+
+/// Grappling hooks are fired by right clicking. When the hook catches on a block the player is
+/// pulled towards it until they reach it, or right click again to let go. Every shot wears down
+/// the hook's durability.
+pub struct GrapplingHookPlugin;
+impl Plugin for GrapplingHookPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Startup, register_grappling_hooks)
+            .add_systems(
+                Update,
+                (
+                    fire_hooks.after(ItemUseSystems),
+                    fly_hooks,
+                    pull_players,
+                    release_hooks,
+                )
+                    .chain(),
+            );
+    }
+}
+
+const HOOK_SPEED: f64 = 40.0;
+/// The hook is lost if it flies further than this from the player, and lets go if the player
+/// somehow ends up further away than this while pulled.
+const MAX_ROPE_LENGTH: f64 = 24.0;
+const PULL_SPEED: f32 = 18.0;
+/// The player lets go of the rope once they are this close to the hook
+const ARRIVE_DISTANCE: f64 = 1.5;
+/// How long the player can be pulled before letting go, in case they get stuck on something
+const MAX_PULL_TIME: f32 = 3.0;
+/// How often the pull velocity is sent to the player
+const PULL_INTERVAL: f32 = 0.1;
+
+#[derive(Component)]
+struct GrapplingHookUses;
+
+#[derive(Component)]
+struct Hook {
+    owner: Entity,
+    velocity: DVec3,
+    /// The block the hook has caught on
+    anchor: Option<BlockPosition>,
+    pull_timer: Timer,
+    pull_interval: Timer,
+}
+
+fn register_grappling_hooks(
+    mut commands: Commands,
+    items: Res<Items>,
+    mut usable_items: ResMut<ItemRegistry>,
+) {
+    let hook = items.get_id("grappling_hook").unwrap();
+    let entity = commands
+        .spawn((ItemUses::default(), GrapplingHookUses))
+        .id();
+    usable_items.insert(hook, entity);
+}
+
+fn fire_hooks(
+    mut commands: Commands,
+    models: Res<Models>,
+    mut player_query: Query<(&mut Inventory, &GlobalTransform, &Camera), With<Player>>,
+    hooks: Query<(Entity, &Hook)>,
+    mut hook_uses: Query<&mut ItemUses, (With<GrapplingHookUses>, Changed<ItemUses>)>,
+) {
+    let Ok(mut uses) = hook_uses.single_mut() else {
+        return;
+    };
+
+    for player_entity in uses.read() {
+        // Using the hook while it is out lets go of it.
+        if let Some((hook_entity, _)) = hooks.iter().find(|(_, hook)| hook.owner == player_entity) {
+            commands.entity(hook_entity).despawn();
+            continue;
+        }
+
+        let (mut inventory, transform, camera) = player_query.get_mut(player_entity).unwrap();
+
+        let item_stack = inventory.held_item_stack_mut();
+        let Some(item) = item_stack.item_mut() else {
+            continue;
+        };
+
+        if let Some(durability) = item.properties["durability"].as_u64() {
+            if durability <= 1 {
+                item_stack.take(1);
+            } else {
+                item.properties["durability"] = (durability - 1).into();
+            }
+        }
+
+        let model_config = models.get_config_by_name("grappling_hook").unwrap();
+        let velocity = camera.forward() * HOOK_SPEED;
+
+        commands.spawn((
+            Model::Asset(model_config.id),
+            Hook {
+                owner: player_entity,
+                velocity,
+                anchor: None,
+                pull_timer: Timer::from_seconds(MAX_PULL_TIME, TimerMode::Once),
+                pull_interval: Timer::from_seconds(PULL_INTERVAL, TimerMode::Repeating),
+            },
+            Transform {
+                translation: transform.translation() + camera.translation,
+                rotation: DQuat::from_rotation_arc(DVec3::NEG_Z, velocity.normalize()),
+                scale: DVec3::splat(0.0625),
+            },
+        ));
+    }
+}
+
+fn fly_hooks(
+    mut commands: Commands,
+    time: Res<Time>,
+    world_map: Res<WorldMap>,
+    player_query: Query<&GlobalTransform, With<Player>>,
+    mut hooks: Query<(Entity, &mut Hook, &mut Transform)>,
+) {
+    for (hook_entity, mut hook, mut transform) in hooks.iter_mut() {
+        if hook.anchor.is_some() {
+            continue;
+        }
+
+        let Ok(owner_transform) = player_query.get(hook.owner) else {
+            commands.entity(hook_entity).despawn();
+            continue;
+        };
+
+        if owner_transform
+            .translation()
+            .distance(transform.translation)
+            > MAX_ROPE_LENGTH
+        {
+            commands.entity(hook_entity).despawn();
+            continue;
+        }
+
+        transform.look_to(hook.velocity, DVec3::Y);
+
+        let max_distance = (hook.velocity * time.delta_secs_f64()).length();
+
+        let blocks = Blocks::get();
+        let mut raycast = world_map.raycast(&transform, max_distance);
+        while let Some(block_id) = raycast.next_block() {
+            if !blocks.get_config(&block_id).is_solid() {
+                continue;
+            }
+
+            transform.translation += hook.velocity.normalize() * raycast.distance();
+            hook.anchor = Some(raycast.position());
+            hook.velocity = DVec3::ZERO;
+            break;
+        }
+
+        if hook.anchor.is_some() {
+            continue;
+        }
+
+        transform.translation += hook.velocity * time.delta_secs_f64();
+        hook.velocity.y -= 14.0 * time.delta_secs_f64();
+    }
+}
+
+fn pull_players(
+    mut commands: Commands,
+    net: Res<Server>,
+    time: Res<Time>,
+    player_query: Query<&GlobalTransform, With<Player>>,
+    mut hooks: Query<(Entity, &mut Hook, &Transform)>,
+) {
+    for (hook_entity, mut hook, transform) in hooks.iter_mut() {
+        if hook.anchor.is_none() {
+            continue;
+        }
+
+        let Ok(owner_transform) = player_query.get(hook.owner) else {
+            commands.entity(hook_entity).despawn();
+            continue;
+        };
+
+        hook.pull_timer.tick(time.delta());
+        let distance = owner_transform
+            .translation()
+            .distance(transform.translation);
+        if hook.pull_timer.is_finished() || distance < ARRIVE_DISTANCE || distance > MAX_ROPE_LENGTH
+        {
+            commands.entity(hook_entity).despawn();
+            continue;
+        }
+
+        hook.pull_interval.tick(time.delta());
+        if !hook.pull_interval.just_finished() {
+            continue;
+        }
+
+        let direction = (transform.translation - owner_transform.translation())
+            .normalize()
+            .as_vec3();
+        net.send_one(
+            hook.owner,
+            messages::PluginData {
+                plugin: "movement".to_owned(),
+                data: bincode::serialize(&MovementPluginPacket::Velocity(direction * PULL_SPEED))
+                    .unwrap(),
+            },
+        );
+    }
+}
+
+// The hook lets go when the block it has caught on is removed.
+fn release_hooks(
+    mut commands: Commands,
+    mut changed_blocks: MessageReader<ChangedBlockEvent>,
+    hooks: Query<(Entity, &Hook)>,
+) {
+    for changed_block in changed_blocks.read() {
+        for (hook_entity, hook) in hooks.iter() {
+            if hook.anchor == Some(changed_block.position) {
+                commands.entity(hook_entity).despawn();
+            }
+        }
+    }
+}
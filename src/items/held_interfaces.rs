@@ -0,0 +1,78 @@
+use std::collections::HashMap;
+
+use fmc::{items::ItemId, networking::Server, players::Player, prelude::*, protocol::messages};
+
+use crate::players::Inventory;
+
+/// Shows an interface while the player holds the item it belongs to, like the needle of a
+/// compass. The interfaces are hidden when the player joins.
+pub struct HeldInterfacePlugin;
+impl Plugin for HeldInterfacePlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(HeldInterfaces::default())
+            .add_systems(Update, (hide_on_join, show_held_interfaces).chain());
+    }
+}
+
+/// The interface that is shown while an item is held, register them at startup.
+#[derive(Resource, Default)]
+pub struct HeldInterfaces(HashMap<ItemId, String>);
+
+impl HeldInterfaces {
+    pub fn insert(&mut self, item_id: ItemId, interface_path: &str) {
+        self.0.insert(item_id, interface_path.to_owned());
+    }
+
+    /// The interface of the item the player is holding
+    pub fn get(&self, inventory: &Inventory) -> Option<&str> {
+        let item = inventory.held_item_stack().item()?;
+        self.0.get(&item.id).map(String::as_str)
+    }
+}
+
+fn set_visibility(net: &Server, player_entity: Entity, interface_path: &str, visible: bool) {
+    net.send_one(
+        player_entity,
+        messages::InterfaceVisibilityUpdate {
+            interface_path: interface_path.to_owned(),
+            visible,
+        },
+    );
+}
+
+fn hide_on_join(
+    net: Res<Server>,
+    held_interfaces: Res<HeldInterfaces>,
+    new_players: Query<Entity, Added<Player>>,
+) {
+    for player_entity in new_players.iter() {
+        for interface_path in held_interfaces.0.values() {
+            set_visibility(&net, player_entity, interface_path, false);
+        }
+    }
+}
+
+fn show_held_interfaces(
+    net: Res<Server>,
+    held_interfaces: Res<HeldInterfaces>,
+    players: Query<(Entity, &Inventory), (With<Player>, Changed<Inventory>)>,
+    connected: Query<(), With<Player>>,
+    mut shown: Local<HashMap<Entity, String>>,
+) {
+    for (player_entity, inventory) in players.iter() {
+        let held = held_interfaces.get(inventory);
+        if shown.get(&player_entity).map(String::as_str) == held {
+            continue;
+        }
+
+        if let Some(interface_path) = shown.remove(&player_entity) {
+            set_visibility(&net, player_entity, &interface_path, false);
+        }
+        if let Some(interface_path) = held {
+            set_visibility(&net, player_entity, interface_path, true);
+            shown.insert(player_entity, interface_path.to_owned());
+        }
+    }
+
+    shown.retain(|player_entity, _| connected.contains(*player_entity));
+}
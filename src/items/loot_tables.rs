@@ -0,0 +1,318 @@
+use std::collections::HashMap;
+
+use fmc::{
+    blocks::{BlockId, Blocks},
+    items::{ItemId, ItemStack, Items},
+    prelude::*,
+    random::{Rng, UniformDistribution},
+};
+use serde::Deserialize;
+
+use super::enchantments;
+
+const LOOT_TABLE_PATH: &str = "./assets/server/loot_tables/";
+const BLOCK_LOOT_PATH: &str = "./assets/server/block_loot.json";
+
+/// Loads the loot tables used to fill chests in generated structures, and the conditions that
+/// change what blocks drop when they are broken.
+pub struct LootTablePlugin;
+impl Plugin for LootTablePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Startup, (load_loot_tables, load_block_loot));
+    }
+}
+
+/// Loot tables by name, the name is the file name of the loot table without its extension.
+#[derive(Resource)]
+pub struct LootTables(HashMap<String, LootTable>);
+
+impl LootTables {
+    pub fn get(&self, name: &str) -> Option<&LootTable> {
+        self.0.get(name)
+    }
+}
+
+pub struct LootTable {
+    // How many times an entry is picked
+    rolls: UniformDistribution<u32>,
+    entries: Vec<LootEntry>,
+    total_weight: u32,
+}
+
+struct LootEntry {
+    item_id: ItemId,
+    weight: u32,
+    count: UniformDistribution<u32>,
+}
+
+impl LootTable {
+    /// Roll the loot table and put the loot into random empty slots of the inventory. Loot that
+    /// doesn't fit is discarded.
+    pub fn fill(&self, items: &Items, inventory: &mut [ItemStack], rng: &mut Rng) {
+        if self.total_weight == 0 {
+            return;
+        }
+
+        for _ in 0..self.rolls.sample(rng) {
+            let mut pick = UniformDistribution::new(0, self.total_weight - 1).sample(rng);
+            let Some(entry) = self.entries.iter().find(|entry| {
+                if pick < entry.weight {
+                    true
+                } else {
+                    pick -= entry.weight;
+                    false
+                }
+            }) else {
+                continue;
+            };
+
+            let count = entry.count.sample(rng);
+            if count == 0 {
+                continue;
+            }
+
+            let empty_slots = inventory
+                .iter()
+                .enumerate()
+                .filter(|(_, item_stack)| item_stack.is_empty())
+                .map(|(index, _)| index)
+                .collect::<Vec<_>>();
+            if empty_slots.is_empty() {
+                return;
+            }
+
+            let slot = empty_slots[UniformDistribution::new(0, empty_slots.len() - 1).sample(rng)];
+            inventory[slot] = ItemStack::new(items.get_config(&entry.item_id), count);
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct LootTableJson {
+    rolls: [u32; 2],
+    entries: Vec<LootEntryJson>,
+}
+
+#[derive(Deserialize)]
+struct LootEntryJson {
+    item: String,
+    weight: u32,
+    count: [u32; 2],
+}
+
+fn load_loot_tables(mut commands: Commands, items: Res<Items>) {
+    let directory = std::fs::read_dir(LOOT_TABLE_PATH).expect(&format!(
+        "Could not read files from loot table directory, make sure it is present at '{}'",
+        LOOT_TABLE_PATH
+    ));
+
+    let mut loot_tables = HashMap::new();
+
+    for entry in directory {
+        let file_path = entry
+            .expect("Failed to read the filenames of the loot tables")
+            .path();
+
+        let file = match std::fs::File::open(&file_path) {
+            Ok(f) => f,
+            Err(e) => panic!(
+                "Failed to open loot table at path: {}\nError: {}",
+                file_path.display(),
+                e
+            ),
+        };
+
+        let json: LootTableJson = match serde_json::from_reader(file) {
+            Ok(l) => l,
+            Err(e) => panic!(
+                "Failed to read loot table at path: {}\nError: {}",
+                file_path.display(),
+                e
+            ),
+        };
+
+        let name = file_path
+            .file_stem()
+            .unwrap()
+            .to_string_lossy()
+            .into_owned();
+
+        if json.rolls[0] > json.rolls[1] {
+            panic!(
+                "Failed to read the loot table '{}', the minimum number of rolls({}) must be less \
+                than the maximum({})",
+                name, json.rolls[0], json.rolls[1]
+            );
+        }
+
+        let mut entries = Vec::with_capacity(json.entries.len());
+        for entry in json.entries {
+            let Some(item_id) = items.get_id(&entry.item) else {
+                panic!(
+                    "Failed to read the loot table '{}', there is no item with the name '{}'",
+                    name, entry.item
+                );
+            };
+
+            if entry.count[0] > entry.count[1] {
+                panic!(
+                    "Failed to read the loot table '{}', the minimum count({}) of '{}' must be \
+                    less than the maximum({})",
+                    name, entry.count[0], entry.item, entry.count[1]
+                );
+            }
+
+            entries.push(LootEntry {
+                item_id,
+                weight: entry.weight,
+                count: UniformDistribution::new(entry.count[0], entry.count[1]),
+            });
+        }
+
+        loot_tables.insert(
+            name,
+            LootTable {
+                rolls: UniformDistribution::new(json.rolls[0], json.rolls[1]),
+                total_weight: entries.iter().map(|entry| entry.weight).sum(),
+                entries,
+            },
+        );
+    }
+
+    commands.insert_resource(LootTables(loot_tables));
+}
+
+/// What blocks drop when they're broken, for the blocks whose drop depends on more than the block
+/// config.
+///
+/// A tool with silk touch makes the block drop itself instead of its usual drop. Fortune makes
+/// the block drop more, each level adds one more possible multiple of the drop, but there is
+/// always a chance it only drops the usual amount.
+#[derive(Resource)]
+pub struct BlockLoot(HashMap<BlockId, BlockLootEntry>);
+
+struct BlockLootEntry {
+    count: UniformDistribution<u32>,
+    // The item of the block itself, set if it can be silk touched
+    silk_touch: Option<ItemId>,
+    fortune: bool,
+}
+
+impl BlockLoot {
+    /// The items the block drops when it is broken with the tool. `drop` is what the block config
+    /// says it should drop.
+    pub fn drop(
+        &self,
+        block_id: BlockId,
+        drop: Option<ItemId>,
+        tool: &ItemStack,
+        items: &Items,
+        rng: &mut Rng,
+    ) -> Option<ItemStack> {
+        let Some(entry) = self.0.get(&block_id) else {
+            return drop.map(|item_id| ItemStack::new(items.get_config(&item_id), 1));
+        };
+
+        if let Some(item_id) = entry.silk_touch
+            && enchantments::has_enchantment(tool, "silk_touch")
+        {
+            return Some(ItemStack::new(items.get_config(&item_id), 1));
+        }
+
+        let item_config = items.get_config(&drop?);
+        let mut count = entry.count.sample(rng);
+
+        if entry.fortune
+            && let Some(level) = enchantments::enchantment_level(tool, "fortune")
+        {
+            // Each multiple up to level + 1 is equally likely, with the chance of no bonus
+            // doubled.
+            let multiplier = UniformDistribution::new(0, level + 1).sample(rng).max(1);
+            count *= multiplier;
+        }
+
+        if count == 0 {
+            return None;
+        }
+
+        Some(ItemStack::new(item_config, count))
+    }
+}
+
+#[derive(Deserialize)]
+struct BlockLootJson {
+    #[serde(default = "default_count")]
+    count: [u32; 2],
+    #[serde(default)]
+    silk_touch: bool,
+    #[serde(default)]
+    fortune: bool,
+}
+
+fn default_count() -> [u32; 2] {
+    [1, 1]
+}
+
+fn load_block_loot(mut commands: Commands, items: Res<Items>) {
+    let file = match std::fs::File::open(BLOCK_LOOT_PATH) {
+        Ok(f) => f,
+        Err(e) => panic!(
+            "Failed to open block loot at path: {}\nError: {}",
+            BLOCK_LOOT_PATH, e
+        ),
+    };
+
+    let json: HashMap<String, BlockLootJson> = match serde_json::from_reader(file) {
+        Ok(b) => b,
+        Err(e) => panic!(
+            "Failed to read block loot at path: {}\nError: {}",
+            BLOCK_LOOT_PATH, e
+        ),
+    };
+
+    let blocks = Blocks::get();
+    let mut block_loot = HashMap::with_capacity(json.len());
+
+    for (block_name, entry) in json {
+        if !blocks.contains_block(&block_name) {
+            panic!(
+                "Failed to read the block loot, there is no block with the name '{}'",
+                block_name
+            );
+        }
+        let block_id = blocks.get_id(&block_name);
+
+        if entry.count[0] > entry.count[1] {
+            panic!(
+                "Failed to read the block loot of '{}', the minimum count({}) must be less than \
+                the maximum({})",
+                block_name, entry.count[0], entry.count[1]
+            );
+        }
+
+        let silk_touch = if entry.silk_touch {
+            // Blocks that can be placed have an item by the same name
+            match items.get_id(&block_name) {
+                Some(item_id) => Some(item_id),
+                None => panic!(
+                    "Failed to read the block loot of '{}', it can be silk touched, but there is \
+                    no item by the same name for it to drop",
+                    block_name
+                ),
+            }
+        } else {
+            None
+        };
+
+        block_loot.insert(
+            block_id,
+            BlockLootEntry {
+                count: UniformDistribution::new(entry.count[0], entry.count[1]),
+                silk_touch,
+                fortune: entry.fortune,
+            },
+        );
+    }
+
+    commands.insert_resource(BlockLoot(block_loot));
+}
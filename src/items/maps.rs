@@ -0,0 +1,208 @@
+use std::collections::HashMap;
+
+use fmc::{
+    bevy::math::DVec2,
+    blocks::{BlockId, BlockPosition, Blocks},
+    items::{ItemId, Items},
+    networking::Server,
+    players::Player,
+    prelude::*,
+    protocol::messages,
+    world::WorldMap,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::players::Inventory;
+
+use super::held_interfaces::HeldInterfaces;
+
+/// Maps draw the area around where they were first held, as seen from above. The area is filled
+/// in as the player holding the map explores it.
+///
+/// The drawing is done on the server from the surface blocks of the loaded chunks, and kept in the
+/// map item. The client can't be sent images, so nothing like map tiles is streamed to it. Each
+/// pixel of the map interface is instead a stack of colored images, and the pixels that change are
+/// sent as visibility updates that show the image of the new color and hide the others. The
+/// interface is written by `utils/map_interface/map_interface.py`.
+pub struct MapPlugin;
+impl Plugin for MapPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Startup, register_maps)
+            .add_systems(Update, draw_maps);
+    }
+}
+
+/// Number of pixels along each side of the map
+const MAP_SIZE: i32 = 16;
+/// Number of blocks along each side of a pixel
+const PIXEL_SIZE: i32 = 4;
+/// How close the player has to be to a pixel for it to be drawn
+const EXPLORE_RADIUS: f64 = 24.0;
+/// How far above and below the player the surface is looked for
+const SURFACE_SEARCH: i32 = 32;
+/// Seconds between each time the maps are drawn
+const DRAW_INTERVAL: f32 = 0.5;
+/// Number of colors in the interface, each pixel has an image of each. Changing this or
+/// [MAP_SIZE] means generating the interface again.
+const COLORS: u8 = 8;
+/// Pixels that haven't been explored
+const UNEXPLORED: char = '.';
+
+#[derive(Resource)]
+struct MapItem(ItemId);
+
+/// The drawing, stored in the "map" property of the item
+#[derive(Serialize, Deserialize)]
+struct MapData {
+    /// The lowest corner of the drawn area
+    x: i32,
+    z: i32,
+    /// The color of each pixel, row by row from the lowest z. Pixels are a digit that is the index
+    /// of their color, or '.' if they are unexplored.
+    pixels: String,
+}
+
+impl MapData {
+    fn new(position: BlockPosition) -> Self {
+        // Maps are aligned to a grid, so that maps made close to each other show the same area.
+        let width = MAP_SIZE * PIXEL_SIZE;
+        Self {
+            x: position.x.div_euclid(width) * width,
+            z: position.z.div_euclid(width) * width,
+            pixels: UNEXPLORED
+                .to_string()
+                .repeat((MAP_SIZE * MAP_SIZE) as usize),
+        }
+    }
+}
+
+fn register_maps(
+    mut commands: Commands,
+    items: Res<Items>,
+    mut held_interfaces: ResMut<HeldInterfaces>,
+) {
+    let map = items.get_id("map").unwrap();
+    held_interfaces.insert(map, "map");
+    commands.insert_resource(MapItem(map));
+}
+
+// Index of the color the block is drawn with
+fn map_color(block_name: &str) -> u8 {
+    let is = |words: &[&str]| words.iter().any(|word| block_name.contains(word));
+    if is(&["water", "kelp", "seagrass"]) {
+        0
+    } else if is(&["snow", "ice"]) {
+        5
+    } else if is(&["sand"]) {
+        1
+    } else if is(&["leaves"]) {
+        3
+    } else if is(&["grass", "wheat", "soil"]) {
+        2
+    } else if is(&["stone", "ore", "obsidian"]) {
+        4
+    } else if is(&["nether", "lava", "fire"]) {
+        7
+    } else {
+        6
+    }
+}
+
+// The highest block that isn't air in the column, None if there is no loaded ground around the
+// player's height.
+fn surface(world_map: &WorldMap, air: BlockId, x: i32, z: i32, around_y: i32) -> Option<BlockId> {
+    ((around_y - SURFACE_SEARCH)..=(around_y + SURFACE_SEARCH))
+        .rev()
+        .filter_map(|y| world_map.get_block(BlockPosition::new(x, y, z)))
+        .find(|block_id| *block_id != air)
+}
+
+fn draw_maps(
+    net: Res<Server>,
+    time: Res<Time>,
+    world_map: Res<WorldMap>,
+    map_item: Res<MapItem>,
+    mut player_query: Query<(Entity, &mut Inventory, &Transform), With<Player>>,
+    mut since_last_draw: Local<f32>,
+    mut shown: Local<HashMap<Entity, String>>,
+    mut colors: Local<HashMap<BlockId, u8>>,
+) {
+    *since_last_draw += time.delta_secs();
+    if *since_last_draw < DRAW_INTERVAL {
+        return;
+    }
+    *since_last_draw = 0.0;
+
+    let blocks = Blocks::get();
+    let air = blocks.get_id("air");
+
+    for (player_entity, mut inventory, transform) in player_query.iter_mut() {
+        let Some(item) = inventory
+            .held_item_stack()
+            .item()
+            .filter(|item| item.id == map_item.0)
+        else {
+            // The whole map is sent the next time it is held
+            shown.remove(&player_entity);
+            continue;
+        };
+
+        let player_position = BlockPosition::from(transform.translation);
+        let mut map_data: MapData = serde_json::from_value(item.properties["map"].clone())
+            .unwrap_or_else(|_| MapData::new(player_position));
+
+        let mut pixels: Vec<char> = map_data.pixels.chars().collect();
+        for (index, pixel) in pixels.iter_mut().enumerate() {
+            if *pixel != UNEXPLORED {
+                continue;
+            }
+
+            let x = map_data.x + (index as i32 % MAP_SIZE) * PIXEL_SIZE + PIXEL_SIZE / 2;
+            let z = map_data.z + (index as i32 / MAP_SIZE) * PIXEL_SIZE + PIXEL_SIZE / 2;
+            let distance = DVec2::new(x as f64, z as f64).distance(transform.translation.xz());
+            if distance > EXPLORE_RADIUS {
+                continue;
+            }
+
+            let Some(block_id) = surface(&world_map, air, x, z, player_position.y) else {
+                continue;
+            };
+            let color = *colors
+                .entry(block_id)
+                .or_insert_with(|| map_color(&blocks.get_config(&block_id).name));
+            *pixel = char::from_digit(color as u32, 10).unwrap();
+        }
+
+        let pixels: String = pixels.into_iter().collect();
+        if pixels != map_data.pixels || item.properties["map"].is_null() {
+            map_data.pixels = pixels;
+            let item = inventory.held_item_stack_mut().item_mut().unwrap();
+            item.properties["map"] = serde_json::to_value(&map_data).unwrap();
+        }
+
+        let previous = shown.get(&player_entity);
+        let mut node_update = messages::InterfaceNodeVisibilityUpdate::default();
+        let mut changed = false;
+        for (index, pixel) in map_data.pixels.chars().enumerate() {
+            if previous.is_some_and(|previous| previous.chars().nth(index) == Some(pixel)) {
+                continue;
+            }
+            changed = true;
+
+            let color = pixel.to_digit(10).map(|color| color as u8);
+            for image in 0..COLORS {
+                if Some(image) == color {
+                    node_update.set_visible(format!("map/{}/{}", index, image));
+                } else {
+                    node_update.set_hidden(format!("map/{}/{}", index, image));
+                }
+            }
+        }
+        if changed {
+            net.send_one(player_entity, node_update);
+        }
+        shown.insert(player_entity, map_data.pixels);
+    }
+
+    shown.retain(|player_entity, _| player_query.contains(*player_entity));
+}
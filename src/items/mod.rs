@@ -3,15 +3,29 @@ use std::collections::HashMap;
 use fmc::{items::ItemId, prelude::*};
 
 pub mod crafting;
+pub mod display;
 mod dropped_items;
+pub mod enchantments;
+pub mod loot_tables;
+pub mod tool_tiers;
+pub mod tooltips;
 
 pub mod arrows;
+pub mod boats;
+pub mod bone_meal;
+pub mod bows;
 pub mod bread;
+pub mod clocks;
+pub mod compasses;
+pub mod grappling_hooks;
+pub mod held_interfaces;
 pub mod hoes;
+pub mod maps;
 pub mod seeds;
 pub mod spawn_crates;
+pub mod spears;
 
-pub use dropped_items::DroppedItem;
+pub use dropped_items::{DroppedItem, PickupEvent};
 
 pub struct ItemPlugin;
 impl Plugin for ItemPlugin {
@@ -19,11 +33,22 @@ impl Plugin for ItemPlugin {
         app.insert_resource(ItemRegistry::default())
             .add_plugins(dropped_items::DroppedItemsPlugin)
             .add_plugins(crafting::CraftingPlugin)
+            .add_plugins(loot_tables::LootTablePlugin)
+            .add_plugins(tool_tiers::ToolTierPlugin)
             .add_plugins(hoes::HoePlugin)
             .add_plugins(bread::BreadPlugin)
             .add_plugins(spawn_crates::CratePlugin)
             .add_plugins(arrows::ArrowPlugin)
-            .add_plugins(seeds::SeedPlugin);
+            .add_plugins(boats::BoatPlugin)
+            .add_plugins(bows::BowPlugin)
+            .add_plugins(grappling_hooks::GrapplingHookPlugin)
+            .add_plugins(seeds::SeedPlugin)
+            .add_plugins(bone_meal::BoneMealPlugin)
+            .add_plugins(spears::SpearPlugin)
+            .add_plugins(held_interfaces::HeldInterfacePlugin)
+            .add_plugins(compasses::CompassPlugin)
+            .add_plugins(clocks::ClockPlugin)
+            .add_plugins(maps::MapPlugin);
     }
 }
 
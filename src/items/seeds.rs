@@ -30,6 +30,7 @@ fn register_seeds(
                 SeedConfig {
                     air: blocks.get_id("air"),
                     soil: blocks.get_id("soil"),
+                    wet_soil: blocks.get_id("wet_soil"),
                 },
             ))
             .id(),
@@ -40,6 +41,7 @@ fn register_seeds(
 struct SeedConfig {
     pub air: BlockId,
     pub soil: BlockId,
+    pub wet_soil: BlockId,
 }
 
 fn use_seeds(
@@ -55,8 +57,8 @@ fn use_seeds(
     for player_entity in uses.read() {
         let targets = player_query.get(player_entity).unwrap();
 
-        let Some(Target::Block { block_position, .. }) =
-            targets.get_first_block(|block_id| *block_id == config.soil)
+        let Some(Target::Block { block_position, .. }) = targets
+            .get_first_block(|block_id| *block_id == config.soil || *block_id == config.wet_soil)
         else {
             continue;
         };
@@ -0,0 +1,351 @@
+use fmc::{
+    bevy::math::DVec3,
+    blocks::{BlockPosition, Blocks},
+    items::{ItemStack, Items},
+    models::{ModelMap, Models},
+    physics::Collider,
+    players::{Camera, Player},
+    prelude::*,
+    world::{ChangedBlockEvent, WorldMap, chunk::ChunkPosition},
+};
+
+use super::{
+    DroppedItem, ItemRegistry, ItemUseSystems, ItemUses, display::ItemDisplay,
+    enchantments::has_enchantment,
+};
+use crate::{
+    mobs::{Mob, MobDamageEvent},
+    players::{GameMode, Health, Inventory, PlayerDamageEvent},
+};
+
+/// Spears are melee weapons that can also be thrown by right clicking. Thrown spears stick in
+/// whatever they hit, and can be picked back up. Spears with the "returning" enchantment fly back
+/// to whoever threw them.
+pub struct SpearPlugin;
+impl Plugin for SpearPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Startup, register_spears).add_systems(
+            Update,
+            (
+                throw_spears.after(ItemUseSystems),
+                fly,
+                return_to_owner,
+                pick_up_spears,
+                release_stuck_spears,
+            )
+                .chain(),
+        );
+    }
+}
+
+const THROW_SPEED: f64 = 30.0;
+const RETURN_SPEED: f64 = 25.0;
+/// How long a returning spear stays where it hit before flying back
+const RETURN_DELAY: f32 = 1.0;
+const PICKUP_DISTANCE: f64 = 1.5;
+/// Damage dealt by a thrown spear if the item doesn't define its own "damage" property
+const DEFAULT_DAMAGE: u32 = 8;
+
+const SPEARS: [&str; 2] = ["spear", "returning_spear"];
+
+#[derive(Component)]
+struct SpearUses;
+
+#[derive(PartialEq)]
+enum SpearState {
+    Flying,
+    Stuck(BlockPosition),
+    // The spear hit a mob or player. Spears that don't return fall to the ground as dropped items.
+    HitEntity,
+    Returning,
+}
+
+#[derive(Component)]
+struct ThrownSpear {
+    item_stack: ItemStack,
+    owner: Entity,
+    velocity: DVec3,
+    state: SpearState,
+    // Counts down until a spear with the returning enchantment starts flying back
+    return_timer: Option<Timer>,
+}
+
+impl ThrownSpear {
+    fn hit(&mut self, state: SpearState) {
+        self.velocity = DVec3::ZERO;
+        self.state = state;
+        if has_enchantment(&self.item_stack, "returning") {
+            self.return_timer = Some(Timer::from_seconds(RETURN_DELAY, TimerMode::Once));
+        }
+    }
+
+    fn is_returning(&self) -> bool {
+        self.return_timer.is_some()
+    }
+}
+
+fn register_spears(
+    mut commands: Commands,
+    items: Res<Items>,
+    mut usable_items: ResMut<ItemRegistry>,
+) {
+    let entity = commands.spawn((ItemUses::default(), SpearUses)).id();
+    for spear in SPEARS {
+        usable_items.insert(items.get_id(spear).unwrap(), entity);
+    }
+}
+
+fn throw_spears(
+    mut commands: Commands,
+    items: Res<Items>,
+    models: Res<Models>,
+    mut player_query: Query<(&mut Inventory, &GlobalTransform, &Camera), With<Player>>,
+    mut spear_uses: Query<&mut ItemUses, (With<SpearUses>, Changed<ItemUses>)>,
+) {
+    let Ok(mut uses) = spear_uses.single_mut() else {
+        return;
+    };
+
+    for player_entity in uses.read() {
+        let (mut inventory, transform, camera) = player_query.get_mut(player_entity).unwrap();
+
+        let item_stack = inventory.held_item_stack_mut().take(1);
+        if item_stack.is_empty() {
+            continue;
+        }
+
+        let display = ItemDisplay::new(&items, &models, &item_stack, 0.25, 0.75);
+        let velocity = camera.forward() * THROW_SPEED;
+        let mut spear_transform = Transform {
+            translation: transform.translation() + camera.translation,
+            scale: DVec3::splat(display.scale),
+            ..default()
+        };
+        spear_transform.look_to(velocity, DVec3::Y);
+
+        commands.spawn((
+            ThrownSpear {
+                item_stack,
+                owner: player_entity,
+                velocity,
+                state: SpearState::Flying,
+                return_timer: None,
+            },
+            display.model,
+            spear_transform,
+        ));
+    }
+}
+
+fn fly(
+    time: Res<Time>,
+    items: Res<Items>,
+    world_map: Res<WorldMap>,
+    model_map: Res<ModelMap>,
+    mut spears: Query<(&mut ThrownSpear, &mut Transform)>,
+    targets: Query<
+        (Entity, &Transform, &Collider, Has<Player>),
+        (Or<(With<Player>, With<Mob>)>, Without<ThrownSpear>),
+    >,
+    mut mob_damage_events: MessageWriter<MobDamageEvent>,
+    mut player_damage_events: MessageWriter<PlayerDamageEvent>,
+) {
+    for (mut spear, mut transform) in spears.iter_mut() {
+        if spear.state != SpearState::Flying {
+            continue;
+        }
+
+        transform.look_to(spear.velocity, DVec3::Y);
+
+        let max_distance = (spear.velocity * time.delta_secs_f64()).length();
+
+        // The closest mob or player in the spear's path
+        let mut closest_hit: Option<(Entity, f64, bool)> = None;
+        for chunk_position in ChunkPosition::from(transform.translation).neighbourhood() {
+            for (entity, target_transform, collider, is_player) in
+                targets.iter_many(model_map.iter_entities(&chunk_position))
+            {
+                if entity == spear.owner {
+                    continue;
+                }
+
+                let Some((distance, _)) = collider.ray_intersection(target_transform, &transform)
+                else {
+                    continue;
+                };
+
+                if distance <= max_distance
+                    && closest_hit.is_none_or(|(_, closest, _)| distance < closest)
+                {
+                    closest_hit = Some((entity, distance, is_player));
+                }
+            }
+        }
+
+        if let Some((entity, distance, is_player)) = closest_hit {
+            let damage = items
+                .get_config(&spear.item_stack.item().unwrap().id)
+                .properties
+                .get("damage")
+                .and_then(|damage| damage.as_u64())
+                .map(|damage| damage as u32)
+                .unwrap_or(DEFAULT_DAMAGE);
+
+            if is_player {
+                player_damage_events.write(PlayerDamageEvent {
+                    player_entity: entity,
+                    damage,
+                    knock_back: Some(spear.velocity.normalize() * 10.0),
+                });
+            } else {
+                mob_damage_events.write(MobDamageEvent {
+                    mob_entity: entity,
+                    damage,
+                    attacker: Some(spear.owner),
+                    knock_back: Some(spear.velocity.normalize() * 10.0),
+                });
+            }
+
+            transform.translation += spear.velocity.normalize() * distance;
+            spear.hit(SpearState::HitEntity);
+            continue;
+        }
+
+        let blocks = Blocks::get();
+        let mut friction = DVec3::ZERO;
+        let mut raycast = world_map.raycast(&transform, max_distance);
+        while let Some(block_id) = raycast.next_block() {
+            let block_config = blocks.get_config(&block_id);
+            if let Some(drag) = block_config.drag() {
+                friction = friction.max(drag);
+                continue;
+            }
+
+            // Leave the tip of the spear in the block
+            transform.translation += spear.velocity.normalize() * (raycast.distance() + 0.2);
+            spear.hit(SpearState::Stuck(raycast.position()));
+            break;
+        }
+
+        if spear.state != SpearState::Flying {
+            continue;
+        }
+
+        transform.translation += spear.velocity * time.delta_secs_f64();
+
+        let mass = 10.0;
+        spear.velocity.y -= 14.0 * time.delta_secs_f64();
+        spear.velocity *= (-friction / mass * time.delta_secs_f64()).exp();
+    }
+}
+
+fn return_to_owner(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut owners: Query<(&mut Inventory, &GlobalTransform, &Camera), With<Player>>,
+    mut spears: Query<(Entity, &mut ThrownSpear, &mut Transform)>,
+) {
+    for (spear_entity, mut spear, mut transform) in spears.iter_mut() {
+        let Some(timer) = &mut spear.return_timer else {
+            // Spears without the enchantment that hit an entity fall to the ground.
+            if spear.state == SpearState::HitEntity {
+                commands.entity(spear_entity).despawn();
+                commands.spawn((
+                    DroppedItem::new(std::mem::take(&mut spear.item_stack)),
+                    Transform::from_translation(transform.translation),
+                ));
+            }
+            continue;
+        };
+
+        timer.tick(time.delta());
+        if !timer.is_finished() {
+            continue;
+        }
+
+        let Ok((mut inventory, owner_transform, camera)) = owners.get_mut(spear.owner) else {
+            // The owner left, leave the spear for someone else.
+            commands.entity(spear_entity).despawn();
+            commands.spawn((
+                DroppedItem::new(std::mem::take(&mut spear.item_stack)),
+                Transform::from_translation(transform.translation),
+            ));
+            continue;
+        };
+
+        spear.state = SpearState::Returning;
+
+        // Fly through anything in the way, aiming for the owner's chest.
+        let target = owner_transform.translation() + camera.translation * 0.8;
+        let direction = (target - transform.translation).normalize_or_zero();
+        let step = RETURN_SPEED * time.delta_secs_f64();
+
+        if transform.translation.distance(target) <= step.max(0.5) {
+            inventory.insert(&mut spear.item_stack);
+            commands.entity(spear_entity).despawn();
+
+            if !spear.item_stack.is_empty() {
+                commands.spawn((
+                    DroppedItem::new(std::mem::take(&mut spear.item_stack)),
+                    Transform::from_translation(target),
+                ));
+            }
+            continue;
+        }
+
+        transform.translation += direction * step;
+        transform.look_to(-direction, DVec3::Y);
+    }
+}
+
+// Spears stuck in the terrain are picked up by walking up to them.
+fn pick_up_spears(
+    mut commands: Commands,
+    mut players: Query<(&mut Inventory, &GlobalTransform, &Health, &GameMode), With<Player>>,
+    mut spears: Query<(Entity, &mut ThrownSpear, &Transform)>,
+) {
+    for (spear_entity, mut spear, transform) in spears.iter_mut() {
+        if !matches!(spear.state, SpearState::Stuck(_)) || spear.is_returning() {
+            continue;
+        }
+
+        for (mut inventory, player_transform, health, game_mode) in players.iter_mut() {
+            if health.is_dead()
+                || *game_mode == GameMode::Spectator
+                || player_transform
+                    .translation()
+                    .distance(transform.translation)
+                    > PICKUP_DISTANCE
+            {
+                continue;
+            }
+
+            inventory.insert(&mut spear.item_stack);
+            if spear.item_stack.is_empty() {
+                commands.entity(spear_entity).despawn();
+                break;
+            }
+        }
+    }
+}
+
+// When the block a spear is stuck in is removed, the spear drops.
+fn release_stuck_spears(
+    mut commands: Commands,
+    mut changed_blocks: MessageReader<ChangedBlockEvent>,
+    mut spears: Query<(Entity, &mut ThrownSpear, &Transform)>,
+) {
+    for changed_block in changed_blocks.read() {
+        for (spear_entity, mut spear, transform) in spears.iter_mut() {
+            if spear.state != SpearState::Stuck(changed_block.position) || spear.is_returning() {
+                continue;
+            }
+
+            commands.entity(spear_entity).despawn();
+            commands.spawn((
+                DroppedItem::new(std::mem::take(&mut spear.item_stack)),
+                Transform::from_translation(transform.translation),
+            ));
+        }
+    }
+}
@@ -0,0 +1,116 @@
+use std::collections::HashMap;
+
+use fmc::{
+    blocks::{BlockId, Blocks},
+    items::{ItemId, Items},
+    prelude::*,
+};
+use serde::Deserialize;
+
+const TOOL_TIERS_PATH: &str = "./assets/server/tool_tiers.json";
+
+/// Some blocks need a tool of a certain tier to drop anything, like diamond ore needing at least
+/// an iron pickaxe. The tiers of the tools and what the blocks need are read from
+/// `tool_tiers.json`.
+pub struct ToolTierPlugin;
+impl Plugin for ToolTierPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Startup, load_tool_tiers);
+    }
+}
+
+#[derive(Deserialize, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "lowercase")]
+pub enum ToolTier {
+    Wood,
+    Stone,
+    Iron,
+}
+
+impl ToolTier {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Wood => "Wood",
+            Self::Stone => "Stone",
+            Self::Iron => "Iron",
+        }
+    }
+}
+
+#[derive(Resource)]
+pub struct ToolTiers {
+    tools: HashMap<ItemId, ToolTier>,
+    blocks: HashMap<BlockId, ToolTier>,
+}
+
+impl ToolTiers {
+    /// If the block drops its item when broken with the tool. This only checks the tier, the
+    /// block config still decides if the tool is the right kind.
+    pub fn can_harvest(&self, block_id: BlockId, tool: Option<ItemId>) -> bool {
+        let Some(required) = self.blocks.get(&block_id) else {
+            return true;
+        };
+
+        tool.and_then(|item_id| self.tools.get(&item_id))
+            .is_some_and(|tier| tier >= required)
+    }
+
+    /// The tier of the tool, None if the item isn't a tool.
+    pub fn tool_tier(&self, item_id: ItemId) -> Option<ToolTier> {
+        self.tools.get(&item_id).copied()
+    }
+}
+
+#[derive(Deserialize)]
+struct ToolTiersJson {
+    tools: HashMap<String, ToolTier>,
+    blocks: HashMap<String, ToolTier>,
+}
+
+fn load_tool_tiers(mut commands: Commands, items: Res<Items>) {
+    let file = match std::fs::File::open(TOOL_TIERS_PATH) {
+        Ok(f) => f,
+        Err(e) => panic!(
+            "Failed to open tool tiers at path: {}\nError: {}",
+            TOOL_TIERS_PATH, e
+        ),
+    };
+
+    let json: ToolTiersJson = match serde_json::from_reader(file) {
+        Ok(t) => t,
+        Err(e) => panic!(
+            "Failed to read tool tiers at path: {}\nError: {}",
+            TOOL_TIERS_PATH, e
+        ),
+    };
+
+    let blocks = Blocks::get();
+
+    let tools = json
+        .tools
+        .into_iter()
+        .map(|(name, tier)| match items.get_id(&name) {
+            Some(item_id) => (item_id, tier),
+            None => panic!(
+                "Failed to read the tool tiers, there is no item with the name '{}'",
+                name
+            ),
+        })
+        .collect();
+
+    let blocks = json
+        .blocks
+        .into_iter()
+        .map(|(name, tier)| {
+            if !blocks.contains_block(&name) {
+                panic!(
+                    "Failed to read the tool tiers, there is no block with the name '{}'",
+                    name
+                );
+            }
+            (blocks.get_id(&name), tier)
+        })
+        .collect();
+
+    commands.insert_resource(ToolTiers { tools, blocks });
+}
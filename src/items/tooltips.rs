@@ -0,0 +1,88 @@
+use fmc::{
+    bevy::ecs::system::SystemParam,
+    items::{ItemStack, Items},
+    prelude::*,
+};
+
+use super::tool_tiers::ToolTiers;
+
+/// Builds the tooltips shown when hovering an item box. Every item box should get its tooltip
+/// from here, so the same item looks the same in all interfaces.
+///
+/// The stats come from the item config: "damage", "attack_speed", "arrow_damage", "healing" and
+/// "fuel", and the tier from `tool_tiers.json`. The description and enchantments are read from
+/// the item stack itself, as they can be different for each stack.
+#[derive(SystemParam)]
+pub struct ItemTooltips<'w> {
+    items: Res<'w, Items>,
+    tool_tiers: Res<'w, ToolTiers>,
+}
+
+impl ItemTooltips<'_> {
+    /// The tooltip of the item stack, one stat per line. None if there is nothing to show.
+    pub fn get(&self, item_stack: &ItemStack) -> Option<String> {
+        let item = item_stack.item()?;
+        let properties = &self.items.get_config(&item.id).properties;
+        let mut lines = Vec::new();
+
+        if let Some(description) = item.properties["description"].as_str() {
+            lines.push(description.to_owned());
+        }
+
+        if let Some(name) = item.properties["name"].as_str() {
+            lines.push(format!("Named \"{}\"", name));
+        }
+
+        if let Some(enchantments) = item.properties["enchantments"].as_object() {
+            for (name, level) in enchantments {
+                let Some(level) = level.as_u64().filter(|level| *level > 0) else {
+                    continue;
+                };
+                lines.push(format!("{} {}", capitalize(name), roman_numeral(level)));
+            }
+        }
+
+        for (property, label) in [
+            ("damage", "Damage"),
+            ("attack_speed", "Attack speed"),
+            ("arrow_damage", "Arrow damage"),
+            ("healing", "Heals"),
+        ] {
+            if let Some(value) = properties.get(property).and_then(|value| value.as_f64()) {
+                lines.push(format!("{}: {}", label, value));
+            }
+        }
+
+        if let Some(tier) = self.tool_tiers.tool_tier(item.id) {
+            lines.push(format!("Tier: {}", tier.name()));
+        }
+
+        if let Some(fuel) = properties.get("fuel").and_then(|fuel| fuel.as_f64()) {
+            lines.push(format!("Burns for {} seconds", fuel));
+        }
+
+        if lines.is_empty() {
+            None
+        } else {
+            Some(lines.join("\n"))
+        }
+    }
+}
+
+fn capitalize(name: &str) -> String {
+    let name = name.replace('_', " ");
+    let mut chars = name.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => name,
+    }
+}
+
+// Enchantment levels are shown as roman numerals, levels that are too high for them as numbers.
+fn roman_numeral(level: u64) -> String {
+    const NUMERALS: [&str; 10] = ["I", "II", "III", "IV", "V", "VI", "VII", "VIII", "IX", "X"];
+    NUMERALS
+        .get(level as usize - 1)
+        .map(|numeral| numeral.to_string())
+        .unwrap_or_else(|| level.to_string())
+}
@@ -1,13 +1,23 @@
-mod assets;
+pub mod assets;
 pub mod chat;
+pub mod console;
+pub mod database_io;
 pub mod explosions;
 pub mod items;
+pub mod metrics;
 pub mod mobs;
+pub mod nameplates;
 pub mod players;
+pub mod reload;
+pub mod scripting;
 pub mod settings;
 pub mod skybox;
+pub mod weather;
 pub mod world;
 
+#[cfg(test)]
+mod testing;
+
 pub use fmc;
 
 pub mod prelude {
@@ -15,7 +25,10 @@ pub mod prelude {
     pub use fmc::prelude::*;
 }
 
-use fmc::bevy::app::{PluginGroup, PluginGroupBuilder};
+use fmc::bevy::{
+    app::{PluginGroup, PluginGroupBuilder},
+    log::LogPlugin,
+};
 pub struct DefaultPlugins;
 impl PluginGroup for DefaultPlugins {
     fn build(self) -> fmc::bevy::app::PluginGroupBuilder {
@@ -23,14 +36,25 @@ impl PluginGroup for DefaultPlugins {
         group
             // This must run first so all the expected assets are present
             .add(assets::ExtractBundledAssetsPlugin)
-            .add_group(fmc::DefaultPlugins)
+            .add(assets::AssetErrorsPlugin)
+            .add_group(fmc::DefaultPlugins.set(LogPlugin {
+                custom_layer: console::log_layer,
+                ..Default::default()
+            }))
             .add(settings::SettingsPlugin)
+            .add(database_io::DatabaseIoPlugin)
             .add(items::ItemPlugin)
             .add(players::PlayerPlugin)
             .add(world::WorldPlugin)
             .add(skybox::SkyPlugin)
+            .add(weather::WeatherPlugin)
             .add(mobs::MobsPlugin)
             .add(chat::ChatPlugin)
+            .add(console::ConsolePlugin)
             .add(explosions::ExplosionsPlugin)
+            .add(nameplates::NameplatePlugin)
+            .add(metrics::MetricsPlugin)
+            .add(scripting::ScriptingPlugin)
+            .add(reload::ReloadPlugin)
     }
 }
@@ -7,5 +7,13 @@ fn main() {
         std::env::set_current_dir("server").unwrap();
     }
 
-    App::new().add_plugins(fmc_173::DefaultPlugins).run();
+    let mut app = App::new();
+    app.add_plugins(fmc_173::DefaultPlugins);
+
+    // Checks the terrain generation against its snapshots and exits, instead of running the server.
+    if let Some(snapshots) = fmc_173::world::TerrainSnapshotsPlugin::from_args() {
+        app.add_plugins(snapshots);
+    }
+
+    app.run();
 }
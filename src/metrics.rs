@@ -0,0 +1,273 @@
+use std::{
+    collections::VecDeque,
+    fmt::Write as _,
+    io::{Read, Write},
+    net::TcpListener,
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicU64, Ordering},
+    },
+    time::{Duration, Instant},
+};
+
+use fmc::{players::Player, prelude::*};
+
+use crate::{items::DroppedItem, mobs::Mob, settings::Settings};
+
+/// Collects numbers about how the server is doing: how long ticks take, how many entities there
+/// are and how long chunks take to generate.
+///
+/// Operators can see a summary with `/metrics`. If `metrics-address` is set in the settings, the
+/// same numbers are served in the Prometheus text format at that address.
+///
+/// Bevy doesn't time the systems one by one, so only the tick as a whole is timed, from the start
+/// of [First] to the end of [Last].
+pub struct MetricsPlugin;
+impl Plugin for MetricsPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(Metrics::default())
+            .add_systems(Startup, start_endpoint)
+            .add_systems(First, start_tick)
+            .add_systems(Last, (end_tick, count_entities, update_endpoint).chain());
+    }
+}
+
+/// How many ticks the tick times are averaged over
+const TICK_SAMPLES: usize = 100;
+/// Seconds between each time the numbers served by the endpoint are updated
+const ENDPOINT_INTERVAL: f32 = 1.0;
+/// How long the endpoint waits for a request before giving up on the connection. Requests are
+/// answered one at a time, so a client that never sends anything would block the others.
+const ENDPOINT_TIMEOUT: Duration = Duration::from_secs(5);
+
+static CHUNKS_GENERATED: AtomicU64 = AtomicU64::new(0);
+static CHUNK_GENERATION_NANOS: AtomicU64 = AtomicU64::new(0);
+
+/// Add a generated chunk to the metrics. The terrain is generated off the main thread, so this
+/// is kept outside of the [Metrics] resource.
+pub fn record_chunk_generation(duration: Duration) {
+    CHUNKS_GENERATED.fetch_add(1, Ordering::Relaxed);
+    CHUNK_GENERATION_NANOS.fetch_add(duration.as_nanos() as u64, Ordering::Relaxed);
+}
+
+#[derive(Resource, Default)]
+pub struct Metrics {
+    tick_start: Option<Instant>,
+    /// How long the latest ticks took to run, the newest last
+    tick_times: VecDeque<Duration>,
+    players: usize,
+    mobs: usize,
+    dropped_items: usize,
+    entities: usize,
+}
+
+impl Metrics {
+    fn average_tick_time(&self) -> Duration {
+        if self.tick_times.is_empty() {
+            return Duration::ZERO;
+        }
+        self.tick_times.iter().sum::<Duration>() / self.tick_times.len() as u32
+    }
+
+    fn max_tick_time(&self) -> Duration {
+        self.tick_times.iter().max().copied().unwrap_or_default()
+    }
+
+    fn chunk_generation() -> (u64, Duration) {
+        let count = CHUNKS_GENERATED.load(Ordering::Relaxed);
+        let nanos = CHUNK_GENERATION_NANOS.load(Ordering::Relaxed);
+        let average = if count == 0 {
+            Duration::ZERO
+        } else {
+            Duration::from_nanos(nanos / count)
+        };
+        (count, average)
+    }
+
+    /// A few lines to show in the chat
+    pub fn summary(&self) -> Vec<String> {
+        let (chunks, chunk_time) = Self::chunk_generation();
+        vec![
+            format!(
+                "Tick: {:.2}ms average, {:.2}ms max over the last {} ticks",
+                self.average_tick_time().as_secs_f64() * 1000.0,
+                self.max_tick_time().as_secs_f64() * 1000.0,
+                self.tick_times.len()
+            ),
+            format!(
+                "Entities: {} total, {} players, {} mobs, {} dropped items",
+                self.entities, self.players, self.mobs, self.dropped_items
+            ),
+            format!(
+                "Chunks generated: {}, {:.2}ms on average",
+                chunks,
+                chunk_time.as_secs_f64() * 1000.0
+            ),
+        ]
+    }
+
+    /// The metrics in the Prometheus text format
+    fn prometheus(&self) -> String {
+        let (chunks, _) = Self::chunk_generation();
+        let chunk_seconds = CHUNK_GENERATION_NANOS.load(Ordering::Relaxed) as f64 / 1_000_000_000.0;
+
+        let mut text = String::new();
+        let mut metric = |name: &str, kind: &str, help: &str, value: f64| {
+            let _ = writeln!(text, "# HELP {} {}", name, help);
+            let _ = writeln!(text, "# TYPE {} {}", name, kind);
+            let _ = writeln!(text, "{} {}", name, value);
+        };
+
+        metric(
+            "fmc_tick_seconds_average",
+            "gauge",
+            "Average time spent running a tick",
+            self.average_tick_time().as_secs_f64(),
+        );
+        metric(
+            "fmc_tick_seconds_max",
+            "gauge",
+            "Longest tick among the latest ticks",
+            self.max_tick_time().as_secs_f64(),
+        );
+        metric(
+            "fmc_entities",
+            "gauge",
+            "Entities in the world",
+            self.entities as f64,
+        );
+        metric(
+            "fmc_players",
+            "gauge",
+            "Players online",
+            self.players as f64,
+        );
+        metric("fmc_mobs", "gauge", "Mobs in the world", self.mobs as f64);
+        metric(
+            "fmc_dropped_items",
+            "gauge",
+            "Dropped items in the world",
+            self.dropped_items as f64,
+        );
+        metric(
+            "fmc_chunks_generated_total",
+            "counter",
+            "Chunks generated since the server started",
+            chunks as f64,
+        );
+        metric(
+            "fmc_chunk_generation_seconds_total",
+            "counter",
+            "Time spent generating chunks since the server started",
+            chunk_seconds,
+        );
+
+        text
+    }
+}
+
+fn start_tick(mut metrics: ResMut<Metrics>) {
+    metrics.tick_start = Some(Instant::now());
+}
+
+fn end_tick(mut metrics: ResMut<Metrics>) {
+    let Some(start) = metrics.tick_start.take() else {
+        return;
+    };
+
+    if metrics.tick_times.len() == TICK_SAMPLES {
+        metrics.tick_times.pop_front();
+    }
+    metrics.tick_times.push_back(start.elapsed());
+}
+
+fn count_entities(
+    mut metrics: ResMut<Metrics>,
+    entities: Query<()>,
+    players: Query<(), With<Player>>,
+    mobs: Query<(), With<Mob>>,
+    dropped_items: Query<(), With<DroppedItem>>,
+) {
+    metrics.entities = entities.iter().count();
+    metrics.players = players.iter().count();
+    metrics.mobs = mobs.iter().count();
+    metrics.dropped_items = dropped_items.iter().count();
+}
+
+/// The latest metrics in the Prometheus format, shared with the thread that serves them.
+#[derive(Resource)]
+struct MetricsEndpoint(Arc<Mutex<String>>);
+
+fn start_endpoint(mut commands: Commands, settings: Res<Settings>) {
+    if settings.metrics_address.is_empty() {
+        return;
+    }
+
+    let listener = match TcpListener::bind(&settings.metrics_address) {
+        Ok(l) => l,
+        Err(e) => {
+            error!(
+                "Failed to serve metrics at address: {}\nError: {}",
+                settings.metrics_address, e
+            );
+            return;
+        }
+    };
+
+    let text = Arc::new(Mutex::new(String::new()));
+    let shared = text.clone();
+    std::thread::Builder::new()
+        .name("metrics".to_owned())
+        .spawn(move || {
+            for stream in listener.incoming() {
+                let Ok(mut stream) = stream else {
+                    continue;
+                };
+                if stream.set_read_timeout(Some(ENDPOINT_TIMEOUT)).is_err()
+                    || stream.set_write_timeout(Some(ENDPOINT_TIMEOUT)).is_err()
+                {
+                    continue;
+                }
+
+                // Whatever is asked for, the answer is the metrics.
+                let mut request = [0; 1024];
+                let _ = stream.read(&mut request);
+
+                let body = shared.lock().unwrap().clone();
+                let _ = write!(
+                    stream,
+                    "HTTP/1.1 200 OK\r\n\
+                    Content-Type: text/plain; version=0.0.4\r\n\
+                    Content-Length: {}\r\n\
+                    Connection: close\r\n\r\n{}",
+                    body.len(),
+                    body
+                );
+            }
+        })
+        .expect("Failed to start the metrics thread");
+
+    commands.insert_resource(MetricsEndpoint(text));
+}
+
+fn update_endpoint(
+    time: Res<Time>,
+    metrics: Res<Metrics>,
+    endpoint: Option<Res<MetricsEndpoint>>,
+    mut timer: Local<Timer>,
+) {
+    let Some(endpoint) = endpoint else {
+        return;
+    };
+
+    if timer.duration().is_zero() {
+        *timer = Timer::from_seconds(ENDPOINT_INTERVAL, TimerMode::Repeating);
+    }
+
+    timer.tick(time.delta());
+    if !timer.just_finished() {
+        return;
+    }
+
+    *endpoint.0.lock().unwrap() = metrics.prometheus();
+}
@@ -0,0 +1,79 @@
+use fmc::{
+    bevy::math::DVec3,
+    items::DropTable,
+    models::{Model, Models},
+    physics::{Collider, Physics, shapes::Aabb},
+    prelude::*,
+};
+
+use crate::players::HandHits;
+
+use super::{
+    Faction, MobConfig, MobHealth, MobRandomSound, MobSoundCollection, MobStats, Mobs, RandomMobs,
+    flying::Flier,
+};
+
+/// Bats flutter around in dark caves. They are only there for atmosphere, and don't drop anything.
+pub struct BatPlugin;
+impl Plugin for BatPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Startup, setup);
+    }
+}
+
+#[derive(Bundle)]
+struct BatBundle {
+    health: MobHealth,
+    physics: Physics,
+    collider: Collider,
+    hits: HandHits,
+    flier: Flier,
+    random_sound: MobRandomSound,
+}
+
+impl Default for BatBundle {
+    fn default() -> Self {
+        Self {
+            health: MobHealth::new(6),
+            physics: Physics::default(),
+            collider: Collider::Single(Aabb {
+                center: DVec3::new(0.0, 0.25, 0.0),
+                half_extents: DVec3::new(0.25, 0.25, 0.25),
+            }),
+            hits: HandHits::default(),
+            // Bats are fast and erratic
+            flier: Flier::new(5.0, 0.5, 0.5, 2.0),
+            random_sound: MobRandomSound::default(),
+        }
+    }
+}
+
+fn setup(models: Res<Models>, mut mobs: ResMut<Mobs>, mut random_mobs: ResMut<RandomMobs>) {
+    // TODO: Needs a model of its own, it is just a feather for now.
+    let model_id = models.get_config_by_name("feather").unwrap().id;
+
+    let spawn_function = move |commands: &mut EntityCommands| {
+        commands.insert((BatBundle::default(), Model::Asset(model_id)));
+    };
+
+    // TODO: Needs sounds of its own, these are borrowed from the spider.
+    let sounds = MobSoundCollection {
+        random: vec![
+            "mobs/spider/random_1.ogg".to_owned(),
+            "mobs/spider/random_2.ogg".to_owned(),
+        ],
+        damage: vec!["mobs/spider/random_3.ogg".to_owned()],
+        death: vec!["mobs/spider/death.ogg".to_owned()],
+    };
+
+    let mob_id = mobs.add_mob(MobConfig {
+        name: "bat".to_owned(),
+        spawn_function: Box::new(spawn_function),
+        sounds,
+        drop_table: DropTable::new(0.0, &[]).unwrap(),
+        faction: Faction::Passive,
+        stats: MobStats::default(),
+    });
+
+    random_mobs.add_ambient(3, mob_id);
+}
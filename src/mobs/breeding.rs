@@ -0,0 +1,177 @@
+use fmc::{bevy::math::DVec3, items::ItemId, players::Player, prelude::*, world::WorldMap};
+
+use crate::players::Inventory;
+
+use super::{Mob, MobHealth, MobInteraction, Mobs, pathfinding::PathFinder};
+
+/// Mobs that are [Breedable] are put in love mode by feeding them their food. Two mobs of the same
+/// kind that are in love near each other walk up to each other and make a baby.
+///
+/// Babies are smaller and grow up after a while, feeding them makes them grow up faster. Parents
+/// can't breed again until their cooldown is over.
+pub struct BreedingPlugin;
+impl Plugin for BreedingPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, (feed_mobs, breed, grow_babies).chain());
+    }
+}
+
+/// How long a mob stays in love after being fed
+const LOVE_DURATION: f32 = 30.0;
+/// How long the parents have to wait before they can breed again
+const BREEDING_COOLDOWN: f32 = 300.0;
+/// How long it takes for a baby to grow up
+const GROWTH_DURATION: f32 = 600.0;
+/// How much of the growth is skipped each time a baby is fed
+const FEEDING_GROWTH: f32 = 0.1;
+/// How much smaller babies are than grown mobs
+const BABY_SCALE: f64 = 0.5;
+/// How far apart two mobs in love can be to find each other
+const MATE_DISTANCE: f64 = 8.0;
+/// How close they have to get to make a baby
+const BREEDING_DISTANCE: f64 = 1.5;
+
+/// Lets a mob be bred by feeding it its food
+#[derive(Component)]
+pub struct Breedable {
+    pub food: ItemId,
+}
+
+#[derive(Component)]
+struct InLove(Timer);
+
+#[derive(Component)]
+struct BreedingCooldown(Timer);
+
+/// Inserted on mobs that are not grown up yet
+#[derive(Component)]
+pub struct Baby {
+    growth: Timer,
+}
+
+fn feed_mobs(
+    mut commands: Commands,
+    mut player_query: Query<&mut Inventory, With<Player>>,
+    mut mob_query: Query<(
+        &Breedable,
+        &MobHealth,
+        Option<&mut Baby>,
+        Has<InLove>,
+        Has<BreedingCooldown>,
+    )>,
+    mut interaction_events: MessageReader<MobInteraction>,
+) {
+    for interaction in interaction_events.read() {
+        let Ok((breedable, health, baby, in_love, on_cooldown)) =
+            mob_query.get_mut(interaction.mob_entity)
+        else {
+            continue;
+        };
+        let Ok(mut inventory) = player_query.get_mut(interaction.player_entity) else {
+            continue;
+        };
+
+        let held_item_stack = inventory.held_item_stack_mut();
+        if health.is_dead()
+            || !held_item_stack
+                .item()
+                .is_some_and(|item| item.id == breedable.food)
+        {
+            continue;
+        }
+
+        if let Some(mut baby) = baby {
+            held_item_stack.take(1);
+            baby.growth.tick(std::time::Duration::from_secs_f32(
+                GROWTH_DURATION * FEEDING_GROWTH,
+            ));
+        } else if !in_love && !on_cooldown {
+            held_item_stack.take(1);
+            commands
+                .entity(interaction.mob_entity)
+                .insert(InLove(Timer::from_seconds(LOVE_DURATION, TimerMode::Once)));
+        }
+    }
+}
+
+fn breed(
+    mut commands: Commands,
+    time: Res<Time>,
+    world_map: Res<WorldMap>,
+    mobs: Res<Mobs>,
+    mut lovers: Query<(Entity, &Mob, &mut InLove, &mut PathFinder, &Transform)>,
+    mut cooldowns: Query<(Entity, &mut BreedingCooldown)>,
+) {
+    for (entity, mut cooldown) in cooldowns.iter_mut() {
+        cooldown.0.tick(time.delta());
+        if cooldown.0.is_finished() {
+            commands.entity(entity).remove::<BreedingCooldown>();
+        }
+    }
+
+    let mut available = Vec::new();
+    for (entity, mob, mut in_love, _, transform) in lovers.iter_mut() {
+        in_love.0.tick(time.delta());
+        if in_love.0.is_finished() {
+            commands.entity(entity).remove::<InLove>();
+        } else {
+            available.push((entity, mob.id, transform.translation));
+        }
+    }
+
+    while let Some((entity, mob_id, position)) = available.pop() {
+        let Some(index) = available.iter().position(|(_, other_id, other_position)| {
+            *other_id == mob_id && other_position.distance(position) < MATE_DISTANCE
+        }) else {
+            continue;
+        };
+        let (partner, _, partner_position) = available.swap_remove(index);
+
+        if position.distance(partner_position) > BREEDING_DISTANCE {
+            for (mob_entity, goal) in [(entity, partner_position), (partner, position)] {
+                let (_, _, _, mut path_finder, transform) = lovers.get_mut(mob_entity).unwrap();
+                if !path_finder.has_goal() {
+                    path_finder.find_path(&world_map, transform.translation, goal);
+                }
+            }
+            continue;
+        }
+
+        for parent in [entity, partner] {
+            commands
+                .entity(parent)
+                .remove::<InLove>()
+                .insert(BreedingCooldown(Timer::from_seconds(
+                    BREEDING_COOLDOWN,
+                    TimerMode::Once,
+                )));
+        }
+
+        let mut entity_commands = commands.spawn((
+            Mob { id: mob_id },
+            Baby {
+                growth: Timer::from_seconds(GROWTH_DURATION, TimerMode::Once),
+            },
+            Transform {
+                translation: (position + partner_position) / 2.0,
+                scale: DVec3::splat(BABY_SCALE),
+                ..default()
+            },
+        ));
+        (mobs.get_config(mob_id).spawn_function)(&mut entity_commands);
+    }
+}
+
+fn grow_babies(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut babies: Query<(Entity, &mut Baby, &mut Transform)>,
+) {
+    for (entity, mut baby, mut transform) in babies.iter_mut() {
+        baby.growth.tick(time.delta());
+        if baby.growth.is_finished() {
+            transform.scale = DVec3::ONE;
+            commands.entity(entity).remove::<Baby>();
+        }
+    }
+}
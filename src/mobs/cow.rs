@@ -20,12 +20,12 @@ use fmc::{
 
 use crate::{
     items::spawn_crates::MobCrates,
-    players::{GameMode, HandHits},
+    players::{GameMode, HandHits, HandInteractions, VehicleConfig},
 };
 
 use super::{
-    Mob, MobConfig, MobHead, MobHealth, MobSoundCollection, Mobs, RandomMobs, Wanderer,
-    pathfinding::PathFinder,
+    Faction, Mob, MobConfig, MobHead, MobHealth, MobSoundCollection, MobStats, Mobs, RandomMobs,
+    Wanderer, breeding::Breedable, pathfinding::PathFinder, saddles::Rideable,
 };
 
 pub struct CowPlugin;
@@ -72,7 +72,9 @@ struct CowBundle {
     path_finder: PathFinder,
     collider: Collider,
     hits: HandHits,
+    interactions: HandInteractions,
     wanderer: Wanderer,
+    rideable: Rideable,
 }
 
 impl Default for CowBundle {
@@ -95,7 +97,17 @@ impl Default for CowBundle {
                 DVec3::new(0.45, 1.4, 0.45),
             ),
             hits: HandHits::default(),
+            interactions: HandInteractions::default(),
             wanderer: Wanderer::new(2.0, 5.0),
+            rideable: Rideable {
+                seat_offset: DVec3::new(0.0, 1.2, 0.0),
+                vehicle: VehicleConfig {
+                    seat_height: 1.2,
+                    acceleration: 20.0,
+                    drag: 4.0,
+                    water_only: false,
+                },
+            },
         }
     }
 }
@@ -128,6 +140,8 @@ fn setup(
     let move_animation = model.animations["walk"];
     let idle_animation = model.animations["idle"];
 
+    let wheat = items.get_id("wheat").unwrap();
+
     let spawn_function = move |commands: &mut EntityCommands| {
         let mut animation_player = AnimationPlayer::default();
         animation_player.set_move_animation(Some(move_animation));
@@ -136,6 +150,7 @@ fn setup(
 
         commands.insert((
             CowBundle::default(),
+            Breedable { food: wheat },
             Model::Asset(cow_id),
             animation_player,
             MobHead::new(
@@ -152,9 +167,15 @@ fn setup(
     let drop_table = DropTable::new(1.0, &vec![(leather, 1.0, 0, 2)]).unwrap();
 
     let mob_id = mobs.add_mob(MobConfig {
+        name: "cow".to_owned(),
         spawn_function: Box::new(spawn_function),
         sounds,
         drop_table,
+        faction: Faction::Passive,
+        stats: MobStats {
+            knockback_resistance: 0.4,
+            ..default()
+        },
     });
 
     random_mobs.add_friendly(4, mob_id);
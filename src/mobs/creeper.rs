@@ -14,8 +14,8 @@ use fmc::{
 use crate::{explosions::ExplosionEvent, items::spawn_crates::MobCrates, players::HandHits};
 
 use super::{
-    Mob, MobConfig, MobHead, MobHealth, MobSoundCollection, Mobs, RandomMobs, Target, Wanderer,
-    pathfinding::PathFinder,
+    Faction, Mob, MobConfig, MobHead, MobHealth, MobSoundCollection, MobStats, Mobs, RandomMobs,
+    Target, Wanderer, pathfinding::PathFinder,
 };
 
 pub struct CreeperPlugin;
@@ -111,9 +111,12 @@ fn setup(
 
     let feather = items.get_id("feather").unwrap();
     let mob_id = mobs.add_mob(MobConfig {
+        name: "creeper".to_owned(),
         spawn_function: Box::new(spawn_function),
         sounds: sounds,
         drop_table: DropTable::new(1.0, &[(feather, 1.0, 0, 2)]).unwrap(),
+        faction: Faction::Hostile,
+        stats: MobStats::default(),
     });
 
     random_mobs.add_hostile(1, mob_id);
@@ -0,0 +1,131 @@
+use fmc::{
+    bevy::math::{DQuat, DVec3},
+    blocks::{BlockPosition, Blocks},
+    physics::Physics,
+    prelude::*,
+    random::{Rng, UniformDistribution},
+    world::WorldMap,
+};
+
+use super::MobHealth;
+
+// How far away from the mob blocks are felt
+const FEELER_LENGTH: f64 = 2.5;
+// How quickly the mob can change its velocity
+const AGILITY: f64 = 4.0;
+
+/// Flying locomotion for small mobs. Instead of finding a path, the mob picks a random direction
+/// to fly in and steers away from walls, floors and ceilings as it gets close to them.
+#[derive(Component)]
+pub struct Flier {
+    speed: f64,
+    // How much the mob prefers to fly level, 0.0 for any direction, 1.0 for only horizontally.
+    level: f64,
+    heading: DVec3,
+    timer: Timer,
+    min_time: f32,
+    max_time: f32,
+    rng: Rng,
+}
+
+impl Flier {
+    /// The mob flies at `speed`, changing direction every `min_time` to `max_time` seconds.
+    pub fn new(speed: f64, level: f64, min_time: f32, max_time: f32) -> Self {
+        let mut flier = Self {
+            speed,
+            level,
+            heading: DVec3::NEG_Z,
+            timer: Timer::default(),
+            min_time,
+            max_time,
+            rng: Rng::default(),
+        };
+        flier.change_heading();
+
+        flier
+    }
+
+    fn change_heading(&mut self) {
+        let yaw = self.rng.next_f32() as f64 * std::f64::consts::TAU;
+        let pitch = (self.rng.next_f32() as f64 - 0.5) * std::f64::consts::PI * (1.0 - self.level);
+        self.heading = DVec3::new(
+            yaw.sin() * pitch.cos(),
+            pitch.sin(),
+            yaw.cos() * pitch.cos(),
+        );
+
+        self.timer = Timer::from_seconds(
+            UniformDistribution::new(self.min_time, self.max_time).sample(&mut self.rng),
+            TimerMode::Once,
+        );
+    }
+}
+
+pub(super) fn fly(
+    world_map: Res<WorldMap>,
+    time: Res<Time>,
+    mut fliers: Query<(&mut Flier, &mut Physics, &mut Transform, &MobHealth)>,
+) {
+    let blocks = Blocks::get();
+    // Unloaded terrain counts as solid so mobs don't fly off into it.
+    let is_solid = |position: DVec3| match world_map.get_block(BlockPosition::from(position)) {
+        Some(block_id) => blocks.get_config(&block_id).is_solid(),
+        None => true,
+    };
+
+    for (mut flier, mut physics, mut transform, health) in fliers.iter_mut() {
+        // Dead mobs fall to the ground
+        if health.is_dead() {
+            continue;
+        }
+
+        flier.timer.tick(time.delta());
+        if flier.timer.just_finished() {
+            flier.change_heading();
+        }
+
+        let position = transform.translation;
+
+        // Turn around if flying straight into something, a few tries is enough to find a way out
+        // of most corners, the avoidance below takes care of the rest.
+        for _ in 0..4 {
+            if !is_solid(position + flier.heading * FEELER_LENGTH) {
+                break;
+            }
+            flier.change_heading();
+        }
+
+        // Push away from nearby blocks, the closer the block the harder the push.
+        let mut avoidance = DVec3::ZERO;
+        for direction in [
+            DVec3::X,
+            DVec3::NEG_X,
+            DVec3::Y,
+            DVec3::NEG_Y,
+            DVec3::Z,
+            DVec3::NEG_Z,
+        ] {
+            for step in 1..=5 {
+                let distance = step as f64 * 0.5;
+                if is_solid(position + direction * distance) {
+                    avoidance -= direction * (1.0 - distance / (FEELER_LENGTH + 0.5));
+                    break;
+                }
+            }
+        }
+
+        let desired_velocity = (flier.heading + avoidance * 2.0).normalize_or_zero() * flier.speed;
+
+        // Gravity still acts on the mob, steering towards the desired velocity every tick cancels
+        // it out, but lets it sag a little, which makes it bob as it flies.
+        let blend = (time.delta_secs_f64() * AGILITY).min(1.0);
+        physics.velocity = physics.velocity.lerp(desired_velocity, blend);
+
+        let horizontal = desired_velocity.with_y(0.0);
+        if horizontal.length_squared() > 0.01 {
+            let rotation = DQuat::from_rotation_arc(DVec3::NEG_Z, horizontal.normalize());
+            let max_rotation = time.delta_secs_f64() * std::f64::consts::TAU;
+            transform.rotation = transform.rotation.rotate_towards(rotation, max_rotation);
+        }
+    }
+}
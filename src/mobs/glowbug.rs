@@ -0,0 +1,80 @@
+use fmc::{
+    bevy::math::DVec3,
+    items::DropTable,
+    models::{Model, ModelColor, Models},
+    physics::{Collider, Physics, shapes::Aabb},
+    prelude::*,
+};
+
+use crate::players::HandHits;
+
+use super::{
+    Faction, MobConfig, MobHealth, MobRandomSound, MobSoundCollection, MobStats, Mobs, RandomMobs,
+    flying::Flier,
+};
+
+/// Glowbugs drift slowly through dark caves in small swarms. They are only there for atmosphere,
+/// and don't drop anything.
+pub struct GlowbugPlugin;
+impl Plugin for GlowbugPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Startup, setup);
+    }
+}
+
+#[derive(Bundle)]
+struct GlowbugBundle {
+    health: MobHealth,
+    physics: Physics,
+    collider: Collider,
+    hits: HandHits,
+    flier: Flier,
+    random_sound: MobRandomSound,
+    color: ModelColor,
+}
+
+impl Default for GlowbugBundle {
+    fn default() -> Self {
+        Self {
+            health: MobHealth::new(1),
+            physics: Physics::default(),
+            collider: Collider::Single(Aabb {
+                center: DVec3::new(0.0, 0.1, 0.0),
+                half_extents: DVec3::new(0.1, 0.1, 0.1),
+            }),
+            hits: HandHits::default(),
+            // Glowbugs drift slowly and mostly keep their height
+            flier: Flier::new(1.0, 0.8, 2.0, 5.0),
+            random_sound: MobRandomSound::default(),
+            color: ModelColor::new(0.8, 1.0, 0.3, 1.0),
+        }
+    }
+}
+
+fn setup(models: Res<Models>, mut mobs: ResMut<Mobs>, mut random_mobs: ResMut<RandomMobs>) {
+    // TODO: Needs a model of its own, it is just a tinted feather for now. It should also give off
+    // light, but light sources can only be blocks.
+    let model_id = models.get_config_by_name("feather").unwrap().id;
+
+    let spawn_function = move |commands: &mut EntityCommands| {
+        commands.insert((GlowbugBundle::default(), Model::Asset(model_id)));
+    };
+
+    // TODO: Needs sounds of its own, the glass chime is a stand-in.
+    let sounds = MobSoundCollection {
+        random: vec!["glass_1.ogg".to_owned(), "glass_2.ogg".to_owned()],
+        damage: Vec::new(),
+        death: vec!["glass_2.ogg".to_owned()],
+    };
+
+    let mob_id = mobs.add_mob(MobConfig {
+        name: "glowbug".to_owned(),
+        spawn_function: Box::new(spawn_function),
+        sounds,
+        drop_table: DropTable::new(0.0, &[]).unwrap(),
+        faction: Faction::Passive,
+        stats: MobStats::default(),
+    });
+
+    random_mobs.add_ambient(5, mob_id);
+}
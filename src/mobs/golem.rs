@@ -0,0 +1,353 @@
+use fmc::{
+    bevy::math::{DQuat, DVec3},
+    items::{DropTable, Items},
+    models::{AnimationPlayer, Model, Models},
+    physics::{Collider, Physics},
+    prelude::*,
+    random::{Rng, UniformDistribution},
+    world::WorldMap,
+};
+
+use crate::players::HandHits;
+
+use super::{
+    Faction, Mob, MobConfig, MobDamageEvent, MobHealth, MobId, MobSoundCollection, MobStats, Mobs,
+    pathfinding::PathFinder, villager::Villages,
+};
+
+/// A large mob that defends villages from hostile mobs.
+pub struct GolemPlugin;
+impl Plugin for GolemPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Startup, setup).add_systems(
+            Update,
+            (
+                spawn_golems,
+                (patrol, hunt_hostile_mobs, follow_path, attack).chain(),
+            ),
+        );
+    }
+}
+
+/// A village needs this many villagers that have claimed a bed before a golem will spawn.
+const VILLAGERS_PER_GOLEM: usize = 4;
+/// How far from the village center the golem patrols.
+const PATROL_RADIUS: i32 = 12;
+/// Hostile mobs within this distance of the golem are attacked.
+const AGGRO_DISTANCE: f64 = 16.0;
+const ATTACK_COOLDOWN: f32 = 1.5;
+
+#[derive(Resource)]
+struct GolemMobId(MobId);
+
+#[derive(Component)]
+struct Golem {
+    // Where the golem first appeared, it patrols around this point.
+    post: Option<DVec3>,
+    target: Option<Entity>,
+    patrol_timer: Timer,
+    attack_cooldown: Timer,
+    rng: Rng,
+}
+
+impl Golem {
+    fn new() -> Self {
+        Self {
+            post: None,
+            target: None,
+            patrol_timer: Timer::default(),
+            attack_cooldown: Timer::from_seconds(ATTACK_COOLDOWN, TimerMode::Once),
+            rng: Rng::default(),
+        }
+    }
+
+    fn reset_patrol_timer(&mut self) {
+        self.patrol_timer = Timer::from_seconds(
+            UniformDistribution::new(5.0, 10.0).sample(&mut self.rng),
+            TimerMode::Once,
+        );
+    }
+}
+
+#[derive(Bundle)]
+struct GolemBundle {
+    health: MobHealth,
+    golem: Golem,
+    physics: Physics,
+    path_finder: PathFinder,
+    collider: Collider,
+    hits: HandHits,
+}
+
+impl Default for GolemBundle {
+    fn default() -> Self {
+        Self {
+            health: MobHealth::new(100),
+            golem: Golem::new(),
+            physics: Physics::default(),
+            path_finder: PathFinder::new(3, 1, 1),
+            collider: Collider::from_min_max(
+                DVec3::new(-0.45, 0.0, -0.45),
+                DVec3::new(0.45, 2.6, 0.45),
+            ),
+            hits: HandHits::default(),
+        }
+    }
+}
+
+fn setup(mut commands: Commands, items: Res<Items>, models: Res<Models>, mut mobs: ResMut<Mobs>) {
+    // TODO: Needs a model of its own, it is just a big zombie for now.
+    let model = models.get_config_by_name("zombie").unwrap();
+    let model_id = model.id;
+
+    let move_animation = model.animations["wander"];
+    let idle_animation = model.animations["idle"];
+
+    let spawn_function = move |commands: &mut EntityCommands| {
+        let mut animation_player = AnimationPlayer::default();
+        animation_player.set_move_animation(Some(move_animation));
+        animation_player.set_idle_animation(Some(idle_animation));
+        animation_player.set_transition_time(0.5);
+
+        commands.insert((
+            GolemBundle::default(),
+            Model::Asset(model_id),
+            animation_player,
+        ));
+    };
+
+    let iron_ingot = items.get_id("iron_ingot").unwrap();
+    let mob_id = mobs.add_mob(MobConfig {
+        name: "golem".to_owned(),
+        spawn_function: Box::new(spawn_function),
+        sounds: MobSoundCollection::default(),
+        drop_table: DropTable::new(1.0, &[(iron_ingot, 1.0, 3, 5)]).unwrap(),
+        faction: Faction::Defender,
+        stats: MobStats {
+            knockback_resistance: 1.0,
+            armor: 10,
+            attack_damage: 14,
+        },
+    });
+
+    commands.insert_resource(GolemMobId(mob_id));
+}
+
+// Villages with enough villagers get a golem, one for every VILLAGERS_PER_GOLEM villagers that
+// have a bed.
+fn spawn_golems(
+    mut commands: Commands,
+    time: Res<Time>,
+    mobs: Res<Mobs>,
+    golem_mob_id: Res<GolemMobId>,
+    villages: Res<Villages>,
+    golems: Query<&GlobalTransform, With<Golem>>,
+    mut timer: Local<Timer>,
+) {
+    if timer.duration().is_zero() {
+        *timer = Timer::from_seconds(10.0, TimerMode::Repeating);
+    }
+
+    timer.tick(time.delta());
+    if !timer.just_finished() {
+        return;
+    }
+
+    for village in villages.iter() {
+        let wanted = village.beds.min(village.villagers) / VILLAGERS_PER_GOLEM;
+        if wanted == 0 {
+            continue;
+        }
+
+        let present = golems
+            .iter()
+            .filter(|transform| {
+                transform.translation().distance_squared(village.center)
+                    < (PATROL_RADIUS as f64 * 2.0).powi(2)
+            })
+            .count();
+
+        if present >= wanted {
+            continue;
+        }
+
+        let mut entity_commands = commands.spawn((
+            Mob { id: golem_mob_id.0 },
+            Transform::from_translation(village.center + DVec3::new(0.5, 1.0, 0.5)),
+        ));
+        (mobs.get_config(golem_mob_id.0).spawn_function)(&mut entity_commands);
+    }
+}
+
+fn patrol(
+    time: Res<Time>,
+    world_map: Res<WorldMap>,
+    mut golems: Query<(&mut Golem, &mut PathFinder, &GlobalTransform)>,
+) {
+    for (mut golem, mut path_finder, transform) in golems.iter_mut() {
+        let post = *golem.post.get_or_insert(transform.translation());
+
+        if golem.target.is_some() || path_finder.has_goal() {
+            continue;
+        }
+
+        golem.patrol_timer.tick(time.delta());
+        if !golem.patrol_timer.is_finished() {
+            continue;
+        }
+        golem.reset_patrol_timer();
+
+        let offset = UniformDistribution::new(-PATROL_RADIUS, PATROL_RADIUS);
+        let x = offset.sample(&mut golem.rng) as f64;
+        let z = offset.sample(&mut golem.rng) as f64;
+        path_finder.find_path(
+            &world_map,
+            transform.translation(),
+            post + DVec3::new(x, 0.0, z),
+        );
+    }
+}
+
+fn hunt_hostile_mobs(
+    world_map: Res<WorldMap>,
+    mobs: Query<(Entity, &Faction, &GlobalTransform, &MobHealth)>,
+    mut golems: Query<(&mut Golem, &mut PathFinder, &GlobalTransform)>,
+) {
+    for (mut golem, mut path_finder, golem_transform) in golems.iter_mut() {
+        let golem_position = golem_transform.translation();
+
+        // Drop the target when it dies or gets away
+        if let Some(target) = golem.target
+            && !matches!(
+                mobs.get(target),
+                Ok((_, _, transform, health))
+                    if !health.is_dead()
+                        && transform.translation().distance(golem_position) < AGGRO_DISTANCE
+            )
+        {
+            golem.target = None;
+        }
+
+        if golem.target.is_none() {
+            golem.target = mobs
+                .iter()
+                .filter(|(_, faction, transform, health)| {
+                    **faction == Faction::Hostile
+                        && !health.is_dead()
+                        && transform.translation().distance(golem_position) < AGGRO_DISTANCE
+                })
+                .min_by(|(_, _, a, _), (_, _, b, _)| {
+                    a.translation()
+                        .distance_squared(golem_position)
+                        .total_cmp(&b.translation().distance_squared(golem_position))
+                })
+                .map(|(entity, _, _, _)| entity);
+        }
+
+        let Some(target) = golem.target else {
+            continue;
+        };
+
+        let (_, _, target_transform, _) = mobs.get(target).unwrap();
+        path_finder.find_path(&world_map, golem_position, target_transform.translation());
+    }
+}
+
+// Formula for how much speed you need to reach a height
+// sqrt(2 * gravity * wanted height(1.4)) + some for air resistance
+const JUMP_VELOCITY: f64 = 9.0;
+const HUNTING_ACCELERATION: f64 = 25.0;
+const PATROL_ACCELERATION: f64 = 10.0;
+
+fn follow_path(
+    time: Res<Time>,
+    mut golems: Query<(
+        &MobHealth,
+        &Golem,
+        &mut PathFinder,
+        &mut Physics,
+        &mut Transform,
+    )>,
+) {
+    for (health, golem, mut path_finder, mut physics, mut transform) in golems.iter_mut() {
+        // Mob entities are kept for a little while after death to show a death pose
+        if health.is_dead() {
+            continue;
+        }
+
+        let Some(next_position) = path_finder.next_node(transform.translation) else {
+            continue;
+        };
+
+        let direction = (next_position - transform.translation)
+            .with_y(0.0)
+            .normalize();
+        let rotation = DQuat::from_rotation_arc(DVec3::NEG_Z, direction);
+        let max_rotation = time.delta_secs_f64() * std::f64::consts::PI;
+        transform.rotation = transform.rotation.rotate_towards(rotation, max_rotation);
+
+        if next_position.y - transform.translation.y > 0.1
+            && physics.is_against_wall()
+            && physics.is_grounded()
+        {
+            physics.velocity.y = JUMP_VELOCITY;
+        }
+
+        let mut acceleration = if golem.target.is_some() {
+            HUNTING_ACCELERATION
+        } else {
+            PATROL_ACCELERATION
+        };
+
+        if !physics.is_grounded() {
+            acceleration *= 0.1;
+        }
+
+        physics.acceleration.x += direction.x * acceleration;
+        physics.acceleration.z += direction.z * acceleration;
+    }
+}
+
+fn attack(
+    time: Res<Time>,
+    mobs: Res<Mobs>,
+    mut golems: Query<(Entity, &Mob, &mut Golem, &GlobalTransform)>,
+    targets: Query<&GlobalTransform, Without<Golem>>,
+    mut damage_events: MessageWriter<MobDamageEvent>,
+) {
+    for (golem_entity, mob, mut golem, golem_transform) in golems.iter_mut() {
+        golem.attack_cooldown.tick(time.delta());
+
+        let Some(target) = golem.target else {
+            continue;
+        };
+
+        let Ok(target_transform) = targets.get(target) else {
+            continue;
+        };
+
+        if !golem.attack_cooldown.is_finished()
+            || golem_transform
+                .translation()
+                .distance_squared(target_transform.translation())
+                > 6.25
+        {
+            continue;
+        }
+
+        golem.attack_cooldown.reset();
+
+        // Flings the target into the air
+        let horizontal = (target_transform.translation() - golem_transform.translation())
+            .xz()
+            .normalize_or_zero()
+            * 20.0;
+
+        damage_events.write(MobDamageEvent {
+            mob_entity: target,
+            damage: mobs.get_config(mob.id).stats.attack_damage,
+            attacker: Some(golem_entity),
+            knock_back: Some(DVec3::new(horizontal.x, 14.0, horizontal.y)),
+        });
+    }
+}
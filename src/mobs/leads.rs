@@ -0,0 +1,203 @@
+use fmc::{
+    bevy::math::DVec3,
+    blocks::{BlockPosition, Blocks},
+    items::{ItemStack, Items},
+    physics::Physics,
+    players::{Player, Target, Targets},
+    prelude::*,
+    world::WorldMap,
+};
+
+use crate::{
+    items::{DroppedItem, ItemRegistry, ItemUseSystems, ItemUses},
+    players::Inventory,
+};
+
+use super::{MobHealth, MobInteraction, pathfinding::PathFinder};
+
+/// Mobs are put on a lead by right clicking them while holding one, and follow the player holding
+/// it. Right clicking a block with a lead ties the player's leashed mobs to the block, and right
+/// clicking a leashed mob with a lead takes the lead off.
+///
+/// The lead breaks and drops if it is pulled too far, the block it is tied to is broken, or the
+/// player holding it leaves.
+pub struct LeadPlugin;
+impl Plugin for LeadPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Startup, register_leads).add_systems(
+            Update,
+            (leash_mobs, tie_leads.after(ItemUseSystems), pull_leashed),
+        );
+    }
+}
+
+/// How far the mob can stray before the lead starts pulling it
+const LEAD_LENGTH: f64 = 5.0;
+/// How far the mob can be pulled before the lead snaps
+const BREAK_DISTANCE: f64 = 12.0;
+/// Acceleration per block the lead is stretched past its length
+const SPRING: f64 = 8.0;
+
+/// What the other end of the lead is held by
+#[derive(Clone, Copy, PartialEq)]
+pub enum LeadHolder {
+    Player(Entity),
+    /// Tied to a block
+    Post(BlockPosition),
+}
+
+/// Inserted on mobs that are on a lead
+#[derive(Component)]
+pub struct Leashed {
+    pub holder: LeadHolder,
+}
+
+#[derive(Component)]
+struct LeadUses;
+
+fn register_leads(
+    mut commands: Commands,
+    items: Res<Items>,
+    mut item_registry: ResMut<ItemRegistry>,
+) {
+    let lead = items.get_id("lead").unwrap();
+    let entity = commands.spawn((ItemUses::default(), LeadUses)).id();
+    item_registry.insert(lead, entity);
+}
+
+fn drop_lead(commands: &mut Commands, items: &Items, position: DVec3) {
+    let lead = items.get_id("lead").unwrap();
+    commands.spawn((
+        DroppedItem::new(ItemStack::new(items.get_config(&lead), 1)),
+        Transform::from_translation(position),
+    ));
+}
+
+fn leash_mobs(
+    mut commands: Commands,
+    items: Res<Items>,
+    mut player_query: Query<(&mut Inventory, &GlobalTransform), With<Player>>,
+    mob_query: Query<(&MobHealth, Option<&Leashed>), With<PathFinder>>,
+    mut interaction_events: MessageReader<MobInteraction>,
+) {
+    let lead = items.get_id("lead").unwrap();
+
+    for interaction in interaction_events.read() {
+        let Ok((health, leashed)) = mob_query.get(interaction.mob_entity) else {
+            continue;
+        };
+        let Ok((mut inventory, transform)) = player_query.get_mut(interaction.player_entity) else {
+            continue;
+        };
+
+        if health.is_dead() {
+            continue;
+        }
+
+        let held_item_stack = inventory.held_item_stack_mut();
+        if !held_item_stack.item().is_some_and(|item| item.id == lead) {
+            continue;
+        }
+
+        if leashed.is_some() {
+            commands.entity(interaction.mob_entity).remove::<Leashed>();
+
+            let mut lead_stack = ItemStack::new(items.get_config(&lead), 1);
+            inventory.insert(&mut lead_stack);
+            if !lead_stack.is_empty() {
+                commands.spawn((
+                    DroppedItem::new(lead_stack),
+                    Transform::from_translation(transform.translation()),
+                ));
+            }
+        } else {
+            held_item_stack.take(1);
+            commands.entity(interaction.mob_entity).insert(Leashed {
+                holder: LeadHolder::Player(interaction.player_entity),
+            });
+        }
+    }
+}
+
+fn tie_leads(
+    player_query: Query<&Targets, With<Player>>,
+    mut leashed_query: Query<&mut Leashed>,
+    mut lead_uses: Query<&mut ItemUses, (With<LeadUses>, Changed<ItemUses>)>,
+) {
+    let Ok(mut uses) = lead_uses.single_mut() else {
+        return;
+    };
+
+    for player_entity in uses.read() {
+        let targets = player_query.get(player_entity).unwrap();
+
+        let blocks = Blocks::get();
+        let Some(Target::Block { block_position, .. }) =
+            targets.get_first_block(|block_id| blocks.get_config(block_id).is_solid())
+        else {
+            continue;
+        };
+
+        for mut leashed in leashed_query.iter_mut() {
+            if leashed.holder == LeadHolder::Player(player_entity) {
+                leashed.holder = LeadHolder::Post(*block_position);
+            }
+        }
+    }
+}
+
+fn pull_leashed(
+    mut commands: Commands,
+    world_map: Res<WorldMap>,
+    items: Res<Items>,
+    player_query: Query<&GlobalTransform, With<Player>>,
+    mut mob_query: Query<(
+        Entity,
+        &Leashed,
+        &MobHealth,
+        &Transform,
+        &mut Physics,
+        &mut PathFinder,
+    )>,
+) {
+    for (mob_entity, leashed, health, transform, mut physics, mut path_finder) in
+        mob_query.iter_mut()
+    {
+        let holder_position = match leashed.holder {
+            LeadHolder::Player(player_entity) => player_query
+                .get(player_entity)
+                .ok()
+                .map(|transform| transform.translation()),
+            LeadHolder::Post(block_position) => world_map
+                .get_block(block_position)
+                .filter(|block_id| Blocks::get().get_config(block_id).is_solid())
+                .map(|_| block_position.as_dvec3() + DVec3::splat(0.5)),
+        };
+
+        let distance = holder_position
+            .map(|position| position.distance(transform.translation))
+            .unwrap_or(f64::MAX);
+
+        if health.is_dead() || distance > BREAK_DISTANCE {
+            commands.entity(mob_entity).remove::<Leashed>();
+            drop_lead(
+                &mut commands,
+                &items,
+                transform.translation + DVec3::Y * 0.5,
+            );
+            continue;
+        }
+
+        if distance <= LEAD_LENGTH {
+            continue;
+        }
+
+        // Stop wandering off and let the lead drag it along
+        path_finder.clear();
+
+        let direction = (holder_position.unwrap() - transform.translation)
+            .with_y(0.0)
+            .normalize_or_zero();
+        physics.acceleration += direction * (distance - LEAD_LENGTH) * SPRING;
+    }
+}
@@ -1,4 +1,4 @@
-use std::{f32::consts::FRAC_PI_2, ops::Mul, time::Duration};
+use std::{collections::HashSet, f32::consts::FRAC_PI_2, ops::Mul, time::Duration};
 
 use fmc::{
     bevy::math::{DQuat, DVec2, DVec3},
@@ -20,16 +20,32 @@ use serde::{Deserialize, Serialize};
 
 use crate::{
     items::DroppedItem,
-    players::{GameMode, HandHits, HandSystems, Inventory},
+    players::{
+        AttackCooldown, FallDamage, GameMode, HandHits, HandInteractions, HandSystems, Inventory,
+        MeleeAttack, shared_connections::PlayerSubscriptions,
+    },
     skybox::Clock,
+    world::{budgets::ChunkBudgets, game_rules::GameRules, has_sky_access},
 };
 
+pub mod bat;
+pub mod breeding;
 pub mod cow;
 pub mod creeper;
 pub mod duck;
+mod flying;
+pub mod glowbug;
+pub mod golem;
+pub mod leads;
+pub mod name_tags;
 mod pathfinding;
+pub mod persistence;
+pub mod raids;
+pub mod saddles;
 pub mod skeleton;
+pub mod spawner;
 pub mod spider;
+pub mod villager;
 pub mod zombie;
 
 pub struct MobsPlugin;
@@ -37,25 +53,45 @@ impl Plugin for MobsPlugin {
     fn build(&self, app: &mut App) {
         app.insert_resource(Mobs::default())
             .insert_resource(RandomMobs::default())
+            .insert_resource(MobCaps::default())
             .add_message::<MobDamageEvent>()
+            .add_message::<MobDeathEvent>()
+            .add_message::<MobInteraction>()
             .add_plugins(duck::DuckPlugin)
             .add_plugins(zombie::ZombiePlugin)
             .add_plugins(skeleton::SkeletonPlugin)
             .add_plugins(cow::CowPlugin)
             .add_plugins(creeper::CreeperPlugin)
             .add_plugins(spider::SpiderPlugin)
+            .add_plugins(villager::VillagerPlugin)
+            .add_plugins(golem::GolemPlugin)
+            .add_plugins(bat::BatPlugin)
+            .add_plugins(glowbug::GlowbugPlugin)
+            .add_plugins(raids::RaidPlugin)
+            .add_plugins(saddles::SaddlePlugin)
+            .add_plugins(leads::LeadPlugin)
+            .add_plugins(breeding::BreedingPlugin)
+            .add_plugins(name_tags::NameTagPlugin)
+            .add_plugins(persistence::PersistencePlugin)
+            .add_plugins(spawner::SpawnerPlugin)
             .add_systems(
                 Update,
                 (
                     sync_mob_caps,
+                    update_mob_caps,
+                    assign_factions,
                     // spawn_hostile_random_mobs,
                     // spawn_friendly_random_mobs,
+                    spawn_ambient_random_mobs,
                     despawn_mobs,
+                    despawn_hostile_mobs,
                     handle_hand_hits.after(HandSystems),
+                    handle_hand_interactions.after(HandSystems),
                     damage_mobs,
                     play_random_sound,
                     look_around,
                     wander,
+                    flying::fly,
                     targeting,
                 ),
             );
@@ -71,9 +107,42 @@ pub struct Mob {
 }
 
 pub struct MobConfig {
+    pub name: String,
     pub spawn_function: Box<dyn Fn(&mut EntityCommands) + Send + Sync + 'static>,
     pub sounds: MobSoundCollection,
     pub drop_table: DropTable,
+    pub faction: Faction,
+    pub stats: MobStats,
+}
+
+/// How tough a mob is, and how hard it hits
+#[derive(Default)]
+pub struct MobStats {
+    /// How much of the knock back the mob ignores when it is damaged, from 0 to 1
+    pub knockback_resistance: f64,
+    /// Each point of armor reduces the damage taken by 4%, up to 80%
+    pub armor: u32,
+    /// Damage dealt by the mob's attacks
+    pub attack_damage: u32,
+}
+
+impl MobStats {
+    fn reduce_damage(&self, damage: u32) -> u32 {
+        let reduction = (self.armor as f32 * 0.04).min(0.8);
+        ((damage as f32 * (1.0 - reduction)).round() as u32).max(1)
+    }
+
+    fn reduce_knock_back(&self, knock_back: DVec3) -> DVec3 {
+        knock_back * (1.0 - self.knockback_resistance.clamp(0.0, 1.0))
+    }
+}
+
+/// Which side a mob is on. Hostile mobs attack players, and defenders attack hostile mobs.
+#[derive(Component, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Faction {
+    Passive,
+    Hostile,
+    Defender,
 }
 
 #[derive(Resource, Default)]
@@ -91,6 +160,10 @@ impl Mobs {
     pub fn get_config(&self, mob_id: MobId) -> &MobConfig {
         &self.configs[mob_id]
     }
+
+    pub fn get_id(&self, name: &str) -> Option<MobId> {
+        self.configs.iter().position(|config| config.name == name)
+    }
 }
 
 // A *loose* cap on how many mobs can be spawned near a player. Each player has its own MobCap.
@@ -100,11 +173,13 @@ impl Mobs {
 pub struct MobCap {
     friendly: u32,
     hostile: u32,
+    ambient: u32,
 }
 
 impl MobCap {
     const FRIENDLY_CAPACITY: u32 = 12;
     const HOSTILE_CAPACITY: u32 = 16;
+    const AMBIENT_CAPACITY: u32 = 8;
 
     fn at_hostile_capacity(&self) -> bool {
         self.hostile >= Self::HOSTILE_CAPACITY
@@ -113,6 +188,77 @@ impl MobCap {
     fn at_friendly_capacity(&self) -> bool {
         self.friendly >= Self::FRIENDLY_CAPACITY
     }
+
+    fn at_ambient_capacity(&self) -> bool {
+        self.ambient >= Self::AMBIENT_CAPACITY
+    }
+}
+
+/// How many chunks out from each player random mobs are spawned and kept, horizontally
+const SIMULATION_RADIUS: i32 = 4;
+
+/// Caps on the number of random mobs in the whole world. Each player brings the chunks around
+/// them, and the caps grow with the number of chunks so that players that are spread out get as
+/// many mobs each as a player that is alone, while players that are close together have to share.
+///
+/// This sits on top of the [MobCap] of each player, so crowded areas don't get more than the
+/// chunks around them can hold.
+#[derive(Resource, Default)]
+pub struct MobCaps {
+    hostile_cap: usize,
+    passive_cap: usize,
+    hostile: usize,
+    passive: usize,
+}
+
+impl MobCaps {
+    /// If another random mob of the type can be spawned
+    fn can_spawn(&self, mob_type: &RandomMobType) -> bool {
+        match mob_type {
+            RandomMobType::Hostile => self.hostile < self.hostile_cap,
+            RandomMobType::Friendly => self.passive < self.passive_cap,
+            // Ambient mobs only have the caps of the players
+            RandomMobType::Ambient => true,
+        }
+    }
+
+    /// Count a mob that was spawned this tick
+    fn add(&mut self, mob_type: &RandomMobType) {
+        match mob_type {
+            RandomMobType::Hostile => self.hostile += 1,
+            RandomMobType::Friendly => self.passive += 1,
+            RandomMobType::Ambient => (),
+        }
+    }
+}
+
+fn update_mob_caps(
+    mut mob_caps: ResMut<MobCaps>,
+    players: Query<&ChunkPosition, With<Player>>,
+    random_mobs: Query<&RandomMobType, Without<MobDespawn>>,
+) {
+    let mut columns = HashSet::new();
+    for chunk_position in players.iter() {
+        for x in -SIMULATION_RADIUS..=SIMULATION_RADIUS {
+            for z in -SIMULATION_RADIUS..=SIMULATION_RADIUS {
+                columns.insert((
+                    chunk_position.x + x * Chunk::SIZE as i32,
+                    chunk_position.z + z * Chunk::SIZE as i32,
+                ));
+            }
+        }
+    }
+
+    let columns_per_player = (SIMULATION_RADIUS * 2 + 1).pow(2) as usize;
+    let cap = |per_player: u32| (per_player as usize * columns.len()).div_ceil(columns_per_player);
+
+    mob_caps.hostile_cap = cap(MobCap::HOSTILE_CAPACITY);
+    mob_caps.passive_cap = cap(MobCap::FRIENDLY_CAPACITY);
+    mob_caps.hostile = 0;
+    mob_caps.passive = 0;
+    for mob_type in random_mobs.iter() {
+        mob_caps.add(mob_type);
+    }
 }
 
 // TODO: This should probably be within some simulation distance and not render distance
@@ -120,42 +266,59 @@ impl MobCap {
 // When players get within render distance of each other, their mob caps are synced so as to not
 // spawn double the mobs when they are close to each other.
 fn sync_mob_caps(
-    chunk_subscriptions: Res<ChunkSubscriptions>,
+    player_subscriptions: PlayerSubscriptions,
     mut mob_caps: Query<&mut MobCap>,
     chunk_positions: Query<&ChunkPosition, (With<Player>, Changed<ChunkPosition>)>,
 ) {
     for chunk_position in chunk_positions.iter() {
-        let Some(subscribers) = chunk_subscriptions.get_subscribers(&chunk_position) else {
+        let Some(subscribers) = player_subscriptions.get_players(&chunk_position) else {
             continue;
         };
+        let subscribers: Vec<Entity> = subscribers.collect();
 
         if subscribers.len() == 1 {
             continue;
         }
 
         let mut max = MobCap::default();
-        for player_cap in mob_caps.iter_many(subscribers) {
+        for player_cap in mob_caps.iter_many(&subscribers) {
             max.friendly = player_cap.friendly.max(max.friendly);
             max.hostile = player_cap.hostile.max(max.hostile);
+            max.ambient = player_cap.ambient.max(max.ambient);
         }
 
         for player_entity in subscribers {
-            let mut mob_cap = mob_caps.get_mut(*player_entity).unwrap();
-            *mob_cap = max;
+            if let Ok(mut mob_cap) = mob_caps.get_mut(player_entity) {
+                *mob_cap = max;
+            }
         }
     }
 }
 
+fn assign_factions(
+    mut commands: Commands,
+    mobs: Res<Mobs>,
+    added_mobs: Query<(Entity, &Mob), Added<Mob>>,
+) {
+    for (entity, mob) in added_mobs.iter() {
+        let config = mobs.get_config(mob.id);
+        commands.entity(entity).insert(config.faction);
+    }
+}
+
 #[derive(Component)]
 enum RandomMobType {
     Hostile,
     Friendly,
+    Ambient,
 }
 
 #[derive(Resource, Default)]
 pub struct RandomMobs {
     hostile: Vec<(u32, MobId)>,
     friendly: Vec<(u32, MobId)>,
+    // Mobs that spawn in dark caves, purely for atmosphere
+    ambient: Vec<(u32, MobId)>,
 }
 
 impl RandomMobs {
@@ -167,6 +330,10 @@ impl RandomMobs {
         self.friendly.push((count, mob_id));
     }
 
+    fn add_ambient(&mut self, count: u32, mob_id: MobId) {
+        self.ambient.push((count, mob_id));
+    }
+
     fn choose_friendly(&self, rng: &mut Rng) -> (u32, MobId) {
         let index = rng.next_usize() % self.friendly.len();
         self.friendly[index]
@@ -176,6 +343,11 @@ impl RandomMobs {
         let index = rng.next_usize() % self.hostile.len();
         self.hostile[index]
     }
+
+    fn choose_ambient(&self, rng: &mut Rng) -> (u32, MobId) {
+        let index = rng.next_usize() % self.ambient.len();
+        self.ambient[index]
+    }
 }
 
 fn spawn_friendly_random_mobs(
@@ -183,6 +355,8 @@ fn spawn_friendly_random_mobs(
     world_map: Res<WorldMap>,
     mobs: Res<Mobs>,
     random_mobs: Res<RandomMobs>,
+    mut budgets: ResMut<ChunkBudgets>,
+    mut mob_caps: ResMut<MobCaps>,
     mut player_caps: Query<(&mut MobCap, &ChunkPosition)>,
     mut rng: Local<Rng>,
 ) {
@@ -209,6 +383,10 @@ fn spawn_friendly_random_mobs(
         let surface_blocks = [grass, stone];
         let surface = Surface::new(chunk, &surface_blocks, air);
 
+        if !budgets.can_spawn_mob(&spawn_chunk) || !mob_caps.can_spawn(&RandomMobType::Friendly) {
+            continue;
+        }
+
         let (group_size, mob_id) = random_mobs.choose_friendly(&mut rng);
 
         let mob_config = mobs.get_config(mob_id);
@@ -232,9 +410,11 @@ fn spawn_friendly_random_mobs(
 
             (mob_config.spawn_function)(&mut entity_commands);
 
+            budgets.add_mob(spawn_chunk);
             mob_cap.friendly += 1;
+            mob_caps.add(&RandomMobType::Friendly);
 
-            if mob_cap.at_friendly_capacity() {
+            if mob_cap.at_friendly_capacity() || !mob_caps.can_spawn(&RandomMobType::Friendly) {
                 continue 'outer;
             }
 
@@ -250,9 +430,16 @@ fn spawn_hostile_random_mobs(
     mobs: Res<Mobs>,
     clock: Res<Clock>,
     random_mobs: Res<RandomMobs>,
+    game_rules: Res<GameRules>,
+    mut budgets: ResMut<ChunkBudgets>,
+    mut mob_caps: ResMut<MobCaps>,
     mut player_caps: Query<(&mut MobCap, &ChunkPosition)>,
     mut rng: Local<Rng>,
 ) {
+    if !game_rules.difficulty().allows_hostile_mobs() {
+        return;
+    }
+
     'outer: for (mut mob_cap, chunk_position) in player_caps.iter_mut() {
         if mob_cap.at_hostile_capacity() {
             continue;
@@ -289,6 +476,10 @@ fn spawn_hostile_random_mobs(
         let surface_blocks = [grass, stone];
         let surface = Surface::new(chunk, &surface_blocks, air);
 
+        if !budgets.can_spawn_mob(&spawn_chunk) || !mob_caps.can_spawn(&RandomMobType::Hostile) {
+            continue;
+        }
+
         let (group_size, mob_id) = random_mobs.choose_hostile(&mut rng);
 
         let mob_config = mobs.get_config(mob_id);
@@ -312,39 +503,183 @@ fn spawn_hostile_random_mobs(
 
             (mob_config.spawn_function)(&mut entity_commands);
 
+            budgets.add_mob(spawn_chunk);
             mob_cap.hostile += 1;
+            mob_caps.add(&RandomMobType::Hostile);
 
-            if mob_cap.at_hostile_capacity() {
+            if mob_cap.at_hostile_capacity() || !mob_caps.can_spawn(&RandomMobType::Hostile) {
                 continue 'outer;
             }
         }
     }
 }
 
+// How far from the player ambient mobs are spawned, they are not spawned closer than the minimum
+// so they aren't seen popping into existence.
+const AMBIENT_SPAWN_RANGE: i32 = 24;
+const AMBIENT_MIN_DISTANCE: i32 = 10;
+// How far away a torch has to be for a spot to be considered dark.
+const AMBIENT_DARKNESS_RADIUS: i32 = 7;
+
+// TODO: Light levels are only known to the client, so this only looks for torches.
+//
+/// If there is a light source close enough to light up the position.
+fn is_lit(world_map: &WorldMap, position: BlockPosition) -> bool {
+    let torch = Blocks::get().get_id("torch");
+
+    for x in -AMBIENT_DARKNESS_RADIUS..=AMBIENT_DARKNESS_RADIUS {
+        for y in -AMBIENT_DARKNESS_RADIUS..=AMBIENT_DARKNESS_RADIUS {
+            for z in -AMBIENT_DARKNESS_RADIUS..=AMBIENT_DARKNESS_RADIUS {
+                if world_map.get_block(position + IVec3::new(x, y, z)) == Some(torch) {
+                    return true;
+                }
+            }
+        }
+    }
+
+    false
+}
+
+// Ambient mobs are spawned in dark pockets of air underground, somewhere around the player.
+fn spawn_ambient_random_mobs(
+    mut commands: Commands,
+    time: Res<Time>,
+    world_map: Res<WorldMap>,
+    mobs: Res<Mobs>,
+    random_mobs: Res<RandomMobs>,
+    mut budgets: ResMut<ChunkBudgets>,
+    mut player_caps: Query<(&mut MobCap, &GlobalTransform), With<Player>>,
+    mut timer: Local<Timer>,
+    mut rng: Local<Rng>,
+) {
+    // Checking that a spot is dark is expensive, so only one attempt per player is made every
+    // second.
+    timer.tick(time.delta());
+    if !timer.is_finished() {
+        return;
+    }
+    *timer = Timer::from_seconds(1.0, TimerMode::Once);
+
+    if random_mobs.ambient.is_empty() {
+        return;
+    }
+
+    let blocks = Blocks::get();
+    let air = blocks.get_id("air");
+    let is_air = |position: BlockPosition| world_map.get_block(position) == Some(air);
+
+    let range = UniformDistribution::new(-AMBIENT_SPAWN_RANGE, AMBIENT_SPAWN_RANGE);
+    let scatter = UniformDistribution::new(-2i32, 2);
+
+    'outer: for (mut mob_cap, transform) in player_caps.iter_mut() {
+        if mob_cap.at_ambient_capacity() {
+            continue;
+        }
+
+        let offset = IVec3::new(
+            range.sample(&mut rng),
+            range.sample(&mut rng),
+            range.sample(&mut rng),
+        );
+        if offset.length_squared() < AMBIENT_MIN_DISTANCE.pow(2) {
+            continue;
+        }
+
+        let spawn_position = BlockPosition::from(transform.translation()) + offset;
+        // Needs some room to fly
+        if !is_air(spawn_position) || !is_air(spawn_position + IVec3::Y) {
+            continue;
+        }
+
+        let spawn_chunk = ChunkPosition::from(spawn_position);
+        if !budgets.can_spawn_mob(&spawn_chunk) {
+            continue;
+        }
+
+        if has_sky_access(&world_map, spawn_position) || is_lit(&world_map, spawn_position) {
+            continue;
+        }
+
+        let (group_size, mob_id) = random_mobs.choose_ambient(&mut rng);
+        let mob_config = mobs.get_config(mob_id);
+
+        for _ in 0..group_size {
+            let position = spawn_position
+                + IVec3::new(
+                    scatter.sample(&mut rng),
+                    scatter.sample(&mut rng),
+                    scatter.sample(&mut rng),
+                );
+            if !is_air(position) {
+                continue;
+            }
+
+            let mut entity_commands = commands.spawn((
+                Mob { id: mob_id },
+                RandomMobType::Ambient,
+                Transform::from_translation(position.as_dvec3() + DVec3::splat(0.5)),
+            ));
+
+            (mob_config.spawn_function)(&mut entity_commands);
+
+            budgets.add_mob(spawn_chunk);
+            mob_cap.ambient += 1;
+
+            if mob_cap.at_ambient_capacity() {
+                continue 'outer;
+            }
+        }
+    }
+}
+
+/// Random hostile mobs that aren't after anyone are despawned when they are this far away from
+/// every player, so they don't take up the mob cap where no one will meet them.
+const IDLE_HOSTILE_DESPAWN_DISTANCE: f64 = 32.0;
+
 fn despawn_mobs(
     mut commands: Commands,
-    chunk_subscriptions: Res<ChunkSubscriptions>,
-    mob_query: Query<(Entity, &GlobalTransform), With<Mob>>,
+    player_subscriptions: PlayerSubscriptions,
+    mob_query: Query<
+        (
+            Entity,
+            &GlobalTransform,
+            Option<&RandomMobType>,
+            Option<&Target>,
+        ),
+        (With<Mob>, Without<persistence::Persistent>),
+    >,
     mut player_query: Query<(&GlobalTransform, &mut MobCap), With<Player>>,
     despawned_mobs: Query<(Entity, &GlobalTransform, &RandomMobType), With<MobDespawn>>,
 ) {
-    'outer: for (mob_entity, mob_transform) in mob_query.iter() {
+    // Persistent mobs are never despawned, they are stored when their chunk is unloaded.
+    'outer: for (mob_entity, mob_transform, mob_type, target) in mob_query.iter() {
         let chunk_position = ChunkPosition::from(mob_transform.translation());
-        let Some(subscribers) = chunk_subscriptions.get_subscribers(&chunk_position) else {
+        let Some(subscribers) = player_subscriptions.get_players(&chunk_position) else {
             // If there are no subscribers, the chunk isn't loaded anymore, instantly despawn
             commands.entity(mob_entity).insert(MobDespawn);
             continue;
         };
 
         for player_entity in subscribers {
-            let (player_transform, _) = player_query.get(*player_entity).unwrap();
+            let Ok((player_transform, _)) = player_query.get(player_entity) else {
+                continue;
+            };
             let distance = player_transform
                 .translation()
                 .distance_squared(mob_transform.translation());
             // TODO: Should this use the simulation distance? There's really no use in having
             // random mobs be simulated far away, and if fills up the mob cap so there won't be any
             // near players.
-            let radius = (Chunk::SIZE as f64 * 4.0).powi(2);
+            //
+            // Zombies keep track of their target themselves, but they lose interest long before
+            // this distance.
+            let radius = if matches!(mob_type, Some(RandomMobType::Hostile))
+                && target.is_none_or(|target| target.get().is_none())
+            {
+                IDLE_HOSTILE_DESPAWN_DISTANCE.powi(2)
+            } else {
+                (Chunk::SIZE as f64 * SIMULATION_RADIUS as f64).powi(2)
+            };
 
             if distance < radius {
                 continue 'outer;
@@ -356,9 +691,11 @@ fn despawn_mobs(
 
     for (entity, transform, mob_type) in despawned_mobs.iter() {
         let chunk_position = ChunkPosition::from(transform.translation());
-        if let Some(subscribers) = chunk_subscriptions.get_subscribers(&chunk_position) {
+        if let Some(subscribers) = player_subscriptions.get_players(&chunk_position) {
             for subscriber in subscribers {
-                let (_, mut mob_cap) = player_query.get_mut(*subscriber).unwrap();
+                let Ok((_, mut mob_cap)) = player_query.get_mut(subscriber) else {
+                    continue;
+                };
                 match mob_type {
                     RandomMobType::Hostile => {
                         mob_cap.hostile = mob_cap.hostile.saturating_sub(1);
@@ -366,6 +703,9 @@ fn despawn_mobs(
                     RandomMobType::Friendly => {
                         mob_cap.friendly = mob_cap.friendly.saturating_sub(1);
                     }
+                    RandomMobType::Ambient => {
+                        mob_cap.ambient = mob_cap.ambient.saturating_sub(1);
+                    }
                 }
             }
         }
@@ -373,6 +713,30 @@ fn despawn_mobs(
     }
 }
 
+// Hostile mobs are removed when the difficulty is peaceful. Random mobs go through
+// 'despawn_mobs' so they are taken off the mob caps.
+fn despawn_hostile_mobs(
+    mut commands: Commands,
+    game_rules: Res<GameRules>,
+    hostile_mobs: Query<(Entity, &Faction, Has<RandomMobType>), Without<MobDespawn>>,
+) {
+    if game_rules.difficulty().allows_hostile_mobs() {
+        return;
+    }
+
+    for (entity, faction, is_random) in hostile_mobs.iter() {
+        if *faction != Faction::Hostile {
+            continue;
+        }
+
+        if is_random {
+            commands.entity(entity).insert(MobDespawn);
+        } else {
+            commands.entity(entity).despawn();
+        }
+    }
+}
+
 #[derive(Default)]
 pub struct MobSoundCollection {
     random: Vec<String>,
@@ -435,6 +799,11 @@ impl MobHealth {
         self.hearts == 0
     }
 
+    /// How much health the mob has left, from 0 to 1
+    pub fn fraction(&self) -> f32 {
+        self.hearts as f32 / self.max as f32
+    }
+
     fn is_invincible(&self) -> bool {
         self.invincibility.is_some()
     }
@@ -462,41 +831,82 @@ impl MobHealth {
 pub struct MobDespawn;
 
 fn handle_hand_hits(
+    time: Res<Time>,
     items: Res<Items>,
-    player_inventory_query: Query<(&Inventory, &Camera), With<Player>>,
-    mut mob_hits: Query<(Entity, &Mob, &HandHits, &mut Physics, &MobHealth), Changed<HandHits>>,
+    mut player_query: Query<(&Inventory, &Camera, &FallDamage, &mut AttackCooldown), With<Player>>,
+    mob_hits: Query<(Entity, &HandHits, &MobHealth), (With<Mob>, Changed<HandHits>)>,
     mut damage_events: MessageWriter<MobDamageEvent>,
 ) {
-    for (mob_entity, mob, hits, mut physics, health) in mob_hits.iter_mut() {
+    for (mob_entity, hits, health) in mob_hits.iter() {
         if health.is_invincible() {
             continue;
         }
 
         for player in hits.iter() {
-            let (inventory, camera) = player_inventory_query.get(player).unwrap();
-            let damage = if let Some(item) = inventory.held_item_stack().item() {
-                let item_config = items.get_config(&item.id);
-                if let Some(damage_json) = item_config.properties.get("damage") {
-                    damage_json.as_u64().unwrap_or(1) as u32
-                } else {
-                    5
-                }
-            } else {
-                5
-            };
+            let (inventory, camera, fall_damage, mut cooldown) =
+                player_query.get_mut(player).unwrap();
+            let attack = MeleeAttack::new(
+                &items,
+                inventory,
+                camera,
+                &mut cooldown,
+                fall_damage.is_falling(),
+                time.elapsed(),
+            );
+
+            damage_events.write(MobDamageEvent {
+                mob_entity,
+                damage: attack.damage,
+                attacker: Some(player),
+                knock_back: Some(attack.knock_back),
+            });
+        }
+    }
+}
 
-            let horizontal = camera.forward().xz().normalize() * 10.0;
-            physics.velocity = DVec3::new(horizontal.x, 7.0, horizontal.y);
+/// Sent when a player right clicks a mob. What the mob does depends on the mob and on the item
+/// the player is holding, so the [HandInteractions] of mobs are read once and passed on as these.
+#[derive(Message)]
+pub struct MobInteraction {
+    pub mob_entity: Entity,
+    pub player_entity: Entity,
+}
 
-            damage_events.write(MobDamageEvent { mob_entity, damage });
+fn handle_hand_interactions(
+    mut mob_interactions: Query<
+        (Entity, &mut HandInteractions),
+        (With<Mob>, Changed<HandInteractions>),
+    >,
+    mut interaction_events: MessageWriter<MobInteraction>,
+) {
+    for (mob_entity, mut interactions) in mob_interactions.iter_mut() {
+        for player_entity in interactions.read() {
+            interaction_events.write(MobInteraction {
+                mob_entity,
+                player_entity,
+            });
         }
     }
 }
 
+/// Send to damage a mob
 #[derive(Message)]
-struct MobDamageEvent {
-    mob_entity: Entity,
-    damage: u32,
+pub struct MobDamageEvent {
+    pub mob_entity: Entity,
+    pub damage: u32,
+    /// The entity that caused the damage, if any
+    pub attacker: Option<Entity>,
+    /// Velocity the mob is pushed with when the damage lands
+    pub knock_back: Option<DVec3>,
+}
+
+/// Sent when a mob dies
+#[derive(Message)]
+pub struct MobDeathEvent {
+    pub mob_id: MobId,
+    pub position: DVec3,
+    /// The entity that dealt the killing blow
+    pub killer: Option<Entity>,
 }
 
 const INVINCIBILITY_TIME: f64 = 0.5;
@@ -514,11 +924,13 @@ fn damage_mobs(
         &mut MobHealth,
         &mut Transform,
         &mut ModelColor,
+        Option<&mut Physics>,
     )>,
     mut damage_events: MessageReader<MobDamageEvent>,
+    mut death_events: MessageWriter<MobDeathEvent>,
     mut rng: Local<Rng>,
 ) {
-    for (mob_entity, mob, collider, mut health, mut mob_transform, mut color) in
+    for (mob_entity, mob, collider, mut health, mut mob_transform, mut color, _) in
         mob_query.iter_mut()
     {
         if !health.is_invincible() {
@@ -554,7 +966,7 @@ fn damage_mobs(
     }
 
     for damage_event in damage_events.read() {
-        let Ok((mob_entity, mut mob, _, mut health, transform, mut color)) =
+        let Ok((mob_entity, mut mob, _, mut health, transform, mut color, physics)) =
             mob_query.get_mut(damage_event.mob_entity)
         else {
             continue;
@@ -564,14 +976,26 @@ fn damage_mobs(
             continue;
         }
 
-        health.damage(damage_event.damage);
-
         let config = mobs.get_config(mob.id);
 
+        health.damage(config.stats.reduce_damage(damage_event.damage));
+
+        if let Some(knock_back) = damage_event.knock_back
+            && let Some(mut physics) = physics
+        {
+            physics.velocity = config.stats.reduce_knock_back(knock_back);
+        }
+
         if health.is_dead() {
             // Use the invincibility to keep the entity alive so the death animation can be shown.
             health.set_invincible(1.0);
 
+            death_events.write(MobDeathEvent {
+                mob_id: mob.id,
+                position: transform.translation,
+                killer: damage_event.attacker,
+            });
+
             if let Some((item_id, count)) = config.drop_table.drop(&mut rng) {
                 let item_config = items.get_config(&item_id);
                 let item_stack = ItemStack::new(item_config, 1);
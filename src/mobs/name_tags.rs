@@ -0,0 +1,112 @@
+use fmc::{items::Items, networking::Server, players::Player, prelude::*};
+
+use crate::{chat::send_message, players::Inventory};
+
+use super::{MobHealth, MobInteraction, persistence::Persistent};
+
+/// Name tags are given a name with the /name command, and give it to the mob they are used on.
+/// Named mobs show their name above them and are [Persistent], so they are never despawned.
+pub struct NameTagPlugin;
+impl Plugin for NameTagPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_message::<NameItem>()
+            .add_systems(Update, (name_items, name_mobs));
+    }
+}
+
+const MAX_NAME_LENGTH: usize = 32;
+
+/// Name the name tag the player is holding
+#[derive(Message)]
+pub struct NameItem {
+    pub player_entity: Entity,
+    pub name: String,
+}
+
+/// The name a mob was given with a name tag
+#[derive(Component, Clone)]
+pub struct CustomName(pub String);
+
+fn name_items(
+    net: Res<Server>,
+    items: Res<Items>,
+    mut player_query: Query<&mut Inventory, With<Player>>,
+    mut name_events: MessageReader<NameItem>,
+) {
+    let name_tag = items.get_id("name_tag").unwrap();
+
+    for name_event in name_events.read() {
+        let Ok(mut inventory) = player_query.get_mut(name_event.player_entity) else {
+            continue;
+        };
+
+        let held_item_stack = inventory.held_item_stack_mut();
+        let Some(item) = held_item_stack
+            .item_mut()
+            .filter(|item| item.id == name_tag)
+        else {
+            send_message(
+                &net,
+                name_event.player_entity,
+                "Hold a name tag to name it".to_owned(),
+            );
+            continue;
+        };
+
+        let name = name_event.name.trim();
+        if name.is_empty() || name.chars().count() > MAX_NAME_LENGTH {
+            send_message(
+                &net,
+                name_event.player_entity,
+                format!("Names must be 1 to {} characters long", MAX_NAME_LENGTH),
+            );
+            continue;
+        }
+
+        item.properties["name"] = name.into();
+        send_message(
+            &net,
+            name_event.player_entity,
+            format!("Named the name tag {}", name),
+        );
+    }
+}
+
+fn name_mobs(
+    mut commands: Commands,
+    items: Res<Items>,
+    mut player_query: Query<&mut Inventory, With<Player>>,
+    mob_query: Query<&MobHealth>,
+    mut interaction_events: MessageReader<MobInteraction>,
+) {
+    let name_tag = items.get_id("name_tag").unwrap();
+
+    for interaction in interaction_events.read() {
+        let Ok(health) = mob_query.get(interaction.mob_entity) else {
+            continue;
+        };
+        let Ok(mut inventory) = player_query.get_mut(interaction.player_entity) else {
+            continue;
+        };
+
+        if health.is_dead() {
+            continue;
+        }
+
+        let held_item_stack = inventory.held_item_stack_mut();
+        // Name tags that haven't been named do nothing
+        let Some(name) = held_item_stack
+            .item()
+            .filter(|item| item.id == name_tag)
+            .and_then(|item| item.properties["name"].as_str())
+            .map(str::to_owned)
+        else {
+            continue;
+        };
+
+        held_item_stack.take(1);
+        commands
+            .entity(interaction.mob_entity)
+            .insert((CustomName(name), Persistent));
+    }
+}
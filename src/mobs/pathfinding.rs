@@ -47,6 +47,11 @@ impl PathFinder {
         }
     }
 
+    /// Forget the current path, the mob stops where it is.
+    pub fn clear(&mut self) {
+        self.path.clear();
+    }
+
     pub fn find_path(&mut self, world_map: &WorldMap, start: DVec3, goal: DVec3) {
         // Even width npcs walk the edges of the blocks while odd width npcs walk the center of blocks.
         let mut block_start = if self.width % 2 == 0 {
@@ -0,0 +1,266 @@
+use fmc::{
+    bevy::math::{DQuat, DVec3},
+    blocks::BlockPosition,
+    database::Database,
+    prelude::*,
+    world::{ChunkLoadEvent, chunk::ChunkPosition},
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    database_io::{DatabaseIo, DatabaseTask},
+    players::shared_connections::PlayerSubscriptions,
+};
+
+use super::{Mob, MobHealth, Mobs, name_tags::CustomName};
+
+/// Mobs are usually removed when their chunk is unloaded. [Persistent] mobs are written to the
+/// database instead, and spawned again when their chunk is loaded.
+pub struct PersistencePlugin;
+impl Plugin for PersistencePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Startup, setup)
+            .add_systems(Update, (store_unloaded_mobs, load_mobs))
+            .add_systems(Last, store_mobs_on_shutdown.run_if(on_message::<AppExit>));
+    }
+}
+
+/// Marks a mob that should be kept when its chunk is unloaded
+#[derive(Component)]
+pub struct Persistent;
+
+/// The place a mob returns to, e.g. the spot a villager gathers at. It is kept when the mob is
+/// stored.
+#[derive(Component, Clone, Copy, Deref)]
+pub struct Home(pub BlockPosition);
+
+#[derive(Serialize, Deserialize)]
+struct MobSave {
+    /// Name of the mob's [MobConfig](super::MobConfig), ids change between runs
+    mob: String,
+    position: DVec3,
+    rotation: DQuat,
+    #[serde(default)]
+    custom_name: Option<String>,
+    #[serde(default)]
+    home: Option<IVec3>,
+}
+
+impl MobSave {
+    fn write(
+        saves: Vec<(ChunkPosition, MobSave)>,
+        connection: &rusqlite::Connection,
+    ) -> rusqlite::Result<()> {
+        let mut stmt = connection
+            .prepare("INSERT INTO persistent_mobs (x, y, z, data) VALUES (?, ?, ?, ?)")?;
+        for (chunk_position, save) in saves {
+            stmt.execute(rusqlite::params![
+                chunk_position.x,
+                chunk_position.y,
+                chunk_position.z,
+                serde_json::to_string(&save)
+                    .map_err(|e| rusqlite::Error::ToSqlConversionFailure(Box::new(e)))?
+            ])?;
+        }
+        Ok(())
+    }
+
+    // The mobs are removed from the database as they are loaded, they are written back when the
+    // chunk is unloaded again. If a row can't be read, none of the chunk's mobs are removed.
+    fn take(chunk_position: ChunkPosition, database: &Database) -> rusqlite::Result<Vec<MobSave>> {
+        let conn = database.get_write_connection();
+        let params = rusqlite::params![chunk_position.x, chunk_position.y, chunk_position.z];
+
+        let mut stmt =
+            conn.prepare("SELECT data FROM persistent_mobs WHERE x = ? AND y = ? AND z = ?")?;
+        let saves = stmt
+            .query_map(params, |row| {
+                let data: String = row.get(0)?;
+                serde_json::from_str(&data).map_err(|e| {
+                    rusqlite::Error::FromSqlConversionFailure(
+                        0,
+                        rusqlite::types::Type::Text,
+                        Box::new(e),
+                    )
+                })
+            })?
+            .collect::<rusqlite::Result<Vec<MobSave>>>()?;
+
+        conn.execute(
+            "DELETE FROM persistent_mobs WHERE x = ? AND y = ? AND z = ?",
+            params,
+        )?;
+
+        Ok(saves)
+    }
+}
+
+fn setup(database: Res<Database>) {
+    let conn = database.get_write_connection();
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS persistent_mobs (
+            id INTEGER PRIMARY KEY,
+            x INTEGER NOT NULL,
+            y INTEGER NOT NULL,
+            z INTEGER NOT NULL,
+            data TEXT NOT NULL
+        );
+        CREATE INDEX IF NOT EXISTS persistent_mobs_chunk ON persistent_mobs (x, y, z);",
+    )
+    .unwrap();
+}
+
+fn mob_save(
+    mobs: &Mobs,
+    mob: &Mob,
+    transform: &Transform,
+    custom_name: Option<&CustomName>,
+    home: Option<&Home>,
+) -> (ChunkPosition, MobSave) {
+    (
+        ChunkPosition::from(transform.translation),
+        MobSave {
+            mob: mobs.get_config(mob.id).name.clone(),
+            position: transform.translation,
+            rotation: transform.rotation,
+            custom_name: custom_name.map(|custom_name| custom_name.0.clone()),
+            home: home.map(|home| *home.0),
+        },
+    )
+}
+
+fn store_unloaded_mobs(
+    mut commands: Commands,
+    database_io: Res<DatabaseIo>,
+    mobs: Res<Mobs>,
+    player_subscriptions: PlayerSubscriptions,
+    mob_query: Query<
+        (
+            Entity,
+            &Mob,
+            &MobHealth,
+            &Transform,
+            Option<&CustomName>,
+            Option<&Home>,
+        ),
+        With<Persistent>,
+    >,
+) {
+    let mut saves = Vec::new();
+
+    for (mob_entity, mob, health, transform, custom_name, home) in mob_query.iter() {
+        // Dead mobs are only kept around to show a death pose
+        if health.is_dead() {
+            continue;
+        }
+
+        let chunk_position = ChunkPosition::from(transform.translation);
+        if player_subscriptions.get_players(&chunk_position).is_some() {
+            continue;
+        }
+
+        saves.push(mob_save(&mobs, mob, transform, custom_name, home));
+        commands.entity(mob_entity).despawn();
+    }
+
+    if !saves.is_empty() {
+        database_io.run(move |database| {
+            if let Err(e) = MobSave::write(saves, &database.get_write_connection()) {
+                error!("Failed to store unloaded mobs\nError: {}", e);
+            }
+        });
+    }
+}
+
+fn load_mobs(
+    mut commands: Commands,
+    database_io: Res<DatabaseIo>,
+    mobs: Res<Mobs>,
+    mut loaded_chunks: MessageReader<ChunkLoadEvent>,
+    mut tasks: Local<Vec<(ChunkPosition, DatabaseTask<rusqlite::Result<Vec<MobSave>>>)>>,
+) {
+    for loaded_chunk in loaded_chunks.read() {
+        let chunk_position = loaded_chunk.position;
+        tasks.push((
+            chunk_position,
+            database_io.run(move |database| MobSave::take(chunk_position, database)),
+        ));
+    }
+
+    tasks.retain_mut(|(chunk_position, task)| {
+        let Some(saves) = task.try_take() else {
+            return true;
+        };
+
+        let saves = match saves {
+            Ok(saves) => saves,
+            Err(e) => {
+                error!(
+                    "Failed to load the mobs of the chunk at {}, {}, {}\nError: {}",
+                    chunk_position.x, chunk_position.y, chunk_position.z, e
+                );
+                return false;
+            }
+        };
+
+        for save in saves {
+            // The mob may have been removed from the game since it was saved
+            let Some(mob_id) = mobs.get_id(&save.mob) else {
+                continue;
+            };
+
+            let mut entity_commands = commands.spawn((
+                Mob { id: mob_id },
+                Persistent,
+                Transform {
+                    translation: save.position,
+                    rotation: save.rotation,
+                    ..default()
+                },
+            ));
+            (mobs.get_config(mob_id).spawn_function)(&mut entity_commands);
+
+            if let Some(custom_name) = save.custom_name {
+                entity_commands.insert(CustomName(custom_name));
+            }
+
+            if let Some(home) = save.home {
+                entity_commands.insert(Home(BlockPosition(home)));
+            }
+        }
+
+        false
+    });
+}
+
+fn store_mobs_on_shutdown(
+    database_io: Res<DatabaseIo>,
+    mobs: Res<Mobs>,
+    mob_query: Query<
+        (
+            &Mob,
+            &MobHealth,
+            &Transform,
+            Option<&CustomName>,
+            Option<&Home>,
+        ),
+        With<Persistent>,
+    >,
+) {
+    let saves: Vec<(ChunkPosition, MobSave)> = mob_query
+        .iter()
+        .filter(|(_, health, _, _, _)| !health.is_dead())
+        .map(|(mob, _, transform, custom_name, home)| {
+            mob_save(&mobs, mob, transform, custom_name, home)
+        })
+        .collect();
+
+    database_io.run(move |database| {
+        if let Err(e) = MobSave::write(saves, &database.get_write_connection()) {
+            error!("Failed to store mobs on shutdown\nError: {}", e);
+        }
+    });
+
+    // The server exits right after this, so it must be written before returning.
+    database_io.flush();
+}
@@ -0,0 +1,451 @@
+use std::collections::HashSet;
+
+use fmc::{
+    bevy::math::DVec3,
+    blocks::{BlockPosition, Blocks},
+    items::{ItemStack, Items},
+    networking::Server,
+    players::Player,
+    prelude::*,
+    protocol::messages,
+    random::{Rng, UniformDistribution},
+    world::WorldMap,
+};
+
+use crate::{
+    chat::{CHAT_FONT_SIZE, CHAT_TEXT_COLOR, send_message},
+    items::DroppedItem,
+    players::{Inventory, SpawnPoint},
+    skybox::{Clock, DayNightEvent},
+    world::game_rules::GameRules,
+};
+
+use super::{Mob, MobDeathEvent, MobHealth, Mobs, pathfinding::PathFinder, villager::Villages};
+
+/// Waves of hostile mobs that attack villages and player bases at night.
+///
+/// A raid starts when a player with a [BadOmen] enters a village at night, or at every full moon
+/// near each player's village or bed. Defeating all the waves before sunrise rewards the players
+/// that took part.
+pub struct RaidPlugin;
+impl Plugin for RaidPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(Raids::default()).add_systems(
+            Update,
+            (
+                give_bad_omen,
+                (start_raids, run_raids, update_raid_interfaces).chain(),
+                march_raiders,
+                hide_interface_on_join,
+            ),
+        );
+    }
+}
+
+/// Players and raiders further than this from the center of a raid don't take part in it.
+const RAID_RADIUS: f64 = 48.0;
+/// Raiders are spawned in a ring between these distances from the raid's center.
+const SPAWN_DISTANCE: (f64, f64) = (24.0, 32.0);
+const WAVES: u32 = 3;
+/// Time between a wave being defeated and the next wave arriving.
+const WAVE_DELAY: f32 = 10.0;
+/// Every n'th night is a full moon.
+const MOON_CYCLE: u32 = 8;
+/// Number of segments in the raid's progress bar
+const PROGRESS_STAGES: usize = 20;
+/// Mobs that take part in raids
+const RAIDER_MOBS: [&str; 4] = ["zombie", "skeleton", "spider", "creeper"];
+/// Given to each player that defended against a raid
+const REWARD: (&str, u32) = ("diamond", 3);
+
+/// A player that killed a village defender. The next time they visit a village at night a raid
+/// will start.
+#[derive(Component)]
+pub struct BadOmen;
+
+/// A mob that was spawned by a raid, it walks towards the raid's center when it has nothing else
+/// to do.
+#[derive(Component)]
+struct Raider {
+    center: DVec3,
+}
+
+struct Raid {
+    center: DVec3,
+    // How many waves that have been spawned
+    wave: u32,
+    // The raiders of the current wave
+    raiders: Vec<Entity>,
+    // How many raiders the current wave started with
+    wave_size: usize,
+    // Time until the next wave arrives
+    wave_timer: Timer,
+    // Players that are close enough to see the raid's progress
+    participants: HashSet<Entity>,
+}
+
+impl Raid {
+    fn new(center: DVec3) -> Self {
+        Self {
+            center,
+            wave: 0,
+            raiders: Vec::new(),
+            wave_size: 0,
+            wave_timer: Timer::from_seconds(WAVE_DELAY, TimerMode::Once),
+            participants: HashSet::new(),
+        }
+    }
+
+    fn is_victory(&self) -> bool {
+        self.wave == WAVES && self.raiders.is_empty()
+    }
+}
+
+/// The raids that are in progress
+#[derive(Resource, Default)]
+struct Raids(Vec<Raid>);
+
+impl Raids {
+    fn contains(&self, position: DVec3) -> bool {
+        self.0
+            .iter()
+            .any(|raid| raid.center.distance(position) < RAID_RADIUS)
+    }
+}
+
+fn hide_interface(net: &Server, player_entity: Entity) {
+    net.send_one(
+        player_entity,
+        messages::InterfaceVisibilityUpdate {
+            interface_path: "raid".to_owned(),
+            visible: false,
+        },
+    );
+}
+
+fn hide_interface_on_join(net: Res<Server>, new_players: Query<Entity, Added<Player>>) {
+    for player_entity in new_players.iter() {
+        hide_interface(&net, player_entity);
+    }
+}
+
+// Killing a golem brings a bad omen
+fn give_bad_omen(
+    mut commands: Commands,
+    net: Res<Server>,
+    mobs: Res<Mobs>,
+    players: Query<(), (With<Player>, Without<BadOmen>)>,
+    mut death_events: MessageReader<MobDeathEvent>,
+) {
+    for death_event in death_events.read() {
+        if Some(death_event.mob_id) != mobs.get_id("golem") {
+            continue;
+        }
+
+        let Some(player_entity) = death_event.killer else {
+            continue;
+        };
+
+        if !players.contains(player_entity) {
+            continue;
+        }
+
+        commands.entity(player_entity).insert(BadOmen);
+        send_message(&net, player_entity, "You feel a bad omen".to_owned());
+    }
+}
+
+fn start_raids(
+    mut commands: Commands,
+    net: Res<Server>,
+    time: Res<Time>,
+    clock: Res<Clock>,
+    villages: Res<Villages>,
+    game_rules: Res<GameRules>,
+    mut raids: ResMut<Raids>,
+    players: Query<(Entity, &GlobalTransform, &SpawnPoint, Has<BadOmen>), With<Player>>,
+    mut day_night_events: MessageReader<DayNightEvent>,
+    mut nights: Local<u32>,
+    mut timer: Local<Timer>,
+) {
+    if timer.duration().is_zero() {
+        *timer = Timer::from_seconds(1.0, TimerMode::Repeating);
+    }
+
+    let mut full_moon_rising = false;
+    for event in day_night_events.read() {
+        if *event == DayNightEvent::Dusk {
+            *nights += 1;
+            full_moon_rising = *nights % MOON_CYCLE == 0;
+        }
+    }
+
+    timer.tick(time.delta());
+    if !timer.just_finished() && !full_moon_rising {
+        return;
+    }
+
+    // No raids on peaceful, but bad omens are kept for when the difficulty is raised.
+    if !clock.is_night() || !game_rules.difficulty().allows_hostile_mobs() {
+        return;
+    }
+
+    for (player_entity, transform, spawn_point, has_bad_omen) in players.iter() {
+        if !has_bad_omen && !full_moon_rising {
+            continue;
+        }
+
+        let player_position = transform.translation();
+        let village_center = villages
+            .iter()
+            .map(|village| village.center)
+            .find(|center| center.distance(player_position) < RAID_RADIUS);
+
+        let center = if let Some(village_center) = village_center {
+            village_center
+        } else if full_moon_rising
+            && let Some(spawn_point) = **spawn_point
+            && spawn_point.as_dvec3().distance(player_position) < RAID_RADIUS
+        {
+            // Without a village nearby the player's base is attacked instead.
+            spawn_point.as_dvec3()
+        } else {
+            continue;
+        };
+
+        if has_bad_omen {
+            commands.entity(player_entity).remove::<BadOmen>();
+        }
+
+        if raids.contains(center) {
+            continue;
+        }
+
+        send_message(&net, player_entity, "A raid is approaching!".to_owned());
+        raids.0.push(Raid::new(center));
+    }
+}
+
+fn run_raids(
+    mut commands: Commands,
+    net: Res<Server>,
+    time: Res<Time>,
+    clock: Res<Clock>,
+    world_map: Res<WorldMap>,
+    mobs: Res<Mobs>,
+    items: Res<Items>,
+    mut raids: ResMut<Raids>,
+    mut players: Query<(&mut Inventory, &GlobalTransform), With<Player>>,
+    raiders: Query<&MobHealth, With<Raider>>,
+    mut rng: Local<Rng>,
+) {
+    raids.0.retain_mut(|raid| {
+        raid.raiders
+            .retain(|entity| raiders.get(*entity).is_ok_and(|health| !health.is_dead()));
+
+        if raid.is_victory() {
+            let (reward_item, reward_amount) = REWARD;
+            let reward_id = items.get_id(reward_item).unwrap();
+
+            for player_entity in raid.participants.iter() {
+                let Ok((mut inventory, transform)) = players.get_mut(*player_entity) else {
+                    continue;
+                };
+
+                send_message(
+                    &net,
+                    *player_entity,
+                    "The raid was defeated! You are a hero of the village".to_owned(),
+                );
+
+                let mut reward = ItemStack::new(items.get_config(&reward_id), reward_amount);
+                inventory.insert(&mut reward);
+
+                // Drop what doesn't fit in the inventory
+                if !reward.is_empty() {
+                    commands.spawn((
+                        DroppedItem::new(reward),
+                        Transform::from_translation(transform.translation()),
+                    ));
+                }
+
+                hide_interface(&net, *player_entity);
+            }
+
+            return false;
+        }
+
+        // The raiders must be defeated before sunrise
+        if !clock.is_night() {
+            for player_entity in raid.participants.iter() {
+                send_message(&net, *player_entity, "The raid was lost".to_owned());
+                hide_interface(&net, *player_entity);
+            }
+
+            for raider in raid.raiders.iter() {
+                commands.entity(*raider).remove::<Raider>();
+            }
+
+            return false;
+        }
+
+        if !raid.raiders.is_empty() || raid.wave == WAVES {
+            return true;
+        }
+
+        raid.wave_timer.tick(time.delta());
+        if !raid.wave_timer.is_finished() {
+            return true;
+        }
+        raid.wave_timer.reset();
+
+        let angle_distribution = UniformDistribution::new(0.0, std::f64::consts::TAU);
+        let distance_distribution = UniformDistribution::new(SPAWN_DISTANCE.0, SPAWN_DISTANCE.1);
+
+        for _ in 0..3 + 2 * raid.wave {
+            let mob_name = RAIDER_MOBS[rng.next_usize() % RAIDER_MOBS.len()];
+            let Some(mob_id) = mobs.get_id(mob_name) else {
+                continue;
+            };
+
+            let angle = angle_distribution.sample(&mut rng);
+            let distance = distance_distribution.sample(&mut rng);
+            let position = raid.center + DVec3::new(angle.cos(), 0.0, angle.sin()) * distance;
+
+            let Some(spawn_position) = find_surface(&world_map, BlockPosition::from(position))
+            else {
+                continue;
+            };
+
+            let mut entity_commands = commands.spawn((
+                Mob { id: mob_id },
+                Raider {
+                    center: raid.center,
+                },
+                Transform::from_translation(spawn_position.as_dvec3() + DVec3::new(0.5, 0.0, 0.5)),
+            ));
+            (mobs.get_config(mob_id).spawn_function)(&mut entity_commands);
+
+            raid.raiders.push(entity_commands.id());
+        }
+
+        raid.wave += 1;
+        raid.wave_size = raid.raiders.len();
+
+        true
+    });
+}
+
+// Finds the first block from above that has room for a mob to stand on it. The search is limited
+// to a few blocks above and below the position, as raids happen at ground level.
+fn find_surface(world_map: &WorldMap, position: BlockPosition) -> Option<BlockPosition> {
+    let blocks = Blocks::get();
+
+    let mut open_blocks = 0;
+    for y in (position.y - 16..position.y + 16).rev() {
+        let block_position = BlockPosition::new(position.x, y, position.z);
+        let block_id = world_map.get_block(block_position)?;
+
+        if !blocks.get_config(&block_id).is_solid() {
+            open_blocks += 1;
+        } else if open_blocks >= 2 {
+            return Some(block_position + IVec3::Y);
+        } else {
+            open_blocks = 0;
+        }
+    }
+
+    None
+}
+
+// Raiders that aren't busy hunting walk towards the center of the raid.
+fn march_raiders(
+    world_map: Res<WorldMap>,
+    mut raiders: Query<(&Raider, &mut PathFinder, &GlobalTransform)>,
+) {
+    for (raider, mut path_finder, transform) in raiders.iter_mut() {
+        if path_finder.has_goal() || transform.translation().distance(raider.center) < 4.0 {
+            continue;
+        }
+
+        path_finder.find_path(&world_map, transform.translation(), raider.center);
+    }
+}
+
+// Shows the players near a raid which wave it is on and how many raiders are left of it.
+fn update_raid_interfaces(
+    net: Res<Server>,
+    time: Res<Time>,
+    mut raids: ResMut<Raids>,
+    players: Query<(Entity, &GlobalTransform), With<Player>>,
+    mut timer: Local<Timer>,
+) {
+    if timer.duration().is_zero() {
+        *timer = Timer::from_seconds(1.0, TimerMode::Repeating);
+    }
+
+    timer.tick(time.delta());
+    if !timer.just_finished() {
+        return;
+    }
+
+    for raid in raids.0.iter_mut() {
+        let nearby: HashSet<Entity> = players
+            .iter()
+            .filter(|(_, transform)| transform.translation().distance(raid.center) < RAID_RADIUS)
+            .map(|(entity, _)| entity)
+            .collect();
+
+        for player_entity in raid.participants.difference(&nearby) {
+            hide_interface(&net, *player_entity);
+        }
+
+        for player_entity in nearby.difference(&raid.participants) {
+            net.send_one(
+                *player_entity,
+                messages::InterfaceVisibilityUpdate {
+                    interface_path: "raid".to_owned(),
+                    visible: true,
+                },
+            );
+        }
+
+        raid.participants = nearby;
+
+        if raid.participants.is_empty() {
+            continue;
+        }
+
+        let title = if raid.raiders.is_empty() {
+            format!("Raid - Wave {} of {} approaching", raid.wave + 1, WAVES)
+        } else {
+            format!("Raid - Wave {} of {}", raid.wave, WAVES)
+        };
+        net.send_many(
+            &raid.participants,
+            messages::InterfaceTextUpdate {
+                interface_path: "raid/title".to_owned(),
+                index: 0,
+                text: title,
+                font_size: CHAT_FONT_SIZE,
+                color: CHAT_TEXT_COLOR.to_owned(),
+            },
+        );
+
+        let remaining = if raid.raiders.is_empty() {
+            0
+        } else {
+            let fraction = raid.raiders.len() as f32 / raid.wave_size as f32;
+            (fraction * PROGRESS_STAGES as f32).ceil() as usize
+        };
+
+        let mut node_update = messages::InterfaceNodeVisibilityUpdate::default();
+        for stage in 0..remaining {
+            node_update.set_visible(format!("raid/progress/{}", stage));
+        }
+        for stage in remaining..PROGRESS_STAGES {
+            node_update.set_hidden(format!("raid/progress/{}", stage));
+        }
+        net.send_many(&raid.participants, node_update);
+    }
+}
@@ -0,0 +1,167 @@
+use std::collections::HashSet;
+
+use fmc::{
+    bevy::math::{DQuat, DVec3},
+    items::{ItemStack, Items},
+    physics::Physics,
+    players::{Camera, Player},
+    prelude::*,
+};
+
+use crate::{
+    items::DroppedItem,
+    players::{Dismount, Inventory, Mount, Riding, Seat, VehicleConfig},
+};
+
+use super::{MobHealth, MobInteraction, Wanderer, breeding::Breedable, pathfinding::PathFinder};
+
+/// Mobs that are [Rideable] can be saddled by right clicking them with a saddle. Right clicking a
+/// saddled mob with anything else rides it, and right clicking it again gets off.
+///
+/// The ridden mob is a vehicle like a boat, the rider's movement plugin steers it and the mob
+/// stops walking about on its own until the rider gets off. The saddle drops when the mob dies.
+pub struct SaddlePlugin;
+impl Plugin for SaddlePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, (saddle_mobs, steer_ridden_mobs, unsaddle_dead_mobs));
+    }
+}
+
+/// Lets a mob be saddled and ridden. The config is given to the [Seat] once it is saddled.
+#[derive(Component)]
+pub struct Rideable {
+    /// Where the rider's feet are, relative to the mob
+    pub seat_offset: DVec3,
+    pub vehicle: VehicleConfig,
+}
+
+/// Inserted on mobs that wear a saddle
+#[derive(Component)]
+pub struct Saddled;
+
+fn saddle_mobs(
+    mut commands: Commands,
+    items: Res<Items>,
+    mut player_query: Query<(&mut Inventory, Option<&Riding>), With<Player>>,
+    mob_query: Query<(&Rideable, &MobHealth, Has<Saddled>, Option<&Breedable>)>,
+    mut interaction_events: MessageReader<MobInteraction>,
+    mut mount_events: MessageWriter<Mount>,
+    mut dismount_events: MessageWriter<Dismount>,
+) {
+    let saddle = items.get_id("saddle").unwrap();
+    let lead = items.get_id("lead").unwrap();
+    let name_tag = items.get_id("name_tag").unwrap();
+
+    for interaction in interaction_events.read() {
+        let Ok((rideable, health, saddled, breedable)) = mob_query.get(interaction.mob_entity)
+        else {
+            continue;
+        };
+        let Ok((mut inventory, riding)) = player_query.get_mut(interaction.player_entity) else {
+            continue;
+        };
+
+        if health.is_dead() {
+            continue;
+        }
+
+        if riding.is_some_and(|riding| riding.vehicle() == interaction.mob_entity) {
+            dismount_events.write(Dismount {
+                player_entity: interaction.player_entity,
+            });
+            continue;
+        }
+
+        let held_item_stack = inventory.held_item_stack_mut();
+        let held_item = held_item_stack.item().map(|item| item.id);
+
+        if !saddled {
+            if held_item == Some(saddle) {
+                held_item_stack.take(1);
+                commands.entity(interaction.mob_entity).insert((
+                    Saddled,
+                    Seat::new(rideable.seat_offset, rideable.vehicle.clone()),
+                ));
+            }
+        } else if held_item != Some(saddle)
+            && held_item != Some(lead)
+            && held_item != Some(name_tag)
+            // Feeding is left to breeding
+            && held_item != breedable.map(|breedable| breedable.food)
+        {
+            mount_events.write(Mount {
+                player_entity: interaction.player_entity,
+                vehicle: interaction.mob_entity,
+            });
+        }
+    }
+}
+
+// The rider moves the mob, it should not try to walk off by itself or keep the momentum it had
+// when the rider got on.
+fn steer_ridden_mobs(
+    camera_query: Query<&Camera, With<Player>>,
+    mut mob_query: Query<
+        (
+            Entity,
+            &Seat,
+            &mut Wanderer,
+            &mut PathFinder,
+            &mut Physics,
+            &mut Transform,
+        ),
+        With<Saddled>,
+    >,
+    mut ridden: Local<HashSet<Entity>>,
+) {
+    for (mob_entity, seat, mut wanderer, mut path_finder, mut physics, mut transform) in
+        mob_query.iter_mut()
+    {
+        let Some(rider) = seat.rider() else {
+            if ridden.remove(&mob_entity) {
+                wanderer.enable();
+            }
+            continue;
+        };
+
+        if ridden.insert(mob_entity) {
+            wanderer.disable();
+            path_finder.clear();
+        }
+
+        physics.velocity.x = 0.0;
+        physics.velocity.z = 0.0;
+
+        let Ok(camera) = camera_query.get(rider) else {
+            continue;
+        };
+
+        let direction = camera.forward().with_y(0.0).normalize_or_zero();
+        if direction != DVec3::ZERO {
+            transform.rotation = DQuat::from_rotation_arc(DVec3::NEG_Z, direction);
+        }
+    }
+
+    ridden.retain(|mob_entity| mob_query.contains(*mob_entity));
+}
+
+fn unsaddle_dead_mobs(
+    mut commands: Commands,
+    items: Res<Items>,
+    mob_query: Query<(Entity, &MobHealth, &Transform), (With<Saddled>, Changed<MobHealth>)>,
+) {
+    for (mob_entity, health, transform) in mob_query.iter() {
+        if !health.is_dead() {
+            continue;
+        }
+
+        // Removing the seat lets the rider off
+        commands.entity(mob_entity).remove::<(Saddled, Seat)>();
+
+        let saddle = items.get_id("saddle").unwrap();
+        commands.spawn((
+            DroppedItem::new(ItemStack::new(items.get_config(&saddle), 1)),
+            Transform::from_translation(transform.translation + DVec3::Y * 0.5),
+        ));
+    }
+}
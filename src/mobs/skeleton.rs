@@ -16,11 +16,12 @@ use crate::{
     items::{arrows::Arrow, spawn_crates::MobCrates},
     players::{GameMode, HandHits, PlayerDamageEvent},
     skybox::Clock,
+    world::game_rules::GameRules,
 };
 
 use super::{
-    Mob, MobConfig, MobHead, MobHealth, MobSoundCollection, Mobs, RandomMobs, Target, Wanderer,
-    pathfinding::PathFinder,
+    Faction, Mob, MobConfig, MobHead, MobHealth, MobSoundCollection, MobStats, Mobs, RandomMobs,
+    Target, Wanderer, pathfinding::PathFinder,
 };
 
 pub struct SkeletonPlugin;
@@ -162,10 +163,17 @@ fn setup(
     // };
 
     let feather = items.get_id("feather").unwrap();
+    let bone = items.get_id("bone").unwrap();
     let mob_id = mobs.add_mob(MobConfig {
+        name: "skeleton".to_owned(),
         spawn_function: Box::new(spawn_skeleton),
         sounds: MobSoundCollection::default(),
-        drop_table: DropTable::new(1.0, &[(feather, 1.0, 0, 2)]).unwrap(),
+        drop_table: DropTable::new(1.0, &[(feather, 1.0, 0, 2), (bone, 1.0, 0, 2)]).unwrap(),
+        faction: Faction::Hostile,
+        stats: MobStats {
+            attack_damage: 4,
+            ..default()
+        },
     });
 
     random_mobs.add_hostile(4, mob_id);
@@ -179,8 +187,12 @@ fn attack(
     time: Res<Time>,
     world_map: Res<WorldMap>,
     models: Res<Models>,
+    mobs: Res<Mobs>,
+    game_rules: Res<GameRules>,
     player_query: Query<(&Transform, &Camera), With<Player>>,
     mut skeletons: Query<(
+        Entity,
+        &Mob,
         &mut Skeleton,
         &mut PathFinder,
         &HandHits,
@@ -188,8 +200,14 @@ fn attack(
         &mut Target,
     )>,
 ) {
-    for (mut skeleton, mut path_finder, hand_hits, skeleton_transform, mut target) in
-        skeletons.iter_mut()
+    for (
+        skeleton_entity,
+        mut skeleton,
+        mut path_finder,
+        hand_hits,
+        skeleton_transform,
+        mut target,
+    ) in skeletons.iter_mut()
     {
         if let Some(player_entity) = hand_hits.iter().last() {
             target.set(Some(player_entity));
@@ -219,7 +237,13 @@ fn attack(
             let velocity = (player_head - skeleton_head).normalize() * 40.0;
             commands.spawn((
                 Model::Asset(model_config.id),
-                Arrow::new(velocity),
+                Arrow::new(velocity)
+                    .with_owner(skeleton_entity)
+                    .with_damage(
+                        game_rules
+                            .difficulty()
+                            .scale_damage(mobs.get_config(mob.id).stats.attack_damage),
+                    ),
                 Transform {
                     translation: skeleton_head,
                     rotation: DQuat::from_rotation_arc(DVec3::NEG_Z, velocity.normalize()),
@@ -0,0 +1,158 @@
+use fmc::{
+    bevy::math::DVec3,
+    blocks::{BlockData, BlockPosition, Blocks},
+    players::Player,
+    prelude::*,
+    random::{Rng, UniformDistribution},
+    world::WorldMap,
+};
+
+use crate::world::game_rules::{Difficulty, GameRules};
+
+use super::{Mob, Mobs};
+
+/// Spawners are blocks that keep spawning hostile mobs around them while players are close by.
+/// They are found in dungeons. How often they spawn depends on the difficulty, and they stop
+/// while it is peaceful.
+pub struct SpawnerPlugin;
+impl Plugin for SpawnerPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Startup, setup)
+            .add_systems(Update, (choose_mob, spawn_mobs).chain());
+    }
+}
+
+/// Mobs a spawner can spawn, the one it spawns is picked from its position.
+const SPAWNER_MOBS: [&str; 3] = ["zombie", "skeleton", "spider"];
+/// The spawner is only active while a player is within this distance.
+const ACTIVATION_RANGE: f64 = 16.0;
+/// Mobs are spawned within this many blocks of the spawner horizontally.
+const SPAWN_RANGE: i32 = 4;
+/// The spawner stops spawning when there are this many mobs near it.
+const MAX_NEARBY_MOBS: usize = 6;
+/// Time between each time the spawner spawns mobs on normal difficulty, in seconds.
+const SPAWN_DELAY: (f32, f32) = (10.0, 30.0);
+
+#[derive(Component)]
+struct Spawner {
+    mob_name: &'static str,
+    timer: Timer,
+    rng: Rng,
+}
+
+fn setup(mut blocks: ResMut<Blocks>) {
+    let block_id = blocks.get_id("spawner");
+    let block = blocks.get_config_mut(&block_id);
+    block.set_spawn_function(spawn_function);
+}
+
+fn spawn_function(commands: &mut EntityCommands, _block_data: Option<&BlockData>) {
+    commands.insert(Spawner {
+        mob_name: SPAWNER_MOBS[0],
+        timer: Timer::default(),
+        rng: Rng::default(),
+    });
+}
+
+fn spawn_delay(rng: &mut Rng, difficulty: Difficulty) -> Timer {
+    let (min, max) = SPAWN_DELAY;
+    // Peaceful never spawns, the delay only matters once the difficulty is raised again.
+    let rate = difficulty.spawn_rate().max(Difficulty::Easy.spawn_rate());
+    Timer::from_seconds(
+        UniformDistribution::new(min, max).sample(rng) / rate,
+        TimerMode::Once,
+    )
+}
+
+// The spawn function doesn't know where the block is, so the mob is picked once the position is
+// available. Using the position means it doesn't have to be saved.
+fn choose_mob(
+    game_rules: Res<GameRules>,
+    mut spawners: Query<(&mut Spawner, &BlockPosition), Added<Spawner>>,
+) {
+    for (mut spawner, position) in spawners.iter_mut() {
+        let seed = ((position.x as u64) << 32 | position.z as u32 as u64) ^ position.y as u64;
+        spawner.rng = Rng::new(seed);
+        spawner.mob_name = SPAWNER_MOBS[spawner.rng.next_usize() % SPAWNER_MOBS.len()];
+        spawner.timer = spawn_delay(&mut spawner.rng, game_rules.difficulty());
+    }
+}
+
+fn spawn_mobs(
+    mut commands: Commands,
+    time: Res<Time>,
+    world_map: Res<WorldMap>,
+    mobs: Res<Mobs>,
+    game_rules: Res<GameRules>,
+    players: Query<&GlobalTransform, With<Player>>,
+    nearby_mobs: Query<&GlobalTransform, With<Mob>>,
+    mut spawners: Query<(&mut Spawner, &BlockPosition)>,
+) {
+    if !game_rules.difficulty().allows_hostile_mobs() {
+        return;
+    }
+
+    let blocks = Blocks::get();
+    let is_air = |position: BlockPosition| {
+        world_map
+            .get_block(position)
+            .is_some_and(|block_id| !blocks.get_config(&block_id).is_solid())
+    };
+    let is_solid = |position: BlockPosition| {
+        world_map
+            .get_block(position)
+            .is_some_and(|block_id| blocks.get_config(&block_id).is_solid())
+    };
+
+    for (mut spawner, block_position) in spawners.iter_mut() {
+        let center = block_position.as_dvec3() + DVec3::splat(0.5);
+
+        if !players
+            .iter()
+            .any(|transform| transform.translation().distance(center) < ACTIVATION_RANGE)
+        {
+            continue;
+        }
+
+        spawner.timer.tick(time.delta());
+        if !spawner.timer.is_finished() {
+            continue;
+        }
+        spawner.timer = spawn_delay(&mut spawner.rng, game_rules.difficulty());
+
+        let nearby = nearby_mobs
+            .iter()
+            .filter(|transform| transform.translation().distance(center) < ACTIVATION_RANGE)
+            .count();
+        if nearby >= MAX_NEARBY_MOBS {
+            continue;
+        }
+
+        let Some(mob_id) = mobs.get_id(spawner.mob_name) else {
+            continue;
+        };
+
+        let horizontal = UniformDistribution::new(-SPAWN_RANGE, SPAWN_RANGE);
+        let vertical = UniformDistribution::new(-1, 1);
+        let count = UniformDistribution::new(1, 3).sample(&mut spawner.rng);
+
+        for _ in 0..count {
+            let position = *block_position
+                + IVec3::new(
+                    horizontal.sample(&mut spawner.rng),
+                    vertical.sample(&mut spawner.rng),
+                    horizontal.sample(&mut spawner.rng),
+                );
+
+            if !is_solid(position - IVec3::Y) || !is_air(position) || !is_air(position + IVec3::Y) {
+                continue;
+            }
+
+            let mut entity_commands = commands.spawn((
+                Mob { id: mob_id },
+                Transform::from_translation(position.as_dvec3() + DVec3::new(0.5, 0.0, 0.5)),
+            ));
+            (mobs.get_config(mob_id).spawn_function)(&mut entity_commands);
+        }
+    }
+}
@@ -12,11 +12,12 @@ use fmc::{
 use crate::{
     items::spawn_crates::MobCrates,
     players::{HandHits, PlayerDamageEvent},
+    world::game_rules::GameRules,
 };
 
 use super::{
-    Mob, MobConfig, MobHealth, MobSoundCollection, Mobs, RandomMobs, Target, Wanderer,
-    pathfinding::PathFinder,
+    Faction, Mob, MobConfig, MobHealth, MobSoundCollection, MobStats, Mobs, RandomMobs, Target,
+    Wanderer, pathfinding::PathFinder,
 };
 
 pub struct SpiderPlugin;
@@ -118,9 +119,15 @@ fn setup(
 
     let feather = items.get_id("feather").unwrap();
     let mob_id = mobs.add_mob(MobConfig {
+        name: "spider".to_owned(),
         spawn_function: Box::new(spawn_function),
         sounds,
         drop_table: DropTable::new(1.0, &[(feather, 1.0, 0, 2)]).unwrap(),
+        faction: Faction::Hostile,
+        stats: MobStats {
+            attack_damage: 5,
+            ..default()
+        },
     });
 
     random_mobs.add_hostile(1, mob_id);
@@ -134,10 +141,13 @@ fn actions(
     time: Res<Time>,
     world_map: Res<WorldMap>,
     models: Res<Models>,
+    mobs: Res<Mobs>,
+    game_rules: Res<GameRules>,
     player_query: Query<&Transform, With<Player>>,
     mut spider_query: Query<
         (
             Entity,
+            &Mob,
             &MobHealth,
             &mut Spider,
             &mut PathFinder,
@@ -153,6 +163,7 @@ fn actions(
 ) {
     for (
         entity,
+        mob,
         health,
         mut spider,
         mut path_finder,
@@ -189,7 +200,9 @@ fn actions(
             let knock_back = DVec3::new(horizontal.x, 7.5, horizontal.y);
             damage_event_writer.write(PlayerDamageEvent {
                 player_entity,
-                damage: 5,
+                damage: game_rules
+                    .difficulty()
+                    .scale_damage(mobs.get_config(mob.id).stats.attack_damage),
                 knock_back: Some(knock_back),
             });
 
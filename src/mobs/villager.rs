@@ -0,0 +1,648 @@
+use std::collections::HashMap;
+
+use fmc::{
+    bevy::math::{DQuat, DVec3},
+    blocks::{BlockId, BlockPosition, Blocks},
+    items::{DropTable, ItemId, ItemStack, Items},
+    models::{AnimationPlayer, Model, Models},
+    networking::Server,
+    physics::{Collider, Physics},
+    players::Player,
+    prelude::*,
+    random::{Rng, UniformDistribution},
+    world::WorldMap,
+};
+use serde::Deserialize;
+
+use crate::{
+    chat::send_message,
+    items::{DroppedItem, spawn_crates::MobCrates},
+    players::{HandHits, HandInteractions, Inventory},
+    skybox::DayNightEvent,
+};
+
+use super::{
+    Faction, MobConfig, MobHead, MobHealth, MobInteraction, MobSoundCollection, MobStats, Mobs,
+    pathfinding::PathFinder,
+    persistence::{Home, Persistent},
+};
+
+const PROFESSIONS_PATH: &str = "./assets/server/professions.json";
+
+pub struct VillagerPlugin;
+impl Plugin for VillagerPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(VillageClaims::default())
+            .insert_resource(Villages::default())
+            .add_systems(Startup, (setup, load_professions))
+            .add_systems(
+                Update,
+                (
+                    (
+                        follow_schedule,
+                        claim_points_of_interest,
+                        pursue_activity,
+                        follow_path,
+                    )
+                        .chain(),
+                    trade,
+                    release_claims,
+                    update_villages,
+                ),
+            );
+    }
+}
+
+/// How far from its home a villager looks for beds and job sites.
+const SEARCH_RADIUS: i32 = 16;
+/// How far from its home a villager strays while socializing.
+const SOCIALIZE_RADIUS: i32 = 4;
+/// Villagers that live within this distance of a village's center are part of the village.
+const VILLAGE_RADIUS: f64 = 32.0;
+
+/// What a villager is doing at the current time of day. It works in the morning, socializes in
+/// the afternoon and sleeps through the night.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+enum Activity {
+    Sleep,
+    Work,
+    #[default]
+    Socialize,
+}
+
+impl From<DayNightEvent> for Activity {
+    fn from(event: DayNightEvent) -> Self {
+        match event {
+            DayNightEvent::Dawn => Activity::Work,
+            DayNightEvent::Noon => Activity::Socialize,
+            DayNightEvent::Dusk => Activity::Sleep,
+        }
+    }
+}
+
+#[derive(Component)]
+struct Villager {
+    bed: Option<BlockPosition>,
+    job_site: Option<BlockPosition>,
+    // Index into Professions, decided by the job site the villager has claimed.
+    profession: Option<usize>,
+    activity: Activity,
+    // Searching for beds and job sites is expensive, so it is only done periodically.
+    search_timer: Timer,
+    // Time until it picks a new place to stand while socializing
+    socialize_timer: Timer,
+    rng: Rng,
+}
+
+impl Villager {
+    const EYES: DVec3 = DVec3::new(0.0, 1.65, 0.0);
+
+    fn new() -> Self {
+        Self {
+            bed: None,
+            job_site: None,
+            profession: None,
+            activity: Activity::Socialize,
+            search_timer: Timer::from_seconds(5.0, TimerMode::Repeating),
+            socialize_timer: Timer::default(),
+            rng: Rng::default(),
+        }
+    }
+
+    fn reset_socialize_timer(&mut self) {
+        self.socialize_timer = Timer::from_seconds(
+            UniformDistribution::new(3.0, 8.0).sample(&mut self.rng),
+            TimerMode::Once,
+        );
+    }
+}
+
+#[derive(Bundle)]
+struct VillagerBundle {
+    health: MobHealth,
+    villager: Villager,
+    physics: Physics,
+    path_finder: PathFinder,
+    collider: Collider,
+    hits: HandHits,
+    interactions: HandInteractions,
+}
+
+impl Default for VillagerBundle {
+    fn default() -> Self {
+        Self {
+            health: MobHealth::new(20),
+            villager: Villager::new(),
+            physics: Physics::default(),
+            path_finder: PathFinder::new(2, 1, 1),
+            collider: Collider::from_min_max(
+                DVec3::new(-0.3, 0.0, -0.3),
+                DVec3::new(0.3, 1.8, 0.3),
+            ),
+            hits: HandHits::default(),
+            interactions: HandInteractions::default(),
+        }
+    }
+}
+
+/// Beds and job sites that have been claimed by a villager. A block can only be claimed by one
+/// villager at a time.
+#[derive(Resource, Default)]
+struct VillageClaims {
+    claims: HashMap<BlockPosition, Entity>,
+}
+
+impl VillageClaims {
+    fn is_claimed(&self, block_position: &BlockPosition) -> bool {
+        self.claims.contains_key(block_position)
+    }
+
+    fn claim(&mut self, block_position: BlockPosition, villager_entity: Entity) {
+        self.claims.insert(block_position, villager_entity);
+    }
+
+    fn release(&mut self, block_position: &BlockPosition) {
+        self.claims.remove(block_position);
+    }
+}
+
+/// A group of villagers that live close to each other.
+pub struct Village {
+    pub center: DVec3,
+    pub villagers: usize,
+    /// How many of the villagers have claimed a bed
+    pub beds: usize,
+}
+
+/// The villages of all loaded villagers. Villages aren't stored, they are recomputed from where
+/// the villagers live.
+#[derive(Resource, Default)]
+pub struct Villages(Vec<Village>);
+
+impl Villages {
+    pub fn iter(&self) -> impl Iterator<Item = &Village> {
+        self.0.iter()
+    }
+}
+
+struct Trade {
+    input: (ItemId, u32),
+    output: (ItemId, u32),
+    description: String,
+}
+
+struct Profession {
+    name: String,
+    job_site: BlockId,
+    trades: Vec<Trade>,
+}
+
+#[derive(Resource)]
+struct Professions(Vec<Profession>);
+
+impl Professions {
+    fn by_job_site(&self, block_id: BlockId) -> Option<usize> {
+        self.0
+            .iter()
+            .position(|profession| profession.job_site == block_id)
+    }
+}
+
+#[derive(Deserialize)]
+struct ItemJson {
+    item: String,
+    amount: u32,
+}
+
+#[derive(Deserialize)]
+struct TradeJson {
+    input: ItemJson,
+    output: ItemJson,
+}
+
+#[derive(Deserialize)]
+struct ProfessionJson {
+    name: String,
+    job_site: String,
+    trades: Vec<TradeJson>,
+}
+
+fn load_professions(mut commands: Commands, items: Res<Items>) {
+    let file = match std::fs::File::open(PROFESSIONS_PATH) {
+        Ok(f) => f,
+        Err(e) => panic!(
+            "Failed to open villager professions at path: {}\nError: {}",
+            PROFESSIONS_PATH, e
+        ),
+    };
+
+    let professions_json: Vec<ProfessionJson> = match serde_json::from_reader(file) {
+        Ok(p) => p,
+        Err(e) => panic!(
+            "Failed to read villager professions at path: {}\nError: {}",
+            PROFESSIONS_PATH, e
+        ),
+    };
+
+    let blocks = Blocks::get();
+
+    let get_item = |item_json: &ItemJson, profession: &str| -> (ItemId, u32) {
+        match items.get_id(&item_json.item) {
+            Some(id) => (id, item_json.amount),
+            None => panic!(
+                "Failed to read the villager profession '{}', there is no item with the name '{}'",
+                profession, item_json.item
+            ),
+        }
+    };
+
+    let mut professions = Vec::with_capacity(professions_json.len());
+    for json in professions_json {
+        let job_site = blocks.get_id(&json.job_site);
+
+        let trades = json
+            .trades
+            .iter()
+            .map(|trade| Trade {
+                input: get_item(&trade.input, &json.name),
+                output: get_item(&trade.output, &json.name),
+                description: format!(
+                    "{} {} for {} {}",
+                    trade.input.amount, trade.input.item, trade.output.amount, trade.output.item
+                ),
+            })
+            .collect();
+
+        professions.push(Profession {
+            name: json.name,
+            job_site,
+            trades,
+        });
+    }
+
+    commands.insert_resource(Professions(professions));
+}
+
+fn setup(
+    items: Res<Items>,
+    models: Res<Models>,
+    mut mobs: ResMut<Mobs>,
+    mut mob_crates: ResMut<MobCrates>,
+) {
+    // Villagers don't have a model of their own yet
+    let model = models.get_config_by_name("player").unwrap();
+    let model_id = model.id;
+
+    let move_animation = model.animations["walk"];
+    let idle_animation = model.animations["idle"];
+
+    let spawn_function = move |commands: &mut EntityCommands| {
+        let mut animation_player = AnimationPlayer::default();
+        animation_player.set_move_animation(Some(move_animation));
+        animation_player.set_idle_animation(Some(idle_animation));
+        animation_player.set_transition_time(0.15);
+
+        // Villages would empty out if the villagers were despawned like other mobs.
+        commands.insert((
+            VillagerBundle::default(),
+            Persistent,
+            Model::Asset(model_id),
+            animation_player,
+            MobHead::new(
+                Villager::EYES,
+                std::f32::consts::FRAC_PI_4,
+                std::f32::consts::FRAC_PI_8,
+            ),
+        ));
+    };
+
+    let bread = items.get_id("bread").unwrap();
+    let mob_id = mobs.add_mob(MobConfig {
+        name: "villager".to_owned(),
+        spawn_function: Box::new(spawn_function),
+        sounds: MobSoundCollection::default(),
+        drop_table: DropTable::new(0.5, &[(bread, 1.0, 1, 1)]).unwrap(),
+        faction: Faction::Passive,
+        stats: MobStats::default(),
+    });
+
+    let villager_crate_id = items.get_id("villager_crate").unwrap();
+    mob_crates.add_crate(villager_crate_id, mob_id);
+}
+
+fn follow_schedule(
+    mut day_night_events: MessageReader<DayNightEvent>,
+    mut activity: Local<Activity>,
+    mut villagers: Query<&mut Villager>,
+) {
+    if let Some(event) = day_night_events.read().last() {
+        *activity = Activity::from(*event);
+    }
+
+    for mut villager in villagers.iter_mut() {
+        villager.activity = *activity;
+    }
+}
+
+// Validates the villager's claims, and searches around its home for beds and job sites to claim
+// if it is missing any.
+fn claim_points_of_interest(
+    mut commands: Commands,
+    time: Res<Time>,
+    world_map: Res<WorldMap>,
+    professions: Res<Professions>,
+    mut claims: ResMut<VillageClaims>,
+    mut villagers: Query<(Entity, &mut Villager, &GlobalTransform, Option<&Home>)>,
+) {
+    let blocks = Blocks::get();
+    let bed_foot = blocks.get_id("bed_foot");
+
+    for (villager_entity, mut villager, transform, home) in villagers.iter_mut() {
+        // The position the villager first appeared at. It gathers here to socialize, and
+        // searches for beds and job sites around it.
+        let Some(home) = home.map(|home| home.0) else {
+            commands
+                .entity(villager_entity)
+                .insert(Home(BlockPosition::from(transform.translation())));
+            continue;
+        };
+
+        villager.search_timer.tick(time.delta());
+        if !villager.search_timer.just_finished() {
+            continue;
+        }
+
+        // A claim is only dropped when the block is known to be gone, unloaded chunks are
+        // assumed to still contain it.
+        if let Some(bed) = villager.bed
+            && world_map
+                .get_block(bed)
+                .is_some_and(|block_id| block_id != bed_foot)
+        {
+            claims.release(&bed);
+            villager.bed = None;
+        }
+
+        if let Some(job_site) = villager.job_site
+            && let Some(block_id) = world_map.get_block(job_site)
+            && professions.by_job_site(block_id) != villager.profession
+        {
+            claims.release(&job_site);
+            villager.job_site = None;
+            villager.profession = None;
+        }
+
+        if villager.bed.is_some() && villager.job_site.is_some() {
+            continue;
+        }
+
+        for x in -SEARCH_RADIUS..=SEARCH_RADIUS {
+            for y in -4..=4 {
+                for z in -SEARCH_RADIUS..=SEARCH_RADIUS {
+                    let block_position = home + IVec3::new(x, y, z);
+                    let Some(block_id) = world_map.get_block(block_position) else {
+                        continue;
+                    };
+
+                    if claims.is_claimed(&block_position) {
+                        continue;
+                    }
+
+                    if villager.bed.is_none() && block_id == bed_foot {
+                        claims.claim(block_position, villager_entity);
+                        villager.bed = Some(block_position);
+                    } else if villager.job_site.is_none()
+                        && let Some(profession) = professions.by_job_site(block_id)
+                    {
+                        claims.claim(block_position, villager_entity);
+                        villager.job_site = Some(block_position);
+                        villager.profession = Some(profession);
+                    }
+                }
+            }
+        }
+    }
+}
+
+// Sets the villager's path towards wherever its current activity takes place.
+fn pursue_activity(
+    time: Res<Time>,
+    world_map: Res<WorldMap>,
+    mut villagers: Query<(
+        &mut Villager,
+        &mut PathFinder,
+        &GlobalTransform,
+        &MobHealth,
+        &Home,
+    )>,
+) {
+    for (mut villager, mut path_finder, transform, health, home) in villagers.iter_mut() {
+        if health.is_dead() || path_finder.has_goal() {
+            continue;
+        }
+
+        let home = home.0;
+
+        let destination = match villager.activity {
+            Activity::Sleep => villager.bed.unwrap_or(home),
+            Activity::Work => villager.job_site.unwrap_or(home),
+            Activity::Socialize => {
+                villager.socialize_timer.tick(time.delta());
+                if !villager.socialize_timer.is_finished() {
+                    continue;
+                }
+                villager.reset_socialize_timer();
+
+                let offset = UniformDistribution::new(-SOCIALIZE_RADIUS, SOCIALIZE_RADIUS);
+                let x = offset.sample(&mut villager.rng);
+                let z = offset.sample(&mut villager.rng);
+                home + IVec3::new(x, 0, z)
+            }
+        };
+
+        let goal = destination.as_dvec3() + DVec3::new(0.5, 0.0, 0.5);
+        // Close enough, the bed and the job site can't be stood inside of.
+        if goal.distance_squared(transform.translation()) < 4.0 {
+            continue;
+        }
+
+        path_finder.find_path(&world_map, transform.translation(), goal);
+    }
+}
+
+// Formula for how much speed you need to reach a height
+// sqrt(2 * gravity * wanted height(1.4)) + some for air resistance
+const JUMP_VELOCITY: f64 = 9.0;
+const WALKING_ACCELERATION: f64 = 30.0;
+
+fn follow_path(
+    time: Res<Time>,
+    mut villagers: Query<(
+        &MobHealth,
+        &Villager,
+        &mut PathFinder,
+        &mut Physics,
+        &mut Transform,
+    )>,
+) {
+    for (health, villager, mut path_finder, mut physics, mut transform) in villagers.iter_mut() {
+        // Mob entities are kept for a little while after death to show a death pose
+        if health.is_dead() {
+            continue;
+        }
+
+        let Some(next_position) = path_finder.next_node(transform.translation) else {
+            continue;
+        };
+
+        let direction = (next_position - transform.translation)
+            .with_y(0.0)
+            .normalize();
+        let rotation = DQuat::from_rotation_arc(DVec3::NEG_Z, direction);
+        let max_rotation = time.delta_secs_f64() * std::f64::consts::TAU;
+        transform.rotation = transform.rotation.rotate_towards(rotation, max_rotation);
+
+        if next_position.y - transform.translation.y > 0.1
+            && physics.is_against_wall()
+            && physics.is_grounded()
+        {
+            physics.velocity.y = JUMP_VELOCITY;
+        }
+
+        let mut acceleration = WALKING_ACCELERATION;
+
+        if !physics.is_grounded() {
+            acceleration *= 0.1;
+        }
+
+        // Stroll when there's no work to get to
+        if villager.activity == Activity::Socialize {
+            acceleration *= 0.5;
+        }
+
+        physics.acceleration += transform.forward() * acceleration;
+    }
+}
+
+// Right clicking a villager with an item it wants makes the trade, otherwise it tells the player
+// what it has to offer.
+fn trade(
+    mut commands: Commands,
+    net: Res<Server>,
+    items: Res<Items>,
+    professions: Res<Professions>,
+    mut player_query: Query<(&mut Inventory, &GlobalTransform), With<Player>>,
+    villagers: Query<&Villager>,
+    mut interaction_events: MessageReader<MobInteraction>,
+) {
+    for interaction in interaction_events.read() {
+        let Ok(villager) = villagers.get(interaction.mob_entity) else {
+            continue;
+        };
+        let player_entity = interaction.player_entity;
+
+        let Ok((mut inventory, transform)) = player_query.get_mut(player_entity) else {
+            continue;
+        };
+
+        if villager.activity == Activity::Sleep {
+            send_message(&net, player_entity, "The villager is asleep".to_owned());
+            continue;
+        }
+
+        let Some(profession) = villager.profession.map(|index| &professions.0[index]) else {
+            send_message(
+                &net,
+                player_entity,
+                "The villager doesn't have a job".to_owned(),
+            );
+            continue;
+        };
+
+        let held_item_stack = inventory.held_item_stack_mut();
+        let trade = profession.trades.iter().find(|trade| {
+            held_item_stack
+                .item()
+                .is_some_and(|item| item.id == trade.input.0)
+                && held_item_stack.size() >= trade.input.1
+        });
+
+        let Some(trade) = trade else {
+            let mut text = format!("The {} trades:", profession.name);
+            for trade in profession.trades.iter() {
+                text.push('\n');
+                text.push_str(&trade.description);
+            }
+            send_message(&net, player_entity, text);
+            continue;
+        };
+
+        held_item_stack.take(trade.input.1);
+
+        let (item_id, amount) = trade.output;
+        let mut output = ItemStack::new(items.get_config(&item_id), amount);
+        inventory.insert(&mut output);
+
+        // Drop what doesn't fit in the inventory
+        if !output.is_empty() {
+            commands.spawn((
+                DroppedItem::new(output),
+                Transform::from_translation(transform.translation()),
+            ));
+        }
+    }
+}
+
+fn release_claims(
+    mut claims: ResMut<VillageClaims>,
+    mut removed_villagers: RemovedComponents<Villager>,
+) {
+    for villager_entity in removed_villagers.read() {
+        claims.claims.retain(|_, entity| *entity != villager_entity);
+    }
+}
+
+fn update_villages(
+    time: Res<Time>,
+    mut villages: ResMut<Villages>,
+    villagers: Query<(&Villager, &Home)>,
+    mut timer: Local<Timer>,
+) {
+    if timer.duration().is_zero() {
+        *timer = Timer::from_seconds(5.0, TimerMode::Repeating);
+    }
+
+    timer.tick(time.delta());
+    if !timer.just_finished() {
+        return;
+    }
+
+    villages.0.clear();
+
+    for (villager, home) in villagers.iter() {
+        let home = home.as_dvec3();
+
+        let index = match villages
+            .0
+            .iter()
+            .position(|village| village.center.distance_squared(home) < VILLAGE_RADIUS.powi(2))
+        {
+            Some(index) => index,
+            None => {
+                // The first villager found becomes the center of the village.
+                villages.0.push(Village {
+                    center: home,
+                    villagers: 0,
+                    beds: 0,
+                });
+                villages.0.len() - 1
+            }
+        };
+
+        let village = &mut villages.0[index];
+        village.villagers += 1;
+        if villager.bed.is_some() {
+            village.beds += 1;
+        }
+    }
+}
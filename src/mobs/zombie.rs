@@ -15,10 +15,14 @@ use fmc::{
 use crate::{
     items::spawn_crates::MobCrates,
     players::{GameMode, HandHits, PlayerDamageEvent},
+    world::{
+        blocks::door::BangOnDoor,
+        game_rules::{Difficulty, GameRules},
+    },
 };
 
 use super::{
-    Mob, MobConfig, MobHealth, MobSoundCollection, Mobs, RandomMobs, Wanderer,
+    Faction, Mob, MobConfig, MobHealth, MobSoundCollection, MobStats, Mobs, RandomMobs, Wanderer,
     pathfinding::PathFinder,
 };
 
@@ -26,13 +30,15 @@ pub struct ZombiePlugin;
 impl Plugin for ZombiePlugin {
     fn build(&self, app: &mut App) {
         app.add_systems(Startup, setup)
-            .add_systems(Update, (follow_path, hunt_player, attack));
+            .add_systems(Update, (follow_path, hunt_player, attack, bang_on_doors));
     }
 }
 
 #[derive(Component, Default)]
 struct Zombie {
     target: Option<Entity>,
+    // When the zombie last hit a door in its way, in seconds since startup
+    last_door_bang: f64,
 }
 
 impl Zombie {
@@ -132,9 +138,16 @@ fn setup(
 
     let feather = items.get_id("feather").unwrap();
     let mob_id = mobs.add_mob(MobConfig {
+        name: "zombie".to_owned(),
         spawn_function: Box::new(spawn_zombie),
         sounds,
         drop_table: DropTable::new(1.0, &[(feather, 1.0, 0, 2)]).unwrap(),
+        faction: Faction::Hostile,
+        stats: MobStats {
+            armor: 2,
+            attack_damage: 5,
+            ..default()
+        },
     });
 
     random_mobs.add_hostile(4, mob_id);
@@ -323,11 +336,13 @@ fn follow_path(
 }
 
 fn attack(
-    zombies: Query<(&Zombie, &GlobalTransform)>,
+    mobs: Res<Mobs>,
+    game_rules: Res<GameRules>,
+    zombies: Query<(&Zombie, &Mob, &GlobalTransform)>,
     players: Query<&GlobalTransform, With<Player>>,
     mut damage_event_writer: MessageWriter<PlayerDamageEvent>,
 ) {
-    for (zombie, zombie_transform) in zombies.iter() {
+    for (zombie, mob, zombie_transform) in zombies.iter() {
         let Some(target) = zombie.target else {
             continue;
         };
@@ -344,9 +359,60 @@ fn attack(
             let knock_back = DVec3::new(horizontal.x, 7.0, horizontal.y);
             damage_event_writer.write(PlayerDamageEvent {
                 player_entity: target,
-                damage: 5,
+                damage: game_rules
+                    .difficulty()
+                    .scale_damage(mobs.get_config(mob.id).stats.attack_damage),
                 knock_back: Some(knock_back),
             });
         }
     }
 }
+
+/// Seconds between each time a zombie hits a door that is in its way
+const DOOR_BANG_INTERVAL: f64 = 1.0;
+
+// On hard difficulty, zombies that are hunting a player and are stopped by a closed door bang on
+// it until it breaks.
+fn bang_on_doors(
+    time: Res<Time>,
+    world_map: Res<WorldMap>,
+    game_rules: Res<GameRules>,
+    mut zombies: Query<(&mut Zombie, &MobHealth, &Physics, &GlobalTransform)>,
+    mut bang_events: MessageWriter<BangOnDoor>,
+) {
+    if !game_rules.mob_griefing() || game_rules.difficulty() != Difficulty::Hard {
+        return;
+    }
+
+    let now = time.elapsed_secs_f64();
+    let blocks = Blocks::get();
+    let doors = [blocks.get_id("oak door"), blocks.get_id("iron door")];
+
+    for (mut zombie, health, physics, transform) in zombies.iter_mut() {
+        if zombie.target.is_none()
+            || health.is_dead()
+            || !physics.is_against_wall()
+            || now - zombie.last_door_bang < DOOR_BANG_INTERVAL
+        {
+            continue;
+        }
+
+        let in_front = transform.translation() + transform.forward().with_y(0.0) * 0.8;
+        let Some(door_position) = [0.5, 1.5]
+            .into_iter()
+            .map(|height| BlockPosition::from(in_front + DVec3::Y * height))
+            .find(|position| {
+                world_map
+                    .get_block(*position)
+                    .is_some_and(|block_id| doors.contains(&block_id))
+            })
+        else {
+            continue;
+        };
+
+        zombie.last_door_bang = now;
+        bang_events.write(BangOnDoor {
+            position: door_position,
+        });
+    }
+}
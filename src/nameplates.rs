@@ -0,0 +1,338 @@
+use fmc::{
+    bevy::math::DVec3,
+    models::{Model, ModelVisibility, Observers},
+    physics::Collider,
+    players::Player,
+    prelude::*,
+};
+
+use crate::{
+    mobs::{Mob, MobHealth, name_tags::CustomName},
+    settings::Settings,
+};
+
+/// Floating text above entities. Players have their username shown above their head, named mobs
+/// show their name, and mobs show a health bar for a little while after they have been hurt.
+pub struct NameplatePlugin;
+impl Plugin for NameplatePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            (
+                add_player_nameplates,
+                update_mob_nameplates,
+                hide_hidden_nameplates,
+                update_health_bars.run_if(|settings: Res<Settings>| settings.mob_health_bars),
+                hide_health_bars,
+            ),
+        );
+    }
+}
+
+/// Texture atlas with one 8x8 cell for each printable ascii character, 16 cells to a row.
+const FONT_TEXTURE: &str = "nameplate_font.png";
+const FONT_TEXTURE_SIZE: [f32; 2] = [128.0, 56.0];
+/// Glyphs are 5 pixels wide with a 1 pixel shadow, the rest of the cell is left out.
+const GLYPH_WIDTH: f32 = 6.0;
+const GLYPH_HEIGHT: f32 = 8.0;
+/// Cells past the font that hold the colors of the health bar.
+const BAR_FILL_CELL: usize = 96;
+const BAR_BACKGROUND_CELL: usize = 97;
+/// Size of one texture pixel in blocks
+const PIXEL_SIZE: f32 = 1.0 / 40.0;
+
+/// Height of the name above the player's feet
+const PLAYER_NAMEPLATE_HEIGHT: f64 = 2.1;
+/// Distance between the top of the mob and its health bar
+const HEALTH_BAR_OFFSET: f64 = 0.3;
+/// Distance between the top of the mob and its name, above the health bar
+const MOB_NAMEPLATE_OFFSET: f64 = 0.5;
+const HEALTH_BAR_WIDTH: f32 = 1.0;
+const HEALTH_BAR_HEIGHT: f32 = 0.1;
+/// How long the health bar stays visible after the mob was last hurt
+const HEALTH_BAR_TIME: f32 = 3.0;
+
+/// Points to the nameplate of the entity
+#[derive(Component)]
+struct Nameplate(Entity);
+
+#[derive(Component)]
+struct HealthBar {
+    entity: Entity,
+    fraction: f32,
+    timer: Timer,
+}
+
+// Collects quads into a flat mesh centered on the entity's origin. Every quad is added twice,
+// back to back, so the plate can be read from both in front of and behind the entity.
+#[derive(Default)]
+struct PlateMesh {
+    indices: Vec<u32>,
+    vertices: Vec<[f32; 3]>,
+    normals: Vec<[f32; 3]>,
+    uvs: Vec<[f32; 2]>,
+}
+
+impl PlateMesh {
+    // 'left' and 'right' are as seen from in front of the entity. Takes the uvs as
+    // [left, top, right, bottom].
+    fn quad(&mut self, left: f32, right: f32, bottom: f32, top: f32, uv: [f32; 4]) {
+        // Entities face towards -Z, so the viewer in front of them has +X to their left.
+        for (direction, normal) in [(-1.0, [0.0, 0.0, -1.0]), (1.0, [0.0, 0.0, 1.0])] {
+            let first = self.vertices.len() as u32;
+            self.vertices.extend([
+                [left * direction, top, 0.0],
+                [left * direction, bottom, 0.0],
+                [right * direction, bottom, 0.0],
+                [right * direction, top, 0.0],
+            ]);
+            self.normals.extend([normal; 4]);
+            self.uvs.extend([
+                [uv[0], uv[1]],
+                [uv[0], uv[3]],
+                [uv[2], uv[3]],
+                [uv[2], uv[1]],
+            ]);
+            self.indices
+                .extend([0, 1, 2, 0, 2, 3].map(|index| index + first));
+        }
+    }
+
+    fn into_model(self) -> Model {
+        Model::Custom {
+            mesh_indices: self.indices,
+            mesh_vertices: self.vertices,
+            mesh_normals: self.normals,
+            mesh_uvs: Some(self.uvs),
+            material_color_texture: Some(FONT_TEXTURE.to_owned()),
+            material_parallax_texture: None,
+            material_alpha_mode: 2,
+            material_alpha_cutoff: 0.5,
+            material_double_sided: false,
+        }
+    }
+}
+
+// Uvs of the part of the cell that holds the glyph
+fn glyph_uvs(cell: usize) -> [f32; 4] {
+    let x = (cell % 16) as f32 * 8.0;
+    let y = (cell / 16) as f32 * 8.0;
+    [
+        (x + 1.0) / FONT_TEXTURE_SIZE[0],
+        y / FONT_TEXTURE_SIZE[1],
+        (x + 1.0 + GLYPH_WIDTH) / FONT_TEXTURE_SIZE[0],
+        (y + GLYPH_HEIGHT) / FONT_TEXTURE_SIZE[1],
+    ]
+}
+
+// Uvs of a single pixel at the center of the cell, for quads of a solid color.
+fn color_uvs(cell: usize) -> [f32; 4] {
+    let u = ((cell % 16) as f32 * 8.0 + 4.0) / FONT_TEXTURE_SIZE[0];
+    let v = ((cell / 16) as f32 * 8.0 + 4.0) / FONT_TEXTURE_SIZE[1];
+    [u, v, u, v]
+}
+
+fn text_model(text: &str) -> Model {
+    let mut mesh = PlateMesh::default();
+
+    let glyph_width = GLYPH_WIDTH * PIXEL_SIZE;
+    let glyph_height = GLYPH_HEIGHT * PIXEL_SIZE;
+    let mut left = -(text.chars().count() as f32 * glyph_width) / 2.0;
+
+    for character in text.chars() {
+        // The font only has upper case letters, anything it doesn't have is shown as '?'
+        let character = character.to_ascii_uppercase();
+        let cell = if (' '..='~').contains(&character) {
+            character as usize - ' ' as usize
+        } else {
+            '?' as usize - ' ' as usize
+        };
+
+        mesh.quad(left, left + glyph_width, 0.0, glyph_height, glyph_uvs(cell));
+        left += glyph_width;
+    }
+
+    mesh.into_model()
+}
+
+fn health_bar_model(fraction: f32) -> Model {
+    let mut mesh = PlateMesh::default();
+
+    let left = -HEALTH_BAR_WIDTH / 2.0;
+    let middle = left + HEALTH_BAR_WIDTH * fraction;
+    let right = HEALTH_BAR_WIDTH / 2.0;
+
+    if fraction > 0.0 {
+        mesh.quad(
+            left,
+            middle,
+            0.0,
+            HEALTH_BAR_HEIGHT,
+            color_uvs(BAR_FILL_CELL),
+        );
+    }
+    if fraction < 1.0 {
+        mesh.quad(
+            middle,
+            right,
+            0.0,
+            HEALTH_BAR_HEIGHT,
+            color_uvs(BAR_BACKGROUND_CELL),
+        );
+    }
+
+    mesh.into_model()
+}
+
+fn collider_top(collider: &Collider) -> f64 {
+    match collider {
+        Collider::Single(aabb) => aabb.center.y + aabb.half_extents.y,
+        Collider::Multi(aabbs) => aabbs
+            .iter()
+            .map(|aabb| aabb.center.y + aabb.half_extents.y)
+            .fold(0.0, f64::max),
+    }
+}
+
+// TODO: Nameplates should be hidden from players that are far away, and when the player is
+// sneaking. Visibility is the same for all observers, so the best that can be done for now is
+// that models are only sent to the players that are subscribed to the chunk they are in. The
+// server doesn't know when players sneak either, the movement plugin keeps that to itself.
+fn add_player_nameplates(
+    mut commands: Commands,
+    player_query: Query<(Entity, &Player), Added<Player>>,
+) {
+    for (player_entity, player) in player_query.iter() {
+        // The player doesn't need to see their own name.
+        let mut observers = Observers::default();
+        observers.exclude(player_entity);
+
+        let nameplate_entity = commands
+            .spawn((
+                text_model(&player.username),
+                ModelVisibility::Visible,
+                observers,
+                Transform::from_translation(DVec3::new(0.0, PLAYER_NAMEPLATE_HEIGHT, 0.0)),
+                ChildOf(player_entity),
+            ))
+            .id();
+
+        commands
+            .entity(player_entity)
+            .insert(Nameplate(nameplate_entity));
+    }
+}
+
+fn update_mob_nameplates(
+    mut commands: Commands,
+    mob_query: Query<(Entity, &CustomName, &Collider, Option<&Nameplate>), Changed<CustomName>>,
+    mut nameplate_query: Query<&mut Model, Without<Mob>>,
+) {
+    for (mob_entity, custom_name, collider, maybe_nameplate) in mob_query.iter() {
+        if let Some(nameplate) = maybe_nameplate {
+            if let Ok(mut model) = nameplate_query.get_mut(nameplate.0) {
+                *model = text_model(&custom_name.0);
+            }
+            continue;
+        }
+
+        let nameplate_entity = commands
+            .spawn((
+                text_model(&custom_name.0),
+                ModelVisibility::Visible,
+                Transform::from_translation(DVec3::new(
+                    0.0,
+                    collider_top(collider) + MOB_NAMEPLATE_OFFSET,
+                    0.0,
+                )),
+                ChildOf(mob_entity),
+            ))
+            .id();
+
+        commands
+            .entity(mob_entity)
+            .insert(Nameplate(nameplate_entity));
+    }
+}
+
+// Players that can't be seen shouldn't give themselves away through their name, e.g. spectators.
+fn hide_hidden_nameplates(
+    owner_query: Query<(&ModelVisibility, &Nameplate), Changed<ModelVisibility>>,
+    mut nameplate_query: Query<&mut ModelVisibility, Without<Nameplate>>,
+) {
+    for (owner_visibility, nameplate) in owner_query.iter() {
+        let Ok(mut visibility) = nameplate_query.get_mut(nameplate.0) else {
+            continue;
+        };
+
+        if matches!(owner_visibility, ModelVisibility::Hidden) {
+            visibility.set_if_neq(ModelVisibility::Hidden);
+        } else {
+            visibility.set_if_neq(ModelVisibility::Visible);
+        }
+    }
+}
+
+fn update_health_bars(
+    mut commands: Commands,
+    mut mob_query: Query<
+        (Entity, &MobHealth, &Collider, Option<&mut HealthBar>),
+        (With<Mob>, Changed<MobHealth>),
+    >,
+    mut health_bar_query: Query<(&mut Model, &mut ModelVisibility), Without<Mob>>,
+) {
+    for (mob_entity, health, collider, maybe_health_bar) in mob_query.iter_mut() {
+        let fraction = health.fraction();
+
+        if let Some(mut health_bar) = maybe_health_bar {
+            // The health is also changed when the mob's invincibility runs out.
+            if health_bar.fraction == fraction {
+                continue;
+            }
+
+            health_bar.fraction = fraction;
+            health_bar.timer.reset();
+
+            if let Ok((mut model, mut visibility)) = health_bar_query.get_mut(health_bar.entity) {
+                *model = health_bar_model(fraction);
+                visibility.set_if_neq(ModelVisibility::Visible);
+            }
+        } else if fraction < 1.0 {
+            let health_bar_entity = commands
+                .spawn((
+                    health_bar_model(fraction),
+                    ModelVisibility::Visible,
+                    Transform::from_translation(DVec3::new(
+                        0.0,
+                        collider_top(collider) + HEALTH_BAR_OFFSET,
+                        0.0,
+                    )),
+                    ChildOf(mob_entity),
+                ))
+                .id();
+
+            commands.entity(mob_entity).insert(HealthBar {
+                entity: health_bar_entity,
+                fraction,
+                timer: Timer::from_seconds(HEALTH_BAR_TIME, TimerMode::Once),
+            });
+        }
+    }
+}
+
+fn hide_health_bars(
+    time: Res<Time>,
+    mut mob_query: Query<&mut HealthBar>,
+    mut health_bar_query: Query<&mut ModelVisibility, Without<Mob>>,
+) {
+    for mut health_bar in mob_query.iter_mut() {
+        health_bar.timer.tick(time.delta());
+        if !health_bar.timer.just_finished() {
+            continue;
+        }
+
+        if let Ok(mut visibility) = health_bar_query.get_mut(health_bar.entity) {
+            visibility.set_if_neq(ModelVisibility::Hidden);
+        }
+    }
+}
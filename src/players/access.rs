@@ -0,0 +1,298 @@
+use std::collections::{HashMap, HashSet};
+
+use fmc::{
+    database::Database,
+    networking::{NetworkEvent, Server},
+    players::Player,
+    prelude::*,
+};
+
+use crate::{chat::send_message, database_io::DatabaseIo, settings::Settings};
+
+/// Keeps track of the whitelist, banned and muted players and operators, and turns away players
+/// that are not allowed to join.
+pub struct AccessControlPlugin;
+impl Plugin for AccessControlPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Startup, load_access_lists)
+            .add_systems(Update, enforce_access);
+    }
+}
+
+/// Operator level needed to add players to the whitelist
+pub const WHITELIST_LEVEL: u8 = 2;
+/// Operator level needed to change the game rules, the difficulty, the time and game modes
+pub const GAME_RULE_LEVEL: u8 = 2;
+/// Operator level needed to use color codes in the chat
+pub const CHAT_COLOR_LEVEL: u8 = 1;
+/// Operator level needed to teleport with /tp, spectators can always teleport to other players
+pub const TELEPORT_LEVEL: u8 = 2;
+/// Operator level needed to set and remove warps
+pub const WARP_LEVEL: u8 = 2;
+/// Operator level needed to drain water with /drain
+pub const DRAIN_LEVEL: u8 = 2;
+/// Operator level needed to change blocks with /fill, /setblock and /clone, and to /undo it
+pub const EDIT_LEVEL: u8 = 3;
+/// Operator level needed to save and paste schematics with /schem
+pub const SCHEMATIC_LEVEL: u8 = 3;
+/// Operator level needed to see the server's metrics
+pub const METRICS_LEVEL: u8 = 2;
+/// Operator level needed to be told about chunks that keep exceeding their entity budget
+pub const BUDGET_ALERT_LEVEL: u8 = 2;
+/// Operator level needed to ban and pardon players
+pub const BAN_LEVEL: u8 = 3;
+/// Operator level needed to mute and unmute players
+pub const MUTE_LEVEL: u8 = 3;
+/// Operator level needed to see the server's warnings and errors in the chat
+pub const LOG_LEVEL: u8 = 3;
+/// Operator level needed to save and back up the world
+pub const SAVE_LEVEL: u8 = 4;
+/// Operator level needed to reload the recipes, biomes and blueprints
+pub const RELOAD_LEVEL: u8 = 4;
+/// Operator level needed to create and switch worlds
+pub const WORLD_LEVEL: u8 = 4;
+/// Operator level needed to make other players operators
+pub const OP_LEVEL: u8 = 4;
+
+/// The whitelist, ban list, muted players and operators. Changes are written to the database in
+/// the background.
+#[derive(Resource)]
+pub struct AccessControl {
+    whitelist: HashSet<String>,
+    // Banned players and the reason they were banned
+    bans: HashMap<String, String>,
+    // Players that can't use the chat
+    mutes: HashSet<String>,
+    // Operators and their level, from 1 to 4
+    operators: HashMap<String, u8>,
+}
+
+impl AccessControl {
+    pub fn is_whitelisted(&self, username: &str) -> bool {
+        self.whitelist.contains(username)
+    }
+
+    /// The reason the player was banned, None if they aren't.
+    pub fn ban_reason(&self, username: &str) -> Option<&str> {
+        self.bans.get(username).map(|reason| reason.as_str())
+    }
+
+    /// If the player is kept from using the chat
+    pub fn is_muted(&self, username: &str) -> bool {
+        self.mutes.contains(username)
+    }
+
+    /// The operator level of the player, 0 if they are not an operator.
+    pub fn operator_level(&self, username: &str) -> u8 {
+        self.operators.get(username).copied().unwrap_or(0)
+    }
+
+    pub fn whitelist_add(&mut self, username: &str, database_io: &DatabaseIo) {
+        let name = username.to_owned();
+        database_io.run(move |database| {
+            let conn = database.get_write_connection();
+            conn.execute(
+                "INSERT OR REPLACE INTO whitelist (username) VALUES (?)",
+                rusqlite::params![name],
+            )
+            .unwrap();
+        });
+
+        self.whitelist.insert(username.to_owned());
+    }
+
+    pub fn ban(&mut self, username: &str, reason: &str, database_io: &DatabaseIo) {
+        let (name, text) = (username.to_owned(), reason.to_owned());
+        database_io.run(move |database| {
+            let conn = database.get_write_connection();
+            conn.execute(
+                "INSERT OR REPLACE INTO bans (username, reason) VALUES (?,?)",
+                rusqlite::params![name, text],
+            )
+            .unwrap();
+        });
+
+        self.bans.insert(username.to_owned(), reason.to_owned());
+    }
+
+    /// Remove a player's ban, returns false if they weren't banned.
+    pub fn pardon(&mut self, username: &str, database_io: &DatabaseIo) -> bool {
+        let name = username.to_owned();
+        database_io.run(move |database| {
+            let conn = database.get_write_connection();
+            conn.execute(
+                "DELETE FROM bans WHERE username = ?",
+                rusqlite::params![name],
+            )
+            .unwrap();
+        });
+
+        self.bans.remove(username).is_some()
+    }
+
+    pub fn mute(&mut self, username: &str, database_io: &DatabaseIo) {
+        let name = username.to_owned();
+        database_io.run(move |database| {
+            let conn = database.get_write_connection();
+            conn.execute(
+                "INSERT OR REPLACE INTO mutes (username) VALUES (?)",
+                rusqlite::params![name],
+            )
+            .unwrap();
+        });
+
+        self.mutes.insert(username.to_owned());
+    }
+
+    /// Let a muted player use the chat again, returns false if they weren't muted.
+    pub fn unmute(&mut self, username: &str, database_io: &DatabaseIo) -> bool {
+        let name = username.to_owned();
+        database_io.run(move |database| {
+            let conn = database.get_write_connection();
+            conn.execute(
+                "DELETE FROM mutes WHERE username = ?",
+                rusqlite::params![name],
+            )
+            .unwrap();
+        });
+
+        self.mutes.remove(username)
+    }
+
+    /// Set the operator level of a player, level 0 removes them as an operator.
+    pub fn set_operator_level(&mut self, username: &str, level: u8, database_io: &DatabaseIo) {
+        let name = username.to_owned();
+        database_io.run(move |database| {
+            let conn = database.get_write_connection();
+            if level == 0 {
+                conn.execute(
+                    "DELETE FROM operators WHERE username = ?",
+                    rusqlite::params![name],
+                )
+                .unwrap();
+            } else {
+                conn.execute(
+                    "INSERT OR REPLACE INTO operators (username, level) VALUES (?,?)",
+                    rusqlite::params![name, level],
+                )
+                .unwrap();
+            }
+        });
+
+        if level == 0 {
+            self.operators.remove(username);
+        } else {
+            self.operators.insert(username.to_owned(), level);
+        }
+    }
+}
+
+fn load_access_lists(
+    mut commands: Commands,
+    database: Res<Database>,
+    database_io: Res<DatabaseIo>,
+    settings: Res<Settings>,
+) {
+    let (whitelist, bans, mutes, operators) = {
+        let conn = database.get_write_connection();
+        conn.execute_batch(
+            "CREATE TABLE IF NOT EXISTS whitelist (username TEXT PRIMARY KEY);
+            CREATE TABLE IF NOT EXISTS bans (username TEXT PRIMARY KEY, reason TEXT NOT NULL);
+            CREATE TABLE IF NOT EXISTS mutes (username TEXT PRIMARY KEY);
+            CREATE TABLE IF NOT EXISTS operators (username TEXT PRIMARY KEY, level INTEGER NOT NULL);",
+        )
+        .unwrap();
+
+        let mut stmt = conn.prepare("SELECT username FROM whitelist").unwrap();
+        let whitelist = stmt
+            .query_map([], |row| row.get::<usize, String>(0))
+            .unwrap()
+            .map(|username| username.unwrap())
+            .collect();
+
+        let mut stmt = conn.prepare("SELECT username, reason FROM bans").unwrap();
+        let bans = stmt
+            .query_map([], |row| {
+                Ok((row.get::<usize, String>(0)?, row.get::<usize, String>(1)?))
+            })
+            .unwrap()
+            .map(|ban| ban.unwrap())
+            .collect();
+
+        let mut stmt = conn.prepare("SELECT username FROM mutes").unwrap();
+        let mutes = stmt
+            .query_map([], |row| row.get::<usize, String>(0))
+            .unwrap()
+            .map(|username| username.unwrap())
+            .collect();
+
+        let mut stmt = conn
+            .prepare("SELECT username, level FROM operators")
+            .unwrap();
+        let operators = stmt
+            .query_map([], |row| {
+                Ok((row.get::<usize, String>(0)?, row.get::<usize, u8>(1)?))
+            })
+            .unwrap()
+            .map(|operator| operator.unwrap())
+            .collect();
+
+        (whitelist, bans, mutes, operators)
+    };
+
+    let mut access_control = AccessControl {
+        whitelist,
+        bans,
+        mutes,
+        operators,
+    };
+
+    // Operators listed in the settings file always have full permissions, this is how the first
+    // operator is made.
+    for username in settings.operators.iter() {
+        access_control.set_operator_level(username, OP_LEVEL, &database_io);
+    }
+
+    commands.insert_resource(access_control);
+}
+
+fn enforce_access(
+    net: Res<Server>,
+    settings: Res<Settings>,
+    access_control: Res<AccessControl>,
+    player_query: Query<&Player>,
+    mut network_events: MessageReader<NetworkEvent>,
+) {
+    for network_event in network_events.read() {
+        let NetworkEvent::Connected { entity } = network_event else {
+            continue;
+        };
+
+        let Ok(player) = player_query.get(*entity) else {
+            continue;
+        };
+
+        let reason = if let Some(reason) = access_control.ban_reason(&player.username) {
+            if reason.is_empty() {
+                "You are banned from this server".to_owned()
+            } else {
+                format!("You are banned from this server: {}", reason)
+            }
+        } else if settings.whitelist
+            && !access_control.is_whitelisted(&player.username)
+            && access_control.operator_level(&player.username) == 0
+        {
+            "You are not whitelisted on this server".to_owned()
+        } else {
+            continue;
+        };
+
+        kick(&net, *entity, reason);
+    }
+}
+
+/// Tell the player why they are being disconnected, and disconnect them.
+pub fn kick(net: &Server, player_entity: Entity, reason: String) {
+    send_message(net, player_entity, reason);
+    net.disconnect(player_entity);
+}
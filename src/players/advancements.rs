@@ -0,0 +1,442 @@
+use std::collections::HashSet;
+
+use fmc::{
+    blocks::{BLOCK_CONFIG_PATH, BlockId, Blocks},
+    items::{ItemId, ItemStack, Items},
+    networking::Server,
+    players::Player,
+    prelude::*,
+    protocol::messages,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    assets::{AssetError, AssetErrorSystems, AssetErrors},
+    chat::{CHAT_FONT_SIZE, CHAT_TEXT_COLOR, broadcast_message},
+    items::{DroppedItem, crafting::CraftEvent},
+    mobs::{MobDeathEvent, MobId, Mobs},
+};
+
+use super::{BlockMinedEvent, Inventory};
+
+const ADVANCEMENTS_PATH: &str = "./assets/server/advancements.json";
+
+pub struct AdvancementPlugin;
+impl Plugin for AdvancementPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_message::<AdvancementEvent>()
+            .add_message::<OpenAdvancements>()
+            // Mobs are registered during Startup, so their names can't be resolved before that.
+            .add_systems(PostStartup, load_advancements.before(AssetErrorSystems))
+            .add_systems(
+                Update,
+                (
+                    (track_inventories, track_crafting, track_kills, track_mining),
+                    grant_advancements,
+                    open_advancements,
+                )
+                    .chain(),
+            );
+    }
+}
+
+/// The advancements a player has completed.
+#[derive(Component, Serialize, Deserialize, Default, Clone)]
+pub struct Advancements {
+    completed: HashSet<String>,
+}
+
+enum Criteria {
+    ObtainItem(ItemId),
+    CraftItem(ItemId),
+    SmeltItem(ItemId),
+    KillMob(MobId),
+    MineBlock(BlockId),
+}
+
+struct Advancement {
+    name: String,
+    title: String,
+    description: String,
+    // The advancement that must be completed before this one can be
+    parent: Option<String>,
+    criteria: Criteria,
+    reward: Option<(ItemId, u32)>,
+}
+
+#[derive(Resource)]
+struct AdvancementTree(Vec<Advancement>);
+
+impl AdvancementTree {
+    /// Iterate over the advancements the player has not completed, but has completed the parent of.
+    fn available<'a>(
+        &'a self,
+        completed: &'a Advancements,
+    ) -> impl Iterator<Item = (usize, &'a Advancement)> + 'a {
+        self.0.iter().enumerate().filter(|(_, advancement)| {
+            !completed.completed.contains(&advancement.name)
+                && advancement
+                    .parent
+                    .as_ref()
+                    .is_none_or(|parent| completed.completed.contains(parent))
+        })
+    }
+
+    // The advancements page, lists all the advancements and which of them the player has made.
+    fn build_interface(&self, completed: &Advancements) -> messages::InterfaceTextUpdate {
+        let count = self
+            .0
+            .iter()
+            .filter(|advancement| completed.completed.contains(&advancement.name))
+            .count();
+
+        let mut text = format!("Advancements: {}/{}", count, self.0.len());
+        for advancement in self.0.iter() {
+            let line = if completed.completed.contains(&advancement.name) {
+                format!("[x] {} - {}", advancement.title, advancement.description)
+            } else if let Some(parent) = advancement
+                .parent
+                .as_ref()
+                .filter(|parent| !completed.completed.contains(*parent))
+            {
+                // The parent is always defined before its children
+                let parent = self.0.iter().find(|a| &a.name == parent).unwrap();
+                format!("[ ] {} - requires {}", advancement.title, parent.title)
+            } else {
+                format!("[ ] {} - {}", advancement.title, advancement.description)
+            };
+            text.push('\n');
+            text.push_str(&line);
+        }
+
+        messages::InterfaceTextUpdate {
+            interface_path: "advancements/list".to_owned(),
+            index: 0,
+            text,
+            font_size: CHAT_FONT_SIZE,
+            color: CHAT_TEXT_COLOR.to_owned(),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum CriteriaJson {
+    ObtainItem { item: String },
+    CraftItem { item: String },
+    SmeltItem { item: String },
+    KillMob { mob: String },
+    MineBlock { block: String },
+}
+
+#[derive(Deserialize)]
+struct RewardJson {
+    item: String,
+    amount: u32,
+}
+
+#[derive(Deserialize)]
+struct AdvancementJson {
+    name: String,
+    title: String,
+    description: String,
+    #[serde(default)]
+    parent: Option<String>,
+    criteria: CriteriaJson,
+    #[serde(default)]
+    reward: Option<RewardJson>,
+}
+
+fn load_advancements(
+    mut commands: Commands,
+    items: Res<Items>,
+    mobs: Res<Mobs>,
+    mut asset_errors: ResMut<AssetErrors>,
+) {
+    let file = match std::fs::File::open(ADVANCEMENTS_PATH) {
+        Ok(f) => f,
+        Err(e) => panic!(
+            "Failed to open advancements at path: {}\nError: {}",
+            ADVANCEMENTS_PATH, e
+        ),
+    };
+
+    let advancements_json: Vec<AdvancementJson> = match serde_json::from_reader(file) {
+        Ok(a) => a,
+        Err(e) => panic!(
+            "Failed to read advancements at path: {}\nError: {}",
+            ADVANCEMENTS_PATH, e
+        ),
+    };
+
+    let get_item = |name: &str, advancement: &str| -> ItemId {
+        match items.get_id(name) {
+            Some(id) => id,
+            None => panic!(
+                "Failed to read the advancement '{}', there is no item with the name '{}'",
+                advancement, name
+            ),
+        }
+    };
+
+    let mut advancements = Vec::with_capacity(advancements_json.len());
+    for json in advancements_json {
+        if let Some(parent) = &json.parent {
+            if !advancements
+                .iter()
+                .any(|advancement: &Advancement| &advancement.name == parent)
+            {
+                panic!(
+                    "Failed to read the advancement '{}', its parent '{}' must be defined before it.",
+                    json.name, parent
+                );
+            }
+        }
+
+        let criteria = match &json.criteria {
+            CriteriaJson::ObtainItem { item } => Criteria::ObtainItem(get_item(item, &json.name)),
+            CriteriaJson::CraftItem { item } => Criteria::CraftItem(get_item(item, &json.name)),
+            CriteriaJson::SmeltItem { item } => Criteria::SmeltItem(get_item(item, &json.name)),
+            CriteriaJson::KillMob { mob } => match mobs.get_id(mob) {
+                Some(mob_id) => Criteria::KillMob(mob_id),
+                None => panic!(
+                    "Failed to read the advancement '{}', there is no mob with the name '{}'",
+                    json.name, mob
+                ),
+            },
+            CriteriaJson::MineBlock { block } => {
+                if !Blocks::get().contains_block(block) {
+                    asset_errors.push(
+                        AssetError::new(
+                            ADVANCEMENTS_PATH,
+                            format!(
+                                "The advancement '{}' requires mining the block '{}', but no \
+                                block by that name exists",
+                                json.name, block
+                            ),
+                        )
+                        .field("criteria")
+                        .misspelled(block, BLOCK_CONFIG_PATH),
+                    );
+                    continue;
+                }
+                Criteria::MineBlock(Blocks::get().get_id(block))
+            }
+        };
+
+        let reward = json
+            .reward
+            .as_ref()
+            .map(|reward| (get_item(&reward.item, &json.name), reward.amount));
+
+        advancements.push(Advancement {
+            name: json.name,
+            title: json.title,
+            description: json.description,
+            parent: json.parent,
+            criteria,
+            reward,
+        });
+    }
+
+    commands.insert_resource(AdvancementTree(advancements));
+}
+
+/// Show the player the advancements page
+#[derive(Message)]
+pub struct OpenAdvancements {
+    pub player_entity: Entity,
+}
+
+/// Sent when a player has met the criteria of an advancement
+#[derive(Message)]
+struct AdvancementEvent {
+    player_entity: Entity,
+    // Index into the AdvancementTree
+    advancement: usize,
+}
+
+fn track_inventories(
+    tree: Res<AdvancementTree>,
+    player_query: Query<(Entity, &Inventory, &Advancements), Changed<Inventory>>,
+    mut advancement_events: MessageWriter<AdvancementEvent>,
+) {
+    for (player_entity, inventory, advancements) in player_query.iter() {
+        for (index, advancement) in tree.available(advancements) {
+            let Criteria::ObtainItem(item_id) = advancement.criteria else {
+                continue;
+            };
+
+            if inventory
+                .iter()
+                .any(|item_stack| item_stack.item().is_some_and(|item| item.id == item_id))
+            {
+                advancement_events.write(AdvancementEvent {
+                    player_entity,
+                    advancement: index,
+                });
+            }
+        }
+    }
+}
+
+fn track_crafting(
+    tree: Res<AdvancementTree>,
+    player_query: Query<&Advancements>,
+    mut craft_events: MessageReader<CraftEvent>,
+    mut advancement_events: MessageWriter<AdvancementEvent>,
+) {
+    for craft_event in craft_events.read() {
+        let Ok(advancements) = player_query.get(craft_event.player_entity) else {
+            continue;
+        };
+
+        for (index, advancement) in tree.available(advancements) {
+            let item_id = match (&advancement.criteria, craft_event.collection) {
+                (Criteria::CraftItem(item_id), "crafting") => item_id,
+                (Criteria::SmeltItem(item_id), "smelting") => item_id,
+                _ => continue,
+            };
+
+            if *item_id == craft_event.item_id {
+                advancement_events.write(AdvancementEvent {
+                    player_entity: craft_event.player_entity,
+                    advancement: index,
+                });
+            }
+        }
+    }
+}
+
+fn track_kills(
+    tree: Res<AdvancementTree>,
+    player_query: Query<&Advancements>,
+    mut death_events: MessageReader<MobDeathEvent>,
+    mut advancement_events: MessageWriter<AdvancementEvent>,
+) {
+    for death_event in death_events.read() {
+        let Some(player_entity) = death_event.killer else {
+            continue;
+        };
+
+        let Ok(advancements) = player_query.get(player_entity) else {
+            continue;
+        };
+
+        for (index, advancement) in tree.available(advancements) {
+            if let Criteria::KillMob(mob_id) = advancement.criteria
+                && mob_id == death_event.mob_id
+            {
+                advancement_events.write(AdvancementEvent {
+                    player_entity,
+                    advancement: index,
+                });
+            }
+        }
+    }
+}
+
+fn track_mining(
+    tree: Res<AdvancementTree>,
+    player_query: Query<&Advancements>,
+    mut mined_events: MessageReader<BlockMinedEvent>,
+    mut advancement_events: MessageWriter<AdvancementEvent>,
+) {
+    for mined_event in mined_events.read() {
+        let Ok(advancements) = player_query.get(mined_event.player_entity) else {
+            continue;
+        };
+
+        for (index, advancement) in tree.available(advancements) {
+            if let Criteria::MineBlock(block_id) = advancement.criteria
+                && block_id == mined_event.block_id
+            {
+                advancement_events.write(AdvancementEvent {
+                    player_entity: mined_event.player_entity,
+                    advancement: index,
+                });
+            }
+        }
+    }
+}
+
+fn grant_advancements(
+    mut commands: Commands,
+    net: Res<Server>,
+    items: Res<Items>,
+    tree: Res<AdvancementTree>,
+    mut player_query: Query<(&Player, &mut Advancements, &mut Inventory, &GlobalTransform)>,
+    mut advancement_events: MessageReader<AdvancementEvent>,
+) {
+    for advancement_event in advancement_events.read() {
+        let Ok((player, mut advancements, mut inventory, transform)) =
+            player_query.get_mut(advancement_event.player_entity)
+        else {
+            continue;
+        };
+
+        let advancement = &tree.0[advancement_event.advancement];
+
+        // The same advancement can be triggered several times before it is granted.
+        if !advancements.completed.insert(advancement.name.clone()) {
+            continue;
+        }
+
+        net.send_one(
+            advancement_event.player_entity,
+            messages::InterfaceTextUpdate {
+                interface_path: "toast/text".to_owned(),
+                index: i32::MAX,
+                text: format!(
+                    "Advancement made! {}\n{}",
+                    advancement.title, advancement.description
+                ),
+                font_size: CHAT_FONT_SIZE,
+                color: CHAT_TEXT_COLOR.to_owned(),
+            },
+        );
+
+        broadcast_message(
+            &net,
+            format!(
+                "{} has made the advancement [{}]",
+                player.username, advancement.title
+            ),
+        );
+
+        if let Some((item_id, amount)) = advancement.reward {
+            let mut reward = ItemStack::new(items.get_config(&item_id), amount);
+            inventory.insert(&mut reward);
+
+            // Drop what doesn't fit in the inventory
+            if !reward.is_empty() {
+                commands.spawn((
+                    DroppedItem::new(reward),
+                    Transform::from_translation(transform.translation()),
+                ));
+            }
+        }
+    }
+}
+
+fn open_advancements(
+    net: Res<Server>,
+    tree: Res<AdvancementTree>,
+    player_query: Query<&Advancements>,
+    mut open_events: MessageReader<OpenAdvancements>,
+) {
+    for open_event in open_events.read() {
+        let Ok(advancements) = player_query.get(open_event.player_entity) else {
+            continue;
+        };
+
+        net.send_one(open_event.player_entity, tree.build_interface(advancements));
+        net.send_one(
+            open_event.player_entity,
+            messages::InterfaceVisibilityUpdate {
+                interface_path: "advancements".to_owned(),
+                visible: true,
+            },
+        );
+    }
+}
@@ -0,0 +1,94 @@
+use std::time::Duration;
+
+use fmc::{bevy::math::DVec3, items::Items, players::Camera, prelude::*};
+
+use super::Inventory;
+
+/// Damage dealt by the hand, and by items that don't define their own "damage" property
+const DEFAULT_DAMAGE: u32 = 2;
+/// Attacks per second at full strength for the hand, and items that don't define their own
+/// "attack_speed" property
+const DEFAULT_ATTACK_SPEED: f32 = 4.0;
+/// Attacks made before the cooldown is over deal at least this fraction of the damage
+const MIN_STRENGTH: f32 = 0.2;
+/// Critical hits are only possible when the attack is at least this strong
+const CRITICAL_STRENGTH: f32 = 0.9;
+const CRITICAL_MULTIPLIER: f32 = 1.5;
+const KNOCK_BACK: f64 = 10.0;
+
+/// Tracks when the player last attacked. Attacking again before the held item's cooldown is
+/// over weakens the attack.
+#[derive(Component, Default)]
+pub struct AttackCooldown {
+    last_attack: Duration,
+    // Strength of the last attack, reused when several entities are hit by the same swing.
+    strength: f32,
+}
+
+impl AttackCooldown {
+    // Restarts the cooldown, and returns the strength of the attack from 0 to 1.
+    fn swing(&mut self, now: Duration, attack_speed: f32) -> f32 {
+        if now == self.last_attack {
+            return self.strength;
+        }
+
+        let elapsed = (now - self.last_attack).as_secs_f32();
+        self.strength = (elapsed * attack_speed).min(1.0);
+        self.last_attack = now;
+        self.strength
+    }
+}
+
+/// A melee attack made by a player. The same calculation is used no matter what is hit.
+pub struct MeleeAttack {
+    pub damage: u32,
+    pub knock_back: DVec3,
+    /// Attacks made while falling deal extra damage
+    pub critical: bool,
+}
+
+impl MeleeAttack {
+    pub fn new(
+        items: &Items,
+        inventory: &Inventory,
+        camera: &Camera,
+        cooldown: &mut AttackCooldown,
+        is_falling: bool,
+        now: Duration,
+    ) -> Self {
+        let (damage, attack_speed) = if let Some(item) = inventory.held_item_stack().item() {
+            let properties = &items.get_config(&item.id).properties;
+            (
+                properties
+                    .get("damage")
+                    .and_then(|damage| damage.as_u64())
+                    .map(|damage| damage as u32)
+                    .unwrap_or(DEFAULT_DAMAGE),
+                properties
+                    .get("attack_speed")
+                    .and_then(|speed| speed.as_f64())
+                    .map(|speed| speed as f32)
+                    .unwrap_or(DEFAULT_ATTACK_SPEED),
+            )
+        } else {
+            (DEFAULT_DAMAGE, DEFAULT_ATTACK_SPEED)
+        };
+
+        let strength = cooldown.swing(now, attack_speed);
+        let critical = is_falling && strength >= CRITICAL_STRENGTH;
+
+        let mut damage =
+            damage as f32 * (MIN_STRENGTH + (1.0 - MIN_STRENGTH) * strength * strength);
+        if critical {
+            damage *= CRITICAL_MULTIPLIER;
+        }
+
+        let horizontal = camera.forward().xz().normalize_or_zero() * KNOCK_BACK * strength as f64;
+
+        Self {
+            damage: (damage.round() as u32).max(1),
+            knock_back: DVec3::new(horizontal.x, 7.0 * strength as f64, horizontal.y),
+            critical,
+        }
+    }
+}
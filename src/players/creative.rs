@@ -0,0 +1,310 @@
+use fmc::{
+    blocks::Blocks,
+    interfaces::{HeldInterfaceStack, InterfaceEvents, InterfaceSystems, RegisterInterfaceNode},
+    items::{ItemId, ItemStack, Items},
+    networking::{NetworkMessage, Server},
+    players::{Player, Target, Targets},
+    prelude::*,
+    protocol::messages,
+};
+
+use crate::items::tooltips::ItemTooltips;
+
+use super::{GameMode, HandInteractions, HandSystems, Inventory};
+
+const ITEM_CONFIGURATIONS_PATH: &str = "./assets/client/items/configurations";
+
+/// The number of item boxes in the palette, only the first items that match the search are shown.
+const PALETTE_SIZE: usize = 54;
+
+/// Gives creative players a palette in their inventory with every item in the game, and lets
+/// them pick the block they're looking at into their hand.
+///
+/// The palette is searched by writing in the box above it, the items are filtered by the server.
+/// Taking an item from the palette gives a full stack of it, and putting an item into it deletes
+/// the item.
+///
+/// There is no middle click in the protocol, so blocks are picked by right clicking them with an
+/// empty hand.
+pub struct CreativePlugin;
+impl Plugin for CreativePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Startup, load_palette).add_systems(
+            Update,
+            (
+                initialize_palette,
+                show_palette,
+                search_palette,
+                handle_palette_events.in_set(InterfaceSystems::HandleEvents),
+                pick_blocks.after(HandSystems),
+            ),
+        );
+    }
+}
+
+/// All the items, sorted by name
+#[derive(Resource)]
+struct Palette {
+    items: Vec<(String, ItemId)>,
+}
+
+impl Palette {
+    fn search(&self, text: &str) -> Vec<ItemId> {
+        let text = text.trim().to_lowercase();
+        self.items
+            .iter()
+            .filter(|(name, _)| name.contains(&text))
+            .map(|(_, item_id)| *item_id)
+            .collect()
+    }
+}
+
+/// The items that match the player's search, in the order they are shown in the palette.
+#[derive(Component)]
+struct PaletteSearch {
+    items: Vec<ItemId>,
+}
+
+#[derive(Component)]
+struct PaletteNode;
+
+fn load_palette(mut commands: Commands, items: Res<Items>) {
+    let directory = match std::fs::read_dir(ITEM_CONFIGURATIONS_PATH) {
+        Ok(d) => d,
+        Err(e) => panic!(
+            "Failed to open item configurations at path: {}\nError: {}",
+            ITEM_CONFIGURATIONS_PATH, e
+        ),
+    };
+
+    let mut palette = Vec::new();
+    for dir_entry in directory {
+        let path = match dir_entry {
+            Ok(d) => d.path(),
+            Err(e) => panic!(
+                "Failed to read item configurations at path: {}\nError: {}",
+                ITEM_CONFIGURATIONS_PATH, e
+            ),
+        };
+
+        let Some(name) = path.file_stem().and_then(|name| name.to_str()) else {
+            continue;
+        };
+        if let Some(item_id) = items.get_id(name) {
+            palette.push((name.replace('_', " "), item_id));
+        }
+    }
+    palette.sort_by(|a, b| a.0.cmp(&b.0));
+
+    commands.insert_resource(Palette { items: palette });
+}
+
+fn send_palette(
+    net: &Server,
+    items: &Items,
+    tooltips: &ItemTooltips,
+    player_entity: Entity,
+    shown: &[ItemId],
+) {
+    let mut update = messages::InterfaceItemBoxUpdate::default();
+    for index in 0..PALETTE_SIZE {
+        if let Some(item_id) = shown.get(index) {
+            let item_config = items.get_config(item_id);
+            let item_stack = ItemStack::new(item_config, item_config.max_stack_size);
+            update.add_itembox(
+                "creative_items",
+                index as u32,
+                *item_id,
+                item_config.max_stack_size,
+                None,
+                tooltips.get(&item_stack).as_deref(),
+            );
+        } else {
+            update.add_empty_itembox("creative_items", index as u32);
+        }
+    }
+    net.send_one(player_entity, update);
+}
+
+fn initialize_palette(
+    mut commands: Commands,
+    palette: Res<Palette>,
+    new_player_query: Query<Entity, Added<Player>>,
+    mut registration_events: MessageWriter<RegisterInterfaceNode>,
+) {
+    for player_entity in new_player_query.iter() {
+        commands.entity(player_entity).insert(PaletteSearch {
+            items: palette.search(""),
+        });
+
+        commands.entity(player_entity).with_children(|parent| {
+            let palette_entity = parent.spawn(PaletteNode).id();
+            registration_events.write(RegisterInterfaceNode {
+                player_entity,
+                node_path: String::from("creative_items"),
+                node_entity: palette_entity,
+            });
+        });
+    }
+}
+
+// The palette is only part of the inventory while the player is in creative
+fn show_palette(
+    net: Res<Server>,
+    tooltips: ItemTooltips,
+    items: Res<Items>,
+    palette: Res<Palette>,
+    player_query: Query<(Entity, &GameMode, Option<&PaletteSearch>), Changed<GameMode>>,
+) {
+    for (player_entity, game_mode, search) in player_query.iter() {
+        let mut visibility = messages::InterfaceNodeVisibilityUpdate::default();
+        if *game_mode == GameMode::Creative {
+            visibility.set_visible("creative_search".to_owned());
+            visibility.set_visible("creative_items".to_owned());
+            // New players don't have their search yet, it starts out empty.
+            match search {
+                Some(search) => send_palette(&net, &items, &tooltips, player_entity, &search.items),
+                None => send_palette(&net, &items, &tooltips, player_entity, &palette.search("")),
+            }
+        } else {
+            visibility.set_hidden("creative_search".to_owned());
+            visibility.set_hidden("creative_items".to_owned());
+        }
+        net.send_one(player_entity, visibility);
+    }
+}
+
+fn search_palette(
+    net: Res<Server>,
+    tooltips: ItemTooltips,
+    items: Res<Items>,
+    palette: Res<Palette>,
+    mut player_query: Query<(&GameMode, &mut PaletteSearch)>,
+    mut text_input_events: MessageReader<NetworkMessage<messages::InterfaceTextInput>>,
+) {
+    for text_input in text_input_events.read() {
+        if text_input.interface_path != "creative_search" {
+            continue;
+        }
+
+        let Ok((game_mode, mut search)) = player_query.get_mut(text_input.player_entity) else {
+            continue;
+        };
+
+        if *game_mode != GameMode::Creative {
+            continue;
+        }
+
+        search.items = palette.search(&text_input.text);
+        send_palette(
+            &net,
+            &items,
+            &tooltips,
+            text_input.player_entity,
+            &search.items,
+        );
+    }
+}
+
+fn handle_palette_events(
+    net: Res<Server>,
+    tooltips: ItemTooltips,
+    items: Res<Items>,
+    mut player_query: Query<(&GameMode, &PaletteSearch, &mut HeldInterfaceStack), With<Player>>,
+    mut interface_events: Query<
+        (&mut InterfaceEvents, &ChildOf),
+        (Changed<InterfaceEvents>, With<PaletteNode>),
+    >,
+) {
+    for (mut events, parent) in interface_events.iter_mut() {
+        let Ok((game_mode, search, mut held_item)) = player_query.get_mut(parent.0) else {
+            continue;
+        };
+
+        for event in events.read() {
+            if *game_mode != GameMode::Creative {
+                continue;
+            }
+
+            match *event {
+                messages::InterfaceInteraction::TakeItem {
+                    index, quantity, ..
+                } => {
+                    let Some(item_id) = search.items.get(index as usize) else {
+                        continue;
+                    };
+
+                    // Whatever the player held is thrown away for the new item
+                    if held_item.item().is_some_and(|item| item.id != *item_id) {
+                        let size = held_item.size();
+                        held_item.take(size);
+                    }
+
+                    let item_config = items.get_config(item_id);
+                    let mut item_stack = ItemStack::new(item_config, item_config.max_stack_size);
+                    item_stack.transfer_to(&mut held_item, quantity);
+                }
+                messages::InterfaceInteraction::PlaceItem { quantity, .. } => {
+                    held_item.take(quantity);
+                }
+                _ => (),
+            }
+        }
+
+        // The client moves the items around in the palette like in any other item box, it is
+        // sent again so it always shows the full palette.
+        send_palette(&net, &items, &tooltips, parent.0, &search.items);
+    }
+}
+
+// Right clicking a block with an empty hand in creative puts its item in the hand.
+fn pick_blocks(
+    items: Res<Items>,
+    palette: Res<Palette>,
+    mut player_query: Query<(&GameMode, &Targets, &mut Inventory), With<Player>>,
+    interactable_query: Query<(), With<HandInteractions>>,
+    mut clicks: MessageReader<NetworkMessage<messages::RightClick>>,
+) {
+    for right_click in clicks.read() {
+        if right_click.message == messages::RightClick::Release {
+            continue;
+        }
+
+        let Ok((game_mode, targets, mut inventory)) =
+            player_query.get_mut(right_click.player_entity)
+        else {
+            continue;
+        };
+
+        if *game_mode != GameMode::Creative || !inventory.held_item_stack().is_empty() {
+            continue;
+        }
+
+        // The click was used to interact with something
+        if targets
+            .iter()
+            .filter_map(|target| target.entity())
+            .any(|entity| interactable_query.contains(entity))
+        {
+            continue;
+        }
+
+        let blocks = Blocks::get();
+        let Some(Target::Block { block_id, .. }) =
+            targets.get_first_block(|block_id| blocks.get_config(block_id).is_solid())
+        else {
+            continue;
+        };
+
+        let Some((_, item_id)) = palette
+            .items
+            .iter()
+            .find(|(_, item_id)| items.get_config(item_id).block == Some(*block_id))
+        else {
+            continue;
+        };
+
+        let item_config = items.get_config(item_id);
+        *inventory.held_item_stack_mut() = ItemStack::new(item_config, item_config.max_stack_size);
+    }
+}
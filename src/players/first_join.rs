@@ -0,0 +1,157 @@
+use fmc::{
+    items::{ItemId, ItemStack, Items},
+    networking::Server,
+    prelude::*,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{chat::send_message, items::DroppedItem};
+
+use super::Inventory;
+
+/// Sets up players the first time they join the server. They are given the items of the
+/// starting kit along with a welcome message and book, and are optionally spawned in a tutorial
+/// area instead of at the world spawn.
+pub struct FirstJoinPlugin;
+impl Plugin for FirstJoinPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Startup, load_starting_kit)
+            .add_systems(Update, welcome_new_players);
+    }
+}
+
+const STARTING_KIT_PATH: &str = "./assets/server/starting_kit.json";
+
+/// Marks if the player has been through the first join setup. It is saved with the player, so the
+/// setup is only done once.
+#[derive(Component, Serialize, Deserialize, Default, Clone, Copy)]
+pub struct FirstJoin {
+    complete: bool,
+}
+
+impl FirstJoin {
+    /// Players that were saved before the first join setup existed have already joined once.
+    pub(super) fn completed() -> Self {
+        Self { complete: true }
+    }
+}
+
+#[derive(Deserialize)]
+struct StartingKitJson {
+    #[serde(default)]
+    items: Vec<StartingItemJson>,
+    #[serde(default)]
+    welcome_message: Vec<String>,
+    welcome_book: Option<WelcomeBookJson>,
+    tutorial_spawn: Option<IVec3>,
+}
+
+#[derive(Deserialize)]
+struct StartingItemJson {
+    item: String,
+    amount: u32,
+}
+
+#[derive(Deserialize)]
+struct WelcomeBookJson {
+    item: String,
+    text: String,
+}
+
+/// What new players are given when they first join.
+#[derive(Resource)]
+pub struct StartingKit {
+    items: Vec<(ItemId, u32)>,
+    /// Lines of text shown in the chat
+    welcome_message: Vec<String>,
+    /// An item with the text of the book as its description
+    welcome_book: Option<(ItemId, String)>,
+    /// Where new players are spawned, the world spawn is used if not set.
+    pub tutorial_spawn: Option<IVec3>,
+}
+
+fn load_starting_kit(mut commands: Commands, items: Res<Items>) {
+    let file = match std::fs::File::open(STARTING_KIT_PATH) {
+        Ok(f) => f,
+        Err(e) => panic!(
+            "Failed to open starting kit at path: {}\nError: {}",
+            STARTING_KIT_PATH, e
+        ),
+    };
+
+    let json: StartingKitJson = match serde_json::from_reader(file) {
+        Ok(k) => k,
+        Err(e) => panic!(
+            "Failed to read starting kit at path: {}\nError: {}",
+            STARTING_KIT_PATH, e
+        ),
+    };
+
+    let get_item = |name: &str| -> ItemId {
+        match items.get_id(name) {
+            Some(id) => id,
+            None => panic!(
+                "Failed to read the starting kit, there is no item with the name '{}'",
+                name
+            ),
+        }
+    };
+
+    commands.insert_resource(StartingKit {
+        items: json
+            .items
+            .iter()
+            .map(|item| (get_item(&item.item), item.amount))
+            .collect(),
+        welcome_message: json.welcome_message,
+        welcome_book: json
+            .welcome_book
+            .map(|book| (get_item(&book.item), book.text)),
+        tutorial_spawn: json.tutorial_spawn,
+    });
+}
+
+fn welcome_new_players(
+    mut commands: Commands,
+    net: Res<Server>,
+    items: Res<Items>,
+    starting_kit: Res<StartingKit>,
+    mut player_query: Query<(Entity, &mut FirstJoin, &mut Inventory, &Transform), Added<FirstJoin>>,
+) {
+    for (player_entity, mut first_join, mut inventory, transform) in player_query.iter_mut() {
+        if first_join.complete {
+            continue;
+        }
+        first_join.complete = true;
+
+        let mut item_stacks: Vec<ItemStack> = starting_kit
+            .items
+            .iter()
+            .map(|(item_id, amount)| ItemStack::new(items.get_config(item_id), *amount))
+            .collect();
+
+        if let Some((item_id, text)) = &starting_kit.welcome_book {
+            let mut book = ItemStack::new(items.get_config(item_id), 1);
+            if let Some(item) = book.item_mut() {
+                item.properties["description"] = text.clone().into();
+            }
+            item_stacks.push(book);
+        }
+
+        for mut item_stack in item_stacks {
+            inventory.insert(&mut item_stack);
+
+            // Drop what doesn't fit in the inventory
+            if !item_stack.is_empty() {
+                commands.spawn((
+                    DroppedItem::new(item_stack),
+                    Transform::from_translation(transform.translation),
+                ));
+            }
+        }
+
+        for line in starting_kit.welcome_message.iter() {
+            send_message(&net, player_entity, line.clone());
+        }
+    }
+}
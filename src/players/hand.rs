@@ -16,29 +16,53 @@ use fmc::{
 };
 
 use crate::{
-    items::{DroppedItem, ItemRegistry, ItemUseSystems, ItemUses},
-    players::Inventory,
+    chat::send_message,
+    items::{
+        DroppedItem, ItemRegistry, ItemUseSystems, ItemUses, loot_tables::BlockLoot,
+        tool_tiers::ToolTiers,
+    },
+    mobs::Mob,
+    players::{GameMode, Inventory, Sneaking},
+    settings::Settings,
+    world::{
+        block_variants::BlockVariants,
+        blocks::partial_blocks::placement_variant,
+        budgets::{ChunkBudgetSystems, ChunkBudgets},
+    },
 };
 
 pub struct HandPlugin;
 impl Plugin for HandPlugin {
     fn build(&self, app: &mut App) {
-        app.insert_resource(MiningEvents::default()).add_systems(
-            Update,
-            (
-                handle_left_clicks.in_set(HandSystems),
-                handle_right_clicks
-                    .in_set(ItemUseSystems)
-                    .in_set(HandSystems),
-                break_blocks.after(handle_left_clicks),
-            ),
-        );
+        app.insert_resource(MiningEvents::default())
+            .insert_resource(ReachViolations::default())
+            .add_message::<BlockMinedEvent>()
+            .add_systems(
+                Update,
+                (
+                    handle_left_clicks.in_set(HandSystems),
+                    handle_right_clicks
+                        .in_set(ItemUseSystems)
+                        .in_set(HandSystems)
+                        .after(ChunkBudgetSystems),
+                    break_blocks.after(handle_left_clicks),
+                    forget_reach_violations,
+                ),
+            );
     }
 }
 
 #[derive(SystemSet, Debug, Hash, PartialEq, Eq, Clone)]
 pub struct HandSystems;
 
+/// Sent when a player has mined a block with a tool that can harvest it
+#[derive(Message)]
+pub struct BlockMinedEvent {
+    pub player_entity: Entity,
+    pub block_id: BlockId,
+    pub block_position: BlockPosition,
+}
+
 /// Component that tracks when a player right clicks the entity
 #[derive(Component, Default)]
 pub struct HandInteractions {
@@ -71,15 +95,95 @@ impl HandHits {
     }
 }
 
+/// How many interactions out of reach a player can make before it is logged
+const REACH_STRIKES: u32 = 20;
+
+// Counts the interactions of each player that were out of reach. Clients pick what they interact
+// with, and a modified client could reach through walls or across the map.
+#[derive(Resource, Default)]
+struct ReachViolations(HashMap<Entity, u32>);
+
+impl ReachViolations {
+    fn strike(&mut self, player_entity: Entity, player: &Player) {
+        let strikes = self.0.entry(player_entity).or_default();
+        *strikes += 1;
+
+        if *strikes >= REACH_STRIKES {
+            *strikes = 0;
+            warn!(
+                "{} has tried to interact with {} things out of reach, they might be cheating.",
+                player.username, REACH_STRIKES
+            );
+        }
+    }
+}
+
+fn forget_reach_violations(
+    mut reach_violations: ResMut<ReachViolations>,
+    mut removed_players: RemovedComponents<Player>,
+) {
+    for player_entity in removed_players.read() {
+        reach_violations.0.remove(&player_entity);
+    }
+}
+
+// Checks that the target is within reach of the camera, and that there isn't a solid block in
+// between them.
+fn within_reach(
+    target: &Target,
+    camera_position: DVec3,
+    camera: &Camera,
+    reach: f64,
+    world_map: &WorldMap,
+) -> bool {
+    let distance = target.distance();
+    if distance > reach {
+        return false;
+    }
+
+    let target_block = match target {
+        Target::Block { block_position, .. } => Some(*block_position),
+        _ => None,
+    };
+
+    let blocks = Blocks::get();
+    let ray = Transform {
+        translation: camera_position,
+        rotation: camera.rotation,
+        ..default()
+    };
+    let mut raycast = world_map.raycast(&ray, distance);
+    while let Some(block_id) = raycast.next_block() {
+        // A little leeway, the target is hit at the edge of the block it is in.
+        if Some(raycast.position()) == target_block || raycast.distance() >= distance - 0.01 {
+            return true;
+        } else if blocks.get_config(&block_id).is_solid() {
+            return false;
+        }
+    }
+
+    return true;
+}
+
 fn handle_left_clicks(
     mut clicks: MessageReader<NetworkMessage<messages::LeftClick>>,
     models: Res<Models>,
+    world_map: Res<WorldMap>,
+    settings: Res<Settings>,
     mut player_query: Query<
-        (&Targets, &Camera, &GlobalTransform, &mut AnimationPlayer),
+        (
+            &Player,
+            &Targets,
+            &Camera,
+            &GlobalTransform,
+            &GameMode,
+            &mut AnimationPlayer,
+        ),
         With<Player>,
     >,
     mut hittable_entities: Query<(&mut HandHits, Option<&ModelVisibility>)>,
     mut mining_events: ResMut<MiningEvents>,
+    mut reach_violations: ResMut<ReachViolations>,
     mut click_tracker: Local<HashSet<Entity>>,
 ) {
     for (mut hand_hits, _) in hittable_entities.iter_mut() {
@@ -89,8 +193,17 @@ fn handle_left_clicks(
     }
 
     for click in clicks.read() {
-        let (targets, camera, transform, mut animation_player) =
-            player_query.get_mut(click.player_entity).unwrap();
+        // Players that are still loading have nothing to click with
+        let Ok((player, targets, camera, transform, game_mode, mut animation_player)) =
+            player_query.get_mut(click.player_entity)
+        else {
+            continue;
+        };
+
+        // Spectators can look, but not touch
+        if *game_mode == GameMode::Spectator {
+            continue;
+        }
 
         let camera_position = transform.translation() + camera.translation;
 
@@ -119,16 +232,39 @@ fn handle_left_clicks(
                     let block_config = Blocks::get().get_config(block_id);
 
                     if block_config.hardness.is_some() {
+                        let creative = *game_mode == GameMode::Creative;
+                        // Creative players break a block each time they click, holding the
+                        // button down doesn't tear through everything in front of them.
+                        if creative && !first_click {
+                            break;
+                        }
+
+                        if !within_reach(
+                            target,
+                            camera_position,
+                            camera,
+                            settings.reach,
+                            &world_map,
+                        ) {
+                            // Holding the button down sends a click every tick, only count
+                            // the first.
+                            if first_click {
+                                reach_violations.strike(click.player_entity, player);
+                            }
+                            break;
+                        }
+
                         let hit_position = camera_position + camera.forward() * *distance;
                         mining_events.insert(
-                            *block_position,
-                            (
-                                click.player_entity,
-                                *block_id,
-                                *block_face,
+                            click.player_entity,
+                            MiningEvent {
+                                block_position: *block_position,
+                                block_id: *block_id,
+                                block_face: *block_face,
                                 hit_position,
-                                *entity,
-                            ),
+                                block_entity: *entity,
+                                creative,
+                            },
                         );
 
                         break;
@@ -139,6 +275,16 @@ fn handle_left_clicks(
                         if matches!(maybe_visibility, Some(ModelVisibility::Hidden)) {
                             continue;
                         }
+                        if !within_reach(
+                            target,
+                            camera_position,
+                            camera,
+                            settings.reach,
+                            &world_map,
+                        ) {
+                            reach_violations.strike(click.player_entity, player);
+                            break;
+                        }
                         hits.push(click.player_entity);
                     }
                 }
@@ -148,15 +294,29 @@ fn handle_left_clicks(
     }
 }
 
+#[derive(Debug)]
+struct MiningEvent {
+    block_position: BlockPosition,
+    block_id: BlockId,
+    block_face: BlockFace,
+    hit_position: DVec3,
+    block_entity: Option<Entity>,
+    // Creative players break blocks instantly and get nothing from them
+    creative: bool,
+}
+
+// A player can only mine one block at a time, but several players can mine the same block.
 #[derive(Resource, Deref, DerefMut, Default, Debug)]
-struct MiningEvents(HashMap<BlockPosition, (Entity, BlockId, BlockFace, DVec3, Option<Entity>)>);
+struct MiningEvents(HashMap<Entity, MiningEvent>);
 
 // Keeps the state of how far along a block is to breaking
 #[derive(Debug)]
 struct BreakingBlock {
     model_entity: Entity,
     progress: f32,
-    prev_hit: std::time::Instant,
+    // When each of the players mining the block last hit it. They each add to the progress for
+    // the time since their own last hit.
+    miners: HashMap<Entity, std::time::Instant>,
     particle_timer: Timer,
 }
 
@@ -168,6 +328,7 @@ fn break_blocks(
     time: Res<Time>,
     net: Res<Server>,
     items: Res<Items>,
+    (tool_tiers, block_loot): (Res<ToolTiers>, Res<BlockLoot>),
     models: Res<Models>,
     world_map: Res<WorldMap>,
     particle_effects: Res<ParticleEffects>,
@@ -175,7 +336,10 @@ fn break_blocks(
     inventory_query: Query<&Inventory, With<Player>>,
     block_model_query: Query<&Transform, (With<BlockPosition>, With<Model>)>,
     mut breaking_model_query: Query<(&mut Model, &mut ModelVisibility), With<BreakingBlockMarker>>,
-    mut block_update_writer: MessageWriter<BlockUpdate>,
+    (mut block_update_writer, mut block_mined_events): (
+        MessageWriter<BlockUpdate>,
+        MessageWriter<BlockMinedEvent>,
+    ),
     mut mining_events: ResMut<MiningEvents>,
     mut being_broken: Local<HashMap<BlockPosition, BreakingBlock>>,
     mut rng: Local<Rng>,
@@ -184,9 +348,25 @@ fn break_blocks(
 
     let blocks = Blocks::get();
 
-    for (block_position, (player_entity, block_id, block_face, hit_position, maybe_block_entity)) in
-        mining_events.drain()
+    // Several players can finish the same block at once, it should only break for one of them.
+    let mut broken_blocks = HashSet::new();
+
+    for (
+        player_entity,
+        MiningEvent {
+            block_position,
+            block_id,
+            block_face,
+            hit_position,
+            block_entity: maybe_block_entity,
+            creative,
+        },
+    ) in mining_events.drain()
     {
+        if broken_blocks.contains(&block_position) {
+            continue;
+        }
+
         let block_config = blocks.get_config(&block_id);
 
         let Some(hardness) = block_config.hardness else {
@@ -196,19 +376,22 @@ fn break_blocks(
 
         let inventory = inventory_query.get(player_entity).unwrap();
 
-        let tool_config = if let Some(item) = inventory.held_item_stack().item() {
-            Some(items.get_config(&item.id))
-        } else {
-            None
-        };
-
-        let broken = if let Some(breaking_block) = being_broken.get_mut(&block_position) {
-            if (now - breaking_block.prev_hit).as_secs_f32() > 0.05 {
+        let held_item_id = inventory.held_item_stack().item().map(|item| item.id);
+        let tool_config = held_item_id.map(|item_id| items.get_config(&item_id));
+
+        let broken = if creative {
+            true
+        } else if let Some(breaking_block) = being_broken.get_mut(&block_position) {
+            let Some(prev_hit) = breaking_block
+                .miners
+                .insert(player_entity, now)
+                .filter(|prev_hit| (now - *prev_hit).as_secs_f32() <= 0.05)
+            else {
                 // The interval between two clicks needs to be short in order to be counted as
-                // holding the button down.
-                breaking_block.prev_hit = now;
+                // holding the button down. Players that join in start adding to the progress on
+                // their next hit.
                 continue;
-            }
+            };
 
             if breaking_block.particle_timer.is_finished() {
                 let chunk_position = ChunkPosition::from(block_position);
@@ -248,9 +431,7 @@ fn break_blocks(
             } else {
                 1.0
             };
-            breaking_block.progress +=
-                (now - breaking_block.prev_hit).as_secs_f32() / hardness * efficiency;
-            breaking_block.prev_hit = now;
+            breaking_block.progress += (now - prev_hit).as_secs_f32() / hardness * efficiency;
 
             let progress = breaking_block.progress;
 
@@ -302,6 +483,8 @@ fn break_blocks(
 
         // When hardness is zero it will break instantly
         if broken || hardness == 0.0 {
+            broken_blocks.insert(block_position);
+
             let chunk_position = ChunkPosition::from(block_position);
             if let Some(subscribers) = chunk_subscriptions.get_subscribers(&chunk_position) {
                 let position = block_position.as_dvec3() + DVec3::splat(0.5);
@@ -338,12 +521,25 @@ fn break_blocks(
                 block_data: None,
             });
 
-            let Some(dropped_item_id) = block_config.drop(tool_config) else {
+            if creative || !tool_tiers.can_harvest(block_id, held_item_id) {
                 continue;
-            };
+            }
+
+            block_mined_events.write(BlockMinedEvent {
+                player_entity,
+                block_id,
+                block_position,
+            });
 
-            let item_config = items.get_config(&dropped_item_id);
-            let item_stack = ItemStack::new(item_config, 1);
+            let Some(item_stack) = block_loot.drop(
+                block_id,
+                block_config.drop(tool_config),
+                inventory.held_item_stack(),
+                &items,
+                &mut rng,
+            ) else {
+                continue;
+            };
 
             commands.spawn((
                 DroppedItem::new(item_stack),
@@ -401,16 +597,20 @@ fn break_blocks(
                 BreakingBlock {
                     model_entity,
                     progress: 0.0,
-                    prev_hit: std::time::Instant::now(),
+                    miners: HashMap::from([(player_entity, now)]),
                     particle_timer,
                 },
             );
         }
     }
 
-    // Remove break progress after not being hit for 0.5 seconds.
+    // Players stop mining after not hitting for 0.5 seconds, and the break progress is removed
+    // once no one is mining the block.
     being_broken.retain(|_, breaking_block| {
-        let remove_timout = (now - breaking_block.prev_hit).as_secs_f32() > 0.5;
+        breaking_block
+            .miners
+            .retain(|_, prev_hit| (now - *prev_hit).as_secs_f32() <= 0.5);
+        let remove_timout = breaking_block.miners.is_empty();
         let remove_broken = breaking_block.progress >= 1.0;
 
         if remove_timout || remove_broken {
@@ -744,22 +944,34 @@ fn build_breaking_model(block_config: &BlockConfig, models: &Models) -> (Model,
 fn handle_right_clicks(
     net: Res<Server>,
     world_map: Res<WorldMap>,
-    items: Res<Items>,
-    item_registry: Res<ItemRegistry>,
+    (items, item_registry): (Res<Items>, Res<ItemRegistry>),
     model_map: Res<ModelMap>,
     chunk_subscriptions: Res<ChunkSubscriptions>,
+    settings: Res<Settings>,
+    block_variants: Res<BlockVariants>,
     model_query: Query<(&Collider, &GlobalTransform), (With<Model>, Without<BlockPosition>)>,
-    mut player_query: Query<(&mut Inventory, &Targets, &Camera), With<Player>>,
+    body_query: Query<
+        (&Collider, &GlobalTransform, Option<&GameMode>),
+        Or<(With<Player>, With<Mob>)>,
+    >,
+    mut player_query: Query<(
+        &Player,
+        &mut Inventory,
+        &Targets,
+        &Camera,
+        &GlobalTransform,
+        &GameMode,
+        &Sneaking,
+    )>,
     mut item_use_query: Query<&mut ItemUses>,
     mut hand_interaction_query: Query<&mut HandInteractions>,
+    (mut reach_violations, mut chunk_budgets): (ResMut<ReachViolations>, ResMut<ChunkBudgets>),
     mut block_update_writer: MessageWriter<BlockUpdate>,
     mut clicks: MessageReader<NetworkMessage<messages::RightClick>>,
     mut rng: Local<Rng>,
 ) {
-    // TODO: ActionOrder currently does nothing, but there needs to be some system for deviating
-    // from the set order. Like if you hold shift, placing blocks should take precedence over
-    // interacting. And there's a bunch of stuff like this where you want to do something else
-    // depending on some condition.
+    // Each click goes through the actions in order until one of them does something. Sneaking
+    // skips interacting, so blocks can be placed against chests and crafting tables.
     enum ActionOrder {
         Interact,
         PlaceBlock,
@@ -767,10 +979,38 @@ fn handle_right_clicks(
     }
 
     for right_click in clicks.read() {
-        let (mut inventory, targets, camera) =
-            player_query.get_mut(right_click.player_entity).unwrap();
+        // Releases are only of interest to items that are held down, like bows.
+        if right_click.message == messages::RightClick::Release {
+            continue;
+        }
+
+        // Players that are still loading have nothing to click with
+        let Ok((player, mut inventory, targets, camera, transform, game_mode, sneaking)) =
+            player_query.get_mut(right_click.player_entity)
+        else {
+            continue;
+        };
+
+        if *game_mode == GameMode::Spectator {
+            continue;
+        }
+
+        let camera_position = transform.translation() + camera.translation;
+        // Interactions out of reach are dropped
+        let mut reachable = |target: &Target| {
+            let reachable =
+                within_reach(target, camera_position, camera, settings.reach, &world_map);
+            if !reachable {
+                reach_violations.strike(right_click.player_entity, player);
+            }
+            reachable
+        };
 
-        let mut action = ActionOrder::Interact;
+        let mut action = if **sneaking {
+            ActionOrder::PlaceBlock
+        } else {
+            ActionOrder::Interact
+        };
 
         'outer: loop {
             match action {
@@ -781,7 +1021,9 @@ fn handle_right_clicks(
                         };
 
                         if let Ok(mut interactions) = hand_interaction_query.get_mut(entity) {
-                            interactions.push(right_click.player_entity);
+                            if reachable(target) {
+                                interactions.push(right_click.player_entity);
+                            }
                             break 'outer;
                         }
                     }
@@ -791,15 +1033,26 @@ fn handle_right_clicks(
                 ActionOrder::PlaceBlock => {
                     let blocks = Blocks::get();
 
-                    let Some(Target::Block {
+                    let Some(target) =
+                        targets.get_first_block(|block_id| blocks.get_config(block_id).is_solid())
+                    else {
+                        action = ActionOrder::UseItem;
+                        continue;
+                    };
+
+                    if !reachable(target) {
+                        break;
+                    }
+
+                    let Target::Block {
                         block_position,
                         block_id,
                         block_face,
+                        distance,
                         ..
-                    }) = targets.get_first_block(|block_id| blocks.get_config(block_id).is_solid())
+                    } = target
                     else {
-                        action = ActionOrder::UseItem;
-                        continue;
+                        unreachable!()
                     };
 
                     let blocks = Blocks::get();
@@ -814,6 +1067,13 @@ fn handle_right_clicks(
                         &blocks,
                         &world_map,
                     ) {
+                        let hit_position = camera_position + camera.forward() * *distance;
+                        let block_id = placement_variant(
+                            &block_variants,
+                            block_id,
+                            *block_face,
+                            hit_position.y,
+                        );
                         let block_config = blocks.get_config(&block_id);
                         let block_state = block_config.placement_rotation(*block_face, camera);
 
@@ -825,27 +1085,58 @@ fn handle_right_clicks(
                             .unwrap_or_default();
 
                         let replaced_block_transform = Transform {
-                            translation: block_position.as_dvec3() + DVec3::splat(0.5),
+                            translation: replaced_block_position.as_dvec3() + DVec3::splat(0.5),
                             rotation,
                             ..default()
                         };
 
-                        for (model_collider, global_transform) in
-                            model_query.iter_many(model_map.iter_entities(&chunk_position))
-                        {
-                            if model_collider
+                        let intersects = |collider: &Collider, transform: &GlobalTransform| {
+                            collider
                                 .intersection(
-                                    &global_transform.compute_transform(),
+                                    &transform.compute_transform(),
                                     &replaced_block_transform,
                                     &block_config.collider,
                                 )
                                 .is_some()
-                            {
-                                continue;
-                            }
+                        };
+
+                        // Blocks can't be placed inside players, mobs or other models. Blocks
+                        // that can be walked through, like torches, are let through if the
+                        // server allows it.
+                        let can_overlap =
+                            !block_config.is_solid() && settings.place_non_solid_in_entities;
+                        let obstructed = !can_overlap
+                            && (model_query
+                                .iter_many(model_map.iter_entities(&chunk_position))
+                                .any(|(collider, transform)| intersects(collider, transform))
+                                || body_query.iter().any(|(collider, transform, game_mode)| {
+                                    game_mode != Some(&GameMode::Spectator)
+                                        && transform
+                                            .translation()
+                                            .distance_squared(replaced_block_transform.translation)
+                                            < 16.0
+                                        && intersects(collider, transform)
+                                }));
+
+                        if obstructed {
+                            break;
                         }
 
-                        equipped_item_stack.take(1);
+                        if !chunk_budgets.can_place_block(&chunk_position, &block_id) {
+                            send_message(
+                                &net,
+                                right_click.player_entity,
+                                "There are too many blocks like this in the area, it can't be placed"
+                                    .to_owned(),
+                            );
+                            break;
+                        }
+                        chunk_budgets.add_block(chunk_position, &block_id);
+
+                        // Creative players have an endless supply of every block
+                        if *game_mode != GameMode::Creative {
+                            equipped_item_stack.take(1);
+                        }
 
                         if let Some(subscribers) =
                             chunk_subscriptions.get_subscribers(&chunk_position)
@@ -1,5 +1,6 @@
 use fmc::{
     bevy::math::DVec3,
+    blocks::BlockPosition,
     interfaces::{InterfaceEvents, InterfaceSystems, RegisterInterfaceNode},
     items::ItemStack,
     networking::{NetworkMessage, Server},
@@ -8,19 +9,24 @@ use fmc::{
     prelude::*,
     protocol::messages,
     random::Rng,
+    world::WorldMap,
 };
 
 use serde::{Deserialize, Serialize};
 
-use crate::items::DroppedItem;
+use crate::{items::DroppedItem, world::game_rules::GameRules};
 
-use super::{Equipment, GameMode, Inventory, RespawnEvent, movement::MovementPluginPacket};
+use super::{
+    Equipment, GameMode, Inventory, RespawnEvent,
+    movement::{ClimbableBlocks, MovementPluginPacket},
+};
 
 pub struct HealthPlugin;
 impl Plugin for HealthPlugin {
     fn build(&self, app: &mut App) {
         app.add_message::<PlayerDamageEvent>()
             .add_message::<HealEvent>()
+            .add_message::<PlayerLandEvent>()
             .add_systems(
                 Update,
                 (
@@ -125,13 +131,29 @@ pub struct HealEvent {
     pub healing: u32,
 }
 
+/// Sent when a player lands after falling at least one block, jumps included.
+#[derive(Message)]
+pub struct PlayerLandEvent {
+    pub player_entity: Entity,
+    pub position: DVec3,
+    /// How many blocks the player fell
+    pub distance: u32,
+}
+
+/// Tracks how far the player has fallen
 #[derive(Component)]
-struct FallDamage {
+pub struct FallDamage {
     hearts: u32,
     last_position: DVec3,
     last_update: std::time::Instant,
 }
 
+impl FallDamage {
+    pub fn is_falling(&self) -> bool {
+        self.hearts > 0
+    }
+}
+
 impl Default for FallDamage {
     fn default() -> Self {
         Self {
@@ -144,22 +166,46 @@ impl Default for FallDamage {
 }
 
 fn fall_damage(
+    world_map: Res<WorldMap>,
+    climbable_blocks: Res<ClimbableBlocks>,
     mut fall_damage_query: Query<&mut FallDamage, With<Player>>,
     mut position_events: MessageReader<NetworkMessage<messages::PlayerPosition>>,
     mut damage_events: MessageWriter<PlayerDamageEvent>,
+    mut land_events: MessageWriter<PlayerLandEvent>,
 ) {
     for position_update in position_events.read() {
-        let mut fall_damage = fall_damage_query
-            .get_mut(position_update.player_entity)
-            .unwrap();
+        let Ok(mut fall_damage) = fall_damage_query.get_mut(position_update.player_entity) else {
+            continue;
+        };
 
         let now = std::time::Instant::now();
+
+        // Grabbing onto something climbable stops the fall, the same as in the movement plugin.
+        let block_position = BlockPosition::from(position_update.position);
+        if world_map
+            .get_block(block_position)
+            .is_some_and(|block_id| climbable_blocks.get(block_id).is_some())
+        {
+            fall_damage.hearts = 0;
+            fall_damage.last_position = position_update.position;
+            fall_damage.last_update = now;
+            continue;
+        }
+
         // TODO: The velocity is not stable when falling? Varies greatly from values of -8 to -3
         // to -20 where it should be either strictly increasing or decreasing
         // This will sometimes cause fall damage to be negated.
         let velocity = (position_update.position.y - fall_damage.last_position.y)
             / now.duration_since(fall_damage.last_update).as_secs_f64();
         if velocity > -0.1 {
+            if fall_damage.hearts != 0 {
+                land_events.write(PlayerLandEvent {
+                    player_entity: position_update.player_entity,
+                    position: position_update.position,
+                    distance: fall_damage.hearts,
+                });
+            }
+
             if fall_damage.hearts.saturating_sub(3) != 0 {
                 damage_events.write(PlayerDamageEvent {
                     player_entity: position_update.player_entity,
@@ -186,6 +232,7 @@ fn change_health(
     mut commands: Commands,
     net: Res<Server>,
     time: Res<Time>,
+    game_rules: Res<GameRules>,
     mut health_query: Query<(
         Entity,
         &GameMode,
@@ -261,7 +308,8 @@ fn change_health(
                 &mut equipment.leggings,
                 &mut equipment.boots,
             ]) {
-                if item_stack.is_empty() {
+                // The items stay with the player
+                if item_stack.is_empty() || game_rules.keep_inventory() {
                     continue;
                 }
 
@@ -1,6 +1,6 @@
 use fmc::{
     interfaces::{HeldInterfaceStack, InterfaceEvents, InterfaceSystems, RegisterInterfaceNode},
-    items::{ItemStack, Items},
+    items::{ItemId, ItemStack, Items},
     networking::{NetworkMessage, Server},
     players::Player,
     prelude::*,
@@ -8,7 +8,11 @@ use fmc::{
 };
 
 use crate::{
-    items::crafting::{CraftingGrid, Recipes},
+    items::{
+        PickupEvent,
+        crafting::{CraftEvent, CraftingGrid, Recipes},
+        tooltips::ItemTooltips,
+    },
     players::{Equipment, Inventory},
 };
 
@@ -29,9 +33,12 @@ impl Plugin for InventoryInterfacePlugin {
                     handle_equipment_events::<BootsNode>,
                     handle_crafting_input_events,
                     handle_crafting_output_events,
+                    handle_craft_all_button,
+                    handle_sort_button,
                 )
                     .in_set(InterfaceSystems::HandleEvents),
                 equip_item,
+                flash_hotbar,
             ),
         );
     }
@@ -44,6 +51,10 @@ fn initialize_interface(
     mut registration_events: MessageWriter<RegisterInterfaceNode>,
 ) {
     for player_entity in new_player_query.iter() {
+        commands
+            .entity(player_entity)
+            .insert((SentItemBoxes::default(), HotbarFlashes::default()));
+
         commands.entity(player_entity).with_children(|parent| {
             let inventory_entity = parent.spawn(InventoryNode).id();
             registration_events.write(RegisterInterfaceNode {
@@ -100,6 +111,20 @@ fn initialize_interface(
                 node_path: String::from("inventory/crafting_output"),
                 node_entity: crafting_output_entity,
             });
+
+            let craft_all_entity = parent.spawn(CraftAllButton).id();
+            registration_events.write(RegisterInterfaceNode {
+                player_entity,
+                node_path: String::from("inventory/craft_all"),
+                node_entity: craft_all_entity,
+            });
+
+            let sort_button_entity = parent.spawn(SortButton).id();
+            registration_events.write(RegisterInterfaceNode {
+                player_entity,
+                node_path: String::from("inventory/sort"),
+                node_entity: sort_button_entity,
+            });
         });
 
         let mut crafting_items_boxes = messages::InterfaceItemBoxUpdate::default();
@@ -108,82 +133,240 @@ fn initialize_interface(
         }
 
         net.send_one(player_entity, crafting_items_boxes);
+
+        let mut flash_visibility = messages::InterfaceNodeVisibilityUpdate::default();
+        for slot in 0..HOTBAR_SIZE {
+            flash_visibility.set_hidden(format!("hotbar_flash/{}", slot));
+        }
+        net.send_one(player_entity, flash_visibility);
     }
 }
 
-fn send_server_updates(
+const HOTBAR_SIZE: usize = 9;
+/// How long a hotbar slot lights up for when it receives picked up items, in seconds.
+const FLASH_DURATION: f32 = 0.25;
+
+/// Hotbar slots that are lit up, and the time left until they go dark.
+#[derive(Component, Default)]
+struct HotbarFlashes(Vec<(usize, Timer)>);
+
+fn flash_hotbar(
     net: Res<Server>,
-    inventory_query: Query<(Entity, &Inventory), Changed<Inventory>>,
-    equipment_query: Query<(Entity, &Equipment), Changed<Equipment>>,
+    time: Res<Time>,
+    mut players: Query<(Entity, &mut HotbarFlashes)>,
+    mut pickup_events: MessageReader<PickupEvent>,
 ) {
-    for (player_entity, inventory) in inventory_query.iter() {
-        let mut inventory_node = messages::InterfaceItemBoxUpdate::default();
-
-        for (i, item_stack) in inventory.iter().skip(9).enumerate() {
-            if let Some(item) = item_stack.item() {
-                inventory_node.add_itembox(
-                    "inventory",
-                    i as u32,
-                    item.id,
-                    item_stack.size(),
-                    item.properties["durability"].as_u64().map(|v| v as u32),
-                    item.properties["description"].as_str(),
-                );
+    for pickup in pickup_events.read() {
+        if pickup.slot >= HOTBAR_SIZE {
+            continue;
+        }
+
+        let Ok((_, mut flashes)) = players.get_mut(pickup.player_entity) else {
+            continue;
+        };
+
+        let timer = Timer::from_seconds(FLASH_DURATION, TimerMode::Once);
+        if let Some((_, existing)) = flashes.0.iter_mut().find(|(slot, _)| *slot == pickup.slot) {
+            *existing = timer;
+            continue;
+        }
+        flashes.0.push((pickup.slot, timer));
+
+        let mut visibility = messages::InterfaceNodeVisibilityUpdate::default();
+        visibility.set_visible(format!("hotbar_flash/{}", pickup.slot));
+        net.send_one(pickup.player_entity, visibility);
+    }
+
+    for (player_entity, mut flashes) in players.iter_mut() {
+        if flashes.0.is_empty() {
+            continue;
+        }
+
+        let mut visibility = messages::InterfaceNodeVisibilityUpdate::default();
+        let mut changed = false;
+        flashes.0.retain_mut(|(slot, timer)| {
+            timer.tick(time.delta());
+            if timer.is_finished() {
+                visibility.set_hidden(format!("hotbar_flash/{}", slot));
+                changed = true;
+                false
             } else {
-                inventory_node.add_empty_itembox("inventory", i as u32);
-                //inventory_node.add_itembox("inventory", i as u32, 1, 2, None, None);
+                true
             }
+        });
+
+        if changed {
+            net.send_one(player_entity, visibility);
         }
-        net.send_one(player_entity, inventory_node);
-
-        let mut hotbar_node = messages::InterfaceItemBoxUpdate::default();
-
-        for (i, item_stack) in inventory.iter().enumerate().take(9) {
-            if let Some(item) = item_stack.item() {
-                hotbar_node.add_itembox(
-                    "hotbar",
-                    i as u32,
-                    item.id,
-                    item_stack.size(),
-                    item.properties["durability"].as_u64().map(|v| v as u32),
-                    item.properties["description"].as_str(),
-                );
+    }
+}
+
+/// How often the whole inventory is sent even if it hasn't changed, in case the client has gotten
+/// out of sync.
+const FULL_REFRESH_INTERVAL: f32 = 30.0;
+
+/// What an item box shows on the client.
+#[derive(PartialEq)]
+struct ItemBox {
+    item_id: ItemId,
+    size: u32,
+    durability: Option<u32>,
+    description: Option<String>,
+}
+
+impl ItemBox {
+    fn new(item_stack: &ItemStack, tooltips: &ItemTooltips) -> Option<Self> {
+        let item = item_stack.item()?;
+        Some(Self {
+            item_id: item.id,
+            size: item_stack.size(),
+            durability: item.properties["durability"].as_u64().map(|v| v as u32),
+            description: tooltips.get(item_stack),
+        })
+    }
+}
+
+/// The item boxes the client was last sent. When the inventory changes, only the boxes that
+/// differ from what the client has are sent.
+#[derive(Component)]
+pub struct SentItemBoxes {
+    // Hotbar first, then the inventory, same as the [Inventory]. Empty until the first update.
+    inventory: Vec<Option<ItemBox>>,
+    equipment: [Option<ItemBox>; 4],
+    refresh_timer: Timer,
+}
+
+impl Default for SentItemBoxes {
+    fn default() -> Self {
+        Self {
+            inventory: Vec::new(),
+            equipment: Default::default(),
+            refresh_timer: Timer::from_seconds(FULL_REFRESH_INTERVAL, TimerMode::Repeating),
+        }
+    }
+}
+
+impl SentItemBoxes {
+    // The client moves items around in its own interface before the server does. Once the
+    // server has done the same, the client already shows what is in the inventory.
+    fn sync_inventory(&mut self, inventory: &[ItemStack], tooltips: &ItemTooltips) {
+        for (sent, item_stack) in self.inventory.iter_mut().zip(inventory) {
+            *sent = ItemBox::new(item_stack, tooltips);
+        }
+    }
+
+    /// Add the whole inventory to the update. Used by interfaces that move items into the
+    /// inventory, so the client gets the inventory and their own item boxes in one message.
+    pub fn add_inventory(
+        &mut self,
+        update: &mut messages::InterfaceItemBoxUpdate,
+        inventory: &[ItemStack],
+        tooltips: &ItemTooltips,
+    ) {
+        for (i, item_stack) in inventory.iter().enumerate() {
+            let item_box = ItemBox::new(item_stack, tooltips);
+            if i < HOTBAR_SIZE {
+                add_item_box(update, "hotbar", i as u32, &item_box);
             } else {
-                hotbar_node.add_empty_itembox("hotbar", i as u32);
-                //hotbar_node.add_itembox("hotbar", i as u32, 1, 2, None, None);
-                //inventory.add_itembox(
-                //    1, i as u32, 1, 2, None,
-                //    None,
-                //);
+                add_item_box(update, "inventory", (i - HOTBAR_SIZE) as u32, &item_box);
             }
         }
+        self.sync_inventory(inventory, tooltips);
+    }
+}
 
-        net.send_one(player_entity, hotbar_node);
+fn add_item_box(
+    update: &mut messages::InterfaceItemBoxUpdate,
+    interface_path: &str,
+    index: u32,
+    item_box: &Option<ItemBox>,
+) {
+    if let Some(item_box) = item_box {
+        update.add_itembox(
+            interface_path,
+            index,
+            item_box.item_id,
+            item_box.size,
+            item_box.durability,
+            item_box.description.as_deref(),
+        );
+    } else {
+        update.add_empty_itembox(interface_path, index);
     }
+}
 
-    for (player_entity, equipment) in equipment_query.iter() {
-        let mut equipment_node = messages::InterfaceItemBoxUpdate::default();
-        for (item_stack, interface_path) in [
-            (&equipment.helmet, "equipment/helmet"),
-            (&equipment.chestplate, "equipment/chestplate"),
-            (&equipment.leggings, "equipment/leggings"),
-            (&equipment.boots, "equipment/boots"),
-        ] {
-            if let Some(item) = item_stack.item() {
-                equipment_node.add_itembox(
-                    interface_path,
-                    0,
-                    item.id,
-                    item_stack.size(),
-                    item.properties["durability"].as_u64().map(|v| v as u32),
-                    item.properties["description"].as_str(),
-                );
-            } else {
-                equipment_node.add_empty_itembox(interface_path, 0);
+fn send_server_updates(
+    net: Res<Server>,
+    time: Res<Time>,
+    tooltips: ItemTooltips,
+    mut player_query: Query<(Entity, Ref<Inventory>, Ref<Equipment>, &mut SentItemBoxes)>,
+) {
+    for (player_entity, inventory, equipment, mut sent) in player_query.iter_mut() {
+        sent.refresh_timer.tick(time.delta());
+        let refresh = sent.refresh_timer.just_finished() || sent.inventory.is_empty();
+
+        if refresh || inventory.is_changed() {
+            sent.inventory.resize_with(inventory.len(), || None);
+
+            let mut hotbar_node = messages::InterfaceItemBoxUpdate::default();
+            let mut inventory_node = messages::InterfaceItemBoxUpdate::default();
+            let mut hotbar_changed = false;
+            let mut inventory_changed = false;
+
+            for (i, item_stack) in inventory.iter().enumerate() {
+                let item_box = ItemBox::new(item_stack, &tooltips);
+                if !refresh && sent.inventory[i] == item_box {
+                    continue;
+                }
+
+                if i < 9 {
+                    add_item_box(&mut hotbar_node, "hotbar", i as u32, &item_box);
+                    hotbar_changed = true;
+                } else {
+                    add_item_box(&mut inventory_node, "inventory", i as u32 - 9, &item_box);
+                    inventory_changed = true;
+                }
+
+                sent.inventory[i] = item_box;
+            }
+
+            if inventory_changed {
+                net.send_one(player_entity, inventory_node);
+            }
+
+            if hotbar_changed {
+                net.send_one(player_entity, hotbar_node);
             }
         }
 
-        net.send_one(player_entity, equipment_node);
+        if refresh || equipment.is_changed() {
+            let mut equipment_node = messages::InterfaceItemBoxUpdate::default();
+            let mut equipment_changed = false;
+
+            for (i, (item_stack, interface_path)) in [
+                (&equipment.helmet, "equipment/helmet"),
+                (&equipment.chestplate, "equipment/chestplate"),
+                (&equipment.leggings, "equipment/leggings"),
+                (&equipment.boots, "equipment/boots"),
+            ]
+            .into_iter()
+            .enumerate()
+            {
+                let item_box = ItemBox::new(item_stack, &tooltips);
+                if !refresh && sent.equipment[i] == item_box {
+                    continue;
+                }
+
+                add_item_box(&mut equipment_node, interface_path, 0, &item_box);
+                equipment_changed = true;
+
+                sent.equipment[i] = item_box;
+            }
+
+            if equipment_changed {
+                net.send_one(player_entity, equipment_node);
+            }
+        }
     }
 }
 
@@ -191,19 +374,25 @@ fn send_server_updates(
 struct InventoryNode;
 
 fn handle_inventory_events(
-    mut inventory_query: Query<(&mut Inventory, &mut HeldInterfaceStack), With<Player>>,
+    tooltips: ItemTooltips,
+    mut inventory_query: Query<
+        (&mut Inventory, &mut HeldInterfaceStack, &mut SentItemBoxes),
+        With<Player>,
+    >,
     mut interface_events: Query<
         (&mut InterfaceEvents, &ChildOf),
         (Changed<InterfaceEvents>, With<InventoryNode>),
     >,
 ) {
     for (mut events, parent) in interface_events.iter_mut() {
-        let (mut inventory, mut held_item) = inventory_query.get_mut(parent.0).unwrap();
+        let (mut inventory, mut held_item, mut sent) = inventory_query.get_mut(parent.0).unwrap();
         let inventory = inventory.bypass_change_detection();
 
         for event in events.read() {
             held_item.transfer(&event, &mut inventory[9..]);
         }
+
+        sent.sync_inventory(inventory, &tooltips);
     }
 }
 
@@ -211,19 +400,25 @@ fn handle_inventory_events(
 struct HotbarNode;
 
 fn handle_hotbar_events(
-    mut inventory_query: Query<(&mut Inventory, &mut HeldInterfaceStack), With<Player>>,
+    tooltips: ItemTooltips,
+    mut inventory_query: Query<
+        (&mut Inventory, &mut HeldInterfaceStack, &mut SentItemBoxes),
+        With<Player>,
+    >,
     mut interface_events: Query<
         (&mut InterfaceEvents, &ChildOf),
         (Changed<InterfaceEvents>, With<HotbarNode>),
     >,
 ) {
     for (mut events, parent) in interface_events.iter_mut() {
-        let (mut inventory, mut held_item) = inventory_query.get_mut(parent.0).unwrap();
+        let (mut inventory, mut held_item, mut sent) = inventory_query.get_mut(parent.0).unwrap();
         let inventory = inventory.bypass_change_detection();
 
         for event in events.read() {
             held_item.transfer(&event, &mut inventory[..9]);
         }
+
+        sent.sync_inventory(inventory, &tooltips);
     }
 }
 
@@ -319,6 +514,7 @@ struct CraftingInput;
 fn handle_crafting_input_events(
     net: Res<Server>,
     recipes: Res<Recipes>,
+    tooltips: ItemTooltips,
     mut inventory_query: Query<(Entity, &mut HeldInterfaceStack, &mut CraftingGrid), With<Player>>,
     mut interface_events: Query<
         (&mut InterfaceEvents, &ChildOf),
@@ -340,7 +536,7 @@ fn handle_crafting_input_events(
                     output.item().unwrap().id,
                     output.capacity(),
                     None,
-                    None,
+                    tooltips.get(&output).as_deref(),
                 );
             } else {
                 update.add_empty_itembox("inventory/crafting_output", 0);
@@ -357,11 +553,13 @@ struct CraftingOutput;
 fn handle_crafting_output_events(
     net: Res<Server>,
     recipes: Res<Recipes>,
+    tooltips: ItemTooltips,
     mut inventory_query: Query<(Entity, &mut CraftingGrid, &mut HeldInterfaceStack), With<Player>>,
     mut interface_events: Query<
         (&mut InterfaceEvents, &ChildOf),
         (Changed<InterfaceEvents>, With<CraftingOutput>),
     >,
+    mut craft_events: MessageWriter<CraftEvent>,
 ) {
     for (mut events, parent) in interface_events.iter_mut() {
         for event in events.read() {
@@ -384,44 +582,171 @@ fn handle_crafting_output_events(
                 if let Some(mut item_stack) =
                     recipes.get("crafting").craft(&mut crafting_input, amount)
                 {
+                    craft_events.write(CraftEvent {
+                        player_entity,
+                        collection: "crafting",
+                        item_id: item_stack.item().unwrap().id,
+                        amount: item_stack.size(),
+                    });
                     item_stack.transfer_to(&mut held_item, u32::MAX);
                 } else {
                     continue;
                 }
 
-                let mut crafting_interface = messages::InterfaceItemBoxUpdate::default();
-
-                for (i, item_stack) in crafting_input.iter().enumerate() {
-                    if let Some(item) = item_stack.item() {
-                        crafting_interface.add_itembox(
-                            "inventory/crafting_input",
-                            i as u32,
-                            item.id,
-                            item_stack.size(),
-                            item.properties["durability"].as_u64().map(|v| v as u32),
-                            item.properties["description"].as_str(),
-                        );
-                    } else {
-                        crafting_interface.add_empty_itembox("inventory/crafting_input", i as u32);
-                    }
-                }
+                net.send_one(
+                    player_entity,
+                    build_crafting_interface(&crafting_input, &recipes, &tooltips),
+                );
+            }
+        }
+    }
+}
 
-                if let Some(output) = recipes.get("crafting").get_output(&crafting_input) {
-                    crafting_interface.add_itembox(
-                        "inventory/crafting_output",
-                        0,
-                        output.item().unwrap().id,
-                        output.capacity(),
-                        None,
-                        None,
-                    );
-                } else {
-                    crafting_interface.add_empty_itembox("inventory/crafting_output", 0);
-                }
+fn build_crafting_interface(
+    crafting_input: &CraftingGrid,
+    recipes: &Recipes,
+    tooltips: &ItemTooltips,
+) -> messages::InterfaceItemBoxUpdate {
+    let mut crafting_interface = messages::InterfaceItemBoxUpdate::default();
+
+    for (i, item_stack) in crafting_input.iter().enumerate() {
+        if let Some(item) = item_stack.item() {
+            crafting_interface.add_itembox(
+                "inventory/crafting_input",
+                i as u32,
+                item.id,
+                item_stack.size(),
+                item.properties["durability"].as_u64().map(|v| v as u32),
+                tooltips.get(item_stack).as_deref(),
+            );
+        } else {
+            crafting_interface.add_empty_itembox("inventory/crafting_input", i as u32);
+        }
+    }
+
+    if let Some(output) = recipes.get("crafting").get_output(crafting_input) {
+        crafting_interface.add_itembox(
+            "inventory/crafting_output",
+            0,
+            output.item().unwrap().id,
+            output.capacity(),
+            None,
+            tooltips.get(&output).as_deref(),
+        );
+    } else {
+        crafting_interface.add_empty_itembox("inventory/crafting_output", 0);
+    }
+
+    crafting_interface
+}
+
+#[derive(Component)]
+struct CraftAllButton;
 
-                net.send_one(player_entity, crafting_interface)
+// Crafts as much as the inventory has room for and puts it straight in the inventory.
+fn handle_craft_all_button(
+    net: Res<Server>,
+    recipes: Res<Recipes>,
+    tooltips: ItemTooltips,
+    mut inventory_query: Query<
+        (&mut CraftingGrid, &mut Inventory, &mut SentItemBoxes),
+        With<Player>,
+    >,
+    mut interface_events: Query<
+        (&mut InterfaceEvents, &ChildOf),
+        (Changed<InterfaceEvents>, With<CraftAllButton>),
+    >,
+    mut craft_events: MessageWriter<CraftEvent>,
+) {
+    for (mut events, parent) in interface_events.iter_mut() {
+        let mut pressed = false;
+        for event in events.read() {
+            if matches!(*event, messages::InterfaceInteraction::Button { .. }) {
+                pressed = true;
             }
         }
+
+        if !pressed {
+            continue;
+        }
+
+        let player_entity = parent.0;
+        let (mut crafting_input, mut inventory, mut sent) =
+            inventory_query.get_mut(player_entity).unwrap();
+        let Some(mut item_stack) = recipes
+            .get("crafting")
+            .craft_to_fit(&mut crafting_input, &inventory)
+        else {
+            continue;
+        };
+
+        craft_events.write(CraftEvent {
+            player_entity,
+            collection: "crafting",
+            item_id: item_stack.item().unwrap().id,
+            amount: item_stack.size(),
+        });
+        inventory.insert(&mut item_stack);
+
+        let mut update = build_crafting_interface(&crafting_input, &recipes, &tooltips);
+        sent.add_inventory(&mut update, &inventory, &tooltips);
+        net.send_one(player_entity, update);
+    }
+}
+
+#[derive(Component)]
+struct SortButton;
+
+/// Merge the stacks of the same item and order them by item, empty slots are moved to the end.
+fn sort_item_stacks(item_stacks: &mut [ItemStack]) {
+    let mut sorted: Vec<ItemStack> = Vec::with_capacity(item_stacks.len());
+    for item_stack in item_stacks.iter_mut() {
+        let mut item_stack = std::mem::take(item_stack);
+        for existing in sorted.iter_mut() {
+            if item_stack.is_empty() {
+                break;
+            }
+
+            if existing.item() == item_stack.item() {
+                item_stack.transfer_to(existing, u32::MAX);
+            }
+        }
+
+        if !item_stack.is_empty() {
+            sorted.push(item_stack);
+        }
+    }
+
+    // The sort is stable, stacks of the same item keep their order with the fullest first.
+    sorted.sort_by_key(|item_stack| item_stack.item().unwrap().id);
+
+    for (slot, item_stack) in item_stacks.iter_mut().zip(sorted) {
+        *slot = item_stack;
+    }
+}
+
+// The sort button orders the inventory, the hotbar is left as the player arranged it.
+fn handle_sort_button(
+    mut inventory_query: Query<&mut Inventory, With<Player>>,
+    mut interface_events: Query<
+        (&mut InterfaceEvents, &ChildOf),
+        (Changed<InterfaceEvents>, With<SortButton>),
+    >,
+) {
+    for (mut events, parent) in interface_events.iter_mut() {
+        let mut pressed = false;
+        for event in events.read() {
+            if matches!(*event, messages::InterfaceInteraction::Button { .. }) {
+                pressed = true;
+            }
+        }
+
+        if !pressed {
+            continue;
+        }
+
+        let mut inventory = inventory_query.get_mut(parent.0).unwrap();
+        sort_item_stacks(&mut inventory[HOTBAR_SIZE..]);
     }
 }
 
@@ -440,7 +765,9 @@ fn equip_item(
             continue;
         }
 
-        let mut inventory = inventory.get_mut(equip_event.player_entity).unwrap();
+        let Ok(mut inventory) = inventory.get_mut(equip_event.player_entity) else {
+            continue;
+        };
         inventory.equipped_item = equip_event.index as usize;
     }
 }
@@ -0,0 +1,528 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use fmc::{
+    database::Database,
+    interfaces::{HeldInterfaceStack, InterfaceEvents, RegisterInterfaceNode},
+    items::ItemStack,
+    networking::Server,
+    players::Player,
+    prelude::*,
+    protocol::messages,
+};
+
+use crate::{
+    chat::send_message,
+    database_io::{DatabaseIo, DatabaseTask},
+    items::{DroppedItem, tooltips::ItemTooltips},
+};
+
+use super::{Inventory, roster::Roster};
+
+/// Lets players send messages and items to each other, also when the recipient is offline.
+///
+/// Mail is kept in the database until it is claimed. When a player joins they are told if they
+/// have any, and `/mail` opens their mailbox. Opening it shows the messages in the chat, and the
+/// items that were sent along can be taken out of the mailbox interface.
+pub struct MailPlugin;
+impl Plugin for MailPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_message::<SendMail>()
+            .add_message::<OpenMailbox>()
+            .insert_resource(PendingLetters::default())
+            .add_systems(Startup, setup)
+            .add_systems(
+                Update,
+                (
+                    (load_mail, deliver_mail).chain(),
+                    (send_mail, finish_sending).chain(),
+                    open_mailbox,
+                    handle_interface_events,
+                ),
+            );
+    }
+}
+
+/// How many letters a mailbox can hold, the same as the number of slots in the interface.
+const MAILBOX_CAPACITY: usize = 27;
+/// Letters that haven't been claimed after this many seconds are thrown away.
+const MAIL_EXPIRY: u64 = 30 * 24 * 60 * 60;
+const MAX_MESSAGE_LENGTH: usize = 256;
+
+/// Send a letter to a player. The item stack the sender is holding is sent along with the message.
+#[derive(Message)]
+pub struct SendMail {
+    pub sender_entity: Entity,
+    pub recipient: String,
+    pub message: String,
+}
+
+/// Show the player the letters in their mailbox.
+#[derive(Message)]
+pub struct OpenMailbox {
+    pub player_entity: Entity,
+}
+
+struct Letter {
+    id: i64,
+    sender: String,
+    message: String,
+    item_stack: ItemStack,
+}
+
+impl Letter {
+    fn load(username: &str, database: &Database) -> Vec<Self> {
+        let conn = database.get_read_connection();
+
+        let mut stmt = conn
+            .prepare("SELECT id, sender, message, item FROM mail WHERE recipient = ? ORDER BY id")
+            .unwrap();
+        stmt.query_map([username], |row| {
+            let item: String = row.get(3)?;
+            Ok(Letter {
+                id: row.get(0)?,
+                sender: row.get(1)?,
+                message: row.get(2)?,
+                item_stack: serde_json::from_str(&item).unwrap(),
+            })
+        })
+        .unwrap()
+        .map(|letter| letter.unwrap())
+        .collect()
+    }
+}
+
+/// The letters of a player, held while they are online.
+#[derive(Component)]
+struct Mailbox {
+    letters: Vec<Letter>,
+}
+
+impl Mailbox {
+    // Only letters with items in them take up a slot in the interface.
+    fn parcels(&mut self) -> impl Iterator<Item = &mut Letter> {
+        self.letters
+            .iter_mut()
+            .filter(|letter| !letter.item_stack.is_empty())
+    }
+
+    fn build_interface(&mut self, tooltips: &ItemTooltips) -> messages::InterfaceItemBoxUpdate {
+        let mut item_box_update = messages::InterfaceItemBoxUpdate::default();
+        let mut parcels = self.parcels();
+        for i in 0..MAILBOX_CAPACITY {
+            if let Some(item_stack) = parcels.next().map(|letter| &letter.item_stack) {
+                let item = item_stack.item().unwrap();
+                item_box_update.add_itembox(
+                    "mailbox",
+                    i as u32,
+                    item.id,
+                    item_stack.size(),
+                    item.properties["durability"].as_u64().map(|v| v as u32),
+                    tooltips.get(item_stack).as_deref(),
+                );
+            } else {
+                item_box_update.add_empty_itembox("mailbox", i as u32);
+            }
+        }
+
+        item_box_update
+    }
+}
+
+/// The interface node of the mailbox, it is a child of the player.
+#[derive(Component)]
+struct MailboxInterface;
+
+#[derive(Component)]
+struct LoadingMail(DatabaseTask<Vec<Letter>>);
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_secs()
+}
+
+fn setup(database: Res<Database>) {
+    let conn = database.get_write_connection();
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS mail (
+            id INTEGER PRIMARY KEY,
+            recipient TEXT NOT NULL,
+            sender TEXT NOT NULL,
+            message TEXT NOT NULL,
+            item TEXT NOT NULL,
+            sent INTEGER NOT NULL
+        );
+        CREATE INDEX IF NOT EXISTS mail_recipient ON mail (recipient);",
+    )
+    .unwrap();
+
+    conn.execute(
+        "DELETE FROM mail WHERE sent < ?",
+        rusqlite::params![now().saturating_sub(MAIL_EXPIRY) as i64],
+    )
+    .unwrap();
+}
+
+fn load_mail(
+    mut commands: Commands,
+    database_io: Res<DatabaseIo>,
+    added_players: Query<(Entity, &Player), Added<Player>>,
+    mut registration_events: MessageWriter<RegisterInterfaceNode>,
+) {
+    for (player_entity, player) in added_players.iter() {
+        let username = player.username.clone();
+        let task = database_io.run(move |database| Letter::load(&username, database));
+
+        let interface_entity = commands.spawn(MailboxInterface).id();
+        registration_events.write(RegisterInterfaceNode {
+            player_entity,
+            node_path: "mailbox".to_owned(),
+            node_entity: interface_entity,
+        });
+
+        commands
+            .entity(player_entity)
+            .insert(LoadingMail(task))
+            .add_child(interface_entity);
+    }
+}
+
+fn deliver_mail(
+    mut commands: Commands,
+    net: Res<Server>,
+    mut loading_mail: Query<(Entity, &mut LoadingMail)>,
+) {
+    for (player_entity, mut loading) in loading_mail.iter_mut() {
+        let Some(letters) = loading.0.try_take() else {
+            continue;
+        };
+
+        if !letters.is_empty() {
+            send_message(
+                &net,
+                player_entity,
+                format!(
+                    "You have {} letter(s) in your mailbox, open it with /mail",
+                    letters.len()
+                ),
+            );
+        }
+
+        commands
+            .entity(player_entity)
+            .remove::<LoadingMail>()
+            .insert(Mailbox { letters });
+    }
+}
+
+enum SendError {
+    NoSuchPlayer,
+    MailboxFull,
+}
+
+struct PendingLetter {
+    sender_entity: Entity,
+    recipient: String,
+    letter: Letter,
+    // The id of the letter once it has been stored
+    task: DatabaseTask<Result<i64, SendError>>,
+}
+
+/// Letters that are being written to the database.
+#[derive(Resource, Default)]
+struct PendingLetters(Vec<PendingLetter>);
+
+fn send_mail(
+    net: Res<Server>,
+    database_io: Res<DatabaseIo>,
+    roster: Res<Roster>,
+    mut senders: Query<(&Player, &mut Inventory)>,
+    mut send_events: MessageReader<SendMail>,
+    mut pending: ResMut<PendingLetters>,
+) {
+    for send_event in send_events.read() {
+        let Ok((player, mut inventory)) = senders.get_mut(send_event.sender_entity) else {
+            continue;
+        };
+
+        if send_event.recipient == player.username {
+            send_message(
+                &net,
+                send_event.sender_entity,
+                "You can't send mail to yourself".to_owned(),
+            );
+            continue;
+        }
+
+        if send_event.message.len() > MAX_MESSAGE_LENGTH {
+            send_message(
+                &net,
+                send_event.sender_entity,
+                format!(
+                    "The message is too long, it can be at most {} characters",
+                    MAX_MESSAGE_LENGTH
+                ),
+            );
+            continue;
+        }
+
+        let item_stack = std::mem::take(inventory.held_item_stack_mut());
+        if item_stack.is_empty() && send_event.message.is_empty() {
+            send_message(
+                &net,
+                send_event.sender_entity,
+                "Write a message or hold the item you want to send".to_owned(),
+            );
+            continue;
+        }
+
+        let letter = Letter {
+            id: 0,
+            sender: player.username.clone(),
+            message: send_event.message.clone(),
+            item_stack,
+        };
+
+        let recipient = send_event.recipient.clone();
+        let is_online = roster.get(&recipient).is_some();
+        let row = (
+            recipient.clone(),
+            letter.sender.clone(),
+            letter.message.clone(),
+            serde_json::to_string(&letter.item_stack).unwrap(),
+        );
+        let task = database_io.run(move |database| {
+            let conn = database.get_write_connection();
+            let (recipient, sender, message, item) = row;
+
+            let exists = is_online
+                || conn
+                    .query_row("SELECT 1 FROM players WHERE name = ?", [&recipient], |_| {
+                        Ok(())
+                    })
+                    .is_ok();
+            if !exists {
+                return Err(SendError::NoSuchPlayer);
+            }
+
+            let count: i64 = conn
+                .query_row(
+                    "SELECT COUNT(*) FROM mail WHERE recipient = ?",
+                    [&recipient],
+                    |row| row.get(0),
+                )
+                .unwrap();
+            if count as usize >= MAILBOX_CAPACITY {
+                return Err(SendError::MailboxFull);
+            }
+
+            conn.execute(
+                "INSERT INTO mail (recipient, sender, message, item, sent) VALUES (?,?,?,?,?)",
+                rusqlite::params![recipient, sender, message, item, now() as i64],
+            )
+            .unwrap();
+
+            Ok(conn.last_insert_rowid())
+        });
+
+        pending.0.push(PendingLetter {
+            sender_entity: send_event.sender_entity,
+            recipient,
+            letter,
+            task,
+        });
+    }
+}
+
+fn finish_sending(
+    mut commands: Commands,
+    net: Res<Server>,
+    roster: Res<Roster>,
+    mut pending: ResMut<PendingLetters>,
+    mut senders: Query<(&mut Inventory, &GlobalTransform)>,
+    mut mailboxes: Query<&mut Mailbox>,
+) {
+    let mut index = 0;
+    while index < pending.0.len() {
+        let Some(result) = pending.0[index].task.try_take() else {
+            index += 1;
+            continue;
+        };
+
+        let PendingLetter {
+            sender_entity,
+            recipient,
+            mut letter,
+            ..
+        } = pending.0.swap_remove(index);
+
+        match result {
+            Ok(id) => {
+                send_message(&net, sender_entity, format!("Sent mail to {}", recipient));
+
+                let Some(recipient_entity) = roster.get_entity(&recipient) else {
+                    continue;
+                };
+
+                if let Ok(mut mailbox) = mailboxes.get_mut(recipient_entity) {
+                    send_message(
+                        &net,
+                        recipient_entity,
+                        format!(
+                            "You have new mail from {}, open your mailbox with /mail",
+                            letter.sender
+                        ),
+                    );
+                    letter.id = id;
+                    mailbox.letters.push(letter);
+                }
+            }
+            Err(error) => {
+                let text = match error {
+                    SendError::NoSuchPlayer => {
+                        format!("There is no player named {}", recipient)
+                    }
+                    SendError::MailboxFull => format!("The mailbox of {} is full", recipient),
+                };
+                send_message(&net, sender_entity, text);
+
+                // The sender gets their item back, if they left in the meantime it is lost.
+                let Ok((mut inventory, transform)) = senders.get_mut(sender_entity) else {
+                    continue;
+                };
+                inventory.insert(&mut letter.item_stack);
+                if !letter.item_stack.is_empty() {
+                    commands.spawn((
+                        DroppedItem::new(letter.item_stack),
+                        Transform::from_translation(transform.translation()),
+                    ));
+                }
+            }
+        }
+    }
+}
+
+fn open_mailbox(
+    net: Res<Server>,
+    tooltips: ItemTooltips,
+    database_io: Res<DatabaseIo>,
+    mut mailboxes: Query<&mut Mailbox>,
+    mut open_events: MessageReader<OpenMailbox>,
+) {
+    for open_event in open_events.read() {
+        let Ok(mut mailbox) = mailboxes.get_mut(open_event.player_entity) else {
+            continue;
+        };
+
+        if mailbox.letters.is_empty() {
+            send_message(
+                &net,
+                open_event.player_entity,
+                "Your mailbox is empty".to_owned(),
+            );
+            continue;
+        }
+
+        for letter in mailbox.letters.iter() {
+            if letter.message.is_empty() {
+                continue;
+            }
+            send_message(
+                &net,
+                open_event.player_entity,
+                format!("[Mail from {}] {}", letter.sender, letter.message),
+            );
+        }
+
+        // Letters without items are done with once they have been read. The messages of the
+        // others are cleared so they aren't shown again.
+        let read: Vec<i64> = mailbox
+            .letters
+            .iter()
+            .filter(|letter| letter.item_stack.is_empty())
+            .map(|letter| letter.id)
+            .collect();
+        mailbox
+            .letters
+            .retain(|letter| !letter.item_stack.is_empty());
+        for letter in mailbox.letters.iter_mut() {
+            letter.message.clear();
+        }
+
+        let parcels: Vec<i64> = mailbox.letters.iter().map(|letter| letter.id).collect();
+        database_io.run(move |database| {
+            let conn = database.get_write_connection();
+            for id in read {
+                conn.execute("DELETE FROM mail WHERE id = ?", [id]).unwrap();
+            }
+            for id in parcels {
+                conn.execute("UPDATE mail SET message = '' WHERE id = ?", [id])
+                    .unwrap();
+            }
+        });
+
+        if mailbox.letters.is_empty() {
+            continue;
+        }
+
+        net.send_one(open_event.player_entity, mailbox.build_interface(&tooltips));
+        net.send_one(
+            open_event.player_entity,
+            messages::InterfaceVisibilityUpdate {
+                interface_path: "mailbox".to_owned(),
+                visible: true,
+            },
+        );
+    }
+}
+
+// Items can only be taken out of the mailbox, anything placed into it is ignored.
+fn handle_interface_events(
+    net: Res<Server>,
+    tooltips: ItemTooltips,
+    database_io: Res<DatabaseIo>,
+    mut players: Query<(&mut Mailbox, &mut HeldInterfaceStack), With<Player>>,
+    mut interface_events: Query<
+        (&mut InterfaceEvents, &ChildOf),
+        (Changed<InterfaceEvents>, With<MailboxInterface>),
+    >,
+) {
+    for (mut events, parent) in interface_events.iter_mut() {
+        let Ok((mut mailbox, mut held_item)) = players.get_mut(parent.0) else {
+            continue;
+        };
+
+        for event in events.read() {
+            if let messages::InterfaceInteraction::TakeItem {
+                index, quantity, ..
+            } = *event
+                && let Some(letter) = mailbox.parcels().nth(index as usize)
+            {
+                letter.item_stack.transfer_to(&mut held_item, quantity);
+
+                let id = letter.id;
+                let item = serde_json::to_string(&letter.item_stack).unwrap();
+                let claimed = letter.item_stack.is_empty();
+                database_io.run(move |database| {
+                    let conn = database.get_write_connection();
+                    if claimed {
+                        conn.execute("DELETE FROM mail WHERE id = ?", [id]).unwrap();
+                    } else {
+                        conn.execute(
+                            "UPDATE mail SET item = ? WHERE id = ?",
+                            rusqlite::params![item, id],
+                        )
+                        .unwrap();
+                    }
+                });
+
+                if claimed {
+                    mailbox.letters.retain(|letter| letter.id != id);
+                }
+            }
+
+            net.send_one(parent.0, mailbox.build_interface(&tooltips));
+        }
+    }
+}
@@ -3,16 +3,17 @@ use fmc::{
         ecs::query::QueryData,
         math::{DQuat, DVec3},
     },
-    blocks::{BlockPosition, Blocks},
+    blocks::{BlockId, BlockPosition, Blocks},
     database::Database,
     interfaces::{HeldInterfaceStack, InterfaceEvents, InterfaceSystems, RegisterInterfaceNode},
     items::ItemStack,
-    models::{AnimationPlayer, Model, Models, Observers},
+    models::{AnimationPlayer, Model, ModelVisibility, Models, Observers},
     networking::{NetworkEvent, NetworkMessage, Server},
     physics::{Collider, Physics},
     players::{Camera, Player},
     prelude::*,
     protocol::messages,
+    random::Rng,
     world::{
         WorldMap,
         chunk::{Chunk, ChunkPosition},
@@ -21,44 +22,86 @@ use fmc::{
 use serde::{Deserialize, Serialize};
 
 use crate::{
-    items::{DroppedItem, crafting::CraftingGrid},
+    database_io::{DatabaseIo, DatabaseTask},
+    items::{DroppedItem, crafting::CraftingGrid, tooltips::ItemTooltips},
     mobs::MobCap,
     settings::Settings,
-    world::WorldProperties,
+    world::{
+        WorldProperties,
+        dimensions::Dimension,
+        saving::{SaveSystems, SaveWorld, WorldSave},
+    },
 };
 
-use self::health::HealthBundle;
+use self::{advancements::Advancements, health::HealthBundle};
 
+pub mod access;
+mod advancements;
+mod combat;
+mod creative;
+mod first_join;
 mod hand;
 mod health;
 mod inventory_interface;
+pub mod mail;
 mod movement;
-
-pub use hand::{HandHits, HandInteractions, HandSystems};
-pub use health::{HealEvent, Health, PlayerDamageEvent};
+mod riding;
+pub mod roster;
+pub mod shared_connections;
+pub mod teleport;
+pub mod temperature;
+mod walking;
+
+pub use advancements::OpenAdvancements;
+pub use combat::{AttackCooldown, MeleeAttack};
+pub use first_join::FirstJoin;
+pub use hand::{BlockMinedEvent, HandHits, HandInteractions, HandSystems};
+pub use health::{FallDamage, HealEvent, Health, PlayerDamageEvent, PlayerLandEvent};
+pub use inventory_interface::SentItemBoxes;
+pub use movement::{ModelCollider, MovementMode, MovementPluginPacket, Sneaking, VehicleConfig};
+pub use riding::{Dismount, Mount, Riding, Seat};
+pub use walking::{Footing, Footsteps, WalkOverEvent};
 
 pub struct PlayerPlugin;
 impl Plugin for PlayerPlugin {
     fn build(&self, app: &mut App) {
         app.add_message::<RespawnEvent>()
+            .add_plugins(access::AccessControlPlugin)
+            .add_plugins(advancements::AdvancementPlugin)
+            .add_plugins(creative::CreativePlugin)
+            .add_plugins(first_join::FirstJoinPlugin)
             .add_plugins(inventory_interface::InventoryInterfacePlugin)
+            .add_plugins(mail::MailPlugin)
             .add_plugins(health::HealthPlugin)
             .add_plugins(hand::HandPlugin)
             .add_plugins(movement::MovementPlugin)
+            .add_plugins(riding::RidingPlugin)
+            .add_plugins(roster::RosterPlugin)
+            .add_plugins(shared_connections::SharedConnectionPlugin)
+            .add_plugins(teleport::TeleportPlugin)
+            .add_plugins(temperature::TemperaturePlugin)
+            .add_plugins(walking::WalkingPlugin)
             .add_systems(
                 Update,
                 (
                     on_gamemode_update,
                     handle_gui_settings,
-                    (add_players, ApplyDeferred).chain(),
-                    respawn_players,
+                    (load_players, add_players, ApplyDeferred).chain(),
+                    (respawn_players, finish_respawns).chain(),
                     rotate_player_model,
-                    discard_items.after(InterfaceSystems::HandleEvents),
+                    (discard_items, handle_trash_events, drop_all)
+                        .after(InterfaceSystems::HandleEvents),
                 ),
             )
             // Save player after all remaining events have been handled. Avoid dupes and other
             // unexpected behaviour.
-            .add_systems(PostUpdate, save_player_data_on_disconnect)
+            .add_systems(
+                PostUpdate,
+                (
+                    save_player_data_on_disconnect,
+                    add_player_data_to_save.in_set(SaveSystems::Collect),
+                ),
+            )
             .add_systems(
                 Last,
                 save_player_data_on_shutdown.run_if(on_message::<AppExit>),
@@ -104,6 +147,30 @@ impl Inventory {
         let index = self.equipped_item;
         &mut self[index]
     }
+
+    /// Move as much of the item stack into the inventory as possible. Stacks of the same item are
+    /// filled first, then empty slots. Whatever doesn't fit is left in the item stack.
+    pub fn insert(&mut self, item_stack: &mut ItemStack) {
+        for slot in self.iter_mut() {
+            if slot.item() == item_stack.item() {
+                item_stack.transfer_to(slot, u32::MAX);
+            }
+
+            if item_stack.is_empty() {
+                return;
+            }
+        }
+
+        for slot in self.iter_mut() {
+            if slot.is_empty() {
+                item_stack.transfer_to(slot, u32::MAX);
+            }
+
+            if item_stack.is_empty() {
+                return;
+            }
+        }
+    }
 }
 
 // TODO: Move this into Inventory, no clue why I separated them
@@ -117,19 +184,33 @@ pub struct Equipment {
     pub boots: ItemStack,
 }
 
+/// Where the player respawns when it dies. If not set, the player respawns at the world's spawn
+/// point.
+#[derive(Component, Serialize, Deserialize, Deref, DerefMut, Default, Clone, Copy)]
+pub struct SpawnPoint(pub Option<IVec3>);
+
 // TODO: Steal "Player" struct name from fmc lib and consolidate some of these components
 /// Components a player consists of
 #[derive(Bundle)]
 pub struct PlayerBundle {
     pub transform: Transform,
     pub camera: Camera,
+    pub movement_mode: MovementMode,
+    pub sneaking: Sneaking,
     pub aabb: Collider,
     pub inventory: Inventory,
     pub equipment: Equipment,
     pub crafting_table: CraftingGrid,
     pub health_bundle: HealthBundle,
+    pub attack_cooldown: AttackCooldown,
+    pub footing: Footing,
+    pub footsteps: Footsteps,
     pub game_mode: GameMode,
     pub mob_cap: MobCap,
+    pub advancements: Advancements,
+    pub spawn_point: SpawnPoint,
+    pub dimension: Dimension,
+    pub first_join: FirstJoin,
 }
 
 impl PlayerBundle {
@@ -146,13 +227,22 @@ impl Default for PlayerBundle {
         Self {
             transform: Transform::default(),
             camera: Camera::default(),
+            movement_mode: MovementMode::default(),
+            sneaking: Sneaking::default(),
             aabb: Collider::from_min_max(DVec3::new(-0.3, 0.0, -0.3), DVec3::new(0.3, 1.8, 0.3)),
             inventory: Inventory::default(),
             equipment: Equipment::default(),
             crafting_table: CraftingGrid::with_size(4),
             health_bundle: HealthBundle::default(),
+            attack_cooldown: AttackCooldown::default(),
+            footing: Footing::default(),
+            footsteps: Footsteps::default(),
             game_mode: GameMode::default(),
             mob_cap: MobCap::default(),
+            advancements: Advancements::default(),
+            spawn_point: SpawnPoint::default(),
+            dimension: Dimension::default(),
+            first_join: FirstJoin::default(),
         }
     }
 }
@@ -170,6 +260,10 @@ impl From<PlayerSave> for PlayerBundle {
             equipment: save.equipment,
             health_bundle: HealthBundle::from_health(save.health),
             game_mode: save.game_mode,
+            advancements: save.advancements,
+            spawn_point: save.spawn_point,
+            dimension: save.dimension,
+            first_join: save.first_join,
             ..default()
         }
     }
@@ -187,18 +281,28 @@ pub struct PlayerSave {
     equipment: Equipment,
     health: Health,
     game_mode: GameMode,
+    #[serde(default)]
+    advancements: Advancements,
+    #[serde(default)]
+    spawn_point: SpawnPoint,
+    #[serde(default)]
+    dimension: Dimension,
+    #[serde(default = "FirstJoin::completed")]
+    first_join: FirstJoin,
 }
 
 impl PlayerSave {
     fn save(&self, username: &str, database: &Database) {
-        let conn = database.get_write_connection();
-
-        let mut stmt = conn
-            .prepare("INSERT OR REPLACE INTO players VALUES (?,?)")
+        self.write(username, &database.get_write_connection())
             .unwrap();
+    }
+
+    fn write(&self, username: &str, connection: &rusqlite::Connection) -> rusqlite::Result<()> {
+        let mut stmt = connection.prepare("INSERT OR REPLACE INTO players VALUES (?,?)")?;
         let json = serde_json::to_string(self).unwrap();
 
-        stmt.execute(rusqlite::params![username, json]).unwrap();
+        stmt.execute(rusqlite::params![username, json])?;
+        Ok(())
     }
 
     fn load(username: &str, database: &Database) -> Option<Self> {
@@ -224,19 +328,47 @@ impl PlayerSave {
     }
 }
 
+/// The player's save is being loaded from the database, the player is added to the world when it
+/// is done.
+#[derive(Component)]
+struct LoadingPlayer(DatabaseTask<Option<PlayerSave>>);
+
+fn load_players(
+    mut commands: Commands,
+    database_io: Res<DatabaseIo>,
+    added_players: Query<(Entity, &Player), Added<Player>>,
+) {
+    for (player_entity, player) in added_players.iter() {
+        let username = player.username.clone();
+        let task = database_io.run(move |database| PlayerSave::load(&username, database));
+        commands.entity(player_entity).insert(LoadingPlayer(task));
+    }
+}
+
 fn add_players(
     mut commands: Commands,
     net: Res<Server>,
     settings: Res<Settings>,
-    database: Res<Database>,
     models: Res<Models>,
+    starting_kit: Res<first_join::StartingKit>,
     mut respawn_events: MessageWriter<RespawnEvent>,
     mut registration_events: MessageWriter<RegisterInterfaceNode>,
-    added_players: Query<(Entity, &Player), Added<Player>>,
+    mut loading_players: Query<(Entity, &mut LoadingPlayer)>,
 ) {
-    for (player_entity, player) in added_players.iter() {
-        let bundle = if let Some(save) = PlayerSave::load(&player.username, &database) {
+    for (player_entity, mut loading) in loading_players.iter_mut() {
+        let Some(save) = loading.0.try_take() else {
+            continue;
+        };
+        commands.entity(player_entity).remove::<LoadingPlayer>();
+
+        let bundle = if let Some(save) = save {
             PlayerBundle::from(save)
+        } else if let Some(tutorial_spawn) = starting_kit.tutorial_spawn {
+            // New players start out in the tutorial instead of at the world spawn.
+            let mut bundle = PlayerBundle::new(settings.game_mode);
+            bundle.transform.translation = tutorial_spawn.as_dvec3() + DVec3::new(0.5, 0.0, 0.5);
+            bundle.dimension = Dimension::from_height(tutorial_spawn.y);
+            bundle
         } else {
             respawn_events.write(RespawnEvent { player_entity });
             PlayerBundle::new(settings.game_mode)
@@ -285,6 +417,26 @@ fn add_players(
             node_entity: discard_items_entity,
         });
 
+        // The delete button is handled by the trash slot it empties
+        let trash_entity = commands.spawn(Trash::default()).id();
+        registration_events.write(RegisterInterfaceNode {
+            player_entity,
+            node_path: "inventory/trash".to_owned(),
+            node_entity: trash_entity,
+        });
+        registration_events.write(RegisterInterfaceNode {
+            player_entity,
+            node_path: "inventory/trash_delete".to_owned(),
+            node_entity: trash_entity,
+        });
+
+        let drop_all_entity = commands.spawn(DropAll).id();
+        registration_events.write(RegisterInterfaceNode {
+            player_entity,
+            node_path: "inventory/drop_all".to_owned(),
+            node_entity: drop_all_entity,
+        });
+
         commands
             .entity(player_entity)
             .insert((
@@ -293,7 +445,7 @@ fn add_players(
                 animation_player,
                 model_observers,
             ))
-            .add_child(discard_items_entity);
+            .add_children(&[discard_items_entity, trash_entity, drop_all_entity]);
     }
 }
 
@@ -306,10 +458,14 @@ struct PlayerQuery {
     equipment: &'static Equipment,
     health: &'static Health,
     game_mode: &'static GameMode,
+    advancements: &'static Advancements,
+    spawn_point: &'static SpawnPoint,
+    dimension: &'static Dimension,
+    first_join: &'static FirstJoin,
 }
 
 fn save_player_data_on_disconnect(
-    database: Res<Database>,
+    database_io: Res<DatabaseIo>,
     mut network_events: MessageReader<NetworkEvent>,
     mut players: Query<PlayerQuery>,
 ) {
@@ -322,7 +478,7 @@ fn save_player_data_on_disconnect(
             continue;
         };
 
-        PlayerSave {
+        let save = PlayerSave {
             position: player_query.transform.translation,
             camera_position: player_query.camera.translation,
             camera_rotation: player_query.camera.rotation,
@@ -330,18 +486,46 @@ fn save_player_data_on_disconnect(
             equipment: player_query.equipment.clone(),
             health: player_query.health.clone(),
             game_mode: *player_query.game_mode,
-        }
-        .save(&player_query.player.username, &database);
+            advancements: player_query.advancements.clone(),
+            spawn_point: *player_query.spawn_point,
+            dimension: *player_query.dimension,
+            first_join: *player_query.first_join,
+        };
+        let username = player_query.player.username.clone();
+        database_io.run(move |database| save.save(&username, database));
     }
 }
 
-fn save_player_data_on_shutdown(
-    database: Res<Database>,
-    mut network_events: MessageReader<NetworkEvent>,
-    mut players: Query<PlayerQuery>,
+fn save_player_data_on_shutdown(database_io: Res<DatabaseIo>, players: Query<PlayerQuery>) {
+    for (username, save) in player_saves(&players) {
+        database_io.run(move |database| save.save(&username, database));
+    }
+
+    // The server exits right after this, so everything must be written before returning.
+    database_io.flush();
+}
+
+fn add_player_data_to_save(
+    players: Query<PlayerQuery>,
+    mut world_save: ResMut<WorldSave>,
+    mut save_events: MessageReader<SaveWorld>,
 ) {
-    for player_query in players.iter() {
-        PlayerSave {
+    if save_events.read().count() == 0 {
+        return;
+    }
+
+    let saves: Vec<(String, PlayerSave)> = player_saves(&players).collect();
+    world_save.add(move |connection| {
+        for (username, save) in saves {
+            save.write(&username, connection)?;
+        }
+        Ok(())
+    });
+}
+
+fn player_saves(players: &Query<PlayerQuery>) -> impl Iterator<Item = (String, PlayerSave)> {
+    players.iter().map(|player_query| {
+        let save = PlayerSave {
             position: player_query.transform.translation,
             camera_position: player_query.camera.translation,
             camera_rotation: player_query.camera.rotation,
@@ -349,9 +533,13 @@ fn save_player_data_on_shutdown(
             equipment: player_query.equipment.clone(),
             health: player_query.health.clone(),
             game_mode: *player_query.game_mode,
-        }
-        .save(&player_query.player.username, &database);
-    }
+            advancements: player_query.advancements.clone(),
+            spawn_point: *player_query.spawn_point,
+            dimension: *player_query.dimension,
+            first_join: *player_query.first_join,
+        };
+        (player_query.player.username.clone(), save)
+    })
 }
 
 #[derive(Message)]
@@ -359,77 +547,192 @@ pub struct RespawnEvent {
     pub player_entity: Entity,
 }
 
-// TODO: If it can't find a valid spawn point it will just oscillate in an infinite loop between the
-// air chunk above and the one it can't find anything in.
-// TODO: This might take a really long time to compute because of the chunk loading, and should
-// probably be done ahead of time through an async task. Idk if the spawn point should change
-// between each spawn. A good idea if it's really hard to validate that the player won't suffocate
-// infinitely.
+/// The player is waiting for a spawn point to be found
+#[derive(Component)]
+struct PendingRespawn(DatabaseTask<BlockPosition>);
+
 fn respawn_players(
+    mut commands: Commands,
     net: Res<Server>,
     world_properties: Res<WorldProperties>,
     world_map: Res<WorldMap>,
-    database: Res<Database>,
-    mut player_query: Query<&mut Transform, With<Player>>,
+    database_io: Res<DatabaseIo>,
+    mut player_query: Query<(&mut Transform, &mut Dimension, &mut SpawnPoint), With<Player>>,
     mut heal_events: MessageWriter<HealEvent>,
     mut respawn_events: MessageReader<RespawnEvent>,
 ) {
     for respawn_event in respawn_events.read() {
-        let blocks = Blocks::get();
-        let air = blocks.get_id("air");
-
-        let mut chunk_position = ChunkPosition::from(world_properties.spawn_point.center);
-        let spawn_position = 'outer: loop {
-            let chunk = futures_lite::future::block_on(Chunk::load(
-                chunk_position,
-                world_map.terrain_generator.clone(),
-                database.clone(),
-            ))
-            .1;
+        let Ok((mut player_transform, mut dimension, mut spawn_point)) =
+            player_query.get_mut(respawn_event.player_entity)
+        else {
+            continue;
+        };
 
-            if chunk.is_uniform() && chunk[0] == air {
-                break BlockPosition::from(chunk_position);
+        // The spawn point is above the bed the player last slept in
+        let mut bed_position = spawn_point
+            .map(|spawn_point| BlockPosition::new(spawn_point.x, spawn_point.y - 1, spawn_point.z));
+
+        if let Some(position) = bed_position
+            && let Some(block_id) = world_map.get_block(position)
+        {
+            if is_bed(block_id) {
+                respawn(
+                    &net,
+                    respawn_event.player_entity,
+                    &mut player_transform,
+                    &mut dimension,
+                    &mut heal_events,
+                    position + IVec3::Y,
+                );
+                continue;
             }
 
-            // Find two consecutive air blocks to spawn in
-            for (i, block_column) in chunk.blocks.chunks_exact(Chunk::SIZE).enumerate() {
-                let mut count = 0;
-                for (j, block) in block_column.iter().enumerate() {
-                    if count == 0 && *block == air {
-                        count += 1;
-                    } else if count == 1 && *block == air {
-                        let mut spawn_position = BlockPosition::from(chunk_position)
-                            + BlockPosition::from(i * Chunk::SIZE + j);
-                        spawn_position.y -= 1;
-                        break 'outer spawn_position;
-                    } else {
-                        count = 0;
-                    }
+            // Beds are usually unset when they are removed, but not when it happens while the
+            // player is offline.
+            **spawn_point = None;
+            bed_position = None;
+        }
+
+        // If the bed's chunk isn't loaded, it is checked when loading it.
+        let chunk_position = ChunkPosition::from(world_properties.spawn_point.center);
+        let task = load_chunks(&world_map, &database_io, move |load_chunk| {
+            if let Some(bed_position) = bed_position {
+                let bed_chunk_position = ChunkPosition::from(bed_position);
+                let chunk = load_chunk(bed_chunk_position);
+                let block_id = if chunk.is_uniform() {
+                    chunk[0]
+                } else {
+                    let offset = *bed_position - *BlockPosition::from(bed_chunk_position);
+                    chunk[[offset.x as usize, offset.y as usize, offset.z as usize]]
+                };
+
+                if is_bed(block_id) {
+                    return bed_position + IVec3::Y;
                 }
             }
 
-            chunk_position.y += Chunk::SIZE as i32;
+            find_world_spawn(chunk_position, load_chunk)
+        });
+        commands
+            .entity(respawn_event.player_entity)
+            .insert(PendingRespawn(task));
+    }
+}
+
+fn finish_respawns(
+    mut commands: Commands,
+    net: Res<Server>,
+    mut player_query: Query<(Entity, &mut Transform, &mut Dimension, &mut PendingRespawn)>,
+    mut heal_events: MessageWriter<HealEvent>,
+) {
+    for (player_entity, mut player_transform, mut dimension, mut pending) in player_query.iter_mut()
+    {
+        let Some(spawn_position) = pending.0.try_take() else {
+            continue;
         };
+        commands.entity(player_entity).remove::<PendingRespawn>();
 
-        let spawn_position = spawn_position.as_dvec3() + DVec3::new(0.5, 0.0, 0.5);
+        respawn(
+            &net,
+            player_entity,
+            &mut player_transform,
+            &mut dimension,
+            &mut heal_events,
+            spawn_position,
+        );
+    }
+}
 
-        // TODO: Because of the latency before the client reports back its new position, the player will
-        // be alive for a small moment at the spot they died, picking up their items again. So we
-        // have to set the position server side too.
-        let mut player_transform = player_query.get_mut(respawn_event.player_entity).unwrap();
-        player_transform.translation = spawn_position;
+fn respawn(
+    net: &Server,
+    player_entity: Entity,
+    player_transform: &mut Transform,
+    dimension: &mut Dimension,
+    heal_events: &mut MessageWriter<HealEvent>,
+    spawn_position: BlockPosition,
+) {
+    let spawn_position = spawn_position.as_dvec3() + DVec3::new(0.5, 0.0, 0.5);
+
+    // TODO: Because of the latency before the client reports back its new position, the player will
+    // be alive for a small moment at the spot they died, picking up their items again. So we
+    // have to set the position server side too.
+    player_transform.translation = spawn_position;
+    *dimension = Dimension::from_height(spawn_position.y.floor() as i32);
+
+    heal_events.write(HealEvent {
+        player_entity,
+        healing: u32::MAX,
+    });
+
+    net.send_one(
+        player_entity,
+        messages::PlayerPosition {
+            position: spawn_position,
+        },
+    );
+}
 
-        heal_events.write(HealEvent {
-            player_entity: respawn_event.player_entity,
-            healing: u32::MAX,
-        });
+fn is_bed(block_id: BlockId) -> bool {
+    let blocks = Blocks::get();
+    block_id == blocks.get_id("bed_foot") || block_id == blocks.get_id("bed_head")
+}
 
-        net.send_one(
-            respawn_event.player_entity,
-            messages::PlayerPosition {
-                position: spawn_position,
-            },
-        );
+// Loading chunks can take a while when they have to be generated, so it is done on the io thread.
+// The function is given a way to load the chunks it needs.
+fn load_chunks<T: Send + 'static>(
+    world_map: &WorldMap,
+    database_io: &DatabaseIo,
+    function: impl FnOnce(&dyn Fn(ChunkPosition) -> Chunk) -> T + Send + 'static,
+) -> DatabaseTask<T> {
+    let terrain_generator = world_map.terrain_generator.clone();
+    database_io.run(move |database| {
+        function(&|chunk_position| {
+            futures_lite::future::block_on(Chunk::load(
+                chunk_position,
+                terrain_generator.clone(),
+                database.clone(),
+            ))
+            .1
+        })
+    })
+}
+
+// TODO: If it can't find a valid spawn point it will just oscillate in an infinite loop between the
+// air chunk above and the one it can't find anything in.
+// TODO: Idk if the spawn point should change between each spawn. A good idea if it's really hard
+// to validate that the player won't suffocate infinitely.
+fn find_world_spawn(
+    mut chunk_position: ChunkPosition,
+    load_chunk: impl Fn(ChunkPosition) -> Chunk,
+) -> BlockPosition {
+    let blocks = Blocks::get();
+    let air = blocks.get_id("air");
+
+    'outer: loop {
+        let chunk = load_chunk(chunk_position);
+
+        if chunk.is_uniform() && chunk[0] == air {
+            break BlockPosition::from(chunk_position);
+        }
+
+        // Find two consecutive air blocks to spawn in
+        for (i, block_column) in chunk.blocks.chunks_exact(Chunk::SIZE).enumerate() {
+            let mut count = 0;
+            for (j, block) in block_column.iter().enumerate() {
+                if count == 0 && *block == air {
+                    count += 1;
+                } else if count == 1 && *block == air {
+                    let mut spawn_position = BlockPosition::from(chunk_position)
+                        + BlockPosition::from(i * Chunk::SIZE + j);
+                    spawn_position.y -= 1;
+                    break 'outer spawn_position;
+                } else {
+                    count = 0;
+                }
+            }
+        }
+
+        chunk_position.y += Chunk::SIZE as i32;
     }
 }
 
@@ -442,7 +745,9 @@ fn rotate_player_model(
     mut camera_rotation_events: MessageReader<NetworkMessage<messages::PlayerCameraRotation>>,
 ) {
     for rotation_update in camera_rotation_events.read() {
-        let mut transform = player_query.get_mut(rotation_update.player_entity).unwrap();
+        let Ok(mut transform) = player_query.get_mut(rotation_update.player_entity) else {
+            continue;
+        };
 
         let rotation = rotation_update.rotation.as_dquat();
 
@@ -453,9 +758,16 @@ fn rotate_player_model(
 
 fn on_gamemode_update(
     net: Res<Server>,
-    player_query: Query<(Entity, &GameMode), Changed<GameMode>>,
+    mut player_query: Query<(Entity, &GameMode, &mut ModelVisibility), Changed<GameMode>>,
 ) {
-    for (player_entity, gamemode) in player_query.iter() {
+    for (player_entity, gamemode, mut visibility) in player_query.iter_mut() {
+        // Spectators are invisible to the other players
+        if *gamemode == GameMode::Spectator {
+            visibility.set_if_neq(ModelVisibility::Hidden);
+        } else {
+            visibility.set_if_neq(ModelVisibility::Visible);
+        }
+
         match gamemode {
             GameMode::Survival => {
                 let mut health_visibility = messages::InterfaceNodeVisibilityUpdate::default();
@@ -552,6 +864,34 @@ fn on_gamemode_update(
 #[derive(Component)]
 struct DiscardItems;
 
+/// Thrown items can't be picked up for this many seconds, so they aren't pulled straight back.
+const THROW_PICKUP_DELAY: f32 = 1.5;
+/// How fast items are thrown
+const THROW_SPEED: f64 = 12.0;
+/// How much the direction of each stack thrown by "drop all" is allowed to differ, so they don't
+/// all land in the same spot.
+const THROW_SPREAD: f64 = 0.3;
+
+/// Throw the item stack out in front of the player. The spread is added to the direction it is
+/// thrown in.
+fn throw_item_stack(
+    commands: &mut Commands,
+    item_stack: ItemStack,
+    transform: &GlobalTransform,
+    camera: &Camera,
+    spread: DVec3,
+) {
+    let dropped_item_position = transform.translation() + camera.translation + camera.forward();
+    commands.spawn((
+        DroppedItem::new(item_stack).with_delay(THROW_PICKUP_DELAY),
+        Transform::from_translation(dropped_item_position),
+        Physics {
+            velocity: (camera.forward() + spread) * THROW_SPEED,
+            ..default()
+        },
+    ));
+}
+
 fn discard_items(
     mut commands: Commands,
     mut inventory_query: Query<(&mut HeldInterfaceStack, &GlobalTransform, &Camera), With<Player>>,
@@ -569,21 +909,96 @@ fn discard_items(
                     continue;
                 }
 
-                let dropped_item_position =
-                    transform.translation() + camera.translation + camera.forward();
-                commands.spawn((
-                    DroppedItem::new(discarded),
-                    Transform::from_translation(dropped_item_position),
-                    Physics {
-                        velocity: camera.forward() * 12.0,
-                        ..default()
-                    },
-                ));
+                throw_item_stack(&mut commands, discarded, transform, camera, DVec3::ZERO);
             }
         }
     }
 }
 
+/// A slot in the inventory that items can be put in to get rid of them. They stay there and can
+/// be taken back out until the delete button is pressed.
+#[derive(Component, Default)]
+struct Trash(ItemStack);
+
+fn handle_trash_events(
+    net: Res<Server>,
+    tooltips: ItemTooltips,
+    mut held_items: Query<&mut HeldInterfaceStack, With<Player>>,
+    mut trash_query: Query<(&mut Trash, &mut InterfaceEvents, &ChildOf), Changed<InterfaceEvents>>,
+) {
+    for (mut trash, mut interface_events, parent) in trash_query.iter_mut() {
+        let mut held_item = held_items.get_mut(parent.0).unwrap();
+        for event in interface_events.read() {
+            if matches!(*event, messages::InterfaceInteraction::Button { .. }) {
+                trash.0 = ItemStack::default();
+            } else {
+                held_item.transfer(&event, std::slice::from_mut(&mut trash.0));
+            }
+        }
+
+        let mut update = messages::InterfaceItemBoxUpdate::default();
+        if let Some(item) = trash.0.item() {
+            update.add_itembox(
+                "inventory/trash",
+                0,
+                item.id,
+                trash.0.size(),
+                item.properties["durability"].as_u64().map(|v| v as u32),
+                tooltips.get(&trash.0).as_deref(),
+            );
+        } else {
+            update.add_empty_itembox("inventory/trash", 0);
+        }
+        net.send_one(parent.0, update);
+    }
+}
+
+#[derive(Component)]
+struct DropAll;
+
+// Throws every stack of the item the player has equipped.
+fn drop_all(
+    mut commands: Commands,
+    mut inventory_query: Query<(&mut Inventory, &GlobalTransform, &Camera), With<Player>>,
+    mut interface_events: Query<
+        (&mut InterfaceEvents, &ChildOf),
+        (Changed<InterfaceEvents>, With<DropAll>),
+    >,
+    mut rng: Local<Rng>,
+) {
+    for (mut interface_events, parent) in interface_events.iter_mut() {
+        let mut pressed = false;
+        for event in interface_events.read() {
+            if matches!(*event, messages::InterfaceInteraction::Button { .. }) {
+                pressed = true;
+            }
+        }
+
+        if !pressed {
+            continue;
+        }
+
+        let (mut inventory, transform, camera) = inventory_query.get_mut(parent.0).unwrap();
+        let Some(item_id) = inventory.held_item_stack().item().map(|item| item.id) else {
+            continue;
+        };
+
+        for item_stack in inventory.iter_mut() {
+            if item_stack.item().map(|item| item.id) != Some(item_id) {
+                continue;
+            }
+
+            let spread = DVec3::new(
+                rng.next_f32() as f64 - 0.5,
+                rng.next_f32() as f64 * 0.5,
+                rng.next_f32() as f64 - 0.5,
+            ) * THROW_SPREAD;
+            let thrown = std::mem::take(item_stack);
+            throw_item_stack(&mut commands, thrown, transform, camera, spread);
+        }
+    }
+}
+
 fn handle_gui_settings(
     mut setting_events: MessageReader<NetworkMessage<messages::GuiSetting>>,
     mut game_mode: Query<&mut GameMode>,
@@ -592,7 +1007,9 @@ fn handle_gui_settings(
         match &setting.message {
             messages::GuiSetting::ButtonSelection { name, selected } => match name.as_str() {
                 "game_mode" => {
-                    let mut game_mode = game_mode.get_mut(setting.player_entity).unwrap();
+                    let Ok(mut game_mode) = game_mode.get_mut(setting.player_entity) else {
+                        continue;
+                    };
                     if *selected == 0 {
                         *game_mode = GameMode::Survival
                     } else if *selected == 1 {
@@ -1,50 +1,154 @@
 use fmc::{
-    blocks::{BlockPosition, Blocks},
+    blocks::{BlockId, BlockPosition, Blocks},
     models::{Model, ModelId, ModelMap, ModelSystems},
     networking::Server,
     physics::Friction,
     physics::{Collider, shapes::Aabb},
-    players::Player,
+    players::{Camera, Player},
     prelude::*,
     protocol::messages,
     world::{
-        ChangedBlockEvent, ChunkLoadEvent, ChunkSubscriptions, WorldMap,
+        ChangedBlockEvent, ChunkLoadEvent, WorldMap,
         chunk::{Chunk, ChunkPosition},
     },
 };
 
-use serde::Serialize;
-use std::collections::HashMap;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{HashMap, HashSet},
+    hash::{DefaultHasher, Hasher},
+};
+
+use crate::settings::Settings;
 
 pub(super) struct MovementPlugin;
 impl Plugin for MovementPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(Update, send_setup)
-            .add_systems(Last, send_block_models.after(ModelSystems));
+        app.add_systems(Startup, (load_keybinds, load_climbable_blocks))
+            .add_systems(
+                Update,
+                (send_setup, send_auto_jump, send_movement_mode).chain(),
+            )
+            .add_systems(Last, send_models.after(ModelSystems));
     }
 }
 
+/// The packets are told apart by the index of their variant, new packets must be added at the end
+/// so older clients can tell them apart from malformed data and ignore them.
 #[derive(Serialize)]
 pub enum MovementPluginPacket<'a> {
     Setup {
         blocks: Vec<CollisionConfig>,
+        keybinds: &'a Keybinds,
+        /// How high a ledge the player walks up without jumping
+        step_height: f32,
     },
     /// Changes the player's velocity
     Velocity(Vec3),
-    /// Notifies the plugin of which models it should collide with.
+    /// Replaces all the models the plugin should collide with.
     Models(&'a HashMap<ModelId, CollisionConfig>),
     /// Changes the game mode
     GameMode(u32),
+    /// Adds models the plugin should collide with, models it already has are replaced.
+    AddModels(&'a HashMap<ModelId, CollisionConfig>),
+    /// Removes models the plugin should no longer collide with.
+    RemoveModels(&'a [ModelId]),
+    /// Puts the plugin in vehicle mode, the player's movement input steers the vehicle they sit
+    /// in instead of the player. `None` returns to walking.
+    Vehicle(Option<VehicleConfig>),
+    /// Changes how the plugin moves the player, see [MovementMode]
+    MovementMode(MovementMode),
+    /// Turns jumping automatically when walking into a ledge on or off
+    AutoJump(bool),
+}
+
+/// How the player's movement plugin moves them, on top of what their game mode allows. Changing
+/// it on a player sends it to their plugin and moves the camera to fit.
+#[derive(Component, Serialize, Default, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MovementMode {
+    /// The plugin moves the player as it sees fit
+    #[default]
+    Walking,
+    /// The player is carried by something and the server decides where they are. The plugin
+    /// doesn't move them.
+    Riding,
+    /// The player can climb up and down anywhere, as if they were on a ladder
+    Climbing,
+    /// The player swims, even when they're not in water
+    Swimming,
+}
+
+impl MovementMode {
+    /// How far the camera is lowered from where it is when standing
+    fn camera_drop(&self) -> f64 {
+        match self {
+            MovementMode::Walking | MovementMode::Climbing => 0.0,
+            MovementMode::Riding => 0.5,
+            MovementMode::Swimming => 1.0,
+        }
+    }
+}
+
+/// If the player is holding down their sneak key. Sneaking players place blocks against blocks
+/// they would otherwise interact with, like chests and furnaces.
+///
+/// The movement plugin reads the sneak key itself, and plugins have no way to send anything back
+/// to the server, so the client doesn't report it yet. It is `false` until it does.
+#[derive(Component, Default, Debug, Clone, Copy, PartialEq, Eq, Deref, DerefMut)]
+pub struct Sneaking(pub bool);
+
+/// How a vehicle moves when the player riding it steers it.
+#[derive(Serialize, Clone, Debug)]
+pub struct VehicleConfig {
+    /// How far below the rider's feet the bottom of the vehicle is
+    pub seat_height: f32,
+    /// How fast the vehicle speeds up when steered
+    pub acceleration: f32,
+    /// How fast the vehicle slows down
+    pub drag: f32,
+    /// The vehicle can only be steered while it floats on water
+    pub water_only: bool,
 }
 
 #[derive(Serialize)]
 pub struct CollisionConfig {
     collider: Vec3Collider,
     friction: Vec3Friction,
-    climbable: bool,
+    climbing: Option<ClimbConfig>,
     is_model: bool,
 }
 
+impl CollisionConfig {
+    fn model(collider: &Collider, friction: &Friction) -> Self {
+        Self {
+            collider: Vec3Collider::from(collider),
+            friction: Vec3Friction::from(friction),
+            climbing: None,
+            is_model: true,
+        }
+    }
+
+    // Identifies this version of the config. Models are only sent again when it changes.
+    fn version(&self) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        hasher.write(&bincode::serialize(self).unwrap());
+        hasher.finish()
+    }
+}
+
+/// Makes players collide with a model that isn't a block, like a boat. Insert it on the model's
+/// entity.
+#[derive(Component)]
+pub struct ModelCollider {
+    pub collider: Collider,
+    pub friction: Friction,
+}
+
+/// The models the player's movement plugin has been told to collide with, and the version of
+/// each one's config.
+#[derive(Component, Default)]
+struct CollidableModels(HashMap<ModelId, u64>);
+
 #[derive(Serialize)]
 enum Vec3Collider {
     Single(Vec3Aabb),
@@ -113,81 +217,204 @@ impl From<&Friction> for Vec3Friction {
     }
 }
 
-fn send_setup(net: Res<Server>, new_players: Query<Entity, Added<Player>>) {
+const CLIMBING_PATH: &str = "./assets/server/climbing.json";
+
+/// How a block is climbed
+#[derive(Serialize, Deserialize, Clone, Copy, Debug)]
+pub struct ClimbConfig {
+    /// How fast the player climbs up
+    pub up_speed: f32,
+    /// The fastest the player slides down
+    pub down_speed: f32,
+    /// Sneaking holds the player in place instead of climbing down
+    pub sneak_holds: bool,
+}
+
+/// The blocks players can climb, read from `climbing.json`
+#[derive(Resource)]
+pub struct ClimbableBlocks(HashMap<BlockId, ClimbConfig>);
+
+impl ClimbableBlocks {
+    pub fn get(&self, block_id: BlockId) -> Option<&ClimbConfig> {
+        self.0.get(&block_id)
+    }
+}
+
+fn load_climbable_blocks(mut commands: Commands) {
+    let file = match std::fs::File::open(CLIMBING_PATH) {
+        Ok(f) => f,
+        Err(e) => panic!(
+            "Failed to open climbable blocks at path: {}\nError: {}",
+            CLIMBING_PATH, e
+        ),
+    };
+
+    let configs: HashMap<String, ClimbConfig> = match serde_json::from_reader(file) {
+        Ok(c) => c,
+        Err(e) => panic!(
+            "Failed to read climbable blocks at path: {}\nError: {}",
+            CLIMBING_PATH, e
+        ),
+    };
+
+    let blocks = Blocks::get();
+
+    let mut climbable = HashMap::new();
+    for (name, config) in configs {
+        if !blocks.contains_block(&name) {
+            panic!(
+                "Failed to read climbable blocks at path: {}\nError: There is no block named '{}'",
+                CLIMBING_PATH, name
+            );
+        }
+        climbable.insert(blocks.get_id(&name), config);
+    }
+
+    commands.insert_resource(ClimbableBlocks(climbable));
+}
+
+const KEYBINDS_PATH: &str = "./assets/server/keybinds.json";
+
+/// Names of the keys the movement plugin can be bound to.
+const KEY_NAMES: [&str; 29] = [
+    "KeyA", "KeyB", "KeyC", "KeyD", "KeyE", "KeyF", "KeyG", "KeyH", "KeyI", "KeyJ", "KeyK", "KeyL",
+    "KeyM", "KeyN", "KeyO", "KeyP", "KeyQ", "KeyR", "KeyS", "KeyT", "KeyU", "KeyV", "KeyW", "KeyX",
+    "KeyY", "KeyZ", "Space", "Shift", "Control",
+];
+
+/// The keys the client's movement plugin moves the player with.
+#[derive(Resource, Serialize, Deserialize)]
+pub struct Keybinds {
+    forward: String,
+    back: String,
+    left: String,
+    right: String,
+    jump: String,
+    sneak: String,
+    sprint: String,
+    /// Toggles flight in creative, if not set flight is toggled by double tapping jump.
+    #[serde(default)]
+    fly_toggle: Option<String>,
+}
+
+fn load_keybinds(mut commands: Commands) {
+    let file = match std::fs::File::open(KEYBINDS_PATH) {
+        Ok(f) => f,
+        Err(e) => panic!(
+            "Failed to open keybinds at path: {}\nError: {}",
+            KEYBINDS_PATH, e
+        ),
+    };
+
+    let keybinds: Keybinds = match serde_json::from_reader(file) {
+        Ok(k) => k,
+        Err(e) => panic!(
+            "Failed to read keybinds at path: {}\nError: {}",
+            KEYBINDS_PATH, e
+        ),
+    };
+
+    let keys = [
+        &keybinds.forward,
+        &keybinds.back,
+        &keybinds.left,
+        &keybinds.right,
+        &keybinds.jump,
+        &keybinds.sneak,
+        &keybinds.sprint,
+    ];
+    for key in keys.into_iter().chain(keybinds.fly_toggle.as_ref()) {
+        if !KEY_NAMES.contains(&key.as_str()) {
+            panic!(
+                "Failed to read keybinds at path: {}\nError: There is no key named '{}', valid keys are: {}",
+                KEYBINDS_PATH,
+                key,
+                KEY_NAMES.join(", ")
+            );
+        }
+    }
+
+    commands.insert_resource(keybinds);
+}
+
+fn send_setup(
+    mut commands: Commands,
+    net: Res<Server>,
+    settings: Res<Settings>,
+    keybinds: Res<Keybinds>,
+    climbable_blocks: Res<ClimbableBlocks>,
+    new_players: Query<Entity, Added<Player>>,
+    mut setup_packet: Local<Option<Vec<u8>>>,
+) {
     for player_entity in new_players.iter() {
-        // TODO: These can be pre-computed
-        let block_collision_configs = Blocks::get()
-            .configs()
-            .iter()
-            .map(|config| CollisionConfig {
-                collider: Vec3Collider::from(&config.collider),
-                friction: Vec3Friction::from(&config.friction),
-                climbable: &config.name == "ladder",
-                is_model: config.model.is_some(),
+        // The block configs, keybinds and step height don't change after startup, so the packet is
+        // only built once.
+        let data = setup_packet.get_or_insert_with(|| {
+            let blocks = Blocks::get();
+            let block_collision_configs = blocks
+                .configs()
+                .iter()
+                .map(|config| CollisionConfig {
+                    collider: Vec3Collider::from(&config.collider),
+                    friction: Vec3Friction::from(&config.friction),
+                    climbing: climbable_blocks.get(blocks.get_id(&config.name)).copied(),
+                    is_model: config.model.is_some(),
+                })
+                .collect();
+
+            bincode::serialize(&MovementPluginPacket::Setup {
+                blocks: block_collision_configs,
+                keybinds: &keybinds,
+                step_height: settings.step_height,
             })
-            .collect();
+            .unwrap()
+        });
 
         net.send_one(
             player_entity,
             messages::PluginData {
                 plugin: "movement".to_owned(),
-                data: bincode::serialize(&MovementPluginPacket::Setup {
-                    blocks: block_collision_configs,
-                })
-                .unwrap(),
+                data: data.clone(),
             },
         );
+
+        commands
+            .entity(player_entity)
+            .insert(CollidableModels::default());
     }
 }
 
-fn send_block_models(
+fn send_auto_jump(
     net: Res<Server>,
-    world_map: Res<WorldMap>,
-    model_map: Res<ModelMap>,
-    chunk_subscriptions: Res<ChunkSubscriptions>,
-    block_model_query: Query<&BlockPosition, With<Model>>,
-    players: Query<(Entity, Ref<ChunkPosition>), With<Player>>,
-    mut changed_blocks: MessageReader<ChangedBlockEvent>,
-    mut loaded_chunks: MessageReader<ChunkLoadEvent>,
-    mut nearby_models: Local<HashMap<ModelId, CollisionConfig>>,
+    settings: Res<Settings>,
+    new_players: Query<Entity, Added<Player>>,
+    players: Query<Entity, With<Player>>,
 ) {
-    let gather_models =
-        |chunk_position: ChunkPosition, nearby_models: &mut HashMap<ModelId, CollisionConfig>| {
-            nearby_models.clear();
-
-            for chunk_position in chunk_position.neighbourhood() {
-                for model_entity in model_map.iter_entities(&chunk_position) {
-                    // TODO: restricted to block models for now, but should include all models
-                    let Ok(block_position) = block_model_query.get(model_entity) else {
-                        continue;
-                    };
-                    let block_id = world_map.get_block(*block_position).unwrap();
-                    let block_config = Blocks::get().get_config(&block_id);
-
-                    if block_config.is_solid() {
-                        nearby_models.insert(
-                            model_entity.index_u32() as ModelId,
-                            CollisionConfig {
-                                collider: Vec3Collider::from(&block_config.collider),
-                                friction: Vec3Friction::from(&block_config.friction),
-                                climbable: false,
-                                is_model: true,
-                            },
-                        );
-                    }
-                }
-            }
-        };
-
-    // When a player moves over a chunk boundary
-    for (player_entity, player_chunk_position) in players.iter() {
-        if !player_chunk_position.is_changed() {
-            continue;
-        }
+    let receivers = if settings.is_changed() {
+        players.iter().collect::<Vec<_>>()
+    } else {
+        new_players.iter().collect()
+    };
 
-        gather_models(*player_chunk_position, &mut nearby_models);
+    for player_entity in receivers {
+        net.send_one(
+            player_entity,
+            messages::PluginData {
+                plugin: "movement".to_owned(),
+                data: bincode::serialize(&MovementPluginPacket::AutoJump(settings.auto_jump))
+                    .unwrap(),
+            },
+        );
+    }
+}
 
-        if nearby_models.is_empty() {
+fn send_movement_mode(
+    net: Res<Server>,
+    mut players: Query<(Entity, Ref<MovementMode>, &mut Camera), With<Player>>,
+) {
+    for (player_entity, movement_mode, mut camera) in players.iter_mut() {
+        // The plugin starts out walking
+        if !movement_mode.is_changed() || movement_mode.is_added() {
             continue;
         }
 
@@ -195,10 +422,37 @@ fn send_block_models(
             player_entity,
             messages::PluginData {
                 plugin: "movement".to_owned(),
-                data: bincode::serialize(&MovementPluginPacket::Models(&nearby_models)).unwrap(),
+                data: bincode::serialize(&MovementPluginPacket::MovementMode(*movement_mode))
+                    .unwrap(),
             },
-        )
+        );
+
+        camera.translation.y = Camera::default().translation.y - movement_mode.camera_drop();
+        net.send_one(
+            player_entity,
+            messages::PlayerCameraPosition {
+                position: camera.translation.as_vec3(),
+            },
+        );
     }
+}
+
+// Players collide with the models in the chunks around them. When the models there change, only
+// the difference from what the player's plugin already has is sent.
+fn send_models(
+    net: Res<Server>,
+    world_map: Res<WorldMap>,
+    model_map: Res<ModelMap>,
+    block_model_query: Query<&BlockPosition, With<Model>>,
+    model_colliders: Query<(Entity, Ref<ModelCollider>, &GlobalTransform)>,
+    mut players: Query<(Entity, Ref<ChunkPosition>, &mut CollidableModels), With<Player>>,
+    mut changed_blocks: MessageReader<ChangedBlockEvent>,
+    mut loaded_chunks: MessageReader<ChunkLoadEvent>,
+    mut removed_colliders: RemovedComponents<ModelCollider>,
+    mut collider_chunks: Local<HashMap<Entity, ChunkPosition>>,
+) {
+    // Chunks whose models have changed
+    let mut changed_chunks = HashSet::new();
 
     for block_update in changed_blocks.read() {
         let from = Blocks::get().get_config(&block_update.from.0);
@@ -208,53 +462,107 @@ fn send_block_models(
             continue;
         }
 
-        let model_chunk_position = ChunkPosition::from(block_update.position);
-        let Some(subscribers) = chunk_subscriptions.get_subscribers(&model_chunk_position) else {
-            continue;
-        };
-
-        for player_entity in subscribers.iter() {
-            let (_, player_chunk_position) = players.get(*player_entity).unwrap();
-            if (*player_chunk_position - model_chunk_position)
-                .abs()
-                .cmple(IVec3::splat(Chunk::SIZE as i32))
-                .all()
-            {
-                gather_models(*player_chunk_position, &mut nearby_models);
-                net.send_one(
-                    *player_entity,
-                    messages::PluginData {
-                        plugin: "movement".to_owned(),
-                        data: bincode::serialize(&MovementPluginPacket::Models(&nearby_models))
-                            .unwrap(),
-                    },
-                )
-            }
-        }
+        changed_chunks.insert(ChunkPosition::from(block_update.position));
     }
 
     for new_chunk in loaded_chunks.read() {
-        let Some(subscribers) = chunk_subscriptions.get_subscribers(&new_chunk.position) else {
+        changed_chunks.insert(new_chunk.position);
+    }
+
+    for (entity, model_collider, transform) in model_colliders.iter() {
+        let chunk_position = ChunkPosition::from(BlockPosition::from(transform.translation()));
+        let previous = collider_chunks.insert(entity, chunk_position);
+        if model_collider.is_changed() || previous != Some(chunk_position) {
+            changed_chunks.insert(chunk_position);
+            changed_chunks.extend(previous);
+        }
+    }
+
+    for entity in removed_colliders.read() {
+        changed_chunks.extend(collider_chunks.remove(&entity));
+    }
+
+    let is_nearby = |player_chunk_position: ChunkPosition, chunk_position: ChunkPosition| {
+        (player_chunk_position - chunk_position)
+            .abs()
+            .cmple(IVec3::splat(Chunk::SIZE as i32))
+            .all()
+    };
+
+    for (player_entity, player_chunk_position, mut collidable_models) in players.iter_mut() {
+        // When a player moves over a chunk boundary the models around it are different
+        if !player_chunk_position.is_changed()
+            && !collidable_models.is_added()
+            && !changed_chunks
+                .iter()
+                .any(|chunk_position| is_nearby(*player_chunk_position, *chunk_position))
+        {
             continue;
-        };
-
-        for player_entity in subscribers.iter() {
-            let (_, player_chunk_position) = players.get(*player_entity).unwrap();
-            if (*player_chunk_position - new_chunk.position)
-                .abs()
-                .cmple(IVec3::splat(Chunk::SIZE as i32))
-                .all()
-            {
-                gather_models(*player_chunk_position, &mut nearby_models);
-                net.send_one(
-                    *player_entity,
-                    messages::PluginData {
-                        plugin: "movement".to_owned(),
-                        data: bincode::serialize(&MovementPluginPacket::Models(&nearby_models))
-                            .unwrap(),
-                    },
-                )
+        }
+
+        let mut nearby_models = HashMap::new();
+
+        for chunk_position in player_chunk_position.neighbourhood() {
+            for model_entity in model_map.iter_entities(&chunk_position) {
+                let Ok(block_position) = block_model_query.get(model_entity) else {
+                    continue;
+                };
+                let block_id = world_map.get_block(*block_position).unwrap();
+                let block_config = Blocks::get().get_config(&block_id);
+
+                if block_config.is_solid() {
+                    nearby_models.insert(
+                        model_entity.index_u32() as ModelId,
+                        CollisionConfig::model(&block_config.collider, &block_config.friction),
+                    );
+                }
             }
         }
+
+        for (model_entity, model_collider, _) in model_colliders.iter() {
+            if is_nearby(*player_chunk_position, collider_chunks[&model_entity]) {
+                nearby_models.insert(
+                    model_entity.index_u32() as ModelId,
+                    CollisionConfig::model(&model_collider.collider, &model_collider.friction),
+                );
+            }
+        }
+
+        let removed: Vec<ModelId> = collidable_models
+            .0
+            .keys()
+            .filter(|model_id| !nearby_models.contains_key(model_id))
+            .copied()
+            .collect();
+        for model_id in removed.iter() {
+            collidable_models.0.remove(model_id);
+        }
+
+        nearby_models.retain(|model_id, config| {
+            let version = config.version();
+            collidable_models.0.insert(*model_id, version) != Some(version)
+        });
+
+        if !removed.is_empty() {
+            net.send_one(
+                player_entity,
+                messages::PluginData {
+                    plugin: "movement".to_owned(),
+                    data: bincode::serialize(&MovementPluginPacket::RemoveModels(&removed))
+                        .unwrap(),
+                },
+            );
+        }
+
+        if !nearby_models.is_empty() {
+            net.send_one(
+                player_entity,
+                messages::PluginData {
+                    plugin: "movement".to_owned(),
+                    data: bincode::serialize(&MovementPluginPacket::AddModels(&nearby_models))
+                        .unwrap(),
+                },
+            );
+        }
     }
 }
@@ -0,0 +1,226 @@
+use fmc::{bevy::math::DVec3, networking::Server, players::Player, prelude::*, protocol::messages};
+
+use crate::mobs::Mob;
+
+use super::{HandInteractions, Health, MovementPluginPacket, VehicleConfig};
+
+/// Lets players ride entities that have a [Seat], like boats.
+///
+/// While riding, the player's movement plugin steers the vehicle instead of the player, and the
+/// vehicle follows the position the player reports. Players mount a vehicle by right clicking it,
+/// and get off by right clicking it again. They are also let off when they die, disconnect, or
+/// the vehicle is removed.
+pub struct RidingPlugin;
+impl Plugin for RidingPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_message::<Mount>()
+            .add_message::<Dismount>()
+            .add_systems(
+                Update,
+                (
+                    interact_with_seats,
+                    dismount_riders,
+                    mount_riders,
+                    empty_seats,
+                    follow_riders,
+                )
+                    .chain(),
+            );
+    }
+}
+
+/// A place on an entity that a player can ride in.
+#[derive(Component)]
+pub struct Seat {
+    rider: Option<Entity>,
+    /// Where the rider's feet are, relative to the entity
+    offset: DVec3,
+    config: VehicleConfig,
+}
+
+impl Seat {
+    pub fn new(offset: DVec3, config: VehicleConfig) -> Self {
+        Self {
+            rider: None,
+            offset,
+            config,
+        }
+    }
+
+    /// The player sitting in the seat
+    pub fn rider(&self) -> Option<Entity> {
+        self.rider
+    }
+}
+
+/// Inserted on players while they ride a vehicle
+#[derive(Component)]
+pub struct Riding {
+    vehicle: Entity,
+}
+
+impl Riding {
+    /// The entity the player is riding
+    pub fn vehicle(&self) -> Entity {
+        self.vehicle
+    }
+}
+
+/// Seat the player in the vehicle. Nothing happens if the seat is taken or the player already
+/// rides something.
+#[derive(Message)]
+pub struct Mount {
+    pub player_entity: Entity,
+    pub vehicle: Entity,
+}
+
+/// Let the player off the vehicle they are riding
+#[derive(Message)]
+pub struct Dismount {
+    pub player_entity: Entity,
+}
+
+fn send_vehicle_config(net: &Server, player_entity: Entity, config: Option<VehicleConfig>) {
+    net.send_one(
+        player_entity,
+        messages::PluginData {
+            plugin: "movement".to_owned(),
+            data: bincode::serialize(&MovementPluginPacket::Vehicle(config)).unwrap(),
+        },
+    );
+}
+
+// Mobs are mounted through their own interactions, see crate::mobs::saddles
+fn interact_with_seats(
+    riders: Query<&Riding>,
+    mut seats: Query<
+        (Entity, &mut HandInteractions),
+        (With<Seat>, Without<Mob>, Changed<HandInteractions>),
+    >,
+    mut mount_events: MessageWriter<Mount>,
+    mut dismount_events: MessageWriter<Dismount>,
+) {
+    for (vehicle, mut interactions) in seats.iter_mut() {
+        for player_entity in interactions.read() {
+            match riders.get(player_entity) {
+                Ok(riding) if riding.vehicle == vehicle => {
+                    dismount_events.write(Dismount { player_entity });
+                }
+                Ok(_) => (),
+                Err(_) => {
+                    mount_events.write(Mount {
+                        player_entity,
+                        vehicle,
+                    });
+                }
+            }
+        }
+    }
+}
+
+fn mount_riders(
+    mut commands: Commands,
+    net: Res<Server>,
+    mut players: Query<&mut Transform, (With<Player>, Without<Riding>)>,
+    mut seats: Query<(&mut Seat, &Transform), Without<Player>>,
+    mut mount_events: MessageReader<Mount>,
+) {
+    for mount in mount_events.read() {
+        let Ok(mut player_transform) = players.get_mut(mount.player_entity) else {
+            continue;
+        };
+        let Ok((mut seat, vehicle_transform)) = seats.get_mut(mount.vehicle) else {
+            continue;
+        };
+        if seat.rider.is_some() {
+            continue;
+        }
+
+        seat.rider = Some(mount.player_entity);
+        commands.entity(mount.player_entity).insert(Riding {
+            vehicle: mount.vehicle,
+        });
+
+        player_transform.translation = vehicle_transform.translation + seat.offset;
+        net.send_one(
+            mount.player_entity,
+            messages::PlayerPosition {
+                position: player_transform.translation,
+            },
+        );
+        send_vehicle_config(&net, mount.player_entity, Some(seat.config.clone()));
+    }
+}
+
+fn dismount_riders(
+    mut commands: Commands,
+    net: Res<Server>,
+    mut players: Query<(Entity, &mut Transform, &Riding, Ref<Health>), With<Player>>,
+    mut seats: Query<&mut Seat>,
+    mut dismount_events: MessageReader<Dismount>,
+) {
+    let mut dismounted: Vec<Entity> = dismount_events
+        .read()
+        .map(|dismount| dismount.player_entity)
+        .collect();
+
+    for (player_entity, _, riding, health) in players.iter() {
+        let vehicle_removed = !seats.contains(riding.vehicle);
+        if vehicle_removed || (health.is_changed() && health.is_dead()) {
+            dismounted.push(player_entity);
+        }
+    }
+    dismounted.sort();
+    dismounted.dedup();
+
+    for player_entity in dismounted {
+        let Ok((_, mut transform, riding, _)) = players.get_mut(player_entity) else {
+            continue;
+        };
+
+        if let Ok(mut seat) = seats.get_mut(riding.vehicle) {
+            seat.rider = None;
+        }
+        commands.entity(player_entity).remove::<Riding>();
+
+        // Step off on top of the vehicle so the player doesn't end up inside it
+        transform.translation += DVec3::Y;
+        net.send_one(
+            player_entity,
+            messages::PlayerPosition {
+                position: transform.translation,
+            },
+        );
+        send_vehicle_config(&net, player_entity, None);
+    }
+}
+
+// Riders that disconnect leave their seat without being dismounted.
+fn empty_seats(riders: Query<&Riding>, mut seats: Query<(Entity, &mut Seat)>) {
+    for (vehicle, mut seat) in seats.iter_mut() {
+        let Some(rider) = seat.rider else {
+            continue;
+        };
+
+        if !riders
+            .get(rider)
+            .is_ok_and(|riding| riding.vehicle == vehicle)
+        {
+            seat.rider = None;
+        }
+    }
+}
+
+// The rider's movement plugin moves the player, the vehicle follows along underneath.
+fn follow_riders(
+    riders: Query<(&Riding, &Transform), (With<Player>, Changed<Transform>)>,
+    mut vehicles: Query<(&Seat, &mut Transform), Without<Player>>,
+) {
+    for (riding, player_transform) in riders.iter() {
+        let Ok((seat, mut vehicle_transform)) = vehicles.get_mut(riding.vehicle) else {
+            continue;
+        };
+
+        vehicle_transform.translation = player_transform.translation - seat.offset;
+    }
+}
@@ -0,0 +1,183 @@
+use std::collections::BTreeMap;
+
+use fmc::{
+    networking::{NetworkEvent, Server},
+    players::Player,
+    prelude::*,
+    protocol::messages,
+};
+
+use crate::chat::{CHAT_FONT_SIZE, CHAT_TEXT_COLOR, broadcast_message};
+
+use crate::world::dimensions::Dimension;
+
+use super::GameMode;
+
+/// Keeps track of which players are online, and sends the player list to the clients.
+pub struct RosterPlugin;
+impl Plugin for RosterPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(Roster::default()).add_systems(
+            Update,
+            (
+                (handle_connections, update_game_modes, update_dimensions).chain(),
+                send_player_list.after(update_dimensions),
+            ),
+        );
+    }
+}
+
+/// How often the player list is sent to the clients
+const PLAYER_LIST_INTERVAL: f32 = 2.0;
+
+/// An online player
+pub struct RosterEntry {
+    pub entity: Entity,
+    pub game_mode: GameMode,
+    /// Name of the dimension the player is in
+    pub dimension: String,
+    /// Round trip time to the player's client.
+    // TODO: Not exposed by the networking layer yet, always None.
+    pub ping: Option<std::time::Duration>,
+}
+
+/// All online players, by username.
+#[derive(Resource, Default)]
+pub struct Roster {
+    players: BTreeMap<String, RosterEntry>,
+}
+
+impl Roster {
+    pub fn get(&self, username: &str) -> Option<&RosterEntry> {
+        self.players.get(username)
+    }
+
+    /// The entity of the player with the given username, if they are online
+    pub fn get_entity(&self, username: &str) -> Option<Entity> {
+        self.players.get(username).map(|entry| entry.entity)
+    }
+
+    /// Iterate over the online players in alphabetical order
+    pub fn iter(&self) -> impl Iterator<Item = (&String, &RosterEntry)> {
+        self.players.iter()
+    }
+
+    pub fn len(&self) -> usize {
+        self.players.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.players.is_empty()
+    }
+
+    fn get_by_entity_mut(&mut self, entity: Entity) -> Option<&mut RosterEntry> {
+        self.players
+            .values_mut()
+            .find(|entry| entry.entity == entity)
+    }
+
+    fn build_player_list(&self) -> messages::InterfaceTextUpdate {
+        let mut text = format!("Players online: {}", self.len());
+        for (username, entry) in self.players.iter() {
+            let game_mode = match entry.game_mode {
+                GameMode::Survival => "survival",
+                GameMode::Creative => "creative",
+                GameMode::Spectator => "spectator",
+            };
+            let ping = entry
+                .ping
+                .map(|ping| format!("{}ms", ping.as_millis()))
+                .unwrap_or("-".to_owned());
+            text.push_str(&format!(
+                "\n{}  {}  {}  {}",
+                username, game_mode, entry.dimension, ping
+            ));
+        }
+
+        messages::InterfaceTextUpdate {
+            interface_path: "player_list/players".to_owned(),
+            index: 0,
+            text,
+            font_size: CHAT_FONT_SIZE,
+            color: CHAT_TEXT_COLOR.to_owned(),
+        }
+    }
+}
+
+// TODO: Maybe players should be passed the chat history too.
+// TODO: The "joined game" message sometimes shows for the player that joined. Intermitent problem,
+// the message should arrive before the client finishes setup. In which case it should be
+// discarded after two event buffer switches.
+fn handle_connections(
+    net: Res<Server>,
+    mut roster: ResMut<Roster>,
+    player_query: Query<&Player>,
+    mut network_events: MessageReader<NetworkEvent>,
+) {
+    for event in network_events.read() {
+        match event {
+            NetworkEvent::Connected { entity } => {
+                let player = player_query.get(*entity).unwrap();
+                roster.players.insert(
+                    player.username.clone(),
+                    RosterEntry {
+                        entity: *entity,
+                        // The game mode is loaded with the rest of the player, it is updated
+                        // once it has been.
+                        game_mode: GameMode::default(),
+                        dimension: "overworld".to_owned(),
+                        ping: None,
+                    },
+                );
+
+                broadcast_message(&net, format!("{} joined the game", player.username));
+            }
+            NetworkEvent::Disconnected { entity } => {
+                let player = player_query.get(*entity).unwrap();
+                roster.players.remove(&player.username);
+
+                broadcast_message(&net, format!("{} left the game", player.username));
+            }
+        }
+    }
+}
+
+fn update_game_modes(
+    mut roster: ResMut<Roster>,
+    player_query: Query<(Entity, &GameMode), Changed<GameMode>>,
+) {
+    for (player_entity, game_mode) in player_query.iter() {
+        if let Some(entry) = roster.get_by_entity_mut(player_entity) {
+            entry.game_mode = *game_mode;
+        }
+    }
+}
+
+fn update_dimensions(
+    mut roster: ResMut<Roster>,
+    player_query: Query<(Entity, &Dimension), Changed<Dimension>>,
+) {
+    for (player_entity, dimension) in player_query.iter() {
+        if let Some(entry) = roster.get_by_entity_mut(player_entity) {
+            entry.dimension = dimension.name().to_owned();
+        }
+    }
+}
+
+fn send_player_list(
+    net: Res<Server>,
+    time: Res<Time>,
+    roster: Res<Roster>,
+    mut timer: Local<Timer>,
+) {
+    if timer.duration().is_zero() {
+        *timer = Timer::from_seconds(PLAYER_LIST_INTERVAL, TimerMode::Repeating);
+    }
+
+    timer.tick(time.delta());
+    if !timer.just_finished() && !roster.is_changed() {
+        return;
+    }
+
+    net.broadcast(roster.build_player_list());
+}
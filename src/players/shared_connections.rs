@@ -0,0 +1,128 @@
+use std::collections::HashMap;
+
+use fmc::{
+    bevy::ecs::system::SystemParam,
+    networking::NetworkEvent,
+    prelude::*,
+    world::{ChunkSubscriptions, chunk::ChunkPosition},
+};
+
+/// Lets several player entities share the network connection of one player, e.g. bots and
+/// companions that are controlled by the server, or fake players in a test harness. The chunk
+/// subscriptions are kept per connection, so the entities that share a connection see the same
+/// chunks as the player that owns it.
+///
+/// Guests don't receive any messages. Messages sent to a guest entity go nowhere, they are not
+/// forwarded to the connection it shares, since that player would then see them as their own.
+pub struct SharedConnectionPlugin;
+impl Plugin for SharedConnectionPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(SharedConnections::default())
+            .add_systems(
+                Update,
+                (add_guests, remove_guests, despawn_guests_on_disconnect).chain(),
+            );
+    }
+}
+
+/// Marks a player entity that has no network connection of its own. It uses the chunk
+/// subscriptions of the connection it shares instead.
+#[derive(Component)]
+pub struct SharedConnection {
+    /// The player entity that owns the connection
+    pub connection: Entity,
+}
+
+/// Which player entities share each connection
+#[derive(Resource, Default)]
+pub struct SharedConnections {
+    guests: HashMap<Entity, Vec<Entity>>,
+    // The connection of each guest, so it can be found again when the guest is removed
+    connections: HashMap<Entity, Entity>,
+}
+
+impl SharedConnections {
+    /// The player entities that share the connection, not including the one that owns it.
+    pub fn guests(&self, connection: Entity) -> &[Entity] {
+        self.guests
+            .get(&connection)
+            .map(Vec::as_slice)
+            .unwrap_or(&[])
+    }
+}
+
+/// Chunk subscriptions by player entity instead of by connection.
+///
+/// [ChunkSubscriptions] only knows of the connections, which is what should be used when sending
+/// messages. Use this instead when the subscribers are treated as players, like when looking for
+/// the players close to a chunk.
+#[derive(SystemParam)]
+pub struct PlayerSubscriptions<'w> {
+    chunk_subscriptions: Res<'w, ChunkSubscriptions>,
+    shared_connections: Res<'w, SharedConnections>,
+}
+
+impl PlayerSubscriptions<'_> {
+    /// All player entities that can see the chunk, None if the chunk isn't loaded.
+    pub fn get_players(
+        &self,
+        chunk_position: &ChunkPosition,
+    ) -> Option<impl Iterator<Item = Entity> + '_> {
+        let connections = self.chunk_subscriptions.get_subscribers(chunk_position)?;
+        Some(connections.iter().flat_map(|connection| {
+            std::iter::once(*connection)
+                .chain(self.shared_connections.guests(*connection).iter().copied())
+        }))
+    }
+}
+
+fn add_guests(
+    mut shared_connections: ResMut<SharedConnections>,
+    guest_query: Query<(Entity, &SharedConnection), Added<SharedConnection>>,
+) {
+    for (guest_entity, shared_connection) in guest_query.iter() {
+        shared_connections
+            .connections
+            .insert(guest_entity, shared_connection.connection);
+        shared_connections
+            .guests
+            .entry(shared_connection.connection)
+            .or_default()
+            .push(guest_entity);
+    }
+}
+
+fn remove_guests(
+    mut shared_connections: ResMut<SharedConnections>,
+    mut removed: RemovedComponents<SharedConnection>,
+) {
+    for guest_entity in removed.read() {
+        let Some(connection) = shared_connections.connections.remove(&guest_entity) else {
+            continue;
+        };
+
+        if let Some(guests) = shared_connections.guests.get_mut(&connection) {
+            guests.retain(|entity| *entity != guest_entity);
+            if guests.is_empty() {
+                shared_connections.guests.remove(&connection);
+            }
+        }
+    }
+}
+
+// The guests can't stay in the world without the connection they see it through.
+fn despawn_guests_on_disconnect(
+    mut commands: Commands,
+    shared_connections: Res<SharedConnections>,
+    mut network_events: MessageReader<NetworkEvent>,
+) {
+    for event in network_events.read() {
+        let NetworkEvent::Disconnected { entity } = event else {
+            continue;
+        };
+
+        for guest_entity in shared_connections.guests(*entity) {
+            commands.entity(*guest_entity).despawn();
+        }
+    }
+}
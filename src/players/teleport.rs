@@ -0,0 +1,289 @@
+use std::collections::{BTreeMap, HashMap};
+
+use fmc::{
+    bevy::math::DVec3,
+    database::Database,
+    networking::Server,
+    players::Player,
+    prelude::*,
+    protocol::messages,
+    world::{WorldMap, chunk::ChunkPosition},
+};
+
+use crate::{
+    chat::send_message,
+    database_io::{DatabaseIo, DatabaseTask},
+    world::{WorldProperties, dimensions::Dimension},
+};
+
+use super::{Dismount, Riding, find_world_spawn, load_chunks};
+
+/// Moves players to other players, coordinates, the world spawn, their home, or one of the warps
+/// set up by the operators.
+///
+/// The chunk at the destination is loaded on the io thread before the player is moved, so they
+/// don't arrive in terrain that is still being generated. Homes and warps are kept in the
+/// database.
+pub struct TeleportPlugin;
+impl Plugin for TeleportPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_message::<Teleport>()
+            .add_message::<SetWaypoint>()
+            .add_message::<RemoveWarp>()
+            .add_systems(Startup, load_waypoints)
+            .add_systems(
+                Update,
+                (
+                    (set_waypoints, remove_warps),
+                    (start_teleports, finish_teleports).chain(),
+                ),
+            );
+    }
+}
+
+/// Move a player to the destination once it has loaded
+#[derive(Message)]
+pub struct Teleport {
+    pub player_entity: Entity,
+    pub destination: Destination,
+}
+
+pub enum Destination {
+    Position(DVec3),
+    WorldSpawn,
+    Waypoint(Waypoint),
+}
+
+pub enum Waypoint {
+    /// The player's own home
+    Home,
+    /// A named warp, shared by all players
+    Warp(String),
+}
+
+/// Set the waypoint to where the player is standing
+#[derive(Message)]
+pub struct SetWaypoint {
+    pub player_entity: Entity,
+    pub waypoint: Waypoint,
+}
+
+#[derive(Message)]
+pub struct RemoveWarp {
+    /// The player that removed the warp, None for the server console
+    pub requested_by: Option<Entity>,
+    pub name: String,
+}
+
+/// The homes of the players by username, and the warps by name.
+#[derive(Resource)]
+struct Waypoints {
+    homes: HashMap<String, DVec3>,
+    warps: BTreeMap<String, DVec3>,
+}
+
+impl Waypoints {
+    fn load(database: &Database) -> Self {
+        let conn = database.get_write_connection();
+        conn.execute_batch(
+            "CREATE TABLE IF NOT EXISTS homes (
+                username TEXT PRIMARY KEY,
+                x REAL NOT NULL,
+                y REAL NOT NULL,
+                z REAL NOT NULL
+            );
+            CREATE TABLE IF NOT EXISTS warps (
+                name TEXT PRIMARY KEY,
+                x REAL NOT NULL,
+                y REAL NOT NULL,
+                z REAL NOT NULL
+            );",
+        )
+        .unwrap();
+
+        let read = |table: &str| {
+            let mut stmt = conn.prepare(&format!("SELECT * FROM {}", table)).unwrap();
+            stmt.query_map([], |row| {
+                Ok((
+                    row.get::<_, String>(0)?,
+                    DVec3::new(row.get(1)?, row.get(2)?, row.get(3)?),
+                ))
+            })
+            .unwrap()
+            .map(|waypoint| waypoint.unwrap())
+            .collect::<Vec<_>>()
+        };
+
+        Self {
+            homes: read("homes").into_iter().collect(),
+            warps: read("warps").into_iter().collect(),
+        }
+    }
+}
+
+/// The player is waiting for the destination to load
+#[derive(Component)]
+struct PendingTeleport(DatabaseTask<DVec3>);
+
+fn load_waypoints(mut commands: Commands, database: Res<Database>) {
+    commands.insert_resource(Waypoints::load(&database));
+}
+
+fn set_waypoints(
+    net: Res<Server>,
+    database_io: Res<DatabaseIo>,
+    mut waypoints: ResMut<Waypoints>,
+    player_query: Query<(&Player, &Transform)>,
+    mut set_events: MessageReader<SetWaypoint>,
+) {
+    for set_event in set_events.read() {
+        let Ok((player, transform)) = player_query.get(set_event.player_entity) else {
+            continue;
+        };
+        let position = transform.translation;
+
+        let (table, name) = match &set_event.waypoint {
+            Waypoint::Home => {
+                waypoints.homes.insert(player.username.clone(), position);
+                send_message(&net, set_event.player_entity, "Set your home".to_owned());
+                ("homes", player.username.clone())
+            }
+            Waypoint::Warp(name) => {
+                waypoints.warps.insert(name.clone(), position);
+                send_message(
+                    &net,
+                    set_event.player_entity,
+                    format!("Set the warp {}", name),
+                );
+                ("warps", name.clone())
+            }
+        };
+
+        database_io.run(move |database| {
+            let conn = database.get_write_connection();
+            conn.execute(
+                &format!("INSERT OR REPLACE INTO {} VALUES (?,?,?,?)", table),
+                rusqlite::params![name, position.x, position.y, position.z],
+            )
+            .unwrap();
+        });
+    }
+}
+
+fn remove_warps(
+    net: Res<Server>,
+    database_io: Res<DatabaseIo>,
+    mut waypoints: ResMut<Waypoints>,
+    mut remove_events: MessageReader<RemoveWarp>,
+) {
+    for remove_event in remove_events.read() {
+        let text = if waypoints.warps.remove(&remove_event.name).is_some() {
+            let name = remove_event.name.clone();
+            database_io.run(move |database| {
+                let conn = database.get_write_connection();
+                conn.execute("DELETE FROM warps WHERE name = ?", rusqlite::params![name])
+                    .unwrap();
+            });
+            format!("Removed the warp {}", remove_event.name)
+        } else {
+            format!("There is no warp named {}", remove_event.name)
+        };
+
+        match remove_event.requested_by {
+            Some(player_entity) => send_message(&net, player_entity, text),
+            None => info!("{}", text),
+        }
+    }
+}
+
+fn start_teleports(
+    mut commands: Commands,
+    net: Res<Server>,
+    world_map: Res<WorldMap>,
+    world_properties: Res<WorldProperties>,
+    database_io: Res<DatabaseIo>,
+    waypoints: Res<Waypoints>,
+    player_query: Query<(&Player, Has<Riding>)>,
+    mut teleport_events: MessageReader<Teleport>,
+    mut dismount_events: MessageWriter<Dismount>,
+) {
+    for teleport in teleport_events.read() {
+        let Ok((player, is_riding)) = player_query.get(teleport.player_entity) else {
+            continue;
+        };
+
+        // The chunk is loaded only to have it ready when the player arrives
+        let load_position = |position: DVec3| {
+            let chunk_position = ChunkPosition::from(position);
+            load_chunks(&world_map, &database_io, move |load_chunk| {
+                load_chunk(chunk_position);
+                position
+            })
+        };
+
+        let task = match &teleport.destination {
+            Destination::Position(position) => load_position(*position),
+            Destination::WorldSpawn => {
+                let chunk_position = ChunkPosition::from(world_properties.spawn_point.center);
+                load_chunks(&world_map, &database_io, move |load_chunk| {
+                    find_world_spawn(chunk_position, load_chunk).as_dvec3()
+                        + DVec3::new(0.5, 0.0, 0.5)
+                })
+            }
+            Destination::Waypoint(Waypoint::Home) => {
+                let Some(home) = waypoints.homes.get(&player.username) else {
+                    send_message(
+                        &net,
+                        teleport.player_entity,
+                        "You have not set a home, use /sethome".to_owned(),
+                    );
+                    continue;
+                };
+                load_position(*home)
+            }
+            Destination::Waypoint(Waypoint::Warp(name)) => {
+                let Some(warp) = waypoints.warps.get(name) else {
+                    let names: Vec<&str> = waypoints.warps.keys().map(String::as_str).collect();
+                    send_message(
+                        &net,
+                        teleport.player_entity,
+                        format!(
+                            "There is no warp named {}, the warps are: {}",
+                            name,
+                            names.join(", ")
+                        ),
+                    );
+                    continue;
+                };
+                load_position(*warp)
+            }
+        };
+        commands
+            .entity(teleport.player_entity)
+            .insert(PendingTeleport(task));
+
+        // Riders would be pulled back to their vehicle
+        if is_riding {
+            dismount_events.write(Dismount {
+                player_entity: teleport.player_entity,
+            });
+        }
+    }
+}
+
+fn finish_teleports(
+    mut commands: Commands,
+    net: Res<Server>,
+    mut player_query: Query<(Entity, &mut Transform, &mut Dimension, &mut PendingTeleport)>,
+) {
+    for (player_entity, mut transform, mut dimension, mut pending) in player_query.iter_mut() {
+        let Some(position) = pending.0.try_take() else {
+            continue;
+        };
+        commands.entity(player_entity).remove::<PendingTeleport>();
+
+        transform.translation = position;
+        *dimension = Dimension::from_height(position.y.floor() as i32);
+        net.send_one(player_entity, messages::PlayerPosition { position });
+    }
+}
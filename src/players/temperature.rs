@@ -0,0 +1,202 @@
+use fmc::{
+    blocks::{BlockPosition, Blocks},
+    networking::Server,
+    players::Player,
+    prelude::*,
+    world::WorldMap,
+};
+
+use crate::{
+    chat::send_message,
+    settings::Settings,
+    skybox::Clock,
+    world::{Climate, dimensions::Dimension, has_sky_access},
+};
+
+use super::{Equipment, PlayerDamageEvent};
+
+/// Keeps track of how warm or cold it is where each player is standing.
+///
+/// The temperature comes from the climate of the biome, how deep underground the player is, the
+/// time of day and any fires or furnaces nearby. Players without armor take damage when it is
+/// freezing.
+pub struct TemperaturePlugin;
+impl Plugin for TemperaturePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, (add_temperature, update_temperature).chain());
+    }
+}
+
+/// How often the temperature is measured, in seconds. Freezing players take damage this often.
+const UPDATE_INTERVAL: f32 = 2.0;
+/// Temperature at the surface for climate values of 0, and how much it changes for each unit of
+/// climate. Deserts are around 35 degrees and the snowy biomes around -5.
+const CLIMATE_BASE: f32 = 15.0;
+const CLIMATE_SCALE: f32 = 35.0;
+/// How much warmer it is at noon and colder at midnight, only felt under the open sky.
+const DAY_SWING: f32 = 8.0;
+/// Deep underground the temperature is always the same.
+const CAVE_TEMPERATURE: f32 = 12.0;
+/// How far below sea level the surface temperature stops mattering.
+const CAVE_DEPTH: f32 = 32.0;
+const NETHER_TEMPERATURE: f32 = 45.0;
+/// Blocks that give off heat, and how much warmer they make it right next to them.
+const HEAT_SOURCES: [(&str, f32); 3] = [("fire", 15.0), ("furnace_on", 10.0), ("torch", 3.0)];
+/// Heat sources are felt within this many blocks.
+const HEAT_RANGE: i32 = 3;
+const FREEZING_DAMAGE: u32 = 1;
+
+#[derive(Clone, Copy, PartialEq, Eq, Default)]
+enum Comfort {
+    #[default]
+    Comfortable,
+    Freezing,
+    Overheating,
+}
+
+/// The temperature around the player, in degrees.
+#[derive(Component, Default)]
+pub struct Temperature {
+    temperature: f32,
+    comfort: Comfort,
+}
+
+impl Temperature {
+    pub fn get(&self) -> f32 {
+        self.temperature
+    }
+
+    pub fn is_freezing(&self) -> bool {
+        self.comfort == Comfort::Freezing
+    }
+
+    pub fn is_overheating(&self) -> bool {
+        self.comfort == Comfort::Overheating
+    }
+}
+
+fn add_temperature(mut commands: Commands, new_players: Query<Entity, Added<Player>>) {
+    for player_entity in new_players.iter() {
+        commands
+            .entity(player_entity)
+            .insert(Temperature::default());
+    }
+}
+
+fn environment_temperature(
+    world_map: &WorldMap,
+    climate: &Climate,
+    clock: &Clock,
+    dimension: Dimension,
+    position: BlockPosition,
+) -> f32 {
+    let mut temperature = if dimension == Dimension::Nether {
+        NETHER_TEMPERATURE
+    } else {
+        let mut temperature =
+            CLIMATE_BASE + climate.temperature(position.x, position.z) * CLIMATE_SCALE;
+
+        if has_sky_access(world_map, position) {
+            temperature += clock.sun_elevation() * DAY_SWING;
+        }
+
+        let depth = (-position.y as f32 / CAVE_DEPTH).clamp(0.0, 1.0);
+        temperature + (CAVE_TEMPERATURE - temperature) * depth
+    };
+
+    let blocks = Blocks::get();
+    let heat_sources: Vec<_> = HEAT_SOURCES
+        .iter()
+        .map(|(name, heat)| (blocks.get_id(name), *heat))
+        .collect();
+
+    for x in -HEAT_RANGE..=HEAT_RANGE {
+        for y in -HEAT_RANGE..=HEAT_RANGE {
+            for z in -HEAT_RANGE..=HEAT_RANGE {
+                let offset = IVec3::new(x, y, z);
+                let Some(block_id) = world_map.get_block(position + offset) else {
+                    continue;
+                };
+                let Some((_, heat)) = heat_sources.iter().find(|(id, _)| *id == block_id) else {
+                    continue;
+                };
+                let falloff = 1.0 - offset.as_vec3().length() / (HEAT_RANGE as f32 + 1.0);
+                temperature += heat * falloff.max(0.0);
+            }
+        }
+    }
+
+    temperature
+}
+
+fn update_temperature(
+    net: Res<Server>,
+    time: Res<Time>,
+    settings: Res<Settings>,
+    world_map: Res<WorldMap>,
+    climate: Res<Climate>,
+    clock: Res<Clock>,
+    mut players: Query<(
+        Entity,
+        &GlobalTransform,
+        &Dimension,
+        &Equipment,
+        &mut Temperature,
+    )>,
+    mut damage_events: MessageWriter<PlayerDamageEvent>,
+    mut timer: Local<Timer>,
+) {
+    timer.tick(time.delta());
+    if !timer.is_finished() {
+        return;
+    }
+    *timer = Timer::from_seconds(UPDATE_INTERVAL, TimerMode::Once);
+
+    for (player_entity, transform, dimension, equipment, mut temperature) in players.iter_mut() {
+        // Measured at the head, the feet are often inside the block below.
+        let position = BlockPosition::from(transform.translation()) + IVec3::Y;
+        temperature.temperature =
+            environment_temperature(&world_map, &climate, &clock, *dimension, position);
+
+        let comfort = if temperature.temperature <= settings.freezing_temperature {
+            Comfort::Freezing
+        } else if temperature.temperature >= settings.overheating_temperature {
+            Comfort::Overheating
+        } else {
+            Comfort::Comfortable
+        };
+
+        let has_armor = [
+            &equipment.helmet,
+            &equipment.chestplate,
+            &equipment.leggings,
+            &equipment.boots,
+        ]
+        .iter()
+        .any(|item_stack| !item_stack.is_empty());
+
+        if comfort == Comfort::Freezing && !has_armor {
+            damage_events.write(PlayerDamageEvent {
+                player_entity,
+                damage: FREEZING_DAMAGE,
+                knock_back: None,
+            });
+        }
+
+        // TODO: There is no hunger yet. When there is, overheating should make players hungry
+        // faster, for now they are only told about it.
+        if comfort == temperature.comfort {
+            continue;
+        }
+
+        let message = match comfort {
+            Comfort::Freezing if has_armor => "It is freezing, your armor keeps you warm",
+            Comfort::Freezing => "You are freezing! Find some warmth or put on armor",
+            Comfort::Overheating => "The heat is exhausting, find some shade",
+            Comfort::Comfortable => "The temperature is bearable again",
+        };
+        send_message(&net, player_entity, message.to_owned());
+
+        temperature.comfort = comfort;
+    }
+}
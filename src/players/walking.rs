@@ -0,0 +1,225 @@
+use fmc::{
+    bevy::math::DVec3,
+    blocks::{BlockId, BlockPosition, Blocks},
+    networking::{NetworkMessage, Server},
+    players::Player,
+    prelude::*,
+    protocol::messages,
+    random::Rng,
+    world::{ChunkSubscriptions, WorldMap, chunk::ChunkPosition},
+};
+
+use super::{GameMode, PlayerLandEvent, Sneaking};
+
+/// Tells blocks when players walk over them. Blocks that react to being walked on, like soil
+/// that is trampled, read the [WalkOverEvent]s.
+///
+/// Players that walk make footstep sounds from the "step" sounds of the block they walk on, and
+/// land with a thud after falling.
+pub struct WalkingPlugin;
+impl Plugin for WalkingPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_message::<WalkOverEvent>()
+            .add_systems(Update, (walk_over_blocks, play_footsteps).chain());
+    }
+}
+
+/// How far a player walks between each footstep, running makes them come faster.
+const STRIDE: f64 = 1.7;
+/// Moves longer than this between two position updates are teleports, not steps.
+const MAX_STEP_DISTANCE: f64 = 4.0;
+/// Players that fall at least this many blocks land with a thud.
+const LANDING_DISTANCE: u32 = 2;
+
+/// Sent when a player steps onto a block, or lands on it after falling.
+#[derive(Message)]
+pub struct WalkOverEvent {
+    pub player_entity: Entity,
+    pub position: BlockPosition,
+    pub block_id: BlockId,
+    /// How many blocks the player fell before landing on the block, 0 if they walked onto it.
+    pub fall_distance: u32,
+}
+
+/// The block the player last stood on
+#[derive(Component, Default)]
+pub struct Footing(Option<BlockPosition>);
+
+/// How far the player has walked since their last footstep
+#[derive(Component, Default)]
+pub struct Footsteps {
+    last_position: Option<DVec3>,
+    distance: f64,
+}
+
+// The player's feet are right at the top edge of the block they stand on.
+fn block_below(position: DVec3) -> BlockPosition {
+    BlockPosition::from(position - DVec3::new(0.0, 0.1, 0.0))
+}
+
+fn walk_over_blocks(
+    world_map: Res<WorldMap>,
+    mut player_query: Query<(&mut Footing, &GameMode), With<Player>>,
+    mut position_events: MessageReader<NetworkMessage<messages::PlayerPosition>>,
+    mut land_events: MessageReader<PlayerLandEvent>,
+    mut walk_over_events: MessageWriter<WalkOverEvent>,
+) {
+    let blocks = Blocks::get();
+
+    for position_update in position_events.read() {
+        let Ok((mut footing, game_mode)) = player_query.get_mut(position_update.player_entity)
+        else {
+            continue;
+        };
+
+        if *game_mode == GameMode::Spectator {
+            continue;
+        }
+
+        let position = block_below(position_update.position);
+        if footing.0 == Some(position) {
+            continue;
+        }
+
+        // Only solid blocks can be stood on, anything else is stepped through.
+        let Some(block_id) = world_map
+            .get_block(position)
+            .filter(|block_id| blocks.get_config(block_id).is_solid())
+        else {
+            footing.0 = None;
+            continue;
+        };
+
+        footing.0 = Some(position);
+
+        walk_over_events.write(WalkOverEvent {
+            player_entity: position_update.player_entity,
+            position,
+            block_id,
+            fall_distance: 0,
+        });
+    }
+
+    for land_event in land_events.read() {
+        let position = block_below(land_event.position);
+        let Some(block_id) = world_map.get_block(position) else {
+            continue;
+        };
+
+        walk_over_events.write(WalkOverEvent {
+            player_entity: land_event.player_entity,
+            position,
+            block_id,
+            fall_distance: land_event.distance,
+        });
+    }
+}
+
+fn play_step_sound(
+    net: &Server,
+    chunk_subscriptions: &ChunkSubscriptions,
+    rng: &mut Rng,
+    block_id: BlockId,
+    position: BlockPosition,
+    volume: f32,
+    speed: f32,
+) {
+    let block_config = Blocks::get().get_config(&block_id);
+    let Some(subscribers) = chunk_subscriptions.get_subscribers(&ChunkPosition::from(position))
+    else {
+        return;
+    };
+    let Some(step_sound) = block_config.sound.step(rng) else {
+        return;
+    };
+
+    net.send_many(
+        subscribers,
+        messages::Sound {
+            position: Some(position.as_dvec3() + DVec3::new(0.5, 1.0, 0.5)),
+            volume,
+            speed,
+            sound: step_sound.to_owned(),
+        },
+    );
+}
+
+fn play_footsteps(
+    net: Res<Server>,
+    world_map: Res<WorldMap>,
+    chunk_subscriptions: Res<ChunkSubscriptions>,
+    mut player_query: Query<(&Footing, &mut Footsteps, &Sneaking, &GameMode), With<Player>>,
+    mut position_events: MessageReader<NetworkMessage<messages::PlayerPosition>>,
+    mut walk_over_events: MessageReader<WalkOverEvent>,
+    mut rng: Local<Rng>,
+) {
+    for position_update in position_events.read() {
+        let Ok((footing, mut footsteps, sneaking, game_mode)) =
+            player_query.get_mut(position_update.player_entity)
+        else {
+            continue;
+        };
+
+        let Some(last_position) = footsteps.last_position.replace(position_update.position) else {
+            continue;
+        };
+
+        // Only steps taken on the ground count, jumping over a gap doesn't make a sound.
+        let Some(position) = footing.0 else {
+            continue;
+        };
+
+        let distance = (position_update.position - last_position)
+            .with_y(0.0)
+            .length();
+        if distance > MAX_STEP_DISTANCE || *game_mode == GameMode::Spectator {
+            continue;
+        }
+
+        footsteps.distance += distance;
+        if footsteps.distance < STRIDE {
+            continue;
+        }
+        footsteps.distance -= STRIDE;
+
+        // Sneaking is quiet
+        if **sneaking {
+            continue;
+        }
+
+        let Some(block_id) = world_map.get_block(position) else {
+            continue;
+        };
+        play_step_sound(
+            &net,
+            &chunk_subscriptions,
+            &mut rng,
+            block_id,
+            position,
+            0.15,
+            1.0,
+        );
+    }
+
+    for walk_over in walk_over_events.read() {
+        if walk_over.fall_distance < LANDING_DISTANCE {
+            continue;
+        }
+
+        if let Ok((_, mut footsteps, _, _)) = player_query.get_mut(walk_over.player_entity) {
+            footsteps.distance = 0.0;
+        }
+
+        // Longer falls land harder
+        let volume = (0.3 + walk_over.fall_distance as f32 * 0.05).min(1.0);
+        play_step_sound(
+            &net,
+            &chunk_subscriptions,
+            &mut rng,
+            walk_over.block_id,
+            walk_over.position,
+            volume,
+            0.6,
+        );
+    }
+}
@@ -0,0 +1,134 @@
+use std::{path::Path, time::SystemTime};
+
+use fmc::{blocks::Blocks, items::Items, networking::Server, prelude::*};
+
+use crate::{
+    assets::AssetErrors,
+    chat::send_message,
+    items::crafting::Recipes,
+    world::{Biomes, SharedBiomes},
+};
+
+/// Reloads the recipes, biomes and blueprints while the server is running, either when asked to
+/// with `/reload` or when the files change.
+///
+/// Everything is read and validated before anything is replaced, so a mistake in one of the
+/// files leaves the server running with what it had. The error is reported to the player that
+/// asked for the reload, or logged if it was the files that changed.
+///
+/// Block configs are loaded by fmc when the server starts and can't be reloaded.
+pub struct ReloadPlugin;
+impl Plugin for ReloadPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_message::<ReloadAssets>()
+            .add_systems(Update, (watch_files, reload_assets).chain());
+    }
+}
+
+/// The directories that are watched for changes
+const WATCHED_PATHS: [&str; 3] = [
+    "./assets/client/items/recipes",
+    "./assets/server/biomes",
+    "./assets/server/blueprints",
+];
+/// Seconds between each time the watched directories are checked
+const WATCH_INTERVAL: f32 = 2.0;
+
+/// Read the recipes, biomes and blueprints again
+#[derive(Message)]
+pub struct ReloadAssets {
+    /// The player that asked for the reload, they are told how it went.
+    pub requested_by: Option<Entity>,
+}
+
+// The time the most recently changed file in the directory was changed
+fn last_modified(path: &Path) -> Option<SystemTime> {
+    let metadata = std::fs::metadata(path).ok()?;
+    if !metadata.is_dir() {
+        return metadata.modified().ok();
+    }
+
+    std::fs::read_dir(path)
+        .ok()?
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| last_modified(&entry.path()))
+        .chain(metadata.modified().ok())
+        .max()
+}
+
+fn watch_files(
+    time: Res<Time>,
+    mut reload_events: MessageWriter<ReloadAssets>,
+    mut timer: Local<Timer>,
+    mut modified: Local<Option<Vec<Option<SystemTime>>>>,
+) {
+    if timer.duration().is_zero() {
+        *timer = Timer::from_seconds(WATCH_INTERVAL, TimerMode::Repeating);
+    }
+
+    timer.tick(time.delta());
+    if !timer.just_finished() {
+        return;
+    }
+
+    let current: Vec<Option<SystemTime>> = WATCHED_PATHS
+        .iter()
+        .map(|path| last_modified(Path::new(path)))
+        .collect();
+
+    // The first check only remembers how the files were when the server started.
+    if modified
+        .as_ref()
+        .is_some_and(|modified| *modified != current)
+    {
+        reload_events.write(ReloadAssets { requested_by: None });
+    }
+    *modified = Some(current);
+}
+
+fn reload_assets(
+    mut commands: Commands,
+    net: Res<Server>,
+    items: Res<Items>,
+    blocks: Res<Blocks>,
+    shared_biomes: Res<SharedBiomes>,
+    mut reload_events: MessageReader<ReloadAssets>,
+) {
+    let requests: Vec<Option<Entity>> = reload_events
+        .read()
+        .map(|reload| reload.requested_by)
+        .collect();
+    if requests.is_empty() {
+        return;
+    }
+
+    let text = match (Recipes::load(&items), Biomes::load(&blocks)) {
+        (Ok(recipes), Ok(biomes)) => {
+            commands.insert_resource(recipes);
+            shared_biomes.replace(biomes);
+            info!("Reloaded the recipes, biomes and blueprints");
+            "Reloaded the recipes, biomes and blueprints".to_owned()
+        }
+        (recipes, biomes) => {
+            let mut errors = AssetErrors::default();
+            if let Err(e) = recipes {
+                errors.extend(e);
+            }
+            if let Err(e) = biomes {
+                errors.extend(e);
+            }
+            error!(
+                "Failed to reload the assets, keeping the old ones.\n{}",
+                errors
+            );
+            format!(
+                "Failed to reload the assets, keeping the old ones.\n{}",
+                errors
+            )
+        }
+    };
+
+    for player_entity in requests.into_iter().flatten() {
+        send_message(&net, player_entity, text.clone());
+    }
+}
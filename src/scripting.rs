@@ -0,0 +1,581 @@
+use std::collections::HashMap;
+
+use fmc::{
+    blocks::{BlockData, BlockPosition, Blocks},
+    items::{ItemId, Items},
+    networking::{NetworkMessage, Server},
+    players::Player,
+    prelude::*,
+    protocol::messages,
+    world::{BlockUpdate, WorldMap},
+};
+use wasmi::{Caller, Config, Engine, Extern, Instance, Linker, Module, Store};
+
+use crate::{
+    chat::send_message,
+    items::{ItemRegistry, ItemUseSystems, ItemUses},
+    players::HandInteractions,
+};
+
+const SCRIPTS_PATH: &str = "./server_plugins";
+/// How much work a plugin can do in one call before it is stopped, so a plugin that loops forever
+/// can't hang the server. Roughly one unit per instruction.
+const FUEL_PER_CALL: u64 = 10_000_000;
+
+/// Loads server plugins compiled to WASM from `./server_plugins`, so gameplay can be changed
+/// without recompiling the server.
+///
+/// When a plugin is loaded its `init` function is called, where it registers what it wants to
+/// handle by calling the host functions in the `fmc` import module:
+///
+/// - `register_chat_command(name_ptr, name_len)`: `on_chat_command(player, args_ptr, args_len)` is
+///   called when a player writes `/name args` in the chat.
+/// - `register_item_use(item_ptr, item_len)`: `on_item_use(player, item_ptr, item_len)` is called
+///   when a player uses the item. Items that the server already handles can't be registered.
+/// - `register_block_interaction(block_ptr, block_len)`: `on_block_interaction(player, x, y, z,
+///   block_ptr, block_len)` is called when a player right clicks the block. This replaces any
+///   behaviour the block had.
+///
+/// The callbacks can in turn call `send_message(player, text_ptr, text_len)`, `set_block(x, y, z,
+/// block_ptr, block_len)` and `log(text_ptr, text_len)`. Players are passed as the bits of their
+/// entity, an `i64`, and all strings are utf-8. To pass strings to the callbacks the plugin must
+/// export its `memory` and an `alloc(len) -> ptr` function the server can write into.
+///
+/// Each call into a plugin is given [FUEL_PER_CALL] fuel, a call that runs out fails like any
+/// other error in the plugin.
+pub struct ScriptingPlugin;
+impl Plugin for ScriptingPlugin {
+    fn build(&self, app: &mut App) {
+        // Runs after startup so the items the server handles itself have been registered.
+        app.add_systems(PostStartup, load_scripts).add_systems(
+            Update,
+            (
+                (
+                    run_chat_commands,
+                    run_item_uses.after(ItemUseSystems),
+                    run_block_interactions,
+                ),
+                apply_actions,
+            )
+                .chain(),
+        );
+    }
+}
+
+/// What a plugin asked to handle during `init`
+enum Registration {
+    ChatCommand(String),
+    ItemUse(String),
+    BlockInteraction(String),
+}
+
+/// Changes to the world a plugin made during a callback, applied after it returns.
+enum Action {
+    SendMessage {
+        player_entity: Entity,
+        text: String,
+    },
+    SetBlock {
+        position: BlockPosition,
+        block: String,
+    },
+}
+
+#[derive(Default)]
+struct HostState {
+    name: String,
+    registrations: Vec<Registration>,
+    actions: Vec<Action>,
+}
+
+struct Script {
+    store: Store<HostState>,
+    instance: Instance,
+    chat_commands: Vec<String>,
+}
+
+impl Script {
+    fn load(
+        engine: &Engine,
+        linker: &Linker<HostState>,
+        name: &str,
+        wasm: &[u8],
+    ) -> Result<Self, String> {
+        let module = Module::new(engine, wasm).map_err(|e| e.to_string())?;
+        let mut store = Store::new(
+            engine,
+            HostState {
+                name: name.to_owned(),
+                ..default()
+            },
+        );
+        store.set_fuel(FUEL_PER_CALL).map_err(|e| e.to_string())?;
+        let instance = linker
+            .instantiate(&mut store, &module)
+            .and_then(|instance| instance.start(&mut store))
+            .map_err(|e| e.to_string())?;
+
+        let init = instance
+            .get_typed_func::<(), ()>(&store, "init")
+            .map_err(|e| e.to_string())?;
+        store.set_fuel(FUEL_PER_CALL).map_err(|e| e.to_string())?;
+        init.call(&mut store, ()).map_err(|e| e.to_string())?;
+
+        Ok(Self {
+            store,
+            instance,
+            chat_commands: Vec::new(),
+        })
+    }
+
+    // Called before each callback, the fuel left over from the last call isn't carried over.
+    fn refuel(&mut self) -> Result<(), String> {
+        self.store
+            .set_fuel(FUEL_PER_CALL)
+            .map_err(|e| e.to_string())
+    }
+
+    /// Copy the string into the plugin's memory, returning where it was put.
+    fn write_string(&mut self, text: &str) -> Result<(i32, i32), String> {
+        let alloc = self
+            .instance
+            .get_typed_func::<i32, i32>(&self.store, "alloc")
+            .map_err(|e| e.to_string())?;
+        let memory = self
+            .instance
+            .get_memory(&self.store, "memory")
+            .ok_or("The plugin doesn't export its memory")?;
+
+        let ptr = alloc
+            .call(&mut self.store, text.len() as i32)
+            .map_err(|e| e.to_string())?;
+        memory
+            .write(&mut self.store, ptr as usize, text.as_bytes())
+            .map_err(|e| e.to_string())?;
+        Ok((ptr, text.len() as i32))
+    }
+
+    fn on_chat_command(&mut self, player_entity: Entity, args: &str) -> Result<(), String> {
+        self.refuel()?;
+        let (ptr, len) = self.write_string(args)?;
+        self.instance
+            .get_typed_func::<(i64, i32, i32), ()>(&self.store, "on_chat_command")
+            .and_then(|func| func.call(&mut self.store, (player_entity.to_bits() as i64, ptr, len)))
+            .map_err(|e| e.to_string())
+    }
+
+    fn on_item_use(&mut self, player_entity: Entity, item: &str) -> Result<(), String> {
+        self.refuel()?;
+        let (ptr, len) = self.write_string(item)?;
+        self.instance
+            .get_typed_func::<(i64, i32, i32), ()>(&self.store, "on_item_use")
+            .and_then(|func| func.call(&mut self.store, (player_entity.to_bits() as i64, ptr, len)))
+            .map_err(|e| e.to_string())
+    }
+
+    fn on_block_interaction(
+        &mut self,
+        player_entity: Entity,
+        position: BlockPosition,
+        block: &str,
+    ) -> Result<(), String> {
+        self.refuel()?;
+        let (ptr, len) = self.write_string(block)?;
+        self.instance
+            .get_typed_func::<(i64, i32, i32, i32, i32, i32), ()>(
+                &self.store,
+                "on_block_interaction",
+            )
+            .and_then(|func| {
+                func.call(
+                    &mut self.store,
+                    (
+                        player_entity.to_bits() as i64,
+                        position.x,
+                        position.y,
+                        position.z,
+                        ptr,
+                        len,
+                    ),
+                )
+            })
+            .map_err(|e| e.to_string())
+    }
+
+    fn name(&self) -> &str {
+        &self.store.data().name
+    }
+}
+
+/// All the loaded plugins
+#[derive(Resource)]
+struct Scripts {
+    scripts: Vec<Script>,
+    /// Which scripts handle the use of each item
+    item_uses: HashMap<ItemId, Vec<usize>>,
+    /// Which scripts handle interaction with each block, by block name
+    block_interactions: HashMap<String, Vec<usize>>,
+}
+
+/// Handles the item uses of all the items registered by plugins
+#[derive(Component)]
+struct ScriptedItem(ItemId);
+
+#[derive(Component)]
+struct ScriptedBlock;
+
+fn read_string(caller: &Caller<'_, HostState>, ptr: i32, len: i32) -> Option<String> {
+    let memory = caller.get_export("memory").and_then(Extern::into_memory)?;
+    let ptr = usize::try_from(ptr).ok()?;
+    let len = usize::try_from(len).ok()?;
+    // Checked before allocating, a plugin could otherwise make the server allocate any amount.
+    if ptr.checked_add(len)? > memory.data_size(caller) {
+        return None;
+    }
+    let mut buffer = vec![0; len];
+    memory.read(caller, ptr, &mut buffer).ok()?;
+    String::from_utf8(buffer).ok()
+}
+
+fn host_functions(engine: &Engine) -> Linker<HostState> {
+    let mut linker = Linker::new(engine);
+    linker
+        .func_wrap(
+            "fmc",
+            "log",
+            |caller: Caller<'_, HostState>, ptr: i32, len: i32| {
+                if let Some(text) = read_string(&caller, ptr, len) {
+                    info!("[{}] {}", caller.data().name, text);
+                }
+            },
+        )
+        .unwrap()
+        .func_wrap(
+            "fmc",
+            "register_chat_command",
+            |mut caller: Caller<'_, HostState>, ptr: i32, len: i32| {
+                if let Some(name) = read_string(&caller, ptr, len) {
+                    caller
+                        .data_mut()
+                        .registrations
+                        .push(Registration::ChatCommand(name));
+                }
+            },
+        )
+        .unwrap()
+        .func_wrap(
+            "fmc",
+            "register_item_use",
+            |mut caller: Caller<'_, HostState>, ptr: i32, len: i32| {
+                if let Some(item) = read_string(&caller, ptr, len) {
+                    caller
+                        .data_mut()
+                        .registrations
+                        .push(Registration::ItemUse(item));
+                }
+            },
+        )
+        .unwrap()
+        .func_wrap(
+            "fmc",
+            "register_block_interaction",
+            |mut caller: Caller<'_, HostState>, ptr: i32, len: i32| {
+                if let Some(block) = read_string(&caller, ptr, len) {
+                    caller
+                        .data_mut()
+                        .registrations
+                        .push(Registration::BlockInteraction(block));
+                }
+            },
+        )
+        .unwrap()
+        .func_wrap(
+            "fmc",
+            "send_message",
+            |mut caller: Caller<'_, HostState>, player: i64, ptr: i32, len: i32| {
+                // Plugins can pass any number, it isn't necessarily an entity.
+                let Some(player_entity) = Entity::try_from_bits(player as u64) else {
+                    return;
+                };
+                if let Some(text) = read_string(&caller, ptr, len) {
+                    caller.data_mut().actions.push(Action::SendMessage {
+                        player_entity,
+                        text,
+                    });
+                }
+            },
+        )
+        .unwrap()
+        .func_wrap(
+            "fmc",
+            "set_block",
+            |mut caller: Caller<'_, HostState>, x: i32, y: i32, z: i32, ptr: i32, len: i32| {
+                if let Some(block) = read_string(&caller, ptr, len) {
+                    caller.data_mut().actions.push(Action::SetBlock {
+                        position: BlockPosition::new(x, y, z),
+                        block,
+                    });
+                }
+            },
+        )
+        .unwrap();
+    linker
+}
+
+fn load_scripts(
+    mut commands: Commands,
+    items: Res<Items>,
+    mut blocks: ResMut<Blocks>,
+    mut item_registry: ResMut<ItemRegistry>,
+) {
+    let mut scripts = Scripts {
+        scripts: Vec::new(),
+        item_uses: HashMap::new(),
+        block_interactions: HashMap::new(),
+    };
+
+    // Plugins are optional, the directory doesn't have to exist.
+    let Ok(directory) = std::fs::read_dir(SCRIPTS_PATH) else {
+        commands.insert_resource(scripts);
+        return;
+    };
+
+    let mut config = Config::default();
+    config.consume_fuel(true);
+    let engine = Engine::new(&config);
+    let linker = host_functions(&engine);
+
+    for dir_entry in directory {
+        let path = match dir_entry {
+            Ok(d) => d.path(),
+            Err(e) => panic!(
+                "Failed to read server plugins at path: {}\nError: {}",
+                SCRIPTS_PATH, e
+            ),
+        };
+
+        if path.extension().is_none_or(|extension| extension != "wasm") {
+            continue;
+        }
+
+        let name = path.file_stem().unwrap().to_string_lossy().into_owned();
+        let wasm = match std::fs::read(&path) {
+            Ok(w) => w,
+            Err(e) => panic!(
+                "Failed to open server plugin at path: {}\nError: {}",
+                path.display(),
+                e
+            ),
+        };
+
+        let mut script = match Script::load(&engine, &linker, &name, &wasm) {
+            Ok(s) => s,
+            Err(e) => {
+                error!("Failed to load server plugin '{}'\nError: {}", name, e);
+                continue;
+            }
+        };
+
+        let index = scripts.scripts.len();
+        for registration in std::mem::take(&mut script.store.data_mut().registrations) {
+            match registration {
+                Registration::ChatCommand(command) => script.chat_commands.push(command),
+                Registration::ItemUse(item) => {
+                    let Some(item_id) = items.get_id(&item) else {
+                        error!(
+                            "Server plugin '{}' registered unknown item '{}'",
+                            name, item
+                        );
+                        continue;
+                    };
+
+                    if !scripts.item_uses.contains_key(&item_id) {
+                        if item_registry.contains_key(&item_id) {
+                            error!(
+                                "Server plugin '{}' can't handle '{}', it is already used by the server",
+                                name, item
+                            );
+                            continue;
+                        }
+                        item_registry.insert(
+                            item_id,
+                            commands
+                                .spawn((ItemUses::default(), ScriptedItem(item_id)))
+                                .id(),
+                        );
+                    }
+                    scripts.item_uses.entry(item_id).or_default().push(index);
+                }
+                Registration::BlockInteraction(block) => {
+                    if !blocks.contains_block(&block) {
+                        error!(
+                            "Server plugin '{}' registered unknown block '{}'",
+                            name, block
+                        );
+                        continue;
+                    }
+
+                    let block_id = blocks.get_id(&block);
+                    blocks
+                        .get_config_mut(&block_id)
+                        .set_spawn_function(scripted_block);
+                    scripts
+                        .block_interactions
+                        .entry(block)
+                        .or_default()
+                        .push(index);
+                }
+            }
+        }
+
+        info!("Loaded server plugin '{}'", name);
+        scripts.scripts.push(script);
+    }
+
+    commands.insert_resource(scripts);
+}
+
+fn scripted_block(commands: &mut EntityCommands, _block_data: Option<&BlockData>) {
+    commands.insert((ScriptedBlock, HandInteractions::default()));
+}
+
+fn run_chat_commands(
+    mut scripts: ResMut<Scripts>,
+    mut chat_messages: MessageReader<NetworkMessage<messages::InterfaceTextInput>>,
+) {
+    for chat_message in chat_messages.read() {
+        if chat_message.interface_path != "chat/input" {
+            continue;
+        }
+
+        let Some(command) = chat_message.text.strip_prefix("/") else {
+            continue;
+        };
+        let (name, args) = command.split_once(" ").unwrap_or((command, ""));
+
+        for script in scripts.scripts.iter_mut() {
+            if !script.chat_commands.iter().any(|command| command == name) {
+                continue;
+            }
+
+            if let Err(e) = script.on_chat_command(chat_message.player_entity, args.trim()) {
+                error!(
+                    "Server plugin '{}' failed to run /{}\nError: {}",
+                    script.name(),
+                    name,
+                    e
+                );
+            }
+        }
+    }
+}
+
+fn run_item_uses(
+    items: Res<Items>,
+    mut scripts: ResMut<Scripts>,
+    mut item_uses: Query<(&mut ItemUses, &ScriptedItem), Changed<ItemUses>>,
+) {
+    let scripts = scripts.as_mut();
+
+    for (mut uses, scripted_item) in item_uses.iter_mut() {
+        let item_name = &items.get_config(&scripted_item.0).name;
+        let Some(handlers) = scripts.item_uses.get(&scripted_item.0) else {
+            continue;
+        };
+
+        for player_entity in uses.read() {
+            for index in handlers {
+                let script = &mut scripts.scripts[*index];
+                if let Err(e) = script.on_item_use(player_entity, item_name) {
+                    error!(
+                        "Server plugin '{}' failed to use '{}'\nError: {}",
+                        script.name(),
+                        item_name,
+                        e
+                    );
+                }
+            }
+        }
+    }
+}
+
+fn run_block_interactions(
+    world_map: Res<WorldMap>,
+    mut scripts: ResMut<Scripts>,
+    mut block_hits: Query<
+        (&BlockPosition, &mut HandInteractions),
+        (With<ScriptedBlock>, Changed<HandInteractions>),
+    >,
+) {
+    let scripts = scripts.as_mut();
+    let blocks = Blocks::get();
+
+    for (block_position, mut interactions) in block_hits.iter_mut() {
+        let Some(block_id) = world_map.get_block(*block_position) else {
+            continue;
+        };
+        let block_name = &blocks.get_config(&block_id).name;
+        let Some(handlers) = scripts.block_interactions.get(block_name) else {
+            continue;
+        };
+
+        for player_entity in interactions.read() {
+            for index in handlers {
+                let script = &mut scripts.scripts[*index];
+                if let Err(e) =
+                    script.on_block_interaction(player_entity, *block_position, block_name)
+                {
+                    error!(
+                        "Server plugin '{}' failed to interact with '{}'\nError: {}",
+                        script.name(),
+                        block_name,
+                        e
+                    );
+                }
+            }
+        }
+    }
+}
+
+fn apply_actions(
+    net: Res<Server>,
+    mut scripts: ResMut<Scripts>,
+    players: Query<(), With<Player>>,
+    mut block_updates: MessageWriter<BlockUpdate>,
+) {
+    let blocks = Blocks::get();
+
+    for script in scripts.scripts.iter_mut() {
+        let name = script.name().to_owned();
+        for action in std::mem::take(&mut script.store.data_mut().actions) {
+            match action {
+                Action::SendMessage {
+                    player_entity,
+                    text,
+                } => {
+                    if !players.contains(player_entity) {
+                        continue;
+                    }
+
+                    send_message(&net, player_entity, text);
+                }
+                Action::SetBlock { position, block } => {
+                    if !blocks.contains_block(&block) {
+                        error!(
+                            "Server plugin '{}' tried to place unknown block '{}'",
+                            name, block
+                        );
+                        continue;
+                    }
+
+                    block_updates.write(BlockUpdate::Replace {
+                        position,
+                        block_id: blocks.get_id(&block),
+                        block_state: None,
+                        block_data: None,
+                    });
+                }
+            }
+        }
+    }
+}
@@ -6,7 +6,15 @@ use std::{
     io::{BufRead, BufReader},
 };
 
-use crate::players::GameMode;
+use crate::{database_io::DatabaseIo, players::GameMode, world::game_rules::Difficulty};
+
+/// Where the worlds are stored, each in its own database.
+pub const WORLD_DIRECTORY: &str = "./worlds";
+
+/// Path to the database of the world with this name
+pub fn world_path(name: &str) -> String {
+    format!("{}/{}.sqlite", WORLD_DIRECTORY, name)
+}
 
 pub struct SettingsPlugin;
 impl Plugin for SettingsPlugin {
@@ -16,11 +24,7 @@ impl Plugin for SettingsPlugin {
         } else {
             let mut settings = Settings::load_from_file();
 
-            let database_path = if let Some(name) = &settings.world_name {
-                name.clone()
-            } else {
-                Database::DEFAULT_PATH.to_owned()
-            };
+            let database_path = settings.database_path();
 
             // Overwrite settings from the file that can't be changed after the world
             // has been created.
@@ -62,6 +66,40 @@ pub struct Settings {
     pub render_distance: u32,
     /// The default game mode of new players
     pub game_mode: GameMode,
+    /// Only let players on the whitelist join
+    pub whitelist: bool,
+    /// Players that are always operators with full permissions
+    pub operators: Vec<String>,
+    /// If new worlds let mobs and the weather change the world, e.g. lightning starting fires.
+    /// Use the mobGriefing game rule to change it for an existing world.
+    pub mob_griefing: bool,
+    /// The difficulty of new worlds. Use /difficulty to change it for an existing world.
+    pub difficulty: Difficulty,
+    /// Players without armor freeze at or below this temperature
+    pub freezing_temperature: f32,
+    /// Players overheat at or above this temperature
+    pub overheating_temperature: f32,
+    /// Show a health bar above mobs for a little while after they are hurt
+    pub mob_health_bars: bool,
+    /// Seconds between each time the world is saved, 0 to only save on shutdown and when asked to
+    pub autosave_interval: u32,
+    /// How high a ledge players walk up without jumping
+    pub step_height: f32,
+    /// Players jump automatically when they walk into a ledge one block high
+    pub auto_jump: bool,
+    /// Let blocks that can be walked through, like torches and flowers, be placed where a player
+    /// or mob is standing.
+    pub place_non_solid_in_entities: bool,
+    /// Seconds a player has to wait between chat messages, operators don't have to wait
+    pub chat_cooldown: f32,
+    /// Show warnings and errors from the server log in the chat of operators that are online
+    pub log_to_operators: bool,
+    /// Address to serve metrics at in the Prometheus format, e.g. 127.0.0.1:9100. Left empty the
+    /// metrics are only available with /metrics.
+    pub metrics_address: String,
+    /// How far away from their camera players can reach blocks and entities, interactions
+    /// further away are ignored.
+    pub reach: f64,
 }
 
 impl Default for Settings {
@@ -72,6 +110,21 @@ impl Default for Settings {
             pvp: false,
             render_distance: 16,
             game_mode: GameMode::Survival,
+            whitelist: false,
+            operators: Vec::new(),
+            mob_griefing: true,
+            difficulty: Difficulty::Normal,
+            freezing_temperature: -5.0,
+            overheating_temperature: 40.0,
+            mob_health_bars: true,
+            autosave_interval: 300,
+            step_height: 0.51,
+            auto_jump: false,
+            place_non_solid_in_entities: true,
+            chat_cooldown: 1.0,
+            log_to_operators: false,
+            metrics_address: String::new(),
+            reach: 6.0,
         }
     }
 }
@@ -136,7 +189,7 @@ impl Settings {
                     if value.is_empty() {
                         panic!("The world name cannot be empty");
                     }
-                    settings.world_name = Some("./".to_owned() + value + ".sqlite");
+                    settings.world_name = Some(value.to_owned());
                 }
                 "seed" => {
                     settings.seed = value.to_owned();
@@ -167,6 +220,121 @@ impl Settings {
                         }
                     };
                 }
+                "whitelist" => {
+                    settings.whitelist = value.parse::<bool>().unwrap_or_else(|_| {
+                        panic!(
+                            "Server property 'whitelist' must be one of 'true/false', cannot be: '{value}'",
+                        )
+                    });
+                }
+                "operators" => {
+                    settings.operators = value
+                        .split(",")
+                        .map(|username| username.trim())
+                        .filter(|username| !username.is_empty())
+                        .map(|username| username.to_owned())
+                        .collect();
+                }
+                "mob-griefing" => {
+                    settings.mob_griefing = value.parse::<bool>().unwrap_or_else(|_| {
+                        panic!(
+                            "Server property 'mob-griefing' must be one of 'true/false', cannot be: '{value}'",
+                        )
+                    });
+                }
+                "difficulty" => {
+                    settings.difficulty = Difficulty::from_name(value).unwrap_or_else(|| {
+                        panic!(
+                            "Server property 'difficulty' must be one of 'peaceful', 'easy', 'normal' or 'hard', cannot be: '{value}'",
+                        )
+                    });
+                }
+                "freezing-temperature" => {
+                    settings.freezing_temperature = value.parse::<f32>().unwrap_or_else(|_| {
+                        panic!(
+                            "Server property 'freezing-temperature' must be a number, cannot be: '{value}'",
+                        )
+                    });
+                }
+                "overheating-temperature" => {
+                    settings.overheating_temperature = value.parse::<f32>().unwrap_or_else(|_| {
+                        panic!(
+                            "Server property 'overheating-temperature' must be a number, cannot be: '{value}'",
+                        )
+                    });
+                }
+                "mob-health-bars" => {
+                    settings.mob_health_bars = value.parse::<bool>().unwrap_or_else(|_| {
+                        panic!(
+                            "Server property 'mob-health-bars' must be one of 'true/false', cannot be: '{value}'",
+                        )
+                    });
+                }
+                "autosave-interval" => {
+                    settings.autosave_interval = value.parse::<u32>().unwrap_or_else(|_| {
+                        panic!(
+                            "Server property 'autosave-interval' must be a positive number, cannot be: '{value}'",
+                        )
+                    });
+                }
+                "step-height" => {
+                    settings.step_height = value
+                        .parse::<f32>()
+                        .ok()
+                        .filter(|height| *height >= 0.0)
+                        .unwrap_or_else(|| {
+                            panic!(
+                                "Server property 'step-height' must be a positive number, cannot be: '{value}'",
+                            )
+                        });
+                }
+                "auto-jump" => {
+                    settings.auto_jump = value.parse::<bool>().unwrap_or_else(|_| {
+                        panic!(
+                            "Server property 'auto-jump' must be one of 'true/false', cannot be: '{value}'",
+                        )
+                    });
+                }
+                "place-non-solid-in-entities" => {
+                    settings.place_non_solid_in_entities =
+                        value.parse::<bool>().unwrap_or_else(|_| {
+                            panic!(
+                                "Server property 'place-non-solid-in-entities' must be one of 'true/false', cannot be: '{value}'",
+                            )
+                        });
+                }
+                "chat-cooldown" => {
+                    settings.chat_cooldown = value
+                        .parse::<f32>()
+                        .ok()
+                        .filter(|cooldown| *cooldown >= 0.0)
+                        .unwrap_or_else(|| {
+                            panic!(
+                                "Server property 'chat-cooldown' must be a positive number, cannot be: '{value}'",
+                            )
+                        });
+                }
+                "log-to-operators" => {
+                    settings.log_to_operators = value.parse::<bool>().unwrap_or_else(|_| {
+                        panic!(
+                            "Server property 'log-to-operators' must be one of 'true/false', cannot be: '{value}'",
+                        )
+                    });
+                }
+                "metrics-address" => {
+                    settings.metrics_address = value.to_owned();
+                }
+                "reach" => {
+                    settings.reach = value
+                        .parse::<f64>()
+                        .ok()
+                        .filter(|reach| *reach > 0.0 && reach.is_finite())
+                        .unwrap_or_else(|| {
+                            panic!(
+                                "Server property 'reach' must be a positive number, cannot be: '{value}'",
+                            )
+                        });
+                }
                 _ => {
                     error!("Invalid setting '{name}' in settings file at line {line_num}",);
                 }
@@ -184,20 +352,68 @@ impl Settings {
         }
         contents = contents + "seed = " + &self.seed + "\n";
         contents = contents + "pvp = " + &self.pvp.to_string() + "\n";
-        contents = contents + "render-distance = " + &self.render_distance.to_string();
+        contents = contents + "render-distance = " + &self.render_distance.to_string() + "\n";
+        contents = contents + "whitelist = " + &self.whitelist.to_string() + "\n";
+        contents = contents + "operators = " + &self.operators.join(", ") + "\n";
+        contents = contents + "mob-griefing = " + &self.mob_griefing.to_string() + "\n";
+        contents = contents + "difficulty = " + self.difficulty.name() + "\n";
+        contents = contents + "freezing-temperature = " + &self.freezing_temperature.to_string() + "\n";
+        contents = contents + "overheating-temperature = " + &self.overheating_temperature.to_string() + "\n";
+        contents = contents + "mob-health-bars = " + &self.mob_health_bars.to_string() + "\n";
+        contents = contents + "autosave-interval = " + &self.autosave_interval.to_string() + "\n";
+        contents = contents + "step-height = " + &self.step_height.to_string() + "\n";
+        contents = contents + "auto-jump = " + &self.auto_jump.to_string() + "\n";
+        contents = contents + "place-non-solid-in-entities = " + &self.place_non_solid_in_entities.to_string() + "\n";
+        contents = contents + "chat-cooldown = " + &self.chat_cooldown.to_string() + "\n";
+        contents = contents + "log-to-operators = " + &self.log_to_operators.to_string() + "\n";
+        contents = contents + "metrics-address = " + &self.metrics_address + "\n";
+        contents = contents + "reach = " + &self.reach.to_string();
 
         std::fs::write("./server_settings.txt", contents).unwrap();
     }
 
-    fn save_to_database(&self, database: &Database) {
-        let connection = database.get_write_connection();
+    fn save_to_database(&self, database_io: &DatabaseIo) {
+        let data = serde_json::to_string(self).unwrap();
+        database_io.run(move |database| {
+            let connection = database.get_write_connection();
+
+            connection
+                .execute(
+                    "INSERT OR REPLACE INTO storage (name, data) VALUES (?,?)",
+                    rusqlite::params!["settings", data],
+                )
+                .unwrap();
+        });
+    }
 
-        connection
-            .execute(
-                "INSERT OR REPLACE INTO storage (name, data) VALUES (?,?)",
-                rusqlite::params!["settings", serde_json::to_string(self).unwrap()],
-            )
-            .unwrap();
+    /// The name of the world that was chosen in the settings file, None if it's the default
+    /// world.
+    pub fn world_name(&self) -> Option<&str> {
+        self.world_name.as_deref()
+    }
+
+    /// Load another world the next time the server starts, None for the default world. The
+    /// settings file is written right away, so it's safe to shut down the server immediately
+    /// after.
+    pub fn set_world_name(&mut self, name: Option<&str>) {
+        self.world_name = name.map(str::to_owned);
+        self.save_to_file();
+    }
+
+    // Worlds used to be stored next to the settings file. Those are still loaded from there, new
+    // worlds are put in the world directory.
+    fn database_path(&self) -> String {
+        let Some(name) = &self.world_name else {
+            return Database::DEFAULT_PATH.to_owned();
+        };
+
+        let old_path = format!("./{}.sqlite", name);
+        if std::path::Path::new(&old_path).exists() {
+            old_path
+        } else {
+            std::fs::create_dir_all(WORLD_DIRECTORY).ok();
+            world_path(name)
+        }
     }
 
     pub fn seed(&self) -> u64 {
@@ -218,7 +434,7 @@ impl Settings {
     }
 }
 
-fn save_settings(settings: Res<Settings>, database: Res<Database>) {
+fn save_settings(settings: Res<Settings>, database_io: Res<DatabaseIo>) {
     settings.save_to_file();
-    settings.save_to_database(&database);
+    settings.save_to_database(&database_io);
 }
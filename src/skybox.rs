@@ -1,6 +1,8 @@
 use std::time::Duration;
 
-use fmc::{networking::Server, prelude::*, protocol::messages};
+use fmc::{networking::Server, players::Player, prelude::*, protocol::messages};
+
+use crate::world::game_rules::GameRules;
 
 /// Handles the day/night cycle
 pub struct SkyPlugin;
@@ -9,7 +11,8 @@ impl Plugin for SkyPlugin {
         app.insert_resource(Clock {
             time: Duration::from_secs_f32(100.0),
         })
-        .add_systems(Update, day_night_cycle);
+        .add_message::<DayNightEvent>()
+        .add_systems(Update, (day_night_cycle, send_time).chain());
     }
 }
 
@@ -20,6 +23,39 @@ const SUNRISE: f32 = 0.0;
 const SUNSET: f32 = DAY_LENGTH / 2.0;
 const MIDNIGHT: f32 = DAY_LENGTH * 0.75;
 const NOON: f32 = DAY_LENGTH * 0.25;
+// Night is when it is dark enough for monsters
+const NIGHT_START: f32 = 700.0;
+const NIGHT_END: f32 = 1100.0;
+
+/// Sent when the night starts and ends, and at noon, including when the time is changed. The
+/// current part of the day is also sent when the server starts.
+#[derive(Message, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum DayNightEvent {
+    /// The night has ended
+    Dawn,
+    /// The sun has passed its highest point
+    Noon,
+    /// The night has started
+    Dusk,
+}
+
+impl DayNightEvent {
+    // The last transition the clock has passed
+    fn from_clock(clock: &Clock) -> Self {
+        if clock.is_night() {
+            DayNightEvent::Dusk
+        } else if (NOON..=NIGHT_START).contains(&clock.get_time()) {
+            DayNightEvent::Noon
+        } else {
+            DayNightEvent::Dawn
+        }
+    }
+}
+
+/// Freezes the time of day for a single player, they see the sky as it is at this time no matter
+/// what the [Clock] says. The clock itself keeps running.
+#[derive(Component)]
+pub struct FrozenTime(pub f32);
 
 /// The current time of day, 0s = dawn, 600s = dusk
 #[derive(DerefMut, Deref, Resource)]
@@ -29,7 +65,8 @@ pub struct Clock {
 
 impl Clock {
     pub fn is_night(&self) -> bool {
-        self.time.as_secs() > 700 && self.time.as_secs() < 1100
+        let time = self.get_time();
+        time > NIGHT_START && time < NIGHT_END
     }
 
     pub fn set_time(&mut self, time: f32) {
@@ -42,6 +79,21 @@ impl Clock {
         self.time.as_secs_f32() % DAY_LENGTH
     }
 
+    /// How many seconds a full day takes
+    pub fn day_length(&self) -> f32 {
+        DAY_LENGTH
+    }
+
+    /// Move the time forward by this many seconds, or back if negative.
+    pub fn advance(&mut self, seconds: f32) {
+        self.set_time(self.get_time() + seconds);
+    }
+
+    /// How high the sun is, 1 at noon, 0 at sunrise and sunset and -1 at midnight.
+    pub fn sun_elevation(&self) -> f32 {
+        (self.get_time() / DAY_LENGTH * std::f32::consts::TAU).sin()
+    }
+
     pub fn set_sunrise(&mut self) {
         self.time = Duration::from_secs_f32(SUNRISE);
     }
@@ -59,12 +111,45 @@ impl Clock {
     }
 }
 
-fn day_night_cycle(time: Res<Time>, net: Res<Server>, mut clock: ResMut<Clock>) {
-    clock.time += time.delta();
+fn day_night_cycle(
+    time: Res<Time>,
+    game_rules: Res<GameRules>,
+    mut clock: ResMut<Clock>,
+    mut day_night_events: MessageWriter<DayNightEvent>,
+    mut last_transition: Local<Option<DayNightEvent>>,
+) {
+    if game_rules.daylight_cycle() {
+        clock.time += time.delta();
+    }
+
+    // Checked after the time has been changed by anything, so jumps in time also count.
+    let transition = DayNightEvent::from_clock(&clock);
+    if *last_transition != Some(transition) {
+        *last_transition = Some(transition);
+        day_night_events.write(transition);
+    }
+}
+
+fn time_angle(time: f32) -> f32 {
+    time * std::f32::consts::TAU / DAY_LENGTH
+}
 
-    let message = messages::Time {
-        angle: clock.time.as_secs_f32() * std::f32::consts::TAU / DAY_LENGTH,
-    };
+fn send_time(
+    net: Res<Server>,
+    clock: Res<Clock>,
+    players: Query<(Entity, Option<&FrozenTime>), With<Player>>,
+) {
+    for (player_entity, frozen_time) in players.iter() {
+        let time = match frozen_time {
+            Some(frozen_time) => frozen_time.0,
+            None => clock.get_time(),
+        };
 
-    net.broadcast(message);
+        net.send_one(
+            player_entity,
+            messages::Time {
+                angle: time_angle(time),
+            },
+        );
+    }
 }
@@ -0,0 +1,41 @@
+use std::{
+    path::PathBuf,
+    sync::{Mutex, PoisonError},
+};
+
+use fmc::{
+    bevy::ecs::{schedule::IntoScheduleConfigs, system::ScheduleSystem},
+    prelude::*,
+};
+
+/// Start the server and run the system once everything has been loaded, the server exits right
+/// after. The block and item configurations are only loaded by a running server, so tests that
+/// need them go through this.
+pub fn run_with_assets<M>(system: impl IntoScheduleConfigs<ScheduleSystem, M>) {
+    // The servers share the working directory, so only one can run at a time.
+    static RUNNING: Mutex<()> = Mutex::new(());
+    let _running = RUNNING.lock().unwrap_or_else(PoisonError::into_inner);
+
+    // The server extracts its assets to the working directory, and removes the old ones first
+    // when it is run by cargo. It must not be the crate's own directory.
+    let directory = std::env::temp_dir().join("fmc_173_tests");
+    std::fs::create_dir_all(&directory).unwrap();
+    std::env::set_current_dir(&directory).unwrap();
+
+    App::new()
+        .add_plugins(crate::DefaultPlugins)
+        .add_systems(Update, (system, exit).chain())
+        .run();
+}
+
+/// Path to a directory of intentionally broken asset files, in `tests/fixtures/`
+pub fn fixture(name: &str) -> String {
+    let path: PathBuf = [env!("CARGO_MANIFEST_DIR"), "tests", "fixtures", name]
+        .iter()
+        .collect();
+    path.to_string_lossy().into_owned()
+}
+
+fn exit(mut app_exit: MessageWriter<AppExit>) {
+    app_exit.write(AppExit::Success);
+}
@@ -0,0 +1,258 @@
+use fmc::{
+    bevy::math::DVec3,
+    blocks::{BlockData, BlockPosition, Blocks},
+    networking::Server,
+    particle_effects::ParticleEffects,
+    players::Player,
+    prelude::*,
+    protocol::messages,
+    random::{Rng, UniformDistribution},
+    world::{BlockUpdate, ChunkSubscriptions, WorldMap, chunk::ChunkPosition},
+};
+
+use crate::{
+    mobs::{Mob, MobDamageEvent},
+    players::PlayerDamageEvent,
+    world::{blocks::fire, dimensions::Dimension, game_rules::GameRules, has_sky_access},
+};
+
+/// Switches between clear weather and thunderstorms. During thunderstorms lightning strikes the
+/// ground around players, and lightning rods draw the strikes to themselves.
+pub struct WeatherPlugin;
+impl Plugin for WeatherPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(Weather::default())
+            .add_message::<LightningStrikeEvent>()
+            .add_systems(Startup, setup)
+            .add_systems(
+                Update,
+                (change_weather, strike_lightning, lightning_strikes).chain(),
+            );
+    }
+}
+
+// How long the weather lasts before it changes, in seconds
+const CLEAR_DURATION: (f32, f32) = (600.0, 1800.0);
+const THUNDERSTORM_DURATION: (f32, f32) = (120.0, 300.0);
+
+/// Lightning strikes within this many blocks of each player
+const STRIKE_RANGE: i32 = 64;
+/// Average time between strikes around each player in seconds
+const STRIKE_INTERVAL: f32 = 10.0;
+/// Lightning that would strike within this many blocks of a lightning rod strikes the rod instead
+const ROD_RADIUS: i32 = 32;
+/// Entities within this distance of the strike are hit
+const STRIKE_RADIUS: f64 = 3.0;
+const STRIKE_DAMAGE: u32 = 5;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum WeatherKind {
+    Clear,
+    Thunderstorm,
+}
+
+// TODO: The client can't show rain or darken the sky, so thunderstorms are only lightning for now.
+//
+/// The current weather, it changes on its own when its time runs out.
+#[derive(Resource)]
+pub struct Weather {
+    kind: WeatherKind,
+    timer: Timer,
+    rng: Rng,
+}
+
+impl Default for Weather {
+    fn default() -> Self {
+        let mut weather = Self {
+            kind: WeatherKind::Clear,
+            timer: Timer::default(),
+            rng: Rng::default(),
+        };
+        let (min, max) = CLEAR_DURATION;
+        let duration = UniformDistribution::new(min, max).sample(&mut weather.rng);
+        weather.set(WeatherKind::Clear, duration);
+        weather
+    }
+}
+
+impl Weather {
+    pub fn kind(&self) -> WeatherKind {
+        self.kind
+    }
+
+    pub fn is_thunderstorm(&self) -> bool {
+        self.kind == WeatherKind::Thunderstorm
+    }
+
+    /// Change the weather, it lasts for `duration` seconds before it changes on its own.
+    pub fn set(&mut self, kind: WeatherKind, duration: f32) {
+        self.kind = kind;
+        self.timer = Timer::from_seconds(duration, TimerMode::Once);
+    }
+}
+
+/// Sent when lightning strikes. Lightning can also be summoned by sending it.
+#[derive(Message)]
+pub struct LightningStrikeEvent {
+    /// The block that was struck
+    pub position: BlockPosition,
+}
+
+/// Marks the block entity of a lightning rod
+#[derive(Component)]
+struct LightningRod;
+
+fn setup(mut blocks: ResMut<Blocks>) {
+    let block_id = blocks.get_id("lightning_rod");
+    let block = blocks.get_config_mut(&block_id);
+    block.set_spawn_function(lightning_rod_spawn_function);
+}
+
+fn lightning_rod_spawn_function(commands: &mut EntityCommands, _block_data: Option<&BlockData>) {
+    commands.insert(LightningRod);
+}
+
+fn change_weather(time: Res<Time>, mut weather: ResMut<Weather>) {
+    weather.timer.tick(time.delta());
+    if !weather.timer.is_finished() {
+        return;
+    }
+
+    let (kind, (min, max)) = match weather.kind {
+        WeatherKind::Clear => (WeatherKind::Thunderstorm, THUNDERSTORM_DURATION),
+        WeatherKind::Thunderstorm => (WeatherKind::Clear, CLEAR_DURATION),
+    };
+    let duration = UniformDistribution::new(min, max).sample(&mut weather.rng);
+    weather.set(kind, duration);
+}
+
+// Pick random spots around the players for lightning to strike.
+fn strike_lightning(
+    time: Res<Time>,
+    world_map: Res<WorldMap>,
+    weather: Res<Weather>,
+    players: Query<(&GlobalTransform, &Dimension), With<Player>>,
+    lightning_rods: Query<&BlockPosition, With<LightningRod>>,
+    mut strikes: MessageWriter<LightningStrikeEvent>,
+    mut rng: Local<Rng>,
+) {
+    if !weather.is_thunderstorm() {
+        return;
+    }
+
+    let air = Blocks::get().get_id("air");
+    let chance = time.delta_secs() / STRIKE_INTERVAL;
+    let range = UniformDistribution::new(-STRIKE_RANGE, STRIKE_RANGE);
+
+    for (transform, dimension) in players.iter() {
+        // There's no weather in the nether
+        if *dimension != Dimension::Overworld || rng.next_f32() > chance {
+            continue;
+        }
+
+        let player_position = BlockPosition::from(transform.translation());
+        let x = player_position.x + range.sample(&mut rng);
+        let z = player_position.z + range.sample(&mut rng);
+
+        let closest_rod = lightning_rods
+            .iter()
+            .map(|rod| (rod, IVec2::new(rod.x - x, rod.z - z).length_squared()))
+            .filter(|(_, distance)| *distance <= ROD_RADIUS * ROD_RADIUS)
+            .filter(|(rod, _)| has_sky_access(&world_map, **rod))
+            .min_by_key(|(_, distance)| *distance);
+
+        let position = if let Some((rod, _)) = closest_rod {
+            *rod
+        } else {
+            // The highest block in the column
+            let Some(ground) = (player_position.y - STRIKE_RANGE
+                ..=player_position.y + STRIKE_RANGE)
+                .rev()
+                .map(|y| BlockPosition::new(x, y, z))
+                .find(|position| {
+                    world_map
+                        .get_block(*position)
+                        .is_some_and(|block_id| block_id != air)
+                })
+            else {
+                continue;
+            };
+
+            if !has_sky_access(&world_map, ground) {
+                continue;
+            }
+
+            ground
+        };
+
+        strikes.write(LightningStrikeEvent { position });
+    }
+}
+
+fn lightning_strikes(
+    net: Res<Server>,
+    game_rules: Res<GameRules>,
+    world_map: Res<WorldMap>,
+    particle_effects: Res<ParticleEffects>,
+    chunk_subscriptions: Res<ChunkSubscriptions>,
+    players: Query<(Entity, &GlobalTransform), With<Player>>,
+    mobs: Query<(Entity, &GlobalTransform), With<Mob>>,
+    mut strikes: MessageReader<LightningStrikeEvent>,
+    mut player_damage_events: MessageWriter<PlayerDamageEvent>,
+    mut mob_damage_events: MessageWriter<MobDamageEvent>,
+    mut block_updates: MessageWriter<BlockUpdate>,
+) {
+    for strike in strikes.read() {
+        let center = strike.position.as_dvec3() + DVec3::new(0.5, 1.0, 0.5);
+
+        if let Some(subscribers) =
+            chunk_subscriptions.get_subscribers(&ChunkPosition::from(strike.position))
+        {
+            // TODO: Needs a bolt and a thunder sound of its own, the explosion is a stand-in.
+            net.send_many(
+                subscribers,
+                messages::Sound {
+                    position: Some(center),
+                    volume: 1.0,
+                    speed: 0.5,
+                    sound: "explosion.ogg".to_owned(),
+                },
+            );
+            net.send_many(
+                subscribers,
+                messages::ParticleEffect {
+                    id: particle_effects.get_id("explosion_white").unwrap(),
+                    position: center,
+                    rotation: Quat::IDENTITY,
+                    texture: "particles/explosion2.png".to_owned(),
+                    color: Vec4::new(0.8, 0.85, 1.0, 1.0),
+                },
+            );
+        }
+
+        for (player_entity, transform) in players.iter() {
+            if transform.translation().distance(center) <= STRIKE_RADIUS {
+                player_damage_events.write(PlayerDamageEvent {
+                    player_entity,
+                    damage: STRIKE_DAMAGE,
+                    knock_back: None,
+                });
+            }
+        }
+
+        for (mob_entity, transform) in mobs.iter() {
+            if transform.translation().distance(center) <= STRIKE_RADIUS {
+                mob_damage_events.write(MobDamageEvent {
+                    mob_entity,
+                    damage: STRIKE_DAMAGE,
+                    attacker: None,
+                    knock_back: None,
+                });
+            }
+        }
+
+        if game_rules.mob_griefing() {
+            fire::ignite(&world_map, strike.position, &mut block_updates);
+        }
+    }
+}
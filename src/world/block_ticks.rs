@@ -0,0 +1,185 @@
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    time::Duration,
+};
+
+use fmc::{
+    blocks::{BlockId, BlockPosition},
+    players::Player,
+    prelude::*,
+    random::Rng,
+    world::{
+        WorldMap,
+        chunk::{Chunk, ChunkPosition},
+    },
+};
+
+pub struct BlockTicksPlugin;
+impl Plugin for BlockTicksPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(BlockTicks::default()).add_systems(
+            Update,
+            (scheduled_ticks, random_ticks).in_set(BlockTickSystems),
+        );
+    }
+}
+
+/// Order systems that read [BlockTickEvents] after this [SystemSet] to receive the ticks the same
+/// frame they are sent.
+#[derive(SystemSet, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct BlockTickSystems;
+
+/// How many blocks are randomly chosen to be ticked in each simulated chunk every update.
+const RANDOM_TICKS_PER_CHUNK: usize = 3;
+/// Chunks within this many chunks of a player are simulated and receive random ticks.
+const SIMULATION_RADIUS: i32 = 3;
+
+/// Keeps track of which entities handle ticks for a block and the ticks that have been scheduled.
+///
+/// Each block that wants to receive ticks must have a handler entity registered. When a block of
+/// that type is ticked, the position will be pushed to the [BlockTickEvents] of all its handlers.
+#[derive(Resource, Default)]
+pub struct BlockTicks {
+    handlers: HashMap<BlockId, Vec<Entity>>,
+    // The handlers that also want random ticks, blocks that only have handlers interested in
+    // scheduled ticks are left out so they won't be randomly ticked.
+    random: HashMap<BlockId, Vec<Entity>>,
+    // Scheduled ticks ordered by the time they should happen
+    scheduled: BTreeMap<Duration, Vec<BlockPosition>>,
+    elapsed: Duration,
+}
+
+impl BlockTicks {
+    /// Register a handler entity for a block. If `random_ticks` is true, the handler will also
+    /// receive random ticks for the block while it is inside a simulated chunk. A block can have
+    /// several handlers, each of them gets every tick.
+    pub fn register(&mut self, block_id: BlockId, handler_entity: Entity, random_ticks: bool) {
+        self.handlers
+            .entry(block_id)
+            .or_default()
+            .push(handler_entity);
+        if random_ticks {
+            self.random
+                .entry(block_id)
+                .or_default()
+                .push(handler_entity);
+        }
+    }
+
+    /// Tick the block at the given position after `delay` has passed. The tick is sent to the
+    /// handler of whatever block occupies the position when the delay runs out.
+    pub fn schedule_tick(&mut self, position: BlockPosition, delay: Duration) {
+        self.scheduled
+            .entry(self.elapsed + delay)
+            .or_default()
+            .push(position);
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BlockTickKind {
+    /// The tick was scheduled through [BlockTicks::schedule_tick]
+    Scheduled,
+    /// The block was chosen at random
+    Random,
+}
+
+/// The block ticks the handler has received during the last update.
+///
+/// Attach this to an entity and register the entity as a handler in [BlockTicks].
+#[derive(Component, Default)]
+pub struct BlockTickEvents(Vec<(BlockPosition, BlockTickKind)>);
+
+impl BlockTickEvents {
+    pub fn read(&mut self) -> impl Iterator<Item = (BlockPosition, BlockTickKind)> + '_ {
+        self.0.drain(..)
+    }
+
+    pub fn push(&mut self, block_position: BlockPosition, kind: BlockTickKind) {
+        self.0.push((block_position, kind));
+    }
+}
+
+fn scheduled_ticks(
+    time: Res<Time>,
+    world_map: Res<WorldMap>,
+    mut block_ticks: ResMut<BlockTicks>,
+    mut handler_query: Query<&mut BlockTickEvents>,
+) {
+    let block_ticks = &mut *block_ticks;
+    block_ticks.elapsed += time.delta();
+
+    let elapsed = block_ticks.elapsed;
+    while let Some(entry) = block_ticks.scheduled.first_entry() {
+        if *entry.key() > elapsed {
+            break;
+        }
+
+        for block_position in entry.remove() {
+            let Some(block_id) = world_map.get_block(block_position) else {
+                // The chunk was unloaded before the tick happened
+                continue;
+            };
+
+            let Some(handlers) = block_ticks.handlers.get(&block_id) else {
+                continue;
+            };
+
+            for handler_entity in handlers {
+                if let Ok(mut events) = handler_query.get_mut(*handler_entity) {
+                    events.push(block_position, BlockTickKind::Scheduled);
+                }
+            }
+        }
+    }
+}
+
+fn random_ticks(
+    world_map: Res<WorldMap>,
+    block_ticks: Res<BlockTicks>,
+    player_query: Query<&ChunkPosition, With<Player>>,
+    mut handler_query: Query<&mut BlockTickEvents>,
+    mut simulated_chunks: Local<HashSet<ChunkPosition>>,
+    mut rng: Local<Rng>,
+) {
+    if block_ticks.random.is_empty() {
+        return;
+    }
+
+    // Players that are close to each other share chunks, so they're collected first to not tick
+    // any chunk twice.
+    simulated_chunks.clear();
+    for player_chunk_position in player_query.iter() {
+        for x in -SIMULATION_RADIUS..=SIMULATION_RADIUS {
+            for y in -SIMULATION_RADIUS..=SIMULATION_RADIUS {
+                for z in -SIMULATION_RADIUS..=SIMULATION_RADIUS {
+                    let offset = IVec3::new(x, y, z) * Chunk::SIZE as i32;
+                    simulated_chunks.insert(*player_chunk_position + ChunkPosition::from(offset));
+                }
+            }
+        }
+    }
+
+    for chunk_position in simulated_chunks.iter() {
+        let Some(chunk) = world_map.get_chunk(chunk_position) else {
+            continue;
+        };
+
+        for _ in 0..RANDOM_TICKS_PER_CHUNK {
+            let chunk_index = rng.next_usize() % (Chunk::SIZE * Chunk::SIZE * Chunk::SIZE);
+            let block_id = chunk[chunk_index];
+
+            let Some(handlers) = block_ticks.random.get(&block_id) else {
+                continue;
+            };
+
+            let block_position =
+                BlockPosition::from(*chunk_position) + BlockPosition::from(chunk_index);
+            for handler_entity in handlers {
+                if let Ok(mut events) = handler_query.get_mut(*handler_entity) {
+                    events.push(block_position, BlockTickKind::Random);
+                }
+            }
+        }
+    }
+}
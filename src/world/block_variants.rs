@@ -0,0 +1,235 @@
+use std::collections::HashMap;
+
+use fmc::{
+    blocks::{BLOCK_CONFIG_PATH, BlockId, Blocks},
+    prelude::*,
+};
+use serde::Deserialize;
+
+const BLOCK_VARIANTS_PATH: &str = "./assets/server/block_variants.json";
+
+/// Block states beyond rotation, like the growth stage of a crop or whether a furnace is lit.
+///
+/// Blocks that only differ in how they look are grouped into families, where each combination of
+/// the family's properties is its own block. Rotation is still kept in the
+/// [BlockState](fmc::blocks::BlockState) and can be combined with any of them. Since every
+/// variant is a block of its own, the client renders it from its block config like any other
+/// block, and saved chunks refer to it by name, so they keep working when a family changes.
+///
+/// The families are read from `block_variants.json`. Each one lists its properties and how many
+/// values each can have, followed by its blocks in order, with the last property changing
+/// fastest.
+pub struct BlockVariantsPlugin;
+impl Plugin for BlockVariantsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Startup, load_block_variants.in_set(BlockVariantSystems));
+    }
+}
+
+/// Systems that use the [BlockVariants] at startup must run after this set.
+#[derive(SystemSet, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct BlockVariantSystems;
+
+struct BlockFamily {
+    // Name of each property and how many values it can have
+    properties: Vec<(String, u8)>,
+    blocks: Vec<BlockId>,
+}
+
+impl BlockFamily {
+    fn property_index(&self, property: &str) -> usize {
+        match self
+            .properties
+            .iter()
+            .position(|(name, _)| name == property)
+        {
+            Some(index) => index,
+            None => panic!("Block variants don't have a property named '{}'", property),
+        }
+    }
+}
+
+#[derive(Resource)]
+pub struct BlockVariants {
+    families: Vec<BlockFamily>,
+    by_name: HashMap<String, usize>,
+    // The family each block belongs to, and which variant of it it is
+    by_block: HashMap<BlockId, (usize, usize)>,
+}
+
+impl BlockVariants {
+    /// Build a variant of the family, the properties all start at 0.
+    ///
+    /// Panics if there's no family by that name.
+    pub fn builder(&self, family: &str) -> BlockStateBuilder<'_> {
+        let Some(index) = self.by_name.get(family) else {
+            panic!("There is no block family named '{}'", family);
+        };
+        let family = &self.families[*index];
+
+        BlockStateBuilder {
+            family,
+            values: vec![0; family.properties.len()],
+        }
+    }
+
+    /// The properties of the block, or None if it isn't part of the family.
+    pub fn state(&self, family: &str, block_id: BlockId) -> Option<BlockStateBuilder<'_>> {
+        let (family_index, mut variant) = *self.by_block.get(&block_id)?;
+        if self.by_name.get(family) != Some(&family_index) {
+            return None;
+        }
+        let family = &self.families[family_index];
+
+        let mut values = vec![0; family.properties.len()];
+        for (value, (_, count)) in values.iter_mut().zip(&family.properties).rev() {
+            *value = (variant % *count as usize) as u8;
+            variant /= *count as usize;
+        }
+
+        Some(BlockStateBuilder { family, values })
+    }
+}
+
+/// Sets and reads the properties of a block variant.
+pub struct BlockStateBuilder<'a> {
+    family: &'a BlockFamily,
+    values: Vec<u8>,
+}
+
+impl BlockStateBuilder<'_> {
+    /// Panics if the property doesn't exist or can't have the value.
+    pub fn with(mut self, property: &str, value: u8) -> Self {
+        let index = self.family.property_index(property);
+        let (_, count) = self.family.properties[index];
+        if value >= count {
+            panic!(
+                "The block property '{}' can only have {} values, tried to set it to {}",
+                property, count, value
+            );
+        }
+        self.values[index] = value;
+        self
+    }
+
+    pub fn with_bool(self, property: &str, value: bool) -> Self {
+        self.with(property, value as u8)
+    }
+
+    pub fn get(&self, property: &str) -> u8 {
+        self.values[self.family.property_index(property)]
+    }
+
+    pub fn get_bool(&self, property: &str) -> bool {
+        self.get(property) != 0
+    }
+
+    /// How many values the property can have.
+    pub fn value_count(&self, property: &str) -> u8 {
+        self.family.properties[self.family.property_index(property)].1
+    }
+
+    /// The block with these properties.
+    pub fn block_id(&self) -> BlockId {
+        let mut variant = 0;
+        for (value, (_, count)) in self.values.iter().zip(&self.family.properties) {
+            variant = variant * *count as usize + *value as usize;
+        }
+        self.family.blocks[variant]
+    }
+}
+
+#[derive(Deserialize)]
+struct BlockFamilyJson {
+    properties: Vec<PropertyJson>,
+    blocks: Vec<String>,
+}
+
+#[derive(Deserialize)]
+struct PropertyJson {
+    name: String,
+    values: u8,
+}
+
+fn load_block_variants(mut commands: Commands) {
+    let file = match std::fs::File::open(BLOCK_VARIANTS_PATH) {
+        Ok(f) => f,
+        Err(e) => panic!(
+            "Failed to open block variants at path: {}\nError: {}",
+            BLOCK_VARIANTS_PATH, e
+        ),
+    };
+
+    let json: HashMap<String, BlockFamilyJson> = match serde_json::from_reader(file) {
+        Ok(v) => v,
+        Err(e) => panic!(
+            "Failed to read block variants at path: {}\nError: {}",
+            BLOCK_VARIANTS_PATH, e
+        ),
+    };
+
+    let blocks = Blocks::get();
+
+    let mut block_variants = BlockVariants {
+        families: Vec::with_capacity(json.len()),
+        by_name: HashMap::with_capacity(json.len()),
+        by_block: HashMap::new(),
+    };
+
+    for (name, family_json) in json {
+        let variant_count = family_json
+            .properties
+            .iter()
+            .map(|property| property.values as usize)
+            .product::<usize>();
+        if variant_count != family_json.blocks.len() {
+            panic!(
+                "Failed to read the block family '{}', its properties make {} variants, but it \
+                has {} blocks",
+                name,
+                variant_count,
+                family_json.blocks.len()
+            );
+        }
+
+        let family_index = block_variants.families.len();
+        let mut family_blocks = Vec::with_capacity(family_json.blocks.len());
+
+        for (variant, block_name) in family_json.blocks.iter().enumerate() {
+            if !blocks.contains_block(block_name) {
+                panic!(
+                    "Failed to read the block family '{}', there is no block with the name '{}'. \
+                    Make sure a block by the same name is present at '{}'",
+                    name, block_name, BLOCK_CONFIG_PATH
+                );
+            }
+
+            let block_id = blocks.get_id(block_name);
+            if block_variants
+                .by_block
+                .insert(block_id, (family_index, variant))
+                .is_some()
+            {
+                panic!(
+                    "Failed to read the block family '{}', the block '{}' is already part of \
+                    another family",
+                    name, block_name
+                );
+            }
+
+            family_blocks.push(block_id);
+        }
+
+        block_variants.families.push(BlockFamily {
+            properties: family_json
+                .properties
+                .into_iter()
+                .map(|property| (property.name, property.values))
+                .collect(),
+            blocks: family_blocks,
+        });
+        block_variants.by_name.insert(name, family_index);
+    }
+
+    commands.insert_resource(block_variants);
+}
@@ -0,0 +1,129 @@
+use std::collections::HashMap;
+
+use fmc::{
+    bevy::math::DVec3,
+    blocks::{BlockId, BlockRotation, BlockState, Blocks},
+    items::{ItemStack, Items},
+    prelude::*,
+    world::BlockUpdate,
+};
+
+use crate::{
+    items::DroppedItem,
+    world::neighbor_updates::{BlockNeighborUpdates, NeighborUpdateSystems, NeighborUpdates},
+};
+
+/// Blocks that need another block to hold them up, like torches and crops. When the block they
+/// are attached to is removed they pop off and drop as an item.
+///
+/// Register blocks in [AttachedBlocks] during [Startup].
+pub struct AttachedBlocksPlugin;
+impl Plugin for AttachedBlocksPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(AttachedBlocks::default())
+            .add_systems(PostStartup, subscribe)
+            .add_systems(Update, pop_off.after(NeighborUpdateSystems));
+    }
+}
+
+/// Which side of a block it is attached to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Support {
+    /// The block below
+    Below,
+    /// The block behind it, given by its rotation, or the block below if it isn't rotated.
+    Behind,
+}
+
+impl Support {
+    fn direction(&self, block_state: Option<BlockState>) -> IVec3 {
+        match self {
+            Support::Below => IVec3::NEG_Y,
+            Support::Behind => match block_state.and_then(|state| state.rotation()) {
+                Some(BlockRotation::Front) => IVec3::NEG_Z,
+                Some(BlockRotation::Right) => IVec3::NEG_X,
+                Some(BlockRotation::Back) => IVec3::Z,
+                Some(BlockRotation::Left) => IVec3::X,
+                None => IVec3::NEG_Y,
+            },
+        }
+    }
+}
+
+/// The blocks that pop off when what they're attached to is removed
+#[derive(Resource, Default)]
+pub struct AttachedBlocks {
+    blocks: HashMap<BlockId, Support>,
+}
+
+impl AttachedBlocks {
+    pub fn register(&mut self, block_id: BlockId, support: Support) {
+        self.blocks.insert(block_id, support);
+    }
+}
+
+#[derive(Component)]
+struct AttachedBlocksHandler;
+
+fn subscribe(
+    mut commands: Commands,
+    attached_blocks: Res<AttachedBlocks>,
+    mut neighbor_updates: ResMut<NeighborUpdates>,
+) {
+    let handler = commands
+        .spawn((BlockNeighborUpdates::default(), AttachedBlocksHandler))
+        .id();
+
+    for block_id in attached_blocks.blocks.keys() {
+        neighbor_updates.subscribe(*block_id, handler);
+    }
+}
+
+fn pop_off(
+    mut commands: Commands,
+    items: Res<Items>,
+    attached_blocks: Res<AttachedBlocks>,
+    mut handler_query: Query<
+        &mut BlockNeighborUpdates,
+        (With<AttachedBlocksHandler>, Changed<BlockNeighborUpdates>),
+    >,
+    mut block_updates: MessageWriter<BlockUpdate>,
+) {
+    let Ok(mut neighbor_updates) = handler_query.single_mut() else {
+        return;
+    };
+
+    let blocks = Blocks::get();
+
+    for neighbor_update in neighbor_updates.read() {
+        let (block_id, block_state) = neighbor_update.block;
+        let Some(support) = attached_blocks.blocks.get(&block_id) else {
+            continue;
+        };
+
+        if support.direction(block_state) != neighbor_update.direction
+            || blocks.get_config(&neighbor_update.to.0).is_solid()
+        {
+            continue;
+        }
+
+        block_updates.write(BlockUpdate::Replace {
+            position: neighbor_update.position,
+            block_id: blocks.get_id("air"),
+            block_state: None,
+            block_data: None,
+        });
+
+        let Some(dropped_item_id) = blocks.get_config(&block_id).drop(None) else {
+            continue;
+        };
+
+        let item_config = items.get_config(&dropped_item_id);
+        let item_stack = ItemStack::new(item_config, 1);
+
+        commands.spawn((
+            DroppedItem::new(item_stack),
+            Transform::from_translation(neighbor_update.position.as_dvec3() + DVec3::splat(0.5)),
+        ));
+    }
+}
@@ -0,0 +1,176 @@
+use std::collections::HashMap;
+
+use fmc::{
+    bevy::ecs::system::EntityCommands,
+    blocks::{BlockData, BlockId, BlockPosition, Blocks},
+    networking::Server,
+    players::Player,
+    prelude::*,
+    protocol::messages,
+    world::ChangedBlockEvent,
+};
+
+use crate::{
+    chat::{broadcast_message, send_message},
+    players::{HandInteractions, SpawnPoint},
+    skybox::{Clock, DayNightEvent},
+};
+
+use super::multi_block::MultiBlocks;
+
+pub struct BedPlugin;
+impl Plugin for BedPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(Sleepers::default())
+            .add_systems(Startup, setup)
+            .add_systems(
+                Update,
+                (
+                    handle_interactions,
+                    remove_spawn_points,
+                    (wake_players, skip_night).chain(),
+                ),
+            );
+    }
+}
+
+/// Players can't wander further than this from their bed while sleeping.
+const MAX_SLEEP_DISTANCE: f64 = 2.0;
+
+#[derive(Component)]
+struct Bed;
+
+/// The players that are currently sleeping, and the bed they are sleeping in.
+#[derive(Resource, Default)]
+struct Sleepers(HashMap<Entity, BlockPosition>);
+
+fn setup(mut blocks: ResMut<Blocks>, mut multi_blocks: ResMut<MultiBlocks>) {
+    let bed_foot = blocks.get_id("bed_foot");
+    let bed_head = blocks.get_id("bed_head");
+
+    // The foot is placed where the player clicks, and the head extends away from the player.
+    multi_blocks.register(&[(bed_foot, IVec3::ZERO), (bed_head, IVec3::NEG_Z)]);
+
+    blocks
+        .get_config_mut(&bed_foot)
+        .set_spawn_function(spawn_function);
+    blocks
+        .get_config_mut(&bed_head)
+        .set_spawn_function(spawn_function);
+}
+
+fn spawn_function(commands: &mut EntityCommands, _block_data: Option<&BlockData>) {
+    commands.insert((Bed, HandInteractions::default()));
+}
+
+fn handle_interactions(
+    net: Res<Server>,
+    clock: Res<Clock>,
+    mut sleepers: ResMut<Sleepers>,
+    mut player_query: Query<&mut SpawnPoint, With<Player>>,
+    mut bed_query: Query<
+        (&BlockPosition, &mut HandInteractions),
+        (With<Bed>, Changed<HandInteractions>),
+    >,
+) {
+    for (block_position, mut interactions) in bed_query.iter_mut() {
+        for player_entity in interactions.read() {
+            let Ok(mut spawn_point) = player_query.get_mut(player_entity) else {
+                continue;
+            };
+
+            if !clock.is_night() {
+                send_message(
+                    &net,
+                    player_entity,
+                    "You can only sleep at night".to_owned(),
+                );
+                continue;
+            }
+
+            // The player respawns standing on top of the bed
+            let bed_spawn = *(*block_position + IVec3::Y);
+            if **spawn_point != Some(bed_spawn) {
+                **spawn_point = Some(bed_spawn);
+                send_message(&net, player_entity, "Respawn point set".to_owned());
+            }
+
+            sleepers.0.insert(player_entity, *block_position);
+        }
+    }
+}
+
+// When a bed is removed, the players that had it as their spawn point go back to spawning at
+// the world spawn.
+fn remove_spawn_points(
+    mut player_query: Query<&mut SpawnPoint, With<Player>>,
+    mut changed_blocks: MessageReader<ChangedBlockEvent>,
+    mut bed_blocks: Local<Vec<BlockId>>,
+) {
+    if bed_blocks.is_empty() {
+        let blocks = Blocks::get();
+        bed_blocks.push(blocks.get_id("bed_foot"));
+        bed_blocks.push(blocks.get_id("bed_head"));
+    }
+
+    for changed_block in changed_blocks.read() {
+        if !bed_blocks.contains(&changed_block.from.0) || changed_block.from.0 == changed_block.to.0
+        {
+            continue;
+        }
+
+        let bed_spawn = *(changed_block.position + IVec3::Y);
+        for mut spawn_point in player_query.iter_mut() {
+            if **spawn_point == Some(bed_spawn) {
+                **spawn_point = None;
+            }
+        }
+    }
+}
+
+// Players stop sleeping when they leave, walk away from the bed, or it is no longer night.
+fn wake_players(
+    mut sleepers: ResMut<Sleepers>,
+    player_query: Query<&GlobalTransform, With<Player>>,
+    mut day_night_events: MessageReader<DayNightEvent>,
+) {
+    // The time can be set straight from night to noon, so anything but dusk means it is day.
+    let day = day_night_events
+        .read()
+        .filter(|event| **event != DayNightEvent::Dusk)
+        .count()
+        > 0;
+    if day {
+        sleepers.0.clear();
+        return;
+    }
+
+    sleepers.0.retain(|player_entity, bed_position| {
+        let Ok(transform) = player_query.get(*player_entity) else {
+            return false;
+        };
+
+        let bed_center = bed_position.as_dvec3() + DVec3::splat(0.5);
+        transform.translation().distance(bed_center) <= MAX_SLEEP_DISTANCE
+    });
+}
+
+fn skip_night(
+    net: Res<Server>,
+    mut clock: ResMut<Clock>,
+    mut sleepers: ResMut<Sleepers>,
+    player_query: Query<(), With<Player>>,
+) {
+    if sleepers.0.is_empty() || sleepers.0.len() < player_query.iter().count() {
+        return;
+    }
+
+    sleepers.0.clear();
+    clock.set_sunrise();
+
+    // The time is broadcast every update, but the clients should see the sun rise immediately
+    // even if this runs after the day/night cycle this update. Sunrise is at angle 0.
+    net.broadcast(messages::Time { angle: 0.0 });
+
+    broadcast_message(&net, "Everyone slept through the night".to_owned());
+}
@@ -0,0 +1,98 @@
+use std::collections::HashSet;
+
+use fmc::{
+    bevy::math::DVec3,
+    blocks::BlockPosition,
+    networking::{NetworkEvent, Server},
+    players::Player,
+    prelude::*,
+    protocol::messages,
+};
+
+/// Interfaces that are opened by interacting with a block, like chests, furnaces and crafting
+/// tables, are closed when the player walks too far away from the block, the block is removed or
+/// the player leaves.
+///
+/// The client doesn't tell the server when it closes an interface by itself, so these are the only
+/// times the server knows it is closed. Blocks are told with a [CloseBlockInterface] so they can
+/// stop sending updates and give back the items that were left in the interface.
+pub struct BlockInterfacePlugin;
+impl Plugin for BlockInterfacePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_message::<CloseBlockInterface>()
+            .add_systems(Update, close_block_interfaces.in_set(BlockInterfaceSystems));
+    }
+}
+
+/// Systems that read [CloseBlockInterface] should run after this [SystemSet], so that items are
+/// given back before the player is saved when they leave.
+#[derive(SystemSet, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct BlockInterfaceSystems;
+
+/// How far the player can be from the center of the block before its interface is closed
+const MAX_DISTANCE: f64 = 8.0;
+
+/// The interface the player opened by interacting with a block. Inserted on the player entity,
+/// opening another block's interface replaces it.
+#[derive(Component)]
+pub struct OpenBlockInterface {
+    pub block_entity: Entity,
+    pub block_position: BlockPosition,
+    pub interface_path: &'static str,
+}
+
+/// Sent when the server closes a block interface the player had open
+#[derive(Message)]
+pub struct CloseBlockInterface {
+    pub player_entity: Entity,
+    pub block_entity: Entity,
+    pub block_position: BlockPosition,
+}
+
+fn close_block_interfaces(
+    mut commands: Commands,
+    net: Res<Server>,
+    block_query: Query<(), With<BlockPosition>>,
+    player_query: Query<(Entity, &GlobalTransform, &OpenBlockInterface), With<Player>>,
+    mut network_events: MessageReader<NetworkEvent>,
+    mut close_events: MessageWriter<CloseBlockInterface>,
+) {
+    let disconnected: HashSet<Entity> = network_events
+        .read()
+        .filter_map(|network_event| match network_event {
+            NetworkEvent::Disconnected { entity } => Some(*entity),
+            _ => None,
+        })
+        .collect();
+
+    for (player_entity, transform, open_interface) in player_query.iter() {
+        let block_center = open_interface.block_position.as_dvec3() + DVec3::splat(0.5);
+        let too_far = transform.translation().distance(block_center) > MAX_DISTANCE;
+        let block_removed = !block_query.contains(open_interface.block_entity);
+        let disconnected = disconnected.contains(&player_entity);
+
+        if !too_far && !block_removed && !disconnected {
+            continue;
+        }
+
+        // Players that leave are despawned, there's no interface left to close.
+        if !disconnected {
+            net.send_one(
+                player_entity,
+                messages::InterfaceVisibilityUpdate {
+                    interface_path: open_interface.interface_path.to_owned(),
+                    visible: false,
+                },
+            );
+            commands
+                .entity(player_entity)
+                .remove::<OpenBlockInterface>();
+        }
+
+        close_events.write(CloseBlockInterface {
+            player_entity,
+            block_entity: open_interface.block_entity,
+            block_position: open_interface.block_position,
+        });
+    }
+}
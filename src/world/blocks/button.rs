@@ -0,0 +1,128 @@
+use std::time::Duration;
+
+use fmc::{
+    bevy::ecs::system::EntityCommands,
+    blocks::{BlockData, BlockPosition, Blocks},
+    prelude::*,
+    world::{BlockUpdate, WorldMap},
+};
+
+use super::{
+    attached::{AttachedBlocks, Support},
+    power::{MAX_POWER, Power},
+};
+use crate::{
+    players::HandInteractions,
+    world::{
+        block_ticks::{BlockTickEvents, BlockTickSystems, BlockTicks},
+        block_variants::{BlockVariantSystems, BlockVariants},
+    },
+};
+
+/// Buttons power the blocks around them for a moment after they're pressed.
+pub struct ButtonPlugin;
+impl Plugin for ButtonPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Startup, setup.after(BlockVariantSystems))
+            .add_systems(Update, (press, release.after(BlockTickSystems)));
+    }
+}
+
+/// How long a button stays pressed
+const PRESS_DURATION: Duration = Duration::from_secs(1);
+
+#[derive(Component)]
+struct ButtonBlock;
+
+fn setup(
+    mut commands: Commands,
+    mut blocks: ResMut<Blocks>,
+    block_variants: Res<BlockVariants>,
+    mut block_ticks: ResMut<BlockTicks>,
+    mut power: ResMut<Power>,
+    mut attached_blocks: ResMut<AttachedBlocks>,
+) {
+    let handler = commands
+        .spawn((BlockTickEvents::default(), ButtonBlock))
+        .id();
+
+    for pressed in [false, true] {
+        let block_id = block_variants
+            .builder("button")
+            .with_bool("pressed", pressed)
+            .block_id();
+        blocks
+            .get_config_mut(&block_id)
+            .set_spawn_function(spawn_function);
+        attached_blocks.register(block_id, Support::Behind);
+        if pressed {
+            power.register_source(block_id, MAX_POWER);
+            block_ticks.register(block_id, handler, false);
+        }
+    }
+}
+
+fn spawn_function(commands: &mut EntityCommands, _block_data: Option<&BlockData>) {
+    commands.insert((ButtonBlock, HandInteractions::default()));
+}
+
+fn press(
+    world_map: Res<WorldMap>,
+    block_variants: Res<BlockVariants>,
+    mut block_ticks: ResMut<BlockTicks>,
+    mut block_hits: Query<
+        (&BlockPosition, &mut HandInteractions),
+        (With<ButtonBlock>, Changed<HandInteractions>),
+    >,
+    mut block_update_writer: MessageWriter<BlockUpdate>,
+) {
+    for (block_position, mut interactions) in block_hits.iter_mut() {
+        if interactions.read().count() == 0 {
+            continue;
+        }
+
+        let Some(button) = world_map
+            .get_block(*block_position)
+            .and_then(|block_id| block_variants.state("button", block_id))
+        else {
+            continue;
+        };
+
+        if button.get_bool("pressed") {
+            continue;
+        }
+
+        block_update_writer.write(BlockUpdate::Swap {
+            position: *block_position,
+            block_id: button.with_bool("pressed", true).block_id(),
+            block_state: world_map.get_block_state(*block_position),
+        });
+        block_ticks.schedule_tick(*block_position, PRESS_DURATION);
+    }
+}
+
+fn release(
+    world_map: Res<WorldMap>,
+    block_variants: Res<BlockVariants>,
+    mut button_ticks: Query<&mut BlockTickEvents, (With<ButtonBlock>, Changed<BlockTickEvents>)>,
+    mut block_update_writer: MessageWriter<BlockUpdate>,
+) {
+    let Ok(mut ticks) = button_ticks.single_mut() else {
+        return;
+    };
+
+    for (block_position, _) in ticks.read() {
+        let Some(button) = world_map
+            .get_block(block_position)
+            .and_then(|block_id| block_variants.state("button", block_id))
+        else {
+            continue;
+        };
+
+        block_update_writer.write(BlockUpdate::Swap {
+            position: block_position,
+            block_id: button.with_bool("pressed", false).block_id(),
+            block_state: world_map.get_block_state(block_position),
+        });
+    }
+}
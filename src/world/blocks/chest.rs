@@ -4,16 +4,26 @@ use fmc::{
     bevy::ecs::system::EntityCommands,
     blocks::{BlockData, BlockPosition, Blocks},
     interfaces::{HeldInterfaceStack, InterfaceEvents, InterfaceSystems, RegisterInterfaceNode},
-    items::ItemStack,
+    items::{ItemStack, Items},
     networking::Server,
     players::Player,
     prelude::*,
     protocol::messages,
-    world::BlockUpdate,
+    random::Rng,
+    world::{BlockUpdate, WorldMap},
 };
 use serde::{Deserialize, Serialize};
 
-use crate::players::HandInteractions;
+use crate::{
+    items::{loot_tables::LootTables, tooltips::ItemTooltips},
+    players::{HandInteractions, Inventory},
+    settings::Settings,
+};
+
+use super::{
+    block_interface::{CloseBlockInterface, OpenBlockInterface},
+    container::{Container, Containers},
+};
 
 pub struct ChestPlugin;
 impl Plugin for ChestPlugin {
@@ -26,11 +36,25 @@ impl Plugin for ChestPlugin {
                     handle_block_hits,
                     handle_interface_events.in_set(InterfaceSystems::HandleEvents),
                     handle_despawn,
+                    close_interfaces,
+                    update_container,
+                    fill_loot_chests,
                 ),
             );
     }
 }
 
+/// The first slots of the player's inventory are the hotbar, they are left out of quick stacking.
+const HOTBAR_SIZE: usize = 9;
+
+/// Chests placed by terrain generation, and the loot table they are filled from. A loot chest is
+/// turned into a normal chest with loot in it the first time it is loaded.
+const LOOT_CHESTS: [(&str, &str); 3] = [
+    ("shipwreck_chest", "shipwreck"),
+    ("fishing_chest", "fishing_village"),
+    ("dungeon_chest", "dungeon"),
+];
+
 #[derive(Component, Serialize, Deserialize)]
 struct Chest {
     inventory: Vec<ItemStack>,
@@ -43,7 +67,7 @@ impl Chest {
         }
     }
 
-    fn build_interface(&self) -> messages::InterfaceItemBoxUpdate {
+    fn build_interface(&self, tooltips: &ItemTooltips) -> messages::InterfaceItemBoxUpdate {
         let mut item_box_update = messages::InterfaceItemBoxUpdate::default();
         for (i, item_stack) in self.inventory.iter().enumerate() {
             if !item_stack.is_empty() {
@@ -53,7 +77,7 @@ impl Chest {
                     item_stack.item().unwrap().id,
                     item_stack.size(),
                     None,
-                    None,
+                    tooltips.get(item_stack).as_deref(),
                 );
             } else {
                 item_box_update.add_empty_itembox("chest", i as u32);
@@ -62,6 +86,36 @@ impl Chest {
 
         item_box_update
     }
+
+    /// Move the items the chest already has some of out of the inventory and into the chest.
+    fn quick_stack(&mut self, inventory: &mut [ItemStack]) {
+        for item_stack in inventory.iter_mut() {
+            if item_stack.is_empty()
+                || !self
+                    .inventory
+                    .iter()
+                    .any(|stored| stored.item() == item_stack.item())
+            {
+                continue;
+            }
+
+            // Stacks of the same item are filled before the empty slots.
+            for slot in self.inventory.iter_mut() {
+                if slot.item() == item_stack.item() {
+                    item_stack.transfer_to(slot, u32::MAX);
+                }
+            }
+            for slot in self.inventory.iter_mut() {
+                if item_stack.is_empty() {
+                    break;
+                }
+
+                if slot.is_empty() {
+                    item_stack.transfer_to(slot, u32::MAX);
+                }
+            }
+        }
+    }
 }
 
 #[derive(Resource, Default)]
@@ -79,6 +133,14 @@ impl ChestRegistry {
         }
     }
 
+    fn remove_player(&mut self, player_entity: Entity) {
+        if let Some(chest_entity) = self.player_to_chest.remove(&player_entity)
+            && let Some(player_entities) = self.chest_to_players.get_mut(&chest_entity)
+        {
+            player_entities.remove(&player_entity);
+        }
+    }
+
     fn set_active_chest(&mut self, player_entity: Entity, crafting_table_entity: Entity) {
         if let Some(old_table_entity) = self.player_to_chest.remove(&player_entity) {
             self.chest_to_players
@@ -96,10 +158,24 @@ impl ChestRegistry {
     }
 }
 
+/// Marks a loot chest that has not been filled yet.
+#[derive(Component)]
+struct LootChest;
+
 fn setup(mut blocks: ResMut<Blocks>) {
     let block_id = blocks.get_id("chest");
     let block = blocks.get_config_mut(&block_id);
     block.set_spawn_function(spawn_function);
+
+    for (block_name, _) in LOOT_CHESTS {
+        let block_id = blocks.get_id(block_name);
+        let block = blocks.get_config_mut(&block_id);
+        block.set_spawn_function(loot_chest_spawn_function);
+    }
+}
+
+fn loot_chest_spawn_function(commands: &mut EntityCommands, _block_data: Option<&BlockData>) {
+    commands.insert(LootChest);
 }
 
 fn spawn_function(commands: &mut EntityCommands, block_data: Option<&BlockData>) {
@@ -110,13 +186,14 @@ fn spawn_function(commands: &mut EntityCommands, block_data: Option<&BlockData>)
         commands.insert(Chest::new());
     }
 
-    commands.insert(HandInteractions::default());
+    commands.insert((Container, HandInteractions::default()));
 }
 
 fn handle_interface_events(
     net: Res<Server>,
+    tooltips: ItemTooltips,
     registry: Res<ChestRegistry>,
-    mut player_query: Query<&mut HeldInterfaceStack, With<Player>>,
+    mut player_query: Query<(&mut HeldInterfaceStack, &mut Inventory), With<Player>>,
     mut input_events: Query<
         (Entity, &BlockPosition, &mut Chest, &mut InterfaceEvents),
         Changed<InterfaceEvents>,
@@ -125,9 +202,19 @@ fn handle_interface_events(
 ) {
     for (chest_entity, block_position, mut chest, mut events) in input_events.iter_mut() {
         for event in events.read() {
-            let mut held_item = player_query.get_mut(event.player_entity).unwrap();
+            // The interface has been closed, but the client can still send events for it.
+            if registry.player_to_chest.get(&event.player_entity) != Some(&chest_entity) {
+                continue;
+            }
+
+            let (mut held_item, mut inventory) = player_query.get_mut(event.player_entity).unwrap();
 
-            held_item.transfer(&event, &mut chest.inventory);
+            // The only button is the quick stack button, the chest is registered for it too.
+            if matches!(*event, messages::InterfaceInteraction::Button { .. }) {
+                chest.quick_stack(&mut inventory[HOTBAR_SIZE..]);
+            } else {
+                held_item.transfer(&event, &mut chest.inventory);
+            }
 
             block_update_writer.write(BlockUpdate::Data {
                 position: *block_position,
@@ -136,29 +223,45 @@ fn handle_interface_events(
 
             net.send_many(
                 &registry.chest_to_players[&chest_entity],
-                chest.build_interface(),
+                chest.build_interface(&tooltips),
             );
         }
     }
 }
 
 fn handle_block_hits(
+    mut commands: Commands,
     net: Res<Server>,
+    tooltips: ItemTooltips,
     mut registry: ResMut<ChestRegistry>,
-    mut block_hits: Query<(Entity, &Chest, &mut HandInteractions), Changed<HandInteractions>>,
+    mut block_hits: Query<
+        (Entity, &BlockPosition, &Chest, &mut HandInteractions),
+        Changed<HandInteractions>,
+    >,
     mut registration_events: MessageWriter<RegisterInterfaceNode>,
 ) {
-    for (chest_entity, chest, mut block_hits) in block_hits.iter_mut() {
+    for (chest_entity, block_position, chest, mut block_hits) in block_hits.iter_mut() {
         for player_entity in block_hits.read() {
             registry.set_active_chest(player_entity, chest_entity);
 
+            commands.entity(player_entity).insert(OpenBlockInterface {
+                block_entity: chest_entity,
+                block_position: *block_position,
+                interface_path: "chest",
+            });
+
             registration_events.write(RegisterInterfaceNode {
                 player_entity,
                 node_path: "chest".to_owned(),
                 node_entity: chest_entity,
             });
+            registration_events.write(RegisterInterfaceNode {
+                player_entity,
+                node_path: "chest/quick_stack".to_owned(),
+                node_entity: chest_entity,
+            });
 
-            net.send_one(player_entity, chest.build_interface());
+            net.send_one(player_entity, chest.build_interface(&tooltips));
 
             net.send_one(
                 player_entity,
@@ -179,3 +282,70 @@ fn handle_despawn(
         registry.remove_chest(chest_entity)
     }
 }
+
+fn close_interfaces(
+    mut registry: ResMut<ChestRegistry>,
+    mut close_events: MessageReader<CloseBlockInterface>,
+) {
+    for close_event in close_events.read() {
+        registry.remove_player(close_event.player_entity);
+    }
+}
+
+fn update_container(
+    mut containers: ResMut<Containers>,
+    chest_query: Query<(Entity, &BlockPosition, &Chest), Changed<Chest>>,
+) {
+    for (chest_entity, block_position, chest) in chest_query.iter() {
+        containers.set(chest_entity, *block_position, &chest.inventory);
+    }
+}
+
+// Replaces loot chests with normal chests filled from their loot table.
+fn fill_loot_chests(
+    items: Res<Items>,
+    settings: Res<Settings>,
+    world_map: Res<WorldMap>,
+    loot_tables: Res<LootTables>,
+    loot_chests: Query<&BlockPosition, Added<LootChest>>,
+    mut block_update_writer: MessageWriter<BlockUpdate>,
+) {
+    let blocks = Blocks::get();
+
+    for block_position in loot_chests.iter() {
+        let Some(block_id) = world_map.get_block(*block_position) else {
+            continue;
+        };
+
+        let block_name = &blocks.get_config(&block_id).name;
+        let Some((_, loot_table)) = LOOT_CHESTS.iter().find(|(name, _)| name == block_name) else {
+            continue;
+        };
+
+        // Seeded by position so the loot is the same no matter when the chest is first loaded.
+        let seed = ((block_position.x as u64) << 40
+            ^ (block_position.y as u64) << 20
+            ^ block_position.z as u32 as u64)
+            .overflowing_mul(settings.seed())
+            .0;
+        let mut rng = Rng::new(seed);
+
+        let Some(loot_table) = loot_tables.get(loot_table) else {
+            panic!(
+                "The loot chest '{}' is filled from the loot table '{}', but no loot table by \
+                that name exists.",
+                block_name, loot_table
+            );
+        };
+
+        let mut chest = Chest::new();
+        loot_table.fill(&items, &mut chest.inventory, &mut rng);
+
+        block_update_writer.write(BlockUpdate::Replace {
+            position: *block_position,
+            block_id: blocks.get_id("chest"),
+            block_state: world_map.get_block_state(*block_position),
+            block_data: Some(serde_json::to_vec(&chest).map(BlockData).unwrap()),
+        });
+    }
+}
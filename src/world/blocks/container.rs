@@ -0,0 +1,92 @@
+use std::collections::HashMap;
+
+use fmc::{
+    bevy::math::DVec3, blocks::BlockPosition, items::ItemStack, prelude::*, world::WorldMap,
+};
+
+use crate::items::DroppedItem;
+
+/// Block entities that hold items, like chests and furnaces, drop their items when the block is
+/// broken.
+///
+/// The items live in the block entity's own component, which is gone by the time its removal can
+/// be seen, so a copy of them is kept in [Containers] for each loaded container. Block entities
+/// are also removed when their chunk is unloaded, their items are then kept in the block data.
+pub struct ContainerPlugin;
+impl Plugin for ContainerPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(Containers::default())
+            .add_systems(Update, drop_contents);
+    }
+}
+
+/// Marks a block entity that holds items. Its items must be kept up to date in [Containers].
+#[derive(Component)]
+pub struct Container;
+
+struct ContainerContents {
+    position: BlockPosition,
+    item_stacks: Vec<ItemStack>,
+}
+
+/// Copy of the items held by each loaded container, so they can be dropped after the block entity
+/// has been removed.
+#[derive(Resource, Default)]
+pub struct Containers(HashMap<Entity, ContainerContents>);
+
+impl Containers {
+    /// Set the items held by the container, call this whenever they change.
+    pub fn set<'a>(
+        &mut self,
+        entity: Entity,
+        position: BlockPosition,
+        item_stacks: impl IntoIterator<Item = &'a ItemStack>,
+    ) {
+        self.0.insert(
+            entity,
+            ContainerContents {
+                position,
+                item_stacks: item_stacks
+                    .into_iter()
+                    .filter(|item_stack| !item_stack.is_empty())
+                    .cloned()
+                    .collect(),
+            },
+        );
+    }
+}
+
+fn drop_contents(
+    mut commands: Commands,
+    world_map: Res<WorldMap>,
+    mut containers: ResMut<Containers>,
+    container_query: Query<&BlockPosition, With<Container>>,
+    mut removed_containers: RemovedComponents<Container>,
+) {
+    for container_entity in removed_containers.read() {
+        let Some(contents) = containers.0.remove(&container_entity) else {
+            continue;
+        };
+
+        // Unloaded along with its chunk
+        if world_map.get_block(contents.position).is_none() {
+            continue;
+        }
+
+        // Replaced by another container, e.g. when a schematic is pasted over it. The new block
+        // brings its own block data, so the old items are gone along with the old block.
+        if container_query
+            .iter()
+            .any(|position| *position == contents.position)
+        {
+            continue;
+        }
+
+        for item_stack in contents.item_stacks {
+            commands.spawn((
+                DroppedItem::new(item_stack),
+                Transform::from_translation(contents.position.as_dvec3() + DVec3::splat(0.5)),
+            ));
+        }
+    }
+}
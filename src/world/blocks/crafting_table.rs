@@ -1,22 +1,26 @@
-use std::collections::{HashMap, HashSet};
+use std::collections::HashMap;
 
 use fmc::{
-    bevy::ecs::system::EntityCommands,
-    blocks::{BlockData, Blocks},
+    bevy::{ecs::system::EntityCommands, math::DVec3},
+    blocks::{BlockData, BlockPosition, Blocks},
     interfaces::{HeldInterfaceStack, InterfaceEvents, InterfaceSystems, RegisterInterfaceNode},
-    items::{ItemStack, Items},
     networking::Server,
     players::Player,
     prelude::*,
     protocol::messages,
 };
-use serde::{Deserialize, Serialize};
 
 use crate::{
-    items::crafting::{CraftingGrid, Recipes},
-    players::HandInteractions,
+    items::{
+        DroppedItem,
+        crafting::{CraftEvent, CraftingGrid, Recipes},
+        tooltips::ItemTooltips,
+    },
+    players::{HandInteractions, Inventory, SentItemBoxes},
 };
 
+use super::block_interface::{BlockInterfaceSystems, CloseBlockInterface, OpenBlockInterface};
+
 pub struct CraftingTablePlugin;
 impl Plugin for CraftingTablePlugin {
     fn build(&self, app: &mut App) {
@@ -27,82 +31,78 @@ impl Plugin for CraftingTablePlugin {
                 (
                     handle_block_hits,
                     handle_interface_events.in_set(InterfaceSystems::HandleEvents),
-                    handle_despawn,
+                    return_items.after(BlockInterfaceSystems),
                 ),
             );
     }
 }
 
-#[derive(Component, Deref, DerefMut, Serialize, Deserialize)]
-struct CraftingTable(CraftingGrid);
-
-impl CraftingTable {
-    fn build_input_interface(&self, interface_update: &mut messages::InterfaceItemBoxUpdate) {
-        for (i, item_stack) in self.iter().enumerate() {
-            if let Some(item) = item_stack.item() {
-                interface_update.add_itembox(
-                    "crafting_table/input",
-                    i as u32,
-                    item.id,
-                    item_stack.size(),
-                    None,
-                    None,
-                );
-            } else {
-                interface_update.add_empty_itembox("crafting_table/input", i as u32);
-            }
-        }
-    }
+/// The crafting table block. The items in its grid belong to the player using it, so each player
+/// has their own grid. It is kept in the [CraftingTableRegistry].
+#[derive(Component)]
+struct CraftingTable;
 
-    fn build_output_interface(
-        &self,
-        recipes: &Recipes,
-        interface_update: &mut messages::InterfaceItemBoxUpdate,
-    ) {
-        if let Some(output) = recipes.get("crafting").get_output(self) {
+fn build_input_interface(
+    crafting_grid: &CraftingGrid,
+    tooltips: &ItemTooltips,
+    interface_update: &mut messages::InterfaceItemBoxUpdate,
+) {
+    for (i, item_stack) in crafting_grid.iter().enumerate() {
+        if let Some(item) = item_stack.item() {
             interface_update.add_itembox(
-                "crafting_table/output",
-                0,
-                output.item().unwrap().id,
-                output.capacity(),
-                None,
+                "crafting_table/input",
+                i as u32,
+                item.id,
+                item_stack.size(),
                 None,
+                tooltips.get(item_stack).as_deref(),
             );
         } else {
-            interface_update.add_empty_itembox("crafting_table/output", 0);
+            interface_update.add_empty_itembox("crafting_table/input", i as u32);
         }
     }
 }
 
+fn build_output_interface(
+    crafting_grid: &CraftingGrid,
+    recipes: &Recipes,
+    tooltips: &ItemTooltips,
+    interface_update: &mut messages::InterfaceItemBoxUpdate,
+) {
+    if let Some(output) = recipes.get("crafting").get_output(crafting_grid) {
+        interface_update.add_itembox(
+            "crafting_table/output",
+            0,
+            output.item().unwrap().id,
+            output.capacity(),
+            None,
+            tooltips.get(&output).as_deref(),
+        );
+    } else {
+        interface_update.add_empty_itembox("crafting_table/output", 0);
+    }
+}
+
 #[derive(Resource, Default)]
 struct CraftingTableRegistry {
-    table_to_players: HashMap<Entity, HashSet<Entity>>,
     player_to_table: HashMap<Entity, Entity>,
+    // The grid of each player that is using a crafting table. It is kept until the interface is
+    // closed, so the items are still there if they open another table.
+    player_to_grid: HashMap<Entity, CraftingGrid>,
 }
 
 impl CraftingTableRegistry {
-    fn remove_table(&mut self, crafting_table_entity: Entity) {
-        if let Some(player_entities) = self.table_to_players.remove(&crafting_table_entity) {
-            for entity in player_entities {
-                self.player_to_table.remove(&entity);
-            }
-        }
-    }
-
     fn set_active_table(&mut self, player_entity: Entity, crafting_table_entity: Entity) {
-        if let Some(old_table_entity) = self.player_to_table.remove(&player_entity) {
-            self.table_to_players
-                .get_mut(&old_table_entity)
-                .unwrap()
-                .remove(&player_entity);
-        }
-
-        self.table_to_players
-            .entry(crafting_table_entity)
-            .or_default()
-            .insert(player_entity);
         self.player_to_table
             .insert(player_entity, crafting_table_entity);
+        self.player_to_grid
+            .entry(player_entity)
+            .or_insert_with(|| CraftingGrid::with_size(9));
+    }
+
+    fn remove_player(&mut self, player_entity: Entity) -> Option<CraftingGrid> {
+        self.player_to_table.remove(&player_entity);
+        self.player_to_grid.remove(&player_entity)
     }
 }
 
@@ -112,48 +112,84 @@ fn setup(mut blocks: ResMut<Blocks>) {
     block.set_spawn_function(spawn_function);
 }
 
-fn spawn_function(commands: &mut EntityCommands, block_data: Option<&BlockData>) {
-    if let Some(block_data) = block_data {
-        let crafting_table: CraftingTable = bincode::deserialize(&*block_data).unwrap();
-        commands.insert(crafting_table);
-    } else {
-        commands.insert(CraftingTable(CraftingGrid::with_size(9)));
-    }
-
-    commands.insert(HandInteractions::default());
+fn spawn_function(commands: &mut EntityCommands, _block_data: Option<&BlockData>) {
+    commands.insert((CraftingTable, HandInteractions::default()));
 }
 
 fn handle_interface_events(
     net: Res<Server>,
-    registry: Res<CraftingTableRegistry>,
+    tooltips: ItemTooltips,
+    mut registry: ResMut<CraftingTableRegistry>,
     recipes: Res<Recipes>,
-    mut player_query: Query<&mut HeldInterfaceStack, With<Player>>,
+    mut player_query: Query<
+        (&mut HeldInterfaceStack, &mut Inventory, &mut SentItemBoxes),
+        With<Player>,
+    >,
     mut input_events: Query<
-        (Entity, &mut CraftingTable, &mut InterfaceEvents),
-        Changed<InterfaceEvents>,
+        (Entity, &mut InterfaceEvents),
+        (Changed<InterfaceEvents>, With<CraftingTable>),
     >,
+    mut craft_events: MessageWriter<CraftEvent>,
 ) {
-    for (crafting_table_entity, mut crafting_table, mut events) in input_events.iter_mut() {
+    let registry = &mut *registry;
+
+    for (crafting_table_entity, mut events) in input_events.iter_mut() {
         for event in events.read() {
-            let mut held_item = player_query.get_mut(event.player_entity).unwrap();
+            // The interface has been closed, but the client can still send events for it.
+            if registry.player_to_table.get(&event.player_entity) != Some(&crafting_table_entity) {
+                continue;
+            }
+            let crafting_grid = registry
+                .player_to_grid
+                .get_mut(&event.player_entity)
+                .unwrap();
+
+            let (mut held_item, mut inventory, mut sent) =
+                player_query.get_mut(event.player_entity).unwrap();
 
             let mut interface_update = messages::InterfaceItemBoxUpdate::default();
 
-            if let messages::InterfaceInteraction::TakeItem {
+            // The only button is the craft all button, the crafting table is registered for it
+            // too. As much as fits is crafted straight into the inventory.
+            if matches!(*event, messages::InterfaceInteraction::Button { .. }) {
+                let Some(mut item_stack) = recipes
+                    .get("crafting")
+                    .craft_to_fit(crafting_grid, &inventory)
+                else {
+                    continue;
+                };
+
+                craft_events.write(CraftEvent {
+                    player_entity: event.player_entity,
+                    collection: "crafting",
+                    item_id: item_stack.item().unwrap().id,
+                    amount: item_stack.size(),
+                });
+                inventory.insert(&mut item_stack);
+
+                build_input_interface(crafting_grid, &tooltips, &mut interface_update);
+                build_output_interface(crafting_grid, &recipes, &tooltips, &mut interface_update);
+                sent.add_inventory(&mut interface_update, &inventory, &tooltips);
+            } else if let messages::InterfaceInteraction::TakeItem {
                 interface_path,
                 index,
                 quantity,
             } = &*event
             {
                 if interface_path.ends_with("input") {
-                    let Some(item_stack) = crafting_table.get_mut(*index as usize) else {
+                    let Some(item_stack) = crafting_grid.get_mut(*index as usize) else {
                         continue;
                     };
                     item_stack.transfer_to(&mut held_item, *quantity);
 
-                    crafting_table.build_output_interface(&recipes, &mut interface_update);
+                    build_output_interface(
+                        crafting_grid,
+                        &recipes,
+                        &tooltips,
+                        &mut interface_update,
+                    );
                 } else if interface_path.ends_with("output") {
-                    let Some(output) = recipes.get("crafting").get_output(&crafting_table) else {
+                    let Some(output) = recipes.get("crafting").get_output(crafting_grid) else {
                         continue;
                     };
 
@@ -165,15 +201,26 @@ fn handle_interface_events(
                         };
 
                         if let Some(mut item_stack) =
-                            recipes.get("crafting").craft(&mut crafting_table, amount)
+                            recipes.get("crafting").craft(crafting_grid, amount)
                         {
+                            craft_events.write(CraftEvent {
+                                player_entity: event.player_entity,
+                                collection: "crafting",
+                                item_id: item_stack.item().unwrap().id,
+                                amount: item_stack.size(),
+                            });
                             item_stack.transfer_to(&mut held_item, u32::MAX);
                         } else {
                             continue;
                         }
 
-                        crafting_table.build_input_interface(&mut interface_update);
-                        crafting_table.build_output_interface(&recipes, &mut interface_update);
+                        build_input_interface(crafting_grid, &tooltips, &mut interface_update);
+                        build_output_interface(
+                            crafting_grid,
+                            &recipes,
+                            &tooltips,
+                            &mut interface_update,
+                        );
                     }
                 }
             } else if let messages::InterfaceInteraction::PlaceItem {
@@ -186,38 +233,43 @@ fn handle_interface_events(
                     continue;
                 }
 
-                let Some(item_stack) = crafting_table.get_mut(*index as usize) else {
+                let Some(item_stack) = crafting_grid.get_mut(*index as usize) else {
                     continue;
                 };
                 held_item.transfer_to(item_stack, *quantity);
 
-                crafting_table.build_output_interface(&recipes, &mut interface_update);
+                build_output_interface(crafting_grid, &recipes, &tooltips, &mut interface_update);
             }
 
             if !interface_update.updates.is_empty() {
-                net.send_many(
-                    &registry.table_to_players[&crafting_table_entity],
-                    interface_update,
-                );
+                net.send_one(event.player_entity, interface_update);
             }
         }
     }
 }
 
 fn handle_block_hits(
+    mut commands: Commands,
     net: Res<Server>,
+    tooltips: ItemTooltips,
     mut registry: ResMut<CraftingTableRegistry>,
     recipes: Res<Recipes>,
     mut block_hits: Query<
-        (Entity, &CraftingTable, &mut HandInteractions),
-        Changed<HandInteractions>,
+        (Entity, &BlockPosition, &mut HandInteractions),
+        (Changed<HandInteractions>, With<CraftingTable>),
     >,
     mut registration_events: MessageWriter<RegisterInterfaceNode>,
 ) {
-    for (crafting_table_entity, crafting_table, mut block_hits) in block_hits.iter_mut() {
+    for (crafting_table_entity, block_position, mut block_hits) in block_hits.iter_mut() {
         for player_entity in block_hits.read() {
             registry.set_active_table(player_entity, crafting_table_entity);
 
+            commands.entity(player_entity).insert(OpenBlockInterface {
+                block_entity: crafting_table_entity,
+                block_position: *block_position,
+                interface_path: "crafting_table",
+            });
+
             registration_events.write(RegisterInterfaceNode {
                 player_entity,
                 node_path: String::from("crafting_table/input"),
@@ -228,10 +280,16 @@ fn handle_block_hits(
                 node_path: String::from("crafting_table/output"),
                 node_entity: crafting_table_entity,
             });
+            registration_events.write(RegisterInterfaceNode {
+                player_entity,
+                node_path: String::from("crafting_table/craft_all"),
+                node_entity: crafting_table_entity,
+            });
 
+            let crafting_grid = &registry.player_to_grid[&player_entity];
             let mut itembox_update = messages::InterfaceItemBoxUpdate::default();
-            crafting_table.build_input_interface(&mut itembox_update);
-            crafting_table.build_output_interface(&recipes, &mut itembox_update);
+            build_input_interface(crafting_grid, &tooltips, &mut itembox_update);
+            build_output_interface(crafting_grid, &recipes, &tooltips, &mut itembox_update);
             net.send_one(player_entity, itembox_update);
 
             net.send_one(
@@ -245,17 +303,34 @@ fn handle_block_hits(
     }
 }
 
-fn save_state(mut table_query: Query<(&CraftingTable, &mut BlockData), Changed<CraftingTable>>) {
-    for (crafting_table, mut block_data) in table_query.iter_mut() {
-        *block_data = bincode::serialize(crafting_table).map(BlockData).unwrap();
-    }
-}
-
-fn handle_despawn(
+// When the interface is closed the items in the grid are put back in the player's inventory.
+// What doesn't fit is dropped at the crafting table.
+fn return_items(
+    mut commands: Commands,
     mut registry: ResMut<CraftingTableRegistry>,
-    mut despawned_tables: RemovedComponents<CraftingTable>,
+    mut inventory_query: Query<&mut Inventory, With<Player>>,
+    mut close_events: MessageReader<CloseBlockInterface>,
 ) {
-    for crafting_table_entity in despawned_tables.read() {
-        registry.remove_table(crafting_table_entity)
+    for close_event in close_events.read() {
+        let Some(mut crafting_grid) = registry.remove_player(close_event.player_entity) else {
+            continue;
+        };
+
+        let mut inventory = inventory_query.get_mut(close_event.player_entity).ok();
+
+        for mut item_stack in crafting_grid.drain(..) {
+            if let Some(inventory) = inventory.as_mut() {
+                inventory.insert(&mut item_stack);
+            }
+
+            if !item_stack.is_empty() {
+                commands.spawn((
+                    DroppedItem::new(item_stack),
+                    Transform::from_translation(
+                        close_event.block_position.as_dvec3() + DVec3::splat(0.5),
+                    ),
+                ));
+            }
+        }
     }
 }
@@ -1,48 +1,383 @@
 use fmc::{
-    bevy::math::DQuat,
-    blocks::{BlockData, Blocks},
+    bevy::math::{DQuat, DVec3},
+    blocks::{BlockData, BlockId, BlockPosition, BlockRotation, Blocks},
+    items::{ItemStack, Items},
+    networking::Server,
     prelude::*,
+    protocol::messages,
+    random::Rng,
+    world::{BlockUpdate, ChunkSubscriptions, WorldMap, chunk::ChunkPosition},
 };
 
-use crate::players::HandInteractions;
+use crate::{
+    items::DroppedItem,
+    players::HandInteractions,
+    world::block_variants::{BlockStateBuilder, BlockVariantSystems, BlockVariants},
+};
+
+use super::{
+    attached::{AttachedBlocks, Support},
+    multi_block::MultiBlocks,
+    power::{Power, PowerEvents, PowerSystems},
+};
 
+/// Doors open and close when right clicked or powered. Iron doors can only be opened by power.
+///
+/// Doors are two blocks tall, the bottom half is the door itself and the top half only takes up
+/// the space above it. The halves are kept together by [MultiBlocks]. The top half has an open
+/// and a closed variant with hitboxes that follow the door, and it is what remembers if the door
+/// was left open when the chunk is unloaded.
+///
+/// Two doors next to each other facing the same way are a double door, and open and close
+/// together. Wooden doors can be broken down by mobs hitting them with [BangOnDoor].
 pub struct DoorPlugin;
 impl Plugin for DoorPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(Startup, setup)
-            .add_systems(Update, handle_block_hits);
+        app.add_message::<BangOnDoor>()
+            .add_systems(Startup, setup.after(BlockVariantSystems))
+            .add_systems(
+                Update,
+                (
+                    open_loaded_doors,
+                    handle_block_hits,
+                    handle_power.after(PowerSystems),
+                    bang_on_doors,
+                ),
+            );
     }
 }
 
+/// Block families of the top halves, each has a closed and an open variant
+const DOOR_TOPS: [&str; 2] = ["oak_door_top", "iron_door_top"];
+
+/// How many times a wooden door can be hit before it breaks
+const DOOR_STRENGTH: u32 = 10;
+
+/// Hit the door at the position. Wooden doors break after being hit enough times, other doors
+/// ignore it.
+#[derive(Message)]
+pub struct BangOnDoor {
+    pub position: BlockPosition,
+}
+
 #[derive(Component)]
 struct Door {
     open: bool,
+    wooden: bool,
+    // How many times the door has been banged on
+    damage: u32,
+}
+
+impl Door {
+    fn set_open(&mut self, open: bool, transform: &mut Transform) {
+        if self.open == open {
+            return;
+        }
+
+        if open {
+            transform.rotate(DQuat::from_rotation_y(std::f64::consts::FRAC_PI_2));
+        } else {
+            transform.rotate(DQuat::from_rotation_y(-std::f64::consts::FRAC_PI_2));
+        }
+        self.open = open;
+    }
+}
+
+/// The top half of a wooden door, clicking it opens the door below.
+#[derive(Component)]
+struct DoorTop;
+
+/// Handles the power events of all doors
+#[derive(Component)]
+struct DoorPower;
+
+fn setup(
+    mut commands: Commands,
+    mut blocks: ResMut<Blocks>,
+    mut attached_blocks: ResMut<AttachedBlocks>,
+    mut multi_blocks: ResMut<MultiBlocks>,
+    mut power: ResMut<Power>,
+    block_variants: Res<BlockVariants>,
+) {
+    let handler = commands.spawn((PowerEvents::default(), DoorPower)).id();
+
+    for (name, top_family, spawn_function) in [
+        (
+            "oak door",
+            "oak_door_top",
+            wooden_door as fn(&mut EntityCommands, Option<&BlockData>),
+        ),
+        ("iron door", "iron_door_top", iron_door),
+    ] {
+        let block_id = blocks.get_id(name);
+        let top = block_variants.builder(top_family);
+        let top_id = top.block_id();
+        let top_open_id = top.with_bool("open", true).block_id();
+        attached_blocks.register(block_id, Support::Below);
+        multi_blocks.register(&[(block_id, IVec3::ZERO), (top_id, IVec3::Y)]);
+        multi_blocks.register_alternative(top_id, top_open_id);
+        power.register_consumer(block_id, handler);
+
+        let block = blocks.get_config_mut(&block_id);
+        block.set_spawn_function(spawn_function);
+    }
+
+    for open in [false, true] {
+        let oak_door_top = block_variants
+            .builder("oak_door_top")
+            .with_bool("open", open)
+            .block_id();
+        blocks
+            .get_config_mut(&oak_door_top)
+            .set_spawn_function(wooden_door_top);
+    }
+}
+
+fn wooden_door(commands: &mut EntityCommands, _block_data: Option<&BlockData>) {
+    commands.insert((
+        Door {
+            open: false,
+            wooden: true,
+            damage: 0,
+        },
+        HandInteractions::default(),
+    ));
 }
 
-fn setup(mut blocks: ResMut<Blocks>) {
-    let block_id = blocks.get_id("oak door");
-    let block = blocks.get_config_mut(&block_id);
-    block.set_spawn_function(spawn_function);
+fn wooden_door_top(commands: &mut EntityCommands, _block_data: Option<&BlockData>) {
+    commands.insert((DoorTop, HandInteractions::default()));
 }
 
-fn spawn_function(commands: &mut EntityCommands, _block_data: Option<&BlockData>) {
-    commands.insert((Door { open: false }, HandInteractions::default()));
+// Iron doors don't react to being clicked, they are opened with levers and buttons.
+fn iron_door(commands: &mut EntityCommands, _block_data: Option<&BlockData>) {
+    commands.insert(Door {
+        open: false,
+        wooden: false,
+        damage: 0,
+    });
+}
+
+/// The other half of the double door the door at the position is part of. It is the door next
+/// to it that is the same kind of door and faces the same way.
+fn double_door(world_map: &WorldMap, position: BlockPosition) -> Option<BlockPosition> {
+    let block_id = world_map.get_block(position)?;
+    let rotation = world_map
+        .get_block_state(position)
+        .and_then(|state| state.rotation());
+
+    // The doors stand side by side, along the width of the door.
+    let sideways = match rotation {
+        Some(BlockRotation::Front) | Some(BlockRotation::Back) | None => IVec3::X,
+        Some(BlockRotation::Right) | Some(BlockRotation::Left) => IVec3::Z,
+    };
+
+    [position + sideways, position - sideways]
+        .into_iter()
+        .find(|neighbour| {
+            world_map.get_block(*neighbour) == Some(block_id)
+                && world_map
+                    .get_block_state(*neighbour)
+                    .and_then(|state| state.rotation())
+                    == rotation
+        })
+}
+
+/// The top half of the door at the position
+fn door_top<'a>(
+    world_map: &WorldMap,
+    block_variants: &'a BlockVariants,
+    position: BlockPosition,
+) -> Option<BlockStateBuilder<'a>> {
+    let block_id = world_map.get_block(position + IVec3::Y)?;
+    DOOR_TOPS
+        .iter()
+        .find_map(|family| block_variants.state(family, block_id))
+}
+
+fn set_door_open(
+    world_map: &WorldMap,
+    block_variants: &BlockVariants,
+    doors: &mut Query<(&BlockPosition, &mut Door, &mut Transform)>,
+    block_update_writer: &mut MessageWriter<BlockUpdate>,
+    position: BlockPosition,
+    open: bool,
+) {
+    let double = double_door(world_map, position);
+    for (block_position, mut door, mut transform) in doors.iter_mut() {
+        if *block_position != position && Some(*block_position) != double {
+            continue;
+        }
+
+        door.set_open(open, &mut transform);
+
+        // The top half is swapped for the variant whose hitbox matches the door.
+        if let Some(top) = door_top(world_map, block_variants, *block_position)
+            && top.get_bool("open") != open
+        {
+            let top_position = *block_position + IVec3::Y;
+            block_update_writer.write(BlockUpdate::Swap {
+                position: top_position,
+                block_id: top.with_bool("open", open).block_id(),
+                block_state: world_map.get_block_state(top_position),
+            });
+        }
+    }
+}
+
+// Doors are always closed when spawned, open them again if they were left open.
+fn open_loaded_doors(
+    world_map: Res<WorldMap>,
+    block_variants: Res<BlockVariants>,
+    mut doors: Query<(&BlockPosition, &mut Door, &mut Transform), Added<Door>>,
+) {
+    for (block_position, mut door, mut transform) in doors.iter_mut() {
+        if door_top(&world_map, &block_variants, *block_position)
+            .is_some_and(|top| top.get_bool("open"))
+        {
+            door.set_open(true, &mut transform);
+        }
+    }
 }
 
 fn handle_block_hits(
-    mut block_hits: Query<
-        (Entity, &mut Door, &mut HandInteractions, &mut Transform),
-        Changed<HandInteractions>,
+    world_map: Res<WorldMap>,
+    block_variants: Res<BlockVariants>,
+    mut block_hits: Query<(Entity, &mut HandInteractions), (With<Door>, Changed<HandInteractions>)>,
+    mut top_hits: Query<
+        (&BlockPosition, &mut HandInteractions),
+        (With<DoorTop>, Without<Door>, Changed<HandInteractions>),
     >,
+    mut doors: Query<(&BlockPosition, &mut Door, &mut Transform)>,
+    mut block_update_writer: MessageWriter<BlockUpdate>,
 ) {
-    for (_entity, mut door, mut interactions, mut transform) in block_hits.iter_mut() {
+    let mut clicked = Vec::new();
+
+    for (entity, mut interactions) in block_hits.iter_mut() {
         for _interaction in interactions.read() {
-            if door.open {
-                transform.rotate(DQuat::from_rotation_y(-std::f64::consts::FRAC_PI_2));
-            } else {
-                transform.rotate(DQuat::from_rotation_y(std::f64::consts::FRAC_PI_2));
+            if let Ok((block_position, _, _)) = doors.get(entity) {
+                clicked.push(*block_position);
             }
-            door.open = !door.open;
         }
     }
+
+    for (block_position, mut interactions) in top_hits.iter_mut() {
+        for _interaction in interactions.read() {
+            clicked.push(*block_position - IVec3::Y);
+        }
+    }
+
+    for block_position in clicked {
+        let Some(open) = doors
+            .iter()
+            .find(|(position, _, _)| **position == block_position)
+            .map(|(_, door, _)| !door.open)
+        else {
+            continue;
+        };
+        set_door_open(
+            &world_map,
+            &block_variants,
+            &mut doors,
+            &mut block_update_writer,
+            block_position,
+            open,
+        );
+    }
+}
+
+// Powered doors open, and close again when the power is turned off.
+fn handle_power(
+    world_map: Res<WorldMap>,
+    block_variants: Res<BlockVariants>,
+    mut power_events: Query<&mut PowerEvents, (With<DoorPower>, Changed<PowerEvents>)>,
+    mut doors: Query<(&BlockPosition, &mut Door, &mut Transform)>,
+    mut block_update_writer: MessageWriter<BlockUpdate>,
+) {
+    let Ok(mut events) = power_events.single_mut() else {
+        return;
+    };
+
+    for (block_position, powered) in events.read() {
+        set_door_open(
+            &world_map,
+            &block_variants,
+            &mut doors,
+            &mut block_update_writer,
+            block_position,
+            powered,
+        );
+    }
+}
+
+fn bang_on_doors(
+    mut commands: Commands,
+    net: Res<Server>,
+    items: Res<Items>,
+    world_map: Res<WorldMap>,
+    chunk_subscriptions: Res<ChunkSubscriptions>,
+    mut doors: Query<(&BlockPosition, &mut Door)>,
+    mut bang_events: MessageReader<BangOnDoor>,
+    mut block_update_writer: MessageWriter<BlockUpdate>,
+    mut rng: Local<Rng>,
+) {
+    let blocks = Blocks::get();
+
+    for bang in bang_events.read() {
+        let Some((_, mut door)) = doors
+            .iter_mut()
+            .find(|(position, _)| **position == bang.position)
+        else {
+            continue;
+        };
+
+        // Open doors are already out of the way
+        if !door.wooden || door.open {
+            continue;
+        }
+
+        let Some(block_id) = world_map.get_block(bang.position) else {
+            continue;
+        };
+        let block_config = blocks.get_config(&block_id);
+        let position = bang.position.as_dvec3() + DVec3::splat(0.5);
+
+        if let Some(subscribers) =
+            chunk_subscriptions.get_subscribers(&ChunkPosition::from(bang.position))
+            && let Some(hit_sound) = block_config.sound.hit(&mut rng)
+        {
+            net.send_many(
+                subscribers,
+                messages::Sound {
+                    position: Some(position),
+                    volume: 1.0,
+                    speed: 0.5,
+                    sound: hit_sound.to_owned(),
+                },
+            );
+        }
+
+        door.damage += 1;
+        if door.damage < DOOR_STRENGTH {
+            continue;
+        }
+
+        break_door(&mut commands, &items, block_id, bang.position);
+        block_update_writer.write(BlockUpdate::Replace {
+            position: bang.position,
+            block_id: blocks.get_id("air"),
+            block_state: None,
+            block_data: None,
+        });
+    }
+}
+
+fn break_door(commands: &mut Commands, items: &Items, block_id: BlockId, position: BlockPosition) {
+    let Some(item_id) = Blocks::get().get_config(&block_id).drop(None) else {
+        return;
+    };
+
+    commands.spawn((
+        DroppedItem::new(ItemStack::new(items.get_config(&item_id), 1)),
+        Transform::from_translation(position.as_dvec3() + DVec3::splat(0.5)),
+    ));
 }
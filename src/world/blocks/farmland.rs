@@ -0,0 +1,198 @@
+use std::collections::HashSet;
+
+use fmc::{
+    bevy::math::DVec3,
+    blocks::{BlockId, BlockPosition, Blocks},
+    items::{ItemStack, Items},
+    prelude::*,
+    world::{BlockUpdate, WorldMap},
+};
+
+use crate::{
+    items::DroppedItem,
+    players::WalkOverEvent,
+    weather::Weather,
+    world::{
+        block_ticks::{BlockTickEvents, BlockTickSystems, BlockTicks},
+        block_variants::{BlockVariantSystems, BlockVariants},
+        has_sky_access,
+    },
+};
+
+/// The soil crops are planted in, made by tilling dirt with a hoe.
+///
+/// Soil gets wet when there is water close by or it is rained on, and dries out again when there
+/// isn't. Crops grow faster on wet soil. Dry soil with nothing planted in it slowly turns back
+/// into dirt, and soil that is jumped on is trampled back into dirt at once, breaking the crop.
+pub struct FarmlandPlugin;
+impl Plugin for FarmlandPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Startup, setup.after(BlockVariantSystems))
+            .add_systems(
+                Update,
+                (update_moisture.after(BlockTickSystems), trample_soil),
+            );
+    }
+}
+
+/// Water within this many blocks horizontally keeps the soil wet.
+const WATER_RANGE: i32 = 4;
+
+#[derive(Component)]
+struct Farmland {
+    water: HashSet<BlockId>,
+}
+
+fn setup(
+    mut commands: Commands,
+    block_variants: Res<BlockVariants>,
+    mut block_ticks: ResMut<BlockTicks>,
+) {
+    let blocks = Blocks::get();
+
+    let water = blocks
+        .configs()
+        .iter()
+        .filter(|config| config.name.contains("water"))
+        .map(|config| blocks.get_id(&config.name))
+        .collect();
+
+    let handler = commands
+        .spawn((BlockTickEvents::default(), Farmland { water }))
+        .id();
+
+    for wet in [false, true] {
+        let block_id = block_variants
+            .builder("soil")
+            .with_bool("moisture", wet)
+            .block_id();
+        block_ticks.register(block_id, handler, true);
+    }
+}
+
+fn is_watered(
+    world_map: &WorldMap,
+    weather: &Weather,
+    water: &HashSet<BlockId>,
+    position: BlockPosition,
+) -> bool {
+    if weather.is_thunderstorm() && has_sky_access(world_map, position) {
+        return true;
+    }
+
+    // Water at the same level as the soil, or the level above it
+    for x in -WATER_RANGE..=WATER_RANGE {
+        for z in -WATER_RANGE..=WATER_RANGE {
+            for y in 0..=1 {
+                if world_map
+                    .get_block(position + IVec3::new(x, y, z))
+                    .is_some_and(|block_id| water.contains(&block_id))
+                {
+                    return true;
+                }
+            }
+        }
+    }
+
+    false
+}
+
+fn update_moisture(
+    world_map: Res<WorldMap>,
+    weather: Res<Weather>,
+    block_variants: Res<BlockVariants>,
+    mut farmland_ticks: Query<(&mut BlockTickEvents, &Farmland), Changed<BlockTickEvents>>,
+    mut block_update_writer: MessageWriter<BlockUpdate>,
+) {
+    let Ok((mut ticks, farmland)) = farmland_ticks.single_mut() else {
+        return;
+    };
+
+    let blocks = Blocks::get();
+    let air = blocks.get_id("air");
+
+    for (block_position, _) in ticks.read() {
+        let Some(block_id) = world_map.get_block(block_position) else {
+            continue;
+        };
+
+        let Some(soil) = block_variants.state("soil", block_id) else {
+            continue;
+        };
+
+        let was_wet = soil.get_bool("moisture");
+        let is_wet = is_watered(&world_map, &weather, &farmland.water, block_position);
+
+        if was_wet != is_wet {
+            block_update_writer.write(BlockUpdate::Swap {
+                position: block_position,
+                block_id: soil.with_bool("moisture", is_wet).block_id(),
+                block_state: None,
+            });
+        } else if !is_wet && world_map.get_block(block_position + IVec3::Y) == Some(air) {
+            block_update_writer.write(BlockUpdate::Replace {
+                position: block_position,
+                block_id: blocks.get_id("dirt"),
+                block_state: None,
+                block_data: None,
+            });
+        }
+    }
+}
+
+fn trample_soil(
+    mut commands: Commands,
+    items: Res<Items>,
+    world_map: Res<WorldMap>,
+    block_variants: Res<BlockVariants>,
+    mut walk_over_events: MessageReader<WalkOverEvent>,
+    mut block_update_writer: MessageWriter<BlockUpdate>,
+) {
+    let blocks = Blocks::get();
+
+    for walk_over_event in walk_over_events.read() {
+        // Only jumping or falling onto soil tramples it, walking over it is fine.
+        if walk_over_event.fall_distance == 0
+            || block_variants
+                .state("soil", walk_over_event.block_id)
+                .is_none()
+        {
+            continue;
+        }
+
+        let position = walk_over_event.position;
+
+        block_update_writer.write(BlockUpdate::Replace {
+            position,
+            block_id: blocks.get_id("dirt"),
+            block_state: None,
+            block_data: None,
+        });
+
+        let above = position + IVec3::Y;
+        let Some(crop_id) = world_map.get_block(above) else {
+            continue;
+        };
+
+        if block_variants.state("wheat", crop_id).is_none() {
+            continue;
+        }
+
+        block_update_writer.write(BlockUpdate::Replace {
+            position: above,
+            block_id: blocks.get_id("air"),
+            block_state: None,
+            block_data: None,
+        });
+
+        let Some(dropped_item_id) = blocks.get_config(&crop_id).drop(None) else {
+            continue;
+        };
+
+        let item_config = items.get_config(&dropped_item_id);
+        commands.spawn((
+            DroppedItem::new(ItemStack::new(item_config, 1)),
+            Transform::from_translation(above.as_dvec3() + DVec3::splat(0.5)),
+        ));
+    }
+}
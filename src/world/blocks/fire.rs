@@ -0,0 +1,147 @@
+use std::time::Duration;
+
+use fmc::{
+    blocks::{BlockId, BlockPosition, Blocks},
+    prelude::*,
+    random::{Rng, UniformDistribution},
+    world::{BlockUpdate, ChangedBlockEvent, WorldMap},
+};
+
+use crate::world::block_ticks::{BlockTickEvents, BlockTickSystems, BlockTicks};
+
+/// Fire burns away the flammable block beneath it, spreading to flammable blocks nearby before it
+/// goes out.
+pub struct FirePlugin;
+impl Plugin for FirePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Startup, setup).add_systems(
+            Update,
+            (schedule_burning, burn.after(BlockTickSystems)).chain(),
+        );
+    }
+}
+
+/// Blocks that can catch fire
+const FLAMMABLE: [&str; 11] = [
+    "oak",
+    "birch_log",
+    "oak_planks",
+    "oak_slab",
+    "oak_stairs",
+    "oak_door",
+    "leaves",
+    "birch_leaves",
+    "tall_grass",
+    "crafting_table",
+    "ladder",
+];
+
+// Chance for the block beneath the fire to burn away each time the fire is ticked.
+const BURN_CHANCE: f32 = 0.3;
+// Chance for the fire to spread each time it is ticked.
+const SPREAD_CHANCE: f32 = 0.5;
+
+/// If the block can catch fire
+pub fn is_flammable(block_id: BlockId) -> bool {
+    let name = &Blocks::get().get_config(&block_id).name;
+    FLAMMABLE.contains(&name.as_str())
+}
+
+/// Light the block at the position on fire. The fire is placed on top of it if there is room.
+pub fn ignite(
+    world_map: &WorldMap,
+    position: BlockPosition,
+    block_updates: &mut MessageWriter<BlockUpdate>,
+) {
+    let blocks = Blocks::get();
+    if !world_map.get_block(position).is_some_and(is_flammable)
+        || world_map.get_block(position + IVec3::Y) != Some(blocks.get_id("air"))
+    {
+        return;
+    }
+
+    block_updates.write(BlockUpdate::Replace {
+        position: position + IVec3::Y,
+        block_id: blocks.get_id("fire"),
+        block_state: None,
+        block_data: None,
+    });
+}
+
+#[derive(Component)]
+struct Fire;
+
+fn setup(mut commands: Commands, mut block_ticks: ResMut<BlockTicks>) {
+    let handler = commands.spawn((BlockTickEvents::default(), Fire)).id();
+    // Fires are ticked on a schedule from when they're lit, the random ticks catch the ones that
+    // were burning when the world was saved.
+    block_ticks.register(Blocks::get().get_id("fire"), handler, true);
+}
+
+fn burn_delay(rng: &mut Rng) -> Duration {
+    Duration::from_secs_f32(UniformDistribution::new(1.0, 3.0).sample(rng))
+}
+
+fn schedule_burning(
+    mut block_ticks: ResMut<BlockTicks>,
+    mut changed_blocks: MessageReader<ChangedBlockEvent>,
+    mut rng: Local<Rng>,
+) {
+    let fire = Blocks::get().get_id("fire");
+
+    for changed_block in changed_blocks.read() {
+        if changed_block.to.0 == fire {
+            block_ticks.schedule_tick(changed_block.position, burn_delay(&mut rng));
+        }
+    }
+}
+
+fn burn(
+    world_map: Res<WorldMap>,
+    mut block_ticks: ResMut<BlockTicks>,
+    mut fire_ticks: Query<&mut BlockTickEvents, (With<Fire>, Changed<BlockTickEvents>)>,
+    mut block_updates: MessageWriter<BlockUpdate>,
+    mut rng: Local<Rng>,
+) {
+    let Ok(mut ticks) = fire_ticks.single_mut() else {
+        return;
+    };
+
+    let air = Blocks::get().get_id("air");
+
+    for (block_position, _) in ticks.read() {
+        let below = block_position - IVec3::Y;
+
+        // Without anything to burn the fire goes out
+        if !world_map.get_block(below).is_some_and(is_flammable) {
+            block_updates.write(BlockUpdate::Replace {
+                position: block_position,
+                block_id: air,
+                block_state: None,
+                block_data: None,
+            });
+            continue;
+        }
+
+        if rng.next_f32() < SPREAD_CHANCE {
+            let offset = IVec3::new(
+                rng.next_i32().rem_euclid(3) - 1,
+                rng.next_i32().rem_euclid(3) - 1,
+                rng.next_i32().rem_euclid(3) - 1,
+            );
+            ignite(&world_map, below + offset, &mut block_updates);
+        }
+
+        if rng.next_f32() < BURN_CHANCE {
+            // The fire goes out on its next tick when there is nothing left beneath it.
+            block_updates.write(BlockUpdate::Replace {
+                position: below,
+                block_id: air,
+                block_state: None,
+                block_data: None,
+            });
+        }
+
+        block_ticks.schedule_tick(block_position, burn_delay(&mut rng));
+    }
+}
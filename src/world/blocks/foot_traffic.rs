@@ -0,0 +1,105 @@
+use std::{collections::HashMap, time::Duration};
+
+use fmc::{
+    blocks::{BlockId, BlockPosition, Blocks},
+    prelude::*,
+    world::BlockUpdate,
+};
+
+use crate::players::WalkOverEvent;
+
+/// Blocks that wear down when they are walked over a lot. Grass is trodden into dirt paths, and
+/// the snow on snowy grass is packed down until the grass shows through.
+pub struct FootTrafficPlugin;
+impl Plugin for FootTrafficPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Startup, setup)
+            .add_systems(Update, wear_down_blocks);
+    }
+}
+
+/// Steps are forgotten if the block isn't walked on again within this many seconds.
+const TRAFFIC_MEMORY: f32 = 600.0;
+
+#[derive(Resource)]
+struct WornBlocks(HashMap<BlockId, Wear>);
+
+struct Wear {
+    /// How many steps it takes to wear the block down
+    steps: u32,
+    /// The block it turns into
+    worn: BlockId,
+}
+
+struct Traffic {
+    steps: u32,
+    last_step: Duration,
+}
+
+fn setup(mut commands: Commands) {
+    let blocks = Blocks::get();
+
+    let mut worn_blocks = HashMap::new();
+    worn_blocks.insert(
+        blocks.get_id("grass"),
+        Wear {
+            steps: 40,
+            worn: blocks.get_id("dirt_path"),
+        },
+    );
+    worn_blocks.insert(
+        blocks.get_id("snowy_grass"),
+        Wear {
+            steps: 10,
+            worn: blocks.get_id("grass"),
+        },
+    );
+
+    commands.insert_resource(WornBlocks(worn_blocks));
+}
+
+fn wear_down_blocks(
+    time: Res<Time>,
+    worn_blocks: Res<WornBlocks>,
+    mut walk_over_events: MessageReader<WalkOverEvent>,
+    mut block_update_writer: MessageWriter<BlockUpdate>,
+    mut traffic: Local<HashMap<BlockPosition, Traffic>>,
+    mut cleanup_timer: Local<Timer>,
+) {
+    let now = time.elapsed();
+
+    for walk_over_event in walk_over_events.read() {
+        let Some(wear) = worn_blocks.0.get(&walk_over_event.block_id) else {
+            continue;
+        };
+
+        let traffic = traffic.entry(walk_over_event.position).or_insert(Traffic {
+            steps: 0,
+            last_step: now,
+        });
+
+        if (now - traffic.last_step).as_secs_f32() > TRAFFIC_MEMORY {
+            traffic.steps = 0;
+        }
+        traffic.steps += 1;
+        traffic.last_step = now;
+
+        if traffic.steps < wear.steps {
+            continue;
+        }
+
+        traffic.steps = 0;
+        block_update_writer.write(BlockUpdate::Replace {
+            position: walk_over_event.position,
+            block_id: wear.worn,
+            block_state: None,
+            block_data: None,
+        });
+    }
+
+    cleanup_timer.tick(time.delta());
+    if cleanup_timer.just_finished() {
+        *cleanup_timer = Timer::from_seconds(TRAFFIC_MEMORY, TimerMode::Once);
+        traffic.retain(|_, traffic| (now - traffic.last_step).as_secs_f32() <= TRAFFIC_MEMORY);
+    }
+}
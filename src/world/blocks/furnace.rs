@@ -4,7 +4,7 @@ use fmc::{
     bevy::ecs::system::EntityCommands,
     blocks::{BlockData, BlockPosition, Blocks},
     interfaces::{HeldInterfaceStack, InterfaceEvents, InterfaceSystems, RegisterInterfaceNode},
-    items::{ItemStack, Items},
+    items::{ItemId, ItemStack, Items},
     networking::Server,
     players::Player,
     prelude::*,
@@ -14,15 +14,25 @@ use fmc::{
 use serde::{Deserialize, Serialize};
 
 use crate::{
-    items::crafting::{CraftingGrid, Recipes},
+    assets::{AssetError, AssetErrors},
+    items::{
+        crafting::{CraftEvent, CraftingGrid, ITEM_CONFIG_PATH, Recipes},
+        tooltips::ItemTooltips,
+    },
     players::HandInteractions,
+    world::block_variants::BlockVariants,
+};
+
+use super::{
+    block_interface::{CloseBlockInterface, OpenBlockInterface},
+    container::{Container, Containers},
 };
 
 pub struct FurnacePlugin;
 impl Plugin for FurnacePlugin {
     fn build(&self, app: &mut App) {
         app.insert_resource(FurnaceRegistry::default())
-            .add_systems(Startup, setup)
+            .add_systems(Startup, (setup, validate_fuel))
             .add_systems(
                 Update,
                 (
@@ -30,6 +40,8 @@ impl Plugin for FurnacePlugin {
                     furnace,
                     handle_interface_events.in_set(InterfaceSystems::HandleEvents),
                     handle_despawn,
+                    close_interfaces,
+                    update_container,
                 ),
             );
     }
@@ -77,11 +89,7 @@ impl Furnace {
 
         if self.heat == 0.0 {
             if let Some(item) = self.fuel.item() {
-                let config = items.get_config(&item.id);
-                if let Some(fuel) = config.properties.get("fuel") {
-                    // TODO: Can't panic at runtime like this, make config proxy to deserialize
-                    // these extra fields.
-                    let fuel = fuel.as_f64().expect("The fuel property must a float") as f32;
+                if let Some(fuel) = fuel_time(items, item.id) {
                     self.fuel.take(1);
                     self.heat = fuel;
                     self.heat_max = fuel;
@@ -135,7 +143,10 @@ impl Furnace {
         node_update
     }
 
-    fn build_item_box_interface(&self) -> messages::InterfaceItemBoxUpdate {
+    fn build_item_box_interface(
+        &self,
+        tooltips: &ItemTooltips,
+    ) -> messages::InterfaceItemBoxUpdate {
         let mut item_box_update = messages::InterfaceItemBoxUpdate::default();
         for (item_stack, path) in [
             (&self.crucible[0], "furnace/crucible"),
@@ -149,7 +160,7 @@ impl Furnace {
                     item_stack.item().unwrap().id,
                     item_stack.size(),
                     None,
-                    None,
+                    tooltips.get(item_stack).as_deref(),
                 );
             } else {
                 item_box_update.add_empty_itembox(path, 0);
@@ -175,6 +186,14 @@ impl FurnaceRegistry {
         }
     }
 
+    fn remove_player(&mut self, player_entity: Entity) {
+        if let Some(furnace_entity) = self.player_to_furnace.remove(&player_entity)
+            && let Some(player_entities) = self.furnace_to_players.get_mut(&furnace_entity)
+        {
+            player_entities.remove(&player_entity);
+        }
+    }
+
     fn set_active_furnace(&mut self, player_entity: Entity, furnace_entity: Entity) {
         if let Some(old_table_entity) = self.player_to_furnace.remove(&player_entity) {
             self.furnace_to_players
@@ -201,6 +220,49 @@ fn setup(mut blocks: ResMut<Blocks>) {
     block.set_spawn_function(spawn_function);
 }
 
+/// How many seconds the item burns for, None if it can't be used as fuel. The fuel properties are
+/// checked when the server starts.
+fn fuel_time(items: &Items, item_id: ItemId) -> Option<f32> {
+    items
+        .get_config(&item_id)
+        .properties
+        .get("fuel")
+        .and_then(|fuel| fuel.as_f64())
+        .map(|fuel| fuel as f32)
+}
+
+// Items that can be burned have a "fuel" property, the number of seconds they burn for. The item
+// configs are read by fmc, so the property is checked here.
+fn validate_fuel(mut asset_errors: ResMut<AssetErrors>) {
+    let Ok(directory) = std::fs::read_dir(ITEM_CONFIG_PATH) else {
+        return;
+    };
+
+    for entry in directory.filter_map(|entry| entry.ok()) {
+        let file_path = entry.path();
+        let Ok(file) = std::fs::File::open(&file_path) else {
+            continue;
+        };
+        // Items that can't be read are reported by fmc
+        let Ok(config) = serde_json::from_reader::<_, serde_json::Value>(file) else {
+            continue;
+        };
+
+        if let Some(fuel) = config.get("fuel")
+            && !fuel.is_number()
+        {
+            asset_errors.push(
+                AssetError::new(
+                    &file_path,
+                    format!("The fuel must be a number, not {}", fuel),
+                )
+                .field("fuel")
+                .suggestion("It is how many seconds the item burns for, e.g. \"fuel\": 30.0"),
+            );
+        }
+    }
+}
+
 fn spawn_function(commands: &mut EntityCommands, block_data: Option<&BlockData>) {
     if let Some(block_data) = block_data {
         let furnace: Furnace = bincode::deserialize(&*block_data).unwrap();
@@ -209,7 +271,7 @@ fn spawn_function(commands: &mut EntityCommands, block_data: Option<&BlockData>)
         commands.insert(Furnace::new());
     }
 
-    commands.insert(HandInteractions::default());
+    commands.insert((Container, HandInteractions::default()));
 }
 
 const HEAT_STAGES: f32 = 12.0;
@@ -218,11 +280,13 @@ const SMELT_TIME: f32 = 10.0;
 
 fn furnace(
     net: Res<Server>,
+    tooltips: ItemTooltips,
     world_map: Res<WorldMap>,
     time: Res<Time>,
     recipes: Res<Recipes>,
     items: Res<Items>,
     registry: Res<FurnaceRegistry>,
+    block_variants: Res<BlockVariants>,
     mut furnaces_query: Query<(Entity, &BlockPosition, &mut Furnace)>,
     mut block_update_writer: MessageWriter<BlockUpdate>,
 ) {
@@ -241,22 +305,17 @@ fn furnace(
             }
         }
 
-        if furnace.heat != 0.0 && !furnace.on {
-            block_update_writer.write(BlockUpdate::Swap {
-                position: *block_position,
-                block_id: Blocks::get().get_id("furnace_on"),
-                block_state: world_map.get_block_state(*block_position),
-            });
+        if (furnace.heat != 0.0) != furnace.on {
+            furnace.on = furnace.heat != 0.0;
 
-            furnace.on = true;
-        } else if furnace.heat == 0.0 && furnace.on {
             block_update_writer.write(BlockUpdate::Swap {
                 position: *block_position,
-                block_id: Blocks::get().get_id("furnace"),
+                block_id: block_variants
+                    .builder("furnace")
+                    .with_bool("lit", furnace.on)
+                    .block_id(),
                 block_state: world_map.get_block_state(*block_position),
             });
-
-            furnace.on = false;
         }
 
         if furnace.is_smelting() {
@@ -283,7 +342,7 @@ fn furnace(
                 furnace.cold_start(&items, &recipes);
 
                 if let Some(players) = registry.furnace_to_players.get(&entity) {
-                    net.send_many(players, furnace.build_item_box_interface());
+                    net.send_many(players, furnace.build_item_box_interface(&tooltips));
                     net.send_many(players, furnace.build_progress_interface());
                 }
             }
@@ -293,14 +352,21 @@ fn furnace(
 
 fn handle_interface_events(
     net: Res<Server>,
+    tooltips: ItemTooltips,
     registry: Res<FurnaceRegistry>,
     items: Res<Items>,
     recipes: Res<Recipes>,
     mut player_query: Query<&mut HeldInterfaceStack, With<Player>>,
     mut input_events: Query<(Entity, &mut Furnace, &mut InterfaceEvents), Changed<InterfaceEvents>>,
+    mut craft_events: MessageWriter<CraftEvent>,
 ) {
     for (furnace_entity, mut furnace, mut events) in input_events.iter_mut() {
         for event in events.read() {
+            // The interface has been closed, but the client can still send events for it.
+            if registry.player_to_furnace.get(&event.player_entity) != Some(&furnace_entity) {
+                continue;
+            }
+
             let mut held_item = player_query.get_mut(event.player_entity).unwrap();
 
             if let messages::InterfaceInteraction::TakeItem {
@@ -314,7 +380,21 @@ fn handle_interface_events(
                 } else if interface_path.ends_with("fuel") {
                     furnace.fuel.transfer_to(&mut held_item, *quantity);
                 } else if interface_path.ends_with("output") {
+                    let maybe_item_id = furnace.output.item().map(|item| item.id);
+                    let before = furnace.output.size();
                     furnace.output.transfer_to(&mut held_item, *quantity);
+
+                    let amount = before - furnace.output.size();
+                    if let Some(item_id) = maybe_item_id
+                        && amount > 0
+                    {
+                        craft_events.write(CraftEvent {
+                            player_entity: event.player_entity,
+                            collection: "smelting",
+                            item_id,
+                            amount,
+                        });
+                    }
                 }
             } else if let messages::InterfaceInteraction::PlaceItem {
                 interface_path,
@@ -322,9 +402,18 @@ fn handle_interface_events(
                 ..
             } = &*event
             {
-                if interface_path.ends_with("crucible") {
+                // Items that don't belong in a slot stay in the player's hand. The client has
+                // already moved them, the item boxes sent below move them back.
+                let item_id = held_item.item().map(|item| item.id);
+
+                // The output can only be taken from
+                if interface_path.ends_with("crucible")
+                    && item_id.is_some_and(|item_id| recipes.get("smelting").uses_item(item_id))
+                {
                     held_item.transfer_to(&mut furnace.crucible[0], *quantity);
-                } else if interface_path.ends_with("fuel") {
+                } else if interface_path.ends_with("fuel")
+                    && item_id.is_some_and(|item_id| fuel_time(&items, item_id).is_some())
+                {
                     held_item.transfer_to(&mut furnace.fuel, *quantity);
                 }
             }
@@ -340,22 +429,33 @@ fn handle_interface_events(
             );
             net.send_many(
                 &registry.furnace_to_players[&furnace_entity],
-                furnace.build_item_box_interface(),
+                furnace.build_item_box_interface(&tooltips),
             );
         }
     }
 }
 
 fn handle_block_hits(
+    mut commands: Commands,
     net: Res<Server>,
+    tooltips: ItemTooltips,
     mut registry: ResMut<FurnaceRegistry>,
-    mut block_hits: Query<(Entity, &Furnace, &mut HandInteractions), Changed<HandInteractions>>,
+    mut block_hits: Query<
+        (Entity, &BlockPosition, &Furnace, &mut HandInteractions),
+        Changed<HandInteractions>,
+    >,
     mut registration_events: MessageWriter<RegisterInterfaceNode>,
 ) {
-    for (furnace_entity, furnace, mut block_hits) in block_hits.iter_mut() {
+    for (furnace_entity, block_position, furnace, mut block_hits) in block_hits.iter_mut() {
         for player_entity in block_hits.read() {
             registry.set_active_furnace(player_entity, furnace_entity);
 
+            commands.entity(player_entity).insert(OpenBlockInterface {
+                block_entity: furnace_entity,
+                block_position: *block_position,
+                interface_path: "furnace",
+            });
+
             registration_events.write(RegisterInterfaceNode {
                 player_entity,
                 node_path: String::from("furnace/crucible"),
@@ -374,7 +474,7 @@ fn handle_block_hits(
 
             net.send_one(player_entity, furnace.build_heat_interface());
             net.send_one(player_entity, furnace.build_progress_interface());
-            net.send_one(player_entity, furnace.build_item_box_interface());
+            net.send_one(player_entity, furnace.build_item_box_interface(&tooltips));
 
             net.send_one(
                 player_entity,
@@ -393,6 +493,28 @@ fn save_state(mut table_query: Query<(&Furnace, &mut BlockData), Changed<Furnace
     }
 }
 
+fn close_interfaces(
+    mut registry: ResMut<FurnaceRegistry>,
+    mut close_events: MessageReader<CloseBlockInterface>,
+) {
+    for close_event in close_events.read() {
+        registry.remove_player(close_event.player_entity);
+    }
+}
+
+fn update_container(
+    mut containers: ResMut<Containers>,
+    furnace_query: Query<(Entity, &BlockPosition, &Furnace), Changed<Furnace>>,
+) {
+    for (furnace_entity, block_position, furnace) in furnace_query.iter() {
+        let item_stacks = furnace
+            .crucible
+            .iter()
+            .chain([&furnace.fuel, &furnace.output]);
+        containers.set(furnace_entity, *block_position, item_stacks);
+    }
+}
+
 fn handle_despawn(
     mut registry: ResMut<FurnaceRegistry>,
     mut despawned_tables: RemovedComponents<Furnace>,
@@ -0,0 +1,251 @@
+use fmc::{
+    blocks::{BlockPosition, Blocks},
+    prelude::*,
+    random::Rng,
+    world::{BlockUpdate, WorldMap},
+};
+
+use crate::world::{
+    block_ticks::{BlockTickEvents, BlockTickSystems, BlockTicks},
+    is_opaque, sky_access,
+};
+
+pub struct GrassPlugin;
+impl Plugin for GrassPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Startup, setup)
+            .add_systems(Update, spread_grass.after(BlockTickSystems));
+    }
+}
+
+#[derive(Component)]
+struct Grass;
+
+fn setup(mut commands: Commands, mut block_ticks: ResMut<BlockTicks>) {
+    let handler = commands.spawn((BlockTickEvents::default(), Grass)).id();
+    block_ticks.register(Blocks::get().get_id("grass"), handler, true);
+}
+
+fn spread_grass(
+    world_map: Res<WorldMap>,
+    mut grass_ticks: Query<&mut BlockTickEvents, (With<Grass>, Changed<BlockTickEvents>)>,
+    mut block_update_writer: MessageWriter<BlockUpdate>,
+    mut rng: Local<Rng>,
+) {
+    let Ok(mut ticks) = grass_ticks.single_mut() else {
+        return;
+    };
+
+    let blocks = Blocks::get();
+    let grass = blocks.get_id("grass");
+    let dirt = blocks.get_id("dirt");
+
+    let is_dirt = |position| world_map.get_block(position) == Some(dirt);
+    let opaque = |position| {
+        world_map
+            .get_block(position)
+            .map(|block_id| is_opaque(blocks.get_config(&block_id)))
+    };
+
+    for (block_position, _) in ticks.read() {
+        let spread_offset = IVec3::new(
+            rng.next_i32().rem_euclid(3) - 1,
+            rng.next_i32().rem_euclid(5) - 3,
+            rng.next_i32().rem_euclid(3) - 1,
+        );
+
+        let (position, block_id) = match tick_grass(block_position, spread_offset, is_dirt, opaque)
+        {
+            GrassTick::Wither => (block_position, dirt),
+            GrassTick::Spread(spread_position) => (spread_position, grass),
+            GrassTick::Nothing => continue,
+        };
+
+        block_update_writer.write(BlockUpdate::Replace {
+            position,
+            block_id,
+            block_state: None,
+            block_data: None,
+        });
+    }
+}
+
+/// What a grass block does when it's ticked
+#[derive(Debug, PartialEq)]
+enum GrassTick {
+    /// It's covered and turns to dirt
+    Wither,
+    /// It spreads to the dirt at the position
+    Spread(BlockPosition),
+    Nothing,
+}
+
+// Grass that is covered by an opaque block turns to dirt, otherwise it tries to spread to the
+// dirt block at the offset if the dirt can see the sky. `opaque` is None for positions in chunks
+// that aren't loaded.
+fn tick_grass(
+    block_position: BlockPosition,
+    spread_offset: IVec3,
+    is_dirt: impl Fn(BlockPosition) -> bool,
+    opaque: impl Fn(BlockPosition) -> Option<bool> + Copy,
+) -> GrassTick {
+    // Chunks that aren't loaded are treated as covered, so that grass at the edge of the
+    // loaded world doesn't change based on blocks it can't see.
+    let covered = |position: BlockPosition| opaque(position + IVec3::Y).unwrap_or(true);
+
+    if opaque(block_position + IVec3::Y) == Some(true) {
+        return GrassTick::Wither;
+    }
+
+    // Unloaded neighbours are skipped, grass only spreads across chunk borders when the chunk
+    // on the other side is loaded.
+    let spread_position = block_position + spread_offset;
+    if !is_dirt(spread_position) || covered(spread_position) || !sky_access(spread_position, opaque)
+    {
+        return GrassTick::Nothing;
+    }
+
+    GrassTick::Spread(spread_position)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::{HashMap, HashSet};
+
+    use fmc::world::chunk::ChunkPosition;
+
+    use super::*;
+
+    #[derive(Clone, Copy, PartialEq)]
+    enum Block {
+        Air,
+        Dirt,
+        Stone,
+    }
+
+    /// The loaded chunks are filled with air, except for the blocks that have been set
+    #[derive(Default)]
+    struct World {
+        loaded: HashSet<ChunkPosition>,
+        blocks: HashMap<BlockPosition, Block>,
+    }
+
+    impl World {
+        /// Load the chunk the position is in
+        fn load(&mut self, x: i32, y: i32, z: i32) -> &mut Self {
+            self.loaded
+                .insert(ChunkPosition::from(BlockPosition::new(x, y, z)));
+            self
+        }
+
+        fn set(&mut self, x: i32, y: i32, z: i32, block: Block) -> &mut Self {
+            self.blocks.insert(BlockPosition::new(x, y, z), block);
+            self
+        }
+
+        fn get(&self, position: BlockPosition) -> Option<Block> {
+            if !self.loaded.contains(&ChunkPosition::from(position)) {
+                return None;
+            }
+            Some(self.blocks.get(&position).copied().unwrap_or(Block::Air))
+        }
+
+        fn tick(&self, x: i32, y: i32, z: i32, spread_offset: IVec3) -> GrassTick {
+            tick_grass(
+                BlockPosition::new(x, y, z),
+                spread_offset,
+                |position| self.get(position) == Some(Block::Dirt),
+                |position| self.get(position).map(|block| block == Block::Stone),
+            )
+        }
+    }
+
+    #[test]
+    fn covered_grass_withers() {
+        let mut world = World::default();
+        world.load(0, 0, 0).set(5, 6, 5, Block::Stone);
+
+        assert_eq!(world.tick(5, 5, 5, IVec3::X), GrassTick::Wither);
+    }
+
+    #[test]
+    fn grass_under_an_unloaded_chunk_is_kept() {
+        let mut world = World::default();
+        world.load(0, 0, 0);
+
+        assert_eq!(world.tick(5, 15, 5, IVec3::X), GrassTick::Nothing);
+    }
+
+    #[test]
+    fn spreads_to_dirt_that_can_see_the_sky() {
+        let mut world = World::default();
+        world.load(0, 0, 0).set(6, 4, 5, Block::Dirt);
+
+        assert_eq!(
+            world.tick(5, 5, 5, IVec3::new(1, -1, 0)),
+            GrassTick::Spread(BlockPosition::new(6, 4, 5))
+        );
+    }
+
+    #[test]
+    fn does_not_spread_to_covered_dirt() {
+        let mut world = World::default();
+        world
+            .load(0, 0, 0)
+            .set(6, 4, 5, Block::Dirt)
+            .set(6, 5, 5, Block::Stone);
+
+        assert_eq!(
+            world.tick(5, 5, 5, IVec3::new(1, -1, 0)),
+            GrassTick::Nothing
+        );
+    }
+
+    #[test]
+    fn sky_access_is_blocked_by_the_chunk_above() {
+        let mut world = World::default();
+        world
+            .load(0, 0, 0)
+            .load(0, 16, 0)
+            .set(6, 4, 5, Block::Dirt)
+            .set(6, 20, 5, Block::Stone);
+
+        assert_eq!(
+            world.tick(5, 5, 5, IVec3::new(1, -1, 0)),
+            GrassTick::Nothing
+        );
+    }
+
+    #[test]
+    fn spreads_into_a_loaded_neighbour_chunk() {
+        let mut world = World::default();
+        world
+            .load(0, 0, 0)
+            .load(16, 0, 0)
+            .set(16, 5, 5, Block::Dirt);
+
+        assert_eq!(
+            world.tick(15, 5, 5, IVec3::X),
+            GrassTick::Spread(BlockPosition::new(16, 5, 5))
+        );
+    }
+
+    #[test]
+    fn does_not_spread_into_an_unloaded_chunk() {
+        let mut world = World::default();
+        world.load(0, 0, 0).set(16, 5, 5, Block::Dirt);
+
+        assert_eq!(world.tick(15, 5, 5, IVec3::X), GrassTick::Nothing);
+    }
+
+    #[test]
+    fn does_not_spread_to_dirt_under_an_unloaded_chunk() {
+        let mut world = World::default();
+        world.load(0, 0, 0).set(6, 15, 5, Block::Dirt);
+
+        assert_eq!(
+            world.tick(5, 14, 5, IVec3::new(1, 1, 0)),
+            GrassTick::Nothing
+        );
+    }
+}
@@ -0,0 +1,283 @@
+use std::collections::HashMap;
+
+use fmc::{
+    bevy::{
+        ecs::system::EntityCommands,
+        math::{DQuat, DVec3},
+    },
+    blocks::{BlockData, BlockId, BlockPosition, Blocks},
+    items::{ItemStack, Items},
+    models::Models,
+    physics::Collider,
+    players::Player,
+    prelude::*,
+    world::{BlockUpdate, ChangedBlockEvent, WorldMap},
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    items::{DroppedItem, display::ItemDisplay},
+    players::{HandHits, HandInteractions, Inventory},
+};
+
+pub struct ItemFramePlugin;
+impl Plugin for ItemFramePlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(ItemFrames::default())
+            .add_systems(Startup, setup)
+            .add_systems(
+                Update,
+                (
+                    (handle_interactions, pop_items, update_displays).chain(),
+                    drop_contents,
+                    remove_unloaded,
+                ),
+            );
+    }
+}
+
+/// How far in front of the block's center the item is shown. The frame itself is 0.1 thick and
+/// sits against the back face of the block.
+const DISPLAY_OFFSET: f64 = 0.35;
+/// Half the size of the box the item model is fit inside.
+const DISPLAY_HALF_EXTENT: f64 = 0.25;
+
+#[derive(Component, Serialize, Deserialize, Default)]
+struct ItemFrame {
+    item_stack: ItemStack,
+    // Steps of 45 degrees the item is rotated clockwise
+    rotation: u8,
+}
+
+/// The entity that shows the item of an item frame.
+#[derive(Component)]
+struct ItemFrameDisplay {
+    frame_position: BlockPosition,
+}
+
+struct LoadedFrame {
+    frame_entity: Entity,
+    display_entity: Option<Entity>,
+    // Copy of the frame's item so it can be dropped after the frame's block entity has been
+    // removed.
+    item_stack: ItemStack,
+}
+
+/// Item frames that are currently loaded.
+#[derive(Resource, Default)]
+struct ItemFrames(HashMap<BlockPosition, LoadedFrame>);
+
+fn setup(mut blocks: ResMut<Blocks>) {
+    let block_id = blocks.get_id("item_frame");
+    let block = blocks.get_config_mut(&block_id);
+    block.set_spawn_function(spawn_function);
+}
+
+fn spawn_function(commands: &mut EntityCommands, block_data: Option<&BlockData>) {
+    if let Some(block_data) = block_data {
+        let item_frame: ItemFrame = serde_json::from_slice(&block_data.0).unwrap();
+        commands.insert(item_frame);
+    } else {
+        commands.insert(ItemFrame::default());
+    }
+
+    commands.insert(HandInteractions::default());
+}
+
+// Right clicking an empty frame puts the held item in it, right clicking a frame with an item
+// rotates the item.
+fn handle_interactions(
+    mut player_query: Query<&mut Inventory, With<Player>>,
+    mut frames: Query<
+        (&BlockPosition, &mut ItemFrame, &mut HandInteractions),
+        Changed<HandInteractions>,
+    >,
+    mut block_update_writer: MessageWriter<BlockUpdate>,
+) {
+    for (block_position, mut item_frame, mut interactions) in frames.iter_mut() {
+        for player_entity in interactions.read() {
+            let Ok(mut inventory) = player_query.get_mut(player_entity) else {
+                continue;
+            };
+
+            if item_frame.item_stack.is_empty() {
+                let held_item_stack = inventory.held_item_stack_mut();
+                if held_item_stack.is_empty() {
+                    continue;
+                }
+                item_frame.item_stack = held_item_stack.take(1);
+                item_frame.rotation = 0;
+            } else {
+                item_frame.rotation = (item_frame.rotation + 1) % 8;
+            }
+
+            block_update_writer.write(BlockUpdate::Data {
+                position: *block_position,
+                block_data: Some(serde_json::to_vec(&*item_frame).map(BlockData).unwrap()),
+            });
+        }
+    }
+}
+
+// Punching the item knocks it out of the frame.
+fn pop_items(
+    mut commands: Commands,
+    item_frames: Res<ItemFrames>,
+    displays: Query<(&ItemFrameDisplay, &HandHits, &GlobalTransform), Changed<HandHits>>,
+    mut frames: Query<&mut ItemFrame>,
+    mut block_update_writer: MessageWriter<BlockUpdate>,
+) {
+    for (display, hits, transform) in displays.iter() {
+        if hits.iter().next().is_none() {
+            continue;
+        }
+
+        let Some(loaded_frame) = item_frames.0.get(&display.frame_position) else {
+            continue;
+        };
+
+        let Ok(mut item_frame) = frames.get_mut(loaded_frame.frame_entity) else {
+            continue;
+        };
+
+        if item_frame.item_stack.is_empty() {
+            continue;
+        }
+
+        let item_stack = std::mem::take(&mut item_frame.item_stack);
+        item_frame.rotation = 0;
+
+        commands.spawn((
+            DroppedItem::new(item_stack),
+            Transform::from_translation(transform.translation()),
+        ));
+
+        block_update_writer.write(BlockUpdate::Data {
+            position: display.frame_position,
+            block_data: Some(serde_json::to_vec(&*item_frame).map(BlockData).unwrap()),
+        });
+    }
+}
+
+fn update_displays(
+    mut commands: Commands,
+    items: Res<Items>,
+    models: Res<Models>,
+    world_map: Res<WorldMap>,
+    mut item_frames: ResMut<ItemFrames>,
+    frames: Query<(Entity, &BlockPosition, &ItemFrame), Changed<ItemFrame>>,
+) {
+    for (frame_entity, block_position, item_frame) in frames.iter() {
+        let loaded_frame = item_frames.0.entry(*block_position).or_insert(LoadedFrame {
+            frame_entity,
+            display_entity: None,
+            item_stack: ItemStack::default(),
+        });
+
+        if let Some(display_entity) = loaded_frame.display_entity.take() {
+            commands.entity(display_entity).despawn();
+        }
+
+        loaded_frame.frame_entity = frame_entity;
+        loaded_frame.item_stack = item_frame.item_stack.clone();
+
+        if item_frame.item_stack.is_empty() {
+            continue;
+        }
+
+        // The frame is placed against the back face of the block, so the item is offset towards
+        // it, and faces away from it.
+        let block_rotation = world_map
+            .get_block_state(*block_position)
+            .and_then(|block_state| block_state.rotation())
+            .map(|rotation| rotation.as_quat())
+            .unwrap_or_default();
+        let translation = block_position.as_dvec3()
+            + DVec3::splat(0.5)
+            + block_rotation * DVec3::new(0.0, 0.0, -DISPLAY_OFFSET);
+        let rotation = block_rotation
+            * DQuat::from_rotation_z(-(item_frame.rotation as f64) * std::f64::consts::FRAC_PI_4);
+
+        let display = ItemDisplay::new(
+            &items,
+            &models,
+            &item_frame.item_stack,
+            DISPLAY_HALF_EXTENT,
+            DISPLAY_HALF_EXTENT,
+        );
+
+        let display_entity = commands
+            .spawn((
+                ItemFrameDisplay {
+                    frame_position: *block_position,
+                },
+                display.model,
+                Collider::Single(display.aabb),
+                HandHits::default(),
+                Transform {
+                    translation,
+                    rotation,
+                    scale: DVec3::splat(display.scale),
+                },
+            ))
+            .id();
+
+        loaded_frame.display_entity = Some(display_entity);
+    }
+}
+
+// When an item frame is broken its item is dropped along with it.
+fn drop_contents(
+    mut commands: Commands,
+    mut item_frames: ResMut<ItemFrames>,
+    mut changed_blocks: MessageReader<ChangedBlockEvent>,
+    mut item_frame_id: Local<Option<BlockId>>,
+) {
+    let item_frame_id = *item_frame_id.get_or_insert_with(|| Blocks::get().get_id("item_frame"));
+
+    for changed_block in changed_blocks.read() {
+        if changed_block.from.0 != item_frame_id || changed_block.to.0 == item_frame_id {
+            continue;
+        }
+
+        let Some(loaded_frame) = item_frames.0.remove(&changed_block.position) else {
+            continue;
+        };
+
+        if let Some(display_entity) = loaded_frame.display_entity {
+            commands.entity(display_entity).despawn();
+        }
+
+        if !loaded_frame.item_stack.is_empty() {
+            commands.spawn((
+                DroppedItem::new(loaded_frame.item_stack),
+                Transform::from_translation(changed_block.position.as_dvec3() + DVec3::splat(0.5)),
+            ));
+        }
+    }
+}
+
+// Item frames in chunks that are unloaded take their displays with them. Frames that are broken
+// are handled by 'drop_contents'.
+fn remove_unloaded(
+    mut commands: Commands,
+    world_map: Res<WorldMap>,
+    mut item_frames: ResMut<ItemFrames>,
+    mut removed_frames: RemovedComponents<ItemFrame>,
+) {
+    for frame_entity in removed_frames.read() {
+        item_frames.0.retain(|block_position, loaded_frame| {
+            if loaded_frame.frame_entity != frame_entity
+                || world_map.get_block(*block_position).is_some()
+            {
+                return true;
+            }
+
+            if let Some(display_entity) = loaded_frame.display_entity {
+                commands.entity(display_entity).despawn();
+            }
+
+            false
+        });
+    }
+}
@@ -0,0 +1,61 @@
+use fmc::{
+    prelude::*,
+    world::{BlockUpdate, WorldMap},
+};
+
+use super::power::{Power, PowerEvents, PowerSystems};
+use crate::world::block_variants::{BlockVariantSystems, BlockVariants};
+
+/// Redstone lamps light up while they're powered.
+pub struct LampPlugin;
+impl Plugin for LampPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Startup, setup.after(BlockVariantSystems))
+            .add_systems(Update, switch_lamps.after(PowerSystems));
+    }
+}
+
+#[derive(Component)]
+struct Lamp;
+
+fn setup(mut commands: Commands, block_variants: Res<BlockVariants>, mut power: ResMut<Power>) {
+    let handler = commands.spawn((PowerEvents::default(), Lamp)).id();
+
+    for lit in [false, true] {
+        let block_id = block_variants
+            .builder("redstone_lamp")
+            .with_bool("lit", lit)
+            .block_id();
+        power.register_consumer(block_id, handler);
+    }
+}
+
+fn switch_lamps(
+    world_map: Res<WorldMap>,
+    block_variants: Res<BlockVariants>,
+    mut lamp_events: Query<&mut PowerEvents, (With<Lamp>, Changed<PowerEvents>)>,
+    mut block_update_writer: MessageWriter<BlockUpdate>,
+) {
+    let Ok(mut events) = lamp_events.single_mut() else {
+        return;
+    };
+
+    for (block_position, powered) in events.read() {
+        let Some(lamp) = world_map
+            .get_block(block_position)
+            .and_then(|block_id| block_variants.state("redstone_lamp", block_id))
+        else {
+            continue;
+        };
+
+        if lamp.get_bool("lit") == powered {
+            continue;
+        }
+
+        block_update_writer.write(BlockUpdate::Swap {
+            position: block_position,
+            block_id: lamp.with_bool("lit", powered).block_id(),
+            block_state: None,
+        });
+    }
+}
@@ -0,0 +1,84 @@
+use fmc::{
+    bevy::ecs::system::EntityCommands,
+    blocks::{BlockData, BlockPosition, Blocks},
+    prelude::*,
+    world::{BlockUpdate, WorldMap},
+};
+
+use super::{
+    attached::{AttachedBlocks, Support},
+    power::{MAX_POWER, Power},
+};
+use crate::{
+    players::HandInteractions,
+    world::block_variants::{BlockVariantSystems, BlockVariants},
+};
+
+/// Levers are switched on and off by right clicking them, and power the blocks around them while
+/// they're on.
+pub struct LeverPlugin;
+impl Plugin for LeverPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Startup, setup.after(BlockVariantSystems))
+            .add_systems(Update, handle_block_hits);
+    }
+}
+
+#[derive(Component)]
+struct Lever;
+
+fn setup(
+    mut blocks: ResMut<Blocks>,
+    block_variants: Res<BlockVariants>,
+    mut power: ResMut<Power>,
+    mut attached_blocks: ResMut<AttachedBlocks>,
+) {
+    for on in [false, true] {
+        let block_id = block_variants
+            .builder("lever")
+            .with_bool("on", on)
+            .block_id();
+        blocks
+            .get_config_mut(&block_id)
+            .set_spawn_function(spawn_function);
+        attached_blocks.register(block_id, Support::Below);
+        if on {
+            power.register_source(block_id, MAX_POWER);
+        }
+    }
+}
+
+fn spawn_function(commands: &mut EntityCommands, _block_data: Option<&BlockData>) {
+    commands.insert((Lever, HandInteractions::default()));
+}
+
+fn handle_block_hits(
+    world_map: Res<WorldMap>,
+    block_variants: Res<BlockVariants>,
+    mut block_hits: Query<
+        (&BlockPosition, &mut HandInteractions),
+        (With<Lever>, Changed<HandInteractions>),
+    >,
+    mut block_update_writer: MessageWriter<BlockUpdate>,
+) {
+    for (block_position, mut interactions) in block_hits.iter_mut() {
+        // Flipping it twice in the same update leaves it as it was
+        if interactions.read().count() % 2 == 0 {
+            continue;
+        }
+
+        let Some(lever) = world_map
+            .get_block(*block_position)
+            .and_then(|block_id| block_variants.state("lever", block_id))
+        else {
+            continue;
+        };
+
+        let on = lever.get_bool("on");
+        block_update_writer.write(BlockUpdate::Swap {
+            position: *block_position,
+            block_id: lever.with_bool("on", !on).block_id(),
+            block_state: world_map.get_block_state(*block_position),
+        });
+    }
+}
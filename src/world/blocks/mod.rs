@@ -1,23 +1,59 @@
 use fmc::prelude::*;
 
+pub mod attached;
+mod bed;
+mod block_interface;
+mod button;
 mod chest;
+mod container;
 mod crafting_table;
-mod door;
+pub mod door;
+mod farmland;
+pub mod fire;
+mod foot_traffic;
 mod furnace;
+mod grass;
+mod item_frame;
+mod lamp;
+mod lever;
+pub mod multi_block;
+pub mod partial_blocks;
+pub mod piston;
+mod portal;
+pub mod power;
+mod sponge;
 mod torch;
-mod water;
+pub mod water;
 mod wheat;
 
 /// Adds systems for all blocks that are dynamic in some way
 pub(super) struct BlocksPlugin;
 impl Plugin for BlocksPlugin {
     fn build(&self, app: &mut App) {
-        app.add_plugins(crafting_table::CraftingTablePlugin)
+        app.add_plugins(multi_block::MultiBlockPlugin)
+            .add_plugins(attached::AttachedBlocksPlugin)
+            .add_plugins(bed::BedPlugin)
+            .add_plugins(block_interface::BlockInterfacePlugin)
+            .add_plugins(button::ButtonPlugin)
+            .add_plugins(crafting_table::CraftingTablePlugin)
             .add_plugins(chest::ChestPlugin)
+            .add_plugins(container::ContainerPlugin)
             .add_plugins(furnace::FurnacePlugin)
             .add_plugins(torch::TorchPlugin)
             .add_plugins(water::WaterPlugin)
             .add_plugins(door::DoorPlugin)
+            .add_plugins(farmland::FarmlandPlugin)
+            .add_plugins(fire::FirePlugin)
+            .add_plugins(foot_traffic::FootTrafficPlugin)
+            .add_plugins(grass::GrassPlugin)
+            .add_plugins(item_frame::ItemFramePlugin)
+            .add_plugins(lamp::LampPlugin)
+            .add_plugins(lever::LeverPlugin)
+            .add_plugins(partial_blocks::PartialBlocksPlugin)
+            .add_plugins(piston::PistonPlugin)
+            .add_plugins(portal::PortalPlugin)
+            .add_plugins(power::PowerPlugin)
+            .add_plugins(sponge::SpongePlugin)
             .add_plugins(wheat::WheatPlugin);
     }
 }
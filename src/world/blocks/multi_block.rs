@@ -0,0 +1,269 @@
+use std::collections::HashMap;
+
+use fmc::{
+    blocks::{BlockId, BlockPosition, BlockState, Blocks},
+    prelude::*,
+    world::{BlockUpdate, ChangedBlockEvent, ChunkLoadEvent, WorldMap, chunk::Chunk},
+};
+
+/// Keeps structures that span several blocks consistent. Breaking one part removes the others,
+/// and placing one part places the rest.
+pub struct MultiBlockPlugin;
+impl Plugin for MultiBlockPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(MultiBlocks::default())
+            .add_systems(Update, (keep_structures_whole, repair_loaded_chunks));
+    }
+}
+
+/// Registry of all structures made up of multiple blocks.
+#[derive(Resource, Default)]
+pub struct MultiBlocks {
+    structures: Vec<Vec<(BlockId, IVec3)>>,
+    // Map from block to the structure it is part of, and its index in the structure.
+    parts: HashMap<BlockId, (usize, usize)>,
+    // Blocks that can stand in for a part, mapped to the part they stand in for.
+    alternatives: HashMap<BlockId, BlockId>,
+}
+
+impl MultiBlocks {
+    /// Register a structure. Each part is given as a block and its offset from the first part
+    /// when the block is unrotated. The first part is the one that should be placed by items.
+    pub fn register(&mut self, parts: &[(BlockId, IVec3)]) {
+        let structure_index = self.structures.len();
+        for (part_index, (block_id, _)) in parts.iter().enumerate() {
+            if self.parts.contains_key(block_id) {
+                panic!(
+                    "The block '{}' is part of two different multi block structures",
+                    Blocks::get().get_config(block_id).name
+                );
+            }
+            self.parts.insert(*block_id, (structure_index, part_index));
+        }
+        self.structures.push(parts.to_vec());
+    }
+
+    /// Let another block stand in for a part of a structure, like the top half of a door that
+    /// changes shape when it is opened. Swapping one for the other doesn't count as the part
+    /// being removed.
+    pub fn register_alternative(&mut self, part: BlockId, alternative: BlockId) {
+        if !self.parts.contains_key(&part) {
+            panic!(
+                "The block '{}' was given an alternative, but it isn't part of a multi block \
+                structure",
+                Blocks::get().get_config(&part).name
+            );
+        }
+        self.alternatives.insert(alternative, part);
+    }
+
+    pub fn is_part(&self, block_id: &BlockId) -> bool {
+        self.parts.contains_key(&self.part(*block_id))
+    }
+
+    // The part the block is, or stands in for
+    fn part(&self, block_id: BlockId) -> BlockId {
+        self.alternatives
+            .get(&block_id)
+            .copied()
+            .unwrap_or(block_id)
+    }
+
+    /// Given one part of a structure, get the position and block of all the other parts.
+    pub fn other_parts(
+        &self,
+        block_id: BlockId,
+        block_position: BlockPosition,
+        block_state: Option<BlockState>,
+    ) -> Option<Vec<(BlockPosition, BlockId)>> {
+        let (structure_index, part_index) = self.parts.get(&self.part(block_id))?;
+        let structure = &self.structures[*structure_index];
+
+        let rotate = |offset: IVec3| -> IVec3 {
+            match block_state.and_then(|state| state.rotation()) {
+                Some(rotation) => (rotation.as_quat() * offset.as_dvec3()).round().as_ivec3(),
+                None => offset,
+            }
+        };
+
+        let origin = block_position - rotate(structure[*part_index].1);
+
+        Some(
+            structure
+                .iter()
+                .enumerate()
+                .filter(|(index, _)| index != part_index)
+                .map(|(_, (block_id, offset))| (origin + rotate(*offset), *block_id))
+                .collect(),
+        )
+    }
+}
+
+enum Validation {
+    Whole,
+    // The missing parts can be placed
+    Repairable(Vec<(BlockPosition, BlockId)>),
+    // Something is in the way of the missing parts
+    Broken,
+    // Some of the parts are in chunks that are not loaded
+    Unknown,
+}
+
+fn validate(
+    world_map: &WorldMap,
+    multi_blocks: &MultiBlocks,
+    block_id: BlockId,
+    block_position: BlockPosition,
+    block_state: Option<BlockState>,
+) -> Validation {
+    let blocks = Blocks::get();
+
+    let Some(other_parts) = multi_blocks.other_parts(block_id, block_position, block_state) else {
+        return Validation::Whole;
+    };
+
+    let mut missing = Vec::new();
+    for (position, part_id) in other_parts {
+        let Some(current) = world_map.get_block(position) else {
+            return Validation::Unknown;
+        };
+
+        if multi_blocks.part(current) == part_id {
+            continue;
+        } else if blocks.get_config(&current).replaceable {
+            missing.push((position, part_id));
+        } else {
+            return Validation::Broken;
+        }
+    }
+
+    if missing.is_empty() {
+        return Validation::Whole;
+    } else {
+        return Validation::Repairable(missing);
+    }
+}
+
+fn keep_structures_whole(
+    world_map: Res<WorldMap>,
+    multi_blocks: Res<MultiBlocks>,
+    mut changed_blocks: MessageReader<ChangedBlockEvent>,
+    mut block_updates: MessageWriter<BlockUpdate>,
+) {
+    let air = Blocks::get().get_id("air");
+
+    for changed_block in changed_blocks.read() {
+        let (from_id, from_state) = changed_block.from;
+        let (to_id, to_state) = changed_block.to;
+
+        if multi_blocks.part(from_id) == multi_blocks.part(to_id) {
+            continue;
+        }
+
+        // A part was removed, whether it was broken, blown up or moved, remove the rest of the
+        // structure too.
+        if let Some(other_parts) =
+            multi_blocks.other_parts(from_id, changed_block.position, from_state)
+        {
+            for (position, part_id) in other_parts {
+                if world_map
+                    .get_block(position)
+                    .is_some_and(|block_id| multi_blocks.part(block_id) == part_id)
+                {
+                    block_updates.write(BlockUpdate::Replace {
+                        position,
+                        block_id: air,
+                        block_state: None,
+                        block_data: None,
+                    });
+                }
+            }
+        }
+
+        // A part was placed, fill in the rest if there is room, otherwise it can't be placed.
+        match validate(
+            &world_map,
+            &multi_blocks,
+            to_id,
+            changed_block.position,
+            to_state,
+        ) {
+            Validation::Whole | Validation::Unknown => (),
+            Validation::Repairable(missing) => {
+                block_updates.write_batch(missing.into_iter().map(|(position, block_id)| {
+                    BlockUpdate::Replace {
+                        position,
+                        block_id,
+                        block_state: to_state,
+                        block_data: None,
+                    }
+                }));
+            }
+            Validation::Broken => {
+                block_updates.write(BlockUpdate::Replace {
+                    position: changed_block.position,
+                    block_id: air,
+                    block_state: None,
+                    block_data: None,
+                });
+            }
+        }
+    }
+}
+
+// When a chunk is unloaded while a part of a structure is being changed in a neighbouring chunk,
+// only half of it might have been saved. When the chunk is loaded again it is repaired if possible,
+// or removed.
+fn repair_loaded_chunks(
+    world_map: Res<WorldMap>,
+    multi_blocks: Res<MultiBlocks>,
+    mut loaded_chunks: MessageReader<ChunkLoadEvent>,
+    mut block_updates: MessageWriter<BlockUpdate>,
+) {
+    let air = Blocks::get().get_id("air");
+
+    for loaded_chunk in loaded_chunks.read() {
+        let Some(chunk) = world_map.get_chunk(&loaded_chunk.position) else {
+            continue;
+        };
+
+        for chunk_index in 0..Chunk::SIZE * Chunk::SIZE * Chunk::SIZE {
+            let block_id = chunk[chunk_index];
+            if !multi_blocks.is_part(&block_id) {
+                continue;
+            }
+
+            let block_position =
+                BlockPosition::from(loaded_chunk.position) + BlockPosition::from(chunk_index);
+            let block_state = world_map.get_block_state(block_position);
+
+            match validate(
+                &world_map,
+                &multi_blocks,
+                block_id,
+                block_position,
+                block_state,
+            ) {
+                Validation::Whole | Validation::Unknown => (),
+                Validation::Repairable(missing) => {
+                    block_updates.write_batch(missing.into_iter().map(|(position, block_id)| {
+                        BlockUpdate::Replace {
+                            position,
+                            block_id,
+                            block_state,
+                            block_data: None,
+                        }
+                    }));
+                }
+                Validation::Broken => {
+                    block_updates.write(BlockUpdate::Replace {
+                        position: block_position,
+                        block_id: air,
+                        block_state: None,
+                        block_data: None,
+                    });
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,228 @@
+use fmc::{
+    blocks::{BlockData, BlockFace, BlockId, BlockPosition, BlockRotation, BlockState, Blocks},
+    prelude::*,
+    world::{BlockUpdate, ChangedBlockEvent, WorldMap},
+};
+
+use crate::{
+    players::HandInteractions,
+    world::{
+        block_variants::{BlockVariantSystems, BlockVariants},
+        paste::UpdateSuppression,
+    },
+};
+
+/// Blocks that only fill part of their space and change shape depending on how they're placed
+/// or what is around them.
+///
+/// - Slabs go in the top half of the block when placed against the upper half of a side, or
+///   against the bottom of a block.
+/// - Stairs turn into inner and outer corners when they meet other stairs at a right angle.
+/// - Trapdoors and fence gates are opened and closed by right clicking them.
+///
+/// Each shape is a variant of the block in `block_variants.json`, with its own collision box.
+pub struct PartialBlocksPlugin;
+impl Plugin for PartialBlocksPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Startup, setup.after(BlockVariantSystems))
+            .add_systems(Update, (shape_stairs, handle_block_hits));
+    }
+}
+
+/// Block families that have a bottom and a top variant
+const SLABS: [&str; 3] = ["oak_slab", "stone_slab", "cobblestone_slab"];
+const STAIRS: [&str; 1] = ["oak_stairs"];
+/// Block families that are opened and closed by hand
+const OPENABLE: [&str; 2] = ["oak_trapdoor", "oak_fence_gate"];
+
+// Values of the stairs' "shape" property
+const STRAIGHT: u8 = 0;
+const INNER_LEFT: u8 = 1;
+const INNER_RIGHT: u8 = 2;
+const OUTER_LEFT: u8 = 3;
+const OUTER_RIGHT: u8 = 4;
+
+#[derive(Component)]
+struct Openable;
+
+fn setup(mut blocks: ResMut<Blocks>, block_variants: Res<BlockVariants>) {
+    for family in OPENABLE {
+        for open in [false, true] {
+            let block_id = block_variants
+                .builder(family)
+                .with_bool("open", open)
+                .block_id();
+            blocks
+                .get_config_mut(&block_id)
+                .set_spawn_function(spawn_function);
+        }
+    }
+}
+
+fn spawn_function(commands: &mut EntityCommands, _block_data: Option<&BlockData>) {
+    commands.insert((Openable, HandInteractions::default()));
+}
+
+/// The variant of the block to place, decided by where it was placed. `hit_height` is the height
+/// of the point on the block face that was clicked.
+pub fn placement_variant(
+    block_variants: &BlockVariants,
+    block_id: BlockId,
+    block_face: BlockFace,
+    hit_height: f64,
+) -> BlockId {
+    for family in SLABS {
+        let Some(slab) = block_variants.state(family, block_id) else {
+            continue;
+        };
+
+        let top = match block_face {
+            BlockFace::Bottom => true,
+            BlockFace::Top => false,
+            _ => hit_height - hit_height.floor() > 0.5,
+        };
+        return slab.with_bool("top", top).block_id();
+    }
+
+    block_id
+}
+
+/// The direction the front of the block points, stairs have their low step at the front.
+fn front(block_state: Option<BlockState>) -> IVec3 {
+    match block_state.and_then(|state| state.rotation()) {
+        Some(BlockRotation::Front) | None => IVec3::Z,
+        Some(BlockRotation::Right) => IVec3::X,
+        Some(BlockRotation::Back) => IVec3::NEG_Z,
+        Some(BlockRotation::Left) => IVec3::NEG_X,
+    }
+}
+
+fn is_stairs(block_variants: &BlockVariants, block_id: BlockId) -> bool {
+    STAIRS
+        .iter()
+        .any(|family| block_variants.state(family, block_id).is_some())
+}
+
+// The shape the stairs at the position should have. The tall half of the stairs is at the
+// back, stairs behind it that are turned to the side cut it down to an outer corner, and stairs
+// in front of it that are turned to the side fill it out to an inner corner.
+fn stair_shape(
+    world_map: &WorldMap,
+    block_variants: &BlockVariants,
+    position: BlockPosition,
+) -> u8 {
+    let stairs_front = |position: BlockPosition| {
+        world_map
+            .get_block(position)
+            .filter(|block_id| is_stairs(block_variants, *block_id))
+            .map(|_| front(world_map.get_block_state(position)))
+    };
+
+    let facing = front(world_map.get_block_state(position));
+    // Looking at the stairs from the front
+    let right = IVec3::new(facing.z, 0, -facing.x);
+    let is_turned = |other: IVec3| other.dot(facing) == 0;
+    // A corner can't be made towards stairs that go the same way, they make a straight row.
+    let can_turn = |side: IVec3| stairs_front(position + side) != Some(facing);
+
+    if let Some(behind) = stairs_front(position - facing)
+        && is_turned(behind)
+        && can_turn(behind)
+    {
+        return if behind == right {
+            OUTER_LEFT
+        } else {
+            OUTER_RIGHT
+        };
+    }
+
+    if let Some(ahead) = stairs_front(position + facing)
+        && is_turned(ahead)
+        && can_turn(-ahead)
+    {
+        return if ahead == right {
+            INNER_LEFT
+        } else {
+            INNER_RIGHT
+        };
+    }
+
+    STRAIGHT
+}
+
+// Stairs change shape when stairs are placed or removed next to them
+fn shape_stairs(
+    world_map: Res<WorldMap>,
+    block_variants: Res<BlockVariants>,
+    suppression: Res<UpdateSuppression>,
+    mut changed_blocks: MessageReader<ChangedBlockEvent>,
+    mut block_update_writer: MessageWriter<BlockUpdate>,
+) {
+    for changed_block in changed_blocks.read() {
+        if suppression.is_suppressed(changed_block.position) {
+            continue;
+        }
+
+        if !is_stairs(&block_variants, changed_block.from.0)
+            && !is_stairs(&block_variants, changed_block.to.0)
+        {
+            continue;
+        }
+
+        for offset in [IVec3::ZERO, IVec3::X, IVec3::NEG_X, IVec3::Z, IVec3::NEG_Z] {
+            let position = changed_block.position + offset;
+            let Some(block_id) = world_map.get_block(position) else {
+                continue;
+            };
+            let Some(stairs) = STAIRS
+                .iter()
+                .find_map(|family| block_variants.state(family, block_id))
+            else {
+                continue;
+            };
+
+            let shape = stair_shape(&world_map, &block_variants, position);
+            if stairs.get("shape") == shape {
+                continue;
+            }
+
+            block_update_writer.write(BlockUpdate::Swap {
+                position,
+                block_id: stairs.with("shape", shape).block_id(),
+                block_state: world_map.get_block_state(position),
+            });
+        }
+    }
+}
+
+fn handle_block_hits(
+    world_map: Res<WorldMap>,
+    block_variants: Res<BlockVariants>,
+    mut block_hits: Query<
+        (&BlockPosition, &mut HandInteractions),
+        (With<Openable>, Changed<HandInteractions>),
+    >,
+    mut block_update_writer: MessageWriter<BlockUpdate>,
+) {
+    for (block_position, mut interactions) in block_hits.iter_mut() {
+        // Opening it twice in the same update leaves it as it was
+        if interactions.read().count() % 2 == 0 {
+            continue;
+        }
+
+        let Some(block) = world_map.get_block(*block_position).and_then(|block_id| {
+            OPENABLE
+                .iter()
+                .find_map(|family| block_variants.state(family, block_id))
+        }) else {
+            continue;
+        };
+
+        let open = block.get_bool("open");
+        block_update_writer.write(BlockUpdate::Swap {
+            position: *block_position,
+            block_id: block.with_bool("open", !open).block_id(),
+            block_state: world_map.get_block_state(*block_position),
+        });
+    }
+}
@@ -0,0 +1,401 @@
+use std::collections::{HashMap, HashSet};
+
+use fmc::{
+    bevy::math::DVec3,
+    blocks::{BlockData, BlockId, BlockPosition, BlockRotation, BlockState, Blocks},
+    items::{ItemStack, Items},
+    networking::Server,
+    players::Player,
+    prelude::*,
+    protocol::messages,
+    world::WorldMap,
+};
+use serde::Deserialize;
+
+use super::{
+    attached::{AttachedBlocks, Support},
+    power::{Power, PowerEvents, PowerSystems},
+};
+use crate::{items::DroppedItem, mobs::Mob, world::paste::PasteBlocks};
+
+const PUSHABILITY_PATH: &str = "./assets/server/pushability.json";
+
+/// The most blocks a piston can push at once
+pub const PUSH_LIMIT: usize = 12;
+
+/// Pistons extend while they're powered, pushing the row of blocks in front of them. Sticky
+/// pistons pull the block in front of them back when they retract.
+///
+/// The blocks of a row are moved as a single paste, so nothing reacts to the row while it is only
+/// partly moved. Block entities, like chests, keep their contents when they are moved.
+pub struct PistonPlugin;
+impl Plugin for PistonPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_message::<MovePiston>()
+            .add_systems(Startup, (setup, load_pushability))
+            .add_systems(
+                Update,
+                (handle_power.after(PowerSystems), move_pistons).chain(),
+            );
+    }
+}
+
+/// Extend or retract the piston at the position. Nothing happens if it is already extended or
+/// retracted, or if something is in the way.
+#[derive(Message)]
+pub struct MovePiston {
+    pub position: BlockPosition,
+    pub extend: bool,
+}
+
+/// Handles the power events of all pistons
+#[derive(Component)]
+struct Piston;
+
+/// How a block reacts to being pushed by a piston
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Pushability {
+    /// The block moves with the row
+    Normal,
+    /// The block is destroyed and dropped as an item, ending the row
+    Breaks,
+    /// The piston can't move the row
+    Immovable,
+}
+
+/// How each block reacts to pistons. Blocks that can't be broken and piston heads are always
+/// immovable, and blocks that can be replaced, like tall grass, break. The exceptions
+/// are read from `pushability.json`.
+#[derive(Resource)]
+pub struct PistonPushability {
+    blocks: HashMap<BlockId, Pushability>,
+    air: BlockId,
+}
+
+impl PistonPushability {
+    /// How the block reacts to being pushed, `None` if the piston can push into it as if it was
+    /// empty.
+    pub fn get(&self, block_id: BlockId) -> Option<Pushability> {
+        if block_id == self.air {
+            return None;
+        }
+
+        if let Some(pushability) = self.blocks.get(&block_id) {
+            return Some(*pushability);
+        }
+
+        let block_config = Blocks::get().get_config(&block_id);
+        if block_config.hardness.is_none() {
+            Some(Pushability::Immovable)
+        } else if block_config.replaceable {
+            Some(Pushability::Breaks)
+        } else {
+            Some(Pushability::Normal)
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct PushabilityJson {
+    #[serde(default)]
+    immovable: Vec<String>,
+    #[serde(default)]
+    breaks: Vec<String>,
+}
+
+fn load_pushability(mut commands: Commands) {
+    let file = match std::fs::File::open(PUSHABILITY_PATH) {
+        Ok(f) => f,
+        Err(e) => panic!(
+            "Failed to open piston pushability at path: {}\nError: {}",
+            PUSHABILITY_PATH, e
+        ),
+    };
+
+    let json: PushabilityJson = match serde_json::from_reader(file) {
+        Ok(p) => p,
+        Err(e) => panic!(
+            "Failed to read piston pushability at path: {}\nError: {}",
+            PUSHABILITY_PATH, e
+        ),
+    };
+
+    let blocks = Blocks::get();
+
+    let mut pushability = HashMap::new();
+    for (names, kind) in [
+        (json.immovable, Pushability::Immovable),
+        (json.breaks, Pushability::Breaks),
+    ] {
+        for name in names {
+            if !blocks.contains_block(&name) {
+                panic!(
+                    "Failed to read the piston pushability, there is no block with the name '{}'",
+                    name
+                );
+            }
+            pushability.insert(blocks.get_id(&name), kind);
+        }
+    }
+
+    for name in ["piston_head", "sticky_piston_head"] {
+        pushability.insert(blocks.get_id(name), Pushability::Immovable);
+    }
+
+    commands.insert_resource(PistonPushability {
+        blocks: pushability,
+        air: blocks.get_id("air"),
+    });
+}
+
+fn setup(
+    mut commands: Commands,
+    mut attached_blocks: ResMut<AttachedBlocks>,
+    mut power: ResMut<Power>,
+) {
+    let blocks = Blocks::get();
+
+    let handler = commands.spawn((PowerEvents::default(), Piston)).id();
+    for name in ["piston", "sticky_piston"] {
+        power.register_consumer(blocks.get_id(name), handler);
+    }
+
+    // The head is rotated the same way as the piston, so the piston is behind it. It pops off
+    // when the piston is broken.
+    for name in ["piston_head", "sticky_piston_head"] {
+        attached_blocks.register(blocks.get_id(name), Support::Behind);
+    }
+}
+
+// Pistons extend while they're powered
+fn handle_power(
+    mut power_events: Query<&mut PowerEvents, (With<Piston>, Changed<PowerEvents>)>,
+    mut move_events: MessageWriter<MovePiston>,
+) {
+    let Ok(mut events) = power_events.single_mut() else {
+        return;
+    };
+
+    for (block_position, powered) in events.read() {
+        move_events.write(MovePiston {
+            position: block_position,
+            extend: powered,
+        });
+    }
+}
+
+/// The direction the piston pushes in, the same way its front face points.
+fn facing(block_state: Option<BlockState>) -> IVec3 {
+    match block_state.and_then(|state| state.rotation()) {
+        Some(BlockRotation::Front) | None => IVec3::Z,
+        Some(BlockRotation::Right) => IVec3::X,
+        Some(BlockRotation::Back) => IVec3::NEG_Z,
+        Some(BlockRotation::Left) => IVec3::NEG_X,
+    }
+}
+
+fn head_id(sticky: bool) -> BlockId {
+    if sticky {
+        Blocks::get().get_id("sticky_piston_head")
+    } else {
+        Blocks::get().get_id("piston_head")
+    }
+}
+
+fn is_extended(world_map: &WorldMap, position: BlockPosition) -> bool {
+    let blocks = Blocks::get();
+    let sticky = world_map.get_block(position) == Some(blocks.get_id("sticky_piston"));
+    let front = position + facing(world_map.get_block_state(position));
+    world_map.get_block(front) == Some(head_id(sticky))
+}
+
+/// The blocks a piston would move when it extends
+struct PushedRow {
+    // The blocks that move, ordered from the piston and out
+    blocks: Vec<BlockPosition>,
+    // The block at the end of the row that is destroyed to make room
+    broken: Option<BlockPosition>,
+}
+
+fn pushed_row(
+    world_map: &WorldMap,
+    pushability: &PistonPushability,
+    start: BlockPosition,
+    direction: IVec3,
+) -> Option<PushedRow> {
+    let mut row = PushedRow {
+        blocks: Vec::new(),
+        broken: None,
+    };
+
+    let mut position = start;
+    loop {
+        // Can't push into chunks that aren't loaded
+        let block_id = world_map.get_block(position)?;
+
+        let blocks = Blocks::get();
+        let is_piston =
+            block_id == blocks.get_id("piston") || block_id == blocks.get_id("sticky_piston");
+        // Extended pistons would be pulled apart from their heads
+        let block_pushability = if is_piston && is_extended(world_map, position) {
+            Some(Pushability::Immovable)
+        } else {
+            pushability.get(block_id)
+        };
+
+        match block_pushability {
+            None => break,
+            Some(Pushability::Breaks) => {
+                row.broken = Some(position);
+                break;
+            }
+            Some(Pushability::Immovable) => return None,
+            Some(Pushability::Normal) => {
+                if row.blocks.len() == PUSH_LIMIT {
+                    return None;
+                }
+                row.blocks.push(position);
+            }
+        }
+
+        position = position + direction;
+    }
+
+    Some(row)
+}
+
+fn move_pistons(
+    mut commands: Commands,
+    net: Res<Server>,
+    items: Res<Items>,
+    world_map: Res<WorldMap>,
+    pushability: Res<PistonPushability>,
+    block_data_query: Query<(&BlockPosition, &BlockData)>,
+    mut entity_query: Query<
+        (Entity, &mut Transform, Has<Player>),
+        Or<(With<Player>, With<Mob>, With<DroppedItem>)>,
+    >,
+    mut move_events: MessageReader<MovePiston>,
+    mut paste_writer: MessageWriter<PasteBlocks>,
+) {
+    let blocks = Blocks::get();
+    let air = blocks.get_id("air");
+
+    for move_event in move_events.read() {
+        let Some(block_id) = world_map.get_block(move_event.position) else {
+            continue;
+        };
+        let sticky = block_id == blocks.get_id("sticky_piston");
+        if !sticky && block_id != blocks.get_id("piston") {
+            continue;
+        }
+
+        if is_extended(&world_map, move_event.position) == move_event.extend {
+            continue;
+        }
+
+        let block_state = world_map.get_block_state(move_event.position);
+        let direction = facing(block_state);
+        let front = move_event.position + direction;
+
+        // The blocks that move, and the offset they move by
+        let (moved, offset) = if move_event.extend {
+            let Some(row) = pushed_row(&world_map, &pushability, front, direction) else {
+                continue;
+            };
+
+            if let Some(broken) = row.broken {
+                let broken_id = world_map.get_block(broken).unwrap();
+                if let Some(item_id) = blocks.get_config(&broken_id).drop(None) {
+                    commands.spawn((
+                        DroppedItem::new(ItemStack::new(items.get_config(&item_id), 1)),
+                        Transform::from_translation(broken.as_dvec3() + DVec3::splat(0.5)),
+                    ));
+                }
+            }
+
+            (row.blocks, direction)
+        } else {
+            let pulled = front + direction;
+            let is_pullable = world_map
+                .get_block(pulled)
+                .is_some_and(|block_id| pushability.get(block_id) == Some(Pushability::Normal));
+            if sticky && is_pullable {
+                (vec![pulled], -direction)
+            } else {
+                (Vec::new(), -direction)
+            }
+        };
+
+        // What each position ends up as. The moved blocks leave air behind them, unless another
+        // block is moved into their place.
+        let mut paste: HashMap<BlockPosition, (BlockId, Option<BlockState>, Option<BlockData>)> =
+            HashMap::new();
+        for position in moved.iter() {
+            paste.insert(*position, (air, None, None));
+        }
+        if !move_event.extend {
+            paste.insert(front, (air, None, None));
+        }
+        for (position, block_data) in block_data_query.iter() {
+            if moved.contains(position) {
+                let block_id = world_map.get_block(*position).unwrap();
+                let block_state = world_map.get_block_state(*position);
+                paste.insert(
+                    *position + offset,
+                    (block_id, block_state, Some(block_data.clone())),
+                );
+            }
+        }
+        for position in moved.iter() {
+            paste.entry(*position + offset).or_insert_with(|| {
+                (
+                    world_map.get_block(*position).unwrap(),
+                    world_map.get_block_state(*position),
+                    None,
+                )
+            });
+        }
+        if move_event.extend {
+            let rotation = block_state
+                .and_then(|state| state.rotation())
+                .unwrap_or(BlockRotation::Front);
+            let head_state = BlockState::new().with_rotation(rotation);
+            paste.insert(front, (head_id(sticky), Some(head_state), None));
+        }
+
+        paste_writer.write(PasteBlocks {
+            blocks: paste
+                .into_iter()
+                .map(|(position, (block_id, block_state, block_data))| {
+                    (position, block_id, block_state, block_data)
+                })
+                .collect(),
+        });
+
+        // Entities inside or standing on the moved blocks move along with them, and the head
+        // pushes whatever is in front of the piston.
+        let mut carried: HashSet<BlockPosition> = moved.into_iter().collect();
+        if move_event.extend {
+            carried.insert(front);
+        }
+        for (entity, mut transform, is_player) in entity_query.iter_mut() {
+            let position = BlockPosition::from(transform.translation);
+            let below = BlockPosition::from(transform.translation - DVec3::Y * 0.05);
+            if !carried.contains(&position) && !carried.contains(&below) {
+                continue;
+            }
+
+            transform.translation += offset.as_dvec3();
+            if is_player {
+                net.send_one(
+                    entity,
+                    messages::PlayerPosition {
+                        position: transform.translation,
+                    },
+                );
+            }
+        }
+    }
+}
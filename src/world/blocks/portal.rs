@@ -0,0 +1,351 @@
+use std::collections::{HashMap, HashSet};
+
+use fmc::{
+    bevy::math::DVec3,
+    blocks::{BlockId, BlockPosition, Blocks},
+    networking::Server,
+    players::Player,
+    prelude::*,
+    protocol::messages,
+    world::{BlockUpdate, ChangedBlockEvent, WorldMap},
+};
+
+use crate::world::{dimensions::Dimension, paste::UpdateSuppression};
+
+/// Obsidian frames become portals to the nether when they are closed. Standing in a portal for a
+/// little while takes you to the other dimension, where a portal is built if there isn't one
+/// already.
+pub struct PortalPlugin;
+impl Plugin for PortalPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            ((light_portals, break_portals), enter_portals, arrive).chain(),
+        );
+    }
+}
+
+/// How long a player has to stand in a portal before being taken to the other dimension
+const PORTAL_DELAY: f32 = 3.0;
+/// Largest width or height of the inside of a portal frame
+const MAX_PORTAL_SIZE: i32 = 21;
+/// How far around the destination to look for an existing portal
+const PORTAL_SEARCH_RADIUS: i32 = 16;
+
+/// A player that is on their way through a portal. They are moved to the destination as soon as
+/// the terrain there is loaded.
+#[derive(Component)]
+struct PortalTravel {
+    destination: IVec3,
+    dimension: Dimension,
+}
+
+// Find the inside of a closed obsidian frame that includes the start position. The search is done
+// in the plane spanned by the y axis and the given horizontal axis.
+fn find_frame_interior(
+    world_map: &WorldMap,
+    start: BlockPosition,
+    horizontal: IVec3,
+    air: BlockId,
+    obsidian: BlockId,
+) -> Option<Vec<BlockPosition>> {
+    if world_map.get_block(start) != Some(air) {
+        return None;
+    }
+
+    let directions = [horizontal, -horizontal, IVec3::Y, IVec3::NEG_Y];
+
+    let mut interior = HashSet::from([start]);
+    let mut queue = vec![start];
+    let mut min = IVec3::new(start.x, start.y, start.z);
+    let mut max = min;
+
+    while let Some(position) = queue.pop() {
+        for direction in directions {
+            let neighbour = position + direction;
+            if interior.contains(&neighbour) {
+                continue;
+            }
+
+            match world_map.get_block(neighbour) {
+                Some(block_id) if block_id == obsidian => continue,
+                Some(block_id) if block_id == air => {
+                    interior.insert(neighbour);
+                    queue.push(neighbour);
+
+                    let neighbour = IVec3::new(neighbour.x, neighbour.y, neighbour.z);
+                    min = min.min(neighbour);
+                    max = max.max(neighbour);
+                    if (max - min).max_element() >= MAX_PORTAL_SIZE {
+                        return None;
+                    }
+                }
+                // Anything else, or unloaded terrain, means the frame isn't closed.
+                _ => return None,
+            }
+        }
+    }
+
+    let size = max - min + IVec3::ONE;
+    let width = (size * horizontal.abs()).max_element();
+    if width < 2 || size.y < 3 {
+        return None;
+    }
+
+    Some(interior.into_iter().collect())
+}
+
+// When obsidian is placed, check if it closes a frame, and fill it with portal blocks if it does.
+fn light_portals(
+    world_map: Res<WorldMap>,
+    mut changed_blocks: MessageReader<ChangedBlockEvent>,
+    mut block_updates: MessageWriter<BlockUpdate>,
+) {
+    let blocks = Blocks::get();
+    let obsidian = blocks.get_id("obsidian");
+    let air = blocks.get_id("air");
+    let portal = blocks.get_id("nether_portal");
+
+    for changed_block in changed_blocks.read() {
+        if changed_block.to.0 != obsidian {
+            continue;
+        }
+
+        for horizontal in [IVec3::X, IVec3::Z] {
+            for direction in [horizontal, -horizontal, IVec3::Y, IVec3::NEG_Y] {
+                let Some(interior) = find_frame_interior(
+                    &world_map,
+                    changed_block.position + direction,
+                    horizontal,
+                    air,
+                    obsidian,
+                ) else {
+                    continue;
+                };
+
+                block_updates.write_batch(interior.into_iter().map(|position| {
+                    BlockUpdate::Replace {
+                        position,
+                        block_id: portal,
+                        block_state: None,
+                        block_data: None,
+                    }
+                }));
+                break;
+            }
+        }
+    }
+}
+
+// Breaking the frame or any part of the portal removes the whole portal.
+fn break_portals(
+    world_map: Res<WorldMap>,
+    suppression: Res<UpdateSuppression>,
+    mut changed_blocks: MessageReader<ChangedBlockEvent>,
+    mut block_updates: MessageWriter<BlockUpdate>,
+) {
+    let blocks = Blocks::get();
+    let obsidian = blocks.get_id("obsidian");
+    let air = blocks.get_id("air");
+    let portal = blocks.get_id("nether_portal");
+
+    let mut removed = HashSet::new();
+
+    for changed_block in changed_blocks.read() {
+        let from = changed_block.from.0;
+        if (from != obsidian && from != portal)
+            || changed_block.to.0 == from
+            || suppression.is_suppressed(changed_block.position)
+        {
+            continue;
+        }
+
+        let mut queue = vec![changed_block.position];
+        while let Some(position) = queue.pop() {
+            for direction in [
+                IVec3::X,
+                IVec3::NEG_X,
+                IVec3::Y,
+                IVec3::NEG_Y,
+                IVec3::Z,
+                IVec3::NEG_Z,
+            ] {
+                let neighbour = position + direction;
+                if world_map.get_block(neighbour) == Some(portal) && removed.insert(neighbour) {
+                    queue.push(neighbour);
+                }
+            }
+        }
+    }
+
+    block_updates.write_batch(removed.into_iter().map(|position| BlockUpdate::Replace {
+        position,
+        block_id: air,
+        block_state: None,
+        block_data: None,
+    }));
+}
+
+fn enter_portals(
+    mut commands: Commands,
+    net: Res<Server>,
+    time: Res<Time>,
+    world_map: Res<WorldMap>,
+    mut players: Query<(Entity, &mut Transform, Has<PortalTravel>), With<Player>>,
+    // Time each player has been standing in a portal, None if they have to step out of it first.
+    mut in_portal: Local<HashMap<Entity, Option<Timer>>>,
+) {
+    let portal = Blocks::get().get_id("nether_portal");
+
+    for (player_entity, mut transform, is_travelling) in players.iter_mut() {
+        if is_travelling {
+            continue;
+        }
+
+        let block_position = BlockPosition::from(transform.translation);
+        if world_map.get_block(block_position) != Some(portal) {
+            in_portal.remove(&player_entity);
+            continue;
+        }
+
+        let Some(timer) = in_portal
+            .entry(player_entity)
+            .or_insert_with(|| Some(Timer::from_seconds(PORTAL_DELAY, TimerMode::Once)))
+        else {
+            continue;
+        };
+
+        timer.tick(time.delta());
+        if !timer.is_finished() {
+            continue;
+        }
+
+        // Have to step out of the portal they arrive in before it can be used again.
+        in_portal.insert(player_entity, None);
+
+        let (dimension, destination) = Dimension::travel(transform.translation);
+
+        // Move the player right away so the terrain at the destination starts loading, they are
+        // moved to the exact spot once it has.
+        transform.translation = destination;
+        net.send_one(
+            player_entity,
+            messages::PlayerPosition {
+                position: destination,
+            },
+        );
+
+        commands.entity(player_entity).insert(PortalTravel {
+            destination: destination.floor().as_ivec3(),
+            dimension,
+        });
+    }
+
+    in_portal.retain(|entity, _| players.contains(*entity));
+}
+
+// Finish portal travel once the terrain at the destination has loaded. Players arrive in the
+// closest portal, if there is none, one is built.
+fn arrive(
+    mut commands: Commands,
+    net: Res<Server>,
+    world_map: Res<WorldMap>,
+    mut players: Query<(Entity, &PortalTravel, &mut Transform, &mut Dimension), With<Player>>,
+    mut block_updates: MessageWriter<BlockUpdate>,
+) {
+    let blocks = Blocks::get();
+    let air = blocks.get_id("air");
+    let obsidian = blocks.get_id("obsidian");
+    let portal = blocks.get_id("nether_portal");
+
+    for (player_entity, travel, mut transform, mut dimension) in players.iter_mut() {
+        let heights = travel.dimension.portal_heights();
+
+        let is_loaded = |x: i32, z: i32| {
+            heights
+                .clone()
+                .step_by(16)
+                .all(|y| world_map.get_block(BlockPosition::new(x, y, z)).is_some())
+        };
+
+        let destination = travel.destination;
+        if !is_loaded(destination.x, destination.z) {
+            continue;
+        }
+
+        // Closest portal block that has another portal block beneath it, so the player arrives
+        // standing at the bottom of the portal.
+        let mut closest_portal: Option<(BlockPosition, i32)> = None;
+        for x in -PORTAL_SEARCH_RADIUS..=PORTAL_SEARCH_RADIUS {
+            for z in -PORTAL_SEARCH_RADIUS..=PORTAL_SEARCH_RADIUS {
+                for y in heights.clone() {
+                    let position = BlockPosition::new(destination.x + x, y, destination.z + z);
+                    if world_map.get_block(position) != Some(portal)
+                        || world_map.get_block(position - IVec3::Y) == Some(portal)
+                    {
+                        continue;
+                    }
+
+                    let distance = (IVec3::new(position.x, position.y, position.z) - destination)
+                        .length_squared();
+                    if closest_portal.is_none_or(|(_, closest)| distance < closest) {
+                        closest_portal = Some((position, distance));
+                    }
+                }
+            }
+        }
+
+        let arrival = if let Some((position, _)) = closest_portal {
+            position
+        } else {
+            // Find the lowest open space in the column that has ground beneath it, if there is
+            // none the portal is carved into the terrain at the destination.
+            let ground = heights.clone().find(|y| {
+                let position = BlockPosition::new(destination.x, *y, destination.z);
+                world_map
+                    .get_block(position)
+                    .is_some_and(|block_id| block_id != air)
+                    && (1..=4).all(|above| {
+                        world_map.get_block(position + IVec3::new(0, above, 0)) == Some(air)
+                    })
+            });
+            let floor = ground.unwrap_or(destination.y);
+            let origin = BlockPosition::new(destination.x, floor, destination.z);
+
+            // A 4x5 frame with a 2x3 portal, standing on an obsidian platform
+            let mut updates = Vec::new();
+            for x in -1..=2 {
+                for y in 0..=4 {
+                    let position = origin + IVec3::new(x, y, 0);
+                    let is_frame = x == -1 || x == 2 || y == 0 || y == 4;
+                    updates.push((position, if is_frame { obsidian } else { portal }));
+                }
+
+                for z in [-1, 1] {
+                    updates.push((origin + IVec3::new(x, 0, z), obsidian));
+                    for y in 1..=3 {
+                        updates.push((origin + IVec3::new(x, y, z), air));
+                    }
+                }
+            }
+
+            block_updates.write_batch(updates.into_iter().map(|(position, block_id)| {
+                BlockUpdate::Replace {
+                    position,
+                    block_id,
+                    block_state: None,
+                    block_data: None,
+                }
+            }));
+
+            origin + IVec3::Y
+        };
+
+        let position = arrival.as_dvec3() + DVec3::new(0.5, 0.0, 0.5);
+        transform.translation = position;
+        net.send_one(player_entity, messages::PlayerPosition { position });
+
+        *dimension = travel.dimension;
+        commands.entity(player_entity).remove::<PortalTravel>();
+    }
+}
@@ -0,0 +1,352 @@
+use std::collections::{BinaryHeap, HashMap, HashSet, VecDeque};
+
+use fmc::{
+    blocks::{BlockId, BlockPosition},
+    prelude::*,
+    world::{BlockUpdate, ChangedBlockEvent, WorldMap},
+};
+
+use super::attached::{AttachedBlocks, Support};
+use crate::world::{
+    block_variants::{BlockVariantSystems, BlockVariants},
+    paste::UpdateSuppression,
+};
+
+/// Power is sent from sources, like levers, through redstone wire to the blocks that use it, like
+/// doors and lamps.
+///
+/// Each wire carries a power level, one less than the strongest wire or source next to it. The
+/// level is stored as a variant of the wire block, and is only recalculated for the wires around a
+/// block when it changes, never by scanning the world.
+pub struct PowerPlugin;
+impl Plugin for PowerPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(Power::default())
+            .add_systems(Startup, setup.after(BlockVariantSystems))
+            .add_systems(
+                Update,
+                (propagate_power, notify_consumers)
+                    .chain()
+                    .in_set(PowerSystems),
+            );
+    }
+}
+
+/// Order systems that read [PowerEvents] after this [SystemSet] to receive them the same frame
+/// the power changes.
+#[derive(SystemSet, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct PowerSystems;
+
+/// The strongest power level, what sources give out.
+pub const MAX_POWER: u8 = 15;
+
+// Wires connect to the wires next to them, and to the wires one step up or down from those.
+const WIRE_CONNECTIONS: [IVec3; 12] = [
+    IVec3::new(1, 0, 0),
+    IVec3::new(-1, 0, 0),
+    IVec3::new(0, 0, 1),
+    IVec3::new(0, 0, -1),
+    IVec3::new(1, 1, 0),
+    IVec3::new(-1, 1, 0),
+    IVec3::new(0, 1, 1),
+    IVec3::new(0, 1, -1),
+    IVec3::new(1, -1, 0),
+    IVec3::new(-1, -1, 0),
+    IVec3::new(0, -1, 1),
+    IVec3::new(0, -1, -1),
+];
+
+const NEIGHBOURS: [IVec3; 6] = [
+    IVec3::X,
+    IVec3::NEG_X,
+    IVec3::Y,
+    IVec3::NEG_Y,
+    IVec3::Z,
+    IVec3::NEG_Z,
+];
+
+/// Keeps track of which blocks give out power, and which entities handle the blocks that use it.
+///
+/// A block that uses power must have a handler entity registered. When a source or wire next to a
+/// block of that type is turned on or off, the position of the block is pushed to the handler's
+/// [PowerEvents] together with whether it is powered.
+#[derive(Resource, Default)]
+pub struct Power {
+    sources: HashMap<BlockId, u8>,
+    consumers: HashMap<BlockId, Entity>,
+}
+
+impl Power {
+    /// Make the block a source of power, it powers the wires and blocks on all of its sides.
+    pub fn register_source(&mut self, block_id: BlockId, power: u8) {
+        self.sources.insert(block_id, power.min(MAX_POWER));
+    }
+
+    /// Register a handler entity for a block that uses power.
+    pub fn register_consumer(&mut self, block_id: BlockId, handler_entity: Entity) {
+        self.consumers.insert(block_id, handler_entity);
+    }
+
+    /// The power level the block gives out, zero if it is neither a source nor a wire.
+    pub fn output(&self, block_variants: &BlockVariants, block_id: BlockId) -> u8 {
+        if let Some(power) = self.sources.get(&block_id) {
+            *power
+        } else if let Some(wire) = block_variants.state("redstone_wire", block_id) {
+            wire.get("power")
+        } else {
+            0
+        }
+    }
+
+    /// If any of the blocks next to the position gives out power.
+    pub fn is_powered(
+        &self,
+        world_map: &WorldMap,
+        block_variants: &BlockVariants,
+        position: BlockPosition,
+    ) -> bool {
+        NEIGHBOURS.iter().any(|offset| {
+            world_map
+                .get_block(position + *offset)
+                .is_some_and(|block_id| self.output(block_variants, block_id) > 0)
+        })
+    }
+}
+
+/// Blocks that use power and had the power next to them turned on or off during the last update,
+/// and if they are powered now.
+///
+/// Attach this to an entity and register the entity as a consumer in [Power].
+#[derive(Component, Default)]
+pub struct PowerEvents(Vec<(BlockPosition, bool)>);
+
+impl PowerEvents {
+    pub fn read(&mut self) -> impl Iterator<Item = (BlockPosition, bool)> + '_ {
+        self.0.drain(..)
+    }
+}
+
+fn setup(block_variants: Res<BlockVariants>, mut attached_blocks: ResMut<AttachedBlocks>) {
+    let wire = block_variants.builder("redstone_wire");
+    for power in 0..wire.value_count("power") {
+        let block_id = block_variants
+            .builder("redstone_wire")
+            .with("power", power)
+            .block_id();
+        attached_blocks.register(block_id, Support::Below);
+    }
+}
+
+// Levels the wires have been changed to but that haven't been written to the world yet.
+struct WireLevels<'a> {
+    world_map: &'a WorldMap,
+    block_variants: &'a BlockVariants,
+    changed: HashMap<BlockPosition, u8>,
+}
+
+impl WireLevels<'_> {
+    fn get(&self, position: BlockPosition) -> Option<u8> {
+        let block_id = self.world_map.get_block(position)?;
+        let wire = self.block_variants.state("redstone_wire", block_id)?;
+        Some(
+            self.changed
+                .get(&position)
+                .copied()
+                .unwrap_or(wire.get("power")),
+        )
+    }
+
+    fn strongest_source(&self, power: &Power, position: BlockPosition) -> u8 {
+        NEIGHBOURS
+            .iter()
+            .filter_map(|offset| self.world_map.get_block(position + *offset))
+            .filter_map(|block_id| power.sources.get(&block_id).copied())
+            .max()
+            .unwrap_or(0)
+    }
+}
+
+// When a block changes, the wires that could have been powered through it are turned off, then
+// power is spread back into them from the sources and the wires that kept their power. This is
+// the same way light is usually updated, only the wires around the change are visited.
+fn propagate_power(
+    world_map: Res<WorldMap>,
+    block_variants: Res<BlockVariants>,
+    power: Res<Power>,
+    suppression: Res<UpdateSuppression>,
+    mut changed_blocks: MessageReader<ChangedBlockEvent>,
+    mut block_update_writer: MessageWriter<BlockUpdate>,
+) {
+    let mut levels = WireLevels {
+        world_map: &world_map,
+        block_variants: &block_variants,
+        changed: HashMap::new(),
+    };
+
+    // Wires whose power is removed, with the level they had
+    let mut removal = VecDeque::new();
+    // Wires that can spread power to the ones that were turned off
+    let mut relight = Vec::new();
+
+    for changed_block in changed_blocks.read() {
+        if suppression.is_suppressed(changed_block.position) {
+            continue;
+        }
+
+        let was_wire = block_variants
+            .state("redstone_wire", changed_block.from.0)
+            .is_some();
+        let is_wire = block_variants
+            .state("redstone_wire", changed_block.to.0)
+            .is_some();
+
+        // The wire levels are changed by this system, they are already taken care of.
+        if was_wire && is_wire {
+            continue;
+        }
+
+        let from_output = power.output(&block_variants, changed_block.from.0);
+        let to_output = power.output(&block_variants, changed_block.to.0);
+        if !was_wire && !is_wire && from_output == to_output {
+            continue;
+        }
+
+        // The changed block is removed at the level it had, which turns off the wires that got
+        // their power from it. Wires next to a source have the same level as the source, so it
+        // counts as one higher.
+        let from_level = if was_wire {
+            from_output
+        } else {
+            from_output + 1
+        };
+        removal.push_back((changed_block.position, from_level));
+        if is_wire {
+            levels.changed.insert(changed_block.position, 0);
+            relight.push(changed_block.position);
+        }
+
+        for offset in NEIGHBOURS {
+            let position = changed_block.position + offset;
+            if levels.get(position).is_some() {
+                relight.push(position);
+            }
+        }
+    }
+
+    while let Some((position, old_level)) = removal.pop_front() {
+        for offset in WIRE_CONNECTIONS.iter().chain(NEIGHBOURS.iter()) {
+            let neighbour = position + *offset;
+            let Some(level) = levels.get(neighbour) else {
+                continue;
+            };
+
+            if level == 0 {
+                continue;
+            } else if level < old_level {
+                levels.changed.insert(neighbour, 0);
+                removal.push_back((neighbour, level));
+            }
+            // Either it was turned off and will be relit if there's still a path to a source, or
+            // it has its power from somewhere else and spreads it back.
+            relight.push(neighbour);
+        }
+    }
+
+    let mut queue = BinaryHeap::new();
+    for position in relight {
+        let Some(level) = levels.get(position) else {
+            continue;
+        };
+        let level = level.max(levels.strongest_source(&power, position));
+        if level > 0 {
+            levels.changed.insert(position, level);
+            queue.push((level, position.x, position.y, position.z));
+        }
+    }
+
+    // The strongest wires are spread from first, so each wire is only raised once to its final
+    // level.
+    let mut visited = HashSet::new();
+    while let Some((level, x, y, z)) = queue.pop() {
+        let position = BlockPosition::new(x, y, z);
+        if !visited.insert(position) || levels.get(position) != Some(level) {
+            continue;
+        }
+
+        for offset in WIRE_CONNECTIONS {
+            let neighbour = position + offset;
+            let Some(neighbour_level) = levels.get(neighbour) else {
+                continue;
+            };
+
+            if neighbour_level + 1 < level {
+                levels.changed.insert(neighbour, level - 1);
+                queue.push((level - 1, neighbour.x, neighbour.y, neighbour.z));
+            }
+        }
+    }
+
+    for (position, level) in levels.changed {
+        let Some(block_id) = world_map.get_block(position) else {
+            continue;
+        };
+        let Some(wire) = block_variants.state("redstone_wire", block_id) else {
+            continue;
+        };
+        if wire.get("power") == level {
+            continue;
+        }
+
+        block_update_writer.write(BlockUpdate::Swap {
+            position,
+            block_id: wire.with("power", level).block_id(),
+            block_state: None,
+        });
+    }
+}
+
+fn notify_consumers(
+    world_map: Res<WorldMap>,
+    block_variants: Res<BlockVariants>,
+    power: Res<Power>,
+    suppression: Res<UpdateSuppression>,
+    mut changed_blocks: MessageReader<ChangedBlockEvent>,
+    mut handler_query: Query<&mut PowerEvents>,
+) {
+    let mut push = |position: BlockPosition| {
+        let Some(block_id) = world_map.get_block(position) else {
+            return;
+        };
+        let Some(handler_entity) = power.consumers.get(&block_id) else {
+            return;
+        };
+        if let Ok(mut events) = handler_query.get_mut(*handler_entity) {
+            let powered = power.is_powered(&world_map, &block_variants, position);
+            events.0.push((position, powered));
+        }
+    };
+
+    for changed_block in changed_blocks.read() {
+        if suppression.is_suppressed(changed_block.position) {
+            continue;
+        }
+
+        // A block that uses power was placed, it might have been placed next to a source.
+        if changed_block.from.0 != changed_block.to.0
+            && power.consumers.contains_key(&changed_block.to.0)
+        {
+            push(changed_block.position);
+        }
+
+        // Wires changing between two levels of power doesn't turn anything on or off.
+        let was_on = power.output(&block_variants, changed_block.from.0) > 0;
+        let is_on = power.output(&block_variants, changed_block.to.0) > 0;
+        if was_on == is_on {
+            continue;
+        }
+
+        for offset in NEIGHBOURS {
+            push(changed_block.position + offset);
+        }
+    }
+}
@@ -0,0 +1,77 @@
+use fmc::{
+    blocks::{BlockFace, BlockPosition, Blocks},
+    prelude::*,
+    world::{BlockUpdate, ChangedBlockEvent, WorldMap},
+};
+
+use crate::world::paste::UpdateSuppression;
+
+use super::water::Water;
+
+/// Sponges soak up the water around them when they are placed, or when water flows up to them.
+/// A sponge that has absorbed water turns into a wet sponge, which has to be dried in a furnace
+/// before it can be used again.
+pub struct SpongePlugin;
+impl Plugin for SpongePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, absorb_water);
+    }
+}
+
+/// How far from the sponge water is absorbed
+const SPONGE_RADIUS: u32 = 6;
+/// The most water blocks a sponge can absorb at once
+const SPONGE_CAPACITY: usize = 65;
+
+fn absorb_water(
+    water: Res<Water>,
+    world_map: Res<WorldMap>,
+    suppression: Res<UpdateSuppression>,
+    mut changed_blocks: MessageReader<ChangedBlockEvent>,
+    mut block_updates: MessageWriter<BlockUpdate>,
+) {
+    let blocks = Blocks::get();
+    let sponge = blocks.get_id("sponge");
+    let wet_sponge = blocks.get_id("wet_sponge");
+
+    let mut sponges: Vec<BlockPosition> = Vec::new();
+    for changed_block in changed_blocks.read() {
+        if suppression.is_suppressed(changed_block.position) {
+            continue;
+        }
+
+        if changed_block.to.0 == sponge {
+            sponges.push(changed_block.position);
+        } else if water.is_water(changed_block.to.0, changed_block.to.1) {
+            for block_face in [
+                BlockFace::Front,
+                BlockFace::Right,
+                BlockFace::Back,
+                BlockFace::Left,
+                BlockFace::Top,
+                BlockFace::Bottom,
+            ] {
+                if changed_block[block_face].is_some_and(|block| block.0 == sponge) {
+                    sponges.push(block_face.shift_position(changed_block.position));
+                }
+            }
+        }
+    }
+    sponges.sort_by_key(|position| (position.x, position.y, position.z));
+    sponges.dedup();
+
+    for position in sponges {
+        let absorbed = water.connected(&world_map, position, SPONGE_RADIUS, SPONGE_CAPACITY);
+        if absorbed.is_empty() {
+            continue;
+        }
+
+        water.remove(absorbed, &mut block_updates);
+        block_updates.write(BlockUpdate::Replace {
+            position,
+            block_id: wet_sponge,
+            block_state: None,
+            block_data: None,
+        });
+    }
+}
@@ -1,14 +1,25 @@
 use std::{
-    collections::HashMap,
+    collections::{HashMap, HashSet, VecDeque},
     ops::{Index, IndexMut},
 };
 
 use fmc::{
+    bevy::math::DVec3,
     blocks::{BlockFace, BlockId, BlockPosition, BlockRotation, BlockState, Blocks},
+    physics::Physics,
     prelude::*,
-    world::{BlockUpdate, ChangedBlockEvent},
+    world::{BlockUpdate, ChangedBlockEvent, WorldMap},
 };
 
+use crate::{items::DroppedItem, world::paste::UpdateSuppression};
+
+/// Water spreads out from sources and flows downhill, drying up again when its source is removed.
+///
+/// Water puts out fire it flows into, and dropped items that lie in flowing water are carried
+/// along with it.
+///
+/// Other systems can find and remove bodies of water through the [Water] resource, or with a
+/// [DrainWater] message.
 pub(super) struct WaterPlugin;
 impl Plugin for WaterPlugin {
     fn build(&self, app: &mut App) {
@@ -16,8 +27,12 @@ impl Plugin for WaterPlugin {
             std::time::Duration::from_millis(200),
             TimerMode::Repeating,
         )))
+        .add_message::<DrainWater>()
         .add_systems(Startup, setup)
-        .add_systems(Update, spread_water);
+        .add_systems(
+            Update,
+            (spread_water, drain_water, extinguish_fire, push_items),
+        );
     }
 }
 
@@ -249,13 +264,97 @@ fn setup(mut commands: Commands, blocks: Res<Blocks>) {
     commands.insert_resource(water);
 }
 
+/// Remove the water that is connected to the position, no further than `radius` blocks from it.
+#[derive(Message)]
+pub struct DrainWater {
+    pub position: BlockPosition,
+    pub radius: u32,
+}
+
+/// The most water blocks a single [DrainWater] removes
+const MAX_DRAINED: usize = 4096;
+
+/// Maps between water blocks and the water levels they represent.
 #[derive(Resource, Default)]
-struct Water {
+pub struct Water {
     water_to_block: HashMap<WaterBlock, (BlockId, Option<BlockState>)>,
     block_to_water: HashMap<(BlockId, Option<BlockState>), WaterBlock>,
 }
 
 impl Water {
+    /// If the block is water, both sources and flowing water.
+    pub fn is_water(&self, block_id: BlockId, block_state: Option<BlockState>) -> bool {
+        self.block_to_water.contains_key(&(block_id, block_state))
+    }
+
+    /// The water that can be reached from `start` by moving through water, no further than
+    /// `radius` blocks from it along any axis. The start itself doesn't have to be water. At most
+    /// `limit` positions are returned, the closest ones first.
+    pub fn connected(
+        &self,
+        world_map: &WorldMap,
+        start: BlockPosition,
+        radius: u32,
+        limit: usize,
+    ) -> Vec<BlockPosition> {
+        let mut found = Vec::new();
+        let mut visited = HashSet::from([start]);
+        let mut queue = VecDeque::from([start]);
+
+        while let Some(position) = queue.pop_front() {
+            for offset in [
+                IVec3::X,
+                IVec3::NEG_X,
+                IVec3::Y,
+                IVec3::NEG_Y,
+                IVec3::Z,
+                IVec3::NEG_Z,
+            ] {
+                let neighbour = position + offset;
+                let distance = IVec3::new(
+                    neighbour.x - start.x,
+                    neighbour.y - start.y,
+                    neighbour.z - start.z,
+                )
+                .abs()
+                .max_element() as u32;
+                if distance > radius || !visited.insert(neighbour) {
+                    continue;
+                }
+
+                let Some(block_id) = world_map.get_block(neighbour) else {
+                    continue;
+                };
+                if !self.is_water(block_id, world_map.get_block_state(neighbour)) {
+                    continue;
+                }
+
+                found.push(neighbour);
+                if found.len() == limit {
+                    return found;
+                }
+                queue.push_back(neighbour);
+            }
+        }
+
+        found
+    }
+
+    /// Replace the water at the positions with air.
+    pub fn remove(
+        &self,
+        positions: impl IntoIterator<Item = BlockPosition>,
+        block_updates: &mut MessageWriter<BlockUpdate>,
+    ) {
+        let air = Blocks::get().get_id("air");
+        block_updates.write_batch(positions.into_iter().map(|position| BlockUpdate::Replace {
+            position,
+            block_id: air,
+            block_state: None,
+            block_data: None,
+        }));
+    }
+
     #[track_caller]
     fn add(&mut self, mut water_block: WaterBlock, block_ids: Vec<BlockId>) {
         for block_id in block_ids {
@@ -392,6 +491,27 @@ impl WaterBlock {
         }
     }
 
+    /// The horizontal direction the water flows in, from the high corners towards the low ones.
+    /// Still water and water that is level has no flow.
+    fn flow(&self, position: BlockPosition) -> DVec3 {
+        let offset = |block_face: BlockFace| {
+            block_face.shift_position(position).as_dvec3() - position.as_dvec3()
+        };
+
+        let mut flow = DVec3::ZERO;
+        for (corner, block_faces) in [
+            (Corner::Left, [BlockFace::Left, BlockFace::Front]),
+            (Corner::Right, [BlockFace::Right, BlockFace::Front]),
+            (Corner::FarRight, [BlockFace::Right, BlockFace::Back]),
+            (Corner::FarLeft, [BlockFace::Left, BlockFace::Back]),
+        ] {
+            let direction = offset(block_faces[0]) + offset(block_faces[1]);
+            flow -= direction * self[corner] as u8 as f64;
+        }
+
+        flow.normalize_or_zero()
+    }
+
     fn update_corner(&mut self, corner: Corner, water_level: WaterLevel) {
         if self.corners[corner as usize] == water_level
             || self.corners[corner as usize] == WaterLevel::Ten
@@ -548,6 +668,7 @@ fn spread_water(
     water: Res<Water>,
     time: Res<Time>,
     mut update_timer: ResMut<WaterUpdateTimer>,
+    suppression: Res<UpdateSuppression>,
     mut changed_blocks: MessageReader<ChangedBlockEvent>,
     mut block_updates: MessageWriter<BlockUpdate>,
     mut updates: Local<HashMap<BlockPosition, WaterBlock>>,
@@ -559,6 +680,10 @@ fn spread_water(
         // If there's an update waiting to be sent, but the block is changed, the update is stale
         updates.remove(&changed_block.position);
 
+        if suppression.is_suppressed(changed_block.position) {
+            continue;
+        }
+
         let change_as_water = ChangedBlockAsWater::new(changed_block, &water);
 
         let mut water_block = if let Some(to) = &change_as_water.to {
@@ -825,3 +950,93 @@ fn spread_water(
         }));
     }
 }
+
+fn drain_water(
+    water: Res<Water>,
+    world_map: Res<WorldMap>,
+    mut drain_events: MessageReader<DrainWater>,
+    mut block_updates: MessageWriter<BlockUpdate>,
+) {
+    for drain in drain_events.read() {
+        let mut positions = water.connected(&world_map, drain.position, drain.radius, MAX_DRAINED);
+        if world_map.get_block(drain.position).is_some_and(|block_id| {
+            water.is_water(block_id, world_map.get_block_state(drain.position))
+        }) {
+            positions.push(drain.position);
+        }
+        water.remove(positions, &mut block_updates);
+    }
+}
+
+// Fire that water flows next to or falls onto goes out. The fire is replaced by air, which lets
+// the water spread into the space it left.
+fn extinguish_fire(
+    water: Res<Water>,
+    suppression: Res<UpdateSuppression>,
+    mut changed_blocks: MessageReader<ChangedBlockEvent>,
+    mut block_updates: MessageWriter<BlockUpdate>,
+) {
+    let blocks = Blocks::get();
+    let fire = blocks.get_id("fire");
+    let air = blocks.get_id("air");
+
+    for changed_block in changed_blocks.read() {
+        if suppression.is_suppressed(changed_block.position)
+            || !water.block_to_water.contains_key(&changed_block.to)
+        {
+            continue;
+        }
+
+        for block_face in [
+            BlockFace::Front,
+            BlockFace::Right,
+            BlockFace::Back,
+            BlockFace::Left,
+            BlockFace::Bottom,
+        ] {
+            if changed_block[block_face].is_some_and(|block| block.0 == fire) {
+                block_updates.write(BlockUpdate::Replace {
+                    position: block_face.shift_position(changed_block.position),
+                    block_id: air,
+                    block_state: None,
+                    block_data: None,
+                });
+            }
+        }
+    }
+}
+
+/// The speed dropped items are carried at by flowing water
+const FLOW_SPEED: f64 = 2.0;
+/// How quickly items pick up the speed of the water
+const FLOW_ACCELERATION: f64 = 4.0;
+
+fn push_items(
+    water: Res<Water>,
+    time: Res<Time>,
+    world_map: Res<WorldMap>,
+    mut dropped_items: Query<(&mut Physics, &Transform), With<DroppedItem>>,
+) {
+    let blend = (FLOW_ACCELERATION * time.delta_secs_f64()).min(1.0);
+
+    for (mut physics, transform) in dropped_items.iter_mut() {
+        let block_position = BlockPosition::from(transform.translation);
+        let Some(block_id) = world_map.get_block(block_position) else {
+            continue;
+        };
+        let block_state = world_map.get_block_state(block_position);
+        let Some(water_block) = water.block_to_water.get(&(block_id, block_state)) else {
+            continue;
+        };
+
+        let flow = water_block.flow(block_position);
+        if flow == DVec3::ZERO {
+            continue;
+        }
+
+        // Only the horizontal speed is changed, the items still sink and float as before.
+        let target = flow * FLOW_SPEED;
+        physics.velocity.x += (target.x - physics.velocity.x) * blend;
+        physics.velocity.z += (target.z - physics.velocity.z) * blend;
+    }
+}
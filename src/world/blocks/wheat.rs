@@ -1,139 +1,145 @@
+use std::time::Duration;
+
 use fmc::{
-    bevy::ecs::system::EntityCommands,
-    blocks::{BlockData, BlockPosition, Blocks},
+    blocks::{BlockId, BlockPosition},
     prelude::*,
-    world::BlockUpdate,
+    random::Rng,
+    world::{BlockUpdate, ChangedBlockEvent, ChunkLoadEvent, WorldMap, chunk::Chunk},
+};
+
+use super::attached::{AttachedBlocks, Support};
+use crate::world::{
+    block_ticks::{BlockTickEvents, BlockTickSystems, BlockTicks},
+    block_variants::{BlockVariantSystems, BlockVariants},
 };
 
 pub struct WheatPlugin;
 impl Plugin for WheatPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(Startup, setup).add_systems(Update, grow);
+        app.add_systems(Startup, setup.after(BlockVariantSystems))
+            .add_systems(
+                Update,
+                (
+                    schedule_placed_wheat,
+                    schedule_loaded_wheat,
+                    grow.after(BlockTickSystems),
+                ),
+            );
     }
 }
 
 #[derive(Component)]
-struct Wheat {
-    stage: u8,
-    grow_timer: Timer,
-}
+struct Wheat;
+
+/// Wheat on dry soil only grows on this fraction of its ticks.
+const DRY_GROWTH_CHANCE: f32 = 0.33;
+/// How long wheat waits between each time it tries to grow
+const GROWTH_INTERVAL: Duration = Duration::from_secs(1);
+
+fn setup(
+    mut commands: Commands,
+    block_variants: Res<BlockVariants>,
+    mut block_ticks: ResMut<BlockTicks>,
+    mut attached_blocks: ResMut<AttachedBlocks>,
+) {
+    let handler = commands.spawn((BlockTickEvents::default(), Wheat)).id();
 
-impl Default for Wheat {
-    fn default() -> Self {
-        Self {
-            stage: 0,
-            grow_timer: Timer::from_seconds(1.0, TimerMode::Repeating),
-        }
+    let wheat = block_variants.builder("wheat");
+    // The last stage is fully grown and does not need to be ticked
+    for age in 0..wheat.value_count("age") - 1 {
+        let block_id = block_variants.builder("wheat").with("age", age).block_id();
+        block_ticks.register(block_id, handler, false);
     }
-}
-
-fn setup(mut blocks: ResMut<Blocks>) {
-    let block_id = blocks.get_id("wheat_0");
-    let block = blocks.get_config_mut(&block_id);
-    block.set_spawn_function(spawn_wheat_0);
-
-    let block_id = blocks.get_id("wheat_1");
-    let block = blocks.get_config_mut(&block_id);
-    block.set_spawn_function(spawn_wheat_1);
-
-    let block_id = blocks.get_id("wheat_2");
-    let block = blocks.get_config_mut(&block_id);
-    block.set_spawn_function(spawn_wheat_2);
 
-    let block_id = blocks.get_id("wheat_3");
-    let block = blocks.get_config_mut(&block_id);
-    block.set_spawn_function(spawn_wheat_3);
-
-    let block_id = blocks.get_id("wheat_4");
-    let block = blocks.get_config_mut(&block_id);
-    block.set_spawn_function(spawn_wheat_4);
-
-    let block_id = blocks.get_id("wheat_5");
-    let block = blocks.get_config_mut(&block_id);
-    block.set_spawn_function(spawn_wheat_5);
-
-    let block_id = blocks.get_id("wheat_6");
-    let block = blocks.get_config_mut(&block_id);
-    block.set_spawn_function(spawn_wheat_6);
+    // Crops pop off when the soil below them is removed
+    for age in 0..wheat.value_count("age") {
+        let block_id = block_variants.builder("wheat").with("age", age).block_id();
+        attached_blocks.register(block_id, Support::Below);
+    }
 }
 
-fn spawn_wheat_0(commands: &mut EntityCommands, _block_data: Option<&BlockData>) {
-    commands.insert(Wheat {
-        stage: 0,
-        ..default()
-    });
-}
-fn spawn_wheat_1(commands: &mut EntityCommands, _block_data: Option<&BlockData>) {
-    commands.insert(Wheat {
-        stage: 1,
-        ..default()
-    });
+fn is_growing(block_variants: &BlockVariants, block_id: BlockId) -> bool {
+    block_variants
+        .state("wheat", block_id)
+        .is_some_and(|wheat| wheat.get("age") < wheat.value_count("age") - 1)
 }
-fn spawn_wheat_2(commands: &mut EntityCommands, _block_data: Option<&BlockData>) {
-    commands.insert(Wheat {
-        stage: 2,
-        ..default()
-    });
-}
-fn spawn_wheat_3(commands: &mut EntityCommands, _block_data: Option<&BlockData>) {
-    commands.insert(Wheat {
-        stage: 3,
-        ..default()
-    });
-}
-fn spawn_wheat_4(commands: &mut EntityCommands, _block_data: Option<&BlockData>) {
-    commands.insert(Wheat {
-        stage: 4,
-        ..default()
-    });
-}
-fn spawn_wheat_5(commands: &mut EntityCommands, _block_data: Option<&BlockData>) {
-    commands.insert(Wheat {
-        stage: 5,
-        ..default()
-    });
+
+// Each stage of growth is ticked once when it is planted or grows into it. Random ticks are too
+// rare for wheat to grow at the rate it always has.
+fn schedule_placed_wheat(
+    block_variants: Res<BlockVariants>,
+    mut block_ticks: ResMut<BlockTicks>,
+    mut changed_blocks: MessageReader<ChangedBlockEvent>,
+) {
+    for changed_block in changed_blocks.read() {
+        let (block_id, _) = changed_block.to;
+        if is_growing(&block_variants, block_id) {
+            block_ticks.schedule_tick(changed_block.position, GROWTH_INTERVAL);
+        }
+    }
 }
-fn spawn_wheat_6(commands: &mut EntityCommands, _block_data: Option<&BlockData>) {
-    commands.insert(Wheat {
-        stage: 6,
-        ..default()
-    });
+
+// Scheduled ticks aren't saved, wheat that is loaded from the database starts growing again.
+fn schedule_loaded_wheat(
+    world_map: Res<WorldMap>,
+    block_variants: Res<BlockVariants>,
+    mut block_ticks: ResMut<BlockTicks>,
+    mut loaded_chunks: MessageReader<ChunkLoadEvent>,
+) {
+    for loaded_chunk in loaded_chunks.read() {
+        let Some(chunk) = world_map.get_chunk(&loaded_chunk.position) else {
+            continue;
+        };
+
+        for chunk_index in 0..Chunk::SIZE * Chunk::SIZE * Chunk::SIZE {
+            if is_growing(&block_variants, chunk[chunk_index]) {
+                let block_position =
+                    BlockPosition::from(loaded_chunk.position) + BlockPosition::from(chunk_index);
+                block_ticks.schedule_tick(block_position, GROWTH_INTERVAL);
+            }
+        }
+    }
 }
 
-// TODO: Make 'tick' increment randomly.
-// TODO: Only run this function at daytime?
+// TODO: Only grow at daytime?
 fn grow(
-    time: Res<Time>,
-    mut growing: Query<(&mut Wheat, &BlockPosition)>,
+    world_map: Res<WorldMap>,
+    block_variants: Res<BlockVariants>,
+    mut block_ticks: ResMut<BlockTicks>,
+    mut wheat_ticks: Query<&mut BlockTickEvents, (With<Wheat>, Changed<BlockTickEvents>)>,
     mut block_update_writer: MessageWriter<BlockUpdate>,
+    mut rng: Local<Rng>,
 ) {
-    for (mut wheat, block_position) in growing.iter_mut() {
-        if wheat.stage == 6 {
+    let Ok(mut ticks) = wheat_ticks.single_mut() else {
+        return;
+    };
+
+    for (block_position, _) in ticks.read() {
+        let Some(block_id) = world_map.get_block(block_position) else {
             continue;
-        }
+        };
 
-        wheat.grow_timer.tick(time.delta());
-        if !wheat.grow_timer.just_finished() {
+        let Some(wheat) = block_variants.state("wheat", block_id) else {
+            continue;
+        };
+
+        let age = wheat.get("age");
+        if age == wheat.value_count("age") - 1 {
             continue;
         }
 
-        wheat.stage += 1;
-
-        let blocks = Blocks::get();
-        let block_id = match wheat.stage {
-            0 => blocks.get_id("wheat_1"),
-            1 => blocks.get_id("wheat_2"),
-            2 => blocks.get_id("wheat_3"),
-            3 => blocks.get_id("wheat_4"),
-            4 => blocks.get_id("wheat_5"),
-            5 => blocks.get_id("wheat_6"),
-            6 => blocks.get_id("wheat_7"),
-            _ => unreachable!(),
-        };
+        let is_watered = world_map
+            .get_block(block_position - IVec3::Y)
+            .and_then(|block_id| block_variants.state("soil", block_id))
+            .is_some_and(|soil| soil.get_bool("moisture"));
+        if !is_watered && rng.next_f32() > DRY_GROWTH_CHANCE {
+            block_ticks.schedule_tick(block_position, GROWTH_INTERVAL);
+            continue;
+        }
 
         block_update_writer.write(BlockUpdate::Swap {
-            position: *block_position,
-            block_id,
+            position: block_position,
+            block_id: wheat.with("age", age + 1).block_id(),
             block_state: None,
         });
     }
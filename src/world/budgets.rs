@@ -0,0 +1,223 @@
+use std::collections::{HashMap, HashSet};
+
+use fmc::{
+    blocks::{BlockId, BlockPosition, Blocks},
+    networking::Server,
+    players::Player,
+    prelude::*,
+    world::chunk::ChunkPosition,
+};
+
+use crate::{
+    chat::send_message,
+    items::DroppedItem,
+    mobs::Mob,
+    players::access::{self, AccessControl},
+};
+
+/// Limits on how many entities of each kind a chunk may contain. Dropped items past the limit are
+/// merged or despawned, mobs are not spawned into full chunks, and players can't place blocks
+/// that have an entity into chunks that are full. Chunks that stay over budget are reported to
+/// the log and to the operators that are online.
+pub struct ChunkBudgetPlugin;
+impl Plugin for ChunkBudgetPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(ChunkBudgets::default()).add_systems(
+            Update,
+            (
+                find_entity_blocks.run_if(run_once),
+                count_entities,
+                report_exceeded_budgets,
+            )
+                .chain()
+                .in_set(ChunkBudgetSystems),
+        );
+    }
+}
+
+/// Systems that read the entity counts of [ChunkBudgets] should run after this [SystemSet].
+#[derive(SystemSet, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ChunkBudgetSystems;
+
+pub const MAX_DROPPED_ITEMS: usize = 64;
+pub const MAX_MOBS: usize = 16;
+pub const MAX_BLOCK_ENTITIES: usize = 256;
+
+/// How many seconds in a row a chunk must exceed its budget before it is reported.
+const ALERT_THRESHOLD: u32 = 30;
+
+#[derive(Default, Clone, Copy)]
+pub struct ChunkCounts {
+    pub dropped_items: usize,
+    pub mobs: usize,
+    pub block_entities: usize,
+}
+
+impl ChunkCounts {
+    fn over_budget(&self) -> bool {
+        self.dropped_items > MAX_DROPPED_ITEMS
+            || self.mobs > MAX_MOBS
+            || self.block_entities > MAX_BLOCK_ENTITIES
+    }
+}
+
+/// The number of entities in each chunk, updated every tick.
+#[derive(Resource, Default)]
+pub struct ChunkBudgets {
+    counts: HashMap<ChunkPosition, ChunkCounts>,
+    // How many consecutive seconds each chunk has been over budget
+    strikes: HashMap<ChunkPosition, u32>,
+    // The blocks that have an entity
+    entity_blocks: HashSet<BlockId>,
+}
+
+impl ChunkBudgets {
+    pub fn get(&self, chunk_position: &ChunkPosition) -> ChunkCounts {
+        self.counts.get(chunk_position).cloned().unwrap_or_default()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&ChunkPosition, &ChunkCounts)> {
+        self.counts.iter()
+    }
+
+    /// If another mob can be spawned in the chunk
+    pub fn can_spawn_mob(&self, chunk_position: &ChunkPosition) -> bool {
+        self.get(chunk_position).mobs < MAX_MOBS
+    }
+
+    /// Count a mob that was spawned this tick, so that several spawns in the same tick can't
+    /// exceed the budget.
+    pub fn add_mob(&mut self, chunk_position: ChunkPosition) {
+        self.counts.entry(chunk_position).or_default().mobs += 1;
+    }
+
+    /// If the block can be placed in the chunk. Blocks without an entity can always be placed.
+    pub fn can_place_block(&self, chunk_position: &ChunkPosition, block_id: &BlockId) -> bool {
+        !self.entity_blocks.contains(block_id)
+            || self.get(chunk_position).block_entities < MAX_BLOCK_ENTITIES
+    }
+
+    /// Count a block that was placed this tick, see [ChunkBudgets::add_mob].
+    pub fn add_block(&mut self, chunk_position: ChunkPosition, block_id: &BlockId) {
+        if self.entity_blocks.contains(block_id) {
+            self.counts
+                .entry(chunk_position)
+                .or_default()
+                .block_entities += 1;
+        }
+    }
+}
+
+// Blocks get an entity if they have a model or a spawn function. The spawn functions are set
+// during startup, so this waits until it is over.
+fn find_entity_blocks(mut budgets: ResMut<ChunkBudgets>) {
+    let blocks = Blocks::get();
+    budgets.entity_blocks = blocks
+        .asset_ids()
+        .into_values()
+        .filter(|block_id| {
+            let block = blocks.get_config(block_id);
+            block.spawn_entity_fn.is_some() || block.model.is_some()
+        })
+        .collect();
+}
+
+fn count_entities(
+    mut budgets: ResMut<ChunkBudgets>,
+    dropped_items: Query<&GlobalTransform, With<DroppedItem>>,
+    mobs: Query<&GlobalTransform, With<Mob>>,
+    block_entities: Query<&BlockPosition>,
+) {
+    budgets.counts.clear();
+
+    for transform in dropped_items.iter() {
+        let chunk_position = ChunkPosition::from(transform.translation());
+        budgets
+            .counts
+            .entry(chunk_position)
+            .or_default()
+            .dropped_items += 1;
+    }
+
+    for transform in mobs.iter() {
+        let chunk_position = ChunkPosition::from(transform.translation());
+        budgets.counts.entry(chunk_position).or_default().mobs += 1;
+    }
+
+    for block_position in block_entities.iter() {
+        let chunk_position = ChunkPosition::from(*block_position);
+        budgets
+            .counts
+            .entry(chunk_position)
+            .or_default()
+            .block_entities += 1;
+    }
+}
+
+// A chunk that keeps exceeding its budget is most likely a lag machine or a farm that has gotten
+// out of hand. It is logged and the operators are told so they can go and take a look.
+fn report_exceeded_budgets(
+    net: Res<Server>,
+    time: Res<Time>,
+    access_control: Res<AccessControl>,
+    mut budgets: ResMut<ChunkBudgets>,
+    players: Query<(Entity, &Player)>,
+    mut timer: Local<Timer>,
+) {
+    if timer.duration().is_zero() {
+        *timer = Timer::from_seconds(1.0, TimerMode::Repeating);
+    }
+
+    timer.tick(time.delta());
+    if !timer.just_finished() {
+        return;
+    }
+
+    let budgets = &mut *budgets;
+
+    budgets.strikes.retain(|chunk_position, _| {
+        budgets
+            .counts
+            .get(chunk_position)
+            .is_some_and(ChunkCounts::over_budget)
+    });
+
+    for (chunk_position, counts) in budgets.counts.iter() {
+        if !counts.over_budget() {
+            continue;
+        }
+
+        let strikes = budgets.strikes.entry(*chunk_position).or_default();
+        *strikes += 1;
+
+        if *strikes >= ALERT_THRESHOLD {
+            *strikes = 0;
+            warn!(
+                "The chunk at {:?} has been over its entity budget for {} seconds. It contains {} dropped items, {} mobs and {} block entities.",
+                chunk_position,
+                ALERT_THRESHOLD,
+                counts.dropped_items,
+                counts.mobs,
+                counts.block_entities
+            );
+
+            let position = BlockPosition::from(*chunk_position);
+            let text = format!(
+                "The chunk at {} {} {} has been over its entity budget for {} seconds, it has {} \
+                dropped items, {} mobs and {} block entities",
+                position.x,
+                position.y,
+                position.z,
+                ALERT_THRESHOLD,
+                counts.dropped_items,
+                counts.mobs,
+                counts.block_entities
+            );
+            for (player_entity, player) in players.iter() {
+                if access_control.operator_level(&player.username) >= access::BUDGET_ALERT_LEVEL {
+                    send_message(&net, player_entity, text.clone());
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,68 @@
+use fmc::{bevy::math::DVec3, prelude::*};
+use serde::{Deserialize, Serialize};
+
+// TODO: fmc only supports a single world map. Until it supports more, the nether is generated far
+// below the overworld in the same world. Chunk storage, chunk subscriptions, mobs and models all
+// work by position, so the dimensions are kept apart without them having to know about it. A
+// layer of bedrock at the nether's ceiling keeps players from digging from one into the other.
+//
+/// The lowest block of the nether.
+pub const NETHER_FLOOR: i32 = -16384;
+/// How many blocks tall the nether is.
+pub const NETHER_HEIGHT: i32 = 128;
+/// How many blocks you travel in the overworld for every block travelled in the nether.
+pub const NETHER_SCALE: f64 = 8.0;
+
+/// The dimension a player is in.
+#[derive(Component, Serialize, Deserialize, Default, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum Dimension {
+    #[default]
+    Overworld,
+    Nether,
+}
+
+impl Dimension {
+    /// The dimension a height is in.
+    pub fn from_height(y: i32) -> Self {
+        if y < NETHER_FLOOR + NETHER_HEIGHT {
+            Self::Nether
+        } else {
+            Self::Overworld
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Overworld => "overworld",
+            Self::Nether => "nether",
+        }
+    }
+
+    /// The dimension that is travelled to through a portal, and the position a portal at the given
+    /// position leads to.
+    pub fn travel(position: DVec3) -> (Self, DVec3) {
+        match Self::from_height(position.y.floor() as i32) {
+            Self::Overworld => (
+                Self::Nether,
+                DVec3::new(
+                    position.x / NETHER_SCALE,
+                    (NETHER_FLOOR + NETHER_HEIGHT / 2) as f64,
+                    position.z / NETHER_SCALE,
+                ),
+            ),
+            Self::Nether => (
+                Self::Overworld,
+                DVec3::new(position.x * NETHER_SCALE, 64.0, position.z * NETHER_SCALE),
+            ),
+        }
+    }
+
+    /// The range of heights a portal can be placed at in the dimension.
+    pub fn portal_heights(&self) -> std::ops::Range<i32> {
+        match self {
+            Self::Overworld => -32..96,
+            Self::Nether => NETHER_FLOOR + 4..NETHER_FLOOR + NETHER_HEIGHT - 8,
+        }
+    }
+}
@@ -0,0 +1,322 @@
+use std::collections::{HashMap, VecDeque};
+
+use fmc::{
+    blocks::{BlockData, BlockId, BlockPosition, BlockState, Blocks},
+    networking::Server,
+    players::Player,
+    prelude::*,
+    world::{WorldMap, chunk::ChunkPosition},
+};
+
+use crate::chat::send_message;
+
+use super::{paste::PasteBlocks, schematics::Schematic};
+
+/// Operators change large parts of the world at once with /fill, /setblock and /clone, and take
+/// their changes back with /undo. Boxes of blocks can be saved as schematics with /schem save and
+/// pasted again with /schem paste.
+///
+/// Edits are placed with a [PasteBlocks], so the world doesn't react to the blocks until all of
+/// them are in place. Blocks in chunks that aren't loaded are left as they are. What the changed
+/// blocks were before is kept on the operator's undo stack, the oldest edits are forgotten when
+/// it is full.
+pub struct WorldEditPlugin;
+impl Plugin for WorldEditPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(UndoHistory::default())
+            .add_message::<EditBlocks>()
+            .add_systems(Update, edit_blocks);
+    }
+}
+
+/// The most blocks a single edit can change
+pub const MAX_VOLUME: usize = 32 * 32 * 32;
+/// How many edits each operator can undo
+const UNDO_DEPTH: usize = 16;
+
+type Block = (
+    BlockPosition,
+    BlockId,
+    Option<BlockState>,
+    Option<BlockData>,
+);
+
+pub enum Edit {
+    /// Fill the box between the two corners with the block
+    Fill {
+        from: BlockPosition,
+        to: BlockPosition,
+        block_id: BlockId,
+    },
+    SetBlock {
+        position: BlockPosition,
+        block_id: BlockId,
+    },
+    /// Copy the box between the two corners so that its lowest corner is at the destination
+    Clone {
+        from: BlockPosition,
+        to: BlockPosition,
+        destination: BlockPosition,
+    },
+    /// Save the box between the two corners as a [Schematic] with the given name
+    SaveSchematic {
+        from: BlockPosition,
+        to: BlockPosition,
+        name: String,
+    },
+    /// Paste a saved [Schematic] so that its lowest corner is at the destination, turned the
+    /// given number of quarter turns.
+    PasteSchematic {
+        name: String,
+        destination: BlockPosition,
+        quarter_turns: u8,
+    },
+    /// Revert the last edit of the same operator
+    Undo,
+}
+
+#[derive(Message)]
+pub struct EditBlocks {
+    /// The player that made the edit, None for the server console
+    pub requested_by: Option<Entity>,
+    pub edit: Edit,
+}
+
+/// The blocks each operator's edits replaced, newest last. Kept by username so it outlives the
+/// operator's connection, the console's history is under None.
+#[derive(Resource, Default)]
+struct UndoHistory(HashMap<Option<String>, VecDeque<Vec<Block>>>);
+
+fn reply(net: &Server, requested_by: Option<Entity>, text: String) {
+    let Some(player_entity) = requested_by else {
+        info!("{}", text);
+        return;
+    };
+
+    send_message(net, player_entity, text);
+}
+
+// Every position in the box spanned by the two corners, or None if there are too many.
+fn region(from: BlockPosition, to: BlockPosition) -> Option<Vec<BlockPosition>> {
+    let min = from.min(*to);
+    let max = from.max(*to);
+    let size = (max - min + IVec3::ONE).as_uvec3();
+    if size.x as usize * size.y as usize * size.z as usize > MAX_VOLUME {
+        return None;
+    }
+
+    let mut positions = Vec::with_capacity(size.element_product() as usize);
+    for x in min.x..=max.x {
+        for y in min.y..=max.y {
+            for z in min.z..=max.z {
+                positions.push(BlockPosition::new(x, y, z));
+            }
+        }
+    }
+
+    Some(positions)
+}
+
+fn edit_blocks(
+    net: Res<Server>,
+    world_map: Res<WorldMap>,
+    player_query: Query<&Player>,
+    block_data_query: Query<(&BlockPosition, &BlockData)>,
+    mut undo_history: ResMut<UndoHistory>,
+    mut edit_events: MessageReader<EditBlocks>,
+    mut paste_writer: MessageWriter<PasteBlocks>,
+) {
+    for edit_event in edit_events.read() {
+        let username = match edit_event.requested_by {
+            Some(player_entity) => {
+                let Ok(player) = player_query.get(player_entity) else {
+                    continue;
+                };
+                Some(player.username.clone())
+            }
+            None => None,
+        };
+
+        // Block data is only held by the block entities of the loaded blocks that have any
+        let block_data_at = |positions: &[BlockPosition]| -> HashMap<BlockPosition, BlockData> {
+            let min = positions.iter().fold(IVec3::MAX, |min, p| min.min(**p));
+            let max = positions.iter().fold(IVec3::MIN, |max, p| max.max(**p));
+            block_data_query
+                .iter()
+                .filter(|(position, _)| position.cmpge(min).all() && position.cmple(max).all())
+                .map(|(position, block_data)| (*position, block_data.clone()))
+                .collect()
+        };
+
+        let mut blocks: Vec<Block> = match &edit_event.edit {
+            Edit::Fill { from, to, block_id } => {
+                let Some(positions) = region(*from, *to) else {
+                    reply(
+                        &net,
+                        edit_event.requested_by,
+                        format!("Edits can change at most {} blocks", MAX_VOLUME),
+                    );
+                    continue;
+                };
+                positions
+                    .into_iter()
+                    .map(|position| (position, *block_id, None, None))
+                    .collect()
+            }
+            Edit::SetBlock { position, block_id } => vec![(*position, *block_id, None, None)],
+            Edit::Clone {
+                from,
+                to,
+                destination,
+            } => {
+                let Some(positions) = region(*from, *to) else {
+                    reply(
+                        &net,
+                        edit_event.requested_by,
+                        format!("Edits can change at most {} blocks", MAX_VOLUME),
+                    );
+                    continue;
+                };
+                let offset = *destination - from.min(**to);
+                let mut block_data = block_data_at(&positions);
+                positions
+                    .into_iter()
+                    .filter_map(|position| {
+                        Some((
+                            position + offset,
+                            world_map.get_block(position)?,
+                            world_map.get_block_state(position),
+                            block_data.remove(&position),
+                        ))
+                    })
+                    .collect()
+            }
+            Edit::SaveSchematic { from, to, name } => {
+                let Some(positions) = region(*from, *to) else {
+                    reply(
+                        &net,
+                        edit_event.requested_by,
+                        format!("Schematics can be at most {} blocks", MAX_VOLUME),
+                    );
+                    continue;
+                };
+                let mut block_data = block_data_at(&positions);
+                let Some(schematic_blocks) = positions
+                    .iter()
+                    .map(|position| {
+                        Some((
+                            world_map.get_block(*position)?,
+                            world_map.get_block_state(*position),
+                            block_data.remove(position),
+                        ))
+                    })
+                    .collect::<Option<Vec<_>>>()
+                else {
+                    reply(
+                        &net,
+                        edit_event.requested_by,
+                        "All of the blocks must be loaded to save them".to_owned(),
+                    );
+                    continue;
+                };
+
+                let size = from.max(**to) - from.min(**to) + IVec3::ONE;
+                let text = match Schematic::new(size, schematic_blocks, Blocks::get()).save(name) {
+                    Ok(()) => format!("Saved {} blocks as '{}'", positions.len(), name),
+                    Err(e) => e,
+                };
+                reply(&net, edit_event.requested_by, text);
+                continue;
+            }
+            Edit::PasteSchematic {
+                name,
+                destination,
+                quarter_turns,
+            } => {
+                let schematic = match Schematic::load(name, Blocks::get()) {
+                    Ok(schematic) => schematic,
+                    Err(e) => {
+                        reply(&net, edit_event.requested_by, e);
+                        continue;
+                    }
+                };
+                if schematic.size.element_product() as usize > MAX_VOLUME {
+                    reply(
+                        &net,
+                        edit_event.requested_by,
+                        format!("Edits can change at most {} blocks", MAX_VOLUME),
+                    );
+                    continue;
+                }
+                schematic
+                    .blocks(*quarter_turns, Blocks::get())
+                    .into_iter()
+                    .map(|(offset, block_id, block_state, block_data)| {
+                        (*destination + offset, block_id, block_state, block_data)
+                    })
+                    .collect()
+            }
+            Edit::Undo => {
+                let Some(blocks) = undo_history
+                    .0
+                    .get_mut(&username)
+                    .and_then(|edits| edits.pop_back())
+                else {
+                    reply(&net, edit_event.requested_by, "Nothing to undo".to_owned());
+                    continue;
+                };
+                reply(
+                    &net,
+                    edit_event.requested_by,
+                    format!("Undid the change of {} blocks", blocks.len()),
+                );
+                paste_writer.write(PasteBlocks { blocks });
+                continue;
+            }
+        };
+
+        // Blocks in unloaded chunks are skipped by the paste, they have nothing to undo either.
+        blocks.retain(|(position, ..)| world_map.get_block(*position).is_some());
+        if blocks.is_empty() {
+            reply(
+                &net,
+                edit_event.requested_by,
+                "None of the blocks are loaded".to_owned(),
+            );
+            continue;
+        }
+
+        // Placed chunk by chunk
+        blocks.sort_by_key(|(position, ..)| {
+            let chunk_position = ChunkPosition::from(*position);
+            (chunk_position.x, chunk_position.y, chunk_position.z)
+        });
+
+        let positions: Vec<BlockPosition> = blocks.iter().map(|(position, ..)| *position).collect();
+        let mut block_data = block_data_at(&positions);
+        let previous: Vec<Block> = positions
+            .into_iter()
+            .map(|position| {
+                (
+                    position,
+                    world_map.get_block(position).unwrap(),
+                    world_map.get_block_state(position),
+                    block_data.remove(&position),
+                )
+            })
+            .collect();
+
+        let edits = undo_history.0.entry(username).or_default();
+        if edits.len() == UNDO_DEPTH {
+            edits.pop_front();
+        }
+        edits.push_back(previous);
+
+        reply(
+            &net,
+            edit_event.requested_by,
+            format!("Changed {} blocks", blocks.len()),
+        );
+        paste_writer.write(PasteBlocks { blocks });
+    }
+}
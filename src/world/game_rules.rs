@@ -0,0 +1,171 @@
+use fmc::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use super::WorldProperties;
+
+/// Keeps the [GameRules] of the world in its [WorldProperties], so they are saved along with it.
+pub struct GameRulesPlugin;
+impl Plugin for GameRulesPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            store_game_rules.run_if(resource_changed::<GameRules>),
+        );
+    }
+}
+
+/// Rules that change how the game plays, they are set per world.
+#[derive(Resource, Serialize, Deserialize, Clone, PartialEq, Debug)]
+#[serde(default)]
+pub struct GameRules {
+    keep_inventory: bool,
+    mob_griefing: bool,
+    daylight_cycle: bool,
+    difficulty: Difficulty,
+}
+
+impl GameRules {
+    /// The names the rules are changed by in the /gamerule command
+    pub const NAMES: [&str; 3] = ["keepInventory", "mobGriefing", "daylightCycle"];
+
+    /// The rules a new world starts out with
+    pub fn new(mob_griefing: bool, difficulty: Difficulty) -> Self {
+        Self {
+            mob_griefing,
+            difficulty,
+            ..default()
+        }
+    }
+
+    /// Players keep their items when they die instead of dropping them
+    pub fn keep_inventory(&self) -> bool {
+        self.keep_inventory
+    }
+
+    /// Mobs and explosions can change the world
+    pub fn mob_griefing(&self) -> bool {
+        self.mob_griefing
+    }
+
+    /// The time of day moves forward
+    pub fn daylight_cycle(&self) -> bool {
+        self.daylight_cycle
+    }
+
+    /// How dangerous hostile mobs are
+    pub fn difficulty(&self) -> Difficulty {
+        self.difficulty
+    }
+
+    pub fn set_difficulty(&mut self, difficulty: Difficulty) {
+        self.difficulty = difficulty;
+    }
+
+    /// The value of the rule with the given name
+    pub fn get(&self, name: &str) -> Option<bool> {
+        match name {
+            "keepInventory" => Some(self.keep_inventory),
+            "mobGriefing" => Some(self.mob_griefing),
+            "daylightCycle" => Some(self.daylight_cycle),
+            _ => None,
+        }
+    }
+
+    /// Set the rule with the given name, fails if there is no such rule or the value isn't
+    /// 'true' or 'false'.
+    pub fn set(&mut self, name: &str, value: &str) -> Result<(), String> {
+        let rule = match name {
+            "keepInventory" => &mut self.keep_inventory,
+            "mobGriefing" => &mut self.mob_griefing,
+            "daylightCycle" => &mut self.daylight_cycle,
+            _ => return Err(format!("There is no game rule named {}", name)),
+        };
+
+        *rule = value
+            .parse::<bool>()
+            .map_err(|_| format!("The game rule {} must be 'true' or 'false'", name))?;
+        Ok(())
+    }
+}
+
+impl Default for GameRules {
+    fn default() -> Self {
+        Self {
+            keep_inventory: false,
+            mob_griefing: true,
+            daylight_cycle: true,
+            difficulty: Difficulty::default(),
+        }
+    }
+}
+
+/// How dangerous hostile mobs are.
+///
+/// - Peaceful: there are no hostile mobs, the ones that are around are removed.
+/// - Easy: mobs deal half damage and spawners are slower.
+/// - Normal
+/// - Hard: mobs deal more damage, spawners are faster, and zombies break down wooden doors.
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum Difficulty {
+    Peaceful,
+    Easy,
+    #[default]
+    Normal,
+    Hard,
+}
+
+impl Difficulty {
+    /// The names the difficulties are set by in the settings and the /difficulty command
+    pub const NAMES: [&str; 4] = ["peaceful", "easy", "normal", "hard"];
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "peaceful" => Some(Self::Peaceful),
+            "easy" => Some(Self::Easy),
+            "normal" => Some(Self::Normal),
+            "hard" => Some(Self::Hard),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Peaceful => "peaceful",
+            Self::Easy => "easy",
+            Self::Normal => "normal",
+            Self::Hard => "hard",
+        }
+    }
+
+    /// If hostile mobs can exist
+    pub fn allows_hostile_mobs(&self) -> bool {
+        *self != Self::Peaceful
+    }
+
+    /// The damage a hostile mob deals to a player
+    pub fn scale_damage(&self, damage: u32) -> u32 {
+        match self {
+            Self::Peaceful | Self::Easy => (damage / 2).max(1),
+            Self::Normal => damage,
+            Self::Hard => damage * 3 / 2,
+        }
+    }
+
+    /// How often hostile mobs spawn compared to normal difficulty. Times between spawns are
+    /// divided by it.
+    pub fn spawn_rate(&self) -> f32 {
+        match self {
+            Self::Peaceful => 0.0,
+            Self::Easy => 0.5,
+            Self::Normal => 1.0,
+            Self::Hard => 1.5,
+        }
+    }
+}
+
+fn store_game_rules(game_rules: Res<GameRules>, mut properties: ResMut<WorldProperties>) {
+    if properties.game_rules.as_ref() != Some(&*game_rules) {
+        properties.game_rules = Some(game_rules.clone());
+    }
+}
@@ -1,24 +1,61 @@
 use fmc::{
-    blocks::{BlockPosition, Blocks},
+    blocks::{BlockConfig, BlockPosition, Blocks},
     database::Database,
     prelude::*,
     world::WorldMap,
 };
 use serde::{Deserialize, Serialize};
 
-use crate::settings::Settings;
+use crate::{assets::AssetErrors, database_io::DatabaseIo, settings::Settings, skybox::Clock};
 
+pub mod block_ticks;
+pub mod block_variants;
 pub mod blocks;
+pub mod budgets;
+pub mod dimensions;
+pub mod edit;
+pub mod game_rules;
+pub mod neighbor_updates;
+pub mod paste;
+pub mod physics;
+pub mod saving;
+pub mod schematics;
 mod terrain_generation;
+pub mod worlds;
+
+pub use terrain_generation::{
+    Climate,
+    biomes::{Biomes, SharedBiomes},
+    interpolation,
+    snapshots::TerrainSnapshotsPlugin,
+};
 
 pub struct WorldPlugin;
 impl Plugin for WorldPlugin {
     fn build(&self, app: &mut App) {
-        app.add_plugins(blocks::BlocksPlugin)
+        app.add_plugins(block_ticks::BlockTicksPlugin)
+            .add_plugins(block_variants::BlockVariantsPlugin)
+            .add_plugins(blocks::BlocksPlugin)
+            .add_plugins(budgets::ChunkBudgetPlugin)
+            .add_plugins(edit::WorldEditPlugin)
+            .add_plugins(game_rules::GameRulesPlugin)
+            .add_plugins(neighbor_updates::NeighborUpdatesPlugin)
+            .add_plugins(paste::PastePlugin)
+            .add_plugins(physics::PhysicsPlugin)
+            .add_plugins(saving::SavingPlugin)
+            .add_plugins(worlds::WorldsPlugin)
             .add_systems(Startup, setup)
             .add_systems(
                 Update,
                 save_world_properties.run_if(resource_changed::<WorldProperties>),
+            )
+            .add_systems(
+                PostUpdate,
+                add_world_properties_to_save.in_set(saving::SaveSystems::Collect),
+            )
+            .add_systems(
+                Last,
+                save_world_properties_on_shutdown.run_if(on_message::<AppExit>),
             );
     }
 }
@@ -28,18 +65,70 @@ fn setup(
     database: Res<Database>,
     blocks: Res<Blocks>,
     settings: Res<Settings>,
+    mut clock: ResMut<Clock>,
+    mut asset_errors: ResMut<AssetErrors>,
 ) {
     let properties = WorldProperties::load(database).unwrap_or(WorldProperties::default());
+    if let Some(time) = properties.time {
+        clock.set_time(time);
+    }
+    // Worlds from before game rules existed start out with the rules a new world would have.
+    commands.insert_resource(
+        properties
+            .game_rules
+            .clone()
+            .unwrap_or(game_rules::GameRules::new(
+                settings.mob_griefing,
+                settings.difficulty,
+            )),
+    );
     commands.insert_resource(properties);
 
-    commands.insert_resource(WorldMap::new(terrain_generation::Earth::new(
-        settings.seed(),
-        &blocks,
-    )));
+    // The server stops before any terrain is generated if the biomes can't be loaded.
+    let biomes = match Biomes::load(&blocks) {
+        Ok(b) => b,
+        Err(e) => {
+            asset_errors.extend(e);
+            Biomes::default()
+        }
+    };
+    let earth = terrain_generation::Earth::new(settings.seed(), &blocks, biomes);
+    commands.insert_resource(earth.climate().clone());
+    commands.insert_resource(earth.biomes().clone());
+    commands.insert_resource(WorldMap::new(earth));
+}
+
+fn save_world_properties(database_io: Res<DatabaseIo>, properties: Res<WorldProperties>) {
+    properties.save(&database_io);
+}
+
+fn add_world_properties_to_save(
+    clock: Res<Clock>,
+    mut properties: ResMut<WorldProperties>,
+    mut world_save: ResMut<saving::WorldSave>,
+    mut save_events: MessageReader<saving::SaveWorld>,
+) {
+    if save_events.read().count() == 0 {
+        return;
+    }
+
+    // The time changes every tick, it's only worth writing along with everything else.
+    let properties = properties.bypass_change_detection();
+    properties.time = Some(clock.get_time());
+
+    let json = serde_json::to_string(&*properties).unwrap();
+    world_save.add(move |connection| WorldProperties::write(json, connection));
 }
 
-fn save_world_properties(database: Res<Database>, properties: Res<WorldProperties>) {
-    properties.save(database);
+fn save_world_properties_on_shutdown(
+    database_io: Res<DatabaseIo>,
+    clock: Res<Clock>,
+    mut properties: ResMut<WorldProperties>,
+) {
+    properties.time = Some(clock.get_time());
+    properties.save(&database_io);
+    // The server exits right after this, so it must be written before returning.
+    database_io.flush();
 }
 
 #[derive(Default, Serialize, Deserialize, Resource)]
@@ -47,6 +136,12 @@ pub struct WorldProperties {
     // TODO: This must be set to a valid spawn point when first inserted, currently it is just
     // ignored.
     pub spawn_point: SpawnPoint,
+    /// The time of day when the world was last saved
+    #[serde(default)]
+    pub time: Option<f32>,
+    /// Kept up to date with the [GameRules](game_rules::GameRules) resource
+    #[serde(default)]
+    pub game_rules: Option<game_rules::GameRules>,
 }
 
 impl WorldProperties {
@@ -65,20 +160,57 @@ impl WorldProperties {
         return Some(properties);
     }
 
-    fn save(&self, database: Res<Database>) {
-        let conn = database.get_write_connection();
-        let mut stmt = conn
-            .prepare("INSERT OR REPLACE INTO storage (name, data) VALUES (?,?)")
-            .unwrap();
+    fn save(&self, database_io: &DatabaseIo) {
+        let json = serde_json::to_string(self).unwrap();
+        database_io.run(move |database| {
+            Self::write(json, &database.get_write_connection()).unwrap();
+        });
+    }
 
-        stmt.execute(rusqlite::params![
-            "world_properties",
-            serde_json::to_string(self).unwrap()
-        ])
-        .unwrap();
+    fn write(json: String, connection: &rusqlite::Connection) -> rusqlite::Result<()> {
+        let mut stmt =
+            connection.prepare("INSERT OR REPLACE INTO storage (name, data) VALUES (?,?)")?;
+        stmt.execute(rusqlite::params!["world_properties", json])?;
+        Ok(())
     }
 }
 
+// TODO: The light attenuation of blocks is only known to the client, so for now any solid full
+// cube is considered to block light. This makes glass opaque.
+//
+/// If the block fully blocks light from passing through it.
+pub fn is_opaque(block_config: &BlockConfig) -> bool {
+    block_config.is_solid() && block_config.quads.is_none() && block_config.model.is_none()
+}
+
+/// Check if there are only non-opaque blocks above the position, all the way up to the first
+/// unloaded chunk.
+pub fn has_sky_access(world_map: &WorldMap, block_position: BlockPosition) -> bool {
+    let blocks = Blocks::get();
+    sky_access(block_position, |position| {
+        world_map
+            .get_block(position)
+            .map(|block_id| is_opaque(blocks.get_config(&block_id)))
+    })
+}
+
+/// Same as [has_sky_access], but with `opaque` telling which blocks are opaque. It returns None
+/// for positions in chunks that aren't loaded.
+pub fn sky_access(
+    block_position: BlockPosition,
+    opaque: impl Fn(BlockPosition) -> Option<bool>,
+) -> bool {
+    let mut position = block_position + IVec3::Y;
+    while let Some(opaque) = opaque(position) {
+        if opaque {
+            return false;
+        }
+        position.y += 1;
+    }
+
+    return true;
+}
+
 /// The default spawn point, as opposed to the unique spawn point of a player.
 #[derive(Default, Serialize, Deserialize)]
 pub struct SpawnPoint {
@@ -0,0 +1,125 @@
+use std::collections::HashMap;
+
+use fmc::{
+    blocks::{BlockId, BlockPosition, BlockState},
+    prelude::*,
+    world::ChangedBlockEvent,
+};
+
+use super::paste::UpdateSuppression;
+
+pub struct NeighborUpdatesPlugin;
+impl Plugin for NeighborUpdatesPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_message::<BlockNeighborUpdate>()
+            .insert_resource(NeighborUpdates::default())
+            .add_systems(Update, send_neighbor_updates.in_set(NeighborUpdateSystems));
+    }
+}
+
+/// Order systems that read [BlockNeighborUpdate]s after this [SystemSet] to receive them the same
+/// frame the block changed.
+#[derive(SystemSet, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct NeighborUpdateSystems;
+
+/// Sent to each of the six blocks around a block that has changed.
+#[derive(Message, Debug, Clone, Copy)]
+pub struct BlockNeighborUpdate {
+    /// Position of the block that is told about the change
+    pub position: BlockPosition,
+    /// The block at `position`
+    pub block: (BlockId, Option<BlockState>),
+    /// Offset from `position` to the block that changed
+    pub direction: IVec3,
+    /// What the neighbour was before it changed
+    pub from: (BlockId, Option<BlockState>),
+    /// What the neighbour is now
+    pub to: (BlockId, Option<BlockState>),
+}
+
+impl BlockNeighborUpdate {
+    /// Position of the block that changed
+    pub fn neighbor_position(&self) -> BlockPosition {
+        self.position + self.direction
+    }
+}
+
+/// Keeps track of which entities want to know when the neighbours of a block change.
+///
+/// Every [BlockNeighborUpdate] is sent as a message, but most blocks don't care about their
+/// neighbours. Subscribing a handler entity to a block has the updates for that block pushed to
+/// the handler's [BlockNeighborUpdates] instead, so it doesn't have to filter through all of them.
+#[derive(Resource, Default)]
+pub struct NeighborUpdates {
+    subscribers: HashMap<BlockId, Vec<Entity>>,
+}
+
+impl NeighborUpdates {
+    /// Subscribe a handler entity to the neighbour updates of a block.
+    pub fn subscribe(&mut self, block_id: BlockId, handler_entity: Entity) {
+        let subscribers = self.subscribers.entry(block_id).or_default();
+        if !subscribers.contains(&handler_entity) {
+            subscribers.push(handler_entity);
+        }
+    }
+}
+
+/// The neighbour updates the handler has received during the last update.
+///
+/// Attach this to an entity and subscribe the entity to blocks through [NeighborUpdates].
+#[derive(Component, Default)]
+pub struct BlockNeighborUpdates(Vec<BlockNeighborUpdate>);
+
+impl BlockNeighborUpdates {
+    pub fn read(&mut self) -> impl Iterator<Item = BlockNeighborUpdate> + '_ {
+        self.0.drain(..)
+    }
+}
+
+fn send_neighbor_updates(
+    neighbor_updates: Res<NeighborUpdates>,
+    suppression: Res<UpdateSuppression>,
+    mut changed_blocks: MessageReader<ChangedBlockEvent>,
+    mut neighbor_update_writer: MessageWriter<BlockNeighborUpdate>,
+    mut handler_query: Query<&mut BlockNeighborUpdates>,
+) {
+    for changed_block in changed_blocks.read() {
+        // Pasted blocks are placed in an arbitrary order, the neighbours are told about them
+        // when the paste has settled.
+        if suppression.is_suppressed(changed_block.position) {
+            continue;
+        }
+
+        for (neighbor, offset) in [
+            (changed_block.top, IVec3::Y),
+            (changed_block.bottom, IVec3::NEG_Y),
+            (changed_block.right, IVec3::X),
+            (changed_block.left, IVec3::NEG_X),
+            (changed_block.front, IVec3::Z),
+            (changed_block.back, IVec3::NEG_Z),
+        ] {
+            // The neighbour is in a chunk that isn't loaded
+            let Some(neighbor) = neighbor else {
+                continue;
+            };
+
+            let neighbor_update = BlockNeighborUpdate {
+                position: changed_block.position + offset,
+                block: neighbor,
+                direction: -offset,
+                from: changed_block.from,
+                to: changed_block.to,
+            };
+
+            if let Some(subscribers) = neighbor_updates.subscribers.get(&neighbor.0) {
+                for handler_entity in subscribers {
+                    if let Ok(mut events) = handler_query.get_mut(*handler_entity) {
+                        events.0.push(neighbor_update);
+                    }
+                }
+            }
+
+            neighbor_update_writer.write(neighbor_update);
+        }
+    }
+}
@@ -0,0 +1,169 @@
+use std::collections::HashSet;
+
+use fmc::{
+    blocks::{BlockData, BlockId, BlockPosition, BlockState},
+    prelude::*,
+    world::{BlockUpdate, ChangedBlockEvent, WorldMap},
+};
+
+/// Places many blocks at once without the world reacting to them while they're being placed.
+///
+/// Normally every changed block is reacted to as it's placed, water flows into the gaps that
+/// haven't been filled yet and torches pop off before the wall behind them is there. While a paste
+/// is in progress these reactions are suppressed for the pasted blocks. When every block has been
+/// placed, all of them are updated together in a single pass so the result settles in one go.
+pub struct PastePlugin;
+impl Plugin for PastePlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(UpdateSuppression::default())
+            .add_message::<PasteBlocks>()
+            .add_systems(Update, (paste_blocks, track_pastes).chain())
+            .add_systems(Last, settle_pastes);
+    }
+}
+
+/// If a paste hasn't finished within this many seconds, some of its blocks were never placed,
+/// most likely because their chunk was unloaded. It is settled as it is.
+const PASTE_TIMEOUT: f32 = 5.0;
+
+/// Paste the blocks into the world. Blocks in chunks that aren't loaded are skipped.
+#[derive(Message)]
+pub struct PasteBlocks {
+    pub blocks: Vec<(
+        BlockPosition,
+        BlockId,
+        Option<BlockState>,
+        Option<BlockData>,
+    )>,
+}
+
+struct Paste {
+    // Blocks that have been sent to be placed, but haven't been placed yet
+    pending: HashSet<BlockPosition>,
+    pasted: HashSet<BlockPosition>,
+    // Set the frame all the blocks have been placed. The suppression is kept for one more frame
+    // so the systems that ran before the blocks were placed get to skip them too.
+    placed: bool,
+    timer: Timer,
+}
+
+/// The blocks that are being pasted. Systems that react to changed blocks should skip the blocks
+/// that are suppressed, they are updated again once the paste is done.
+#[derive(Resource, Default)]
+pub struct UpdateSuppression {
+    pastes: Vec<Paste>,
+}
+
+impl UpdateSuppression {
+    pub fn is_suppressed(&self, position: BlockPosition) -> bool {
+        self.pastes
+            .iter()
+            .any(|paste| paste.pasted.contains(&position))
+    }
+}
+
+fn paste_blocks(
+    world_map: Res<WorldMap>,
+    mut suppression: ResMut<UpdateSuppression>,
+    mut pastes: MessageReader<PasteBlocks>,
+    mut block_updates: MessageWriter<BlockUpdate>,
+) {
+    for paste in pastes.read() {
+        let mut pasted = HashSet::with_capacity(paste.blocks.len());
+
+        for (position, block_id, block_state, block_data) in paste.blocks.iter() {
+            let Some(current_id) = world_map.get_block(*position) else {
+                continue;
+            };
+
+            // Replacing a block with itself doesn't change it, and would never be marked as
+            // placed.
+            if current_id == *block_id
+                && world_map.get_block_state(*position) == *block_state
+                && block_data.is_none()
+            {
+                continue;
+            }
+
+            pasted.insert(*position);
+            block_updates.write(BlockUpdate::Replace {
+                position: *position,
+                block_id: *block_id,
+                block_state: *block_state,
+                block_data: block_data.clone(),
+            });
+        }
+
+        if pasted.is_empty() {
+            continue;
+        }
+
+        suppression.pastes.push(Paste {
+            pending: pasted.clone(),
+            pasted,
+            placed: false,
+            timer: Timer::from_seconds(PASTE_TIMEOUT, TimerMode::Once),
+        });
+    }
+}
+
+fn track_pastes(
+    time: Res<Time>,
+    mut suppression: ResMut<UpdateSuppression>,
+    mut changed_blocks: MessageReader<ChangedBlockEvent>,
+) {
+    for changed_block in changed_blocks.read() {
+        for paste in suppression.pastes.iter_mut() {
+            paste.pending.remove(&changed_block.position);
+        }
+    }
+
+    for paste in suppression.pastes.iter_mut() {
+        paste.timer.tick(time.delta());
+    }
+}
+
+// Runs after all other systems, once they have all seen the pasted blocks the suppression is
+// lifted and the blocks are updated again.
+fn settle_pastes(
+    world_map: Res<WorldMap>,
+    mut suppression: ResMut<UpdateSuppression>,
+    mut changed_blocks: MessageWriter<ChangedBlockEvent>,
+) {
+    let mut settled = Vec::new();
+    suppression.pastes.retain_mut(|paste| {
+        if paste.placed || paste.timer.is_finished() {
+            settled.extend(paste.pasted.drain());
+            return false;
+        }
+
+        paste.placed = paste.pending.is_empty();
+        true
+    });
+
+    let block = |position: BlockPosition| {
+        world_map
+            .get_block(position)
+            .map(|block_id| (block_id, world_map.get_block_state(position)))
+    };
+
+    for position in settled {
+        let Some(current) = block(position) else {
+            continue;
+        };
+
+        // The block is unchanged, it is only sent so everything that reacts to blocks changing
+        // gets to see it now that the rest of the paste is in place.
+        changed_blocks.write(ChangedBlockEvent {
+            position,
+            from: current,
+            to: current,
+            top: block(position + IVec3::Y),
+            bottom: block(position - IVec3::Y),
+            right: block(position + IVec3::X),
+            left: block(position - IVec3::X),
+            front: block(position + IVec3::Z),
+            back: block(position - IVec3::Z),
+        });
+    }
+}
@@ -0,0 +1,195 @@
+use fmc::{
+    bevy::math::{DMat3, DVec3},
+    blocks::{BlockPosition, BlockRotation, Blocks},
+    physics::{Collider, Physics, shapes::Aabb},
+    prelude::*,
+    world::WorldMap,
+};
+
+/// Stops fast moving entities, like thrown items, from passing through blocks.
+///
+/// Physics moves an entity the whole way it travels in a tick before checking for collisions, so
+/// if it moves further than the thickness of a block it can end up on the other side of it. Each
+/// tick the path of the fast entities is swept ahead of time, and their velocity is cut short so
+/// they stop where they would hit.
+pub struct PhysicsPlugin;
+impl Plugin for PhysicsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(PreUpdate, prevent_tunneling);
+    }
+}
+
+/// Entities that move less than this in a tick can't pass through a block, they are left to the
+/// normal collision checks.
+const SWEEP_DISTANCE: f64 = 0.25;
+
+/// Where a moving aabb first touches a solid block
+#[derive(Debug, Clone, Copy)]
+pub struct SweepHit {
+    /// The block that was hit
+    pub block_position: BlockPosition,
+    /// How far along the move the hit happened, 0.0 at the start and 1.0 at the end.
+    pub time: f64,
+    /// The axis of the face that was hit, pointing out of the block
+    pub normal: IVec3,
+}
+
+/// Move the aabb by `velocity * delta_time` and find the first solid block in its way. Blocks the
+/// aabb already overlaps at the start are ignored, and so are blocks in chunks that aren't loaded.
+pub fn sweep_aabb(
+    world_map: &WorldMap,
+    aabb: &Aabb,
+    velocity: DVec3,
+    delta_time: f64,
+) -> Option<SweepHit> {
+    let movement = velocity * delta_time;
+    if movement == DVec3::ZERO {
+        return None;
+    }
+
+    let start_min = aabb.center - aabb.half_extents;
+    let start_max = aabb.center + aabb.half_extents;
+    let min = start_min.min(start_min + movement).floor().as_ivec3();
+    let max = start_max.max(start_max + movement).floor().as_ivec3();
+
+    let blocks = Blocks::get();
+
+    let mut first_hit: Option<SweepHit> = None;
+
+    for x in min.x..=max.x {
+        for y in min.y..=max.y {
+            for z in min.z..=max.z {
+                let block_position = BlockPosition::new(x, y, z);
+                let Some(block_id) = world_map.get_block(block_position) else {
+                    continue;
+                };
+
+                let block_config = blocks.get_config(&block_id);
+                if !block_config.is_solid() {
+                    continue;
+                }
+
+                let rotation = world_map
+                    .get_block_state(block_position)
+                    .and_then(|state| state.rotation())
+                    .map(BlockRotation::as_quat)
+                    .unwrap_or_default();
+                let block_transform = Transform {
+                    translation: block_position.as_dvec3() + DVec3::splat(0.5),
+                    rotation,
+                    ..default()
+                };
+
+                let block_aabbs = match &block_config.collider {
+                    Collider::Single(block_aabb) => std::slice::from_ref(block_aabb),
+                    Collider::Multi(block_aabbs) => block_aabbs.as_slice(),
+                };
+
+                for block_aabb in block_aabbs {
+                    let block_aabb = transform_aabb(block_aabb, &block_transform);
+                    let Some((time, normal)) = sweep(aabb, &block_aabb, movement) else {
+                        continue;
+                    };
+
+                    if first_hit.is_none_or(|hit| time < hit.time) {
+                        first_hit = Some(SweepHit {
+                            block_position,
+                            time,
+                            normal,
+                        });
+                    }
+                }
+            }
+        }
+    }
+
+    first_hit
+}
+
+/// The aabb moved, rotated and scaled by the transform
+fn transform_aabb(aabb: &Aabb, transform: &Transform) -> Aabb {
+    let rotation = DMat3::from_quat(transform.rotation);
+    let abs_rotation = DMat3::from_cols(
+        rotation.x_axis.abs(),
+        rotation.y_axis.abs(),
+        rotation.z_axis.abs(),
+    );
+
+    Aabb {
+        center: transform.translation + rotation * (aabb.center * transform.scale),
+        half_extents: abs_rotation * (aabb.half_extents * transform.scale),
+    }
+}
+
+// When the moving aabb enters the static one, as a fraction of the movement, and the normal of
+// the face it enters through. The moving aabb is shrunk to a point and the static one grown by
+// its size, so it becomes a ray against a box.
+fn sweep(moving: &Aabb, other: &Aabb, movement: DVec3) -> Option<(f64, IVec3)> {
+    let half_extents = moving.half_extents + other.half_extents;
+    let min = other.center - half_extents;
+    let max = other.center + half_extents;
+
+    let mut entry = f64::NEG_INFINITY;
+    let mut exit = f64::INFINITY;
+    let mut normal = IVec3::ZERO;
+
+    for axis in 0..3 {
+        let origin = moving.center[axis];
+        let distance = movement[axis];
+
+        if distance == 0.0 {
+            // Moving parallel to the faces, it has to already be between them
+            if origin <= min[axis] || origin >= max[axis] {
+                return None;
+            }
+            continue;
+        }
+
+        let (near, far) = if distance > 0.0 {
+            (min[axis], max[axis])
+        } else {
+            (max[axis], min[axis])
+        };
+        let axis_entry = (near - origin) / distance;
+        let axis_exit = (far - origin) / distance;
+
+        if axis_entry > entry {
+            entry = axis_entry;
+            normal = IVec3::ZERO;
+            normal[axis] = if distance > 0.0 { -1 } else { 1 };
+        }
+        exit = exit.min(axis_exit);
+    }
+
+    // Already overlapping at the start, missed, or too far away to be reached this move.
+    if entry < 0.0 || entry > exit || entry > 1.0 {
+        return None;
+    }
+
+    Some((entry, normal))
+}
+
+fn prevent_tunneling(
+    time: Res<Time>,
+    world_map: Res<WorldMap>,
+    mut entities: Query<(&mut Physics, &Collider, &Transform)>,
+) {
+    let delta_time = time.delta_secs_f64();
+
+    for (mut physics, collider, transform) in entities.iter_mut() {
+        if physics.velocity.length() * delta_time < SWEEP_DISTANCE {
+            continue;
+        }
+
+        let aabb = transform_aabb(&collider.as_aabb(), transform);
+        let Some(hit) = sweep_aabb(&world_map, &aabb, physics.velocity, delta_time) else {
+            continue;
+        };
+
+        // Only the movement into the face is stopped, the entity still slides along it. It stops
+        // a little short so it doesn't start the next tick inside the block.
+        let axis = hit.normal.abs().as_dvec3();
+        let stopped = physics.velocity * axis * (hit.time - 0.01).max(0.0);
+        physics.velocity = physics.velocity * (DVec3::ONE - axis) + stopped;
+    }
+}
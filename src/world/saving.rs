@@ -0,0 +1,230 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use fmc::{networking::Server, prelude::*};
+
+use crate::{
+    chat::send_message,
+    database_io::{DatabaseIo, DatabaseTask},
+    settings::Settings,
+};
+
+/// Saves the world when asked to and at a regular interval, and makes backups of the database.
+///
+/// Everything that is part of a save is written in a single transaction, so a crash in the middle
+/// of it leaves the database as it was before the save started.
+pub struct SavingPlugin;
+impl Plugin for SavingPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_message::<SaveWorld>()
+            .insert_resource(WorldSave::default())
+            .insert_resource(SaveTasks::default())
+            .add_systems(Update, (autosave, finish_saves))
+            .add_systems(PostUpdate, write_world_save.after(SaveSystems::Collect));
+    }
+}
+
+const BACKUP_DIRECTORY: &str = "./backups";
+
+/// Systems that add what they own to the [WorldSave] when a [SaveWorld] message is sent. They
+/// run in [PostUpdate].
+#[derive(SystemSet, Debug, Hash, PartialEq, Eq, Clone)]
+pub enum SaveSystems {
+    Collect,
+}
+
+/// Save everything to the database. Must be sent before [PostUpdate].
+#[derive(Message, Clone, Copy)]
+pub struct SaveWorld {
+    /// The player that asked for the save, they are told when it is done.
+    pub requested_by: Option<Entity>,
+    /// Copy the database to the backup directory once the save is done.
+    pub backup: bool,
+}
+
+type Write = Box<dyn FnOnce(&rusqlite::Connection) -> rusqlite::Result<()> + Send>;
+
+/// What should be written by the next save. Systems in the [SaveSystems::Collect] set add their
+/// data to it when they read a [SaveWorld] message.
+#[derive(Resource, Default)]
+pub struct WorldSave {
+    writes: Vec<Write>,
+}
+
+impl WorldSave {
+    /// Add a write to the transaction, if it fails nothing is saved.
+    pub fn add(
+        &mut self,
+        write: impl FnOnce(&rusqlite::Connection) -> rusqlite::Result<()> + Send + 'static,
+    ) {
+        self.writes.push(Box::new(write));
+    }
+}
+
+#[derive(Resource, Default)]
+struct SaveTasks {
+    saves: Vec<(Vec<SaveWorld>, DatabaseTask<Result<(), String>>)>,
+    backups: Vec<(Option<Entity>, DatabaseTask<Result<String, String>>)>,
+}
+
+fn autosave(
+    settings: Res<Settings>,
+    time: Res<Time>,
+    mut timer: Local<Timer>,
+    mut save_events: MessageWriter<SaveWorld>,
+) {
+    if settings.autosave_interval == 0 {
+        return;
+    }
+
+    if timer.duration().is_zero() || settings.is_changed() {
+        *timer = Timer::from_seconds(settings.autosave_interval as f32, TimerMode::Repeating);
+    }
+
+    timer.tick(time.delta());
+    if timer.just_finished() {
+        save_events.write(SaveWorld {
+            requested_by: None,
+            backup: false,
+        });
+    }
+}
+
+// TODO: Chunks and block data (furnaces, chests etc.) are written by fmc's world module as soon as
+// they change, and it can't be told to hold them back, so they are not part of the transaction.
+fn write_world_save(
+    database_io: Res<DatabaseIo>,
+    mut world_save: ResMut<WorldSave>,
+    mut save_tasks: ResMut<SaveTasks>,
+    mut save_events: MessageReader<SaveWorld>,
+) {
+    // Saves that are requested at the same time are all covered by the same write.
+    let requests: Vec<SaveWorld> = save_events.read().copied().collect();
+    if requests.is_empty() {
+        return;
+    }
+
+    let writes = std::mem::take(&mut world_save.writes);
+    let task = database_io.run(move |database| {
+        let connection = database.get_write_connection();
+        let transaction = connection
+            .unchecked_transaction()
+            .map_err(|e| e.to_string())?;
+        for write in writes {
+            // The transaction is rolled back when it is dropped
+            write(&transaction).map_err(|e| e.to_string())?;
+        }
+        transaction.commit().map_err(|e| e.to_string())
+    });
+
+    // The io thread runs the jobs in order, so the backups include the save.
+    for request in requests.iter().filter(|request| request.backup) {
+        let task = database_io.run(|database| {
+            std::fs::create_dir_all(BACKUP_DIRECTORY).map_err(|e| e.to_string())?;
+            // Backups made within the same second are numbered after the first.
+            let timestamp = timestamp();
+            let mut path = format!("{}/{}.sqlite", BACKUP_DIRECTORY, timestamp);
+            let mut count = 1;
+            while std::path::Path::new(&path).exists() {
+                path = format!("{}/{}_{}.sqlite", BACKUP_DIRECTORY, timestamp, count);
+                count += 1;
+            }
+            // Unlike copying the file, this gives a consistent copy even if the database is
+            // written to while it runs.
+            database
+                .get_read_connection()
+                .execute("VACUUM INTO ?", [&path])
+                .map_err(|e| e.to_string())?;
+            Ok(path)
+        });
+        save_tasks.backups.push((request.requested_by, task));
+    }
+
+    save_tasks.saves.push((requests, task));
+}
+
+fn finish_saves(net: Res<Server>, mut save_tasks: ResMut<SaveTasks>) {
+    save_tasks.saves.retain_mut(|(requests, task)| {
+        let Some(result) = task.try_take() else {
+            return true;
+        };
+
+        match &result {
+            Ok(()) => info!("Saved the world"),
+            Err(e) => error!("Failed to save the world\nError: {}", e),
+        }
+
+        for player_entity in requests.iter().filter_map(|request| request.requested_by) {
+            let text = match &result {
+                Ok(()) => {
+                    "Saved the world. Chunks and block data are not part of the save, they are \
+                    written as soon as they change"
+                        .to_owned()
+                }
+                Err(e) => format!("Failed to save the world: {}", e),
+            };
+            send_message(&net, player_entity, text);
+        }
+
+        false
+    });
+
+    save_tasks.backups.retain_mut(|(requested_by, task)| {
+        let Some(result) = task.try_take() else {
+            return true;
+        };
+
+        let text = match result {
+            Ok(path) => {
+                info!("Backed up the world to {}", path);
+                format!("Backed up the world to {}", path)
+            }
+            Err(e) => {
+                error!("Failed to back up the world\nError: {}", e);
+                format!("Failed to back up the world: {}", e)
+            }
+        };
+
+        if let Some(player_entity) = requested_by {
+            send_message(&net, *player_entity, text);
+        }
+
+        false
+    });
+}
+
+// The current time in UTC as 'year-month-day_hour-minute-second'
+fn timestamp() -> String {
+    let seconds = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_secs() as i64;
+    let days = seconds.div_euclid(86400);
+    let time = seconds.rem_euclid(86400);
+
+    // Converts days since the epoch to a date, see
+    // http://howardhinnant.github.io/date_algorithms.html#civil_from_days
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let day_of_era = z.rem_euclid(146097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 {
+        month_index + 3
+    } else {
+        month_index - 9
+    };
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+
+    format!(
+        "{:04}-{:02}-{:02}_{:02}-{:02}-{:02}",
+        year,
+        month,
+        day,
+        time / 3600,
+        time % 3600 / 60,
+        time % 60
+    )
+}
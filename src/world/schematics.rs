@@ -0,0 +1,186 @@
+use std::{collections::HashMap, path::PathBuf};
+
+use fmc::{
+    blocks::{BlockData, BlockId, BlockRotation, BlockState, Blocks},
+    prelude::*,
+};
+use serde::{Deserialize, Serialize};
+
+/// Schematics are saved here by /schem save, blueprints can reference them by file name.
+pub const SCHEMATIC_PATH: &str = "./assets/server/schematics/";
+
+/// A box of blocks saved to a file so it can be pasted somewhere else, or built by the terrain
+/// generator as part of a structure blueprint.
+#[derive(Serialize, Deserialize)]
+pub struct Schematic {
+    pub size: IVec3,
+    /// The distinct blocks in the box, blocks are stored by name since ids change between runs.
+    palette: Vec<PaletteEntry>,
+    /// Index into the palette of each block. Ordered by x, then y, then z.
+    blocks: Vec<u16>,
+    /// The block data of the blocks that have any, by their index in blocks
+    #[serde(default)]
+    block_data: Vec<(usize, Vec<u8>)>,
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Eq, Hash, Clone)]
+struct PaletteEntry {
+    block: String,
+    /// Quarter turns from the front
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    rotation: Option<u8>,
+}
+
+fn rotation_index(rotation: BlockRotation) -> u8 {
+    match rotation {
+        BlockRotation::Front => 0,
+        BlockRotation::Right => 1,
+        BlockRotation::Back => 2,
+        BlockRotation::Left => 3,
+    }
+}
+
+impl Schematic {
+    /// Create a schematic from the blocks of a box of the given size, ordered by x, then y, then z.
+    pub fn new(
+        size: IVec3,
+        blocks: impl IntoIterator<Item = (BlockId, Option<BlockState>, Option<BlockData>)>,
+        block_configs: &Blocks,
+    ) -> Self {
+        let mut palette = Vec::new();
+        let mut palette_indices = HashMap::new();
+        let mut schematic_blocks = Vec::with_capacity(size.element_product() as usize);
+        let mut block_data = Vec::new();
+
+        for (index, (block_id, block_state, data)) in blocks.into_iter().enumerate() {
+            let entry = PaletteEntry {
+                block: block_configs.get_config(&block_id).name.clone(),
+                rotation: block_state
+                    .and_then(|state| state.rotation())
+                    .map(rotation_index),
+            };
+            let palette_index = *palette_indices.entry(entry.clone()).or_insert_with(|| {
+                palette.push(entry);
+                palette.len() as u16 - 1
+            });
+            schematic_blocks.push(palette_index);
+
+            if let Some(data) = data {
+                block_data.push((index, data.0));
+            }
+        }
+
+        Self {
+            size,
+            palette,
+            blocks: schematic_blocks,
+            block_data,
+        }
+    }
+
+    /// Names become file names, so they are kept to letters, digits, '_' and '-'.
+    pub fn is_valid_name(name: &str) -> bool {
+        !name.is_empty()
+            && name
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+    }
+
+    fn path(name: &str) -> PathBuf {
+        PathBuf::from(SCHEMATIC_PATH).join(format!("{}.json", name))
+    }
+
+    pub fn load(name: &str, blocks: &Blocks) -> Result<Self, String> {
+        let file = std::fs::File::open(Self::path(name))
+            .map_err(|e| format!("Could not open schematic '{}': {}", name, e))?;
+        let schematic: Self = serde_json::from_reader(file)
+            .map_err(|e| format!("Could not read schematic '{}': {}", name, e))?;
+        schematic.validate(blocks)?;
+        Ok(schematic)
+    }
+
+    pub fn save(&self, name: &str) -> Result<(), String> {
+        std::fs::create_dir_all(SCHEMATIC_PATH)
+            .and_then(|_| std::fs::write(Self::path(name), serde_json::to_vec(self).unwrap()))
+            .map_err(|e| format!("Could not save schematic '{}': {}", name, e))
+    }
+
+    fn validate(&self, blocks: &Blocks) -> Result<(), String> {
+        if let Some(entry) = self
+            .palette
+            .iter()
+            .find(|entry| !blocks.contains_block(&entry.block))
+        {
+            return Err(format!(
+                "The schematic contains the block '{}', but no block by that name exists",
+                entry.block
+            ));
+        }
+
+        if self.size.cmplt(IVec3::ONE).any()
+            || self.blocks.len() != self.size.element_product() as usize
+        {
+            return Err("The schematic's size does not match its blocks".to_owned());
+        }
+
+        if self
+            .blocks
+            .iter()
+            .any(|index| *index as usize >= self.palette.len())
+        {
+            return Err("The schematic has blocks that are not in its palette".to_owned());
+        }
+
+        Ok(())
+    }
+
+    /// The blocks of the schematic relative to its lowest corner, after it has been turned the
+    /// given number of quarter turns, the same way block rotations go from front to right.
+    pub fn blocks(
+        &self,
+        quarter_turns: u8,
+        blocks: &Blocks,
+    ) -> Vec<(IVec3, BlockId, Option<BlockState>, Option<BlockData>)> {
+        let quarter_turns = quarter_turns % 4;
+
+        let palette: Vec<(BlockId, Option<BlockState>)> = self
+            .palette
+            .iter()
+            .map(|entry| {
+                let block_state = entry.rotation.map(|rotation| {
+                    BlockState::new()
+                        .with_rotation(BlockRotation::from((rotation + quarter_turns) % 4))
+                });
+                (blocks.get_id(&entry.block), block_state)
+            })
+            .collect();
+        let mut block_data: HashMap<usize, Vec<u8>> = self.block_data.iter().cloned().collect();
+
+        let mut schematic_blocks = Vec::with_capacity(self.blocks.len());
+        let mut index = 0;
+        for x in 0..self.size.x {
+            for y in 0..self.size.y {
+                for z in 0..self.size.z {
+                    // Each turn takes the front (+z) to the right (+x)
+                    let mut position = IVec3::new(x, y, z);
+                    let mut size = self.size;
+                    for _ in 0..quarter_turns {
+                        position = IVec3::new(position.z, position.y, size.x - 1 - position.x);
+                        size = IVec3::new(size.z, size.y, size.x);
+                    }
+
+                    let (block_id, block_state) = palette[self.blocks[index] as usize];
+                    schematic_blocks.push((
+                        position,
+                        block_id,
+                        block_state,
+                        block_data.remove(&index).map(BlockData),
+                    ));
+                    index += 1;
+                }
+            }
+        }
+
+        schematic_blocks
+    }
+}
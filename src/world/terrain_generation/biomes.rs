@@ -1,10 +1,21 @@
-use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
 
-use fmc::blocks::{BLOCK_CONFIG_PATH, BlockId, Blocks};
+use fmc::{
+    blocks::{BLOCK_CONFIG_PATH, BlockId, Blocks},
+    prelude::*,
+};
+use serde::Deserialize;
 
 use super::blueprints::{BLUEPRINT_PATH, Blueprint, load_blueprints};
+use crate::assets::{AssetError, AssetErrors};
+
+pub const BIOME_PATH: &str = "./assets/server/biomes/";
 
 pub struct Biome {
+    /// Where the biome is placed in the climate. Each column of the world gets the biome that is
+    /// closest to its temperature and humidity.
+    pub temperature: f32,
+    pub humidity: f32,
     pub top_layer_block: BlockId,
     pub mid_layer_block: BlockId,
     pub bottom_layer_block: BlockId,
@@ -15,7 +26,10 @@ pub struct Biome {
     pub blueprints: Vec<Blueprint>,
 }
 
+#[derive(Deserialize)]
 struct BiomeJson {
+    temperature: f32,
+    humidity: f32,
     top_layer_block: String,
     mid_layer_block: String,
     bottom_layer_block: String,
@@ -26,97 +40,233 @@ struct BiomeJson {
     blueprints: Vec<String>,
 }
 
-// TODO: Create dynamically so it's easier to change. Should be able to add biomes between
-// intervals and error if they overlap.
+/// Empty if the biomes couldn't be loaded
+#[derive(Default)]
 pub struct Biomes {
-    biomes: [Biome; 1],
+    biomes: Vec<Biome>,
 }
 
 impl Biomes {
-    pub fn load(blocks: &Blocks) -> Self {
-        // TODO: Biomes should be loaded from file, and shouldn't look like this. No sand, air =
-        // filler. Not finished because I haven't decided on the biome model yet.
-        let biome_name = "base".to_owned();
-        let base_biome = BiomeJson {
-            top_layer_block: "grass".to_owned(),
-            mid_layer_block: "dirt".to_owned(),
-            bottom_layer_block: "stone".to_owned(),
-            surface_liquid: "surface_water".to_owned(),
-            sub_surface_liquid: "subsurface_water".to_owned(),
-            air: "air".to_owned(),
-            sand: "sand".to_owned(),
-            blueprints: vec![
-                "oak".to_owned(),
-                "birch".to_owned(),
-                "large_oak".to_owned(),
-                "tall_grass".to_owned(),
-                "coal_ore".to_owned(),
-                "iron_ore".to_owned(),
-                "gold_ore".to_owned(),
-            ],
+    /// Read the biomes and the blueprints they use from the asset directories. Every biome and
+    /// blueprint is checked, and all the problems found are returned together.
+    pub fn load(blocks: &Blocks) -> Result<Self, AssetErrors> {
+        Self::load_from(BIOME_PATH, BLUEPRINT_PATH, blocks)
+    }
+
+    fn load_from(
+        biome_path: &str,
+        blueprint_path: &str,
+        blocks: &Blocks,
+    ) -> Result<Self, AssetErrors> {
+        let directory = std::fs::read_dir(biome_path).map_err(|e| {
+            AssetError::new(
+                biome_path,
+                format!("Could not read files from biome directory\nError: {}", e),
+            )
+            .suggestion(format!("Make sure it is present at '{}'", biome_path))
+        })?;
+
+        let mut errors = AssetErrors::default();
+        // When the blueprints can't be loaded, the biomes are still checked, but not which
+        // blueprints they use.
+        let blueprints = match load_blueprints(blueprint_path, blocks) {
+            Ok(blueprints) => Some(blueprints),
+            Err(e) => {
+                errors.extend(e);
+                None
+            }
         };
+        let mut biomes = Vec::new();
 
-        fn validate_block(biome_name: &str, block_name: &str, blocks: &Blocks) {
-            if !blocks.contains_block(block_name) {
-                panic!(
-                    "Startup failed while validating the biomes. The biome '{}' \
-                    references a block with the name '{}', but no block by that name exists. \
-                    Make sure a block by the same name is present at '{}'",
-                    biome_name, block_name, BLOCK_CONFIG_PATH
-                );
+        for entry in directory {
+            let file_path = match entry {
+                Ok(entry) => entry.path(),
+                Err(e) => {
+                    errors.push(AssetError::new(
+                        biome_path,
+                        format!("Failed to read the filenames of the biomes\nError: {}", e),
+                    ));
+                    continue;
+                }
+            };
+
+            let file = match std::fs::File::open(&file_path) {
+                Ok(f) => f,
+                Err(e) => {
+                    errors.push(AssetError::new(
+                        &file_path,
+                        format!("Failed to open biome\nError: {}", e),
+                    ));
+                    continue;
+                }
+            };
+            let biome: BiomeJson = match serde_json::from_reader(file) {
+                Ok(b) => b,
+                Err(e) => {
+                    errors.push(AssetError::json(&file_path, &e));
+                    continue;
+                }
+            };
+
+            let mut valid = true;
+            for (field, block_name) in [
+                ("top_layer_block", &biome.top_layer_block),
+                ("mid_layer_block", &biome.mid_layer_block),
+                ("bottom_layer_block", &biome.bottom_layer_block),
+                ("surface_liquid", &biome.surface_liquid),
+                ("sub_surface_liquid", &biome.sub_surface_liquid),
+                ("air", &biome.air),
+                ("sand", &biome.sand),
+            ] {
+                if !blocks.contains_block(block_name) {
+                    errors.push(
+                        AssetError::new(
+                            &file_path,
+                            format!("No block by the name '{}' exists", block_name),
+                        )
+                        .field(field)
+                        .misspelled(block_name, BLOCK_CONFIG_PATH),
+                    );
+                    valid = false;
+                }
             }
-        }
 
-        fn validate_blueprint(
-            biome_name: &str,
-            blueprint_name: &str,
-            blueprints: &HashMap<String, Blueprint>,
-        ) {
-            if !blueprints.contains_key(blueprint_name) {
-                panic!(
-                    "Failed while validating the biomes. The biome '{}' depends on a blueprint by \
-                    the name '{}', but no such blueprint file exists. This is most likely the result of \
-                    a missing file at '{}', make sure it is present.",
-                    biome_name, blueprint_name, BLUEPRINT_PATH
-                );
+            let Some(blueprints) = &blueprints else {
+                continue;
+            };
+
+            for blueprint_name in biome.blueprints.iter() {
+                if !blueprints.contains_key(blueprint_name) {
+                    errors.push(
+                        AssetError::new(
+                            &file_path,
+                            format!("No blueprint by the name '{}' exists", blueprint_name),
+                        )
+                        .field("blueprints")
+                        .misspelled(blueprint_name, blueprint_path),
+                    );
+                    valid = false;
+                }
+            }
+
+            if !valid {
+                continue;
             }
+
+            biomes.push(Biome {
+                temperature: biome.temperature,
+                humidity: biome.humidity,
+                top_layer_block: blocks.get_id(&biome.top_layer_block),
+                mid_layer_block: blocks.get_id(&biome.mid_layer_block),
+                bottom_layer_block: blocks.get_id(&biome.bottom_layer_block),
+                surface_liquid: blocks.get_id(&biome.surface_liquid),
+                sub_surface_liquid: blocks.get_id(&biome.sub_surface_liquid),
+                air: blocks.get_id(&biome.air),
+                sand: blocks.get_id(&biome.sand),
+                blueprints: biome
+                    .blueprints
+                    .iter()
+                    .map(|name| blueprints[name].clone())
+                    .collect(),
+            });
         }
 
-        validate_block(&biome_name, &base_biome.top_layer_block, blocks);
-        validate_block(&biome_name, &base_biome.mid_layer_block, blocks);
-        validate_block(&biome_name, &base_biome.bottom_layer_block, blocks);
-        validate_block(&biome_name, &base_biome.surface_liquid, blocks);
-        validate_block(&biome_name, &base_biome.sub_surface_liquid, blocks);
-        validate_block(&biome_name, &base_biome.air, blocks);
-        validate_block(&biome_name, &base_biome.sand, blocks);
-
-        let blueprints = load_blueprints(blocks);
-        for blueprint_name in base_biome.blueprints.iter() {
-            validate_blueprint(&biome_name, blueprint_name, &blueprints);
+        if biomes.is_empty() && errors.is_empty() {
+            errors.push(
+                AssetError::new(biome_path, "There are no biomes")
+                    .suggestion("At least one biome must be present"),
+            );
         }
 
-        let base_biome = Biome {
-            top_layer_block: blocks.get_id(&base_biome.top_layer_block),
-            mid_layer_block: blocks.get_id(&base_biome.mid_layer_block),
-            bottom_layer_block: blocks.get_id(&base_biome.bottom_layer_block),
-            surface_liquid: blocks.get_id(&base_biome.surface_liquid),
-            sub_surface_liquid: blocks.get_id(&base_biome.sub_surface_liquid),
-            air: blocks.get_id(&base_biome.air),
-            sand: blocks.get_id(&base_biome.sand),
-            blueprints: base_biome
-                .blueprints
-                .iter()
-                .map(|name| blueprints[name].clone())
-                .collect(),
-        };
+        errors.into_result(Biomes { biomes })
+    }
 
-        return Biomes {
-            biomes: [base_biome],
-        };
+    /// The biome closest to the given climate.
+    pub fn get_biome(&self, temperature: f32, humidity: f32) -> &Biome {
+        let climate = Vec2::new(temperature, humidity);
+        return self
+            .biomes
+            .iter()
+            .min_by(|a, b| {
+                let a = Vec2::new(a.temperature, a.humidity).distance_squared(climate);
+                let b = Vec2::new(b.temperature, b.humidity).distance_squared(climate);
+                a.total_cmp(&b)
+            })
+            .unwrap();
     }
 
-    // TODO: When implementing this, remember that the call sites also cheat.
-    pub fn get_biome(&self) -> &Biome {
-        return &self.biomes[0];
+    /// All top layer blocks, these are the blocks features are placed on.
+    pub fn top_layer_blocks(&self) -> Vec<BlockId> {
+        let mut blocks: Vec<BlockId> = self
+            .biomes
+            .iter()
+            .map(|biome| biome.top_layer_block)
+            .collect();
+        blocks.sort();
+        blocks.dedup();
+        blocks
+    }
+}
+
+/// The biomes the terrain is generated with. They are shared with the terrain generator so they
+/// can be replaced while the server is running, chunks that are generated after that use the new
+/// biomes.
+#[derive(Resource, Clone)]
+pub struct SharedBiomes(Arc<RwLock<Arc<Biomes>>>);
+
+impl SharedBiomes {
+    pub fn new(biomes: Biomes) -> Self {
+        Self(Arc::new(RwLock::new(Arc::new(biomes))))
+    }
+
+    /// The current biomes
+    pub fn get(&self) -> Arc<Biomes> {
+        self.0.read().unwrap().clone()
+    }
+
+    pub fn replace(&self, biomes: Biomes) {
+        *self.0.write().unwrap() = Arc::new(biomes);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use super::*;
+    use crate::testing::{fixture, run_with_assets};
+
+    #[test]
+    fn broken_biomes() {
+        run_with_assets(|blocks: Res<Blocks>| {
+            let directory = fixture("biomes");
+            let Err(errors) = Biomes::load_from(&directory, BLUEPRINT_PATH, &blocks) else {
+                panic!("The broken biomes were loaded without any errors");
+            };
+
+            let report = errors.to_string();
+            let file = |name: &str| Path::new(&directory).join(name).display().to_string();
+            let expected = [
+                "Found 3 problems in the assets:".to_owned(),
+                format!("{}, line 11", file("missing_field.json")),
+                "missing field `sand`".to_owned(),
+                format!(
+                    "{}, field 'top_layer_block'\n    No block by the name 'gras' exists\n    Did you mean 'grass'?",
+                    file("unknown_names.json")
+                ),
+                format!(
+                    "{}, field 'blueprints'\n    No blueprint by the name 'iron_or' exists\n    Did you mean 'iron_ore'?",
+                    file("unknown_names.json")
+                ),
+            ];
+            for expected in expected {
+                assert!(
+                    report.contains(&expected),
+                    "Expected the report to contain:\n{}\n\nReport:\n{}",
+                    expected,
+                    report
+                );
+            }
+        });
     }
 }
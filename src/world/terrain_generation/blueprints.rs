@@ -1,7 +1,11 @@
 use fmc::prelude::*;
 
 use serde::Deserialize;
-use std::collections::{HashMap, HashSet};
+use std::{
+    collections::{HashMap, HashSet},
+    ops::RangeInclusive,
+    path::Path,
+};
 
 use fmc::{
     blocks::{BLOCK_CONFIG_PATH, BlockId, BlockPosition, Blocks},
@@ -12,6 +16,11 @@ use fmc::{
     },
 };
 
+use crate::{
+    assets::{AssetError, AssetErrors},
+    world::schematics::{SCHEMATIC_PATH, Schematic},
+};
+
 pub const BLUEPRINT_PATH: &str = "./assets/server/blueprints/";
 
 /// Blueprints contain instructions for placing terrain features.
@@ -38,6 +47,16 @@ pub enum Blueprint {
         placed_on: HashSet<BlockId>,
         can_replace: HashSet<BlockId>,
     },
+    // Places a column of blocks on the bottom of a body of liquid, like kelp.
+    UnderwaterDecoration {
+        decoration_block: BlockId,
+        // The block at the top of the column
+        top_block: BlockId,
+        placed_on: HashSet<BlockId>,
+        // The liquids the column can grow up through
+        liquids: HashSet<BlockId>,
+        height: UniformDistribution<i32>,
+    },
     Tree(Tree),
     OreVein {
         /// The block that is placed
@@ -47,6 +66,8 @@ pub enum Blueprint {
         /// Which blocks the ore can be placed into.
         can_replace: HashSet<BlockId>,
     },
+    Structure(Structure),
+    Lake(Lake),
 }
 
 impl Blueprint {
@@ -99,6 +120,25 @@ impl Blueprint {
                     .map(|block_name| blocks.get_id(block_name))
                     .collect::<HashSet<BlockId>>(),
             },
+            JsonBlueprint::UnderwaterDecoration {
+                decoration_block,
+                top_block,
+                placed_on,
+                liquids,
+                max_height,
+            } => Blueprint::UnderwaterDecoration {
+                decoration_block: blocks.get_id(&decoration_block),
+                top_block: blocks.get_id(top_block.as_ref().unwrap_or(decoration_block)),
+                placed_on: placed_on
+                    .iter()
+                    .map(|block_name| blocks.get_id(block_name))
+                    .collect::<HashSet<BlockId>>(),
+                liquids: liquids
+                    .iter()
+                    .map(|block_name| blocks.get_id(block_name))
+                    .collect::<HashSet<BlockId>>(),
+                height: UniformDistribution::new(1, max_height.unwrap_or(1) as i32),
+            },
             JsonBlueprint::Tree {
                 trunk_block,
                 leaf_block,
@@ -149,6 +189,125 @@ impl Blueprint {
                     .map(|block_name| blocks.get_id(block_name))
                     .collect::<HashSet<BlockId>>(),
             },
+            JsonBlueprint::Structure {
+                placement,
+                placed_on,
+                can_replace,
+                palette,
+                foundations,
+                layers,
+                schematic,
+            } => {
+                let palette: HashMap<char, BlockId> = palette
+                    .iter()
+                    .map(|(key, block_name)| {
+                        (key.chars().next().unwrap(), blocks.get_id(block_name))
+                    })
+                    .collect();
+                let foundations: HashSet<char> = foundations
+                    .iter()
+                    .map(|key| key.chars().next().unwrap())
+                    .collect();
+
+                let mut structure_blocks = Vec::new();
+                let mut structure_foundations = Vec::new();
+                let mut size = IVec3::ZERO;
+
+                if let Some(name) = schematic {
+                    let schematic = Schematic::load(name, blocks).unwrap();
+                    let air = blocks.get_id("air");
+                    let foundations: HashSet<BlockId> = foundations
+                        .iter()
+                        .map(|block_name| blocks.get_id(block_name))
+                        .collect();
+
+                    // Terrain generation can't place block states or block data, only the blocks
+                    // themselves are built.
+                    for (position, block_id, _, _) in schematic.blocks(0, blocks) {
+                        if block_id == air {
+                            continue;
+                        }
+                        structure_blocks.push((position, block_id));
+                        if foundations.contains(&block_id) {
+                            structure_foundations.push((position, block_id));
+                        }
+                    }
+                    size = schematic.size;
+                }
+
+                for (y, layer) in layers.iter().enumerate() {
+                    for (z, row) in layer.iter().enumerate() {
+                        for (x, key) in row.chars().enumerate() {
+                            let position = IVec3::new(x as i32, y as i32, z as i32);
+                            size = size.max(position + IVec3::ONE);
+
+                            let Some(block_id) = palette.get(&key) else {
+                                continue;
+                            };
+                            structure_blocks.push((position, *block_id));
+                            if foundations.contains(&key) {
+                                structure_foundations.push((position, *block_id));
+                            }
+                        }
+                    }
+                }
+
+                let liquids = |names: &Vec<String>| {
+                    names
+                        .iter()
+                        .map(|block_name| blocks.get_id(block_name))
+                        .collect::<HashSet<BlockId>>()
+                };
+
+                Blueprint::Structure(Structure {
+                    placement: match placement {
+                        PlacementJson::SeaFloor {
+                            liquids: names,
+                            min_depth,
+                        } => Placement::SeaFloor {
+                            liquids: liquids(names),
+                            min_depth: *min_depth,
+                        },
+                        PlacementJson::Coast { liquids: names } => Placement::Coast {
+                            liquids: liquids(names),
+                        },
+                    },
+                    placed_on: placed_on
+                        .iter()
+                        .map(|block_name| blocks.get_id(block_name))
+                        .collect::<HashSet<BlockId>>(),
+                    can_replace: can_replace
+                        .iter()
+                        .map(|block_name| blocks.get_id(block_name))
+                        .collect::<HashSet<BlockId>>(),
+                    blocks: structure_blocks,
+                    foundations: structure_foundations,
+                    size,
+                })
+            }
+            JsonBlueprint::Lake {
+                surface_liquid,
+                liquid,
+                placed_on,
+                can_replace,
+                max_depth,
+                min_area,
+                max_area,
+            } => Blueprint::Lake(Lake {
+                surface_liquid: blocks.get_id(surface_liquid),
+                liquid: blocks.get_id(liquid),
+                placed_on: placed_on
+                    .iter()
+                    .map(|block_name| blocks.get_id(block_name))
+                    .collect::<HashSet<BlockId>>(),
+                can_replace: can_replace
+                    .iter()
+                    .map(|block_name| blocks.get_id(block_name))
+                    .collect::<HashSet<BlockId>>(),
+                max_depth: *max_depth as usize,
+                min_area: *min_area as usize,
+                max_area: *max_area as usize,
+            }),
         }
     }
 
@@ -175,8 +334,27 @@ impl Blueprint {
                 let feature_distribution = UniformDistribution::new(0, Chunk::SIZE.pow(3) - 1);
                 for _ in 0..*count {
                     let position = origin + BlockPosition::from(feature_distribution.sample(rng));
-                    if distribution.sample(position.y, rng) {
-                        blueprint.construct(position, chunk, surface, rng);
+                    if !distribution.sample(position.y, rng) {
+                        continue;
+                    }
+
+                    match blueprint.as_ref() {
+                        // Veins wander away from where they start, they are kept within the
+                        // heights they are distributed over.
+                        Blueprint::OreVein {
+                            ore_block,
+                            count,
+                            can_replace,
+                        } => ore_vein(
+                            position,
+                            *ore_block,
+                            *count,
+                            can_replace,
+                            distribution.heights(),
+                            chunk,
+                            rng,
+                        ),
+                        _ => blueprint.construct(position, chunk, surface, rng),
                     }
                 }
             }
@@ -207,6 +385,46 @@ impl Blueprint {
 
                 terrain_feature.apply(chunk_position, chunk);
             }
+            Blueprint::UnderwaterDecoration {
+                decoration_block,
+                top_block,
+                placed_on,
+                liquids,
+                height,
+            } => {
+                let chunk_position = ChunkPosition::from(origin);
+                let local = origin - BlockPosition::from(chunk_position);
+                let (x, z) = (local.x as usize, local.z as usize);
+
+                let Some(floor_y) = (0..Chunk::SIZE - 1).rev().find(|y| {
+                    placed_on.contains(&chunk[[x, *y, z]])
+                        && liquids.contains(&chunk[[x, *y + 1, z]])
+                }) else {
+                    return;
+                };
+
+                // The column stops one block short of the top of the liquid so it stays
+                // submerged, and it can't grow out of the chunk.
+                let max_y = (floor_y + height.sample(rng) as usize).min(Chunk::SIZE - 2);
+                let mut top = floor_y;
+                for y in floor_y + 1..=max_y {
+                    if !liquids.contains(&chunk[[x, y, z]])
+                        || !liquids.contains(&chunk[[x, y + 1, z]])
+                    {
+                        break;
+                    }
+                    top = y;
+                }
+
+                if top == floor_y {
+                    return;
+                }
+
+                for y in floor_y + 1..top {
+                    chunk[[x, y, z]] = *decoration_block;
+                }
+                chunk[[x, top, z]] = *top_block;
+            }
             Blueprint::Generator(generator_function) => {
                 generator_function(origin, chunk);
             }
@@ -235,30 +453,61 @@ impl Blueprint {
                 count,
                 can_replace,
             } => {
-                let mut terrain_feature = TerrainFeature::default();
-
-                // TODO: Implement as const when making rand lib
-                let directions = Choose::new(&[
-                    IVec3::X,
-                    IVec3::NEG_X,
-                    IVec3::Y,
-                    IVec3::NEG_Y,
-                    IVec3::Z,
-                    IVec3::NEG_Z,
-                ]);
-
-                let mut position = origin;
-                for direction in directions.iter(rng).take(*count as usize) {
-                    position += *direction;
-                    terrain_feature.insert_block(position, *ore_block)
-                }
-
-                terrain_feature.can_replace.extend(can_replace);
-
-                terrain_feature.apply(ChunkPosition::from(origin), chunk);
+                ore_vein(
+                    origin,
+                    *ore_block,
+                    *count,
+                    can_replace,
+                    i32::MIN..=i32::MAX,
+                    chunk,
+                    rng,
+                );
+            }
+            Blueprint::Structure(structure) => {
+                structure.construct(origin, chunk);
             }
+            Blueprint::Lake(lake) => {
+                lake.construct(origin, chunk, surface);
+            }
+        }
+    }
+}
+
+// Places the ore in a random walk from the origin. Steps that would take the vein outside of the
+// heights are skipped.
+fn ore_vein(
+    origin: BlockPosition,
+    ore_block: BlockId,
+    count: u32,
+    can_replace: &HashSet<BlockId>,
+    heights: RangeInclusive<i32>,
+    chunk: &mut Chunk,
+    rng: &mut Rng,
+) {
+    let mut terrain_feature = TerrainFeature::default();
+
+    // TODO: Implement as const when making rand lib
+    let directions = Choose::new(&[
+        IVec3::X,
+        IVec3::NEG_X,
+        IVec3::Y,
+        IVec3::NEG_Y,
+        IVec3::Z,
+        IVec3::NEG_Z,
+    ]);
+
+    let mut position = origin;
+    for direction in directions.iter(rng).take(count as usize) {
+        if !heights.contains(&(position + *direction).y) {
+            continue;
         }
+        position += *direction;
+        terrain_feature.insert_block(position, ore_block)
     }
+
+    terrain_feature.can_replace.extend(can_replace);
+
+    terrain_feature.apply(ChunkPosition::from(origin), chunk);
 }
 
 // This allows json blueprints to be nested in an ergonomic way in exchange for less ergonomic
@@ -330,6 +579,16 @@ enum JsonBlueprint {
         placed_on: Vec<String>,
         can_replace: Vec<String>,
     },
+    UnderwaterDecoration {
+        decoration_block: String,
+        // Placed at the top of the column instead of the decoration block, e.g. the tip of kelp.
+        top_block: Option<String>,
+        placed_on: Vec<String>,
+        // The liquids the column can grow up through
+        liquids: Vec<String>,
+        // The column is a random number of blocks tall, up to this. Only one block if not set.
+        max_height: Option<u32>,
+    },
     Tree {
         trunk_block: String,
         leaf_block: String,
@@ -346,6 +605,38 @@ enum JsonBlueprint {
         count: u32,
         can_replace: Vec<String>,
     },
+    Structure {
+        placement: PlacementJson,
+        placed_on: Vec<String>,
+        can_replace: Vec<String>,
+        // Maps single characters to block names.
+        #[serde(default)]
+        palette: HashMap<String, String>,
+        // Palette characters whose blocks are extended downwards until they reach the ground.
+        // When built from a schematic, these are block names instead.
+        #[serde(default)]
+        foundations: Vec<String>,
+        // Horizontal layers from the bottom up. Each layer is a list of rows along the z axis,
+        // where each character is a block along the x axis. Characters that are not in the
+        // palette leave the terrain as it is.
+        #[serde(default)]
+        layers: Vec<Vec<String>>,
+        // Name of a schematic saved with /schem save to build instead of the palette and layers.
+        // Its air blocks leave the terrain as it is.
+        schematic: Option<String>,
+    },
+    Lake {
+        // The block at the top of the lake
+        surface_liquid: String,
+        // The block that fills the rest of the lake
+        liquid: String,
+        placed_on: Vec<String>,
+        can_replace: Vec<String>,
+        // How many blocks the water can rise above the lowest point of the depression
+        max_depth: u32,
+        min_area: u32,
+        max_area: u32,
+    },
 }
 
 impl JsonBlueprint {
@@ -444,6 +735,31 @@ impl JsonBlueprint {
                     validate_block("can_replace", block, blocks)?;
                 }
             }
+            Self::UnderwaterDecoration {
+                decoration_block,
+                top_block,
+                placed_on,
+                liquids,
+                max_height,
+            } => {
+                validate_block("decoration_block", &decoration_block, blocks)?;
+                if let Some(top_block) = top_block {
+                    validate_block("top_block", top_block, blocks)?;
+                }
+
+                for block in placed_on {
+                    validate_block("placed_on", block, blocks)?;
+                }
+                for block in liquids {
+                    validate_block("liquids", block, blocks)?;
+                }
+
+                if *max_height == Some(0) {
+                    return Some(format!(
+                        "Invalid underwater decoration: max_height must be at least 1"
+                    ));
+                }
+            }
             Self::Tree {
                 trunk_block,
                 leaf_block,
@@ -472,33 +788,176 @@ impl JsonBlueprint {
                     validate_block("can_replace", block_name, blocks)?;
                 }
             }
+            Self::Structure {
+                placement,
+                placed_on,
+                can_replace,
+                palette,
+                foundations,
+                layers,
+                schematic,
+            } => {
+                if let Some(name) = schematic {
+                    if !palette.is_empty() || !layers.is_empty() {
+                        return Some(format!(
+                            "Structures built from a schematic can't have a palette or layers"
+                        ));
+                    }
+
+                    let schematic = match Schematic::load(name, blocks) {
+                        Ok(schematic) => schematic,
+                        Err(e) => {
+                            return Some(format!(
+                                "{e}. Make sure it is present in '{SCHEMATIC_PATH}'"
+                            ));
+                        }
+                    };
+
+                    if let Some(block) = foundations
+                        .iter()
+                        .find(|block| !blocks.contains_block(block))
+                    {
+                        return Some(format!(
+                            "The foundation '{}' is not the name of a block",
+                            block
+                        ));
+                    }
+
+                    if schematic.size.x > Chunk::SIZE as i32
+                        || schematic.size.z > Chunk::SIZE as i32
+                    {
+                        return Some(format!(
+                            "Structures can be at most {} blocks wide",
+                            Chunk::SIZE
+                        ));
+                    }
+                }
+
+                let liquids = match placement {
+                    PlacementJson::SeaFloor { liquids, .. } => liquids,
+                    PlacementJson::Coast { liquids } => liquids,
+                };
+                for block in liquids {
+                    validate_block("liquids", block, blocks)?;
+                }
+                for block in placed_on {
+                    validate_block("placed_on", block, blocks)?;
+                }
+                for block in can_replace {
+                    validate_block("can_replace", block, blocks)?;
+                }
+
+                for (key, block) in palette {
+                    if key.chars().count() != 1 {
+                        return Some(format!(
+                            "Palette keys must be a single character, '{}' is not",
+                            key
+                        ));
+                    }
+                    validate_block("palette", block, blocks)?;
+                }
+
+                // Foundations of schematics are block names, checked above
+                for key in foundations {
+                    if schematic.is_none() && !palette.contains_key(key) {
+                        return Some(format!("The foundation '{}' is not in the palette", key));
+                    }
+                }
+
+                // Structures are constructed one chunk at a time, they have to fit inside one
+                // horizontally.
+                let too_wide = layers.iter().any(|layer| {
+                    layer.len() > Chunk::SIZE
+                        || layer.iter().any(|row| row.chars().count() > Chunk::SIZE)
+                });
+                if too_wide {
+                    return Some(format!(
+                        "Structures can be at most {} blocks wide",
+                        Chunk::SIZE
+                    ));
+                }
+            }
+            Self::Lake {
+                surface_liquid,
+                liquid,
+                placed_on,
+                can_replace,
+                min_area,
+                max_area,
+                ..
+            } => {
+                validate_block("surface_liquid", surface_liquid, blocks)?;
+                validate_block("liquid", liquid, blocks)?;
+                for block in placed_on {
+                    validate_block("placed_on", block, blocks)?;
+                }
+                for block in can_replace {
+                    validate_block("can_replace", block, blocks)?;
+                }
+
+                if min_area > max_area {
+                    return Some(format!(
+                        "Invalid lake: min_area({}) must be less than max_area({})",
+                        min_area, max_area
+                    ));
+                }
+            }
         }
 
         return None;
     }
 }
 
-pub fn load_blueprints(blocks: &Blocks) -> HashMap<String, Blueprint> {
+pub fn load_blueprints(
+    blueprint_path: &str,
+    blocks: &Blocks,
+) -> Result<HashMap<String, Blueprint>, AssetErrors> {
     let mut named_json_blueprints = HashMap::new();
-
-    let directory = std::fs::read_dir(BLUEPRINT_PATH).expect(&format!(
-        "Could not read files from blueprints directory, make sure it is present as '{}'",
-        BLUEPRINT_PATH
-    ));
+    let mut errors = AssetErrors::default();
+
+    let directory = std::fs::read_dir(blueprint_path).map_err(|e| {
+        AssetError::new(
+            blueprint_path,
+            format!(
+                "Could not read files from blueprints directory\nError: {}",
+                e
+            ),
+        )
+        .suggestion(format!("Make sure it is present as '{}'", blueprint_path))
+    })?;
 
     for entry in directory {
-        let file_path = entry
-            .expect("Failed to read the filenames of the block configs")
-            .path();
+        let file_path = match entry {
+            Ok(entry) => entry.path(),
+            Err(e) => {
+                errors.push(AssetError::new(
+                    blueprint_path,
+                    format!(
+                        "Failed to read the filenames of the blueprints\nError: {}",
+                        e
+                    ),
+                ));
+                continue;
+            }
+        };
 
-        let file = std::fs::File::open(&file_path).expect(&format!(
-            "Failed to open blueprint at: '{}'",
-            file_path.display()
-        ));
-        let blueprint: JsonBlueprint = serde_json::from_reader(file).expect(&format!(
-            "Failed to read blueprint at: '{}'",
-            file_path.display()
-        ));
+        let file = match std::fs::File::open(&file_path) {
+            Ok(f) => f,
+            Err(e) => {
+                errors.push(AssetError::new(
+                    &file_path,
+                    format!("Failed to open blueprint\nError: {}", e),
+                ));
+                continue;
+            }
+        };
+        let blueprint: JsonBlueprint = match serde_json::from_reader(file) {
+            Ok(b) => b,
+            Err(e) => {
+                errors.push(AssetError::json(&file_path, &e));
+                continue;
+            }
+        };
         let name = file_path
             .file_stem()
             .unwrap()
@@ -513,7 +972,16 @@ pub fn load_blueprints(blocks: &Blocks) -> HashMap<String, Blueprint> {
             continue;
         };
 
-        panic!("Error while parsing terrain feature blueprint '{blueprint_name}'.\nError: {err}");
+        errors.push(AssetError::new(
+            Path::new(blueprint_path).join(format!("{}.json", blueprint_name)),
+            format!(
+                "Error while parsing terrain feature blueprint '{blueprint_name}'.\nError: {err}"
+            ),
+        ));
+    }
+
+    if !errors.is_empty() {
+        return Err(errors);
     }
 
     let mut blueprints = HashMap::new();
@@ -523,7 +991,7 @@ pub fn load_blueprints(blocks: &Blocks) -> HashMap<String, Blueprint> {
         blueprints.insert(name.to_owned(), blueprint);
     }
 
-    return blueprints;
+    return Ok(blueprints);
 }
 
 #[derive(Clone)]
@@ -705,6 +1173,275 @@ impl FoliageStyle {
     }
 }
 
+/// A structure built from a fixed layout of blocks.
+#[derive(Clone)]
+struct Structure {
+    placement: Placement,
+    // Which blocks the structure can be built on.
+    placed_on: HashSet<BlockId>,
+    // Which blocks the structure can replace.
+    can_replace: HashSet<BlockId>,
+    // Blocks relative to the lowest corner of the structure.
+    blocks: Vec<(IVec3, BlockId)>,
+    // Blocks that are extended downwards until they hit something they can't replace.
+    foundations: Vec<(IVec3, BlockId)>,
+    size: IVec3,
+}
+
+/// Where a structure can be placed.
+#[derive(Clone)]
+enum Placement {
+    /// On the bottom of the sea, fully submerged.
+    SeaFloor {
+        liquids: HashSet<BlockId>,
+        // How many blocks of liquid there must at least be above the structure.
+        min_depth: i32,
+    },
+    /// Along the shoreline, standing partly on land and partly over the water at sea level.
+    Coast { liquids: HashSet<BlockId> },
+}
+
+impl Structure {
+    // How far foundations are extended downwards
+    const MAX_FOUNDATION_DEPTH: i32 = 16;
+
+    fn construct(&self, origin: BlockPosition, chunk: &mut Chunk) {
+        if self.size.x > Chunk::SIZE as i32 || self.size.z > Chunk::SIZE as i32 {
+            return;
+        }
+
+        let chunk_position = ChunkPosition::from(origin);
+        let chunk_origin = BlockPosition::from(chunk_position);
+
+        // Move the structure so its whole footprint is inside the chunk, it's easier to check that
+        // the placement is valid when everything is at hand.
+        let x = (origin.x - chunk_origin.x).min(Chunk::SIZE as i32 - self.size.x) as usize;
+        let z = (origin.z - chunk_origin.z).min(Chunk::SIZE as i32 - self.size.z) as usize;
+        let corners = [
+            (x, z),
+            (x + self.size.x as usize - 1, z),
+            (x, z + self.size.z as usize - 1),
+            (x + self.size.x as usize - 1, z + self.size.z as usize - 1),
+        ];
+
+        let base_y = match &self.placement {
+            Placement::SeaFloor { liquids, min_depth } => {
+                let center = (x + self.size.x as usize / 2, z + self.size.z as usize / 2);
+                let Some(floor_y) = (0..Chunk::SIZE - 1).rev().find(|y| {
+                    self.placed_on.contains(&chunk[[center.0, *y, center.1]])
+                        && liquids.contains(&chunk[[center.0, *y + 1, center.1]])
+                }) else {
+                    return;
+                };
+
+                // The sea surface is at y=0, the structure must be fully below it.
+                let top = chunk_position.y + floor_y as i32 + self.size.y;
+                if top > -min_depth {
+                    return;
+                }
+
+                // Every corner must have liquid above it, or the structure would be sticking out
+                // of a cliff.
+                if !corners
+                    .iter()
+                    .all(|(x, z)| liquids.contains(&chunk[[*x, floor_y + 1, *z]]))
+                {
+                    return;
+                }
+
+                // Sink the bottom layer into the floor
+                floor_y as i32
+            }
+            Placement::Coast { liquids } => {
+                let sea_level = -chunk_position.y;
+                if sea_level < 0 || sea_level >= Chunk::SIZE as i32 - 1 {
+                    return;
+                }
+                let sea_level = sea_level as usize;
+
+                let mut has_land = false;
+                let mut has_water = false;
+                for (x, z) in corners {
+                    let block = chunk[[x, sea_level, z]];
+                    let above = chunk[[x, sea_level + 1, z]];
+                    if liquids.contains(&block) {
+                        has_water = true;
+                    } else if self.placed_on.contains(&block) && !self.placed_on.contains(&above) {
+                        has_land = true;
+                    } else {
+                        // Either too steep or something is in the way
+                        return;
+                    }
+                }
+
+                if !has_land || !has_water {
+                    return;
+                }
+
+                // The bottom layer is level with the ground, one above the water.
+                sea_level as i32 + 1
+            }
+        };
+
+        let structure_origin = chunk_origin + BlockPosition::new(x as i32, base_y, z as i32);
+
+        let mut terrain_feature = TerrainFeature::default();
+        terrain_feature.can_replace.extend(&self.can_replace);
+        for (offset, block_id) in self.blocks.iter() {
+            terrain_feature.insert_block(structure_origin + *offset, *block_id);
+        }
+        terrain_feature
+            .add_bounding_box(structure_origin, structure_origin + self.size - IVec3::ONE);
+        terrain_feature.apply(chunk_position, chunk);
+
+        if self.foundations.is_empty() {
+            return;
+        }
+
+        // Foundations only go through whatever they're allowed to replace, so they stop at the
+        // ground.
+        let mut foundations = TerrainFeature::default();
+        foundations.can_replace.extend(&self.can_replace);
+        for (offset, block_id) in self.foundations.iter() {
+            if offset.y != 0 {
+                continue;
+            }
+            let foundation = structure_origin + *offset;
+            for depth in 1..=Self::MAX_FOUNDATION_DEPTH {
+                let position = foundation - IVec3::new(0, depth, 0);
+                let local = position - chunk_origin;
+                if local.y >= 0
+                    && !self
+                        .can_replace
+                        .contains(&chunk[[local.x as usize, local.y as usize, local.z as usize]])
+                {
+                    break;
+                }
+                foundations.insert_block(position, *block_id);
+            }
+        }
+        foundations.apply(chunk_position, chunk);
+    }
+}
+
+/// A small lake that fills a depression in the ground.
+#[derive(Clone)]
+struct Lake {
+    surface_liquid: BlockId,
+    liquid: BlockId,
+    // Which blocks the lake can be placed on.
+    placed_on: HashSet<BlockId>,
+    // Which blocks the water can fill.
+    can_replace: HashSet<BlockId>,
+    max_depth: usize,
+    min_area: usize,
+    max_area: usize,
+}
+
+impl Lake {
+    fn construct(&self, origin: BlockPosition, chunk: &mut Chunk, surface: &Surface) {
+        let index = origin.as_chunk_index() >> 4;
+        let Some((surface_y, surface_block)) = &surface[index] else {
+            return;
+        };
+
+        if !self.placed_on.contains(surface_block) {
+            return;
+        }
+
+        let chunk_position = ChunkPosition::from(origin);
+        let local = origin - BlockPosition::from(chunk_position);
+        let (x, z) = (local.x as usize, local.z as usize);
+
+        // Raise the water one block at a time for as long as it stays inside the depression.
+        let mut lake = None;
+        let max_level = (surface_y + self.max_depth).min(Chunk::SIZE - 1);
+        for level in surface_y + 1..=max_level {
+            match self.flood(chunk, x, level, z) {
+                Some(area) => lake = Some((level, area)),
+                None => break,
+            }
+        }
+
+        let Some((level, area)) = lake else {
+            return;
+        };
+
+        if area.len() < self.min_area {
+            return;
+        }
+
+        for (x, z) in area {
+            chunk[[x, level, z]] = self.surface_liquid;
+
+            let mut y = level;
+            while self.can_replace.contains(&chunk[[x, y - 1, z]]) {
+                y -= 1;
+                chunk[[x, y, z]] = self.liquid;
+            }
+        }
+    }
+
+    // Flood fill outwards from the position at the given height. Returns the columns the water
+    // covers, or None if it escapes the depression, either by reaching the edge of the chunk,
+    // spilling into a hole that goes below the chunk, or by growing too large.
+    fn flood(
+        &self,
+        chunk: &Chunk,
+        x: usize,
+        level: usize,
+        z: usize,
+    ) -> Option<Vec<(usize, usize)>> {
+        if !self.can_replace.contains(&chunk[[x, level, z]]) {
+            return None;
+        }
+
+        let mut area = vec![(x, z)];
+        let mut visited = HashSet::from([(x, z)]);
+
+        let mut index = 0;
+        while let Some((x, z)) = area.get(index).cloned() {
+            index += 1;
+
+            // What's outside the chunk isn't known, so the lake can't touch its edges.
+            if x == 0 || z == 0 || x == Chunk::SIZE - 1 || z == Chunk::SIZE - 1 {
+                return None;
+            }
+
+            if area.len() > self.max_area {
+                return None;
+            }
+
+            let has_floor = (0..level).any(|y| !self.can_replace.contains(&chunk[[x, y, z]]));
+            if !has_floor {
+                return None;
+            }
+
+            for (x, z) in [(x + 1, z), (x - 1, z), (x, z + 1), (x, z - 1)] {
+                if self.can_replace.contains(&chunk[[x, level, z]]) && visited.insert((x, z)) {
+                    area.push((x, z));
+                }
+            }
+        }
+
+        Some(area)
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum PlacementJson {
+    SeaFloor {
+        // The liquids the structure can be submerged in
+        liquids: Vec<String>,
+        #[serde(default)]
+        min_depth: i32,
+    },
+    Coast {
+        liquids: Vec<String>,
+    },
+}
+
 #[derive(Deserialize)]
 #[serde(tag = "type", rename_all = "lowercase")]
 enum FoliageStyleJson {
@@ -742,6 +1479,13 @@ impl HeightDistribution {
         }
     }
 
+    /// The heights the distribution can place at
+    pub fn heights(&self) -> RangeInclusive<i32> {
+        match *self {
+            Self::Uniform { min, max, .. } | Self::Triangle { min, max, .. } => min..=max,
+        }
+    }
+
     pub fn sample(&self, height: i32, rng: &mut Rng) -> bool {
         match *self {
             Self::Uniform {
@@ -766,9 +1510,9 @@ impl HeightDistribution {
                 if height < min {
                     false
                 } else if height < mid {
-                    chance < probability * ((height - min) / (mid - min)) as f32
+                    chance < probability * (height - min) as f32 / (mid - min) as f32
                 } else if height <= max {
-                    chance < probability * ((max - height) / (max - mid)) as f32
+                    chance < probability * (max - height) as f32 / (max - mid) as f32
                 } else {
                     false
                 }
@@ -776,3 +1520,113 @@ impl HeightDistribution {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{fixture, run_with_assets};
+
+    #[test]
+    fn ore_veins_stay_within_their_heights() {
+        run_with_assets(|blocks: Res<Blocks>| {
+            let blueprints = match load_blueprints(BLUEPRINT_PATH, &blocks) {
+                Ok(blueprints) => blueprints,
+                Err(e) => panic!("{}", e),
+            };
+            let stone = blocks.get_id("stone");
+            let air = blocks.get_id("air");
+            let surface_blocks: Vec<BlockId> = Vec::new();
+
+            let mut ore_layers = 0;
+            for (name, blueprint) in blueprints.iter() {
+                let Blueprint::Distribution {
+                    blueprint: vein,
+                    distribution,
+                    ..
+                } = blueprint
+                else {
+                    continue;
+                };
+                let Blueprint::OreVein { ore_block, .. } = vein.as_ref() else {
+                    continue;
+                };
+                ore_layers += 1;
+
+                // Stone from a chunk below the lowest height to a chunk above the highest, so
+                // veins can wander out in both directions.
+                let heights = distribution.heights();
+                let lowest = heights.start() - Chunk::SIZE as i32;
+                let highest = heights.end() + Chunk::SIZE as i32;
+
+                let mut placed = 0;
+                for chunk_y in (lowest..=highest).step_by(Chunk::SIZE) {
+                    let chunk_position = ChunkPosition::from(BlockPosition::new(0, chunk_y, 0));
+                    for seed in 0..32 {
+                        let mut chunk = Chunk::default();
+                        chunk.blocks = vec![stone; Chunk::SIZE.pow(3)];
+                        let surface = Surface::new(&chunk, &surface_blocks, air);
+                        let mut rng = Rng::new(seed);
+                        blueprint.construct(chunk_position.into(), &mut chunk, &surface, &mut rng);
+
+                        for x in 0..Chunk::SIZE {
+                            for y in 0..Chunk::SIZE {
+                                for z in 0..Chunk::SIZE {
+                                    if chunk[[x, y, z]] != *ore_block {
+                                        continue;
+                                    }
+
+                                    let height = chunk_position.y + y as i32;
+                                    assert!(
+                                        heights.contains(&height),
+                                        "'{}' placed ore at y {}, outside of its heights {:?}",
+                                        name,
+                                        height,
+                                        heights
+                                    );
+                                    placed += 1;
+                                }
+                            }
+                        }
+                    }
+                }
+
+                assert_ne!(placed, 0, "'{}' didn't place any ore", name);
+            }
+
+            assert_ne!(ore_layers, 0, "No ore blueprints were found");
+        });
+    }
+
+    #[test]
+    fn broken_blueprints() {
+        run_with_assets(|blocks: Res<Blocks>| {
+            let directory = fixture("blueprints");
+            let Err(errors) = load_blueprints(&directory, &blocks) else {
+                panic!("The broken blueprints were loaded without any errors");
+            };
+
+            let report = errors.to_string();
+            let file = |name: &str| Path::new(&directory).join(name).display().to_string();
+            let expected = [
+                "Found 3 problems in the assets:".to_owned(),
+                format!("{}, line 6", file("syntax_error.json")),
+                format!(
+                    "{}\n    Error while parsing terrain feature blueprint 'unknown_ore'.\nError: 'ore_block' references a block with the name 'coal_or'",
+                    file("unknown_ore.json")
+                ),
+                format!(
+                    "{}\n    Error while parsing terrain feature blueprint 'inverted_distribution'.\nError: Invalid height distribution: min(20) must be less than mid(-40)",
+                    file("inverted_distribution.json")
+                ),
+            ];
+            for expected in expected {
+                assert!(
+                    report.contains(&expected),
+                    "Expected the report to contain:\n{}\n\nReport:\n{}",
+                    expected,
+                    report
+                );
+            }
+        });
+    }
+}
@@ -0,0 +1,202 @@
+//! The original interpolation, kept to compare the vectorized implementation against.
+
+use fmc::world::chunk::Chunk;
+
+use super::super::{
+    CAVES_HEIGHT_FACTOR, CAVES_WIDTH_FACTOR, CHUNK_HEIGHT, TERRAIN_HEIGHT_FACTOR,
+    TERRAIN_WIDTH_FACTOR,
+};
+
+// XXX: These interpolate functions are specific instead of generic 'interpolate_3d<HEIGHT, WIDTH,
+// DEPTH>' etc because the compiler won't autovec them.
+pub fn continent_height(noise: &[f32]) -> Vec<f32> {
+    const WIDTH: usize = Chunk::SIZE / TERRAIN_WIDTH_FACTOR;
+    const HEIGHT: usize = WIDTH;
+
+    fn index(x: usize, z: usize) -> usize {
+        return x * (HEIGHT + 1) + z;
+    }
+
+    let mut result = vec![0.0; Chunk::SIZE * Chunk::SIZE];
+
+    for x_noise in 0..WIDTH {
+        for z_noise in 0..HEIGHT {
+            let back_left = noise[index(x_noise + 0, z_noise + 0)];
+            let front_left = noise[index(x_noise + 0, z_noise + 1)];
+            let back_right = noise[index(x_noise + 1, z_noise + 0)];
+            let front_right = noise[index(x_noise + 1, z_noise + 1)];
+
+            let back_increment = (back_right - back_left) * 0.25;
+            let front_increment = (front_right - front_left) * 0.25;
+
+            let mut back = back_left;
+            let mut front = front_left;
+
+            for x_index in 0..TERRAIN_WIDTH_FACTOR {
+                let x = x_noise * TERRAIN_WIDTH_FACTOR + x_index;
+
+                let middle_increment = (front - back) * 0.25;
+                let mut density = back;
+
+                for z_index in 0..TERRAIN_WIDTH_FACTOR {
+                    let z = z_noise * WIDTH + z_index;
+                    result[x * Chunk::SIZE + z] = density;
+                    density += middle_increment;
+                }
+
+                back += back_increment;
+                front += front_increment;
+            }
+        }
+    }
+
+    return result;
+}
+
+pub fn caves(noise: &[f32]) -> Vec<f32> {
+    const WIDTH: usize = Chunk::SIZE / CAVES_WIDTH_FACTOR;
+    const HEIGHT: usize = CHUNK_HEIGHT / CAVES_HEIGHT_FACTOR;
+    const DEPTH: usize = WIDTH;
+    const WIDTH_INCREMENT: f32 = 1.0 / CAVES_WIDTH_FACTOR as f32;
+    const HEIGHT_INCREMENT: f32 = 1.0 / CAVES_HEIGHT_FACTOR as f32;
+
+    fn index(x: usize, y: usize, z: usize) -> usize {
+        return x * (DEPTH + 1) * (HEIGHT + 1) + z * (HEIGHT + 1) + y;
+    }
+
+    let mut result = vec![0.0; Chunk::SIZE * CHUNK_HEIGHT * Chunk::SIZE];
+
+    for x_noise in 0..WIDTH {
+        for z_noise in 0..DEPTH {
+            for y_noise in 0..HEIGHT {
+                let mut back_left = noise[index(x_noise + 0, y_noise + 0, z_noise + 0)];
+                let mut front_left = noise[index(x_noise + 0, y_noise + 0, z_noise + 1)];
+                let mut back_right = noise[index(x_noise + 1, y_noise + 0, z_noise + 0)];
+                let mut front_right = noise[index(x_noise + 1, y_noise + 0, z_noise + 1)];
+                let back_left_increment = (noise[index(x_noise + 0, y_noise + 1, z_noise + 0)]
+                    - back_left)
+                    * HEIGHT_INCREMENT;
+                let front_left_increment = (noise[index(x_noise + 0, y_noise + 1, z_noise + 1)]
+                    - front_left)
+                    * HEIGHT_INCREMENT;
+                let back_right_increment = (noise[index(x_noise + 1, y_noise + 1, z_noise + 0)]
+                    - back_right)
+                    * HEIGHT_INCREMENT;
+                let front_right_increment = (noise[index(x_noise + 1, y_noise + 1, z_noise + 1)]
+                    - front_right)
+                    * HEIGHT_INCREMENT;
+
+                for y_index in 0..CAVES_HEIGHT_FACTOR {
+                    let y = y_noise * CAVES_HEIGHT_FACTOR + y_index;
+
+                    let back_increment = (back_right - back_left) * WIDTH_INCREMENT;
+                    let front_increment = (front_right - front_left) * WIDTH_INCREMENT;
+
+                    let mut back = back_left;
+                    let mut front = front_left;
+
+                    for x_index in 0..CAVES_WIDTH_FACTOR {
+                        let x = x_noise * WIDTH + x_index;
+
+                        let bottom_increment = (front - back) * 0.25;
+                        let mut density = back;
+
+                        for z_index in 0..CAVES_WIDTH_FACTOR {
+                            let z = z_noise * WIDTH + z_index;
+                            result[x * Chunk::SIZE * CHUNK_HEIGHT + z * CHUNK_HEIGHT + y] = density;
+                            density += bottom_increment;
+                        }
+
+                        back += back_increment;
+                        front += front_increment;
+                    }
+
+                    back_left += back_left_increment;
+                    front_left += front_left_increment;
+                    back_right += back_right_increment;
+                    front_right += front_right_increment;
+                }
+            }
+        }
+    }
+
+    return result;
+}
+
+// We interpolate from a 4x3x4 to 16x24x16. 24 because we need some of the blocks above the
+// chunk to know if we need to place surface blocks. Note how it affects the noise
+// frequency. It is effectively 4x(8x vertically) since we sample closer together.
+//
+// NOTE: This is useful beyond the performance increase.
+// 1. 3d noise tends to create small floaters that don't look good.
+// 2. Even with complex noise compositions it's very easy to perceive regularity in it.
+//    This breaks it up, while providing better continuity.
+pub fn terrain(noise: &[f32]) -> Vec<f32> {
+    const WIDTH: usize = Chunk::SIZE / TERRAIN_WIDTH_FACTOR;
+    const HEIGHT: usize = CHUNK_HEIGHT / TERRAIN_HEIGHT_FACTOR;
+    const DEPTH: usize = WIDTH;
+    const WIDTH_INCREMENT: f32 = 1.0 / TERRAIN_WIDTH_FACTOR as f32;
+    const HEIGHT_INCREMENT: f32 = 1.0 / TERRAIN_HEIGHT_FACTOR as f32;
+
+    fn index(x: usize, y: usize, z: usize) -> usize {
+        return x * (DEPTH + 1) * (HEIGHT + 1) + z * (HEIGHT + 1) + y;
+    }
+
+    let mut result = vec![0.0; Chunk::SIZE * CHUNK_HEIGHT * Chunk::SIZE];
+
+    for x_noise in 0..WIDTH {
+        for z_noise in 0..DEPTH {
+            for y_noise in 0..HEIGHT {
+                let mut back_left = noise[index(x_noise + 0, y_noise + 0, z_noise + 0)];
+                let mut front_left = noise[index(x_noise + 0, y_noise + 0, z_noise + 1)];
+                let mut back_right = noise[index(x_noise + 1, y_noise + 0, z_noise + 0)];
+                let mut front_right = noise[index(x_noise + 1, y_noise + 0, z_noise + 1)];
+                let back_left_increment = (noise[index(x_noise + 0, y_noise + 1, z_noise + 0)]
+                    - back_left)
+                    * HEIGHT_INCREMENT;
+                let front_left_increment = (noise[index(x_noise + 0, y_noise + 1, z_noise + 1)]
+                    - front_left)
+                    * HEIGHT_INCREMENT;
+                let back_right_increment = (noise[index(x_noise + 1, y_noise + 1, z_noise + 0)]
+                    - back_right)
+                    * HEIGHT_INCREMENT;
+                let front_right_increment = (noise[index(x_noise + 1, y_noise + 1, z_noise + 1)]
+                    - front_right)
+                    * HEIGHT_INCREMENT;
+
+                for y_index in 0..TERRAIN_HEIGHT_FACTOR {
+                    let y = y_noise * TERRAIN_HEIGHT_FACTOR + y_index;
+
+                    let back_increment = (back_right - back_left) * WIDTH_INCREMENT;
+                    let front_increment = (front_right - front_left) * WIDTH_INCREMENT;
+
+                    let mut back = back_left;
+                    let mut front = front_left;
+
+                    for x_index in 0..TERRAIN_WIDTH_FACTOR {
+                        let x = x_noise * WIDTH + x_index;
+
+                        let bottom_increment = (front - back) * 0.25;
+                        let mut density = back;
+
+                        for z_index in 0..TERRAIN_WIDTH_FACTOR {
+                            let z = z_noise * WIDTH + z_index;
+                            result[x * Chunk::SIZE * CHUNK_HEIGHT + z * CHUNK_HEIGHT + y] = density;
+                            density += bottom_increment;
+                        }
+
+                        back += back_increment;
+                        front += front_increment;
+                    }
+
+                    back_left += back_left_increment;
+                    front_left += front_left_increment;
+                    back_right += back_right_increment;
+                    front_right += front_right_increment;
+                }
+            }
+        }
+    }
+
+    return result;
+}
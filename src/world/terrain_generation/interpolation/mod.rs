@@ -0,0 +1,103 @@
+//! Upsampling of the coarse noise that the terrain is generated from.
+//!
+//! The noise is only sampled at a grid of points, and the blocks in between are filled in by
+//! linear interpolation. There are two implementations that give the exact same output, the
+//! [vectorized] one that is used by default and the [legacy] one it replaced. Build with the
+//! `legacy-interpolation` feature to use the old one instead, e.g. to rule out the interpolation
+//! when looking for the cause of a difference in the terrain.
+//!
+//! This is public so it can be benchmarked, see `benches/terrain_generation.rs`.
+
+use fmc::world::chunk::Chunk;
+
+use super::{CAVES_HEIGHT, CAVES_WIDTH, CHUNK_HEIGHT, TERRAIN_HEIGHT, TERRAIN_WIDTH};
+
+pub mod legacy;
+pub mod vectorized;
+
+#[cfg(feature = "legacy-interpolation")]
+pub use legacy::{caves, continent_height, terrain};
+#[cfg(not(feature = "legacy-interpolation"))]
+pub use vectorized::{caves, continent_height, terrain};
+
+/// Length of the noise that goes into [terrain]
+pub const TERRAIN_NOISE_LEN: usize = TERRAIN_WIDTH * TERRAIN_HEIGHT * TERRAIN_WIDTH;
+/// Length of the noise that goes into [caves]
+pub const CAVES_NOISE_LEN: usize = CAVES_WIDTH * CAVES_HEIGHT * CAVES_WIDTH;
+/// Length of the noise that goes into [continent_height]
+pub const CONTINENT_HEIGHT_NOISE_LEN: usize = TERRAIN_WIDTH * TERRAIN_WIDTH;
+
+/// Length of the output of [terrain] and [caves], x major and y minor.
+pub const VOLUME_LEN: usize = Chunk::SIZE * CHUNK_HEIGHT * Chunk::SIZE;
+/// Length of the output of [continent_height], x major.
+pub const AREA_LEN: usize = Chunk::SIZE * Chunk::SIZE;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Noise in the range -100..100, the generator's noise stays well within it.
+    fn noise(len: usize, seed: u32) -> Vec<f32> {
+        let mut state = seed;
+        (0..len)
+            .map(|_| {
+                state = state.wrapping_mul(1664525).wrapping_add(1013904223);
+                (state >> 8) as f32 / (1 << 24) as f32 * 200.0 - 100.0
+            })
+            .collect()
+    }
+
+    fn assert_identical(name: &str, len: usize, legacy: Vec<f32>, vectorized: Vec<f32>) {
+        assert_eq!(
+            legacy.len(),
+            len,
+            "{}: legacy output has the wrong length",
+            name
+        );
+        assert_eq!(
+            vectorized.len(),
+            len,
+            "{}: vectorized output has the wrong length",
+            name
+        );
+        for (index, (legacy, vectorized)) in legacy.iter().zip(vectorized.iter()).enumerate() {
+            assert!(
+                legacy.to_bits() == vectorized.to_bits(),
+                "{}: outputs differ at index {}, legacy: {}, vectorized: {}",
+                name,
+                index,
+                legacy,
+                vectorized
+            );
+        }
+    }
+
+    #[test]
+    fn identical_output() {
+        for seed in 0..256 {
+            let terrain = noise(TERRAIN_NOISE_LEN, seed);
+            assert_identical(
+                "terrain",
+                VOLUME_LEN,
+                legacy::terrain(&terrain),
+                vectorized::terrain(&terrain),
+            );
+
+            let caves = noise(CAVES_NOISE_LEN, seed);
+            assert_identical(
+                "caves",
+                VOLUME_LEN,
+                legacy::caves(&caves),
+                vectorized::caves(&caves),
+            );
+
+            let continent_height = noise(CONTINENT_HEIGHT_NOISE_LEN, seed);
+            assert_identical(
+                "continent height",
+                AREA_LEN,
+                legacy::continent_height(&continent_height),
+                vectorized::continent_height(&continent_height),
+            );
+        }
+    }
+}
@@ -0,0 +1,167 @@
+//! Interpolation that works on whole columns of blocks at a time.
+//!
+//! The legacy implementation goes through the blocks one by one, writing them with a stride of
+//! a full column. Here every vertical column of the chunk is kept in a fixed size array, so each
+//! step of the interpolation is a simple loop over the column that the compiler turns into SIMD
+//! instructions, and the columns are copied to the output in one go.
+//!
+//! Each block still goes through the same additions and multiplications in the same order, so the
+//! output is bit for bit identical to the legacy implementation.
+
+use fmc::world::chunk::Chunk;
+
+use super::super::{
+    CAVES_HEIGHT_FACTOR, CAVES_WIDTH_FACTOR, CHUNK_HEIGHT, TERRAIN_HEIGHT_FACTOR,
+    TERRAIN_WIDTH_FACTOR,
+};
+
+type Column = [f32; CHUNK_HEIGHT];
+
+#[inline(always)]
+fn add_assign(column: &mut Column, other: &Column) {
+    for (value, other) in column.iter_mut().zip(other.iter()) {
+        *value += *other;
+    }
+}
+
+// (to - from) * factor
+#[inline(always)]
+fn increment(from: &Column, to: &Column, factor: f32) -> Column {
+    let mut result = [0.0; CHUNK_HEIGHT];
+    for ((value, from), to) in result.iter_mut().zip(from.iter()).zip(to.iter()) {
+        *value = (*to - *from) * factor;
+    }
+    result
+}
+
+/// Interpolates a 2d grid with a point every 'WIDTH_FACTOR' blocks to one value per column of
+/// the chunk.
+pub fn interpolate_2d<const WIDTH_FACTOR: usize>(noise: &[f32]) -> Vec<f32> {
+    let width = Chunk::SIZE / WIDTH_FACTOR;
+    let increment = 1.0 / WIDTH_FACTOR as f32;
+
+    let index = |x: usize, z: usize| x * (width + 1) + z;
+
+    let mut result = vec![0.0; Chunk::SIZE * Chunk::SIZE];
+
+    // The z segments of a row are independent of each other, so they are done side by side.
+    for x_noise in 0..width {
+        let mut back = [0.0; Chunk::SIZE];
+        let mut front = [0.0; Chunk::SIZE];
+        let mut back_increment = [0.0; Chunk::SIZE];
+        let mut front_increment = [0.0; Chunk::SIZE];
+        for z_noise in 0..width {
+            back[z_noise] = noise[index(x_noise + 0, z_noise + 0)];
+            front[z_noise] = noise[index(x_noise + 0, z_noise + 1)];
+            back_increment[z_noise] =
+                (noise[index(x_noise + 1, z_noise + 0)] - back[z_noise]) * increment;
+            front_increment[z_noise] =
+                (noise[index(x_noise + 1, z_noise + 1)] - front[z_noise]) * increment;
+        }
+
+        for x_index in 0..WIDTH_FACTOR {
+            let x = x_noise * WIDTH_FACTOR + x_index;
+            let row = &mut result[x * Chunk::SIZE..(x + 1) * Chunk::SIZE];
+
+            for z_noise in 0..width {
+                let middle_increment = (front[z_noise] - back[z_noise]) * increment;
+                let mut density = back[z_noise];
+
+                for value in row[z_noise * WIDTH_FACTOR..(z_noise + 1) * WIDTH_FACTOR].iter_mut() {
+                    *value = density;
+                    density += middle_increment;
+                }
+
+                back[z_noise] += back_increment[z_noise];
+                front[z_noise] += front_increment[z_noise];
+            }
+        }
+    }
+
+    result
+}
+
+/// Interpolates a 3d grid with a point every 'WIDTH_FACTOR' blocks horizontally and every
+/// 'HEIGHT_FACTOR' blocks vertically to one value per block of the chunk, plus the extra blocks
+/// above it. The grid and the output are both x major and y minor.
+pub fn interpolate_3d<const WIDTH_FACTOR: usize, const HEIGHT_FACTOR: usize>(
+    noise: &[f32],
+) -> Vec<f32> {
+    let width = Chunk::SIZE / WIDTH_FACTOR;
+    let height = CHUNK_HEIGHT / HEIGHT_FACTOR;
+    let width_increment = 1.0 / WIDTH_FACTOR as f32;
+    let height_increment = 1.0 / HEIGHT_FACTOR as f32;
+
+    let index =
+        |x: usize, y: usize, z: usize| x * (width + 1) * (height + 1) + z * (height + 1) + y;
+
+    // The vertical interpolation between the grid points, done once for each column of the grid
+    // since the columns are shared between neighbouring segments. The values are accumulated from
+    // the bottom of each segment, the same as the legacy implementation.
+    let mut columns: Vec<Column> = Vec::with_capacity((width + 1) * (width + 1));
+    for x in 0..width + 1 {
+        for z in 0..width + 1 {
+            let mut column = [0.0; CHUNK_HEIGHT];
+            for y_noise in 0..height {
+                let mut density = noise[index(x, y_noise, z)];
+                let increment = (noise[index(x, y_noise + 1, z)] - density) * height_increment;
+                for value in
+                    column[y_noise * HEIGHT_FACTOR..(y_noise + 1) * HEIGHT_FACTOR].iter_mut()
+                {
+                    *value = density;
+                    density += increment;
+                }
+            }
+            columns.push(column);
+        }
+    }
+    let column = |x: usize, z: usize| &columns[x * (width + 1) + z];
+
+    let mut result = vec![0.0; Chunk::SIZE * CHUNK_HEIGHT * Chunk::SIZE];
+
+    for x_noise in 0..width {
+        for z_noise in 0..width {
+            let back_left = column(x_noise + 0, z_noise + 0);
+            let front_left = column(x_noise + 0, z_noise + 1);
+            let back_right = column(x_noise + 1, z_noise + 0);
+            let front_right = column(x_noise + 1, z_noise + 1);
+
+            let back_increment = increment(back_left, back_right, width_increment);
+            let front_increment = increment(front_left, front_right, width_increment);
+
+            let mut back = *back_left;
+            let mut front = *front_left;
+
+            for x_index in 0..WIDTH_FACTOR {
+                let x = x_noise * WIDTH_FACTOR + x_index;
+
+                let bottom_increment = increment(&back, &front, width_increment);
+                let mut density = back;
+
+                for z_index in 0..WIDTH_FACTOR {
+                    let z = z_noise * WIDTH_FACTOR + z_index;
+                    let start = x * Chunk::SIZE * CHUNK_HEIGHT + z * CHUNK_HEIGHT;
+                    result[start..start + CHUNK_HEIGHT].copy_from_slice(&density);
+                    add_assign(&mut density, &bottom_increment);
+                }
+
+                add_assign(&mut back, &back_increment);
+                add_assign(&mut front, &front_increment);
+            }
+        }
+    }
+
+    result
+}
+
+pub fn continent_height(noise: &[f32]) -> Vec<f32> {
+    interpolate_2d::<TERRAIN_WIDTH_FACTOR>(noise)
+}
+
+pub fn caves(noise: &[f32]) -> Vec<f32> {
+    interpolate_3d::<CAVES_WIDTH_FACTOR, CAVES_HEIGHT_FACTOR>(noise)
+}
+
+pub fn terrain(noise: &[f32]) -> Vec<f32> {
+    interpolate_3d::<TERRAIN_WIDTH_FACTOR, TERRAIN_HEIGHT_FACTOR>(noise)
+}
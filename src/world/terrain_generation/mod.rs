@@ -10,21 +10,51 @@ use fmc::{
     },
 };
 
-mod biomes;
+use crate::metrics;
+
+use super::dimensions::Dimension;
+
+pub mod biomes;
 mod blueprints;
+pub mod interpolation;
+mod nether;
+mod ravines;
+pub mod snapshots;
+mod structures;
 
 pub struct Earth {
-    biomes: biomes::Biomes,
+    biomes: biomes::SharedBiomes,
     continents: Noise,
     terrain_height: Noise,
     terrain_shape: Noise,
     caves: Noise,
+    rivers: Noise,
+    ravines: ravines::Ravines,
+    structures: structures::Structures,
+    climate: Climate,
+    nether: nether::Nether,
     seed: u64,
 }
 
 impl TerrainGenerator for Earth {
     fn generate_chunk(&self, chunk_position: ChunkPosition) -> Chunk {
+        let start = std::time::Instant::now();
+        let chunk = self.generate(chunk_position);
+        metrics::record_chunk_generation(start.elapsed());
+        chunk
+    }
+}
+
+impl Earth {
+    fn generate(&self, chunk_position: ChunkPosition) -> Chunk {
+        if Dimension::from_height(chunk_position.y) == Dimension::Nether {
+            return self.nether.generate_chunk(chunk_position);
+        }
+
         let mut chunk = Chunk::default();
+        // Held for the whole chunk so it is generated with the same biomes even if they are
+        // reloaded in the middle of it.
+        let biomes = self.biomes.get();
 
         let air = Blocks::get().get_id("air");
         const MAX_HEIGHT: i32 = 120;
@@ -32,7 +62,10 @@ impl TerrainGenerator for Earth {
             // Don't waste time generating if it is guaranteed to be air.
             chunk.make_uniform(air);
         } else {
-            self.generate_terrain(chunk_position, &mut chunk);
+            self.generate_terrain(&biomes, chunk_position, &mut chunk);
+            // Structures are applied even if the chunk is otherwise empty so no part of them is
+            // lost.
+            self.structures.apply(chunk_position, &mut chunk);
 
             // TODO: Might make sense to test against water too.
             //
@@ -52,7 +85,7 @@ impl TerrainGenerator for Earth {
                 return chunk;
             }
 
-            self.generate_features(chunk_position, &mut chunk);
+            self.generate_features(&biomes, chunk_position, &mut chunk);
         }
 
         return chunk;
@@ -75,9 +108,20 @@ const TERRAIN_WIDTH: usize = Chunk::SIZE / TERRAIN_WIDTH_FACTOR + 1;
 const TERRAIN_HEIGHT: usize = CHUNK_HEIGHT / TERRAIN_HEIGHT_FACTOR + 1;
 const CAVES_WIDTH: usize = Chunk::SIZE / CAVES_WIDTH_FACTOR + 1;
 const CAVES_HEIGHT: usize = CHUNK_HEIGHT / CAVES_HEIGHT_FACTOR + 1;
+// Rivers follow the lines where the river noise crosses zero. Within RIVER_WIDTH of the line the
+// river bed is dug below sea level, out to VALLEY_WIDTH the terrain is cut down into a valley.
+const RIVER_WIDTH: f32 = 0.015;
+const VALLEY_WIDTH: f32 = 0.05;
+// How far below sea level the middle of the river bed is
+const RIVER_DEPTH: f32 = 5.0;
+// How far above sea level the terrain is cut down to at the edge of the valley
+const VALLEY_HEIGHT: f32 = 16.0;
+// How much solid ground is kept beneath a river when carving caves, so the water doesn't pour
+// into them. Deeper than the river so caves to the side of it are kept away too.
+const RIVER_BED_THICKNESS: f32 = 6.0;
 
 impl Earth {
-    pub fn new(seed: u64, blocks: &Blocks) -> Self {
+    pub fn new(seed: u64, blocks: &Blocks, biomes: biomes::Biomes) -> Self {
         let mut rng = Rng::new(seed);
 
         let freq = 1.0 / 2f32.powi(9) * 3.0;
@@ -178,17 +222,33 @@ impl Earth {
         .square();
         let caves = cave_main_3.add(cave_main_4).min(caves);
 
+        // Sampled per block like the climate, the frequency decides how far apart rivers are.
+        let freq = 0.0015;
+        let rivers = Noise::perlin(freq).seed(rng.next_u32()).fbm(3, 0.5, 2.0);
+
+        let climate = Climate::new(rng.next_u32(), rng.next_u32());
+
         Self {
-            biomes: biomes::Biomes::load(blocks),
+            biomes: biomes::SharedBiomes::new(biomes),
             continents,
             terrain_height,
             terrain_shape,
             caves,
+            rivers,
+            ravines: ravines::Ravines::new(seed, blocks),
+            structures: structures::Structures::new(seed, blocks),
+            climate,
+            nether: nether::Nether::new(seed, blocks),
             seed,
         }
     }
 
-    fn generate_terrain(&self, chunk_position: ChunkPosition, chunk: &mut Chunk) {
+    fn generate_terrain(
+        &self,
+        biomes: &biomes::Biomes,
+        chunk_position: ChunkPosition,
+        chunk: &mut Chunk,
+    ) {
         let chunk_x = (chunk_position.x / (TERRAIN_WIDTH_FACTOR as i32)) as f32;
         let chunk_y = (chunk_position.y / (TERRAIN_HEIGHT_FACTOR as i32)) as f32;
         let chunk_z = (chunk_position.z / (TERRAIN_WIDTH_FACTOR as i32)) as f32;
@@ -234,20 +294,38 @@ impl Earth {
             }
         }
 
-        let mut terrain_shape = interpolate(&terrain);
-        let continent_height = interpolate_continent_height(&continent_height);
+        let mut terrain_shape = interpolation::terrain(&terrain);
+        let continent_height = interpolation::continent_height(&continent_height);
 
-        self.carve_caves(chunk_position, &continent_height, &mut terrain_shape);
+        let river_floors = self.river_floors(chunk_position, &continent_height);
+
+        self.carve_caves(
+            chunk_position,
+            &continent_height,
+            &river_floors,
+            &mut terrain_shape,
+        );
+        let ravine_walls = self.ravines.carve(
+            chunk_position,
+            &continent_height,
+            &river_floors,
+            &mut terrain_shape,
+        );
+        self.carve_rivers(chunk_position, &river_floors, &mut terrain_shape);
 
         chunk.blocks = vec![0; Chunk::SIZE.pow(3)];
 
-        let biome = self.biomes.get_biome();
+        let column_biomes = self.column_biomes(biomes, chunk_position);
 
         for x in 0..Chunk::SIZE {
             for z in 0..Chunk::SIZE {
+                let biome = column_biomes[x * Chunk::SIZE + z];
                 let mut layer = 0;
 
                 let continent_height = continent_height[x * Chunk::SIZE + z];
+                // The river channel is filled with water up to sea level, like the sea.
+                let in_river = river_floors[x * Chunk::SIZE + z].is_some_and(|floor| floor < 0.0);
+                let is_wet = continent_height < CONTINTENT_MAX || in_river;
 
                 let mut liquid = false;
 
@@ -258,7 +336,7 @@ impl Earth {
                     let density = terrain_shape[block_index];
 
                     if density <= 0.0 {
-                        if block_height == 0 && continent_height < CONTINTENT_MAX {
+                        if block_height == 0 && is_wet {
                             liquid = true;
                         }
                         layer = 0;
@@ -275,7 +353,7 @@ impl Earth {
                     let density = terrain_shape[block_index];
 
                     let block = if density <= 0.0 {
-                        if block_height == 0 && continent_height < CONTINTENT_MAX {
+                        if block_height == 0 && is_wet {
                             layer = 1;
                             liquid = true;
                             biome.surface_liquid
@@ -290,7 +368,7 @@ impl Earth {
                         biome.bottom_layer_block
                     } else if block_height < 2
                         && block_height > CONTINTENT_MIN as i32 - 3
-                        && continent_height <= 2.0
+                        && (continent_height <= 2.0 || in_river)
                     {
                         layer += 1;
                         biome.sand
@@ -310,15 +388,150 @@ impl Earth {
                 }
             }
         }
+
+        self.ravines.place_ores(
+            chunk_position,
+            &ravine_walls,
+            self.chunk_biome(biomes, chunk_position).bottom_layer_block,
+            chunk,
+        );
+    }
+
+    // Biome of each column in the chunk, indexed by x * Chunk::SIZE + z.
+    fn column_biomes<'a>(
+        &self,
+        biomes: &'a biomes::Biomes,
+        chunk_position: ChunkPosition,
+    ) -> Vec<&'a biomes::Biome> {
+        // How far the climate of a column can be randomly shifted. Where two biomes meet their
+        // columns are mixed together within this distance of the border, instead of meeting in
+        // a hard line.
+        const BLEND: f32 = 0.03;
+
+        let (temperature, _, _) = self.climate.temperature.generate_2d(
+            chunk_position.x as f32,
+            chunk_position.z as f32,
+            Chunk::SIZE,
+            Chunk::SIZE,
+        );
+        let (humidity, _, _) = self.climate.humidity.generate_2d(
+            chunk_position.x as f32,
+            chunk_position.z as f32,
+            Chunk::SIZE,
+            Chunk::SIZE,
+        );
+
+        // Same for every chunk in the column so the blending lines up vertically.
+        let seed = ((chunk_position.x as u64) << 32 | chunk_position.z as u32 as u64)
+            .overflowing_mul(self.seed)
+            .0;
+        let mut rng = Rng::new(seed);
+
+        temperature
+            .into_iter()
+            .zip(humidity)
+            .map(|(temperature, humidity)| {
+                let temperature = temperature + (rng.next_f32() * 2.0 - 1.0) * BLEND;
+                let humidity = humidity + (rng.next_f32() * 2.0 - 1.0) * BLEND;
+                biomes.get_biome(temperature, humidity)
+            })
+            .collect()
+    }
+
+    pub fn climate(&self) -> &Climate {
+        &self.climate
+    }
+
+    pub fn biomes(&self) -> &biomes::SharedBiomes {
+        &self.biomes
+    }
+
+    // The biome at the center of the chunk, used for things that are placed per chunk.
+    fn chunk_biome<'a>(
+        &self,
+        biomes: &'a biomes::Biomes,
+        chunk_position: ChunkPosition,
+    ) -> &'a biomes::Biome {
+        let center = Chunk::SIZE as f32 / 2.0;
+        let (temperature, _, _) = self.climate.temperature.generate_2d(
+            chunk_position.x as f32 + center,
+            chunk_position.z as f32 + center,
+            1,
+            1,
+        );
+        let (humidity, _, _) = self.climate.humidity.generate_2d(
+            chunk_position.x as f32 + center,
+            chunk_position.z as f32 + center,
+            1,
+            1,
+        );
+        biomes.get_biome(temperature[0], humidity[0])
+    }
+
+    // Height each column of the chunk is cut down to by a river, None where there is no river.
+    // Indexed by x * Chunk::SIZE + z.
+    fn river_floors(
+        &self,
+        chunk_position: ChunkPosition,
+        continent_height: &Vec<f32>,
+    ) -> Vec<Option<f32>> {
+        let (rivers, _, _) = self.rivers.generate_2d(
+            chunk_position.x as f32,
+            chunk_position.z as f32,
+            Chunk::SIZE,
+            Chunk::SIZE,
+        );
+
+        rivers
+            .into_iter()
+            .zip(continent_height)
+            .map(|(river, continent_height)| {
+                let distance = river.abs();
+                // The sea floor is already below sea level, rivers end where they reach it.
+                if distance > VALLEY_WIDTH || *continent_height < 0.0 {
+                    return None;
+                }
+
+                let depth = (1.0 - distance / RIVER_WIDTH).max(0.0) * RIVER_DEPTH;
+                let valley = ((distance - RIVER_WIDTH).max(0.0) / (VALLEY_WIDTH - RIVER_WIDTH))
+                    .powi(2)
+                    * VALLEY_HEIGHT;
+                Some(valley - depth)
+            })
+            .collect()
+    }
+
+    // Remove everything above the river floor. Where the floor is below sea level, the channel is
+    // filled with water when the blocks are placed.
+    fn carve_rivers(
+        &self,
+        chunk_position: ChunkPosition,
+        river_floors: &Vec<Option<f32>>,
+        terrain: &mut Vec<f32>,
+    ) {
+        for x in 0..Chunk::SIZE {
+            for z in 0..Chunk::SIZE {
+                let Some(floor) = river_floors[x * Chunk::SIZE + z] else {
+                    continue;
+                };
+
+                for y in 0..CHUNK_HEIGHT {
+                    let height = (chunk_position.y + y as i32) as f32;
+                    if height > floor {
+                        terrain[x * Chunk::SIZE * CHUNK_HEIGHT + z * CHUNK_HEIGHT + y] = -1.0;
+                    }
+                }
+            }
+        }
     }
 
     fn carve_caves(
         &self,
         chunk_position: ChunkPosition,
         continent_height: &Vec<f32>,
+        river_floors: &Vec<Option<f32>>,
         terrain: &mut Vec<f32>,
     ) {
-        let biome = self.biomes.get_biome();
         let chunk_x = (chunk_position.x / (CAVES_WIDTH_FACTOR as i32)) as f32;
         let chunk_y = (chunk_position.y / (CAVES_HEIGHT_FACTOR as i32)) as f32;
         let chunk_z = (chunk_position.z / (CAVES_WIDTH_FACTOR as i32)) as f32;
@@ -331,13 +544,16 @@ impl Earth {
             CAVES_HEIGHT,
             CAVES_WIDTH,
         );
-        let caves = interpolate_caves(&caves);
+        let caves = interpolation::caves(&caves);
 
         // let air = Blocks::get().get_id("air");
         for x in 0..Chunk::SIZE {
             for z in 0..Chunk::SIZE {
                 let continent_height_index = x * Chunk::SIZE + z;
                 let continent_height = continent_height[continent_height_index];
+                // Caves that open up beneath a river would drain it, so they're kept below its bed.
+                let max_cave_height = river_floors[continent_height_index]
+                    .map_or(f32::MAX, |floor| floor - RIVER_BED_THICKNESS);
 
                 for y in 0..CHUNK_HEIGHT {
                     let index = x * Chunk::SIZE * CHUNK_HEIGHT + z * CHUNK_HEIGHT + y;
@@ -350,6 +566,7 @@ impl Earth {
                     cave_density += density_offset;
 
                     if cave_density < threshold
+                        && (height as f32) < max_cave_height
                         && (continent_height == CONTINTENT_MAX
                             || height < CONTINTENT_MIN as i32 - 10)
                     {
@@ -384,9 +601,14 @@ impl Earth {
         //     });
     }
 
-    fn generate_features(&self, chunk_position: ChunkPosition, chunk: &mut Chunk) {
+    fn generate_features(
+        &self,
+        biomes: &biomes::Biomes,
+        chunk_position: ChunkPosition,
+        chunk: &mut Chunk,
+    ) {
         let blocks = Blocks::get();
-        let surface_blocks = [blocks.get_id("grass")];
+        let surface_blocks = biomes.top_layer_blocks();
         let surface = Surface::new(chunk, &surface_blocks, blocks.get_id("air"));
 
         // x position is left 32 bits and z position the right 32 bits. z must be converted to u32
@@ -397,7 +619,9 @@ impl Earth {
             .0;
         let mut rng = Rng::new(seed);
 
-        let biome = self.biomes.get_biome();
+        // TODO: Features are placed by the biome of the whole chunk, so trees from one biome can
+        // be placed a few blocks into another at the borders.
+        let biome = self.chunk_biome(biomes, chunk_position);
 
         for blueprint in biome.blueprints.iter() {
             blueprint.construct(chunk_position.into(), chunk, &surface, &mut rng);
@@ -405,196 +629,37 @@ impl Earth {
     }
 }
 
-// XXX: These interpolate functions are specific instead of generic 'interpolate_3d<HEIGHT, WIDTH,
-// DEPTH>' etc because the compiler won't autovec them.
-fn interpolate_continent_height(noise: &Vec<f32>) -> Vec<f32> {
-    const WIDTH: usize = Chunk::SIZE / TERRAIN_WIDTH_FACTOR;
-    const HEIGHT: usize = WIDTH;
-
-    fn index(x: usize, z: usize) -> usize {
-        return x * (HEIGHT + 1) + z;
-    }
-
-    let mut result = vec![0.0; Chunk::SIZE * Chunk::SIZE];
-
-    for x_noise in 0..WIDTH {
-        for z_noise in 0..HEIGHT {
-            let back_left = noise[index(x_noise + 0, z_noise + 0)];
-            let front_left = noise[index(x_noise + 0, z_noise + 1)];
-            let back_right = noise[index(x_noise + 1, z_noise + 0)];
-            let front_right = noise[index(x_noise + 1, z_noise + 1)];
-
-            let back_increment = (back_right - back_left) * 0.25;
-            let front_increment = (front_right - front_left) * 0.25;
-
-            let mut back = back_left;
-            let mut front = front_left;
-
-            for x_index in 0..TERRAIN_WIDTH_FACTOR {
-                let x = x_noise * TERRAIN_WIDTH_FACTOR + x_index;
-
-                let middle_increment = (front - back) * 0.25;
-                let mut density = back;
-
-                for z_index in 0..TERRAIN_WIDTH_FACTOR {
-                    let z = z_noise * WIDTH + z_index;
-                    result[x * Chunk::SIZE + z] = density;
-                    density += middle_increment;
-                }
-
-                back += back_increment;
-                front += front_increment;
-            }
-        }
-    }
-
-    return result;
+/// The climate decides which biome each column of the world gets. It can be looked up while the
+/// server is running to know what the climate is like at a position.
+#[derive(Resource)]
+pub struct Climate {
+    temperature_seed: u32,
+    humidity_seed: u32,
+    temperature: Noise,
+    humidity: Noise,
 }
 
-fn interpolate_caves(noise: &Vec<f32>) -> Vec<f32> {
-    const WIDTH: usize = Chunk::SIZE / CAVES_WIDTH_FACTOR;
-    const HEIGHT: usize = CHUNK_HEIGHT / CAVES_HEIGHT_FACTOR;
-    const DEPTH: usize = WIDTH;
-    const WIDTH_INCREMENT: f32 = 1.0 / CAVES_WIDTH_FACTOR as f32;
-    const HEIGHT_INCREMENT: f32 = 1.0 / CAVES_HEIGHT_FACTOR as f32;
-
-    fn index(x: usize, y: usize, z: usize) -> usize {
-        return x * (DEPTH + 1) * (HEIGHT + 1) + z * (HEIGHT + 1) + y;
-    }
-
-    let mut result = vec![0.0; Chunk::SIZE * CHUNK_HEIGHT * Chunk::SIZE];
-
-    for x_noise in 0..WIDTH {
-        for z_noise in 0..DEPTH {
-            for y_noise in 0..HEIGHT {
-                let mut back_left = noise[index(x_noise + 0, y_noise + 0, z_noise + 0)];
-                let mut front_left = noise[index(x_noise + 0, y_noise + 0, z_noise + 1)];
-                let mut back_right = noise[index(x_noise + 1, y_noise + 0, z_noise + 0)];
-                let mut front_right = noise[index(x_noise + 1, y_noise + 0, z_noise + 1)];
-                let back_left_increment = (noise[index(x_noise + 0, y_noise + 1, z_noise + 0)]
-                    - back_left)
-                    * HEIGHT_INCREMENT;
-                let front_left_increment = (noise[index(x_noise + 0, y_noise + 1, z_noise + 1)]
-                    - front_left)
-                    * HEIGHT_INCREMENT;
-                let back_right_increment = (noise[index(x_noise + 1, y_noise + 1, z_noise + 0)]
-                    - back_right)
-                    * HEIGHT_INCREMENT;
-                let front_right_increment = (noise[index(x_noise + 1, y_noise + 1, z_noise + 1)]
-                    - front_right)
-                    * HEIGHT_INCREMENT;
-
-                for y_index in 0..CAVES_HEIGHT_FACTOR {
-                    let y = y_noise * CAVES_HEIGHT_FACTOR + y_index;
-
-                    let back_increment = (back_right - back_left) * WIDTH_INCREMENT;
-                    let front_increment = (front_right - front_left) * WIDTH_INCREMENT;
-
-                    let mut back = back_left;
-                    let mut front = front_left;
-
-                    for x_index in 0..CAVES_WIDTH_FACTOR {
-                        let x = x_noise * WIDTH + x_index;
-
-                        let bottom_increment = (front - back) * 0.25;
-                        let mut density = back;
-
-                        for z_index in 0..CAVES_WIDTH_FACTOR {
-                            let z = z_noise * WIDTH + z_index;
-                            result[x * Chunk::SIZE * CHUNK_HEIGHT + z * CHUNK_HEIGHT + y] = density;
-                            density += bottom_increment;
-                        }
-
-                        back += back_increment;
-                        front += front_increment;
-                    }
-
-                    back_left += back_left_increment;
-                    front_left += front_left_increment;
-                    back_right += back_right_increment;
-                    front_right += front_right_increment;
-                }
-            }
+impl Climate {
+    fn new(temperature_seed: u32, humidity_seed: u32) -> Self {
+        // Sampled per block, so the frequency directly decides how large the biomes are.
+        let freq = 0.0015;
+        Self {
+            temperature_seed,
+            humidity_seed,
+            temperature: Noise::perlin(freq).seed(temperature_seed).fbm(3, 0.5, 2.0),
+            humidity: Noise::perlin(freq).seed(humidity_seed).fbm(3, 0.5, 2.0),
         }
     }
 
-    return result;
-}
-
-// We interpolate from a 4x3x4 to 16x24x16. 24 because we need some of the blocks above the
-// chunk to know if we need to place surface blocks. Note how it affects the noise
-// frequency. It is effectively 4x(8x vertically) since we sample closer together.
-//
-// NOTE: This is useful beyond the performance increase.
-// 1. 3d noise tends to create small floaters that don't look good.
-// 2. Even with complex noise compositions it's very easy to perceive regularity in it.
-//    This breaks it up, while providing better continuity.
-fn interpolate(noise: &Vec<f32>) -> Vec<f32> {
-    const WIDTH: usize = Chunk::SIZE / TERRAIN_WIDTH_FACTOR;
-    const HEIGHT: usize = CHUNK_HEIGHT / TERRAIN_HEIGHT_FACTOR;
-    const DEPTH: usize = WIDTH;
-    const WIDTH_INCREMENT: f32 = 1.0 / TERRAIN_WIDTH_FACTOR as f32;
-    const HEIGHT_INCREMENT: f32 = 1.0 / TERRAIN_HEIGHT_FACTOR as f32;
-
-    fn index(x: usize, y: usize, z: usize) -> usize {
-        return x * (DEPTH + 1) * (HEIGHT + 1) + z * (HEIGHT + 1) + y;
+    /// Temperature of the column, around -1 for the coldest biomes and 1 for the warmest.
+    pub fn temperature(&self, x: i32, z: i32) -> f32 {
+        let (temperature, _, _) = self.temperature.generate_2d(x as f32, z as f32, 1, 1);
+        temperature[0]
     }
+}
 
-    let mut result = vec![0.0; Chunk::SIZE * CHUNK_HEIGHT * Chunk::SIZE];
-
-    for x_noise in 0..WIDTH {
-        for z_noise in 0..DEPTH {
-            for y_noise in 0..HEIGHT {
-                let mut back_left = noise[index(x_noise + 0, y_noise + 0, z_noise + 0)];
-                let mut front_left = noise[index(x_noise + 0, y_noise + 0, z_noise + 1)];
-                let mut back_right = noise[index(x_noise + 1, y_noise + 0, z_noise + 0)];
-                let mut front_right = noise[index(x_noise + 1, y_noise + 0, z_noise + 1)];
-                let back_left_increment = (noise[index(x_noise + 0, y_noise + 1, z_noise + 0)]
-                    - back_left)
-                    * HEIGHT_INCREMENT;
-                let front_left_increment = (noise[index(x_noise + 0, y_noise + 1, z_noise + 1)]
-                    - front_left)
-                    * HEIGHT_INCREMENT;
-                let back_right_increment = (noise[index(x_noise + 1, y_noise + 1, z_noise + 0)]
-                    - back_right)
-                    * HEIGHT_INCREMENT;
-                let front_right_increment = (noise[index(x_noise + 1, y_noise + 1, z_noise + 1)]
-                    - front_right)
-                    * HEIGHT_INCREMENT;
-
-                for y_index in 0..TERRAIN_HEIGHT_FACTOR {
-                    let y = y_noise * TERRAIN_HEIGHT_FACTOR + y_index;
-
-                    let back_increment = (back_right - back_left) * WIDTH_INCREMENT;
-                    let front_increment = (front_right - front_left) * WIDTH_INCREMENT;
-
-                    let mut back = back_left;
-                    let mut front = front_left;
-
-                    for x_index in 0..TERRAIN_WIDTH_FACTOR {
-                        let x = x_noise * WIDTH + x_index;
-
-                        let bottom_increment = (front - back) * 0.25;
-                        let mut density = back;
-
-                        for z_index in 0..TERRAIN_WIDTH_FACTOR {
-                            let z = z_noise * WIDTH + z_index;
-                            result[x * Chunk::SIZE * CHUNK_HEIGHT + z * CHUNK_HEIGHT + y] = density;
-                            density += bottom_increment;
-                        }
-
-                        back += back_increment;
-                        front += front_increment;
-                    }
-
-                    back_left += back_left_increment;
-                    front_left += front_left_increment;
-                    back_right += back_right_increment;
-                    front_right += front_right_increment;
-                }
-            }
-        }
+impl Clone for Climate {
+    fn clone(&self) -> Self {
+        Self::new(self.temperature_seed, self.humidity_seed)
     }
-
-    return result;
 }
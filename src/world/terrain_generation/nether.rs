@@ -0,0 +1,95 @@
+use fmc::{
+    blocks::{BlockId, Blocks},
+    noise::{Frequency, Noise},
+    random::Rng,
+    world::{
+        TerrainGenerator,
+        chunk::{Chunk, ChunkPosition},
+    },
+};
+
+use crate::world::dimensions::{NETHER_FLOOR, NETHER_HEIGHT};
+
+/// Generates the nether, a cramped cave world of netherrack.
+pub struct Nether {
+    terrain: Noise,
+    netherrack: BlockId,
+    bedrock: BlockId,
+    air: BlockId,
+}
+
+// How many blocks it takes for the floor and ceiling to go from open to solid.
+const EDGE_FALLOFF: f32 = 12.0;
+
+impl Nether {
+    pub fn new(seed: u64, blocks: &Blocks) -> Self {
+        let mut rng = Rng::new(seed ^ 0x4e45_5448_4552);
+
+        let freq = 0.025;
+        let terrain = Noise::perlin(Frequency {
+            x: freq,
+            y: freq * 1.5,
+            z: freq,
+        })
+        .seed(rng.next_u32())
+        .fbm(5, 0.5, 2.0);
+
+        Self {
+            terrain,
+            netherrack: blocks.get_id("netherrack"),
+            bedrock: blocks.get_id("bedrock"),
+            air: blocks.get_id("air"),
+        }
+    }
+}
+
+impl TerrainGenerator for Nether {
+    fn generate_chunk(&self, chunk_position: ChunkPosition) -> Chunk {
+        let mut chunk = Chunk::default();
+
+        let ceiling = NETHER_FLOOR + NETHER_HEIGHT;
+        if chunk_position.y < NETHER_FLOOR || chunk_position.y >= ceiling {
+            // Solid all the way down, and between the nether and the overworld.
+            chunk.make_uniform(self.netherrack);
+            return chunk;
+        }
+
+        let (density, _, _) = self.terrain.generate_3d(
+            chunk_position.x as f32,
+            chunk_position.y as f32,
+            chunk_position.z as f32,
+            Chunk::SIZE,
+            Chunk::SIZE,
+            Chunk::SIZE,
+        );
+
+        chunk.blocks = vec![0; Chunk::SIZE.pow(3)];
+
+        for x in 0..Chunk::SIZE {
+            for z in 0..Chunk::SIZE {
+                for y in 0..Chunk::SIZE {
+                    let height = chunk_position.y + y as i32;
+                    let from_floor = (height - NETHER_FLOOR) as f32;
+                    let from_ceiling = (ceiling - 1 - height) as f32;
+
+                    // Close off the floor and ceiling
+                    let edge = (1.0 - from_floor / EDGE_FALLOFF).max(0.0)
+                        + (1.0 - from_ceiling / EDGE_FALLOFF).max(0.0);
+
+                    let index = x * Chunk::SIZE * Chunk::SIZE + z * Chunk::SIZE + y;
+                    // The floor and the ceiling can't be dug through, the ceiling is all that
+                    // separates the nether from the bottom of the overworld.
+                    chunk[[x, y, z]] = if height == NETHER_FLOOR || height == ceiling - 1 {
+                        self.bedrock
+                    } else if density[index] + edge > 0.05 {
+                        self.netherrack
+                    } else {
+                        self.air
+                    };
+                }
+            }
+        }
+
+        return chunk;
+    }
+}
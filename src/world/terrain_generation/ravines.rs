@@ -0,0 +1,232 @@
+use fmc::{
+    blocks::{BlockId, Blocks},
+    prelude::*,
+    random::{Rng, UniformDistribution},
+    world::chunk::{Chunk, ChunkPosition},
+};
+
+use super::{CHUNK_HEIGHT, CONTINTENT_MAX};
+
+// The world is divided into square regions, each of which can contain a single ravine. The ravine
+// is generated from the region's seed, so every chunk it passes through can generate its part of
+// it independently.
+const REGION_SIZE: i32 = 128;
+// Chance for a region to have a ravine.
+const RAVINE_CHANCE: f32 = 0.3;
+// The ravine is centered in its region, it can extend this far in each direction. Must be less
+// than REGION_SIZE for the neighbouring regions to cover any ravine that can reach a chunk.
+const MAX_HALF_LENGTH: i32 = 56;
+const MAX_HALF_WIDTH: f32 = 4.5;
+// How thick the band of stone around the ravine that can be turned into ore is.
+const WALL_THICKNESS: f32 = 1.5;
+// Chance for a block in the wall to be turned into ore.
+const ORE_CHANCE: f32 = 0.06;
+
+pub struct Ravines {
+    seed: u64,
+    // Ores that can be placed in ravine walls, and their weight.
+    ores: Vec<(BlockId, u32)>,
+}
+
+struct Ravine {
+    // Points along the middle of the ravine, one block apart.
+    path: Vec<Vec2>,
+    half_width: f32,
+    // Height at the middle of the ravine, it is shallower towards the ends.
+    floor: f32,
+    depth: f32,
+}
+
+impl Ravine {
+    // Ravine generated from the region's seed, None if the region doesn't have one.
+    fn new(region_x: i32, region_z: i32, seed: u64) -> Option<Self> {
+        let region_seed = ((region_x as u64) << 32 | region_z as u32 as u64)
+            .overflowing_mul(seed)
+            .0
+            // So the ravines don't line up with the structures, which use the same kind of seed
+            ^ 0x5241_5649_4e45;
+        let mut rng = Rng::new(region_seed);
+
+        if rng.next_f32() > RAVINE_CHANCE {
+            return None;
+        }
+
+        let center = Vec2::new(
+            (region_x * REGION_SIZE) as f32 + rng.next_f32() * REGION_SIZE as f32,
+            (region_z * REGION_SIZE) as f32 + rng.next_f32() * REGION_SIZE as f32,
+        );
+        let half_length = UniformDistribution::new(32, MAX_HALF_LENGTH).sample(&mut rng);
+        let angle = rng.next_f32() * std::f32::consts::TAU;
+        // How much the direction changes per block, makes the ravine bend slightly
+        let curve = (rng.next_f32() - 0.5) * 0.02;
+
+        // Walk from the center out in both directions.
+        let mut path = Vec::with_capacity(half_length as usize * 2 + 1);
+        for direction in [1.0, -1.0] {
+            let mut position = center;
+            let mut angle = angle;
+            for _ in 0..half_length {
+                path.push(position);
+                position += Vec2::from_angle(angle) * direction;
+                angle += curve;
+            }
+        }
+
+        Some(Self {
+            path,
+            half_width: 2.0 + rng.next_f32() * (MAX_HALF_WIDTH - 2.0),
+            floor: -50.0 + rng.next_f32() * 30.0,
+            depth: 30.0 + rng.next_f32() * 30.0,
+        })
+    }
+
+    // How far along the ravine the closest point is (0 at the center, 1 at the ends), and the
+    // horizontal distance to it.
+    fn closest(&self, position: Vec2) -> (f32, f32) {
+        let half_length = self.path.len() as f32 / 2.0;
+        let mut closest = (0.0, f32::MAX);
+        for (i, point) in self.path.iter().enumerate() {
+            let distance = point.distance_squared(position);
+            if distance < closest.1 {
+                // The path goes out from the center twice, so the index wraps at the halfway point.
+                let along = (i as f32 % half_length) / half_length;
+                closest = (along, distance);
+            }
+        }
+        (closest.0, closest.1.sqrt())
+    }
+}
+
+impl Ravines {
+    pub fn new(seed: u64, blocks: &Blocks) -> Self {
+        Self {
+            seed,
+            ores: vec![
+                (blocks.get_id("coal_ore"), 6),
+                (blocks.get_id("iron_ore"), 3),
+                (blocks.get_id("gold_ore"), 1),
+            ],
+        }
+    }
+
+    /// Carve the ravines that pass through the chunk out of the terrain. Returns the blocks that
+    /// make up the walls of the ravine.
+    pub fn carve(
+        &self,
+        chunk_position: ChunkPosition,
+        continent_height: &Vec<f32>,
+        river_floors: &Vec<Option<f32>>,
+        terrain: &mut Vec<f32>,
+    ) -> Vec<[usize; 3]> {
+        let mut walls = Vec::new();
+
+        let region_x = chunk_position.x.div_euclid(REGION_SIZE);
+        let region_z = chunk_position.z.div_euclid(REGION_SIZE);
+
+        // Carving below sea level next to the sea or a river would leave walls of water hanging in
+        // the air, so ravines only go below it when the chunk is entirely on dry land.
+        let inland = continent_height
+            .iter()
+            .all(|height| *height == CONTINTENT_MAX)
+            && river_floors.iter().all(|floor| floor.is_none());
+
+        for region_x in region_x - 1..=region_x + 1 {
+            for region_z in region_z - 1..=region_z + 1 {
+                let Some(ravine) = Ravine::new(region_x, region_z, self.seed) else {
+                    continue;
+                };
+
+                let chunk_center = Vec2::new(
+                    (chunk_position.x + Chunk::SIZE as i32 / 2) as f32,
+                    (chunk_position.z + Chunk::SIZE as i32 / 2) as f32,
+                );
+                let reach =
+                    MAX_HALF_LENGTH as f32 + MAX_HALF_WIDTH + WALL_THICKNESS + Chunk::SIZE as f32;
+                if ravine.path[0].distance(chunk_center) > reach {
+                    continue;
+                }
+
+                for x in 0..Chunk::SIZE {
+                    for z in 0..Chunk::SIZE {
+                        let column_position = Vec2::new(
+                            (chunk_position.x + x as i32) as f32,
+                            (chunk_position.z + z as i32) as f32,
+                        );
+
+                        let (along, distance) = ravine.closest(column_position);
+                        // Narrow the ravine towards its ends
+                        let taper = (1.0 - along * along).sqrt();
+                        let half_width = ravine.half_width * taper;
+                        if distance > half_width + WALL_THICKNESS {
+                            continue;
+                        }
+
+                        if continent_height[x * Chunk::SIZE + z] != CONTINTENT_MAX {
+                            continue;
+                        }
+
+                        let floor = ravine.floor + ravine.depth * (1.0 - taper);
+                        let top = ravine.floor + ravine.depth * 2.0;
+
+                        for y in 0..CHUNK_HEIGHT {
+                            let height = (chunk_position.y + y as i32) as f32;
+                            if height < floor || height > top || (height < 1.0 && !inland) {
+                                continue;
+                            }
+
+                            // The walls slope inwards towards the floor.
+                            let width_at_height =
+                                half_width * ((height - floor) / 6.0).clamp(0.3, 1.0);
+
+                            let index = x * Chunk::SIZE * CHUNK_HEIGHT + z * CHUNK_HEIGHT + y;
+                            if distance <= width_at_height {
+                                terrain[index] = -1.0;
+                            } else if distance <= width_at_height + WALL_THICKNESS
+                                && y < Chunk::SIZE
+                            {
+                                walls.push([x, y, z]);
+                            }
+                        }
+                    }
+                }
+            }
+        }
+
+        walls
+    }
+
+    /// Turn some of the stone in the ravine walls into ore.
+    pub fn place_ores(
+        &self,
+        chunk_position: ChunkPosition,
+        walls: &[[usize; 3]],
+        stone: BlockId,
+        chunk: &mut Chunk,
+    ) {
+        if walls.is_empty() {
+            return;
+        }
+
+        let seed = ((chunk_position.x as u64) << 32 | chunk_position.z as u32 as u64)
+            .overflowing_mul(self.seed)
+            .0
+            ^ chunk_position.y as u64;
+        let mut rng = Rng::new(seed);
+        let total_weight: u32 = self.ores.iter().map(|(_, weight)| weight).sum();
+
+        for position in walls {
+            if chunk[*position] != stone || rng.next_f32() > ORE_CHANCE {
+                continue;
+            }
+
+            let mut pick = UniformDistribution::new(0, total_weight - 1).sample(&mut rng);
+            for (ore, weight) in self.ores.iter() {
+                if pick < *weight {
+                    chunk[*position] = *ore;
+                    break;
+                }
+                pick -= weight;
+            }
+        }
+    }
+}
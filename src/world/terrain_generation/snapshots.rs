@@ -0,0 +1,255 @@
+use std::collections::BTreeMap;
+
+use fmc::{
+    blocks::{BlockPosition, Blocks},
+    prelude::*,
+    world::{
+        TerrainGenerator,
+        chunk::{Chunk, ChunkPosition},
+    },
+};
+use serde::{Deserialize, Serialize};
+
+use super::{Earth, biomes::Biomes};
+
+const SNAPSHOTS_FLAG: &str = "--terrain-snapshots";
+/// Baselines are stored in the crate so they can be committed along with the generator.
+const SNAPSHOTS_PATH: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/snapshots/terrain.json");
+
+const SEEDS: [u64; 3] = [0, 1, 1337];
+/// How many chunks out from the origin are generated, in each horizontal direction
+const RADIUS: i32 = 1;
+/// The vertical range of chunks that are generated, covers everything from the caves to the
+/// highest mountains.
+const MIN_CHUNK_Y: i32 = -64;
+const MAX_CHUNK_Y: i32 = 128;
+
+#[derive(Serialize, Deserialize, PartialEq)]
+struct Snapshot {
+    /// How many there are of each block, by name
+    histogram: BTreeMap<String, u32>,
+    /// Height of the highest block that isn't air for each column, x major
+    heightmap: Vec<i32>,
+}
+
+impl Snapshot {
+    fn generate(seed: u64, blocks: &Blocks) -> Self {
+        let biomes = match Biomes::load(blocks) {
+            Ok(b) => b,
+            Err(e) => panic!("{}", e),
+        };
+        let earth = Earth::new(seed, blocks, biomes);
+        let air = blocks.get_id("air");
+        let width = (RADIUS * 2 + 1) as usize * Chunk::SIZE;
+
+        let mut histogram = BTreeMap::new();
+        let mut heightmap = vec![i32::MIN; width * width];
+
+        for chunk_x in -RADIUS..=RADIUS {
+            for chunk_z in -RADIUS..=RADIUS {
+                for chunk_y in (MIN_CHUNK_Y..=MAX_CHUNK_Y).step_by(Chunk::SIZE) {
+                    let chunk_position = ChunkPosition::from(BlockPosition::new(
+                        chunk_x * Chunk::SIZE as i32,
+                        chunk_y,
+                        chunk_z * Chunk::SIZE as i32,
+                    ));
+                    let chunk = earth.generate_chunk(chunk_position);
+
+                    for x in 0..Chunk::SIZE {
+                        for z in 0..Chunk::SIZE {
+                            let column = ((chunk_x + RADIUS) as usize * Chunk::SIZE + x) * width
+                                + (chunk_z + RADIUS) as usize * Chunk::SIZE
+                                + z;
+
+                            for y in 0..Chunk::SIZE {
+                                let block_id = if chunk.is_uniform() {
+                                    chunk[0]
+                                } else {
+                                    chunk[[x, y, z]]
+                                };
+
+                                let name = &blocks.get_config(&block_id).name;
+                                *histogram.entry(name.clone()).or_insert(0) += 1;
+
+                                if block_id != air {
+                                    heightmap[column] = heightmap[column].max(chunk_y + y as i32);
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+        }
+
+        Self {
+            histogram,
+            heightmap,
+        }
+    }
+
+    // Lists what differs from the baseline, empty if nothing does.
+    fn differences(&self, baseline: &Snapshot) -> Vec<String> {
+        let mut differences = Vec::new();
+
+        let names = self.histogram.keys().chain(baseline.histogram.keys());
+        for name in names.collect::<std::collections::BTreeSet<_>>() {
+            let count = self.histogram.get(name).copied().unwrap_or(0);
+            let expected = baseline.histogram.get(name).copied().unwrap_or(0);
+            if count != expected {
+                differences.push(format!("{} blocks: {} (was {})", name, count, expected));
+            }
+        }
+
+        let changed_columns = self
+            .heightmap
+            .iter()
+            .zip(baseline.heightmap.iter())
+            .filter(|(height, expected)| height != expected)
+            .count();
+        if changed_columns != 0 || self.heightmap.len() != baseline.heightmap.len() {
+            differences.push(format!(
+                "surface height changed in {} of {} columns",
+                changed_columns,
+                baseline.heightmap.len()
+            ));
+        }
+
+        differences
+    }
+}
+
+/// Snapshots of generated terrain, used to verify that changes to the terrain generation don't
+/// change the world by accident.
+///
+/// The generator needs the block configurations, which are only loaded once the app has started,
+/// so the snapshots are taken by the app itself. `cargo test --test terrain_snapshots` runs the
+/// check, as does starting the server with `--terrain-snapshots check`. Start the server with
+/// `--terrain-snapshots record` to store new baselines. The app exits when it's done, with an
+/// error if the terrain no longer matches.
+pub struct TerrainSnapshotsPlugin {
+    mode: SnapshotMode,
+}
+
+impl TerrainSnapshotsPlugin {
+    /// Compare the generated terrain against the recorded baselines.
+    pub fn check() -> Self {
+        Self {
+            mode: SnapshotMode::Check,
+        }
+    }
+
+    /// Read the mode from the command line arguments, None if the flag isn't given.
+    pub fn from_args() -> Option<Self> {
+        let mut args = std::env::args();
+        args.find(|arg| arg == SNAPSHOTS_FLAG)?;
+        let mode = match args.next().as_deref() {
+            Some("record") => SnapshotMode::Record,
+            Some("check") => SnapshotMode::Check,
+            _ => panic!("Usage: {} <record | check>", SNAPSHOTS_FLAG),
+        };
+        Some(Self { mode })
+    }
+}
+
+impl Plugin for TerrainSnapshotsPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(self.mode)
+            .add_systems(Startup, run_snapshots);
+    }
+}
+
+#[derive(Resource, Clone, Copy, PartialEq)]
+enum SnapshotMode {
+    Record,
+    Check,
+}
+
+fn run_snapshots(
+    mode: Res<SnapshotMode>,
+    blocks: Res<Blocks>,
+    mut app_exit: MessageWriter<AppExit>,
+) {
+    let snapshots: BTreeMap<u64, Snapshot> = SEEDS
+        .into_iter()
+        .map(|seed| {
+            let start = std::time::Instant::now();
+            let snapshot = Snapshot::generate(seed, &blocks);
+            let elapsed = start.elapsed();
+            // Lets the speed of the generator be compared between changes too.
+            let chunk_count = (RADIUS * 2 + 1).pow(2)
+                * (MIN_CHUNK_Y..=MAX_CHUNK_Y).step_by(Chunk::SIZE).count() as i32;
+            info!(
+                "Generated {} chunks for seed {} in {:.2?}, {:.2?} per chunk",
+                chunk_count,
+                seed,
+                elapsed,
+                elapsed / chunk_count as u32
+            );
+            (seed, snapshot)
+        })
+        .collect();
+
+    if *mode == SnapshotMode::Record {
+        let json = serde_json::to_string_pretty(&snapshots).unwrap();
+        let path = std::path::Path::new(SNAPSHOTS_PATH);
+        if let Err(e) =
+            std::fs::create_dir_all(path.parent().unwrap()).and_then(|_| std::fs::write(path, json))
+        {
+            panic!(
+                "Failed to write terrain snapshots to path: {}\nError: {}",
+                SNAPSHOTS_PATH, e
+            );
+        }
+        info!("Recorded terrain snapshots for seeds {:?}", SEEDS);
+        app_exit.write(AppExit::Success);
+        return;
+    }
+
+    let file = match std::fs::File::open(SNAPSHOTS_PATH) {
+        Ok(f) => f,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => panic!(
+            "No terrain snapshots have been recorded, run the server with '{} record' on a \
+            revision where the terrain is known to be right, and commit {}",
+            SNAPSHOTS_FLAG, SNAPSHOTS_PATH
+        ),
+        Err(e) => panic!(
+            "Failed to open terrain snapshots at path: {}\nError: {}",
+            SNAPSHOTS_PATH, e
+        ),
+    };
+
+    let baselines: BTreeMap<u64, Snapshot> = match serde_json::from_reader(file) {
+        Ok(b) => b,
+        Err(e) => panic!(
+            "Failed to read terrain snapshots at path: {}\nError: {}",
+            SNAPSHOTS_PATH, e
+        ),
+    };
+
+    let mut failed = false;
+    for (seed, snapshot) in snapshots.iter() {
+        let Some(baseline) = baselines.get(seed) else {
+            error!("No terrain snapshot recorded for seed {}", seed);
+            failed = true;
+            continue;
+        };
+
+        let differences = snapshot.differences(baseline);
+        if differences.is_empty() {
+            info!("Terrain for seed {} matches its snapshot", seed);
+        } else {
+            error!(
+                "Terrain for seed {} differs from its snapshot:\n{}",
+                seed,
+                differences.join("\n")
+            );
+            failed = true;
+        }
+    }
+
+    app_exit.write(if failed {
+        AppExit::error()
+    } else {
+        AppExit::Success
+    });
+}
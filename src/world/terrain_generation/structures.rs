@@ -0,0 +1,261 @@
+use std::{
+    collections::{HashMap, HashSet},
+    sync::Mutex,
+};
+
+use fmc::{
+    blocks::{BlockId, BlockPosition, Blocks},
+    prelude::*,
+    random::{Rng, UniformDistribution},
+    world::{
+        TerrainFeature,
+        chunk::{Chunk, ChunkPosition},
+    },
+};
+
+// The world is divided into square regions, each of which can contain one of each structure. Where
+// in the region the structure goes and what it looks like is decided by the region's seed, so it
+// is the same no matter which of the chunks it covers is generated first.
+const REGION_SIZE: i32 = 48;
+
+/// A structure that has been laid out in the world. Unlike a [TerrainFeature] it isn't confined to
+/// a single chunk, its blocks are sorted by chunk and applied one chunk at a time.
+#[derive(Default)]
+pub struct StructureFeature {
+    pub can_replace: HashSet<BlockId>,
+    blocks: HashMap<ChunkPosition, Vec<(BlockPosition, BlockId)>>,
+}
+
+impl StructureFeature {
+    pub fn insert_block(&mut self, position: BlockPosition, block_id: BlockId) {
+        self.blocks
+            .entry(ChunkPosition::from(position))
+            .or_default()
+            .push((position, block_id));
+    }
+
+    // Split the structure into one terrain feature for each chunk it covers.
+    fn into_parts(self) -> impl Iterator<Item = (ChunkPosition, TerrainFeature)> {
+        self.blocks
+            .into_iter()
+            .map(move |(chunk_position, blocks)| {
+                let mut terrain_feature = TerrainFeature::default();
+                terrain_feature.can_replace.extend(&self.can_replace);
+                for (position, block_id) in blocks {
+                    terrain_feature.insert_block(position, block_id);
+                }
+                (chunk_position, terrain_feature)
+            })
+    }
+}
+
+trait Structure: Send + Sync {
+    /// Chance for a region to contain the structure
+    fn chance(&self) -> f32;
+    /// How far from its origin the structure can reach in any direction. Must be less than
+    /// REGION_SIZE.
+    fn reach(&self) -> i32;
+    /// Range of heights the origin can be placed at
+    fn heights(&self) -> (i32, i32);
+    fn construct(&self, origin: BlockPosition, rng: &mut Rng) -> StructureFeature;
+}
+
+#[derive(Default)]
+struct StructureState {
+    // The structures that have been laid out, by index and region.
+    generated: HashSet<(usize, IVec2)>,
+    // Parts of structures that belong to chunks that haven't been generated yet.
+    //
+    // TODO: This is not saved, when the server restarts, a chunk that is generated next to a
+    // structure from the last run will lay it out again. The parts that belong to chunks that
+    // were already saved are then left here forever.
+    pending: HashMap<ChunkPosition, Vec<TerrainFeature>>,
+}
+
+/// Structures that can span several chunks, like dungeons.
+///
+/// When the first chunk a structure covers is generated, the whole structure is laid out. That
+/// chunk's part is applied immediately, and the rest is kept until the chunks it belongs to are
+/// generated.
+pub struct Structures {
+    seed: u64,
+    structures: Vec<Box<dyn Structure>>,
+    state: Mutex<StructureState>,
+}
+
+impl Structures {
+    pub fn new(seed: u64, blocks: &Blocks) -> Self {
+        Self {
+            seed,
+            structures: vec![Box::new(Dungeon::new(blocks))],
+            state: Mutex::new(StructureState::default()),
+        }
+    }
+
+    /// Apply the parts of the structures that are inside the chunk.
+    pub fn apply(&self, chunk_position: ChunkPosition, chunk: &mut Chunk) {
+        // The state is held for the whole call, so a structure can't be laid out by one chunk
+        // while another takes its pending parts.
+        let mut state = self.state.lock().unwrap();
+
+        for (index, structure) in self.structures.iter().enumerate() {
+            let reach = structure.reach();
+            let min = BlockPosition::from(chunk_position) - IVec3::splat(reach);
+            let max =
+                BlockPosition::from(chunk_position) + IVec3::splat(Chunk::SIZE as i32 - 1 + reach);
+
+            for region_x in min.x.div_euclid(REGION_SIZE)..=max.x.div_euclid(REGION_SIZE) {
+                for region_z in min.z.div_euclid(REGION_SIZE)..=max.z.div_euclid(REGION_SIZE) {
+                    let region = IVec2::new(region_x, region_z);
+                    if state.generated.contains(&(index, region)) {
+                        continue;
+                    }
+
+                    let mut rng = Rng::new(self.region_seed(index, region));
+                    if rng.next_f32() > structure.chance() {
+                        continue;
+                    }
+
+                    let (min_height, max_height) = structure.heights();
+                    let origin = BlockPosition::new(
+                        region.x * REGION_SIZE
+                            + UniformDistribution::new(0, REGION_SIZE - 1).sample(&mut rng),
+                        UniformDistribution::new(min_height, max_height).sample(&mut rng),
+                        region.y * REGION_SIZE
+                            + UniformDistribution::new(0, REGION_SIZE - 1).sample(&mut rng),
+                    );
+
+                    // Too far away to reach the chunk
+                    if origin.x < min.x
+                        || origin.y < min.y
+                        || origin.z < min.z
+                        || origin.x > max.x
+                        || origin.y > max.y
+                        || origin.z > max.z
+                    {
+                        continue;
+                    }
+
+                    state.generated.insert((index, region));
+
+                    let feature = structure.construct(origin, &mut rng);
+                    for (part_position, part) in feature.into_parts() {
+                        state.pending.entry(part_position).or_default().push(part);
+                    }
+                }
+            }
+        }
+
+        if let Some(parts) = state.pending.remove(&chunk_position) {
+            for part in parts {
+                part.apply(chunk_position, chunk);
+            }
+        }
+    }
+
+    fn region_seed(&self, index: usize, region: IVec2) -> u64 {
+        ((region.x as u64) << 32 | region.y as u32 as u64)
+            .overflowing_mul(self.seed)
+            .0
+            // So the structures don't line up with the ravines or each other
+            ^ 0x5354_5255_4354_0000
+            ^ index as u64
+    }
+}
+
+/// A small cobblestone room hidden underground, with a spawner in the middle and a chest or two
+/// along the walls.
+struct Dungeon {
+    walls: BlockId,
+    air: BlockId,
+    spawner: BlockId,
+    chest: BlockId,
+    can_replace: HashSet<BlockId>,
+}
+
+impl Dungeon {
+    // How many blocks the inside of the room can stretch from the spawner
+    const MIN_HALF_SIZE: i32 = 2;
+    const MAX_HALF_SIZE: i32 = 4;
+    const HEIGHT: i32 = 4;
+
+    fn new(blocks: &Blocks) -> Self {
+        Self {
+            walls: blocks.get_id("cobblestone"),
+            air: blocks.get_id("air"),
+            spawner: blocks.get_id("spawner"),
+            chest: blocks.get_id("dungeon_chest"),
+            can_replace: [
+                "air",
+                "stone",
+                "dirt",
+                "sand",
+                "sandstone",
+                "coal_ore",
+                "iron_ore",
+                "gold_ore",
+                "redstone_ore",
+                "diamond_ore",
+            ]
+            .into_iter()
+            .map(|name| blocks.get_id(name))
+            .collect(),
+        }
+    }
+}
+
+impl Structure for Dungeon {
+    fn chance(&self) -> f32 {
+        0.4
+    }
+
+    fn reach(&self) -> i32 {
+        Self::MAX_HALF_SIZE + Self::HEIGHT
+    }
+
+    fn heights(&self) -> (i32, i32) {
+        (-60, -16)
+    }
+
+    fn construct(&self, origin: BlockPosition, rng: &mut Rng) -> StructureFeature {
+        let mut feature = StructureFeature::default();
+        feature.can_replace.extend(&self.can_replace);
+
+        let half_size = UniformDistribution::new(Self::MIN_HALF_SIZE, Self::MAX_HALF_SIZE);
+        let half_x = half_size.sample(rng);
+        let half_z = half_size.sample(rng);
+
+        // The origin is the floor level inside the room, the walls are one block outside it.
+        for x in -half_x - 1..=half_x + 1 {
+            for z in -half_z - 1..=half_z + 1 {
+                for y in -1..=Self::HEIGHT {
+                    let is_wall =
+                        x.abs() > half_x || z.abs() > half_z || y == -1 || y == Self::HEIGHT;
+                    let block_id = if is_wall { self.walls } else { self.air };
+                    feature.insert_block(origin + IVec3::new(x, y, z), block_id);
+                }
+            }
+        }
+
+        feature.insert_block(origin, self.spawner);
+
+        // Chests are pushed up against a random wall
+        let chest_count = UniformDistribution::new(1, 2).sample(rng);
+        for _ in 0..chest_count {
+            let mut offset = IVec3::new(
+                UniformDistribution::new(-half_x, half_x).sample(rng),
+                0,
+                UniformDistribution::new(-half_z, half_z).sample(rng),
+            );
+            let side = if rng.next_f32() < 0.5 { 1 } else { -1 };
+            if rng.next_f32() < 0.5 {
+                offset.x = half_x * side;
+            } else {
+                offset.z = half_z * side;
+            }
+            feature.insert_block(origin + offset, self.chest);
+        }
+
+        feature
+    }
+}
@@ -0,0 +1,257 @@
+use fmc::{database::Database, networking::Server, prelude::*, terminal::Cli};
+
+use crate::{
+    chat::{broadcast_message, send_message},
+    database_io::{DatabaseIo, DatabaseTask},
+    settings::{self, Settings, WORLD_DIRECTORY},
+};
+
+/// Lets operators list, create and switch between the worlds in the world directory. Each world
+/// is its own database, with its own seed, players and [WorldProperties](super::WorldProperties).
+///
+/// The world the server uses when no world name is set is listed as [DEFAULT_WORLD], it is stored
+/// at fmc's default database path instead of in the world directory.
+///
+/// fmc can only have one world loaded, so switching saves and shuts down the server, and the new
+/// world is loaded when it is started again.
+pub struct WorldsPlugin;
+impl Plugin for WorldsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_message::<ListWorlds>()
+            .add_message::<CreateWorld>()
+            .add_message::<SwitchWorld>()
+            .add_systems(
+                Update,
+                (
+                    list_worlds,
+                    create_worlds,
+                    finish_creating_worlds,
+                    switch_world,
+                ),
+            );
+    }
+}
+
+/// Show the player which worlds there are
+#[derive(Message)]
+pub struct ListWorlds {
+    pub player_entity: Entity,
+}
+
+/// Create a new world, it is not switched to.
+#[derive(Message)]
+pub struct CreateWorld {
+    pub player_entity: Entity,
+    pub name: String,
+    /// A seed is picked from the current time if not set.
+    pub seed: Option<String>,
+}
+
+/// Shut down the server so it loads another world when it's started again.
+#[derive(Message)]
+pub struct SwitchWorld {
+    pub player_entity: Entity,
+    pub name: String,
+}
+
+/// Name of the world that is used when none is set in the settings
+const DEFAULT_WORLD: &str = "default";
+
+/// A world that is being created
+#[derive(Component)]
+struct CreatingWorld {
+    name: String,
+    task: DatabaseTask<Result<(), String>>,
+}
+
+fn world_exists(name: &str) -> bool {
+    name == DEFAULT_WORLD
+        || std::path::Path::new(&settings::world_path(name)).exists()
+        || std::path::Path::new(&format!("./{}.sqlite", name)).exists()
+}
+
+// The names are used as file names, so they are kept to what is safe on all platforms.
+fn is_valid_name(name: &str) -> bool {
+    !name.is_empty()
+        && name.len() <= 32
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+}
+
+fn list_worlds(
+    net: Res<Server>,
+    settings: Res<Settings>,
+    mut list_events: MessageReader<ListWorlds>,
+) {
+    for list_event in list_events.read() {
+        let mut names: Vec<String> = std::fs::read_dir(WORLD_DIRECTORY)
+            .into_iter()
+            .flatten()
+            .filter_map(|entry| {
+                let path = entry.ok()?.path();
+                if path.extension()? != "sqlite" {
+                    return None;
+                }
+                Some(path.file_stem()?.to_str()?.to_owned())
+            })
+            .collect();
+        names.retain(|name| name != DEFAULT_WORLD);
+        names.sort();
+        names.insert(0, DEFAULT_WORLD.to_owned());
+
+        let current = settings.world_name().unwrap_or(DEFAULT_WORLD);
+        if !names.iter().any(|name| name == current) {
+            names.insert(1, current.to_owned());
+        }
+
+        let mut text = String::from("Worlds:");
+        for name in names {
+            text += " ";
+            text += &name;
+            if name == current {
+                text += " (current)";
+            }
+        }
+        send_message(&net, list_event.player_entity, text);
+    }
+}
+
+fn create_worlds(
+    mut commands: Commands,
+    net: Res<Server>,
+    database_io: Res<DatabaseIo>,
+    mut create_events: MessageReader<CreateWorld>,
+) {
+    for create_event in create_events.read() {
+        if !is_valid_name(&create_event.name) {
+            send_message(
+                &net,
+                create_event.player_entity,
+                "World names can only contain letters, numbers, '-' and '_'".to_owned(),
+            );
+            continue;
+        } else if world_exists(&create_event.name) {
+            send_message(
+                &net,
+                create_event.player_entity,
+                format!("There is already a world named {}", create_event.name),
+            );
+            continue;
+        }
+
+        let seed = create_event.seed.clone().unwrap_or_else(|| {
+            std::time::SystemTime::now()
+                .duration_since(std::time::SystemTime::UNIX_EPOCH)
+                .unwrap()
+                .as_secs()
+                .to_string()
+        });
+        let path = settings::world_path(&create_event.name);
+
+        // Creating the database sets up all its tables, so it is done on the io thread to not
+        // hold up the server.
+        let task = database_io.run(move |_| {
+            std::fs::create_dir_all(WORLD_DIRECTORY).map_err(|e| e.to_string())?;
+            let database = Database::new(path);
+            // The seed is read from the world's settings when it is loaded.
+            let settings = serde_json::json!({ "seed": seed }).to_string();
+            database
+                .get_write_connection()
+                .execute(
+                    "INSERT OR REPLACE INTO storage (name, data) VALUES (?,?)",
+                    rusqlite::params!["settings", settings],
+                )
+                .map_err(|e| e.to_string())?;
+            Ok(())
+        });
+
+        commands
+            .entity(create_event.player_entity)
+            .insert(CreatingWorld {
+                name: create_event.name.clone(),
+                task,
+            });
+    }
+}
+
+fn finish_creating_worlds(
+    mut commands: Commands,
+    net: Res<Server>,
+    mut creating_query: Query<(Entity, &mut CreatingWorld)>,
+) {
+    for (player_entity, mut creating) in creating_query.iter_mut() {
+        let Some(result) = creating.task.try_take() else {
+            continue;
+        };
+
+        let text = match result {
+            Ok(()) => format!(
+                "Created the world {}, switch to it with /world switch {}. Switching shuts down \
+                the server, it has to be started again to load the world.",
+                creating.name, creating.name
+            ),
+            Err(e) => {
+                error!("Failed to create the world {}\nError: {}", creating.name, e);
+                format!("Failed to create the world {}: {}", creating.name, e)
+            }
+        };
+        send_message(&net, player_entity, text);
+
+        commands.entity(player_entity).remove::<CreatingWorld>();
+    }
+}
+
+fn switch_world(
+    net: Res<Server>,
+    mut settings: ResMut<Settings>,
+    mut switch_events: MessageReader<SwitchWorld>,
+    mut app_exit: MessageWriter<AppExit>,
+) {
+    for switch_event in switch_events.read() {
+        if Cli::world_path().is_some() {
+            send_message(
+                &net,
+                switch_event.player_entity,
+                "The world was chosen when the server was started, it can't be switched".to_owned(),
+            );
+            continue;
+        } else if settings.world_name().unwrap_or(DEFAULT_WORLD) == switch_event.name {
+            send_message(
+                &net,
+                switch_event.player_entity,
+                format!("{} is already the current world", switch_event.name),
+            );
+            continue;
+        } else if !world_exists(&switch_event.name) {
+            send_message(
+                &net,
+                switch_event.player_entity,
+                format!(
+                    "There is no world named {}, create it with /world create {}",
+                    switch_event.name, switch_event.name
+                ),
+            );
+            continue;
+        }
+
+        if switch_event.name == DEFAULT_WORLD {
+            settings.set_world_name(None);
+        } else {
+            settings.set_world_name(Some(&switch_event.name));
+        }
+
+        broadcast_message(
+            &net,
+            format!(
+                "The server is shutting down to switch to the world {}, it has to be started \
+                again to load it",
+                switch_event.name
+            ),
+        );
+
+        // Everything is saved on shutdown
+        app_exit.write(AppExit::Success);
+        return;
+    }
+}
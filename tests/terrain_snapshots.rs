@@ -0,0 +1,25 @@
+//! Checks the generated terrain against the baselines in `snapshots/terrain.json`.
+//!
+//! The whole server is started so that the block configurations are loaded the same way as when
+//! it runs for real. New baselines are recorded by running the server with
+//! `--terrain-snapshots record`.
+
+use fmc_173::{prelude::*, world::TerrainSnapshotsPlugin};
+
+#[test]
+fn terrain_matches_snapshots() {
+    // The server writes its assets, settings and world database to the working directory.
+    let directory = std::path::Path::new(env!("CARGO_TARGET_TMPDIR")).join("terrain_snapshots");
+    std::fs::create_dir_all(&directory).unwrap();
+    std::env::set_current_dir(&directory).unwrap();
+
+    let exit = App::new()
+        .add_plugins(fmc_173::DefaultPlugins)
+        .add_plugins(TerrainSnapshotsPlugin::check())
+        .run();
+
+    assert!(
+        exit.is_success(),
+        "The generated terrain differs from its snapshots, see the log above"
+    );
+}
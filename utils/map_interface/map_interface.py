@@ -0,0 +1,62 @@
+# Writes the map interface, assets/client/interfaces/map.json
+#
+# The server can't send images to the client, so each pixel of the map is a stack of one image
+# per color. The server draws the map by showing the image of a pixel's color and hiding the
+# rest. Run from the repository root after changing any of the values below, they must match
+# MAP_SIZE and COLORS in src/items/maps.rs.
+import json
+
+# Number of pixels along each side of the map
+map_size = 16
+# Number of colors, there must be an image named map_<color>.png for each
+colors = 8
+# Size of each pixel on screen
+pixel_size = 6
+
+def size(width, height):
+    return {
+        "width": {"Px": width},
+        "height": {"Px": height},
+    }
+
+def pixel(index):
+    return {
+        "path": str(index),
+        "style": size(pixel_size, pixel_size),
+        "content": {
+            "Nodes": [
+                {
+                    "path": str(color),
+                    "style": {"position_type": "Absolute", **size(pixel_size, pixel_size)},
+                    "image": f"map_{color}.png",
+                }
+                for color in range(colors)
+            ]
+        },
+    }
+
+interface = {
+    "path": "map",
+    "style": {
+        "position_type": "Absolute",
+        "right": {"Px": 8},
+        "top": {"Px": 8},
+        "flex_wrap": "Wrap",
+        **size(map_size * pixel_size, map_size * pixel_size),
+    },
+    "background_color": {
+        "LinearRgba": {
+            "red": 0.0,
+            "green": 0.0,
+            "blue": 0.0,
+            "alpha": 0.5,
+        }
+    },
+    "content": {
+        "Nodes": [pixel(index) for index in range(map_size * map_size)]
+    },
+}
+
+with open("assets/client/interfaces/map.json", "w") as file:
+    json.dump(interface, file, indent=4)
+    file.write("\n")